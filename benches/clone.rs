@@ -0,0 +1,37 @@
+//! Measure the cost of cloning queries, whose codes and API key are shared rather than copied,
+//! against that of copying the same strings (what cloning a query used to do).
+//!
+//! Run with `cargo bench --bench clone`.
+
+extern crate quandl_v3;
+
+use std::time::Instant;
+
+use quandl_v3::prelude::*;
+
+const CLONES: usize = 100_000;
+
+fn main() {
+    let mut query = DataQuery::new("WIKI", "AAPL");
+    query.api_key("0123456789abcdefghij").column_index(4);
+
+    let shared = {
+        let start = Instant::now();
+        let clones: Vec<DataQuery> = (0..CLONES).map(|_| query.clone()).collect();
+        assert_eq!(clones.len(), CLONES);
+        start.elapsed()
+    };
+
+    let copied = {
+        let strings = ("WIKI".to_string(), "AAPL".to_string(), "0123456789abcdefghij".to_string());
+
+        let start = Instant::now();
+        let clones: Vec<(String, String, String)> = (0..CLONES).map(|_| strings.clone()).collect();
+        assert_eq!(clones.len(), CLONES);
+        start.elapsed()
+    };
+
+    println!("clone {} queries", CLONES);
+    println!("  queries:         {:?}", shared);
+    println!("  strings alone:   {:?}", copied);
+}
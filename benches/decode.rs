@@ -0,0 +1,48 @@
+//! Compare the serial and parallel decoding paths of `DataQuery` over a synthetic payload.
+//!
+//! Run with `cargo bench --features rayon`.
+
+extern crate rayon;
+extern crate quandl_v3;
+
+use std::time::Instant;
+
+use quandl_v3::prelude::*;
+
+const ROWS: usize = 1_000_000;
+
+fn payload() -> Vec<u8> {
+    let mut csv = String::with_capacity(ROWS * 48);
+
+    for row in 0..ROWS {
+        csv.push_str(&format!("2016-02-{:02},{}.25,{}.5,{}.75,{}\n",
+                              row % 28 + 1, row, row + 1, row + 2, row * 100));
+    }
+
+    csv.into_bytes()
+}
+
+fn main() {
+    type Row = (String, f64, f64, f64, f64);
+
+    let query = DataQuery::new("WIKI", "AAPL");
+    let csv = payload();
+
+    let serial = {
+        let start = Instant::now();
+        let data: Vec<Row> = query.decode(&csv[..]).unwrap();
+        assert_eq!(data.len(), ROWS);
+        start.elapsed()
+    };
+
+    let parallel = {
+        let start = Instant::now();
+        let data: Vec<Row> = query.decode_parallel(&csv[..]).unwrap();
+        assert_eq!(data.len(), ROWS);
+        start.elapsed()
+    };
+
+    println!("decode {} rows ({} bytes)", ROWS, csv.len());
+    println!("  serial:   {:?}", serial);
+    println!("  parallel: {:?} ({} threads)", parallel, rayon::current_num_threads());
+}
@@ -0,0 +1,140 @@
+//! Compare the peak memory of handing a large payload to several sinks through `encoded_bytes`
+//! (shared) and through `encoded_data` (copied).
+//!
+//! Run with `cargo bench --bench payload`. Each path is measured in a child process of its own,
+//! from the `VmHWM` line of `/proc/self/status`, so this only reports numbers on Linux.
+
+extern crate quandl_v3;
+
+use std::env;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::process::Command;
+use std::thread::spawn;
+
+use quandl_v3::Bytes;
+use quandl_v3::prelude::*;
+
+const ROWS: usize = 1_000_000;
+const SINKS: usize = 3;
+
+type Row = Vec<String>;
+
+fn payload() -> Vec<u8> {
+    let mut csv = String::with_capacity(ROWS * 48);
+
+    for row in 0..ROWS {
+        csv.push_str(&format!("2016-02-{:02},{}.25,{}.5,{}.75,{}\n",
+                              row % 28 + 1, row, row + 1, row + 2, row * 100));
+    }
+
+    csv.into_bytes()
+}
+
+/// Serve `body` as CSV to any request, on a local port, for the lifetime of the process.
+///
+fn serve(body: Vec<u8>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+
+    spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+
+            while reader.read_line(&mut line).unwrap() > 2 {
+                line.clear();
+            }
+
+            write!(stream, "HTTP/1.1 200 OK\r\nContent-Type: text/csv\r\n\
+                            Content-Length: {}\r\nConnection: close\r\n\r\n", body.len()).unwrap();
+
+            stream.write_all(&body).unwrap();
+        }
+    });
+
+    format!("http://{}/api/v3", address)
+}
+
+/// Peak resident set size of this process, in kB.
+///
+fn peak_rss() -> Option<usize> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmHWM:"))?;
+
+    line["VmHWM:".len()..].trim().trim_end_matches("kB").trim().parse().ok()
+}
+
+/// Download the payload from `base_url` the way `mode` says, hand it to every sink and report
+/// the peak RSS on the standard output.
+///
+fn measure(mode: &str, base_url: &str) {
+    let mut query = DataQuery::new("WIKI", "AAPL");
+    query.base_url(base_url);
+
+    let received = {
+        match mode {
+            "bytes" => {
+                let bytes = ApiCall::<Vec<Row>>::encoded_bytes(&query).unwrap();
+                let sinks: Vec<Bytes> = (0..SINKS).map(|_| bytes.clone()).collect();
+                sinks.iter().map(|sink| sink.len()).sum::<usize>()
+            },
+
+            "data" => {
+                let data = ApiCall::<Vec<Row>>::encoded_data(&query).unwrap();
+                let sinks: Vec<Vec<u8>> = (0..SINKS).map(|_| data.clone()).collect();
+                sinks.iter().map(|sink| sink.len()).sum::<usize>()
+            },
+
+            other => panic!("unknown mode '{}'", other),
+        }
+    };
+
+    assert_eq!(received, SINKS * payload_len());
+    println!("{}", peak_rss().unwrap_or(0));
+}
+
+fn payload_len() -> usize {
+    env::var("PAYLOAD_LEN").unwrap().parse().unwrap()
+}
+
+fn main() {
+    // `cargo bench` passes `--bench`, and whatever filter it is given.
+    let args: Vec<String> = env::args().skip(1).filter(|arg| !arg.starts_with("--")).collect();
+
+    if let [mode, base_url] = &args[..] {
+        return measure(mode, base_url);
+    }
+
+    if peak_rss().is_none() {
+        println!("peak RSS unavailable on this platform, skipping");
+        return;
+    }
+
+    let body = payload();
+    let length = body.len();
+    let base_url = serve(body);
+
+    let run = |mode: &str| -> usize {
+        let output = {
+            Command::new(env::current_exe().unwrap())
+                .args([mode, &base_url[..]])
+                .env("PAYLOAD_LEN", length.to_string())
+                .output()
+                .unwrap()
+        };
+
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        String::from_utf8(output.stdout).unwrap().trim().parse().unwrap()
+    };
+
+    let bytes = run("bytes");
+    let data = run("data");
+
+    println!("payload of {} bytes handed to {} sinks, peak RSS", length, SINKS);
+    println!("  encoded_bytes: {} kB", bytes);
+    println!("  encoded_data:  {} kB", data);
+}
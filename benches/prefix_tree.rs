@@ -0,0 +1,35 @@
+//! Measure the building and traversal of a `PrefixTree` out of a large code list.
+//!
+//! Run with `cargo bench --bench prefix_tree`.
+
+extern crate quandl_v3;
+
+use std::time::Instant;
+
+use quandl_v3::codes::group_by_prefix;
+use quandl_v3::prelude::*;
+
+const CODES: usize = 100_000;
+
+fn main() {
+    let codes: Vec<Code> = (0..CODES)
+        .map(|index| Code {
+            dataset_code: format!("S{}_C{}_{}", index % 50, index % 1_000, index),
+            database_code: "FRED".to_string(),
+            name: String::new(),
+        })
+        .collect();
+
+    let start = Instant::now();
+    let tree = group_by_prefix(&codes, '_');
+    let built = start.elapsed();
+
+    let start = Instant::now();
+    let under: usize = tree.children("").iter().map(|path| tree.datasets_under(path).len()).sum();
+    assert_eq!(under, CODES);
+    let traversed = start.elapsed();
+
+    println!("prefix tree of {} codes", CODES);
+    println!("  build:           {:?}", built);
+    println!("  datasets_under:  {:?}", traversed);
+}
@@ -0,0 +1,279 @@
+//! Scrape every dataset of a database into a JSON Lines file, over as many runs as it takes.
+//!
+//! ```text
+//! QUANDL_API_KEYS=KEY1,KEY2 cargo run --example full_scrape -- WIKI scrape-wiki
+//! ```
+//!
+//! Each run fetches the code list of the database, then the data and metadata of each of its
+//! datasets, spread over the API keys and within Quandl's limits for free keys. The rows of each
+//! dataset are appended to `<DIRECTORY>/<DATABASE>.jsonl` (see `Dataset::to_jsonl`) as soon as
+//! they arrive, and the dataset is then recorded in `<DIRECTORY>/manifest.jsonl`: an interrupted
+//! run, whether by a crash, a Ctrl-C or the daily limits, is resumed by running the same command
+//! again, which skips the datasets already stored. A crash between storing a dataset and recording
+//! it stores the dataset twice, which readers of the file should thus expect.
+
+extern crate quandl_v3;
+
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::process;
+use std::sync::{Arc, Mutex};
+
+use quandl_v3::{Error, Result};
+use quandl_v3::prelude::*;
+
+const USAGE: &str = "\
+usage: full_scrape DATABASE DIRECTORY
+
+The API keys are read from the QUANDL_API_KEYS environment variable, separated by commas.";
+
+/// Quandl's limits for free keys, as `(calls, seconds)`, see `BatchQuery::limit`.
+///
+pub const FREE_LIMITS: &[(usize, u64)] = &[(300, 10), (2_000, 600), (50_000, 86_400)];
+
+/// What to scrape, and how.
+///
+#[derive(Debug, Clone)]
+pub struct Scrape {
+    /// Code of the database whose datasets to scrape.
+    ///
+    pub database_code: String,
+
+    /// API keys to send the queries with, the code list being fetched with the first one. There
+    /// must be at least one.
+    ///
+    pub keys: Vec<String>,
+
+    /// Limits of each of the keys, `FREE_LIMITS` by default.
+    ///
+    pub limits: Vec<(usize, u64)>,
+
+    /// Number of calls made at once, one per key by default.
+    ///
+    pub threads: usize,
+
+    /// Directory of the data and of the manifest, created if needed.
+    ///
+    pub directory: PathBuf,
+
+    /// Server to send the queries to instead of Quandl's.
+    ///
+    pub base_url: Option<String>,
+}
+
+impl Scrape {
+    /// Scrape of `database_code` into `directory` with `keys`, along the defaults above.
+    ///
+    pub fn new<P: Into<PathBuf>>(database_code: &str, keys: Vec<String>, directory: P) -> Self {
+        Scrape {
+            database_code: database_code.to_string(),
+            threads: keys.len().max(1),
+            keys,
+            limits: FREE_LIMITS.to_vec(),
+            directory: directory.into(),
+            base_url: None,
+        }
+    }
+
+    /// The JSON Lines file the rows are appended to.
+    ///
+    pub fn data_path(&self) -> PathBuf {
+        self.directory.join(format!("{}.jsonl", self.database_code))
+    }
+
+    /// The manifest of the datasets already stored, see `BatchQuery::manifest`.
+    ///
+    pub fn manifest_path(&self) -> PathBuf {
+        self.directory.join("manifest.jsonl")
+    }
+
+    fn arguments<Q: ApiParameters>(&self, query: &mut Q) {
+        if let Some(ref base_url) = self.base_url {
+            query.base_url(base_url);
+        }
+    }
+}
+
+/// What a run of `scrape` did.
+///
+#[derive(Debug)]
+pub struct Summary {
+    /// Number of datasets in the database.
+    ///
+    pub datasets: usize,
+
+    /// Number of datasets stored by this run.
+    ///
+    pub stored: usize,
+
+    /// Number of rows stored by this run.
+    ///
+    pub rows: usize,
+
+    /// Number of datasets skipped since a previous run stored them.
+    ///
+    pub skipped: usize,
+
+    /// Datasets which could not be stored, and why. Running the scrape again retries them.
+    ///
+    pub failed: Vec<(Code, Error)>,
+
+    /// Statistics of the batch.
+    ///
+    pub report: BatchReport,
+}
+
+/// Run `scrape`, calling `on_progress` after each call (see `BatchQuery::on_progress`).
+///
+/// Only failing to fetch the code list or to open the data file fails the run: a dataset which
+/// cannot be fetched or stored is reported in `Summary::failed` instead.
+///
+pub fn scrape<F>(scrape: &Scrape, on_progress: F) -> Result<Summary>
+    where F: Fn(&BatchProgress) + Send + Sync + 'static
+{
+    assert!(!scrape.keys.is_empty(), "no API key to scrape {} with", scrape.database_code);
+
+    let codes = {
+        let mut query = CodeListQuery::new(&scrape.database_code);
+        query.api_key(&scrape.keys[0]);
+        scrape.arguments(&mut query);
+        query.send()?
+    };
+
+    let sink = {
+        fs::create_dir_all(&scrape.directory).map_err(io_error)?;
+        let file = OpenOptions::new().create(true).append(true).open(scrape.data_path());
+        Arc::new(Mutex::new(file.map_err(io_error)?))
+    };
+
+    let mut batch_query = BatchQuery::<DataAndMetadataQuery, Dataset<DataRow>>::new();
+
+    for code in &codes {
+        let mut query = DataAndMetadataQuery::new(&code.database_code, &code.dataset_code);
+        scrape.arguments(&mut query);
+        batch_query.query(query);
+    }
+
+    for key in &scrape.keys {
+        batch_query.key_profile(KeyProfile::new(key));
+    }
+
+    for &(calls, seconds) in &scrape.limits {
+        batch_query.limit(calls, seconds);
+    }
+
+    batch_query
+        .threads(scrape.threads)
+        .manifest(scrape.manifest_path())
+        .on_throttle(|event| {
+            eprintln!("key {} waits {:.1}s for its limit of {} calls per {}s",
+                      event.key_fingerprint,
+                      event.waited.as_secs_f64(),
+                      event.threshold_hit.0,
+                      event.threshold_hit.1.as_secs());
+        })
+        .on_progress(on_progress);
+
+    // The rows are stored from the workers, before the manifest records their dataset.
+    let batch_query = batch_query.try_map_rows(move |_, dataset| store(&sink, &dataset));
+
+    let (results, report) = batch_query.run_with_report();
+
+    let mut summary = {
+        Summary {
+            datasets: codes.len(),
+            stored: 0,
+            rows: 0,
+            skipped: 0,
+            failed: vec![],
+            report: BatchReport::default(),
+        }
+    };
+
+    for (code, result) in codes.into_iter().zip(results) {
+        match result {
+            Ok(rows) => {
+                summary.stored += 1;
+                summary.rows += rows;
+            },
+
+            Err(Error::Skipped(_)) => summary.skipped += 1,
+            Err(e) => summary.failed.push((code, e)),
+        }
+    }
+
+    summary.report = report.get();
+    Ok(summary)
+}
+
+/// Append the rows of `dataset` to `sink` at once, returning how many there are.
+///
+fn store(sink: &Mutex<File>, dataset: &Dataset<DataRow>) -> Result<usize> {
+    let lines = dataset.to_jsonl()?;
+    let mut file = sink.lock().unwrap();
+
+    file.write_all(lines.as_bytes()).and_then(|_| file.flush()).map_err(io_error)?;
+    Ok(dataset.rows.len())
+}
+
+fn io_error(e: std::io::Error) -> Error {
+    Error::IoError(e.to_string())
+}
+
+fn main() {
+    let arguments: Vec<String> = env::args().skip(1).collect();
+
+    let keys: Vec<String> = {
+        env::var("QUANDL_API_KEYS").unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|key| !key.is_empty())
+            .map(String::from)
+            .collect()
+    };
+
+    if arguments.len() != 2 || keys.is_empty() {
+        eprintln!("{}", USAGE);
+        process::exit(2);
+    }
+
+    let config = Scrape::new(&arguments[0], keys, &arguments[1]);
+
+    let result = scrape(&config, |progress| {
+        if progress.completed % 100 == 0 || progress.completed == progress.total {
+            eprintln!("{}/{} datasets ({} failed, {} already stored) in {:.0}s",
+                      progress.completed,
+                      progress.total,
+                      progress.failed,
+                      progress.skipped,
+                      progress.elapsed.as_secs_f64());
+        }
+    });
+
+    match result {
+        Ok(summary) => {
+            for (code, e) in &summary.failed {
+                eprintln!("{}/{}: {}", code.database_code, code.dataset_code, e);
+            }
+
+            println!("{} of {} datasets stored ({} rows), {} by previous runs, {} failed\n{}",
+                     summary.stored,
+                     summary.datasets,
+                     summary.rows,
+                     summary.skipped,
+                     summary.failed.len(),
+                     summary.report);
+
+            if !summary.failed.is_empty() {
+                process::exit(1);
+            }
+        },
+
+        Err(e) => {
+            eprintln!("full_scrape: {}", e);
+            process::exit(1);
+        },
+    }
+}
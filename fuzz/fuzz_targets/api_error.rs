@@ -0,0 +1,11 @@
+//! Bodies of unsuccessful replies, as decoded before being reported.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use quandl_v3::ApiErrorResponse;
+
+fuzz_target!(|payload: &[u8]| {
+    let _ = ApiErrorResponse::decode(payload);
+});
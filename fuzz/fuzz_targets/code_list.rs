@@ -0,0 +1,17 @@
+//! Zipped code lists, which must neither crash nor unzip past their limits.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use quandl_v3::Error;
+use quandl_v3::prelude::*;
+
+fuzz_target!(|zipped_data: &[u8]| {
+    let mut query = CodeListQuery::new("WIKI");
+    query.max_uncompressed_bytes(1 << 20);
+
+    if let Err(Error::UnzippedTooLarge { limit, observed }) = query.decode(zipped_data) {
+        assert!(limit <= 1 << 20 && observed > limit);
+    }
+});
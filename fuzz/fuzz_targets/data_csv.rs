@@ -0,0 +1,17 @@
+//! CSV data of datasets, decoded strictly and leniently, along with the listing of datasets.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use quandl_v3::prelude::*;
+
+fuzz_target!(|csv_data: &[u8]| {
+    let query = DataQuery::new("WIKI", "AAPL");
+
+    let _ = query.decode::<(String, f64, Option<f64>)>(csv_data);
+    let _ = query.decode_lossy::<(String, f64, Option<f64>)>(csv_data);
+    let _ = query.decode::<Vec<String>>(csv_data);
+
+    let _ = DatasetListingQuery::new("WIKI").decode(csv_data);
+});
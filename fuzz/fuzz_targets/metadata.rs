@@ -0,0 +1,12 @@
+//! JSON metadata of databases and datasets.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use quandl_v3::prelude::*;
+
+fuzz_target!(|json_data: &[u8]| {
+    let _ = DatabaseMetadataQuery::new("WIKI").decode(json_data);
+    let _ = DatasetMetadataQuery::new("WIKI", "AAPL").decode(json_data);
+});
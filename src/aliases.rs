@@ -0,0 +1,85 @@
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+
+use chrono::NaiveDate;
+
+/// What became of a database code, see `resolve_code`.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CodeResolution {
+    /// The code is not known to have been renamed nor retired.
+    ///
+    Active,
+
+    /// The database is now published under this code.
+    ///
+    RenamedTo(String),
+
+    /// The database is no longer published, or no longer updated since `since` when that is
+    /// known. `note` tells what became of its data.
+    ///
+    Retired {
+        since: Option<NaiveDate>,
+        note: String,
+    },
+}
+
+/// The codes renamed or retired, as maintained in `aliases.toml`.
+///
+static ALIASES_TOML: &str = include_str!("aliases.toml");
+
+static ALIASES: OnceLock<BTreeMap<String, CodeResolution>> = OnceLock::new();
+
+/// What became of the database code `code` since Quandl was rebranded as Nasdaq Data Link.
+///
+/// Codes are compared regardless of case and surrounding whitespace. A successor is never
+/// renamed itself, so a single resolution is enough.
+///
+/// ```rust
+/// use quandl_v3::aliases::*;
+///
+/// assert_eq!(resolve_code("EOD"), CodeResolution::RenamedTo("QUOTEMEDIA".to_string()));
+/// assert_eq!(resolve_code("FRED"), CodeResolution::Active);
+/// ```
+///
+pub fn resolve_code(code: &str) -> CodeResolution {
+    let aliases = ALIASES.get_or_init(|| parse(ALIASES_TOML));
+    aliases.get(&code.trim().to_uppercase()).cloned().unwrap_or(CodeResolution::Active)
+}
+
+/// Parse `toml`, written in the subset of TOML described in `aliases.toml`.
+///
+/// The file is embedded in the crate and covered by its tests, so it being malformed is a bug:
+/// this panics and tells where.
+///
+fn parse(toml: &str) -> BTreeMap<String, CodeResolution> {
+    let sections = crate::toml_sections::sections("aliases.toml", toml);
+
+    sections.into_iter().map(|(code, mut fields)| {
+        let field = |fields: &mut BTreeMap<String, String>, name: &str| {
+            fields.remove(name).unwrap_or_else(|| {
+                panic!("aliases.toml: missing '{}' in the section of {}.", name, code)
+            })
+        };
+
+        let resolution = {
+            match &field(&mut fields, "status")[..] {
+                "renamed" => CodeResolution::RenamedTo(field(&mut fields, "successor")),
+
+                "retired" => {
+                    let since = fields.remove("since").map(|since| {
+                        NaiveDate::parse_from_str(&since, "%Y-%m-%d").unwrap_or_else(|_| {
+                            panic!("aliases.toml: invalid date '{}' for {}.", since, code)
+                        })
+                    });
+
+                    CodeResolution::Retired { since, note: field(&mut fields, "note") }
+                },
+
+                status => panic!("aliases.toml: unknown status '{}' for {}.", status, code),
+            }
+        };
+
+        (code, resolution)
+    }).collect()
+}
@@ -1,15 +1,28 @@
+use std::time::Instant;
+
+use bytes::Bytes;
+
 use has::Has;
 
 use serde::de::DeserializeOwned;
-use serde_json;
 
-use crate::{Result, Error};
+use url::Url;
+
+use crate::{CancelToken, Result, Error, ValidationError};
+use crate::download::Request;
 use crate::parameters::ApiArguments;
+use crate::types::QueryKind;
 
 /// Quandl API URL used as the base URL for all queries.
 ///
+/// Its last segment is the API version, `QUANDL_API_VERSION`, see `ApiParameters::api_version`.
+///
 pub const QUANDL_API_URL: &str = "https://www.quandl.com/api/v3";
 
+/// Version of Quandl's API queried by default, i.e. the last segment of `QUANDL_API_URL`.
+///
+pub const QUANDL_API_VERSION: &str = "v3";
+
 /// Trait allowing implementers to submit a request through the Quandl API.
 ///
 /// This trait is implemented by all queries.
@@ -18,41 +31,90 @@ pub trait ApiCall<T: DeserializeOwned + Clone>: Has<ApiArguments> {
     /// Returns the URL that will be used to submit the query through Quandl's API.
     ///
     fn url(&self) -> String {
-        let mut url = QUANDL_API_URL.to_string();
-
-        if let Some(prefix) = self.fmt_prefix() {
-            url.push_str(&prefix[..]);
-        }
-
-        if let Some(arguments) = self.fmt_arguments() {
-            url.push('?');
-            url.push_str(&arguments[..]);
-        }
+        url(Has::<ApiArguments>::get_ref(self), self.fmt_prefix(), self.fmt_arguments())
+    }
 
-        url
+    /// Returns the same URL as `url`, parsed, e.g. to inspect or adjust it before sending the
+    /// request by other means.
+    ///
+    /// This fails if the base URL given to `ApiParameters::base_url` is not a valid URL.
+    ///
+    fn parsed_url(&self) -> Result<Url> {
+        parsed_url(Has::<ApiArguments>::get_ref(self), self.fmt_prefix(), self.fmt_arguments())
     }
 
     /// Bypass the parsers and retrieve the byte stream received from Quandl directly.
     ///
+    /// The payload is copied out of the buffer it was received in, see `encoded_bytes` to share
+    /// it instead.
+    ///
     fn encoded_data(&self) -> Result<Vec<u8>> {
-        crate::download::download(self.url())
+        self.encoded_bytes().map(|bytes| bytes.to_vec())
+    }
+
+    /// Same as `encoded_data`, but the payload is returned as received: clones and slices of it
+    /// (e.g. handed to several sinks) share the same buffer instead of copying it.
+    ///
+    fn encoded_bytes(&self) -> Result<Bytes> {
+        let arguments = Has::<ApiArguments>::get_ref(self);
+        arguments.ready(|| crate::parameters::validated(self.validation_errors()))?;
+
+        request(self).fetch().map(|response| response.body)
+    }
+
+    /// Size of the payload Quandl would send for this query, as advertised in the
+    /// `Content-Length` of its response to a HEAD request to the same URL.
+    ///
+    /// This is `None` when the server does not advertise the size or rejects the HEAD request
+    /// (e.g. with `405 Method Not Allowed`); only failing to reach the server is an error.
+    ///
+    fn content_length(&self) -> Result<Option<u64>> {
+        let arguments = Has::<ApiArguments>::get_ref(self);
+        arguments.ready(|| crate::parameters::validated(self.validation_errors()))?;
+
+        let mut request = request(self);
+        request.method = reqwest::Method::HEAD;
+        request.content_length()
     }
 
     /// Submit a request to the Quandl's API and return a parsed object representing the data
     /// received in a Rust-friendly format.
     ///
+    /// The JSON payload is expected to be served as `application/json`, see
+    /// `ApiParameters::accept_any_content_type` to disable that check.
+    ///
     fn send(&self) -> Result<T> {
-        let json_data = {
-            match String::from_utf8(self.encoded_data()?) {
-                Ok(json) => json,
-                Err(e) => { return Err(Error::ParsingFailed(e.to_string())); }
+        let body = checked_body(self, crate::download::JSON)?;
+        crate::download::json(&body)
+    }
+
+    /// Same as `send`, but giving up once `deadline` passes, e.g. when the query is made on behalf
+    /// of a caller which itself has a deadline.
+    ///
+    /// Every request the query takes is given what is left of the budget as its timeout, and none
+    /// is sent once the deadline passed: `Error::DeadlineExceeded` is returned instead, right away
+    /// if the budget is already spent.
+    ///
+    fn send_with_deadline(&self, deadline: Instant) -> Result<T> {
+        crate::download::with_deadline(deadline, || {
+            if Instant::now() >= deadline {
+                return Err(Error::DeadlineExceeded);
             }
-        };
 
-        match serde_json::from_str::<T>(&json_data[..]) {
-            Ok(data) => Ok(data),
-            Err(e) => Err(Error::ParsingFailed(e.to_string())),
-        }
+            self.send()
+        })
+    }
+
+    /// Same as `send`, but giving up as soon as `token` is cancelled, e.g. from another thread.
+    ///
+    /// Nothing is sent if the token is already cancelled, and the request in flight is abandoned
+    /// when it is: `Error::Cancelled` is returned either way, see `CancelToken`.
+    ///
+    fn send_with_cancel(&self, token: &CancelToken) -> Result<T> {
+        token.scope(|| {
+            crate::cancel::check()?;
+            self.send()
+        })
     }
 
     /// If applicable, returns the string that would be appended between the `QUANDL_API_URL` and
@@ -68,21 +130,198 @@ pub trait ApiCall<T: DeserializeOwned + Clone>: Has<ApiArguments> {
     fn fmt_arguments(&self) -> Option<String> {
         None
     }
+
+    /// Code of the database this query targets, if any.
+    ///
+    /// This is used to aggregate statistics by database, see `BatchQuery::run_with_report`.
+    ///
+    fn database_code(&self) -> Option<&str> {
+        None
+    }
+
+    /// Code of the dataset this query targets, if any.
+    ///
+    fn dataset_code(&self) -> Option<&str> {
+        None
+    }
+
+    /// How heavy this query is, `QueryKind::Light` unless it downloads the rows of a dataset.
+    ///
+    /// This is used to spread the queries of a batch over its keys, see `BatchQuery::key_profile`.
+    ///
+    fn kind(&self) -> QueryKind {
+        QueryKind::Light
+    }
+
+    /// Every problem with this query found without sending it, see `ApiParameters::strict`.
+    ///
+    /// The queries of this crate expose these through their `validate` method.
+    ///
+    fn validation_errors(&self) -> Vec<ValidationError> {
+        Has::<ApiArguments>::get_ref(self).validation_errors()
+    }
+}
+
+/// Assemble the URL of a query from its base URL (or `QUANDL_API_URL`), prefix and arguments.
+///
+pub fn url(api_arguments: &ApiArguments, prefix: Option<String>, arguments: Option<String>)
+    -> String
+{
+    let mut url = base_url(api_arguments);
+
+    if let Some(prefix) = prefix {
+        url.push_str(&prefix[..]);
+    }
+
+    if let Some(arguments) = arguments {
+        url.push('?');
+        url.push_str(&arguments[..]);
+    }
+
+    url
+}
+
+/// Same as `url`, but building a `Url` from the parsed base URL.
+///
+/// Characters of the prefix which are not allowed in a path are percent-encoded, and the arguments
+/// are appended to the query string of the base URL, if any.
+///
+pub fn parsed_url(api_arguments: &ApiArguments, prefix: Option<String>, arguments: Option<String>)
+    -> Result<Url>
+{
+    let base_url = base_url(api_arguments);
+
+    let invalid = |reason: &str| {
+        Error::ParsingFailed(format!("invalid base URL '{}': {}.", base_url, reason))
+    };
+
+    let mut url = Url::parse(&base_url).map_err(|e| invalid(&e.to_string()))?;
+
+    if url.cannot_be_a_base() {
+        return Err(invalid("not a hierarchical URL"));
+    }
+
+    if let Some(prefix) = prefix {
+        let path = format!("{}{}", url.path().trim_end_matches('/'), prefix);
+        url.set_path(&path);
+    }
+
+    let query = {
+        match (url.query(), arguments) {
+            (Some(query), Some(arguments)) => Some(format!("{}&{}", query, arguments)),
+            (query, arguments) => arguments.or_else(|| query.map(|x| x.to_string())),
+        }
+    };
+
+    url.set_query(query.as_ref().map(|x| &x[..]));
+    Ok(url)
 }
 
-impl<'a, T: DeserializeOwned + Clone, A: ApiCall<T>> ApiCall<T> for &'a A {
+/// Base URL of the queries: the one given to `ApiParameters::base_url` or `QUANDL_API_URL`, with
+/// its last path segment (the API version) replaced by the one given to
+/// `ApiParameters::api_version`, if any.
+///
+fn base_url(api_arguments: &ApiArguments) -> String {
+    let base_url = api_arguments.base_url.as_ref().map(|x| &x[..]).unwrap_or(QUANDL_API_URL);
+
+    let version = {
+        match api_arguments.api_version {
+            Some(ref version) => version,
+            None => return base_url.to_string(),
+        }
+    };
+
+    // The version is looked for in the path, i.e. after the host and before the query string.
+    let host_start = base_url.find("://").map(|x| x + 3).unwrap_or(0);
+    let (path, query) = {
+        match base_url[host_start..].find('?') {
+            Some(index) => base_url.split_at(host_start + index),
+            None => (base_url, ""),
+        }
+    };
+
+    let path = path.trim_end_matches('/');
+
+    match path.get(host_start..).and_then(|x| x.rfind('/')) {
+        Some(index) => format!("{}/{}{}", &path[..host_start + index], version, query),
+        None => format!("{}/{}{}", path, version, query),
+    }
+}
+
+/// Request sending `call` as configured by its API arguments.
+///
+pub fn request<T, A>(call: &A) -> Request
+    where T: DeserializeOwned + Clone,
+          A: ApiCall<T> + ?Sized,
+{
+    let arguments = Has::<ApiArguments>::get_ref(call);
+
+    Request::get(call.url(), arguments.client.as_ref())
+        .max_bytes(arguments.max_response_bytes)
+        .database_code(call.database_code().map(|code| arguments.code(code)))
+}
+
+/// Download the response to `call`, making sure it was served with one of the `expected` content
+/// types unless the query accepts any.
+///
+pub fn checked_body<T, A>(call: &A, expected: &[&str]) -> Result<Bytes>
+    where T: DeserializeOwned + Clone,
+          A: ApiCall<T> + ?Sized,
+{
+    checked_body_of(call, request(call), expected)
+}
+
+/// Same as `checked_body`, sending `request` (e.g. `request(call)` adjusted) in place of `call`.
+///
+pub fn checked_body_of<T, A>(call: &A, request: Request, expected: &[&str]) -> Result<Bytes>
+    where T: DeserializeOwned + Clone,
+          A: ApiCall<T> + ?Sized,
+{
+    let arguments = Has::<ApiArguments>::get_ref(call);
+    arguments.ready(|| crate::parameters::validated(call.validation_errors()))?;
+
+    let response = request.fetch()?;
+
+    if arguments.any_content_type {
+        Ok(response.body)
+    } else {
+        response.expect_content_type(expected)
+    }
+}
+
+impl<T: DeserializeOwned + Clone, A: ApiCall<T>> ApiCall<T> for &A {
     fn url(&self) -> String {
         ApiCall::<T>::url(*self)
     }
 
+    fn parsed_url(&self) -> Result<Url> {
+        ApiCall::<T>::parsed_url(*self)
+    }
+
     fn encoded_data(&self) -> Result<Vec<u8>> {
         ApiCall::<T>::encoded_data(*self)
     }
 
+    fn encoded_bytes(&self) -> Result<Bytes> {
+        ApiCall::<T>::encoded_bytes(*self)
+    }
+
+    fn content_length(&self) -> Result<Option<u64>> {
+        ApiCall::<T>::content_length(*self)
+    }
+
     fn send(&self) -> Result<T> {
         ApiCall::<T>::send(*self)
     }
 
+    fn send_with_deadline(&self, deadline: Instant) -> Result<T> {
+        ApiCall::<T>::send_with_deadline(*self, deadline)
+    }
+
+    fn send_with_cancel(&self, token: &CancelToken) -> Result<T> {
+        ApiCall::<T>::send_with_cancel(*self, token)
+    }
+
     fn fmt_prefix(&self) -> Option<String> {
         ApiCall::<T>::fmt_prefix(*self)
     }
@@ -90,21 +329,57 @@ impl<'a, T: DeserializeOwned + Clone, A: ApiCall<T>> ApiCall<T> for &'a A {
     fn fmt_arguments(&self) -> Option<String> {
         ApiCall::<T>::fmt_arguments(*self)
     }
+
+    fn database_code(&self) -> Option<&str> {
+        ApiCall::<T>::database_code(*self)
+    }
+
+    fn dataset_code(&self) -> Option<&str> {
+        ApiCall::<T>::dataset_code(*self)
+    }
+
+    fn kind(&self) -> QueryKind {
+        ApiCall::<T>::kind(*self)
+    }
+
+    fn validation_errors(&self) -> Vec<ValidationError> {
+        ApiCall::<T>::validation_errors(*self)
+    }
 }
 
-impl<'a, T: DeserializeOwned + Clone, A: ApiCall<T>> ApiCall<T> for &'a mut A {
+impl<T: DeserializeOwned + Clone, A: ApiCall<T>> ApiCall<T> for &mut A {
     fn url(&self) -> String {
         ApiCall::<T>::url(*self)
     }
 
+    fn parsed_url(&self) -> Result<Url> {
+        ApiCall::<T>::parsed_url(*self)
+    }
+
     fn encoded_data(&self) -> Result<Vec<u8>> {
         ApiCall::<T>::encoded_data(*self)
     }
 
+    fn encoded_bytes(&self) -> Result<Bytes> {
+        ApiCall::<T>::encoded_bytes(*self)
+    }
+
+    fn content_length(&self) -> Result<Option<u64>> {
+        ApiCall::<T>::content_length(*self)
+    }
+
     fn send(&self) -> Result<T> {
         ApiCall::<T>::send(*self)
     }
 
+    fn send_with_deadline(&self, deadline: Instant) -> Result<T> {
+        ApiCall::<T>::send_with_deadline(*self, deadline)
+    }
+
+    fn send_with_cancel(&self, token: &CancelToken) -> Result<T> {
+        ApiCall::<T>::send_with_cancel(*self, token)
+    }
+
     fn fmt_prefix(&self) -> Option<String> {
         ApiCall::<T>::fmt_prefix(*self)
     }
@@ -112,4 +387,20 @@ impl<'a, T: DeserializeOwned + Clone, A: ApiCall<T>> ApiCall<T> for &'a mut A {
     fn fmt_arguments(&self) -> Option<String> {
         ApiCall::<T>::fmt_arguments(*self)
     }
+
+    fn database_code(&self) -> Option<&str> {
+        ApiCall::<T>::database_code(*self)
+    }
+
+    fn dataset_code(&self) -> Option<&str> {
+        ApiCall::<T>::dataset_code(*self)
+    }
+
+    fn kind(&self) -> QueryKind {
+        ApiCall::<T>::kind(*self)
+    }
+
+    fn validation_errors(&self) -> Vec<ValidationError> {
+        ApiCall::<T>::validation_errors(*self)
+    }
 }
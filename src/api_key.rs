@@ -0,0 +1,113 @@
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ptr;
+use std::sync::Arc;
+use std::sync::atomic::{compiler_fence, Ordering};
+
+/// An API key, kept out of sight.
+///
+/// The key is only revealed by `expose_secret`, which this crate calls when formatting the URL a
+/// query is sent to (and to tell keys apart in rate limits and reports): `Debug` prints
+/// `ApiKey(****)` and there is no `Display`, so keys do not end up in logs along with the queries
+/// holding them. Clones share the key rather than copying it, and its bytes are overwritten with
+/// zeros once the last of them is dropped, so that it does not linger in freed memory.
+///
+/// Keys compare and hash on their value, e.g. queries sent with equal keys share the rate limits
+/// of that key in a `BatchQuery` however their keys were built.
+///
+/// ```rust
+/// use quandl_v3::ApiKey;
+///
+/// let key = ApiKey::new("0123456789abcdef");
+///
+/// assert_eq!(format!("{:?}", key), "ApiKey(****)");
+/// assert_eq!(key, ApiKey::from(String::from("0123456789abcdef")));
+/// assert_eq!(key.expose_secret(), "0123456789abcdef");
+/// ```
+///
+#[derive(Clone)]
+pub struct ApiKey(Arc<Secret>);
+
+/// The value of a key, zeroed when dropped.
+///
+struct Secret(Box<str>);
+
+impl ApiKey {
+    /// The key `key`, as is (unlike `ApiParameters::api_key`, this does not trim it).
+    ///
+    pub fn new<S: AsRef<str>>(key: S) -> Self {
+        ApiKey(Arc::new(Secret(Box::from(key.as_ref()))))
+    }
+
+    /// The value of the key.
+    ///
+    pub fn expose_secret(&self) -> &str {
+        &(self.0).0
+    }
+
+    /// This key without its leading and trailing whitespace, shared with `self` if it has none.
+    ///
+    pub(crate) fn trimmed(&self) -> Self {
+        let trimmed = self.expose_secret().trim();
+
+        if trimmed.len() == self.expose_secret().len() {
+            self.clone()
+        } else {
+            ApiKey::new(trimmed)
+        }
+    }
+}
+
+impl<'a> From<&'a str> for ApiKey {
+    fn from(key: &'a str) -> Self {
+        ApiKey::new(key)
+    }
+}
+
+impl From<String> for ApiKey {
+    /// The key `key`, whose buffer is zeroed once copied.
+    ///
+    fn from(key: String) -> Self {
+        let api_key = ApiKey::new(&key);
+        zeroize(&mut key.into_bytes());
+        api_key
+    }
+}
+
+impl fmt::Debug for ApiKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("ApiKey(****)")
+    }
+}
+
+impl PartialEq for ApiKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.expose_secret() == other.expose_secret()
+    }
+}
+
+impl Eq for ApiKey {}
+
+impl Hash for ApiKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.expose_secret().hash(state)
+    }
+}
+
+impl Drop for Secret {
+    fn drop(&mut self) {
+        // Zero bytes are valid UTF-8, so the string stays well-formed until it is freed.
+        zeroize(unsafe { self.0.as_bytes_mut() });
+    }
+}
+
+/// Overwrite `bytes` with zeros, in a way the compiler cannot elide although they are about to
+/// be freed.
+///
+fn zeroize(bytes: &mut [u8]) {
+    for byte in bytes.iter_mut() {
+        unsafe { ptr::write_volatile(byte, 0) };
+    }
+
+    compiler_fence(Ordering::SeqCst);
+}
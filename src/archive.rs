@@ -0,0 +1,329 @@
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use chrono::Utc;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use sha2::{Digest, Sha256};
+
+use crate::{Result, Error};
+
+/// SHA-256 hash of a payload, shown (and serialized) as 64 lowercase hexadecimal digits.
+///
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Hash([u8; 32]);
+
+impl Hash {
+    /// Hash of `payload`.
+    ///
+    pub fn of(payload: &[u8]) -> Self {
+        Hash(Sha256::digest(payload).into())
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+impl fmt::Display for Hash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.iter().try_for_each(|byte| write!(f, "{:02x}", byte))
+    }
+}
+
+impl fmt::Debug for Hash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Hash({})", self)
+    }
+}
+
+impl FromStr for Hash {
+    type Err = Error;
+
+    fn from_str(hex: &str) -> Result<Self> {
+        let invalid = || Error::ParsingFailed(format!("invalid SHA-256 hash '{}'.", hex));
+
+        if hex.len() != 64 || !hex.bytes().all(|x| x.is_ascii_hexdigit()) {
+            return Err(invalid());
+        }
+
+        let mut hash = [0; 32];
+
+        for (index, byte) in hash.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[2 * index..2 * index + 2], 16).map_err(|_| invalid())?;
+        }
+
+        Ok(Hash(hash))
+    }
+}
+
+impl Serialize for Hash {
+    fn serialize<S: Serializer>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Hash {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> ::std::result::Result<Self, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        hex.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Archive of raw payloads in a directory, each stored once under its hash, along with an index
+/// telling which payload each query got.
+///
+/// The payloads are in `objects/`, as `objects/ab/cdef…` for a payload of hash `abcdef…`, and the
+/// index is the JSON Lines file `index.jsonl`, to which an entry (see `ArchiveEntry`) is appended
+/// for every payload stored.
+///
+/// Queries are identified by their URL without its API key, so the index can be shared without
+/// revealing the keys. Several processes (or threads, through several `Archive` of the same
+/// directory) may store payloads at once: payloads are written under a temporary name and then
+/// renamed, and entries are appended to the index at once under an exclusive lock of it. A crash
+/// at worst leaves a temporary file in `objects/`, or a payload which no entry refers to yet.
+///
+/// Payloads can also be archived as they are received by queries, see
+/// `ClientConfig::write_through`.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Archive {
+    directory: PathBuf,
+}
+
+/// Entry of the index of an `Archive`.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchiveEntry {
+    /// URL of the query, without its API key.
+    ///
+    pub url: String,
+
+    /// Hash of the payload the query got.
+    ///
+    pub sha256: Hash,
+
+    /// Size of the payload, in bytes.
+    ///
+    pub size: u64,
+
+    /// When the payload was stored, as an RFC 3339 UTC timestamp.
+    ///
+    pub stored_at: String,
+}
+
+/// A payload of an `Archive` which does not match its index, see `Archive::verify`.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Corruption {
+    /// The file of the payload is missing.
+    ///
+    Missing { hash: Hash, path: PathBuf },
+
+    /// The file of the payload does not hash to its name, e.g. since it was truncated or edited.
+    ///
+    Altered { hash: Hash, actual: Hash, path: PathBuf },
+}
+
+impl fmt::Display for Corruption {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Corruption::Missing { ref hash, ref path } => {
+                write!(f, "payload {} is missing ({})", hash, path.display())
+            },
+
+            Corruption::Altered { ref hash, ref actual, ref path } => {
+                write!(f, "payload {} was altered, it now hashes to {} ({})",
+                       hash, actual, path.display())
+            },
+        }
+    }
+}
+
+const INDEX: &str = "index.jsonl";
+const OBJECTS: &str = "objects";
+const TEMPORARY_EXTENSION: &str = "tmp";
+
+impl Archive {
+    /// Open the archive in `directory`, which is created (empty) if necessary.
+    ///
+    pub fn open<P: AsRef<Path>>(directory: P) -> Result<Self> {
+        let directory = directory.as_ref().to_path_buf();
+        fs::create_dir_all(directory.join(OBJECTS)).map_err(io_error)?;
+        Ok(Archive { directory })
+    }
+
+    /// Directory of the archive.
+    ///
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// Path of the file of the payload of hash `hash`, whether it is stored or not.
+    ///
+    pub fn path(&self, hash: &Hash) -> PathBuf {
+        let hex = hash.to_string();
+        self.directory.join(OBJECTS).join(&hex[..2]).join(&hex[2..])
+    }
+
+    /// Store `payload` as the one the query of `url` got, returning its hash.
+    ///
+    /// A payload already stored, e.g. for another query, is not written again: only the entry of
+    /// `url` is appended to the index. Storing another payload for the same URL later does not
+    /// remove the first one, the index keeping track of both.
+    ///
+    pub fn store<S: AsRef<str>>(&self, url: S, payload: &[u8]) -> Result<Hash> {
+        let hash = Hash::of(payload);
+        let path = self.path(&hash);
+
+        // A file of the wrong size cannot be the payload, and is replaced.
+        let stored = fs::metadata(&path).map(|x| x.len() == payload.len() as u64).unwrap_or(false);
+
+        if !stored {
+            self.write_atomically(&path, payload)?;
+        }
+
+        let entry = {
+            ArchiveEntry {
+                url: canonical_url(url.as_ref()),
+                sha256: hash,
+                size: payload.len() as u64,
+                stored_at: crate::calendar::format_timestamp(&Utc::now()),
+            }
+        };
+
+        self.append(&entry)?;
+        Ok(hash)
+    }
+
+    /// Hash and path of the payload last stored for the query of `url` (API key or not), or
+    /// `None` if none was or if its file is gone (see `verify`).
+    ///
+    pub fn lookup<S: AsRef<str>>(&self, url: S) -> Result<Option<(Hash, PathBuf)>> {
+        let url = canonical_url(url.as_ref());
+
+        let found = {
+            self.entries()?.into_iter()
+                .rev()
+                .find(|entry| entry.url == url)
+                .map(|entry| (entry.sha256, self.path(&entry.sha256)))
+        };
+
+        Ok(found.filter(|(_, path)| path.is_file()))
+    }
+
+    /// Entries of the index, in the order they were stored. Lines of the index which do not
+    /// parse, e.g. the last one if a process crashed while writing it, are skipped.
+    ///
+    pub fn entries(&self) -> Result<Vec<ArchiveEntry>> {
+        let content = {
+            match fs::read_to_string(self.directory.join(INDEX)) {
+                Ok(content) => content,
+                Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+                Err(e) => return Err(io_error(e)),
+            }
+        };
+
+        Ok(content.lines().filter_map(|line| serde_json::from_str(line).ok()).collect())
+    }
+
+    /// Check the payloads referred to by the index against their hashes, returning those which do
+    /// not match, in order of hash. Each payload is read (and hashed) once, however many entries
+    /// refer to it.
+    ///
+    pub fn verify(&self) -> Result<Vec<Corruption>> {
+        let mut hashes: Vec<Hash> = self.entries()?.into_iter().map(|x| x.sha256).collect();
+        hashes.sort();
+        hashes.dedup();
+
+        let mut corruptions = vec![];
+
+        for hash in hashes {
+            let path = self.path(&hash);
+
+            match fs::read(&path) {
+                Ok(payload) => {
+                    let actual = Hash::of(&payload);
+
+                    if actual != hash {
+                        corruptions.push(Corruption::Altered { hash, actual, path });
+                    }
+                },
+
+                Err(ref e) if e.kind() == io::ErrorKind::NotFound => {
+                    corruptions.push(Corruption::Missing { hash, path });
+                },
+
+                Err(e) => return Err(io_error(e)),
+            }
+        }
+
+        Ok(corruptions)
+    }
+
+    fn write_atomically(&self, path: &Path, data: &[u8]) -> Result<()> {
+        let temporary = {
+            self.directory.join(OBJECTS).join(format!("{}.{}", unique_name(), TEMPORARY_EXTENSION))
+        };
+
+        let result = {
+            File::create(&temporary)
+                .and_then(|mut file| file.write_all(data).and_then(|_| file.sync_all()))
+                .and_then(|_| fs::create_dir_all(path.parent().expect("objects have a parent")))
+                .and_then(|_| fs::rename(&temporary, path))
+        };
+
+        if result.is_err() {
+            let _ = fs::remove_file(&temporary);
+        }
+
+        result.map_err(io_error)
+    }
+
+    fn append(&self, entry: &ArchiveEntry) -> Result<()> {
+        let line = serde_json::to_string(entry).map_err(Error::from)?;
+        let path = self.directory.join(INDEX);
+
+        let mut file = {
+            OpenOptions::new().create(true).read(true).append(true).open(&path).map_err(io_error)?
+        };
+
+        file.lock().map_err(io_error)?;
+
+        // A line left unterminated by a crash is terminated first, so that this one parses.
+        let torn = {
+            file.metadata().map_err(io_error)?.len() > 0 && {
+                let mut last = [0];
+                file.seek(SeekFrom::End(-1)).and_then(|_| file.read_exact(&mut last))
+                    .map_err(io_error)?;
+                last[0] != b'\n'
+            }
+        };
+
+        let line = if torn { format!("\n{}\n", line) } else { format!("{}\n", line) };
+
+        // Written at once, and unlocked when `file` is closed.
+        file.write_all(line.as_bytes()).and_then(|_| file.flush()).map_err(io_error)
+    }
+}
+
+/// `url` without its API key, or as given if it is not a valid URL.
+///
+fn canonical_url(url: &str) -> String {
+    match url::Url::parse(url) {
+        Ok(url) => crate::download::without_api_key(url),
+        Err(_) => url.to_string(),
+    }
+}
+
+fn io_error<E: ToString>(e: E) -> Error {
+    Error::IoError(e.to_string())
+}
+
+fn unique_name() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    format!("{}-{}", ::std::process::id(), COUNTER.fetch_add(1, Ordering::SeqCst))
+}
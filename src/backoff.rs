@@ -0,0 +1,286 @@
+use std::convert::TryFrom;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::{Result, Error};
+use crate::config::Config;
+
+/// How the delays of a `Backoff` are randomized, so that clients failing together do not retry
+/// together.
+///
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Jitter {
+    /// The delays are exactly `base * multiplier ^ n`, capped.
+    ///
+    None,
+
+    /// Each delay is drawn uniformly between zero and what it would be without jitter.
+    ///
+    Full,
+
+    /// Each delay is drawn uniformly between `base` and the previous delay times `multiplier`,
+    /// capped, so the delays grow on average without being tied to the number of attempts.
+    ///
+    Decorrelated,
+}
+
+/// Exponential backoff policy, e.g. to retry failed queries (see `retry`).
+///
+/// The `n`-th delay (from 0) is `base * multiplier ^ n`, capped at `max_delay` and randomized
+/// according to `jitter`. At most `max_attempts` attempts are made, the first one included, so
+/// the policy yields one delay less than that; without a maximum it yields delays forever.
+///
+/// A policy (de)serializes with serde, field by field, e.g. to be read from a configuration file.
+/// Missing fields take their default value: 500 milliseconds doubling up to a minute, with full
+/// jitter, for 5 attempts.
+///
+/// ```rust
+/// use std::time::Duration;
+/// use quandl_v3::backoff::*;
+///
+/// let mut backoff = Backoff::default();
+/// backoff.base(Duration::from_millis(100)).jitter(Jitter::None).max_attempts(Some(4));
+///
+/// let delays: Vec<u128> = backoff.delays().map(|x| x.as_millis()).collect();
+/// assert_eq!(delays, [100, 200, 400]);
+/// ```
+///
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Backoff {
+    base: Duration,
+    multiplier: f64,
+    max_delay: Duration,
+    jitter: Jitter,
+    max_attempts: Option<usize>,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Backoff {
+            base: Duration::from_millis(500),
+            multiplier: 2.0,
+            max_delay: Duration::from_secs(60),
+            jitter: Jitter::Full,
+            max_attempts: Some(5),
+        }
+    }
+}
+
+impl Backoff {
+    /// Delay before the first retry, before any jitter.
+    ///
+    pub fn base(&mut self, base: Duration) -> &mut Self {
+        self.base = base;
+        self
+    }
+
+    /// Factor by which each delay grows, at least 1.
+    ///
+    pub fn multiplier(&mut self, multiplier: f64) -> &mut Self {
+        assert!(multiplier >= 1.0 && multiplier.is_finite(), "multiplier: {}", multiplier);
+        self.multiplier = multiplier;
+        self
+    }
+
+    /// Longest delay, jitter included.
+    ///
+    pub fn max_delay(&mut self, max_delay: Duration) -> &mut Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// How the delays are randomized.
+    ///
+    pub fn jitter(&mut self, jitter: Jitter) -> &mut Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Maximum number of attempts, the first one included, or `None` to retry forever.
+    ///
+    pub fn max_attempts(&mut self, max_attempts: Option<usize>) -> &mut Self {
+        assert!(max_attempts != Some(0), "max_attempts: Some(0)");
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// The successive delays of this policy, randomized from the clock, or from the seed of the
+    /// current configuration in its reproducibility mode (see `Config::deterministic`), in which
+    /// case they are always the same.
+    ///
+    pub fn delays(&self) -> Delays {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+        if let Some(seed) = Config::current().seed {
+            return self.delays_with_seed(seed);
+        }
+
+        let nanos = {
+            SystemTime::now().duration_since(UNIX_EPOCH).map(|x| x.as_nanos() as u64).unwrap_or(0)
+        };
+
+        // Delays created at the same instant still differ.
+        self.delays_with_seed(nanos ^ COUNTER.fetch_add(1, Ordering::Relaxed).rotate_left(32))
+    }
+
+    /// The successive delays of this policy, randomized from `seed`: the same seed always gives
+    /// the same delays.
+    ///
+    pub fn delays_with_seed(&self, seed: u64) -> Delays {
+        Delays {
+            policy: *self,
+            attempt: 0,
+            previous: self.base,
+            // The generator must not start from zero, where it would stay.
+            state: seed ^ 0x9e37_79b9_7f4a_7c15 | 1,
+        }
+    }
+
+    /// The policy as deserialized may hold values its setters would reject, which are brought
+    /// back within range instead.
+    ///
+    fn multiplier_or_one(&self) -> f64 {
+        if self.multiplier >= 1.0 && self.multiplier.is_finite() {
+            self.multiplier
+        } else {
+            1.0
+        }
+    }
+}
+
+/// Successive delays of a `Backoff`, from the delay before the first retry on.
+///
+#[derive(Debug, Clone)]
+pub struct Delays {
+    policy: Backoff,
+    attempt: usize,
+    previous: Duration,
+    state: u64,
+}
+
+impl Delays {
+    /// The delay to wait before the next attempt, or `None` once the policy's attempts are
+    /// exhausted.
+    ///
+    pub fn next_delay(&mut self) -> Option<Duration> {
+        let policy = self.policy;
+
+        // The first attempt takes no delay.
+        if policy.max_attempts.map(|max| self.attempt + 1 >= max).unwrap_or(false) {
+            return None;
+        }
+
+        let max = policy.max_delay.as_secs_f64();
+        let base = policy.base.as_secs_f64().min(max);
+        let multiplier = policy.multiplier_or_one();
+
+        let exponential = {
+            // Powers beyond the cap are all the same.
+            let exponent = i32::try_from(self.attempt).unwrap_or(i32::MAX);
+
+            if base > 0.0 {
+                (base * multiplier.powi(exponent)).min(max)
+            } else {
+                0.0
+            }
+        };
+
+        let delay = {
+            match policy.jitter {
+                Jitter::None => exponential,
+                Jitter::Full => exponential * self.uniform(),
+
+                Jitter::Decorrelated => {
+                    let high = (self.previous.as_secs_f64() * multiplier).min(max).max(base);
+                    base + (high - base) * self.uniform()
+                },
+            }
+        };
+
+        // Rounding may overshoot the cap, e.g. for `Duration::MAX`.
+        let delay = {
+            Duration::try_from_secs_f64(delay.max(0.0).min(max))
+                .map(|delay| delay.min(policy.max_delay))
+                .unwrap_or(policy.max_delay)
+        };
+
+        self.attempt += 1;
+        self.previous = delay;
+        Some(delay)
+    }
+
+    /// Number of delays yielded so far.
+    ///
+    pub fn retries(&self) -> usize {
+        self.attempt
+    }
+
+    /// A number drawn uniformly from [0, 1], with xorshift64*.
+    ///
+    fn uniform(&mut self) -> f64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+
+        let x = self.state.wrapping_mul(0x2545_f491_4f6c_dd1d);
+        (x >> 11) as f64 / ((1u64 << 53) - 1) as f64
+    }
+}
+
+impl Iterator for Delays {
+    type Item = Duration;
+
+    fn next(&mut self) -> Option<Duration> {
+        self.next_delay()
+    }
+}
+
+/// Call `f` until it succeeds, sleeping for the delays of `backoff` in-between, as long as it
+/// fails in a way worth retrying (see `is_retryable`). The last error is returned once the
+/// attempts allowed by `backoff` are exhausted.
+///
+/// The sleeps are cut short by the cancellation of a token in effect on the current thread (see
+/// `CancelToken::scope`), `Error::Cancelled` being returned then.
+///
+/// ```rust,no_run
+/// use quandl_v3::backoff::*;
+/// use quandl_v3::prelude::*;
+///
+/// let query = DatabaseMetadataQuery::new("WIKI");
+/// let metadata = retry(&Backoff::default(), || query.send()).unwrap();
+/// ```
+///
+pub fn retry<T, F: FnMut() -> Result<T>>(backoff: &Backoff, mut f: F) -> Result<T> {
+    let mut delays = backoff.delays();
+
+    loop {
+        match f() {
+            Err(ref e) if is_retryable(e) => {
+                match delays.next_delay() {
+                    Some(delay) => crate::cancel::sleep(delay)?,
+                    None => return Err(e.clone()),
+                }
+            },
+
+            result => return result,
+        }
+    }
+}
+
+/// Whether or not `error` is likely to be transient: a download failure of a retryable kind (see
+/// `DownloadErrorKind::is_retryable`), or Quandl (or a proxy in front of it) reporting that a
+/// limit was exceeded.
+///
+pub fn is_retryable(error: &Error) -> bool {
+    match error {
+        Error::DownloadFailed(e) => e.kind().is_retryable(),
+        Error::RateLimitExceeded { .. } => true,
+
+        // Quandl's codes for exceeded limits (QELx01, QELx04...).
+        Error::ApiCallFailed(e) => e.quandl_error.code.starts_with("QEL"),
+
+        _ => false,
+    }
+}
@@ -1,15 +1,28 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 
-use std::thread::spawn;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use std::thread;
 use std::sync::mpsc::{Receiver, TryRecvError, channel};
-use std::sync::{Arc, Mutex, RwLock};
+use std::sync::{Arc, Mutex, PoisonError};
 
-use has::Has;
+use chrono::{DateTime, Utc};
+use has::{Has, HasMut};
+use serde::Serializer;
 use serde::de::DeserializeOwned;
 
-use Result;
+use crate::{ApiKey, CancelToken, Error};
 use crate::api_call::ApiCall;
+use crate::config::Config;
+use crate::key_pool::{self, KeyProfile};
+use crate::latency::{LatencyHistogram, SlowLog, SlowQuery};
+use crate::types::Code;
 use crate::parameters::ApiArguments;
+use crate::rate_limit::{self, RateLimiter};
+#[cfg(feature = "async")] use crate::batch_stream::{BatchStream, Call};
 
 /// Builder pattern run multiple queries in batch.
 ///
@@ -20,21 +33,82 @@ use crate::parameters::ApiArguments;
 /// When batch downloading, it is important to keep Quandl's API limits in mind. Please read the
 /// documentation for methods `limit` and `concurrent_calls` for more information.
 ///
-pub struct BatchQuery<A, T>
-    where T: DeserializeOwned + Clone + Sync + Send + 'static,
-          A: ApiCall<T> + Clone + Sync + Send + 'static,
+/// Running a batch spawns its worker threads, which each own (or, with
+/// `SchedulingStrategy::WorkStealing`, take in turn from a shared list) the queries they send and
+/// pass the results back over a channel. This is why queries and results only need to be `Send`:
+/// neither is ever shared between threads. The returned `Iterator` is then `Send` as well and can
+/// be consumed from any thread, e.g. one dedicated to storing the results.
+///
+/// The workers are numbered from 0, their threads being named `quandl-batch-{n}` after them.
+/// A panic on a worker (e.g. in one of the callbacks of the batch) fails the query it was
+/// processing with an `Error::BatchInternal` telling which worker it was, rather than killing
+/// the worker with the rest of its queries.
+///
+/// The results can also be transformed by the workers as they arrive, see `map_rows`, in which
+/// case `U` is the type of the transformed results.
+///
+pub struct BatchQuery<A, T, U = T>
+    where T: DeserializeOwned + Clone + Send + 'static,
+          A: ApiCall<T> + Clone + Send + 'static,
 {
     offset: usize,
     limits: Vec<(usize, ::std::time::Duration)>,
     queries: Vec<A>,
-    threads: usize,
+    threads: Option<usize>,
     concurrent_calls: bool,
-    marker: ::std::marker::PhantomData<T>,
+    scheduling: SchedulingStrategy<A>,
+    manifest: Option<PathBuf>,
+    anonymous: bool,
+    anonymous_limits: Vec<(usize, ::std::time::Duration)>,
+    on_throttle: Option<ThrottleCallback>,
+    on_progress: Option<ProgressCallback>,
+    slow_queries: usize,
+    key_profiles: Vec<KeyProfile>,
+    set_key: Option<fn(&mut A, &ApiKey)>,
+    on_start: Option<StartHook>,
+    cancel: Option<CancelToken>,
+    reserve_quota: Option<usize>,
+    map: RowMap<T, U>,
 }
 
+/// Quandl's limits for calls made without an API key, as `(calls, seconds)`.
+///
+const ANONYMOUS_LIMITS: [(usize, u64); 2] = [(20, 600), (50, 86_400)];
+
+/// Bucket of the rate limiter shared by the queries without an API key, which no valid key can
+/// collide with.
+///
+const ANONYMOUS: &str = "";
+
+/// Number of calls kept in the slow-query log of a batch by default, see
+/// `BatchQuery::slow_queries`.
+///
+const SLOW_QUERIES: usize = 10;
+
+/// Duration of a call assumed by `BatchQuery::plan`.
+///
+const ASSUMED_CALL_DURATION: Duration = Duration::from_secs(1);
+
+/// Transformation applied to the results of a batch query by its workers.
+///
+type RowMap<T, U> = Arc<dyn Fn(&Code, T) -> Result<U, Error> + Send + Sync>;
+
+/// Callback told of the waits imposed by the rate limits of a batch query.
+///
+pub(crate) type ThrottleCallback = Arc<dyn Fn(&ThrottleEvent) + Send + Sync>;
+
+/// Callback told of each call made by a batch query, see `BatchQuery::on_progress`.
+///
+type ProgressCallback = Arc<dyn Fn(&BatchProgress) + Send + Sync>;
+
+/// Callback given the index of each query about to be sent, which is not sent if it fails, see
+/// `BatchQuery::on_start`.
+///
+pub(crate) type StartHook = Arc<dyn Fn(usize) -> Result<(), Error> + Send + Sync>;
+
 impl<A, T> BatchQuery<A, T>
-    where T: DeserializeOwned + Clone + Sync + Send + 'static,
-          A: ApiCall<T> + Clone + Sync + Send + 'static,
+    where T: DeserializeOwned + Clone + Send + 'static,
+          A: ApiCall<T> + Clone + Send + 'static,
 {
     /// Construct a new (empty) BatchQuery with default state.
     ///
@@ -43,12 +117,34 @@ impl<A, T> BatchQuery<A, T>
             offset: 0,
             limits: vec![],
             queries: vec![],
-            threads: ::num_cpus::get(),
+            threads: None,
             concurrent_calls: false,
-            marker: ::std::marker::PhantomData,
+            scheduling: SchedulingStrategy::RoundRobinStatic,
+            manifest: None,
+            anonymous: false,
+            anonymous_limits: {
+                ANONYMOUS_LIMITS.iter()
+                    .map(|&(limit, timeout)| (limit, Duration::new(timeout, 0)))
+                    .collect()
+            },
+            on_throttle: None,
+            on_progress: None,
+            slow_queries: SLOW_QUERIES,
+            key_profiles: vec![],
+            set_key: None,
+            on_start: None,
+            cancel: None,
+            reserve_quota: None,
+            map: Arc::new(|_, value| Ok(value)),
         }
     }
+}
 
+impl<A, T, U> BatchQuery<A, T, U>
+    where T: DeserializeOwned + Clone + Send + 'static,
+          A: ApiCall<T> + Clone + Send + 'static,
+          U: Send + 'static,
+{
     /// Assume that every API keys has already been used the specified number of times.
     ///
     /// This is an hackish way to send a big batch query underway immediately even if some API keys
@@ -57,6 +153,8 @@ impl<A, T> BatchQuery<A, T>
     /// information about their usage and can be saved/retrieved from disk to always be used as
     /// efficiently as possible.
     ///
+    /// Those calls are considered to have been made when the batch starts running.
+    ///
     pub fn offset(&mut self, offset: usize) -> &mut Self {
         self.offset = offset;
         self
@@ -82,7 +180,10 @@ impl<A, T> BatchQuery<A, T>
     /// * Up to 50 API calls within a day (86,400 seconds).
     ///
     /// This method allow to specify those limits in a future-proof fashion (the limits could
-    /// change at any time and this library does not handle it for this reason).
+    /// change at any time and this library does not handle it for this reason). The limits are
+    /// enforced over sliding windows, i.e. a call is delayed only as long as needed for `limit`
+    /// calls to never happen within `timeout` seconds with the same key. See
+    /// `rate_limit::RateLimiter` for the underlying implementation.
     ///
     /// For example, if not using any key, you would use this method as follow:
     ///
@@ -106,6 +207,7 @@ impl<A, T> BatchQuery<A, T>
     /// ```
     ///
     pub fn limit(&mut self, limit: usize, timeout: u64) -> &mut Self {
+        assert!(limit > 0, "limit: {}", limit);
         self.limits.push((limit, ::std::time::Duration::new(timeout, 0)));
         self
     }
@@ -124,14 +226,18 @@ impl<A, T> BatchQuery<A, T>
         self
     }
 
-    /// Specify the maximum number of threads to use.
+    /// Specify the number of threads to use, which must be bigger than 0.
     ///
-    /// By default the number of logical cores is used. The number of threads specified must be
-    /// bigger than 0.
+    /// By default, the batch runs as many threads as can be busy at once (see `default_threads`):
+    /// no more than the logical cores, the queries to send, nor (unless `concurrent_calls` is
+    /// set) the API keys they are sent with, since the calls made with a key are otherwise made
+    /// one at a time. That number is computed whenever the batch is run or planned, and reported
+    /// along with what bounded it (see `BatchReport::thread_limit`). A single thread is used in
+    /// the reproducibility mode whatever the setting, see `Config::deterministic`.
     ///
     pub fn threads(&mut self, threads: usize) -> &mut Self {
         assert!(threads > 0, "threads: {}", threads);
-        self.threads = threads;
+        self.threads = Some(threads);
         self
     }
 
@@ -146,157 +252,2001 @@ impl<A, T> BatchQuery<A, T>
         self
     }
 
-    /// Execute the batch query and return an iterator which asynchronously fetch the data.
+    /// Choose how queries are assigned to threads, `SchedulingStrategy::RoundRobinStatic` by
+    /// default, and always in the reproducibility mode (see `Config::deterministic`).
     ///
-    pub fn run(self) -> Iterator<Result<T, crate::Error>> {
-        let keys = Arc::new(RwLock::new(HashMap::<String, Mutex<usize>>::new()));
+    pub fn scheduling(&mut self, scheduling: SchedulingStrategy<A>) -> &mut Self {
+        self.scheduling = scheduling;
+        self
+    }
 
-        for query in self.queries.iter() {
-            if let Some(ref key) = Has::<ApiArguments>::get_ref(query).api_key {
-                if !keys.read().unwrap().contains_key(&key[..]) {
-                    keys.write().unwrap().insert(key.clone(), Mutex::new(self.offset));
-                }
-            }
+    /// Send the queries without an API key as well, instead of skipping them.
+    ///
+    /// Those queries share a single bucket as far as rate limiting goes, since Quandl limits them
+    /// by IP address: unless overridden with `anonymous_limits`, they are limited to Quandl's
+    /// limits for anonymous usage, i.e. 20 calls by 10 minutes and 50 calls a day. The limits set
+    /// with `limit` do not apply to them, and neither does `offset`. Like any key, the bucket is
+    /// only ever used by one call at a time unless `concurrent_calls` is set.
+    ///
+    pub fn anonymous(&mut self) -> &mut Self {
+        self.anonymous = true;
+        self
+    }
+
+    /// Replace the limits of the queries without an API key (see `anonymous`) by `limits`, each
+    /// given as `(limit, timeout)` like with `limit`.
+    ///
+    /// Quandl bans the IP addresses exceeding its limits, so only override them with limits known
+    /// to apply, e.g. those of a mirror set with `ApiParameters::base_url`. No limits at all are
+    /// given as an empty slice.
+    ///
+    pub fn anonymous_limits(&mut self, limits: &[(usize, u64)]) -> &mut Self {
+        for &(limit, _) in limits {
+            assert!(limit > 0, "limit: {}", limit);
         }
 
-        let mut jobs: Vec<Vec<A>> = vec![];
+        self.anonymous_limits = {
+            limits.iter().map(|&(limit, timeout)| (limit, Duration::new(timeout, 0))).collect()
+        };
+
+        self
+    }
+
+    /// Call `f` with each `ThrottleEvent` of the batch, i.e. each time a worker is about to wait
+    /// for a rate limit, from that worker's thread.
+    ///
+    /// The events are recorded in the batch's report as well (see `BatchReport::throttles`), this
+    /// is only needed to be told of them as they happen, e.g. to feed some metrics. `f` should
+    /// return quickly since the worker waits for it, and calling this again replaces it.
+    ///
+    pub fn on_throttle<F>(&mut self, f: F) -> &mut Self
+        where F: Fn(&ThrottleEvent) + Send + Sync + 'static
+    {
+        self.on_throttle = Some(Arc::new(f));
+        self
+    }
+
+    /// Call `f` with the progress of the batch after each of its calls, from the thread of the
+    /// worker which made it, e.g. to show a progress bar or to log how far a long run is.
+    ///
+    /// Calls complete in any order when the batch has several workers, so `f` is told of them as
+    /// they complete rather than in the order of the results of the iterator. Like with
+    /// `on_throttle`, `f` should return quickly and calling this again replaces it.
+    ///
+    pub fn on_progress<F>(&mut self, f: F) -> &mut Self
+        where F: Fn(&BatchProgress) + Send + Sync + 'static
+    {
+        self.on_progress = Some(Arc::new(f));
+        self
+    }
+
+    /// Keep the `n` slowest calls of this batch (10 by default) in its report, see
+    /// `BatchReport::slowest`, e.g. to find the datasets whose histories are worth fetching in
+    /// chunks. Only those `n` calls are kept as the batch runs, however many it makes.
+    ///
+    pub fn slow_queries(&mut self, n: usize) -> &mut Self {
+        self.slow_queries = n;
+        self
+    }
+
+    /// Record the queries of this batch which succeed in the manifest file at `path`, and skip
+    /// those already recorded there, e.g. by a previous run which was interrupted.
+    ///
+    /// Queries are identified by their URL, API key excluded. Skipped queries are not sent and
+    /// yield `Error::Skipped` in place of their result, so the results still line up with the
+    /// queries. Failed queries are not recorded, and are thus sent again by the next run.
+    ///
+    /// The manifest is a JSON Lines file, created if needed, to which an entry is appended (and
+    /// flushed) as soon as a query succeeds, along with when it did as an RFC 3339 UTC timestamp:
+    /// a crash loses at most the entry being written, whose query is then simply sent again. If
+    /// the manifest cannot be read or opened, every query of the batch yields the corresponding
+    /// `Error::IoError` without being sent.
+    ///
+    pub fn manifest<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
+        self.manifest = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Give up on the batch once `token` is cancelled, e.g. when its results are no longer
+    /// wanted: the calls in flight are abandoned, and neither they nor the queries left (those
+    /// waiting for a rate limit included) are sent, each yielding `Error::Cancelled` in place of
+    /// its result instead. The results thus still line up with the queries, and are all yielded
+    /// promptly.
+    ///
+    /// Only calls which were sent count in the report, though every cancelled query is counted
+    /// among its errors. See `CancelToken` for the other operations taking a token.
+    ///
+    pub fn cancel_token(&mut self, token: &CancelToken) -> &mut Self {
+        self.cancel = Some(token.clone());
+        self
+    }
+
+    /// Stop sending the queries of an API key once the calls left to it are down to `calls`,
+    /// rather than losing the rest of the day to `429 Too Many Requests` once its daily quota is
+    /// used up: the queries left with that key are not sent, each yielding an
+    /// `Error::QuotaExhausted` in place of its result instead.
+    ///
+    /// The calls left to a key are those reported by the `X-RateLimit-Remaining` header of the
+    /// last response to one of its calls or, until one reports them, those left by the calls made
+    /// within the longest of the key's limits (see `limit`, `key_profile` and `anonymous_limits`),
+    /// its `offset` included. Keys with neither have all of their queries sent. With
+    /// `concurrent_calls`, the calls in flight are not accounted for.
+    ///
+    /// The queries not sent are counted in the report's `skipped` and `quota_skipped`, along with
+    /// the earliest reset of their keys' quotas (told by the `X-RateLimit-Reset` header, or by
+    /// the calls made) as its `quota_resets_at`: the time to resume the batch, e.g. with the same
+    /// `manifest`.
+    ///
+    pub fn reserve_quota(&mut self, calls: usize) -> &mut Self {
+        self.reserve_quota = Some(calls);
+        self
+    }
+
+    /// Transform the result of each query which succeeds with `f`, given the code of the dataset
+    /// (or database) queried, in the worker thread which sent the query.
+    ///
+    /// This applies normalization steps (e.g. currency conversion or unit scaling) as the data
+    /// arrives, rather than in a second pass over all of it. The codes given to `f` have an empty
+    /// `name`, and an empty `dataset_code` for queries which do not target a dataset. Calling this
+    /// again chains the transformations.
+    ///
+    /// A panic in `f` does not take down the worker: the query it was given the result of yields
+    /// an `Error::TransformFailed` with the panic's message instead.
+    ///
+    pub fn map_rows<V, F>(self, f: F) -> BatchQuery<A, T, V>
+        where F: Fn(&Code, U) -> V + Send + Sync + 'static
+    {
+        let map = self.map.clone();
+        self.with_map(Arc::new(move |code, value| map(code, value).map(|x| f(code, x))))
+    }
+
+    /// Same as `map_rows` with a transformation which may fail, in which case the query yields the
+    /// error instead of a result.
+    ///
+    /// Since the transformation is part of the query, a query whose transformation fails is not
+    /// recorded in the manifest (see `manifest`) and is thus sent again by the next run. This
+    /// makes it the place to store the results as they arrive, e.g. to append them to a file: a
+    /// query is only recorded once its result is stored, whatever happens to the process after.
+    ///
+    pub fn try_map_rows<V, F>(self, f: F) -> BatchQuery<A, T, V>
+        where F: Fn(&Code, U) -> Result<V, Error> + Send + Sync + 'static
+    {
+        let map = self.map.clone();
+        self.with_map(Arc::new(move |code, value| map(code, value).and_then(|x| f(code, x))))
+    }
 
-        for _ in 0..self.threads {
-            jobs.push(vec![]);
+    /// This batch, its results transformed by `map` instead.
+    ///
+    fn with_map<V>(self, map: RowMap<T, V>) -> BatchQuery<A, T, V> {
+        BatchQuery {
+            offset: self.offset,
+            limits: self.limits,
+            queries: self.queries,
+            threads: self.threads,
+            concurrent_calls: self.concurrent_calls,
+            scheduling: self.scheduling,
+            manifest: self.manifest,
+            anonymous: self.anonymous,
+            anonymous_limits: self.anonymous_limits,
+            on_throttle: self.on_throttle,
+            on_progress: self.on_progress,
+            slow_queries: self.slow_queries,
+            key_profiles: self.key_profiles,
+            set_key: self.set_key,
+            on_start: self.on_start,
+            cancel: self.cancel,
+            reserve_quota: self.reserve_quota,
+            map,
         }
+    }
+
+    /// Call `f` with the index of each query (among those sent, in the order they were added)
+    /// right before it is sent, once its rate limits allow it, from the worker's thread.
+    ///
+    /// The query is not sent if `f` fails, its result being the error instead. This lets the
+    /// download queue mark its items as in progress before anything is sent for them.
+    ///
+    pub(crate) fn on_start<F>(&mut self, f: F) -> &mut Self
+        where F: Fn(usize) -> Result<(), Error> + Send + Sync + 'static
+    {
+        self.on_start = Some(Arc::new(f));
+        self
+    }
+
+    /// Estimate the volume of data this batch would download, from the sizes advertised by Quandl
+    /// (see `ApiCall::content_length`).
+    ///
+    /// This makes one HEAD request per query, in turn and without rate limiting, so it is meant to
+    /// be used on samples of large batches. As when running the batch, queries without an API key
+    /// are skipped unless the batch is `anonymous`. Queries whose size cannot be told (the server
+    /// does not advertise it, rejects the request or cannot be reached) are counted as unknown.
+    ///
+    pub fn estimate_bytes(&self) -> ByteEstimate {
+        let queries = {
+            self.queries.iter()
+                .filter(|query| {
+                    Has::<ApiArguments>::get_ref(*query).api_key.is_some() || self.anonymous
+                })
+        };
+
+        probe(queries).0
+    }
+
+    /// Price this batch without sending anything: the queries it would send and with which keys,
+    /// and how long it would take with various numbers of threads, waits on rate limits included.
+    ///
+    /// The keys are those the queries would be sent with, pool keys included (see
+    /// `key_profile`), and the queries recorded in the manifest (see `manifest`), if it can be
+    /// read, are counted as skipped. The durations come from replaying the batch against the
+    /// limits it is subject to, `offset` included, with the same `RateLimiter` and scheduling as
+    /// when running it, assuming every call takes `BatchPlan::call_duration` (1 second, use
+    /// `plan_with_probe` to measure it instead). They are estimates for 1, 2, 4, ... threads up
+    /// to those of the batch (see `threads`).
+    ///
+    pub fn plan(&self) -> BatchPlan {
+        self.plan_with(false)
+    }
+
+    /// Same as `plan`, also probing the size of the queries to send like `estimate_bytes` does,
+    /// with one HEAD request each, in turn and without rate limiting. The average time those
+    /// requests took is the duration assumed for the calls, unless none of them succeeded.
+    ///
+    pub fn plan_with_probe(&self) -> BatchPlan {
+        self.plan_with(true)
+    }
+
+    fn plan_with(&self, probing: bool) -> BatchPlan {
+        let mut queries = self.queries.clone();
+        self.assign_pool_keys(&mut queries);
+
+        let completed = {
+            self.manifest.as_ref().map(|path| Manifest::completed(path)).unwrap_or_default()
+        };
+
+        let mut plan = {
+            BatchPlan {
+                call_duration: ASSUMED_CALL_DURATION,
+                ..BatchPlan::default()
+            }
+        };
+
+        // The queries to send, along with the key each one would be sent with.
+        let anonymous = ApiKey::new(ANONYMOUS);
+        let mut sent: Vec<(&A, &ApiKey)> = vec![];
+
+        for query in queries.iter() {
+            let key = {
+                match Has::<ApiArguments>::get_ref(query).api_key {
+                    Some(ref key) => key,
+                    None if self.anonymous => &anonymous,
+
+                    None => {
+                        plan.keyless += 1;
+                        continue;
+                    },
+                }
+            };
+
+            plan.queries += 1;
 
-        for (index, api_call) in self.queries.iter().enumerate() {
-            jobs[index % self.threads].push(api_call.clone());
+            if canonical_url(query).map(|url| completed.contains(&url)).unwrap_or(false) {
+                plan.skipped += 1;
+                continue;
+            }
+
+            *plan.keys.entry(masked_key(key)).or_default() += 1;
+            sent.push((query, key));
         }
 
-        let mut iterator = {
-            Iterator {
-                index: 0,
-                channels: vec![],
+        let keys: HashSet<&ApiKey> = sent.iter().map(|&(_, key)| key).collect();
+        let (threads, thread_limit) = self.thread_count(keys.len(), sent.len());
+        plan.threads = threads;
+        plan.thread_limit = Some(thread_limit);
+
+        if probing {
+            let (bytes, latency) = probe(sent.iter().map(|&(query, _)| query));
+            plan.bytes = Some(bytes);
+
+            if let Some(latency) = latency {
+                plan.call_duration = latency;
+                plan.call_duration_measured = true;
             }
+        }
+
+        let mut threads: Vec<usize> = {
+            ::std::iter::successors(Some(1), |n: &usize| n.checked_mul(2))
+                .take_while(|&n| n < plan.threads)
+                .collect()
         };
 
-        let batch_query = Arc::new(self);
+        threads.push(plan.threads);
 
-        for api_queries in jobs {
-            if !api_queries.is_empty() {
-                let keys = keys.clone();
-                let (tx, rx) = channel();
+        plan.estimates = {
+            threads.into_iter()
+                .map(|threads| self.simulate(&sent, threads, plan.call_duration))
+                .collect()
+        };
 
-                iterator.channels.push(rx);
+        plan
+    }
 
-                let batch_query = batch_query.clone();
+    /// Replay the sending of `queries` (with their keys) by `threads` workers, each call taking
+    /// `call_duration` and waiting on the rate limits of its key as when running the batch.
+    ///
+    fn simulate(&self, queries: &[(&A, &ApiKey)], threads: usize, call_duration: Duration)
+        -> ThreadEstimate
+    {
+        let start = Instant::now();
+        let mut estimate = ThreadEstimate { threads, ..ThreadEstimate::default() };
 
-                spawn(move || {
-                    for api_call in api_queries {
-                        if let Some(ref key) = Has::<ApiArguments>::get_ref(&api_call).api_key {
-                            if batch_query.concurrent_calls {
-                                {
-                                    let keys = keys.read().unwrap();
+        // With work stealing, the workers all take from the first (and only) list.
+        let stealing = matches!(self.scheduling, SchedulingStrategy::WorkStealing);
+        let mut jobs: Vec<::std::collections::VecDeque<usize>> = vec![Default::default(); threads];
 
-                                    let mut calls = {
-                                        keys.get(&key[..]).expect("Key not found")
-                                            .lock().expect("Poisoned Mutex")
-                                    };
+        for (index, &(query, _)) in queries.iter().enumerate() {
+            let worker = {
+                match self.scheduling {
+                    SchedulingStrategy::WorkStealing => 0,
 
-                                    for &(limit, ref duration) in batch_query.limits.iter() {
-                                        if *calls != 0 && *calls % limit == 0 {
-                                            ::std::thread::sleep(duration.clone());
-                                        }
-                                    }
+                    SchedulingStrategy::Custom(assign) => {
+                        let worker = assign(query, threads);
+                        assert!(worker < threads, "worker: {}, threads: {}", worker, threads);
+                        worker
+                    },
 
-                                    *calls += 1;
-                                }
+                    SchedulingStrategy::RoundRobinStatic => index % threads,
+                }
+            };
 
-                                if let Err(_) = tx.send(api_call.send()) {
-                                    panic!("Thread's communication channel closed prematurely.");
-                                }
-                            } else {
-                                let keys = keys.read().unwrap();
+            jobs[worker].push_back(index);
+        }
 
-                                let mut calls = {
-                                    keys.get(&key[..]).expect("Key not found")
-                                        .lock().expect("Poisoned Mutex")
-                                };
+        let mut idle = vec![start; threads];
+        let mut key_idle: HashMap<&ApiKey, Instant> = HashMap::new();
+        let mut limiters: HashMap<&ApiKey, RateLimiter> = HashMap::new();
+        let mut end = start;
 
-                                for &(limit, ref duration) in batch_query.limits.iter() {
-                                    if *calls != 0 && *calls % limit == 0 {
-                                        ::std::thread::sleep(duration.clone());
-                                    }
-                                }
+        loop {
+            // The worker done with its previous call first is the next one to make a call.
+            let worker = {
+                (0..threads)
+                    .filter(|&worker| !jobs[if stealing { 0 } else { worker }].is_empty())
+                    .min_by_key(|&worker| (idle[worker], worker))
+            };
 
-                                *calls += 1;
+            let worker = {
+                match worker {
+                    Some(worker) => worker,
+                    None => break,
+                }
+            };
 
-                                if let Err(_) = tx.send(api_call.send()) {
-                                    panic!("Thread's communication channel closed prematurely.");
-                                }
-                            }
-                        }
-                    }
-                });
+            let index = {
+                match jobs[if stealing { 0 } else { worker }].pop_front() {
+                    Some(index) => index,
+                    None => break,
+                }
+            };
+
+            let key = queries[index].1;
+            let mut at = idle[worker];
+
+            if !self.concurrent_calls {
+                at = at.max(key_idle.get(key).cloned().unwrap_or(start));
+            }
+
+            let limiter = limiters.entry(key).or_insert_with(|| {
+                let mut limiter = RateLimiter::new(self.key_limits(key));
+
+                if key.expose_secret() != ANONYMOUS {
+                    limiter.record(key.expose_secret(), self.offset, start);
+                }
+
+                limiter
+            });
+
+            let wait = limiter.wait_time(key.expose_secret(), at);
+
+            if wait > Duration::from_secs(0) {
+                estimate.throttles += 1;
+                estimate.waited += wait;
+                at += wait;
             }
+
+            limiter.record(key.expose_secret(), 1, at);
+
+            idle[worker] = at + call_duration;
+            key_idle.insert(key, at + call_duration);
+            end = end.max(at + call_duration);
         }
 
-        iterator
+        estimate.duration = end - start;
+        estimate
     }
-}
 
-/// Iterator returned by the `BatchQuery::run` method.
-///
-/// See the `BatchQuery` struct documentation for more information.
-///
-pub struct Iterator<T> {
-    index: usize,
-    channels: Vec<Receiver<T>>,
-}
+    /// Number of worker threads to send `queries` queries with `keys` distinct keys (the anonymous
+    /// calls counting as one), and what decided it, see `threads`.
+    ///
+    fn thread_count(&self, keys: usize, queries: usize) -> (usize, ThreadLimit) {
+        if Config::current().seed.is_some() {
+            return (1, ThreadLimit::Deterministic);
+        }
 
-impl<T: Sync + Send + 'static> Iterator<T> {
-    /// Check if the next `Result` value is ready in a non blocking way.
+        match self.threads {
+            Some(threads) => (threads, ThreadLimit::Explicit),
+            None => default_threads(::num_cpus::get(), keys, queries, self.concurrent_calls),
+        }
+    }
+
+    /// Limits the calls made with `key` are subject to, as `(calls, window)`.
     ///
-    /// If the value is not yet avaiable, `Some(None)` is returned. If the iterator is over, `None`
-    /// is returned. Otherwise, `Some(Result)` is to be expected.
+    fn key_limits(&self, key: &ApiKey) -> Vec<(usize, Duration)> {
+        if key.expose_secret() == ANONYMOUS {
+            return self.anonymous_limits.clone();
+        }
+
+        self.key_profiles.iter()
+            .find(|profile| profile.key == *key && !profile.limits.is_empty())
+            .map(|profile| durations(&profile.limits))
+            .unwrap_or_else(|| self.limits.clone())
+    }
+
+    /// Execute the batch query and return an iterator which asynchronously fetch the data.
     ///
-    /// Note that the implementation of the `Iterator` trait is done by calling this function in
-    /// the `next` implementation and yielding whether this function returns `Some(None)`.
+    pub fn run(self) -> Iterator<Result<U, crate::Error>> {
+        self.run_with_report().0
+    }
+
+    /// Execute the batch query like `run`, also returning a handle to the statistics of the calls
+    /// made, aggregated by database.
     ///
-    pub fn try_next(&mut self) -> Option<Option<T>> {
-        loop {
-            if self.channels.is_empty() {
-                return None;
-            } else {
-                match self.channels[self.index].try_recv() {
-                    Ok(item) => {
-                        self.index = (self.index + 1) % self.channels.len();
-                        return Some(Some(item));
-                    },
+    /// The same report is available from the iterator itself through `Iterator::report`.
+    ///
+    pub fn run_with_report(mut self) -> (Iterator<Result<U, crate::Error>>, ReportHandle) {
+        // In the reproducibility mode, a single worker makes the calls in order.
+        if Config::current().seed.is_some() {
+            self.scheduling = SchedulingStrategy::RoundRobinStatic;
+        }
 
-                    Err(TryRecvError::Disconnected) => {
-                        self.channels.truncate(self.index);
+        let now = Instant::now();
+        let started_at = Utc::now();
 
-                        if self.channels.is_empty() {
-                            return None;
-                        } else {
-                            self.index = 0;
-                        }
-                    },
+        let mut all = ::std::mem::take(&mut self.queries);
+        self.assign_pool_keys(&mut all);
+        self.queries = all;
+
+        let mut limiter = RateLimiter::new(self.limits.clone());
+        let mut keys = HashMap::<ApiKey, Mutex<()>>::new();
+
+        // The keys of the pool with limits of their own are rate limited apart from the others.
+        let mut pool_limiters: HashMap<ApiKey, RateLimiter> = {
+            self.key_profiles.iter()
+                .filter(|profile| !profile.limits.is_empty())
+                .map(|profile| (profile.key.clone(), RateLimiter::new(durations(&profile.limits))))
+                .collect()
+        };
+
+        let manifest = self.manifest.as_ref().map(|path| Manifest::open(path).map(Arc::new));
 
-                    Err(TryRecvError::Empty) => return Some(None),
+        let keyless = {
+            self.queries.iter()
+                .filter(|query| Has::<ApiArguments>::get_ref(*query).api_key.is_none())
+                .count()
+        };
+
+        if keyless > 0 && self.anonymous {
+            log::warn!("{} queries of the batch have no API key and are sent anonymously, limited \
+                        to {:?}: Quandl bans IP addresses exceeding its anonymous limits.",
+                       keyless,
+                       self.anonymous_limits);
+        } else if keyless > 0 {
+            log::warn!("{} queries of the batch have no API key and are skipped, see \
+                        `BatchQuery::anonymous`.",
+                       keyless);
+        }
+
+        // Queries without an API key are skipped unless anonymous; the others are numbered in
+        // order so the iterator can yield their results in that order whatever the scheduling.
+        // Those already resolved (i.e. in the manifest) are not scheduled at all.
+        let anonymous = ApiKey::new(ANONYMOUS);
+        let mut queries: Vec<(usize, A)> = vec![];
+        let mut resolved = HashMap::new();
+        let mut index = 0;
+
+        for query in self.queries.iter() {
+            let key = {
+                match Has::<ApiArguments>::get_ref(query).api_key {
+                    Some(ref key) => key,
+                    None if self.anonymous => &anonymous,
+                    None => continue,
                 }
+            };
+
+            match manifest {
+                Some(Err(ref e)) => { resolved.insert(index, Err(e.clone())); },
+
+                Some(Ok(ref manifest)) if manifest.contains(query) => {
+                    let url = canonical_url(query).unwrap_or_default();
+                    resolved.insert(index, Err(Error::Skipped(url)));
+                },
+
+                _ => {
+                    if !keys.contains_key(key) {
+                        keys.insert(key.clone(), Mutex::new(()));
+
+                        if key != &anonymous {
+                            let limiter = pool_limiters.get_mut(key).unwrap_or(&mut limiter);
+                            limiter.record(key.expose_secret(), self.offset, now);
+                        }
+                    }
+
+                    queries.push((index, query.clone()));
+                },
             }
+
+            index += 1;
         }
-    }
-}
 
-impl<T: Sync + Send + 'static> ::std::iter::Iterator for Iterator<T> {
-    type Item = T;
+        let manifest = manifest.and_then(|manifest| manifest.ok());
 
-    fn next(&mut self) -> Option<Self::Item> {
-        loop {
-            match self.try_next() {
-                Some(Some(item)) => return Some(item),
-                Some(None) => ::std::thread::yield_now(),
-                None => return None,
+        // Results known without sending anything are accounted for upfront.
+        let (threads, thread_limit) = self.thread_count(keys.len(), queries.len());
+
+        let mut initial = {
+            BatchReport {
+                queries: index,
+                threads,
+                thread_limit: Some(thread_limit),
+                started_at: Some(started_at),
+                ..BatchReport::default()
+            }
+        };
+
+        for result in resolved.values() {
+            match *result {
+                Err(Error::Skipped(_)) => initial.skipped += 1,
+                Err(ref e) => initial.record_error(e),
+                Ok(_) => (),
+            }
+        }
+
+        let (total, skipped) = (queries.len(), initial.skipped);
+
+        let keys = Arc::new(keys);
+        let limiter = Arc::new(Mutex::new(limiter));
+        let pool_limiters: Arc<HashMap<ApiKey, Mutex<RateLimiter>>> = {
+            Arc::new(pool_limiters.into_iter().map(|(key, x)| (key, Mutex::new(x))).collect())
+        };
+        let anonymous_limiter = {
+            Arc::new(Mutex::new(RateLimiter::new(self.anonymous_limits.clone())))
+        };
+        let quotas = Arc::new(Quotas::default());
+
+        let jobs: Vec<Jobs<A>> = {
+            match self.scheduling {
+                SchedulingStrategy::WorkStealing => {
+                    let workers = threads.min(queries.len());
+                    let queries = Arc::new(Mutex::new(queries.into_iter()));
+
+                    (0..workers).map(|_| Jobs::Shared(queries.clone())).collect()
+                },
+
+                ref strategy => {
+                    let mut jobs: Vec<Vec<(usize, A)>> = {
+                        (0..threads).map(|_| vec![]).collect()
+                    };
+
+                    for (index, query) in queries {
+                        let worker = {
+                            match *strategy {
+                                SchedulingStrategy::Custom(assign) => {
+                                    let worker = assign(&query, threads);
+
+                                    assert!(worker < threads,
+                                            "worker: {}, threads: {}", worker, threads);
+
+                                    worker
+                                },
+
+                                _ => index % threads,
+                            }
+                        };
+
+                        jobs[worker].push((index, query));
+                    }
+
+                    jobs.into_iter()
+                        .filter(|jobs| !jobs.is_empty())
+                        .map(|jobs| Jobs::Static(jobs.into_iter()))
+                        .collect()
+                },
+            }
+        };
+
+        let report = ReportHandle { report: Arc::new(Mutex::new(initial)) };
+        let (tx, rx) = channel();
+
+        let iterator = {
+            Iterator {
+                index: 0,
+                pending: resolved,
+                receiver: rx,
+                report: report.clone(),
             }
+        };
+
+        let concurrent_calls = self.concurrent_calls;
+        let slow_queries = self.slow_queries;
+        let reserve_quota = self.reserve_quota;
+
+        // Calls made and failed so far, by every worker.
+        let progress = Arc::new(Mutex::new((0, 0)));
+
+        for (worker, mut jobs) in jobs.into_iter().enumerate() {
+            let keys = keys.clone();
+            let limiter = limiter.clone();
+            let pool_limiters = pool_limiters.clone();
+            let anonymous_limiter = anonymous_limiter.clone();
+            let quotas = quotas.clone();
+            let anonymous = anonymous.clone();
+            let report = report.clone();
+            let manifest = manifest.clone();
+            let map = self.map.clone();
+            let on_throttle = self.on_throttle.clone();
+            let on_start = self.on_start.clone();
+            let on_progress = self.on_progress.clone();
+            let progress = progress.clone();
+            let cancel = self.cancel.clone();
+            let tx = tx.clone();
+
+            let body = move || {
+                let _scope = cancel.as_ref().map(CancelToken::enter);
+                let mut local = BatchReport::default();
+                let mut slow_log = SlowLog::new(slow_queries);
+
+                while let Some((index, api_call)) = jobs.next() {
+                    // A panic only fails the query it happened on, the worker going on with the
+                    // others.
+                    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                        // Without waiting for the key to be free.
+                        if let Err(e) = crate::cancel::check() {
+                            local.record_error(&e);
+                            return Err(e);
+                        }
+
+                        let (key, limiter) = {
+                            match Has::<ApiArguments>::get_ref(&api_call).api_key {
+                                Some(ref key) => {
+                                    (key, pool_limiters.get(key).unwrap_or(&*limiter))
+                                },
+
+                                None => (&anonymous, &*anonymous_limiter),
+                            }
+                        };
+
+                        // Unless concurrent calls are allowed, holding the key's lock for the
+                        // whole call ensures a single call is made with it at any time. The lock
+                        // guards no data, so a panic while it was held does not matter.
+                        let _guard = {
+                            if concurrent_calls {
+                                None
+                            } else {
+                                Some(keys[key].lock().unwrap_or_else(PoisonError::into_inner))
+                            }
+                        };
+
+                        // Under the key's lock, the quota is that left by its previous call.
+                        if let Some(reserved) = reserve_quota {
+                            match quotas.left(key, limiter) {
+                                Some((left, resets_at)) if left <= reserved => {
+                                    local.skipped += 1;
+                                    local.quota_skipped += 1;
+                                    local.quota_resets_at = earliest(local.quota_resets_at,
+                                                                     resets_at);
+
+                                    let url = canonical_url(&api_call).unwrap_or_default();
+                                    return Err(Error::QuotaExhausted(url));
+                                },
+
+                                _ => (),
+                            }
+                        }
+
+                        let secret = key.expose_secret();
+
+                        let on_wait = |waited, threshold_hit| {
+                            let event = {
+                                ThrottleEvent {
+                                    key_fingerprint: fingerprint(key),
+                                    waited,
+                                    threshold_hit,
+                                    at: now.elapsed(),
+                                    worker,
+                                }
+                            };
+
+                            if let Some(ref on_throttle) = on_throttle {
+                                on_throttle(&event);
+                            }
+
+                            local.throttles.push(event);
+                        };
+
+                        let acquired = rate_limit::acquire_cancellable(limiter, secret, on_wait);
+
+                        if let Err(e) = acquired {
+                            local.record_error(&e);
+                            return Err(e);
+                        }
+
+                        crate::download::take_received_bytes();
+                        crate::download::take_quota();
+                        let start = Instant::now();
+
+                        let result = {
+                            match on_start {
+                                Some(ref on_start) => {
+                                    on_start(index).and_then(|_| api_call.send())
+                                },
+
+                                None => api_call.send(),
+                            }
+                        };
+
+                        let duration = start.elapsed();
+                        quotas.update(key);
+
+                        let result = result.and_then(|value| {
+                            let code = Code {
+                                database_code: api_call.database_code().unwrap_or("").to_string(),
+                                dataset_code: api_call.dataset_code().unwrap_or("").to_string(),
+                                name: String::new(),
+                            };
+
+                            apply(&*map, &code, value)
+                        });
+
+                        let bytes = crate::download::take_received_bytes();
+
+                        let stats = {
+                            let code = api_call.database_code().unwrap_or("");
+                            local.databases.entry(code.to_string()).or_default()
+                        };
+
+                        stats.calls += 1;
+                        stats.bytes += bytes;
+                        stats.total_duration += duration;
+                        stats.errors += result.is_err() as usize;
+
+                        local.latency.record(duration);
+
+                        slow_log.log(SlowQuery {
+                            database_code: api_call.database_code().unwrap_or("").to_string(),
+                            dataset_code: api_call.dataset_code().unwrap_or("").to_string(),
+                            duration,
+                            bytes,
+                        });
+
+                        *local.keys.entry(masked_key(key)).or_default() += 1;
+
+                        if let Err(ref e) = result {
+                            local.record_error(e);
+                        }
+
+                        if let (Some(ref manifest), Ok(_)) = (&manifest, &result) {
+                            manifest.record(&api_call);
+                        }
+
+                        if let Some(ref on_progress) = on_progress {
+                            let (completed, failed) = {
+                                let mut progress = progress.lock().expect("Poisoned Mutex");
+                                progress.0 += 1;
+                                progress.1 += result.is_err() as usize;
+                                *progress
+                            };
+
+                            on_progress(&BatchProgress {
+                                completed,
+                                failed,
+                                total,
+                                skipped,
+                                elapsed: now.elapsed(),
+                                worker,
+                            });
+                        }
+
+                        result
+                    }));
+
+                    let result = result.unwrap_or_else(|payload| {
+                        let message = panic_message(payload);
+                        let error = Error::BatchInternal { worker, message };
+
+                        log::error!("query {} of the batch failed: {}", index, error);
+                        local.record_error(&error);
+                        Err(error)
+                    });
+
+                    if tx.send((index, result)).is_err() {
+                        panic!("Thread's communication channel closed prematurely.");
+                    }
+                }
+
+                // Merged before `tx` is dropped so the report is complete by the time the
+                // iterator is exhausted.
+                local.slowest = slow_log.into_sorted_vec();
+                report.merge(local, now.elapsed(), slow_queries);
+            };
+
+            thread::Builder::new()
+                .name(format!("quandl-batch-{}", worker))
+                .spawn(body)
+                .expect("failed to spawn a worker thread");
+        }
+
+        (iterator, report)
+    }
+
+    /// Execute the batch query like `run`, as an asynchronous stream of its results instead of an
+    /// iterator, waiting for the rate limits on tokio's timer rather than blocking a thread.
+    ///
+    /// The stream sends one query at a time, in order, each on tokio's blocking thread pool: the
+    /// limits, offset, anonymous queries and keys of the pool all apply as with `run`, but
+    /// `threads`, `scheduling`, `manifest`, `on_progress`, `cancel_token` and `reserve_quota` do
+    /// not. The waits can be received along with the results through `BatchStream::events`, and
+    /// are given to the `on_throttle` callback as well.
+    ///
+    /// The stream must be polled within a tokio runtime with its timer enabled.
+    ///
+    #[cfg(feature = "async")]
+    pub fn stream(mut self) -> BatchStream<U> {
+        let mut all = ::std::mem::take(&mut self.queries);
+        self.assign_pool_keys(&mut all);
+
+        // The batch's limiter comes first, then the anonymous one, then those of the pool.
+        let mut limiters = {
+            vec![RateLimiter::new(self.limits.clone()),
+                 RateLimiter::new(self.anonymous_limits.clone())]
+        };
+
+        let anonymous = ApiKey::new(ANONYMOUS);
+        let mut pool: HashMap<ApiKey, usize> = HashMap::new();
+
+        for profile in self.key_profiles.iter().filter(|profile| !profile.limits.is_empty()) {
+            pool.insert(profile.key.clone(), limiters.len());
+            limiters.push(RateLimiter::new(durations(&profile.limits)));
+        }
+
+        let mut seen = HashSet::new();
+        let mut calls = vec![];
+
+        for api_call in all {
+            let (key, limiter) = {
+                match Has::<ApiArguments>::get_ref(&api_call).api_key {
+                    Some(ref key) => (key.clone(), pool.get(key).cloned().unwrap_or(0)),
+                    None if self.anonymous => (anonymous.clone(), 1),
+                    None => continue,
+                }
+            };
+
+            if key != anonymous && seen.insert(key.clone()) {
+                let secret = key.expose_secret();
+                limiters[limiter].record(secret, self.offset, crate::batch_stream::now());
+            }
+
+            let map = self.map.clone();
+
+            let send = move || {
+                let value = api_call.send()?;
+
+                let code = Code {
+                    database_code: api_call.database_code().unwrap_or("").to_string(),
+                    dataset_code: api_call.dataset_code().unwrap_or("").to_string(),
+                    name: String::new(),
+                };
+
+                apply(&*map, &code, value)
+            };
+
+            calls.push(Call { key, limiter, send: Box::new(send) });
+        }
+
+        BatchStream::new(calls, limiters, self.on_throttle.clone())
+    }
+
+    /// Give the queries without an API key among `queries` one of the pool's, see `key_profile`.
+    ///
+    fn assign_pool_keys(&self, queries: &mut [A]) {
+        let set_key = {
+            match self.set_key {
+                Some(set_key) => set_key,
+                None => return,
+            }
+        };
+
+        let keyless: Vec<usize> = {
+            queries.iter().enumerate()
+                .filter(|(_, query)| Has::<ApiArguments>::get_ref(*query).api_key.is_none())
+                .map(|(index, _)| index)
+                .collect()
+        };
+
+        // Profiles without limits of their own are subject to those of the batch.
+        let limits: Vec<(usize, u64)> = {
+            self.limits.iter().map(|&(calls, window)| (calls, window.as_secs())).collect()
+        };
+
+        let profiles: Vec<KeyProfile> = {
+            self.key_profiles.iter().map(|profile| {
+                if profile.limits.is_empty() {
+                    KeyProfile { limits: limits.clone(), .. profile.clone() }
+                } else {
+                    profile.clone()
+                }
+            }).collect()
+        };
+
+        let kinds: Vec<_> = keyless.iter().map(|&index| queries[index].kind()).collect();
+
+        for (&index, key) in keyless.iter().zip(key_pool::assign_keys(&profiles, &kinds)) {
+            set_key(&mut queries[index], &profiles[key].key);
+        }
+    }
+}
+
+/// Size of `queries`, along with the average time taken by the HEAD requests which got a response
+/// (if any), see `BatchQuery::estimate_bytes`.
+///
+fn probe<'a, T, A, I>(queries: I) -> (ByteEstimate, Option<Duration>)
+    where T: DeserializeOwned + Clone,
+          A: ApiCall<T> + 'a,
+          I: ::std::iter::Iterator<Item = &'a A>,
+{
+    let mut estimate = ByteEstimate::default();
+    let mut responses = 0;
+    let mut latency = Duration::from_secs(0);
+
+    for query in queries {
+        let start = Instant::now();
+        let result = query.content_length();
+
+        if result.is_ok() {
+            responses += 1;
+            latency += start.elapsed();
+        }
+
+        match result {
+            Ok(Some(bytes)) => {
+                estimate.bytes += bytes;
+                estimate.known += 1;
+            },
+
+            _ => estimate.unknown += 1,
+        }
+    }
+
+    (estimate, Some(responses).filter(|&n| n > 0).map(|n| latency / n))
+}
+
+/// `(calls, seconds)` limits as `(calls, window)`, see `BatchQuery::limit`.
+///
+fn durations(limits: &[(usize, u64)]) -> Vec<(usize, Duration)> {
+    limits.iter().map(|&(calls, seconds)| (calls, Duration::from_secs(seconds))).collect()
+}
+
+impl<A, T, U> BatchQuery<A, T, U>
+    where T: DeserializeOwned + Clone + Send + 'static,
+          A: ApiCall<T> + HasMut<ApiArguments> + Clone + Send + 'static,
+          U: Send + 'static,
+{
+    /// Add the key of `profile` to the pool of this batch, which the queries without an API key
+    /// of their own are sent with instead of being skipped (or sent anonymously).
+    ///
+    /// Each of those queries is given a key before the batch starts, by `assign_keys` from the
+    /// kind of the query (see `ApiCall::kind`): with one premium key and several free ones, the
+    /// data queries thus go to the premium key first while the light ones spread over the others.
+    /// The limits of the profile replace those set with `limit` for its key, which is subject to
+    /// the latter if the profile has none. Like any key, each one is only ever used by one call at
+    /// a time unless `concurrent_calls` is set.
+    ///
+    pub fn key_profile(&mut self, profile: KeyProfile) -> &mut Self {
+        assert!(profile.weight > 0.0 && profile.weight.is_finite(), "weight: {}", profile.weight);
+
+        for &(limit, timeout) in &profile.limits {
+            assert!(limit > 0 && timeout > 0, "limit: {}, timeout: {}", limit, timeout);
+        }
+
+        self.key_profiles.push(profile);
+        self.set_key = Some(|query, key| {
+            HasMut::<ApiArguments>::get_mut(query).api_key = Some(key.clone());
+        });
+
+        self
+    }
+}
+
+impl<A, T> Default for BatchQuery<A, T>
+    where T: DeserializeOwned + Clone + Send + 'static,
+          A: ApiCall<T> + Clone + Send + 'static,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Apply `map` to `value`, turning a panic into an error.
+///
+fn apply<T, U>(map: &(dyn Fn(&Code, T) -> Result<U, Error> + Send + Sync), code: &Code, value: T)
+    -> Result<U, Error>
+{
+    let result = panic::catch_unwind(AssertUnwindSafe(|| map(code, value)));
+
+    result.unwrap_or_else(|payload| Err(Error::TransformFailed(panic_message(payload))))
+}
+
+/// Message of the panic whose payload is `payload`.
+///
+fn panic_message(payload: Box<dyn ::std::any::Any + Send>) -> String {
+    payload.downcast_ref::<&str>().map(|x| x.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string())
+}
+
+/// Volume of data a batch query would download, see `BatchQuery::estimate_bytes`.
+///
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct ByteEstimate {
+    /// Sum of the sizes advertised for the `known` queries.
+    ///
+    pub bytes: u64,
+
+    /// Number of queries whose size is known.
+    ///
+    pub known: usize,
+
+    /// Number of queries whose size could not be told, and which `bytes` thus does not account
+    /// for.
+    ///
+    pub unknown: usize,
+}
+
+/// What running a batch query would take, see `BatchQuery::plan`.
+///
+/// Its `Display` implementation is a compact multi-line summary, e.g.
+///
+/// ```text
+/// queries: 2400 (2395 to send, 3 skipped, 2 without an API key)
+/// keys: ****abcd 1198, ****wxyz 1197
+/// calls: 1.00s each (assumed)
+/// 1 thread: 39m55s, throttled 0 times for 0.00s
+/// 2 threads: 19m58s, throttled 0 times for 0.00s
+/// size: 1.2 GB (2395 known, 0 unknown)
+/// ```
+///
+/// while it serializes (e.g. to JSON) field by field, for machine consumption.
+///
+#[derive(Debug, Clone, PartialEq, Default, Serialize)]
+pub struct BatchPlan {
+    /// Number of queries in the batch, queries without an API key excluded (as in a
+    /// `BatchReport`).
+    ///
+    pub queries: usize,
+
+    /// Number of queries which would be skipped since the batch's manifest shows they were
+    /// already completed.
+    ///
+    pub skipped: usize,
+
+    /// Number of queries which would be skipped for lack of an API key, see
+    /// `BatchQuery::anonymous`.
+    ///
+    pub keyless: usize,
+
+    /// Number of queries which would be sent with each API key, masked as in a `BatchReport`.
+    ///
+    pub keys: BTreeMap<String, usize>,
+
+    /// Number of worker threads the batch would run, see `BatchQuery::threads`.
+    ///
+    pub threads: usize,
+
+    /// What decided `threads`, only `None` for plans which are not of a batch query.
+    ///
+    pub thread_limit: Option<ThreadLimit>,
+
+    /// Duration of a call, on which the `estimates` are based.
+    ///
+    pub call_duration: Duration,
+
+    /// Whether `call_duration` was measured by probing the queries rather than assumed.
+    ///
+    pub call_duration_measured: bool,
+
+    /// Estimates by number of threads, in increasing order, the last one being for `threads`.
+    ///
+    pub estimates: Vec<ThreadEstimate>,
+
+    /// Volume of data the batch would download, when probed (see `BatchQuery::plan_with_probe`).
+    ///
+    pub bytes: Option<ByteEstimate>,
+}
+
+impl BatchPlan {
+    /// The estimate for the number of threads of the batch, only `None` for plans which are not
+    /// of a batch query, e.g. `BatchPlan::default()`.
+    ///
+    pub fn estimate(&self) -> Option<&ThreadEstimate> {
+        self.estimates.last()
+    }
+}
+
+impl ::std::fmt::Display for BatchPlan {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        let sent = self.queries - self.skipped;
+
+        write!(f, "queries: {} ({} to send, {} skipped", self.queries, sent, self.skipped)?;
+
+        if self.keyless > 0 {
+            write!(f, ", {} without an API key", self.keyless)?;
+        }
+
+        writeln!(f, ")")?;
+
+        if !self.keys.is_empty() {
+            writeln!(f, "keys: {}", counts(&self.keys))?;
+        }
+
+        if let Some(limit) = self.thread_limit {
+            writeln!(f, "threads: {} ({})", self.threads, limit)?;
+        }
+
+        writeln!(f, "calls: {:.2}s each ({})",
+                 self.call_duration.as_secs_f64(),
+                 if self.call_duration_measured { "measured" } else { "assumed" })?;
+
+        for estimate in &self.estimates {
+            writeln!(f, "{} thread{}: {}, throttled {} times for {:.2}s",
+                     estimate.threads,
+                     if estimate.threads == 1 { "" } else { "s" },
+                     wall_time(estimate.duration),
+                     estimate.throttles,
+                     estimate.waited.as_secs_f64())?;
+        }
+
+        if let Some(ref estimate) = self.bytes {
+            writeln!(f, "size: {} ({} known, {} unknown)",
+                     bytes(estimate.bytes as f64), estimate.known, estimate.unknown)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// What running a batch query would take with a given number of threads, see `BatchPlan`.
+///
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct ThreadEstimate {
+    /// Number of worker threads.
+    ///
+    pub threads: usize,
+
+    /// Wall time from the start of the batch to the completion of its last call.
+    ///
+    pub duration: Duration,
+
+    /// Number of waits imposed by the rate limits.
+    ///
+    pub throttles: usize,
+
+    /// Sum of those waits. Since the workers wait concurrently, this can exceed `duration`.
+    ///
+    pub waited: Duration,
+}
+
+/// Statistics of the calls made to a single database during a batch query.
+///
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct DatabaseStats {
+    /// Number of calls made.
+    ///
+    pub calls: usize,
+
+    /// Number of bytes received, error responses included.
+    ///
+    pub bytes: u64,
+
+    /// Sum of the time spent making the calls, waiting on rate limits excluded. Since calls are
+    /// made concurrently, this can exceed the wall time of the batch.
+    ///
+    pub total_duration: Duration,
+
+    /// Number of calls which returned an error.
+    ///
+    pub errors: usize,
+}
+
+impl DatabaseStats {
+    fn add(&mut self, other: &DatabaseStats) {
+        self.calls += other.calls;
+        self.bytes += other.bytes;
+        self.total_duration += other.total_duration;
+        self.errors += other.errors;
+    }
+}
+
+/// Progress of a batch query, as told to the callback given to `BatchQuery::on_progress` after
+/// each call.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BatchProgress {
+    /// Number of calls made so far, the last one included.
+    ///
+    pub completed: usize,
+
+    /// Number of those calls which failed.
+    ///
+    pub failed: usize,
+
+    /// Number of calls the batch makes in all: its queries, but those not sent because they have
+    /// no API key (see `BatchQuery::anonymous`) or because they were resolved upfront (`skipped`).
+    ///
+    pub total: usize,
+
+    /// Number of queries resolved without being sent, i.e. those already recorded in the manifest
+    /// (see `BatchQuery::manifest`), which are not counted in `total`.
+    ///
+    pub skipped: usize,
+
+    /// Time elapsed since the batch started running.
+    ///
+    pub elapsed: Duration,
+
+    /// Number of the worker which made the call, whose thread is named `quandl-batch-{worker}`.
+    ///
+    pub worker: usize,
+}
+
+/// A wait imposed by the rate limits of a batch query, before one of its calls.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ThrottleEvent {
+    /// Fingerprint of the API key whose limits were reached: the first 8 hexadecimal digits of a
+    /// hash of the key, which tells keys apart without revealing them, or `anonymous` for the
+    /// calls made without one.
+    ///
+    pub key_fingerprint: String,
+
+    /// How long the worker waited.
+    ///
+    pub waited: Duration,
+
+    /// The limit reached, as `(calls, window)`, see `BatchQuery::limit`.
+    ///
+    pub threshold_hit: (usize, Duration),
+
+    /// When the wait began, counting from the start of the batch.
+    ///
+    pub at: Duration,
+
+    /// Number of the worker which waited, whose thread is named `quandl-batch-{worker}`; always 0
+    /// for the single worker of a `BatchStream`.
+    ///
+    pub worker: usize,
+}
+
+/// Statistics of a batch query, see `BatchQuery::run_with_report`.
+///
+/// Its `Display` implementation is a compact multi-line summary of the run, e.g.
+///
+/// ```text
+/// queries: 12 (8 succeeded, 3 failed, 1 skipped)
+/// calls: 11 in 2.50s (4.40 calls/s)
+/// threads: 1 (one per API key)
+/// received: 1.2 MB (480.0 kB/s)
+/// errors: NotFound 2, SubscriptionRequired 1
+/// quandl errors: QECx02 2
+/// subscriptions required: EOD 1
+/// keys: ****wxyz 11
+/// throttled: 2 times for 0.40s
+/// latency: p50 200ms, p90 500ms, p99 1.84s, max 1.84s
+/// slowest: WIKI/AAPL 1.84s (640.2 kB), WIKI/MSFT 480ms (512.7 kB), EOD/IBM 350ms (3.1 kB)
+/// ```
+///
+/// while it serializes (e.g. to JSON) field by field, for machine consumption.
+///
+#[derive(Debug, Clone, PartialEq, Default, Serialize)]
+pub struct BatchReport {
+    /// Statistics by database code. Queries which do not target a specific database (i.e.
+    /// `DatabaseSearch`) are accounted for under the empty string.
+    ///
+    pub databases: HashMap<String, DatabaseStats>,
+
+    /// Number of queries in the batch, queries without an API key excluded.
+    ///
+    pub queries: usize,
+
+    /// Number of queries skipped, since the batch's manifest shows they were already completed
+    /// or the quota left to their API key was reserved (see `quota_skipped`).
+    ///
+    pub skipped: usize,
+
+    /// Number of the queries skipped because the quota left to their API key was down to the
+    /// calls reserved by `BatchQuery::reserve_quota`.
+    ///
+    pub quota_skipped: usize,
+
+    /// Earliest known reset of the quotas of the keys whose queries were skipped for them (see
+    /// `quota_skipped`), serialized as an RFC 3339 UTC timestamp: when the batch can be resumed.
+    /// `None` if no query was skipped so, or if none of their keys' resets is known.
+    ///
+    #[serde(serialize_with = "optional_timestamp")]
+    pub quota_resets_at: Option<DateTime<Utc>>,
+
+    /// Number of worker threads the batch ran, see `BatchQuery::threads`.
+    ///
+    pub threads: usize,
+
+    /// What decided `threads`, only `None` for reports which are not of a batch query.
+    ///
+    pub thread_limit: Option<ThreadLimit>,
+
+    /// Number of errors yielded by the batch, by `Error` variant (e.g. `"DownloadFailed"`).
+    ///
+    pub errors: BTreeMap<String, usize>,
+
+    /// Number of errors yielded by the batch with one of Quandl's error responses (see
+    /// `Error::api_error`), by Quandl error code.
+    ///
+    pub quandl_errors: BTreeMap<String, usize>,
+
+    /// Number of `Error::SubscriptionRequired` yielded by the batch, by database code, i.e. the
+    /// premium databases which the batch's keys are not subscribed to.
+    ///
+    pub subscriptions_required: BTreeMap<String, usize>,
+
+    /// Number of calls made with each API key. Keys are masked down to their last four characters
+    /// (or entirely, when they are too short for that to be safe), and calls made without one
+    /// are counted under `anonymous`.
+    ///
+    pub keys: BTreeMap<String, usize>,
+
+    /// Waits imposed by the rate limits, in the order they began.
+    ///
+    pub throttles: Vec<ThrottleEvent>,
+
+    /// Distribution of the durations of the calls, waiting on rate limits excluded.
+    ///
+    pub latency: LatencyHistogram,
+
+    /// The slowest calls, slowest first: as many as set with `BatchQuery::slow_queries`, or
+    /// fewer if the batch made fewer calls.
+    ///
+    pub slowest: Vec<SlowQuery>,
+
+    /// When the batch started running, serialized as an RFC 3339 UTC timestamp. This is only
+    /// `None` for reports which are not of a batch query, e.g. `BatchReport::default()`.
+    ///
+    #[serde(serialize_with = "optional_timestamp")]
+    pub started_at: Option<DateTime<Utc>>,
+
+    /// Wall time from the start of the batch to the completion of its last call.
+    ///
+    pub elapsed: Duration,
+}
+
+impl BatchReport {
+    /// Statistics over all databases.
+    ///
+    pub fn total(&self) -> DatabaseStats {
+        self.databases.values().fold(DatabaseStats::default(), |mut total, stats| {
+            total.add(stats);
+            total
+        })
+    }
+
+    fn record_error(&mut self, error: &Error) {
+        *self.errors.entry(variant(error).to_string()).or_default() += 1;
+
+        if let Some(response) = error.api_error() {
+            *self.quandl_errors.entry(response.quandl_error.code.clone()).or_default() += 1;
+        }
+
+        if let Error::SubscriptionRequired { ref database_code } = *error {
+            *self.subscriptions_required.entry(database_code.clone()).or_default() += 1;
+        }
+    }
+}
+
+impl ::std::fmt::Display for BatchReport {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        let total = self.total();
+        let failed: usize = self.errors.values().sum();
+        let seconds = self.elapsed.as_secs_f64();
+
+        // Rates over an unmeasurably short run are meaningless rather than infinite.
+        let rate = |x: f64| if seconds > 0.0 { x / seconds } else { 0.0 };
+
+        writeln!(f, "queries: {} ({} succeeded, {} failed, {} skipped)",
+                 self.queries, total.calls - total.errors, failed, self.skipped)?;
+
+        writeln!(f, "calls: {} in {:.2}s ({:.2} calls/s)",
+                 total.calls, seconds, rate(total.calls as f64))?;
+
+        if let Some(limit) = self.thread_limit {
+            writeln!(f, "threads: {} ({})", self.threads, limit)?;
+        }
+
+        writeln!(f, "received: {} ({}/s)",
+                 bytes(total.bytes as f64), bytes(rate(total.bytes as f64)))?;
+
+        if self.errors.is_empty() {
+            writeln!(f, "errors: none")?;
+        } else {
+            writeln!(f, "errors: {}", counts(&self.errors))?;
+        }
+
+        if !self.quandl_errors.is_empty() {
+            writeln!(f, "quandl errors: {}", counts(&self.quandl_errors))?;
+        }
+
+        if !self.subscriptions_required.is_empty() {
+            writeln!(f, "subscriptions required: {}", counts(&self.subscriptions_required))?;
+        }
+
+        if !self.keys.is_empty() {
+            writeln!(f, "keys: {}", counts(&self.keys))?;
+        }
+
+        if self.quota_skipped > 0 {
+            write!(f, "quota reserved: {} skipped", self.quota_skipped)?;
+
+            match self.quota_resets_at {
+                Some(ref at) => {
+                    writeln!(f, ", reset at {}", crate::calendar::format_timestamp(at))?
+                },
+
+                None => writeln!(f)?,
+            }
+        }
+
+        if !self.throttles.is_empty() {
+            let waited: Duration = self.throttles.iter().map(|x| x.waited).sum();
+
+            writeln!(f, "throttled: {} times for {:.2}s",
+                     self.throttles.len(), waited.as_secs_f64())?;
+        }
+
+        if let Some(max) = self.latency.max() {
+            let quantile = |q| latency(self.latency.quantile(q).unwrap_or_default());
+
+            writeln!(f, "latency: p50 {}, p90 {}, p99 {}, max {}",
+                     quantile(0.5), quantile(0.9), quantile(0.99), latency(max))?;
+        }
+
+        if !self.slowest.is_empty() {
+            let slowest: Vec<String> = {
+                self.slowest.iter().take(3).map(|query| {
+                    let code = {
+                        if query.dataset_code.is_empty() {
+                            query.database_code.clone()
+                        } else {
+                            format!("{}/{}", query.database_code, query.dataset_code)
+                        }
+                    };
+
+                    format!("{} {} ({})", code, latency(query.duration), bytes(query.bytes as f64))
+                }).collect()
+            };
+
+            writeln!(f, "slowest: {}", slowest.join(", "))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Name of the variant of `error`, by which errors are counted in a `BatchReport`.
+///
+fn variant(error: &Error) -> &'static str {
+    match *error {
+        Error::ApiCallFailed(_)     => "ApiCallFailed",
+        Error::DownloadFailed(_)    => "DownloadFailed",
+        Error::ParsingFailed(_)     => "ParsingFailed",
+        Error::JsonParsing { .. }   => "JsonParsing",
+        Error::CsvParsing { .. }    => "CsvParsing",
+        Error::ZipExtraction(_)     => "ZipExtraction",
+        Error::IoError(_)           => "IoError",
+        Error::Skipped(_)           => "Skipped",
+        Error::QuotaExhausted(_)    => "QuotaExhausted",
+        Error::ValidationFailed(_)  => "ValidationFailed",
+        Error::TransformFailed(_)   => "TransformFailed",
+        Error::BatchInternal { .. } => "BatchInternal",
+        Error::DeadlineExceeded     => "DeadlineExceeded",
+        Error::Cancelled            => "Cancelled",
+        Error::ResponseTooLarge { .. } => "ResponseTooLarge",
+        Error::UnzippedTooLarge { .. } => "UnzippedTooLarge",
+        Error::SubscriptionRequired { .. } => "SubscriptionRequired",
+        Error::RateLimitExceeded { .. } => "RateLimitExceeded",
+        Error::AccessDenied { .. }  => "AccessDenied",
+        Error::NotFound { .. }      => "NotFound",
+    }
+}
+
+/// Serialization of `BatchReport::started_at` and `BatchReport::quota_resets_at`.
+///
+fn optional_timestamp<S: Serializer>(timestamp: &Option<DateTime<Utc>>, serializer: S)
+    -> ::std::result::Result<S::Ok, S::Error>
+{
+    match timestamp {
+        Some(timestamp) => serializer.collect_str(&crate::calendar::format_timestamp(timestamp)),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// The earlier of `a` and `b`, those known.
+///
+fn earliest(a: Option<DateTime<Utc>>, b: Option<DateTime<Utc>>) -> Option<DateTime<Utc>> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// The quotas left to the keys of a batch, as last reported by the responses to their calls, see
+/// `BatchQuery::reserve_quota`.
+///
+#[derive(Default)]
+struct Quotas(Mutex<HashMap<ApiKey, Quota>>);
+
+/// Calls left to a key, and when they are reset if known.
+///
+type Quota = (usize, Option<DateTime<Utc>>);
+
+impl Quotas {
+    /// Record the quota reported by the response to the call just made with `key`, if any.
+    ///
+    fn update(&self, key: &ApiKey) {
+        if let Some(quota) = crate::download::take_quota() {
+            self.0.lock().expect("Poisoned Mutex").insert(key.clone(), quota);
+        }
+    }
+
+    /// The quota left to `key`, as last reported or else as told by the calls `limiter` recorded.
+    ///
+    fn left(&self, key: &ApiKey, limiter: &Mutex<RateLimiter>) -> Option<Quota> {
+        if let Some(&quota) = self.0.lock().expect("Poisoned Mutex").get(key) {
+            return Some(quota);
+        }
+
+        let limiter = limiter.lock().expect("Poisoned Mutex");
+        let status = limiter.status(key.expose_secret(), Instant::now())?;
+        let reset = chrono::Duration::from_std(status.reset).ok();
+
+        Some((status.remaining, reset.map(|reset| Utc::now() + reset)))
+    }
+}
+
+/// `key` as shown in a `BatchReport`, see `BatchReport::keys`.
+///
+fn masked_key(key: &ApiKey) -> String {
+    if key.expose_secret() == ANONYMOUS {
+        return "anonymous".to_string();
+    }
+
+    let chars: Vec<char> = key.expose_secret().chars().collect();
+
+    if chars.len() > 8 {
+        format!("****{}", chars[chars.len() - 4..].iter().collect::<String>())
+    } else {
+        "****".to_string()
+    }
+}
+
+/// `key` as identified in a `ThrottleEvent`, see `ThrottleEvent::key_fingerprint`.
+///
+/// The hash is FNV-1a, which unlike the hashers of the standard library is the same from one
+/// run (and build) to the next, so fingerprints can be compared across runs.
+///
+pub(crate) fn fingerprint(key: &ApiKey) -> String {
+    if key.expose_secret() == ANONYMOUS {
+        return "anonymous".to_string();
+    }
+
+    let hash = {
+        key.expose_secret().bytes().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+        })
+    };
+
+    format!("{:016x}", hash)[..8].to_string()
+}
+
+/// `"name count"` pairs, separated by commas.
+///
+fn counts(counts: &BTreeMap<String, usize>) -> String {
+    counts.iter().map(|(name, n)| format!("{} {}", name, n)).collect::<Vec<_>>().join(", ")
+}
+
+/// `duration` in hours, minutes and seconds, or just seconds under a minute.
+///
+fn wall_time(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+
+    if seconds < 60 {
+        format!("{:.2}s", duration.as_secs_f64())
+    } else if seconds < 3600 {
+        format!("{}m{:02}s", seconds / 60, seconds % 60)
+    } else {
+        format!("{}h{:02}m{:02}s", seconds / 3600, seconds / 60 % 60, seconds % 60)
+    }
+}
+
+/// `duration` in milliseconds, or in seconds from one second.
+///
+fn latency(duration: Duration) -> String {
+    if duration < Duration::from_secs(1) {
+        format!("{}ms", duration.as_millis())
+    } else {
+        format!("{:.2}s", duration.as_secs_f64())
+    }
+}
+
+/// Amount of bytes in (decimal) units of an appropriate size.
+///
+fn bytes(n: f64) -> String {
+    if n < 1e3 {
+        format!("{} B", n.round())
+    } else if n < 1e6 {
+        format!("{:.1} kB", n / 1e3)
+    } else if n < 1e9 {
+        format!("{:.1} MB", n / 1e6)
+    } else {
+        format!("{:.1} GB", n / 1e9)
+    }
+}
+
+/// Queries completed by the previous runs of a batch, see `BatchQuery::manifest`.
+///
+struct Manifest {
+    completed: HashSet<String>,
+    file: Mutex<File>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ManifestEntry {
+    url: String,
+
+    // When the query succeeded, in UTC. Missing from the entries of earlier versions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    completed_at: Option<String>,
+}
+
+impl Manifest {
+    fn open(path: &Path) -> Result<Self, Error> {
+        let io_error = |e: ::std::io::Error| {
+            Error::IoError(format!("cannot open manifest {}: {}", path.display(), e))
+        };
+
+        let file = OpenOptions::new().create(true).append(true).read(true).open(path);
+        let mut file = file.map_err(io_error)?;
+        let mut content = String::new();
+        file.read_to_string(&mut content).map_err(io_error)?;
+
+        // A line which does not parse was being written when a previous run died, in which case
+        // it is terminated so that the next entries are not appended to it.
+        let completed = completed_urls(&content);
+
+        if !content.is_empty() && !content.ends_with('\n') {
+            file.write_all(b"\n").map_err(io_error)?;
+        }
+
+        Ok(Manifest { completed, file: Mutex::new(file) })
+    }
+
+    /// URLs of the queries completed according to the manifest at `path`, without creating it
+    /// (unlike `open`). A manifest which cannot be read records none.
+    ///
+    fn completed(path: &Path) -> HashSet<String> {
+        ::std::fs::read_to_string(path).map(|content| completed_urls(&content)).unwrap_or_default()
+    }
+
+    fn contains<T, A>(&self, query: &A) -> bool
+        where T: DeserializeOwned + Clone,
+              A: ApiCall<T>,
+    {
+        canonical_url(query).map(|url| self.completed.contains(&url)).unwrap_or(false)
+    }
+
+    /// Append `query` to the manifest. Failures are ignored: the query is then sent again by the
+    /// next run.
+    ///
+    fn record<T, A>(&self, query: &A)
+        where T: DeserializeOwned + Clone,
+              A: ApiCall<T>,
+    {
+        let line = {
+            canonical_url(query)
+                .and_then(|url| {
+                    let completed_at = Some(crate::calendar::format_timestamp(&Utc::now()));
+                    serde_json::to_string(&ManifestEntry { url, completed_at }).ok()
+                })
+                .map(|line| format!("{}\n", line))
+        };
+
+        if let Some(line) = line {
+            let mut file = self.file.lock().expect("Poisoned Mutex");
+
+            // Written at once so entries appended concurrently are never interleaved.
+            let _ = file.write_all(line.as_bytes()).and_then(|_| file.flush());
+        }
+    }
+}
+
+/// URLs of the entries of a manifest, skipping the lines which do not parse.
+///
+fn completed_urls(content: &str) -> HashSet<String> {
+    content.lines()
+        .filter_map(|line| serde_json::from_str::<ManifestEntry>(line).ok())
+        .map(|entry| entry.url)
+        .collect()
+}
+
+/// URL identifying `query` in a manifest, i.e. without its API key.
+///
+fn canonical_url<T, A>(query: &A) -> Option<String>
+    where T: DeserializeOwned + Clone,
+          A: ApiCall<T>,
+{
+    query.parsed_url().ok().map(crate::download::without_api_key)
+}
+
+/// Shared handle to the report of a running batch query.
+///
+/// Each worker thread accumulates its own statistics and merges them into the report once it is
+/// done with its share of the batch, so the report is only complete once the batch's iterator is
+/// exhausted.
+///
+#[derive(Debug, Clone)]
+pub struct ReportHandle {
+    report: Arc<Mutex<BatchReport>>,
+}
+
+impl ReportHandle {
+    /// Snapshot of the report in its current state.
+    ///
+    pub fn get(&self) -> BatchReport {
+        self.report.lock().expect("Poisoned Mutex").clone()
+    }
+
+    fn merge(&self, other: BatchReport, elapsed: Duration, slow_queries: usize) {
+        let mut guard = self.report.lock().expect("Poisoned Mutex");
+        let report = &mut *guard;
+
+        for (code, stats) in other.databases {
+            report.databases.entry(code).or_default().add(&stats);
+        }
+
+        for (counts, other) in [(&mut report.errors, other.errors),
+                                (&mut report.quandl_errors, other.quandl_errors),
+                                (&mut report.subscriptions_required, other.subscriptions_required),
+                                (&mut report.keys, other.keys)]
+        {
+            for (name, n) in other {
+                *counts.entry(name).or_default() += n;
+            }
+        }
+
+        report.skipped += other.skipped;
+        report.quota_skipped += other.quota_skipped;
+        report.quota_resets_at = earliest(report.quota_resets_at, other.quota_resets_at);
+
+        report.throttles.extend(other.throttles);
+        report.throttles.sort_by_key(|x| x.at);
+
+        report.latency.add(&other.latency);
+
+        let mut slow_log = SlowLog::new(slow_queries);
+
+        for query in report.slowest.drain(..).chain(other.slowest) {
+            slow_log.log(query);
+        }
+
+        report.slowest = slow_log.into_sorted_vec();
+
+        report.elapsed = report.elapsed.max(elapsed);
+    }
+}
+
+/// Iterator returned by the `BatchQuery::run` method.
+///
+/// See the `BatchQuery` struct documentation for more information. The iterator is `Send` whenever
+/// its items are, which is the case of the results of every query of this crate.
+///
+pub struct Iterator<T> {
+    index: usize,
+    pending: HashMap<usize, T>,
+    receiver: Receiver<(usize, T)>,
+    report: ReportHandle,
+}
+
+impl<T> Iterator<T> {
+    /// Snapshot of the batch's report, complete once this iterator is exhausted.
+    ///
+    pub fn report(&self) -> BatchReport {
+        self.report.get()
+    }
+}
+
+impl<T> Iterator<T> {
+    /// Check if the next `Result` value is ready in a non blocking way.
+    ///
+    /// If the value is not yet avaiable, `Some(None)` is returned. If the iterator is over, `None`
+    /// is returned. Otherwise, `Some(Result)` is to be expected.
+    ///
+    /// Note that the implementation of the `Iterator` trait is done by calling this function in
+    /// the `next` implementation and yielding whether this function returns `Some(None)`.
+    ///
+    pub fn try_next(&mut self) -> Option<Option<T>> {
+        loop {
+            if let Some(item) = self.pending.remove(&self.index) {
+                self.index += 1;
+                return Some(Some(item));
+            }
+
+            // Results arrive in completion order; those ahead of `index` wait in `pending`.
+            match self.receiver.try_recv() {
+                Ok((index, item)) => { self.pending.insert(index, item); },
+                Err(TryRecvError::Empty) => return Some(None),
+                Err(TryRecvError::Disconnected) => return None,
+            }
+        }
+    }
+}
+
+impl<T> ::std::iter::Iterator for Iterator<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.try_next() {
+                Some(Some(item)) => return Some(item),
+                Some(None) => ::std::thread::yield_now(),
+                None => return None,
+            }
+        }
+    }
+}
+
+/// How the queries of a batch are assigned to its worker threads, see `BatchQuery::scheduling`.
+///
+/// Whatever the strategy, the results are yielded in the order the queries were added.
+///
+pub enum SchedulingStrategy<A> {
+    /// The i-th query (among those with an API key) is assigned to thread `i % threads` before the
+    /// batch starts. This is the default.
+    ///
+    RoundRobinStatic,
+
+    /// Every thread takes the next query not yet started as soon as it is done with its previous
+    /// one, which keeps all threads busy when some queries take much longer than others.
+    ///
+    WorkStealing,
+
+    /// Each query is assigned before the batch starts to the thread returned by the function,
+    /// given the query and the number of threads. The returned index must be lower than the
+    /// number of threads.
+    ///
+    Custom(fn(&A, usize) -> usize),
+}
+
+impl<A> Clone for SchedulingStrategy<A> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<A> Copy for SchedulingStrategy<A> {}
+
+impl<A> ::std::fmt::Debug for SchedulingStrategy<A> {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        match *self {
+            SchedulingStrategy::RoundRobinStatic => write!(f, "RoundRobinStatic"),
+            SchedulingStrategy::WorkStealing => write!(f, "WorkStealing"),
+            SchedulingStrategy::Custom(_) => write!(f, "Custom(..)"),
+        }
+    }
+}
+
+/// What decided the number of worker threads of a batch, see `BatchQuery::threads`.
+///
+/// It displays as a short explanation, e.g. `one per API key`, and serializes in snake case.
+///
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThreadLimit {
+    /// The number was set with `BatchQuery::threads`.
+    ///
+    Explicit,
+
+    /// One thread per logical core.
+    ///
+    Cpus,
+
+    /// One thread per API key (the anonymous calls counting as one key), the calls made with a
+    /// key being made one at a time, see `BatchQuery::concurrent_calls`.
+    ///
+    Keys,
+
+    /// One thread per query to send.
+    ///
+    Queries,
+
+    /// A single thread, in the reproducibility mode, see `Config::deterministic`.
+    ///
+    Deterministic,
+}
+
+impl ::std::fmt::Display for ThreadLimit {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        f.write_str(match *self {
+            ThreadLimit::Explicit => "set explicitly",
+            ThreadLimit::Cpus => "one per logical core",
+            ThreadLimit::Keys => "one per API key",
+            ThreadLimit::Queries => "one per query",
+            ThreadLimit::Deterministic => "reproducibility mode",
+        })
+    }
+}
+
+/// Default number of worker threads of a batch sending `queries` queries with `keys` distinct API
+/// keys on a machine with `cpus` logical cores, along with what decided it, see
+/// `BatchQuery::threads`.
+///
+/// This is the number of threads which can be busy at once: at most one per core, one per query
+/// and, unless `concurrent_calls` is set, one per key, since the calls made with a key are then
+/// made one at a time. Ties go to the queries, then to the keys, and there is always at least one
+/// thread.
+///
+/// ```rust
+/// use quandl_v3::prelude::*;
+///
+/// // A single key serializes the calls, so a second thread would only wait on the first.
+/// assert_eq!(default_threads(8, 1, 100, false), (1, ThreadLimit::Keys));
+/// assert_eq!(default_threads(8, 1, 100, true), (8, ThreadLimit::Cpus));
+/// ```
+///
+pub fn default_threads(cpus: usize, keys: usize, queries: usize, concurrent_calls: bool)
+    -> (usize, ThreadLimit)
+{
+    let keys = if concurrent_calls { usize::MAX } else { keys };
+
+    let bounds = {
+        [(queries, ThreadLimit::Queries), (keys, ThreadLimit::Keys), (cpus, ThreadLimit::Cpus)]
+    };
+
+    // The first of the smallest bounds is the one kept.
+    let (threads, limit) = {
+        bounds.iter().cloned().min_by_key(|&(bound, _)| bound).expect("there are bounds")
+    };
+
+    (threads.max(1), limit)
+}
+
+/// Queries to be sent by a single worker thread, with their index in the batch.
+///
+/// Shared queries are taken out of the list under a lock rather than cloned from a shared `Vec`,
+/// so they never need to be `Sync`.
+///
+enum Jobs<A> {
+    Static(::std::vec::IntoIter<(usize, A)>),
+    Shared(Arc<Mutex<::std::vec::IntoIter<(usize, A)>>>),
+}
+
+impl<A> Jobs<A> {
+    fn next(&mut self) -> Option<(usize, A)> {
+        match self {
+            Jobs::Static(jobs) => jobs.next(),
+            Jobs::Shared(jobs) => jobs.lock().expect("Poisoned Mutex").next(),
         }
     }
 }
@@ -0,0 +1,241 @@
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use futures_core::Stream;
+
+use crate::{ApiKey, Error};
+use crate::batch_query::{fingerprint, ThrottleEvent, ThrottleCallback};
+use crate::rate_limit::RateLimiter;
+
+/// Item of the stream returned by `BatchStream::events`.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub enum BatchEvent<U> {
+    /// The stream waits for the rate limits of a key before its next call, until `until` (on
+    /// tokio's clock, see `tokio::time::Instant::into_std`).
+    ///
+    /// This is yielded before waiting, so the wait can be shown as it happens (e.g. "waiting 9m40s
+    /// for key 1f3a5c7e") instead of the stream appearing stuck. A wait spanning several limits
+    /// may be announced more than once, each time with the limit then binding.
+    ///
+    Throttled {
+        /// Fingerprint of the key, see `ThrottleEvent::key_fingerprint`.
+        ///
+        key_fingerprint: String,
+
+        /// When the next call can be made.
+        ///
+        until: Instant,
+
+        /// The limit reached, as `(calls, window)`, see `BatchQuery::limit`.
+        ///
+        threshold_hit: (usize, Duration),
+    },
+
+    /// The result of the next query of the batch.
+    ///
+    Result(Result<U, Error>),
+}
+
+/// Asynchronous stream of the results of a batch, returned by `BatchQuery::stream`.
+///
+/// Its items are the results of the queries in the order they were added, like those of
+/// `BatchQuery::run`. The waits imposed by the rate limits happen on tokio's timer, without
+/// yielding anything: use `events` to be told of them.
+///
+pub struct BatchStream<U> {
+    events: BatchEvents<U>,
+}
+
+/// Asynchronous stream of the results of a batch interleaved with its waits, see
+/// `BatchStream::events`.
+///
+pub struct BatchEvents<U> {
+    state: Option<State<U>>,
+    next: Option<Advance<U>>,
+}
+
+/// Computation of the next event of a `BatchEvents`, see `advance`.
+///
+type Advance<U> = Pin<Box<dyn Future<Output = (State<U>, Option<BatchEvent<U>>)> + Send>>;
+
+/// A query of a batch, ready to be sent.
+///
+pub(crate) struct Call<U> {
+    /// Key the query is rate limited by.
+    ///
+    pub key: ApiKey,
+
+    /// Index of the limiter of the key, among those given to `BatchStream::new`.
+    ///
+    pub limiter: usize,
+
+    /// The (blocking) call, from sending the query to the result it yields.
+    ///
+    pub send: Box<dyn FnOnce() -> Result<U, Error> + Send>,
+}
+
+struct State<U> {
+    calls: VecDeque<Call<U>>,
+    limiters: Vec<RateLimiter>,
+    on_throttle: Option<ThrottleCallback>,
+    start: Instant,
+
+    /// Whether the wait for the next call has been announced and is to happen.
+    ///
+    announced: bool,
+}
+
+impl<U: Send + 'static> BatchStream<U> {
+    pub(crate) fn new(calls: Vec<Call<U>>,
+                      limiters: Vec<RateLimiter>,
+                      on_throttle: Option<ThrottleCallback>) -> Self
+    {
+        let state = {
+            State {
+                calls: calls.into_iter().collect(),
+                limiters,
+                on_throttle,
+                start: now(),
+                announced: false,
+            }
+        };
+
+        BatchStream { events: BatchEvents { state: Some(state), next: None } }
+    }
+
+    /// Yield each wait imposed by the rate limits as a `BatchEvent::Throttled` before it happens,
+    /// along with the results as `BatchEvent::Result`.
+    ///
+    pub fn events(self) -> BatchEvents<U> {
+        self.events
+    }
+}
+
+impl<U: Send + 'static> Stream for BatchStream<U> {
+    type Item = Result<U, Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let events = &mut self.get_mut().events;
+
+        loop {
+            match Pin::new(&mut *events).poll_next(cx) {
+                Poll::Ready(Some(BatchEvent::Throttled { .. })) => (),
+                Poll::Ready(Some(BatchEvent::Result(result))) => return Poll::Ready(Some(result)),
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl<U: Send + 'static> Stream for BatchEvents<U> {
+    type Item = BatchEvent<U>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        if this.next.is_none() {
+            match this.state.take() {
+                Some(state) => this.next = Some(Box::pin(advance(state))),
+                None => return Poll::Ready(None),
+            }
+        }
+
+        let next = this.next.as_mut().expect("the next event is being computed");
+
+        match next.as_mut().poll(cx) {
+            Poll::Ready((state, event)) => {
+                this.next = None;
+
+                if event.is_some() {
+                    this.state = Some(state);
+                }
+
+                Poll::Ready(event)
+            },
+
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// Current instant on tokio's clock, which tests may pause and advance.
+///
+pub(crate) fn now() -> Instant {
+    tokio::time::Instant::now().into_std()
+}
+
+/// Make the next call of `state`, or announce the wait it needs first, returning the state to
+/// carry on from along with the event (`None` once every call has been made).
+///
+async fn advance<U>(mut state: State<U>) -> (State<U>, Option<BatchEvent<U>>)
+    where U: Send + 'static
+{
+    loop {
+        let (key, limiter) = {
+            match state.calls.front() {
+                Some(call) => (call.key.clone(), call.limiter),
+                None => return (state, None),
+            }
+        };
+
+        let now = now();
+
+        match state.limiters[limiter].binding_limit(key.expose_secret(), now) {
+            Some((threshold_hit, wait)) if !state.announced => {
+                let event = {
+                    ThrottleEvent {
+                        key_fingerprint: fingerprint(&key),
+                        waited: wait,
+                        threshold_hit,
+                        at: now - state.start,
+                        worker: 0,
+                    }
+                };
+
+                if let Some(ref on_throttle) = state.on_throttle {
+                    on_throttle(&event);
+                }
+
+                state.announced = true;
+
+                let throttled = {
+                    BatchEvent::Throttled {
+                        key_fingerprint: event.key_fingerprint,
+                        until: now + wait,
+                        threshold_hit,
+                    }
+                };
+
+                return (state, Some(throttled));
+            },
+
+            // The wait is computed again since the announcement may have been consumed late.
+            Some((_, wait)) => {
+                tokio::time::delay_for(wait).await;
+                state.announced = false;
+            },
+
+            None => {
+                state.limiters[limiter].record(key.expose_secret(), 1, now);
+                state.announced = false;
+
+                let call = state.calls.pop_front().expect("the next call is known");
+
+                let result = {
+                    match tokio::task::spawn_blocking(call.send).await {
+                        Ok(result) => result,
+                        Err(e) if e.is_panic() => ::std::panic::resume_unwind(e.into_panic()),
+                        Err(e) => Err(Error::IoError(format!("the call was cancelled: {}", e))),
+                    }
+                };
+
+                return (state, Some(BatchEvent::Result(result)));
+            },
+        }
+    }
+}
@@ -0,0 +1,354 @@
+//! Reference command line client of this crate, built with `cargo build --features cli`.
+//!
+//! ```text
+//! quandl-fetch data WIKI/AAPL --start 2016-01-01 --rows 10 --format jsonl
+//! quandl-fetch codes WIKI
+//! quandl-fetch search --db WIKI oil
+//! ```
+//!
+//! The API key is read from `QUANDL_API_KEY`, and `--base-url` sends the queries to another
+//! server than Quandl's.
+
+extern crate csv;
+extern crate quandl_v3;
+extern crate serde_json;
+
+use std::env;
+use std::io::{self, Write};
+use std::process;
+
+use quandl_v3::Error;
+use quandl_v3::config::Config;
+use quandl_v3::export::write_jsonl;
+use quandl_v3::prelude::*;
+
+const USAGE: &str = "\
+usage: quandl-fetch [--base-url URL] <command> [options]
+
+commands:
+    data DATABASE/DATASET   rows of a dataset, as CSV (without header) or JSON Lines
+        --start YYYY-MM-DD  earliest date
+        --end YYYY-MM-DD    latest date
+        --rows N            number of rows
+        --order asc|desc    order of the rows, by date
+        --collapse F        frequency (none, daily, weekly, monthly, quarterly, annual)
+        --transform T       transformation (none, diff, rdiff, rdiff_from, cumul, normalize)
+        --column N          single column, besides the date
+        --format csv|jsonl  output format (csv by default)
+
+    codes DATABASE          codes and names of the datasets of a database
+
+    search [KEYWORDS...]    databases matching the keywords
+        --db DATABASE       datasets of this database instead
+        --page N            page of results
+        --per-page N        results per page
+
+The API key is read from the QUANDL_API_KEY environment variable.";
+
+/// Why the command failed: how it was invoked (exit status 2), or what it queried (status 1).
+///
+enum Failure {
+    Usage(String),
+    Query(Error),
+}
+
+impl From<Error> for Failure {
+    fn from(e: Error) -> Self {
+        Failure::Query(e)
+    }
+}
+
+impl From<io::Error> for Failure {
+    fn from(e: io::Error) -> Self {
+        Failure::Query(Error::IoError(e.to_string()))
+    }
+}
+
+impl From<csv::Error> for Failure {
+    fn from(e: csv::Error) -> Self {
+        Failure::Query(Error::IoError(e.to_string()))
+    }
+}
+
+type Outcome = ::std::result::Result<(), Failure>;
+
+fn usage<T, S: Into<String>>(message: S) -> ::std::result::Result<T, Failure> {
+    Err(Failure::Usage(message.into()))
+}
+
+/// The arguments of a command: `--name value` options, in order, and positional arguments.
+///
+struct Arguments {
+    options: Vec<(String, String)>,
+    positional: Vec<String>,
+}
+
+impl Arguments {
+    fn parse<I: Iterator<Item = String>>(mut args: I) -> ::std::result::Result<Self, Failure> {
+        let mut arguments = Arguments { options: vec![], positional: vec![] };
+
+        while let Some(arg) = args.next() {
+            match arg.strip_prefix("--") {
+                Some(name) => {
+                    match args.next() {
+                        Some(value) => arguments.options.push((name.to_string(), value)),
+                        None => return usage(format!("missing value for --{}", name)),
+                    }
+                },
+
+                None => arguments.positional.push(arg),
+            }
+        }
+
+        Ok(arguments)
+    }
+
+    /// Fail on any option of the command not among `known`.
+    ///
+    fn expect_options(&self, known: &[&str]) -> Outcome {
+        match self.options.iter().find(|(name, _)| !known.contains(&&name[..])) {
+            Some((name, _)) => usage(format!("unknown option --{}", name)),
+            None => Ok(()),
+        }
+    }
+
+    /// The last value given to the option `name`, if any.
+    ///
+    fn option(&self, name: &str) -> Option<&str> {
+        self.options.iter().rev().find(|(x, _)| x == name).map(|(_, value)| &value[..])
+    }
+
+    fn parsed<T, F>(&self, name: &str, parse: F) -> ::std::result::Result<Option<T>, Failure>
+        where F: FnOnce(&str) -> Option<T>
+    {
+        match self.option(name) {
+            Some(value) => {
+                match parse(value) {
+                    Some(parsed) => Ok(Some(parsed)),
+                    None => usage(format!("invalid value '{}' for --{}", value, name)),
+                }
+            },
+
+            None => Ok(None),
+        }
+    }
+
+    fn number(&self, name: &str) -> ::std::result::Result<Option<usize>, Failure> {
+        self.parsed(name, |value| value.parse().ok())
+    }
+
+    /// The only positional argument, described as `what`.
+    ///
+    fn single(&self, what: &str) -> ::std::result::Result<&str, Failure> {
+        match &self.positional[..] {
+            [value] => Ok(value),
+            [] => usage(format!("missing {}", what)),
+            _ => usage(format!("expected a single {}", what)),
+        }
+    }
+}
+
+fn data(arguments: &Arguments) -> Outcome {
+    arguments.expect_options(&[
+        "start", "end", "rows", "order", "collapse", "transform", "column", "format",
+    ])?;
+
+    let code = arguments.single("DATABASE/DATASET code")?;
+
+    let mut query = {
+        match code.split_once('/') {
+            Some((database_code, dataset_code)) => DataQuery::new(database_code, dataset_code),
+            None => return usage(format!("invalid dataset code '{}', expected DATABASE/DATASET",
+                                         code)),
+        }
+    };
+
+    if let Some(date) = arguments.option("start") {
+        if query.start_date_str(date).is_err() {
+            return usage(format!("invalid value '{}' for --start", date));
+        }
+    }
+
+    if let Some(date) = arguments.option("end") {
+        if query.end_date_str(date).is_err() {
+            return usage(format!("invalid value '{}' for --end", date));
+        }
+    }
+
+    if let Some(n) = arguments.number("rows")? {
+        query.rows(n);
+    }
+
+    if let Some(order) = arguments.parsed("order", Order::from_api_token)? {
+        query.order(order);
+    }
+
+    if let Some(collapse) = arguments.parsed("collapse", Frequency::from_api_token)? {
+        query.collapse(collapse);
+    }
+
+    if let Some(transform) = arguments.parsed("transform", Transform::from_api_token)? {
+        query.transform(transform);
+    }
+
+    if let Some(index) = arguments.number("column")? {
+        query.column_index(index);
+    }
+
+    query.validate().map_err(Error::ValidationFailed)?;
+
+    let jsonl = {
+        match arguments.option("format").unwrap_or("csv") {
+            "csv" => false,
+            "jsonl" => true,
+            other => return usage(format!("invalid value '{}' for --format", other)),
+        }
+    };
+
+    let csv_data = ApiCall::<Vec<Vec<String>>>::encoded_bytes(&query)?;
+    let stdout = io::stdout();
+
+    if jsonl {
+        let rows: Vec<Vec<serde_json::Value>> = {
+            query.decode::<Vec<String>>(&csv_data)?
+                .into_iter()
+                .map(|row| row.into_iter().map(json_field).collect())
+                .collect()
+        };
+
+        write_jsonl(&rows, stdout.lock())?;
+    } else {
+        stdout.lock().write_all(&csv_data)?;
+    }
+
+    Ok(())
+}
+
+/// A field of a row as JSON: a number if it is one, `null` if empty, a string otherwise.
+///
+fn json_field(field: String) -> serde_json::Value {
+    if field.is_empty() {
+        return serde_json::Value::Null;
+    }
+
+    match field.parse::<f64>().ok().and_then(serde_json::Number::from_f64) {
+        Some(number) => serde_json::Value::Number(number),
+        None => serde_json::Value::String(field),
+    }
+}
+
+fn codes(arguments: &Arguments) -> Outcome {
+    arguments.expect_options(&[])?;
+
+    let codes = CodeListQuery::new(arguments.single("database code")?).send()?;
+    let mut writer = csv::Writer::from_writer(io::stdout());
+
+    for code in &codes {
+        let dataset_code = format!("{}/{}", code.database_code, code.dataset_code);
+        writer.write_record([&dataset_code, &code.name])?;
+    }
+
+    Ok(writer.flush()?)
+}
+
+/// Set the keywords and paging of a search, the same way on either kind of query.
+///
+fn search_parameters<Q: SearchParameters>(query: &mut Q, arguments: &Arguments) -> Outcome {
+    if !arguments.positional.is_empty() {
+        query.query(&arguments.positional);
+    }
+
+    if let Some(n) = arguments.number("page")? {
+        query.page(n);
+    }
+
+    if let Some(n) = arguments.number("per-page")? {
+        query.per_page(n);
+    }
+
+    Ok(())
+}
+
+fn search(arguments: &Arguments) -> Outcome {
+    arguments.expect_options(&["db", "page", "per-page"])?;
+
+    let mut writer = csv::Writer::from_writer(io::stdout());
+
+    match arguments.option("db") {
+        Some(database_code) => {
+            let mut query = DatasetSearch::new(database_code);
+            search_parameters(&mut query, arguments)?;
+
+            for dataset in &query.send()?.datasets {
+                let code = format!("{}/{}", dataset.database_code, dataset.dataset_code);
+                writer.write_record([code, dataset.name.clone()])?;
+            }
+        },
+
+        None => {
+            let mut query = DatabaseSearch::new();
+            search_parameters(&mut query, arguments)?;
+
+            for database in &query.send()?.databases {
+                writer.write_record([&database.database_code, &database.name])?;
+            }
+        },
+    }
+
+    Ok(writer.flush()?)
+}
+
+fn run(args: Vec<String>) -> Outcome {
+    let mut arguments = Arguments::parse(args.into_iter())?;
+
+    let config = {
+        Config {
+            api_key: {
+                env::var("QUANDL_API_KEY").ok()
+                    .filter(|key| !key.trim().is_empty())
+                    .map(ApiKey::from)
+            },
+            base_url: arguments.option("base-url").map(|x| x.to_string()),
+            .. (*Config::current()).clone()
+        }
+    };
+
+    Config::set_global(config);
+    arguments.options.retain(|(name, _)| name != "base-url");
+
+    if arguments.positional.is_empty() {
+        return usage("missing command");
+    }
+
+    let command = arguments.positional.remove(0);
+
+    match &command[..] {
+        "data" => data(&arguments),
+        "codes" => codes(&arguments),
+        "search" => search(&arguments),
+        other => usage(format!("unknown command '{}'", other)),
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    if args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        println!("{}", USAGE);
+        return;
+    }
+
+    match run(args) {
+        Ok(()) => (),
+
+        Err(Failure::Usage(message)) => {
+            eprintln!("quandl-fetch: {}\n\n{}", message, USAGE);
+            process::exit(2);
+        },
+
+        Err(Failure::Query(e)) => {
+            eprintln!("quandl-fetch: {}", e);
+            process::exit(1);
+        },
+    }
+}
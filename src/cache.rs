@@ -0,0 +1,433 @@
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::{Result, Error};
+
+/// A cached response, holding what is needed to revalidate it with a conditional request.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CacheEntry {
+    /// `ETag` header of the response, if any.
+    ///
+    pub etag: Option<String>,
+
+    /// `Content-Type` header of the response, if any.
+    ///
+    pub content_type: Option<String>,
+
+    /// Body of the response.
+    ///
+    pub body: Vec<u8>,
+}
+
+/// Storage for cached responses, keyed by (typically) the request's URL.
+///
+/// Implementations decide how entries are persisted and when they are evicted; a `get` may thus
+/// miss an entry which was `put` earlier.
+///
+pub trait CachePolicy {
+    /// The entry stored under `key`, if still cached. A hit counts as an access of the entry.
+    ///
+    fn get(&self, key: &str) -> Result<Option<CacheEntry>>;
+
+    /// Store `entry` under `key`, replacing any previous entry and evicting other entries as
+    /// needed.
+    ///
+    fn put(&self, key: &str, entry: &CacheEntry) -> Result<()>;
+
+    /// Remove the entry stored under `key`, returning whether or not there was one.
+    ///
+    fn remove(&self, key: &str) -> Result<bool>;
+
+    /// Remove the entries which were not accessed within the last `age`, returning how many were
+    /// removed.
+    ///
+    fn purge_older_than(&self, age: Duration) -> Result<usize>;
+}
+
+/// Cache persisted in a directory, with optional limits on its total size and number of entries.
+///
+/// When a limit is exceeded the least recently used entries are evicted first. An entry bigger
+/// than `max_bytes` on its own is never stored.
+///
+/// Every entry is made of two files: its body and a small index file holding its key, headers,
+/// size and last access time (an RFC 3339 UTC timestamp, to the nanosecond). Files are always
+/// written under a temporary name and then renamed, so several processes may share the same
+/// directory: they never see a partially written file, and a crash at worst leaves temporary or
+/// unreferenced files behind, which are ignored and eventually deleted by `purge_older_than`.
+/// Index files which cannot be read are considered missing.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileCache {
+    directory: PathBuf,
+    max_bytes: Option<u64>,
+    max_entries: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Index {
+    key: String,
+    etag: Option<String>,
+    content_type: Option<String>,
+    body: String,
+    size: u64,
+
+    // Nanoseconds since the epoch, written as a timestamp.
+    #[serde(with = "access_time")]
+    accessed: u64,
+}
+
+const INDEX_EXTENSION: &str = "meta";
+const BODY_EXTENSION: &str = "body";
+const TEMPORARY_EXTENSION: &str = "tmp";
+
+impl FileCache {
+    /// Create a cache storing its entries in `directory`, which is created if necessary.
+    ///
+    /// Entries already present in the directory are kept. There are no limits by default.
+    ///
+    pub fn new<P: AsRef<Path>>(directory: P) -> Result<Self> {
+        fs::create_dir_all(directory.as_ref()).map_err(io_error)?;
+
+        Ok(FileCache {
+            directory: directory.as_ref().to_path_buf(),
+            max_bytes: None,
+            max_entries: None,
+        })
+    }
+
+    /// Limit the total size of the cached bodies.
+    ///
+    pub fn max_bytes(&mut self, max_bytes: u64) -> &mut Self {
+        assert!(max_bytes > 0, "max_bytes: {}", max_bytes);
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+
+    /// Limit the number of cached entries.
+    ///
+    pub fn max_entries(&mut self, max_entries: usize) -> &mut Self {
+        assert!(max_entries > 0, "max_entries: {}", max_entries);
+        self.max_entries = Some(max_entries);
+        self
+    }
+
+    /// Directory in which the entries are stored.
+    ///
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// Keys of the cached entries, least recently used first.
+    ///
+    pub fn keys(&self) -> Result<Vec<String>> {
+        Ok(self.indexes()?.into_iter().map(|(_, index)| index.key).collect())
+    }
+
+    /// Number of cached entries and total size of their bodies.
+    ///
+    pub fn usage(&self) -> Result<(usize, u64)> {
+        let indexes = self.indexes()?;
+        Ok((indexes.len(), indexes.iter().map(|(_, index)| index.size).sum()))
+    }
+
+    fn index_path(&self, key: &str) -> PathBuf {
+        self.directory.join(format!("{:016x}.{}", fnv1a(key), INDEX_EXTENSION))
+    }
+
+    fn read_index(&self, path: &Path, key: &str) -> Option<Index> {
+        read_index(path).filter(|index| index.key == key)
+    }
+
+    /// Valid index files of the directory, least recently used first.
+    ///
+    fn indexes(&self) -> Result<Vec<(PathBuf, Index)>> {
+        let mut indexes = vec![];
+
+        for path in self.files(INDEX_EXTENSION)? {
+            if let Some(index) = read_index(&path) {
+                indexes.push((path, index));
+            }
+        }
+
+        indexes.sort_by(|(_, a), (_, b)| (a.accessed, &a.key).cmp(&(b.accessed, &b.key)));
+        Ok(indexes)
+    }
+
+    fn files(&self, extension: &str) -> Result<Vec<PathBuf>> {
+        let mut files = vec![];
+
+        for entry in fs::read_dir(&self.directory).map_err(io_error)? {
+            let path = entry.map_err(io_error)?.path();
+
+            if path.extension().map(|x| x == extension).unwrap_or(false) {
+                files.push(path);
+            }
+        }
+
+        Ok(files)
+    }
+
+    fn write_index(&self, path: &Path, index: &Index) -> Result<()> {
+        let encoded = serde_json::to_vec(index).map_err(Error::from)?;
+        self.write_atomically(path, &encoded)
+    }
+
+    fn write_atomically(&self, path: &Path, data: &[u8]) -> Result<()> {
+        let temporary = self.directory.join(format!("{}.{}", unique_name(), TEMPORARY_EXTENSION));
+
+        let result = {
+            File::create(&temporary)
+                .and_then(|mut file| file.write_all(data).and_then(|_| file.sync_all()))
+                .and_then(|_| fs::rename(&temporary, path))
+        };
+
+        if result.is_err() {
+            let _ = fs::remove_file(&temporary);
+        }
+
+        result.map_err(io_error)
+    }
+
+    fn remove_entry(&self, path: &Path, index: &Index) -> Result<()> {
+        remove_file(path)?;
+        remove_file(&self.directory.join(&index.body))
+    }
+
+    fn evict(&self) -> Result<()> {
+        let indexes = self.indexes()?;
+
+        let mut entries = indexes.len();
+        let mut bytes: u64 = indexes.iter().map(|(_, index)| index.size).sum();
+
+        for (path, index) in indexes {
+            let over_entries = self.max_entries.map(|max| entries > max).unwrap_or(false);
+            let over_bytes = self.max_bytes.map(|max| bytes > max).unwrap_or(false);
+
+            if !over_entries && !over_bytes {
+                break;
+            }
+
+            self.remove_entry(&path, &index)?;
+
+            entries -= 1;
+            bytes -= index.size;
+        }
+
+        Ok(())
+    }
+}
+
+impl CachePolicy for FileCache {
+    fn get(&self, key: &str) -> Result<Option<CacheEntry>> {
+        let path = self.index_path(key);
+
+        let mut index = match self.read_index(&path, key) {
+            Some(index) => index,
+            None => return Ok(None),
+        };
+
+        // The body may have been replaced or evicted by another process since the index was read.
+        let body = match fs::read(self.directory.join(&index.body)) {
+            Ok(body) if body.len() as u64 == index.size => body,
+            Ok(_) => return Ok(None),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(io_error(e)),
+        };
+
+        let entry = CacheEntry {
+            etag: index.etag.clone(),
+            content_type: index.content_type.clone(),
+            body,
+        };
+
+        index.accessed = timestamp();
+        self.write_index(&path, &index)?;
+
+        Ok(Some(entry))
+    }
+
+    fn put(&self, key: &str, entry: &CacheEntry) -> Result<()> {
+        let size = entry.body.len() as u64;
+
+        if self.max_bytes.map(|max| size > max).unwrap_or(false) {
+            self.remove(key)?;
+            return Ok(());
+        }
+
+        let path = self.index_path(key);
+        let previous = read_index(&path);
+
+        let index = Index {
+            key: key.to_string(),
+            etag: entry.etag.clone(),
+            content_type: entry.content_type.clone(),
+            body: format!("{}.{}", unique_name(), BODY_EXTENSION),
+            size,
+            accessed: timestamp(),
+        };
+
+        // The body is in place before any index refers to it.
+        self.write_atomically(&self.directory.join(&index.body), &entry.body)?;
+        self.write_index(&path, &index)?;
+
+        if let Some(previous) = previous {
+            remove_file(&self.directory.join(&previous.body))?;
+        }
+
+        self.evict()
+    }
+
+    fn remove(&self, key: &str) -> Result<bool> {
+        let path = self.index_path(key);
+
+        match self.read_index(&path, key) {
+            Some(index) => {
+                self.remove_entry(&path, &index)?;
+                Ok(true)
+            },
+
+            None => Ok(false),
+        }
+    }
+
+    fn purge_older_than(&self, age: Duration) -> Result<usize> {
+        let cutoff = timestamp().saturating_sub(age.as_nanos() as u64);
+        let mut purged = 0;
+        let mut referenced = vec![];
+
+        for path in self.files(INDEX_EXTENSION)? {
+            match read_index(&path) {
+                Some(ref index) if index.accessed < cutoff => {
+                    self.remove_entry(&path, index)?;
+                    purged += 1;
+                },
+
+                Some(index) => referenced.push(self.directory.join(index.body)),
+
+                // Left by something else than this cache, since indexes are written atomically.
+                None => remove_file(&path)?,
+            }
+        }
+
+        // Leftovers of interrupted writes, kept for a while in case they are still in progress.
+        let leftovers = {
+            self.files(BODY_EXTENSION)?.into_iter()
+                .filter(|path| !referenced.contains(path))
+                .chain(self.files(TEMPORARY_EXTENSION)?)
+        };
+
+        for path in leftovers {
+            let modified = {
+                fs::metadata(&path)
+                    .and_then(|metadata| metadata.modified())
+                    .map(nanoseconds_since_epoch)
+            };
+
+            match modified {
+                Ok(modified) if modified >= cutoff => (),
+                Err(ref e) if e.kind() == io::ErrorKind::NotFound => (),
+                _ => remove_file(&path)?,
+            }
+        }
+
+        Ok(purged)
+    }
+}
+
+fn read_index(path: &Path) -> Option<Index> {
+    fs::read(path).ok().and_then(|encoded| serde_json::from_slice(&encoded).ok())
+}
+
+/// Remove a file which may already have been removed by another process.
+///
+fn remove_file(path: &Path) -> Result<()> {
+    match fs::remove_file(path) {
+        Err(ref e) if e.kind() != io::ErrorKind::NotFound => Err(io_error(e)),
+        _ => Ok(()),
+    }
+}
+
+fn io_error<E: ToString>(e: E) -> Error {
+    Error::IoError(e.to_string())
+}
+
+/// Stable hash of the keys, used to name their index files.
+///
+fn fnv1a(key: &str) -> u64 {
+    key.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, x| {
+        (hash ^ u64::from(x)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+fn nanoseconds_since_epoch(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map(|x| x.as_nanos() as u64).unwrap_or(0)
+}
+
+/// Current time, strictly increasing within this process so accesses are never tied.
+///
+fn timestamp() -> u64 {
+    static LAST: AtomicU64 = AtomicU64::new(0);
+
+    let now = nanoseconds_since_epoch(SystemTime::now());
+    let mut last = LAST.load(Ordering::SeqCst);
+
+    loop {
+        let next = now.max(last + 1);
+
+        match LAST.compare_exchange(last, next, Ordering::SeqCst, Ordering::SeqCst) {
+            Ok(_) => return next,
+            Err(current) => last = current,
+        }
+    }
+}
+
+/// (De)serialization of access times as RFC 3339 UTC timestamps, which keep their order. Those
+/// written as numbers of nanoseconds by earlier versions are still read.
+///
+mod access_time {
+    use chrono::{DateTime, SecondsFormat};
+    use serde::{Deserialize, Deserializer, Serializer};
+    use serde::de::Error;
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Written {
+        Nanoseconds(u64),
+        Timestamp(String),
+    }
+
+    pub fn serialize<S: Serializer>(accessed: &u64, serializer: S)
+        -> ::std::result::Result<S::Ok, S::Error>
+    {
+        let timestamp = DateTime::from_timestamp_nanos(*accessed as i64);
+        serializer.collect_str(&timestamp.to_rfc3339_opts(SecondsFormat::Nanos, true))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D)
+        -> ::std::result::Result<u64, D::Error>
+    {
+        match Written::deserialize(deserializer)? {
+            Written::Nanoseconds(accessed) => Ok(accessed),
+
+            Written::Timestamp(timestamp) => {
+                crate::calendar::parse_timestamp_utc(&timestamp)
+                    .and_then(|timestamp| timestamp.timestamp_nanos_opt())
+                    .map(|accessed| accessed.max(0) as u64)
+                    .ok_or_else(|| D::Error::custom(format!("invalid timestamp '{}'.", timestamp)))
+            },
+        }
+    }
+}
+
+fn unique_name() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    format!("{}-{}-{}",
+            ::std::process::id(),
+            COUNTER.fetch_add(1, Ordering::SeqCst),
+            nanoseconds_since_epoch(SystemTime::now()))
+}
@@ -0,0 +1,505 @@
+use std::collections::{BTreeSet, HashSet};
+use std::convert::TryFrom;
+
+use chrono::{Datelike, Duration, SecondsFormat, Weekday};
+
+pub use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+
+use crate::{Error, Result};
+use crate::types::Frequency;
+
+/// Trait implemented by calendars able to tell whether a given date is a holiday.
+///
+/// Weekends are always considered non-business days by the functions of this module, an
+/// implementation of this trait only needs to report the additional closing days (e.g. the
+/// holidays of a specific exchange).
+///
+pub trait HolidayCalendar {
+    /// Whether or not the market is closed on `date` for reasons other than it being a weekend.
+    ///
+    fn is_holiday(&self, date: NaiveDate) -> bool;
+}
+
+/// Calendar without holidays; only Saturdays and Sundays are non-business days.
+///
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct WeekendsOnly;
+
+impl HolidayCalendar for WeekendsOnly {
+    fn is_holiday(&self, _: NaiveDate) -> bool {
+        false
+    }
+}
+
+impl HolidayCalendar for [NaiveDate] {
+    fn is_holiday(&self, date: NaiveDate) -> bool {
+        self.contains(&date)
+    }
+}
+
+impl HolidayCalendar for Vec<NaiveDate> {
+    fn is_holiday(&self, date: NaiveDate) -> bool {
+        self.contains(&date)
+    }
+}
+
+impl HolidayCalendar for BTreeSet<NaiveDate> {
+    fn is_holiday(&self, date: NaiveDate) -> bool {
+        self.contains(&date)
+    }
+}
+
+impl HolidayCalendar for HashSet<NaiveDate> {
+    fn is_holiday(&self, date: NaiveDate) -> bool {
+        self.contains(&date)
+    }
+}
+
+impl<C: HolidayCalendar + ?Sized> HolidayCalendar for &C {
+    fn is_holiday(&self, date: NaiveDate) -> bool {
+        (**self).is_holiday(date)
+    }
+}
+
+/// An inclusive range of dates, following the same convention as the `start_date` and `end_date`
+/// data parameters.
+///
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct DateRange {
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+}
+
+impl DateRange {
+    /// Create the range from `start` to `end`, both inclusive.
+    ///
+    /// `start` must not be after `end`.
+    ///
+    pub fn new(start: NaiveDate, end: NaiveDate) -> Self {
+        assert!(start <= end, "start: {}, end: {}", start, end);
+        DateRange { start, end }
+    }
+
+    /// Whether or not `date` is within this range.
+    ///
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        self.start <= date && date <= self.end
+    }
+
+    /// Number of days in this range, both ends included.
+    ///
+    pub fn days(&self) -> usize {
+        (self.end - self.start).num_days() as usize + 1
+    }
+}
+
+impl ::std::fmt::Display for DateRange {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        write!(f, "{} to {}", self.start, self.end)
+    }
+}
+
+/// Whether or not `year` is a leap year in the proleptic Gregorian calendar.
+///
+pub fn is_leap_year(year: i32) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+/// Number of days in the given month of the given year.
+///
+/// The month must be in the range `1..=12`.
+///
+pub fn days_in_month(year: i32, month: u32) -> u32 {
+    assert!((1..=12).contains(&month), "month: {}", month);
+
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Whether or not `date` falls on a Saturday or a Sunday.
+///
+pub fn is_weekend(date: NaiveDate) -> bool {
+    matches!(date.weekday(), Weekday::Sat | Weekday::Sun)
+}
+
+/// Whether or not `date` is neither a weekend nor a holiday of `calendar`.
+///
+pub fn is_business_day<C: HolidayCalendar + ?Sized>(date: NaiveDate, calendar: &C) -> bool {
+    !is_weekend(date) && !calendar.is_holiday(date)
+}
+
+/// The first business day strictly after `date`.
+///
+pub fn next_business_day<C: HolidayCalendar + ?Sized>(date: NaiveDate, calendar: &C) -> NaiveDate {
+    let mut date = date.succ_opt().expect("date out of range");
+
+    while !is_business_day(date, calendar) {
+        date = date.succ_opt().expect("date out of range");
+    }
+
+    date
+}
+
+/// The last business day strictly before `date`.
+///
+pub fn previous_business_day<C: HolidayCalendar + ?Sized>(date: NaiveDate,
+                                                          calendar: &C) -> NaiveDate
+{
+    let mut date = date.pred_opt().expect("date out of range");
+
+    while !is_business_day(date, calendar) {
+        date = date.pred_opt().expect("date out of range");
+    }
+
+    date
+}
+
+/// Number of business days between `start` and `end`, both inclusive (the same convention as the
+/// `start_date` and `end_date` data parameters). Returns 0 if `start` is after `end`.
+///
+pub fn business_days_between<C: HolidayCalendar + ?Sized>(start: NaiveDate,
+                                                          end: NaiveDate,
+                                                          calendar: &C) -> usize
+{
+    start.iter_days()
+        .take_while(|date| *date <= end)
+        .filter(|date| is_business_day(*date, calendar))
+        .count()
+}
+
+/// The ISO 8601 week-numbering year and week number of `date`.
+///
+/// Note that the ISO year may differ from the calendar year for the first and last few days of a
+/// year, e.g. 2021-01-03 falls in the 53rd week of 2020.
+///
+pub fn iso_week(date: NaiveDate) -> (i32, u32) {
+    let week = date.iso_week();
+    (week.year(), week.week())
+}
+
+/// Number of ISO weeks (52 or 53) in the given ISO week-numbering year.
+///
+pub fn weeks_in_iso_year(year: i32) -> u32 {
+    // December 28th always falls in the last ISO week of its year.
+    NaiveDate::from_ymd_opt(year, 12, 28).expect("year out of range").iso_week().week()
+}
+
+/// Monday of the ISO week containing `date`.
+///
+pub fn iso_week_start(date: NaiveDate) -> NaiveDate {
+    date - Duration::days(i64::from(date.weekday().num_days_from_monday()))
+}
+
+/// Sunday of the ISO week containing `date`.
+///
+/// This is the date Quandl uses to label weekly collapsed observations.
+///
+pub fn iso_week_end(date: NaiveDate) -> NaiveDate {
+    iso_week_start(date) + Duration::days(6)
+}
+
+/// First day of the month containing `date`.
+///
+pub fn month_start(date: NaiveDate) -> NaiveDate {
+    ymd(date.year(), date.month(), 1)
+}
+
+/// Last day of the month containing `date`.
+///
+pub fn month_end(date: NaiveDate) -> NaiveDate {
+    ymd(date.year(), date.month(), days_in_month(date.year(), date.month()))
+}
+
+/// Quarter (in the range `1..=4`) of the year containing `date`.
+///
+pub fn quarter(date: NaiveDate) -> u32 {
+    (date.month() - 1) / 3 + 1
+}
+
+/// First day of the quarter containing `date`.
+///
+pub fn quarter_start(date: NaiveDate) -> NaiveDate {
+    ymd(date.year(), (quarter(date) - 1) * 3 + 1, 1)
+}
+
+/// Last day of the quarter containing `date`.
+///
+pub fn quarter_end(date: NaiveDate) -> NaiveDate {
+    let month = quarter(date) * 3;
+    ymd(date.year(), month, days_in_month(date.year(), month))
+}
+
+/// January 1st of the year containing `date`.
+///
+pub fn year_start(date: NaiveDate) -> NaiveDate {
+    ymd(date.year(), 1, 1)
+}
+
+/// December 31st of the year containing `date`.
+///
+pub fn year_end(date: NaiveDate) -> NaiveDate {
+    ymd(date.year(), 12, 31)
+}
+
+/// First day of the period of the given frequency containing `date`.
+///
+/// For `Frequency::none` and `Frequency::daily` this is `date` itself; weekly periods are ISO
+/// weeks (starting on Monday).
+///
+pub fn period_start(date: NaiveDate, frequency: Frequency) -> NaiveDate {
+    match frequency {
+        Frequency::none | Frequency::daily => date,
+        Frequency::weekly => iso_week_start(date),
+        Frequency::monthly => month_start(date),
+        Frequency::quarterly => quarter_start(date),
+        Frequency::annual => year_start(date),
+    }
+}
+
+/// Last day of the period of the given frequency containing `date`.
+///
+/// When collapsing a dataset, Quandl labels each observation with the end of its period; this
+/// function thus maps any date to the date its collapsed observation would carry.
+///
+pub fn period_end(date: NaiveDate, frequency: Frequency) -> NaiveDate {
+    match frequency {
+        Frequency::none | Frequency::daily => date,
+        Frequency::weekly => iso_week_end(date),
+        Frequency::monthly => month_end(date),
+        Frequency::quarterly => quarter_end(date),
+        Frequency::annual => year_end(date),
+    }
+}
+
+/// Whether or not `a` and `b` fall within the same period of the given frequency.
+///
+pub fn same_period(a: NaiveDate, b: NaiveDate, frequency: Frequency) -> bool {
+    period_start(a, frequency) == period_start(b, frequency)
+}
+
+/// A fiscal year, or a quarter of one, of a calendar whose fiscal years end with a given month,
+/// see `DataParameters::start_period` and `DataParameters::end_period`.
+///
+/// Fiscal years are named after the calendar year they end in: with years ending in June, fiscal
+/// 2024 runs from 2023-07-01 to 2024-06-30 and its first quarter from July to September 2023.
+/// Years end in December unless told otherwise, fiscal and calendar years then being the same.
+///
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct PeriodSpec {
+    year: u16,
+    quarter: Option<u8>,
+    year_end_month: u8,
+}
+
+impl PeriodSpec {
+    /// The whole fiscal year `year`, which must be positive.
+    ///
+    pub fn fiscal_year(year: u16) -> Self {
+        assert!(year > 0, "fiscal year: {}", year);
+        PeriodSpec { year, quarter: None, year_end_month: 12 }
+    }
+
+    /// The given quarter (in the range `1..=4`) of the fiscal year `year`, which must be
+    /// positive.
+    ///
+    pub fn fiscal_quarter(year: u16, quarter: u8) -> Self {
+        assert!((1..=4).contains(&quarter), "fiscal quarter: {}", quarter);
+        PeriodSpec { quarter: Some(quarter), ..PeriodSpec::fiscal_year(year) }
+    }
+
+    /// The fiscal quarter containing `date`, for fiscal years ending with `year_end_month`.
+    ///
+    pub fn containing(date: NaiveDate, year_end_month: u8) -> Self {
+        assert!((1..=12).contains(&year_end_month), "year end month: {}", year_end_month);
+
+        // Months since the start of the fiscal year 0.
+        let months = date.year() * 12 + date.month0() as i32 + 12 - i32::from(year_end_month);
+        let year = u16::try_from(months.div_euclid(12)).expect("date out of range");
+
+        PeriodSpec::fiscal_quarter(year, (months.rem_euclid(12) / 3 + 1) as u8)
+            .year_end_month(year_end_month)
+    }
+
+    /// Same period, of fiscal years ending with `month` (in the range `1..=12`).
+    ///
+    pub fn year_end_month(self, month: u8) -> Self {
+        assert!((1..=12).contains(&month), "year end month: {}", month);
+        PeriodSpec { year_end_month: month, ..self }
+    }
+
+    /// The fiscal year of this period.
+    ///
+    pub fn year(&self) -> u16 {
+        self.year
+    }
+
+    /// The quarter of this period, or `None` for a whole year.
+    ///
+    pub fn quarter(&self) -> Option<u8> {
+        self.quarter
+    }
+
+    /// The month fiscal years end with, 12 for December.
+    ///
+    pub fn fiscal_year_end_month(&self) -> u8 {
+        self.year_end_month
+    }
+
+    /// First day of this period.
+    ///
+    pub fn start(&self) -> NaiveDate {
+        let month = self.first_month();
+        ymd(month.div_euclid(12), month.rem_euclid(12) as u32 + 1, 1)
+    }
+
+    /// Last day of this period.
+    ///
+    pub fn end(&self) -> NaiveDate {
+        let month = self.first_month() + if self.quarter.is_some() { 2 } else { 11 };
+        let (year, month) = (month.div_euclid(12), month.rem_euclid(12) as u32 + 1);
+
+        ymd(year, month, days_in_month(year, month))
+    }
+
+    /// Days of this period, i.e. from its `start` to its `end`.
+    ///
+    pub fn range(&self) -> DateRange {
+        DateRange::new(self.start(), self.end())
+    }
+
+    /// First month of this period, counted from January of the year 0.
+    ///
+    fn first_month(&self) -> i32 {
+        let year_start = i32::from(self.year) * 12 + i32::from(self.year_end_month) - 12;
+        year_start + self.quarter.map_or(0, |quarter| 3 * (i32::from(quarter) - 1))
+    }
+}
+
+impl ::std::fmt::Display for PeriodSpec {
+    /// `FY2024` or `FY2024Q1`, followed by the month the fiscal years end with unless December,
+    /// e.g. `FY2024Q1 (June)`.
+    ///
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        write!(f, "FY{}", self.year)?;
+
+        if let Some(quarter) = self.quarter {
+            write!(f, "Q{}", quarter)?;
+        }
+
+        if self.year_end_month != 12 {
+            write!(f, " ({})", ymd(2000, u32::from(self.year_end_month), 1).format("%B"))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Parse a date formatted the way Quandl does, i.e. strictly `YYYY-MM-DD`.
+///
+/// Returns `None` for any other format (including unpadded months or days) and for dates which
+/// do not exist, such as February 30th.
+///
+pub fn parse_date<S: AsRef<str>>(s: S) -> Option<NaiveDate> {
+    let s = s.as_ref().as_bytes();
+
+    let well_formed = {
+        s.len() == 10 && s[4] == b'-' && s[7] == b'-' && {
+            s.iter().enumerate().all(|(index, x)| index == 4 || index == 7 || x.is_ascii_digit())
+        }
+    };
+
+    if !well_formed {
+        return None;
+    }
+
+    let number = |range: std::ops::Range<usize>| {
+        s[range].iter().fold(0, |n, x| n * 10 + u32::from(x - b'0'))
+    };
+
+    NaiveDate::from_ymd_opt(number(0..4) as i32, number(5..7), number(8..10))
+}
+
+/// Parse a timestamp formatted the way Quandl does (RFC 3339, e.g. `2016-03-01T21:47:01.686Z`),
+/// converted to UTC.
+///
+/// Not every payload formats them the same way: the sub-seconds may be missing, and so may the
+/// `Z`, a timestamp without an offset being in UTC all the same. A space may also separate the
+/// date from the time, and ` UTC` stand for the `Z`.
+///
+pub fn parse_timestamp_utc<S: AsRef<str>>(s: S) -> Option<DateTime<Utc>> {
+    let s = s.as_ref().trim();
+
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(s) {
+        return Some(timestamp.with_timezone(&Utc));
+    }
+
+    let s = s.strip_suffix(" UTC").or_else(|| s.strip_suffix('Z')).unwrap_or(s);
+
+    ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f"].iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(s, format).ok())
+        .map(|timestamp| timestamp.and_utc())
+}
+
+/// Same as `parse_timestamp_utc`, without the time zone.
+///
+pub fn parse_timestamp<S: AsRef<str>>(s: S) -> Option<NaiveDateTime> {
+    parse_timestamp_utc(s).map(|timestamp| timestamp.naive_utc())
+}
+
+/// Format `timestamp` the way Quandl does, i.e. in RFC 3339 to the millisecond and with a `Z`,
+/// as every timestamp this crate writes is.
+///
+pub fn format_timestamp(timestamp: &DateTime<Utc>) -> String {
+    timestamp.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+/// Parser of the dates heading the rows of a dataset, into whatever type `D` they are used as,
+/// see `DataQuery::send_dated`.
+///
+/// Besides `IsoDate` and `EpochDays`, any `Fn(&str) -> Result<D>` is a parser.
+///
+pub trait DateParser<D> {
+    /// Parse `s`, the first field of a row as sent by Quandl.
+    ///
+    fn parse(&self, s: &str) -> Result<D>;
+}
+
+impl<D, F: Fn(&str) -> Result<D>> DateParser<D> for F {
+    fn parse(&self, s: &str) -> Result<D> {
+        self(s)
+    }
+}
+
+/// Parser of dates into `NaiveDate`, strictly as `YYYY-MM-DD` (see `parse_date`).
+///
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct IsoDate;
+
+impl DateParser<NaiveDate> for IsoDate {
+    fn parse(&self, s: &str) -> Result<NaiveDate> {
+        match parse_date(s) {
+            Some(date) => Ok(date),
+            None => Err(Error::ParsingFailed("expected a YYYY-MM-DD date.".to_string())),
+        }
+    }
+}
+
+/// Parser of dates into the number of days since 1970-01-01 (negative before it), the dates
+/// being formatted as for `IsoDate`.
+///
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct EpochDays;
+
+impl DateParser<i64> for EpochDays {
+    fn parse(&self, s: &str) -> Result<i64> {
+        IsoDate.parse(s).map(|date| (date - ymd(1970, 1, 1)).num_days())
+    }
+}
+
+fn ymd(year: i32, month: u32, day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(year, month, day).expect("date out of range")
+}
@@ -0,0 +1,265 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::sync::{Arc, Condvar, Mutex, PoisonError};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::{Result, Error, DownloadError, DownloadErrorKind};
+
+/// How often the waits which cannot be woken up by a token (e.g. for a response) check whether
+/// one of the tokens in effect was cancelled.
+///
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+thread_local! {
+    static TOKENS: RefCell<Vec<CancelToken>> = const { RefCell::new(Vec::new()) };
+}
+
+/// A switch to give up on operations in progress, e.g. from a UI thread when the user closes the
+/// view waiting for them.
+///
+/// Tokens are cheap to clone, clones sharing the same switch: `cancel` on any of them cancels
+/// every operation any of them was given to, from whatever thread. Once cancelled, a token stays
+/// so. Operations given up return `Error::Cancelled`, which is never the result of a timeout
+/// (see `Error::DeadlineExceeded` for those).
+///
+/// Tokens are checked
+///
+/// * before each request, which is not sent if one is cancelled;
+/// * while waiting for a response and receiving its body, which is abandoned as soon as one is
+///   (its connection being closed behind it), as is the wait for an identical request in flight
+///   (see `ClientConfig::coalesce`);
+/// * between the pages of a `DatasetListingIterator`, the chunks of a resumable download (whose
+///   progress is saved, for the download to resume later) and the queries of a `BatchQuery`;
+/// * during the waits for a rate limit of a `BatchQuery` and between the attempts of
+///   `backoff::retry`.
+///
+/// They are given to single queries by `ApiCall::send_with_cancel`, to batches by
+/// `BatchQuery::cancel_token`, to the listing of datasets by `DatasetListingIterator::cancel_token`
+/// and to mirror jobs by `MirrorJob::cancel_token`; `scope` puts them in effect for anything else
+/// (e.g. `DatabaseDownloadQuery::download_to_file_retrying`).
+///
+/// ```rust,no_run
+/// use std::thread;
+/// use std::time::Duration;
+///
+/// use quandl_v3::{CancelToken, Error};
+/// use quandl_v3::prelude::*;
+///
+/// let token = CancelToken::new();
+///
+/// {
+///     let token = token.clone();
+///     thread::spawn(move || { thread::sleep(Duration::from_secs(1)); token.cancel() });
+/// }
+///
+/// match DatabaseMetadataQuery::new("WIKI").send_with_cancel(&token) {
+///     Err(Error::Cancelled) => println!("gave up"),
+///     result => println!("{:?}", result),
+/// }
+/// ```
+///
+#[derive(Clone, Default)]
+pub struct CancelToken(Arc<Switch>);
+
+/// Whether a token was cancelled, along with what the threads waiting for it to be wait on.
+///
+#[derive(Default)]
+struct Switch {
+    cancelled: Mutex<bool>,
+    changed: Condvar,
+}
+
+/// Takes the token put in effect by `CancelToken::enter` out of effect when dropped.
+///
+pub(crate) struct Scope(());
+
+impl CancelToken {
+    /// A token which is not cancelled yet.
+    ///
+    pub fn new() -> Self {
+        CancelToken::default()
+    }
+
+    /// Cancel every operation given this token (or one of its clones), including those yet to
+    /// start.
+    ///
+    pub fn cancel(&self) {
+        *self.0.cancelled.lock().unwrap_or_else(PoisonError::into_inner) = true;
+        self.0.changed.notify_all();
+    }
+
+    /// Whether `cancel` was called on this token or one of its clones.
+    ///
+    pub fn is_cancelled(&self) -> bool {
+        *self.0.cancelled.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Run `f`, giving up on every request it sends from the current thread once this token (or
+    /// one of those already in effect, if any) is cancelled.
+    ///
+    /// Requests sent from other threads, e.g. by the workers of a `BatchQuery`, are not affected:
+    /// tokens are given to those with the batch's own `cancel_token`.
+    ///
+    pub fn scope<R, F: FnOnce() -> R>(&self, f: F) -> R {
+        let _scope = self.enter();
+        f()
+    }
+
+    /// Put this token in effect on the current thread, until the returned scope is dropped.
+    ///
+    pub(crate) fn enter(&self) -> Scope {
+        TOKENS.with(|tokens| tokens.borrow_mut().push(self.clone()));
+        Scope(())
+    }
+
+    /// Block until this token is cancelled or `timeout` passes, whichever comes first.
+    ///
+    fn wait(&self, timeout: Duration) {
+        let cancelled = self.0.cancelled.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let _cancelled = {
+            self.0.changed.wait_timeout_while(cancelled, timeout, |cancelled| !*cancelled)
+                .unwrap_or_else(PoisonError::into_inner)
+        };
+    }
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        TOKENS.with(|tokens| tokens.borrow_mut().pop());
+    }
+}
+
+impl fmt::Debug for CancelToken {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CancelToken").field("cancelled", &self.is_cancelled()).finish()
+    }
+}
+
+/// Tokens are equal when they share the same switch, i.e. one is a clone of the other.
+///
+impl PartialEq for CancelToken {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for CancelToken {}
+
+/// Tokens in effect on the current thread, innermost last.
+///
+fn tokens() -> Vec<CancelToken> {
+    TOKENS.with(|tokens| tokens.borrow().clone())
+}
+
+fn check_tokens(tokens: &[CancelToken]) -> Result<()> {
+    if tokens.iter().any(CancelToken::is_cancelled) {
+        Err(Error::Cancelled)
+    } else {
+        Ok(())
+    }
+}
+
+/// Whether a token is in effect on the current thread.
+///
+fn is_active() -> bool {
+    TOKENS.with(|tokens| !tokens.borrow().is_empty())
+}
+
+/// `Error::Cancelled` if one of the tokens in effect on the current thread was cancelled.
+///
+pub(crate) fn check() -> Result<()> {
+    TOKENS.with(|tokens| check_tokens(&tokens.borrow()))
+}
+
+/// Time to wait at most before checking the tokens in effect on the current thread again, while
+/// waiting up to `budget` (if any) for something they cannot interrupt.
+///
+pub(crate) fn poll_interval(budget: Option<Duration>) -> Option<Duration> {
+    if is_active() {
+        Some(budget.map_or(POLL_INTERVAL, |budget| budget.min(POLL_INTERVAL)))
+    } else {
+        budget
+    }
+}
+
+/// Sleep for `duration`, unless one of the tokens in effect on the current thread is cancelled
+/// first, in which case `Error::Cancelled` is returned as soon as it is.
+///
+pub(crate) fn sleep(duration: Duration) -> Result<()> {
+    let tokens = tokens();
+
+    let innermost = {
+        match tokens.last() {
+            Some(token) => token,
+
+            None => {
+                thread::sleep(duration);
+                return Ok(());
+            },
+        }
+    };
+
+    // Too far ahead to be represented, this is waiting for the tokens alone.
+    let end = Instant::now().checked_add(duration);
+
+    loop {
+        check_tokens(&tokens)?;
+
+        let now = Instant::now();
+
+        if end.filter(|&end| now >= end).is_some() {
+            return Ok(());
+        }
+
+        // Only the innermost token wakes this thread up, the others are polled, as is the
+        // innermost one without an end.
+        match (tokens.len(), end) {
+            (1, Some(end)) => innermost.wait(end - now),
+            (_, Some(end)) => innermost.wait((end - now).min(POLL_INTERVAL)),
+            (_, None) => innermost.wait(POLL_INTERVAL),
+        }
+    }
+}
+
+/// Run `f`, which cannot be interrupted (e.g. blocking on a socket), on a thread of its own if
+/// tokens are in effect on the current thread, so as to return `Error::Cancelled` as soon as one
+/// of them is cancelled rather than once `f` returns. What `f` returns is then dropped along with
+/// its thread.
+///
+/// Without any token in effect, `f` is simply run on the current thread.
+///
+pub(crate) fn interruptible<R, F>(f: F) -> Result<R>
+    where R: Send + 'static,
+          F: FnOnce() -> R + Send + 'static,
+{
+    let tokens = tokens();
+
+    if tokens.is_empty() {
+        return Ok(f());
+    }
+
+    check_tokens(&tokens)?;
+
+    let (tx, rx) = mpsc::channel();
+
+    thread::Builder::new()
+        .name("quandl-request".to_string())
+        .spawn(move || { let _ = tx.send(f()); })
+        .map_err(|e| Error::IoError(e.to_string()))?;
+
+    loop {
+        match rx.recv_timeout(POLL_INTERVAL) {
+            Ok(result) => return Ok(result),
+            Err(RecvTimeoutError::Timeout) => check_tokens(&tokens)?,
+
+            Err(RecvTimeoutError::Disconnected) => {
+                let message = "the thread sending the request panicked.";
+                return Err(Error::DownloadFailed(DownloadError::new(DownloadErrorKind::Other,
+                                                                    message)));
+            },
+        }
+    }
+}
@@ -0,0 +1,230 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock, PoisonError};
+
+use url::{Position, Url};
+
+use crate::{Result, Error, DownloadError, DownloadErrorKind};
+use crate::archive::Archive;
+
+/// Settings of the HTTP client sending a query, see `ApiParameters::client_config`.
+///
+/// Queries without a configuration use a default client, trusting the system's root
+/// certificates.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct ClientConfig {
+    pinned_certificates: Vec<Vec<u8>>,
+    coalesce: bool,
+    fallbacks: Vec<String>,
+    archive: Option<Archive>,
+}
+
+/// Host currently serving the requests of each failover group, as its index in the group, see
+/// `ClientConfig::failover_group`.
+///
+static ACTIVE_HOSTS: OnceLock<Mutex<HashMap<Vec<String>, usize>>> = OnceLock::new();
+
+impl ClientConfig {
+    /// Create a configuration identical to the default client.
+    ///
+    pub fn new() -> Self {
+        ClientConfig::default()
+    }
+
+    /// Only trust servers whose certificate chain leads to one of `certificates` (DER-encoded),
+    /// e.g. the chain of Quandl's endpoint for credentialed requests.
+    ///
+    /// The system's root certificates are then ignored altogether by the queries using this
+    /// configuration, for every host they connect to (redirections included). A server failing
+    /// this check is reported as a download failure of kind
+    /// `DownloadErrorKind::UntrustedCertificate`.
+    ///
+    /// Certificates which cannot be decoded make every query using this configuration fail with a
+    /// download failure of kind `DownloadErrorKind::Tls`. At least one certificate must be given.
+    ///
+    pub fn pin_certificates(&mut self, certificates: Vec<Vec<u8>>) -> &mut Self {
+        assert!(!certificates.is_empty(), "certificates: {:?}", certificates);
+        self.pinned_certificates = certificates;
+        self
+    }
+
+    /// Certificates set with `pin_certificates`, if any.
+    ///
+    pub fn pinned_certificates(&self) -> &[Vec<u8>] {
+        &self.pinned_certificates
+    }
+
+    /// Whether or not servers are checked against pinned certificates.
+    ///
+    pub fn is_pinned(&self) -> bool {
+        !self.pinned_certificates.is_empty()
+    }
+
+    /// Share the response of identical requests sent at the same time, i.e. with the same method,
+    /// URL and headers, instead of sending each of them.
+    ///
+    /// While a request using this configuration is in flight, the same request sent from another
+    /// thread waits for it and gets a copy of its result, success or error. Nothing is kept once
+    /// the request completes: a request sent afterwards goes to the server again. Off by default.
+    ///
+    pub fn coalesce(&mut self, coalesce: bool) -> &mut Self {
+        self.coalesce = coalesce;
+        self
+    }
+
+    /// Whether or not identical in-flight requests are coalesced, see `coalesce`.
+    ///
+    pub fn is_coalescing(&self) -> bool {
+        self.coalesce
+    }
+
+    /// Send the requests to the hosts of `hosts` (e.g. `https://mirror.example.com`), in order,
+    /// when the host of their URL cannot be reached.
+    ///
+    /// Only failures to get a response fail over, i.e. download failures of kind
+    /// `DownloadErrorKind::Dns`, `Connect`, `Tls`, `UntrustedCertificate` or `Timeout`: a response
+    /// with an error, even from a proxy, would tell the same from any host. The request is then
+    /// sent to the next host with the same path and query string, the scheme, host and port of
+    /// each fallback being all that is kept of it. The host which last responded keeps serving
+    /// the requests to the same host, with any configuration having the same fallbacks, until it
+    /// cannot be reached in turn: the next one is then tried, back to the host of the URL after
+    /// the last fallback. See `active_host` to tell which host responds.
+    ///
+    /// Every host must be an `http` or `https` URL.
+    ///
+    pub fn fallback_hosts<S: AsRef<str>>(&mut self, hosts: Vec<S>) -> &mut Self {
+        self.fallbacks = {
+            hosts.iter()
+                .map(|host| {
+                    let url = Url::parse(host.as_ref()).ok().filter(|url| {
+                        url.has_host() && (url.scheme() == "http" || url.scheme() == "https")
+                    });
+
+                    match url {
+                        Some(url) => origin(&url),
+                        None => panic!("fallback host: {:?}", host.as_ref()),
+                    }
+                })
+                .collect()
+        };
+
+        self
+    }
+
+    /// Scheme, host and port of the fallbacks set with `fallback_hosts`, if any.
+    ///
+    pub fn fallbacks(&self) -> &[String] {
+        &self.fallbacks
+    }
+
+    /// Store the payload of every successful response received whole by the queries using this
+    /// configuration in `archive`, see `Archive::store`, before it is even decoded.
+    ///
+    /// A payload which cannot be stored fails its query with the corresponding `Error::IoError`,
+    /// so that nothing is used which was not archived. Files downloaded by
+    /// `DatabaseDownloadQuery` are not archived.
+    ///
+    pub fn write_through(&mut self, archive: Archive) -> &mut Self {
+        self.archive = Some(archive);
+        self
+    }
+
+    /// The archive set with `write_through`, if any.
+    ///
+    pub fn archive(&self) -> Option<&Archive> {
+        self.archive.as_ref()
+    }
+
+    /// Scheme, host and port of the host the requests to `url` (e.g. a base URL) are currently
+    /// sent to with this configuration: the host of `url` itself unless it failed over to one of
+    /// the `fallback_hosts`.
+    ///
+    pub fn active_host<S: AsRef<str>>(&self, url: S) -> Result<String> {
+        let url = {
+            Url::parse(url.as_ref()).map_err(|e| {
+                Error::ParsingFailed(format!("invalid URL {:?}: {}", url.as_ref(), e))
+            })?
+        };
+
+        let hosts = self.failover_group(&url);
+        let active = active_host(&hosts);
+        Ok(hosts[active].clone())
+    }
+
+    /// The host of `url` followed by the fallbacks, which the requests to `url` fail over
+    /// through.
+    ///
+    pub(crate) fn failover_group(&self, url: &Url) -> Vec<String> {
+        let mut hosts = vec![origin(url)];
+        hosts.extend(self.fallbacks.iter().cloned());
+        hosts
+    }
+
+    /// Build a client with this configuration.
+    ///
+    pub(crate) fn build(&self) -> Result<reqwest::blocking::Client> {
+        let mut builder = reqwest::blocking::Client::builder();
+
+        if self.is_pinned() {
+            let mut tls = native_tls::TlsConnector::builder();
+            tls.disable_built_in_roots(true);
+
+            for (index, der) in self.pinned_certificates.iter().enumerate() {
+                match native_tls::Certificate::from_der(der) {
+                    Ok(certificate) => { tls.add_root_certificate(certificate); },
+
+                    Err(e) => {
+                        let message = format!("invalid pinned certificate {}: {}", index, e);
+                        return Err(Error::DownloadFailed(DownloadError::new(DownloadErrorKind::Tls,
+                                                                            message)));
+                    },
+                }
+            }
+
+            let tls = {
+                tls.build().map_err(|e| {
+                    Error::DownloadFailed(DownloadError::with_source(DownloadErrorKind::Tls, e))
+                })?
+            };
+
+            builder = builder.use_preconfigured_tls(tls);
+        }
+
+        builder.build().map_err(|e| {
+            Error::DownloadFailed(DownloadError::with_source(DownloadErrorKind::Other, e))
+        })
+    }
+}
+
+/// Scheme, host and port of `url`, e.g. `https://www.quandl.com`.
+///
+pub(crate) fn origin(url: &Url) -> String {
+    url[..Position::BeforePath].to_string()
+}
+
+/// `url` sent to `origin` instead of its own host.
+///
+pub(crate) fn with_origin(url: &Url, origin: &str) -> String {
+    format!("{}{}", origin, &url[Position::BeforePath..])
+}
+
+/// Index of the host serving the requests of `hosts`, see `ClientConfig::failover_group`.
+///
+pub(crate) fn active_host(hosts: &[String]) -> usize {
+    let active = ACTIVE_HOSTS.get_or_init(Default::default).lock();
+    let active = active.unwrap_or_else(PoisonError::into_inner);
+    active.get(hosts).cloned().filter(|&index| index < hosts.len()).unwrap_or(0)
+}
+
+/// Record that `hosts[index]` responded, so it serves the next requests of `hosts`.
+///
+pub(crate) fn set_active_host(hosts: &[String], index: usize) {
+    let active = ACTIVE_HOSTS.get_or_init(Default::default).lock();
+    let mut active = active.unwrap_or_else(PoisonError::into_inner);
+
+    if index == 0 {
+        active.remove(hosts);
+    } else {
+        active.insert(hosts.to_vec(), index);
+    }
+}
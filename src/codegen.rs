@@ -0,0 +1,212 @@
+use std::collections::HashSet;
+
+use crate::schema::{ColType, Schema};
+use crate::types::DatasetMetadata;
+
+/// Keywords (strict, reserved and weak) which cannot be used as field names as-is.
+///
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate",
+    "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl",
+    "in", "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "self", "static", "struct", "super", "trait", "true", "try", "type", "typeof",
+    "union", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+/// Generate the definition of a struct named `struct_name` to decode the rows of the dataset
+/// described by `metadata`, e.g. from a build script.
+///
+/// The first column is the date, as a `String`, and every other column an `Option<f64>` (Quandl
+/// leaves missing values empty). Fields are named after `column_names` as done by `field_names`
+/// and are documented with the original column name.
+///
+/// The struct derives `Deserialize`, which must thus be in scope where it is included (e.g.
+/// through `#[macro_use] extern crate serde_derive;`). Rows are decoded by position, so the query
+/// must not select a subset of the columns with `column_index`.
+///
+pub fn generate_row_struct(metadata: &DatasetMetadata, struct_name: &str) -> String {
+    let doc = format!("Row of the Quandl dataset `{}/{}`: {}",
+                      metadata.database_code,
+                      metadata.dataset_code,
+                      single_line(&metadata.name));
+
+    let fields: Vec<(String, String)> = {
+        metadata.column_names.iter().enumerate().map(|(index, column)| {
+            let kind = if index == 0 { "String" } else { "Option<f64>" };
+            (single_line(column), kind.to_string())
+        }).collect()
+    };
+
+    row_struct(&doc, struct_name, &field_names(&metadata.column_names), &fields)
+}
+
+/// Same as `generate_row_struct`, from a `Schema` (e.g. inferred by `DataQuery::infer_schema`)
+/// rather than the dataset's metadata.
+///
+/// `Date` and `Text` columns are `String`s and `Number` columns `f64`s, wrapped in an `Option`
+/// when nullable. Unnamed columns are documented and named after their position.
+///
+pub fn generate_schema_struct(schema: &Schema, struct_name: &str) -> String {
+    let names: Vec<&str> = schema.columns.iter().map(|x| x.name.as_deref().unwrap_or("")).collect();
+
+    let fields: Vec<(String, String)> = {
+        schema.columns.iter().enumerate().map(|(index, column)| {
+            let doc = {
+                match column.name {
+                    Some(ref name) => single_line(name),
+                    None => format!("Column {} (unnamed).", index),
+                }
+            };
+
+            let kind = {
+                match column.inferred_type {
+                    ColType::Date | ColType::Text => "String",
+                    ColType::Number => "f64",
+                }
+            };
+
+            if column.nullable {
+                (doc, format!("Option<{}>", kind))
+            } else {
+                (doc, kind.to_string())
+            }
+        }).collect()
+    };
+
+    row_struct("Row of a Quandl dataset, as inferred from a sample of its rows.",
+               struct_name,
+               &field_names(&names),
+               &fields)
+}
+
+/// Definition of the struct `struct_name` documented by `doc`, whose fields are named by `names`
+/// and documented and typed by `fields`.
+///
+fn row_struct(doc: &str, struct_name: &str, names: &[String], fields: &[(String, String)])
+    -> String
+{
+    let mut code = String::new();
+
+    code.push_str(&format!("/// {}\n", doc));
+    code.push_str("///\n");
+    code.push_str("#[derive(Debug, Clone, PartialEq, Deserialize)]\n");
+    code.push_str(&format!("pub struct {} {{\n", struct_name));
+
+    for (index, (name, (doc, kind))) in names.iter().zip(fields).enumerate() {
+        if index > 0 {
+            code.push('\n');
+        }
+
+        code.push_str(&format!("    /// {}\n", doc));
+        code.push_str("    ///\n");
+        code.push_str(&format!("    pub {}: {},\n", name, kind));
+    }
+
+    code.push_str("}\n");
+    code
+}
+
+/// Turn column names into distinct `snake_case` identifiers usable as field names.
+///
+/// Words are split on anything which is not an ASCII letter or digit (accented letters included)
+/// and on case changes, e.g. `"Adj. Close"` and `"AdjClose"` both become `adj_close`. `%`, `#`,
+/// `&` and `+` are spelled out as `pct`, `num`, `and` and `plus`, names starting with a digit are
+/// prefixed by `col_`, keywords get a trailing underscore (`type_`) and names without any usable
+/// character are replaced by their position (`column_3`). When several columns end up with the
+/// same name, the later ones are suffixed by `_2`, `_3`, etc.
+///
+pub fn field_names<S: AsRef<str>>(column_names: &[S]) -> Vec<String> {
+    let mut taken = HashSet::new();
+
+    column_names.iter().enumerate().map(|(index, column)| {
+        let name = identifier(column.as_ref(), index);
+        let mut unique = name.clone();
+        let mut n = 2;
+
+        while !taken.insert(unique.clone()) {
+            unique = format!("{}_{}", name.trim_end_matches('_'), n);
+            n += 1;
+        }
+
+        unique
+    }).collect()
+}
+
+fn identifier(column: &str, index: usize) -> String {
+    let words = words(column);
+
+    let name = {
+        if words.is_empty() {
+            format!("column_{}", index)
+        } else {
+            words.join("_")
+        }
+    };
+
+    if name.starts_with(|x: char| x.is_ascii_digit()) {
+        format!("col_{}", name)
+    } else if KEYWORDS.contains(&&name[..]) {
+        format!("{}_", name)
+    } else {
+        name
+    }
+}
+
+/// Lowercase words of `column`, split as described by `field_names`.
+///
+fn words(column: &str) -> Vec<String> {
+    let mut words = vec![];
+    let mut word = String::new();
+    let chars: Vec<char> = column.chars().collect();
+
+    for (index, &x) in chars.iter().enumerate() {
+        let spelled = {
+            match x {
+                '%' => Some("pct"),
+                '#' => Some("num"),
+                '&' => Some("and"),
+                '+' => Some("plus"),
+                _ => None,
+            }
+        };
+
+        if !x.is_ascii_alphanumeric() {
+            if !word.is_empty() {
+                words.push(word.split_off(0));
+            }
+
+            if let Some(spelled) = spelled {
+                words.push(spelled.to_string());
+            }
+
+            continue;
+        }
+
+        // A new word starts at `aB` and at the `B` of `ABc` (as in `PERatio`).
+        let boundary = x.is_ascii_uppercase() && index > 0 && {
+            let previous = chars[index - 1];
+            let next = chars.get(index + 1).cloned().unwrap_or(' ');
+
+            previous.is_ascii_lowercase() ||
+                (previous.is_ascii_uppercase() && next.is_ascii_lowercase())
+        };
+
+        if boundary && !word.is_empty() {
+            words.push(word.split_off(0));
+        }
+
+        word.push(x.to_ascii_lowercase());
+    }
+
+    if !word.is_empty() {
+        words.push(word);
+    }
+
+    words
+}
+
+/// `s` with its control characters (e.g. line breaks) replaced by spaces, to fit in a comment.
+///
+fn single_line(s: &str) -> String {
+    s.chars().map(|x| if x.is_control() { ' ' } else { x }).collect::<String>().trim().to_string()
+}
@@ -0,0 +1,145 @@
+use std::collections::BTreeMap;
+
+use crate::types::Code;
+
+/// Codes of a database arranged by the segments of their dataset codes, as returned by
+/// `group_by_prefix`.
+///
+/// A path designates a node of the tree: its segments joined by the tree's delimiter (e.g.
+/// `"GDP_Q"` with `'_'`), the empty path being the root. Children are kept sorted by segment.
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PrefixTree {
+    delimiter: char,
+    root: PrefixNode,
+}
+
+/// A node of a `PrefixTree`, holding the codes whose dataset code ends at it.
+///
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct PrefixNode {
+    datasets: Vec<Code>,
+    children: BTreeMap<String, PrefixNode>,
+    count: usize,
+}
+
+/// Arrange `codes` in a tree keyed on the segments of their dataset codes, split on `delimiter`.
+///
+/// A dataset code without the delimiter is a single segment, hence a child of the root. The codes
+/// at each node keep the order they have in `codes`.
+///
+pub fn group_by_prefix(codes: &[Code], delimiter: char) -> PrefixTree {
+    let mut root = PrefixNode::default();
+
+    for code in codes {
+        let mut node = &mut root;
+        node.count += 1;
+
+        for segment in code.dataset_code.split(delimiter) {
+            node = node.children.entry(segment.to_string()).or_insert_with(PrefixNode::default);
+            node.count += 1;
+        }
+
+        node.datasets.push(code.clone());
+    }
+
+    PrefixTree { delimiter, root }
+}
+
+impl PrefixTree {
+    /// The delimiter the dataset codes were split on.
+    ///
+    pub fn delimiter(&self) -> char {
+        self.delimiter
+    }
+
+    /// The node of the empty path, under which every code is.
+    ///
+    pub fn root(&self) -> &PrefixNode {
+        &self.root
+    }
+
+    /// The node at `path`, if any code has it as prefix (or is `path` itself).
+    ///
+    pub fn node(&self, path: &str) -> Option<&PrefixNode> {
+        if path.is_empty() {
+            return Some(&self.root);
+        }
+
+        path.split(self.delimiter).try_fold(&self.root, |node, segment| node.child(segment))
+    }
+
+    /// Segments of the children of the node at `path`, in order; none if there is no such node.
+    ///
+    pub fn children(&self, path: &str) -> Vec<&str> {
+        match self.node(path) {
+            Some(node) => node.children().map(|(segment, _)| segment).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Codes at or under the node at `path`, depth first with children in order.
+    ///
+    pub fn datasets_under(&self, path: &str) -> Vec<&Code> {
+        let mut datasets = Vec::new();
+
+        if let Some(node) = self.node(path) {
+            datasets.reserve(node.count);
+            node.collect(&mut datasets);
+        }
+
+        datasets
+    }
+
+    /// Number of codes at or under the node at `path`.
+    ///
+    pub fn count(&self, path: &str) -> usize {
+        self.node(path).map(PrefixNode::count).unwrap_or(0)
+    }
+
+    /// Number of codes in the tree.
+    ///
+    pub fn len(&self) -> usize {
+        self.root.count
+    }
+
+    /// Whether or not the tree is without any code.
+    ///
+    pub fn is_empty(&self) -> bool {
+        self.root.count == 0
+    }
+}
+
+impl PrefixNode {
+    /// Codes whose dataset code ends at this node.
+    ///
+    pub fn datasets(&self) -> &[Code] {
+        &self.datasets
+    }
+
+    /// Children of this node, with their segment, in order.
+    ///
+    pub fn children(&self) -> impl Iterator<Item = (&str, &PrefixNode)> {
+        self.children.iter().map(|(segment, node)| (segment.as_str(), node))
+    }
+
+    /// The child of this node for `segment`, if any.
+    ///
+    pub fn child(&self, segment: &str) -> Option<&PrefixNode> {
+        self.children.get(segment)
+    }
+
+    /// Number of codes at or under this node.
+    ///
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    fn collect<'a>(&'a self, datasets: &mut Vec<&'a Code>) {
+        datasets.extend(&self.datasets);
+
+        for child in self.children.values() {
+            child.collect(datasets);
+        }
+    }
+}
@@ -0,0 +1,204 @@
+/// A column name split into what the column holds and the unit of its values, as parsed by
+/// `ColumnInfo::parse`, e.g. from the `column_names` of a dataset (see `DatasetMetadata::columns`).
+///
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ColumnInfo {
+    /// The name without its unit, e.g. `Value` for `Value (USD mm)`, or the whole (trimmed) name
+    /// when no unit was found in it.
+    ///
+    pub base_name: String,
+
+    /// The unit, as written in the name, e.g. `USD mm` for `Value (USD mm)`.
+    ///
+    pub unit: Option<String>,
+
+    /// The name as given.
+    ///
+    pub raw: String,
+}
+
+/// ISO 4217 codes of the currencies (and metals) recognized as units, see `ColumnInfo::parse`.
+///
+const CURRENCY_CODES: &[&str] = &[
+    "AED", "ARS", "AUD", "BRL", "CAD", "CHF", "CLP", "CNH", "CNY", "COP", "CZK", "DKK", "EGP",
+    "EUR", "GBP", "HKD", "HUF", "IDR", "ILS", "INR", "JPY", "KES", "KRW", "MXN", "MYR", "NGN",
+    "NOK", "NZD", "PEN", "PHP", "PKR", "PLN", "RUB", "SAR", "SEK", "SGD", "THB", "TRY", "TWD",
+    "UAH", "USD", "VND", "XAG", "XAU", "ZAR",
+];
+
+/// Currency symbols recognized as units, see `ColumnInfo::parse`.
+///
+const CURRENCY_SYMBOLS: &[&str] = &["$", "US$", "€", "£", "¥"];
+
+/// Words (in lower case) naming units: scales, rates, weights, volumes and the like.
+///
+const UNIT_WORDS: &[&str] = &[
+    "%", "percent", "pct", "bp", "bps", "points", "index",
+    "k", "000s", "'000", "thousand", "thousands", "m", "mm", "mn", "mln", "million", "millions",
+    "bn", "bln", "billion", "billions", "trillion", "trillions",
+    "units", "lcu", "g", "grams", "kg", "kgs", "t", "tonnes", "tons", "mt", "kt", "oz", "ounces",
+    "lb", "lbs", "pounds", "bbl", "barrels", "bu", "bushels", "gal", "gallons", "litres", "liters",
+    "btu", "mmbtu", "kwh", "mwh", "gwh", "mw", "ha", "hectares", "acres", "km",
+];
+
+/// Words (in lower case) qualifying units, only part of one along a word of `UNIT_WORDS`, a
+/// currency or an index base, e.g. `per` in `USD per barrel` or `current` in `current US$`.
+///
+const QUALIFIERS: &[&str] = &[
+    "of", "per", "in", "a", "current", "constant", "nominal", "real", "ppp", "sa", "nsa",
+    "annualized", "troy", "basis", "barrel", "ounce", "tonne", "ton", "day", "week", "month",
+    "year", "days", "weeks", "months", "years",
+];
+
+impl ColumnInfo {
+    /// Split `name` into its base name and its unit, along the following rules:
+    ///
+    /// * A name ending with parentheses holds a unit in them when every word they enclose (split
+    ///   on spaces, commas and slashes) is either a currency (an ISO 4217 code in upper case like
+    ///   `USD`, or a symbol like `$` or `US$`), a unit (`%`, `mm`, `bn`, `thousands`, `kg`, `bbl`,
+    ///   etc.), an index base (`2010=100`), a year or a qualifier (`per`, `of`, `current`, etc.),
+    ///   with at least one which is not a qualifier nor a year: `Value (USD mm)`, `Rate (%)`,
+    ///   `Price (USD/bbl)` or `GDP (constant 2010 US$)`, but not `Price (Close)`.
+    ///
+    /// * A name ending with a percent sign is in percent, unless the sign follows a digit:
+    ///   `Rate %` and `Yield%`, but not `Top 10%`.
+    ///
+    /// * A name whose last word is a currency code or symbol (see above) is in that currency,
+    ///   the word being possibly preceded by `in`: `Price USD`, `Value in EUR` or `Close, $`.
+    ///
+    /// The rules are tried in that order. Separators (`,`, `-`, `:` and `;`) left at the end of
+    /// the base name are dropped, and a name which would be left without a base name (e.g. `USD`
+    /// or `(%)`) is kept whole without a unit. Anything else, in particular any parenthesized word
+    /// which is unknown, is not a unit: names are left whole rather than split wrongly.
+    ///
+    pub fn parse(name: &str) -> ColumnInfo {
+        let raw = name.to_string();
+        let name = name.trim();
+
+        let split = {
+            parenthesized(name)
+                .or_else(|| percent(name))
+                .or_else(|| currency(name))
+                .map(|(base_name, unit)| (base_name.trim_end_matches(is_separator).trim(), unit))
+                .filter(|(base_name, unit)| !base_name.is_empty() && !unit.is_empty())
+        };
+
+        match split {
+            Some((base_name, unit)) => {
+                ColumnInfo {
+                    base_name: base_name.to_string(),
+                    unit: Some(unit.to_string()),
+                    raw,
+                }
+            },
+
+            None => ColumnInfo { base_name: name.to_string(), unit: None, raw },
+        }
+    }
+}
+
+/// `name` split before its trailing parentheses, if they enclose a unit.
+///
+fn parenthesized(name: &str) -> Option<(&str, &str)> {
+    let inner = name.strip_suffix(')')?;
+    let open = inner.rfind('(')?;
+    let unit = inner[open + 1..].trim();
+
+    if unit.contains(')') {
+        return None;
+    }
+
+    let words: Vec<&str> = {
+        unit.split(|c: char| c.is_whitespace() || c == ',' || c == '/')
+            .filter(|word| !word.is_empty())
+            .collect()
+    };
+
+    let known = words.iter().all(|word| is_unit(word) || is_qualifier(word));
+
+    if known && words.iter().any(|word| is_unit(word)) {
+        // `Price (in USD)` is in `USD`.
+        let unit = {
+            match unit.split_once(char::is_whitespace) {
+                Some((word, rest)) if word.eq_ignore_ascii_case("in") => rest.trim_start(),
+                _ => unit,
+            }
+        };
+
+        Some((&name[..open], unit))
+    } else {
+        None
+    }
+}
+
+/// `name` split before its trailing percent sign, unless it follows a digit.
+///
+fn percent(name: &str) -> Option<(&str, &str)> {
+    let base_name = name.strip_suffix('%')?;
+
+    match base_name.chars().last() {
+        Some(c) if c.is_ascii_digit() => None,
+        _ => Some((base_name, "%")),
+    }
+}
+
+/// `name` split before its last word, if it is a currency, along with a preceding `in`.
+///
+fn currency(name: &str) -> Option<(&str, &str)> {
+    let start = name.rfind(|c: char| c.is_whitespace() || c == ',').map_or(0, |i| i + 1);
+    let unit = &name[start..];
+
+    if !is_currency(unit) {
+        return None;
+    }
+
+    let base_name = name[..start].trim_end();
+
+    match base_name.rsplit_once(char::is_whitespace) {
+        Some((before, word)) if word.eq_ignore_ascii_case("in") => Some((before, unit)),
+        None if base_name.eq_ignore_ascii_case("in") => None,
+        _ => Some((base_name, unit)),
+    }
+}
+
+fn is_currency(word: &str) -> bool {
+    CURRENCY_CODES.contains(&word) || CURRENCY_SYMBOLS.contains(&word)
+}
+
+/// Whether `word` names a unit, a currency or the base of an index (e.g. `2010=100`).
+///
+fn is_unit(word: &str) -> bool {
+    let lowercase = word.trim_end_matches('.').to_lowercase();
+
+    is_currency(word) || UNIT_WORDS.contains(&&lowercase[..]) || {
+        match word.split_once('=') {
+            Some((base, "100")) => is_year(base) || is_years(base),
+            _ => false,
+        }
+    }
+}
+
+fn is_qualifier(word: &str) -> bool {
+    QUALIFIERS.contains(&&word.to_lowercase()[..]) || is_year(word)
+}
+
+fn is_year(word: &str) -> bool {
+    word.len() == 4 && word.chars().all(|c| c.is_ascii_digit())
+}
+
+/// Whether `word` is a range of years such as `1982-84`, as found in the bases of indexes.
+///
+fn is_years(word: &str) -> bool {
+    match word.split_once('-') {
+        Some((first, last)) => {
+            is_year(first) && (last.len() == 2 || last.len() == 4) &&
+                last.chars().all(|c| c.is_ascii_digit())
+        },
+
+        None => false,
+    }
+}
+
+fn is_separator(c: char) -> bool {
+    c.is_whitespace() || c == ',' || c == '-' || c == ':' || c == ';'
+}
@@ -0,0 +1,188 @@
+use has::{Has, HasMut};
+
+use crate::{Result, Error, ValidationError};
+use crate::api_call::ApiCall;
+use crate::batch_query::BatchQuery;
+use crate::merge::AlignedTable;
+use crate::parameters::*;
+use crate::query::{DataAndMetadataQuery, DataQuery, DatasetMetadataQuery};
+use crate::typed_fetch::LIMITS;
+use crate::types::{DataRow, Dataset, Order};
+
+/// Fetch the columns of the dataset of `query` at `indices` (see `DataParameters::column_index`),
+/// joined on their dates into a single table whose columns are in the order of `indices`.
+///
+/// Quandl selects at most one column per query, so the columns are either fetched with a call
+/// each, in a `BatchQuery` of `threads` threads rate limited with the limits of free API keys, or
+/// picked from the whole dataset fetched at once. Calls being what Quandl limits, the latter is
+/// cheaper as soon as more than one column is wanted. The metadata of the dataset is thus fetched
+/// first, which makes this take 2 calls whatever the number of columns: one for the metadata and
+/// one for the data.
+///
+/// Should the metadata be unavailable, the columns are fetched with a call each regardless, and a
+/// warning is logged when that takes more calls than fetching the whole dataset would.
+///
+/// The other parameters of `query` apply to every call, its own `column_index` aside. A query
+/// without an API key is sent anonymously (see `BatchQuery::anonymous`). The first error of any
+/// call fails the whole fetch, and so does an index beyond the last column of the dataset when
+/// its metadata tells as much (as an `Error::ValidationFailed`).
+///
+/// ```rust,no_run
+/// use quandl_v3::prelude::*;
+///
+/// let mut query = DataQuery::new("WIKI", "AAPL");
+/// query.api_key("KEY").start_date(2016, 1, 1);
+///
+/// // The opening and closing prices.
+/// let table = fetch_columns(&query, &[1, 4], 2).unwrap();
+///
+/// for row in table.rows {
+///     println!("{}: {:?}", row.date, row.values);
+/// }
+/// ```
+///
+pub fn fetch_columns(query: &DataQuery, indices: &[usize], threads: usize) -> Result<AlignedTable> {
+    assert!(!indices.is_empty(), "indices: {:?}", indices);
+    assert!(threads > 0, "threads: {}", threads);
+
+    for &index in indices {
+        assert!(index > 0, "indices: 0 is the date column, which is always returned; the first \
+                            data column is 1.");
+    }
+
+    let order = Has::<DataArguments>::get_ref(query).order.unwrap_or(Order::desc);
+
+    let metadata = {
+        let mut metadata_query = DatasetMetadataQuery::new(query.database_code(),
+                                                           query.dataset_code());
+
+        *HasMut::<ApiArguments>::get_mut(&mut metadata_query) = {
+            Has::<ApiArguments>::get_ref(query).clone()
+        };
+
+        metadata_query.send()
+    };
+
+    let column_names = {
+        match metadata {
+            Ok(metadata) => metadata.column_names,
+
+            Err(e) => {
+                if indices.len() > 1 {
+                    log::warn!("fetching {} columns of {}/{} takes a call each, while fetching the \
+                                whole dataset once would take one: its metadata is unavailable \
+                                ({}).",
+                               indices.len(),
+                               query.database_code(),
+                               query.dataset_code(),
+                               e);
+                }
+
+                return by_column(query, indices, threads, order);
+            },
+        }
+    };
+
+    let beyond: Vec<ValidationError> = {
+        indices.iter()
+            .filter(|&&index| index >= column_names.len())
+            .map(|index| {
+                ValidationError::new("column_index",
+                                     format!("{} is beyond the last column of {}/{}, {}.",
+                                             index,
+                                             query.database_code(),
+                                             query.dataset_code(),
+                                             column_names.len().saturating_sub(1)))
+            })
+            .collect()
+    };
+
+    if !beyond.is_empty() {
+        return Err(Error::ValidationFailed(beyond));
+    }
+
+    if indices.len() == 1 {
+        by_column(query, indices, threads, order)
+    } else {
+        projected(query, indices, &column_names, order)
+    }
+}
+
+/// Fetch each column with a call of its own.
+///
+fn by_column(query: &DataQuery, indices: &[usize], threads: usize, order: Order)
+    -> Result<AlignedTable>
+{
+    let mut batch_query = BatchQuery::new();
+    batch_query.threads(threads).anonymous();
+
+    for &(limit, seconds) in &LIMITS {
+        batch_query.limit(limit, seconds);
+    }
+
+    for &index in indices {
+        let mut column_query = {
+            DataAndMetadataQuery::new(query.database_code(), query.dataset_code())
+        };
+
+        *HasMut::<ApiArguments>::get_mut(&mut column_query) = {
+            Has::<ApiArguments>::get_ref(query).clone()
+        };
+
+        *HasMut::<DataArguments>::get_mut(&mut column_query) = {
+            Has::<DataArguments>::get_ref(query).clone()
+        };
+
+        column_query.column_index(index);
+        batch_query.query(column_query);
+    }
+
+    let mut column_names = vec![];
+    let mut columns = vec![];
+
+    for dataset in batch_query.run() {
+        let dataset: Dataset<(String, Option<f64>)> = dataset?;
+        let mut names = dataset.metadata.column_names.into_iter();
+
+        // Quandl lists the date and the selected column.
+        let date = names.next().unwrap_or_default();
+        let name = names.next().unwrap_or_default();
+
+        if column_names.is_empty() {
+            column_names.push(date);
+        }
+
+        column_names.push(name);
+        columns.push(dataset.rows);
+    }
+
+    Ok(AlignedTable::align(column_names, columns, order))
+}
+
+/// Fetch the whole dataset and pick the columns from it.
+///
+fn projected(query: &DataQuery, indices: &[usize], column_names: &[String], order: Order)
+    -> Result<AlignedTable>
+{
+    let mut query = query.clone();
+    HasMut::<DataArguments>::get_mut(&mut query).clear_column_index();
+
+    let rows: Vec<DataRow> = query.send()?;
+
+    // Only the dates with a value, as when the columns are fetched on their own.
+    let columns = {
+        indices.iter().map(|&index| {
+            rows.iter()
+                .filter_map(|row| {
+                    let value = row.values.get(index - 1).cloned().flatten()?;
+                    Some((row.date.clone(), Some(value)))
+                })
+                .collect()
+        }).collect()
+    };
+
+    let mut names = vec![column_names[0].clone()];
+    names.extend(indices.iter().map(|&index| column_names[index].clone()));
+
+    Ok(AlignedTable::align(names, columns, order))
+}
@@ -0,0 +1,284 @@
+use std::fmt;
+
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::ApiErrorResponse;
+use crate::types::{DatabaseMetadata, DatasetMetadata, SearchMetadata};
+
+/// Kind of JSON payload, named after the endpoint serving it.
+///
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PayloadKind {
+    /// `/databases/{database_code}.json`, decoded as a `DatabaseMetadata`.
+    ///
+    DatabaseMetadata,
+
+    /// `/datasets/{database_code}/{dataset_code}/metadata.json`, decoded as a `DatasetMetadata`.
+    ///
+    DatasetMetadata,
+
+    /// `/databases.json`, decoded as a `DatabaseList`.
+    ///
+    DatabaseList,
+
+    /// `/datasets.json`, decoded as a `DatasetList`.
+    ///
+    DatasetList,
+
+    /// `/datasets/{database_code}/{dataset_code}.json`, decoded as a `Dataset`.
+    ///
+    Dataset,
+
+    /// The body of Quandl's unsuccessful replies, decoded as an `ApiErrorResponse`.
+    ///
+    ErrorResponse,
+}
+
+impl PayloadKind {
+    /// Every kind of payload, in the order of their declaration.
+    ///
+    pub const ALL: [PayloadKind; 6] = [
+        PayloadKind::DatabaseMetadata,
+        PayloadKind::DatasetMetadata,
+        PayloadKind::DatabaseList,
+        PayloadKind::DatasetList,
+        PayloadKind::Dataset,
+        PayloadKind::ErrorResponse,
+    ];
+
+    /// Name of this kind in snake case, e.g. `"database_metadata"`, as serialized.
+    ///
+    pub fn name(&self) -> &'static str {
+        match *self {
+            PayloadKind::DatabaseMetadata => "database_metadata",
+            PayloadKind::DatasetMetadata  => "dataset_metadata",
+            PayloadKind::DatabaseList     => "database_list",
+            PayloadKind::DatasetList      => "dataset_list",
+            PayloadKind::Dataset          => "dataset",
+            PayloadKind::ErrorResponse    => "error_response",
+        }
+    }
+
+    /// The kind whose name is `name`, see `name`.
+    ///
+    pub fn from_name(name: &str) -> Option<Self> {
+        PayloadKind::ALL.iter().cloned().find(|kind| kind.name() == name)
+    }
+
+    /// Kind of the payload a successful reply to `url` holds, if it is one of a JSON endpoint,
+    /// e.g. to check the payloads of an `archive::Archive` by the URLs of its entries.
+    ///
+    pub fn of_url<S: AsRef<str>>(url: S) -> Option<Self> {
+        let url = url::Url::parse(url.as_ref()).ok()?;
+        let segments: Vec<&str> = url.path_segments()?.collect();
+
+        match *segments {
+            [.., "databases.json"] => Some(PayloadKind::DatabaseList),
+            [.., "datasets.json"] => Some(PayloadKind::DatasetList),
+            [.., "databases", code] if code.ends_with(".json") => {
+                Some(PayloadKind::DatabaseMetadata)
+            },
+            [.., "datasets", _, _, "metadata.json"] => Some(PayloadKind::DatasetMetadata),
+            [.., "datasets", _, code] if code.ends_with(".json") => Some(PayloadKind::Dataset),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for PayloadKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A part of a payload which this version of the crate fails to decode, see `check_payload`.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Incompatibility {
+    /// Where the part is in the payload, as a JSON pointer (e.g. `/datasets/3/name`), or empty
+    /// when the payload as a whole is not decoded.
+    ///
+    pub path: String,
+
+    /// Why the part is not decoded, as told by the decoder.
+    ///
+    pub message: String,
+}
+
+impl Incompatibility {
+    fn new<S: ToString>(path: &str, message: S) -> Self {
+        Incompatibility { path: path.to_string(), message: message.to_string() }
+    }
+}
+
+impl fmt::Display for Incompatibility {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "payload: {}", self.message)
+        } else {
+            write!(f, "{}: {}", self.path, self.message)
+        }
+    }
+}
+
+/// Check whether `payload`, a payload of the given kind, decodes with the types of this version
+/// of the crate, listing every part of it which does not.
+///
+/// This is meant to be run against payloads archived with an earlier version of the crate (e.g.
+/// those of an `archive::Archive`), to tell before upgrading to this one whether replaying them
+/// would still work. The elements of listings are checked one by one, and the fields of objects
+/// pinpointed when the decoder tells which one failed, so that the incompatibilities of a payload
+/// are all listed rather than only the first.
+///
+/// An empty list means the payload decodes as it did when sent by Quandl.
+///
+pub fn check_payload(kind: PayloadKind, payload: &[u8]) -> Vec<Incompatibility> {
+    let mut incompatibilities = vec![];
+
+    let value = {
+        match crate::download::json::<Value>(payload) {
+            Ok(value) => value,
+
+            Err(e) => {
+                incompatibilities.push(Incompatibility::new("", e));
+                return incompatibilities;
+            },
+        }
+    };
+
+    let found = &mut incompatibilities;
+
+    match kind {
+        PayloadKind::DatabaseMetadata => single::<DatabaseMetadata>(&value, found),
+        PayloadKind::DatasetMetadata => single::<DatasetMetadata>(&value, found),
+        PayloadKind::DatabaseList => listing::<DatabaseMetadata>(&value, "databases", found),
+        PayloadKind::DatasetList => listing::<DatasetMetadata>(&value, "datasets", found),
+        PayloadKind::Dataset => dataset(&value, found),
+        PayloadKind::ErrorResponse => check::<ApiErrorResponse>(value, "", found),
+    }
+
+    incompatibilities
+}
+
+/// Check the object of a metadata payload, its single element.
+///
+fn single<T: DeserializeOwned>(value: &Value, found: &mut Vec<Incompatibility>) {
+    match value {
+        Value::Object(object) if object.len() == 1 => {
+            for (key, value) in object {
+                check::<T>(value.clone(), &pointer("", key), found);
+            }
+        },
+
+        Value::Object(object) => {
+            found.push(Incompatibility::new("", format!("expected a single element, got {}.",
+                                                        object.len())));
+        },
+
+        _ => found.push(Incompatibility::new("", "expected an object.")),
+    }
+}
+
+/// Check the `meta` of a listing, and each of the elements listed under `key`.
+///
+fn listing<T: DeserializeOwned>(value: &Value, key: &str, found: &mut Vec<Incompatibility>) {
+    let object = {
+        match value.as_object() {
+            Some(object) => object,
+            None => return found.push(Incompatibility::new("", "expected an object.")),
+        }
+    };
+
+    match object.get("meta") {
+        Some(meta) => check::<SearchMetadata>(meta.clone(), "/meta", found),
+        None => found.push(Incompatibility::new("", "missing field `meta`")),
+    }
+
+    match object.get(key) {
+        Some(Value::Array(elements)) => {
+            for (index, element) in elements.iter().enumerate() {
+                check::<T>(element.clone(), &pointer(&pointer("", key), &index.to_string()), found);
+            }
+        },
+
+        Some(_) => found.push(Incompatibility::new(&pointer("", key), "expected an array.")),
+        None => found.push(Incompatibility::new("", format!("missing field `{}`", key))),
+    }
+}
+
+/// Check the metadata of a dataset and its rows, which this crate decodes apart: the rows are
+/// only required to be arrays, their values being decoded into a type of the caller's.
+///
+fn dataset(value: &Value, found: &mut Vec<Incompatibility>) {
+    let mut dataset = {
+        match value.get("dataset") {
+            Some(Value::Object(dataset)) => dataset.clone(),
+            Some(_) => return found.push(Incompatibility::new("/dataset", "expected an object.")),
+            None => return found.push(Incompatibility::new("", "missing field `dataset`")),
+        }
+    };
+
+    match dataset.remove("data") {
+        Some(Value::Array(rows)) => {
+            for (index, row) in rows.iter().enumerate() {
+                if !row.is_array() {
+                    found.push(Incompatibility::new(&format!("/dataset/data/{}", index),
+                                                    "expected an array."));
+                }
+            }
+        },
+
+        Some(Value::Null) | None => (),
+        Some(_) => found.push(Incompatibility::new("/dataset/data", "expected an array.")),
+    }
+
+    check::<DatasetMetadata>(Value::Object(dataset), "/dataset", found);
+}
+
+/// Decode `value`, found at `path`, as a `T`.
+///
+/// Decoding errors only tell which field failed by their line in a textual payload, so `value`
+/// is decoded from its pretty-printed text, where each of its fields is on a line of its own.
+///
+fn check<T: DeserializeOwned>(value: Value, path: &str, found: &mut Vec<Incompatibility>) {
+    let text = serde_json::to_string_pretty(&value).expect("values serialize");
+
+    let e = {
+        match serde_json::from_str::<T>(&text) {
+            Ok(_) => return,
+            Err(e) => e,
+        }
+    };
+
+    // The position is that in the pretty-printed text, meaningless to the caller.
+    let message = e.to_string();
+    let message = message.rsplit_once(" at line ").map_or(&message[..], |(message, _)| message);
+
+    let field = {
+        text.lines()
+            .take(e.line())
+            .filter(|line| line.starts_with("  \"") && !line.starts_with("   "))
+            .filter_map(|line| serde_json::from_str::<String>(line.trim().split(": ").next()?).ok())
+            .last()
+    };
+
+    // Missing fields are reported at the end of the object lacking them, past its last field.
+    let located = value.is_object() && e.line() > 1 && !message.starts_with("missing field");
+
+    let path = {
+        match field {
+            Some(ref field) if located => pointer(path, field),
+            _ => path.to_string(),
+        }
+    };
+
+    found.push(Incompatibility::new(&path, message));
+}
+
+/// `path` followed by `token`, escaped as JSON pointers require.
+///
+fn pointer(path: &str, token: &str) -> String {
+    format!("{}/{}", path, token.replace('~', "~0").replace('/', "~1"))
+}
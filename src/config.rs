@@ -0,0 +1,143 @@
+use std::cell::RefCell;
+use std::marker::PhantomData;
+use std::sync::{Arc, OnceLock, PoisonError, RwLock};
+
+use crate::ApiKey;
+use crate::client::ClientConfig;
+
+/// Defaults of the queries created from then on, process-wide or within a scope.
+///
+/// Every query starts out with the API key, base URL, HTTP client, response size limit and code
+/// normalization of the configuration current on the thread creating it (see `current`), which
+/// its `ApiParameters` then override. Since they are captured at creation, queries sent from
+/// other threads (e.g. by a `BatchQuery`) keep the defaults of the thread which created them.
+///
+/// ```rust
+/// use quandl_v3::config::Config;
+/// use quandl_v3::prelude::*;
+///
+/// let _guard = Config::override_scope(Config {
+///     api_key: Some(ApiKey::new("KEY")),
+///     .. Config::default()
+/// });
+///
+/// assert!(DatabaseMetadataQuery::new("WIKI").url().ends_with("?api_key=KEY"));
+/// ```
+///
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Config {
+    /// Default of `ApiParameters::api_key`.
+    ///
+    pub api_key: Option<ApiKey>,
+
+    /// Default of `ApiParameters::base_url`.
+    ///
+    pub base_url: Option<String>,
+
+    /// Default of `ApiParameters::client_config`.
+    ///
+    pub client: Option<ClientConfig>,
+
+    /// Default of `ApiParameters::max_response_bytes`.
+    ///
+    pub max_response_bytes: Option<u64>,
+
+    /// Default of `ApiParameters::auto_uppercase`.
+    ///
+    pub auto_uppercase: bool,
+
+    /// Seed of the reproducibility mode, if it is on, see `deterministic`.
+    ///
+    pub seed: Option<u64>,
+}
+
+/// Restores the configuration which was current before `Config::override_scope` when dropped.
+///
+/// Guards are bound to the thread which created them. Dropping one also ends the scopes opened
+/// after it on that thread which are still alive.
+///
+#[derive(Debug)]
+#[must_use = "the override ends as soon as the guard is dropped"]
+pub struct Guard {
+    depth: usize,
+    _not_send: PhantomData<*const ()>,
+}
+
+static GLOBAL: OnceLock<RwLock<Arc<Config>>> = OnceLock::new();
+
+thread_local! {
+    /// The scoped overrides of this thread, innermost last.
+    static SCOPES: RefCell<Vec<Arc<Config>>> = const { RefCell::new(vec![]) };
+}
+
+fn global() -> &'static RwLock<Arc<Config>> {
+    GLOBAL.get_or_init(|| RwLock::new(Arc::new(Config::default())))
+}
+
+impl Config {
+    /// The configuration in effect on this thread: that of its innermost override scope, if any,
+    /// otherwise the global one (empty unless set with `set_global`).
+    ///
+    pub fn current() -> Arc<Config> {
+        if let Some(config) = SCOPES.with(|scopes| scopes.borrow().last().cloned()) {
+            return config;
+        }
+
+        global().read().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+
+    /// Configuration of the reproducibility mode, seeded with `seed`: two runs making the same
+    /// calls with the same seed then make them the same way, e.g. to reproduce a bug report.
+    ///
+    /// Whatever would otherwise be random is drawn from `seed` instead (i.e. the jitter of
+    /// `Backoff::delays`), and batch queries make their calls one at a time in the order of their
+    /// queries, on a single thread, whatever their `threads` and `scheduling`. This makes the
+    /// calls slower, so this mode is meant for debugging rather than production. The other
+    /// settings are the defaults, to be set along with the seed as needed:
+    ///
+    /// ```rust
+    /// use quandl_v3::ApiKey;
+    /// use quandl_v3::config::Config;
+    ///
+    /// let _guard = Config::override_scope(Config {
+    ///     api_key: Some(ApiKey::new("KEY")),
+    ///     .. Config::deterministic(42)
+    /// });
+    /// ```
+    ///
+    pub fn deterministic(seed: u64) -> Self {
+        Config { seed: Some(seed), .. Config::default() }
+    }
+
+    /// Replace the process-wide configuration, returning the previous one.
+    ///
+    /// Threads within an override scope keep the configuration of that scope until it ends.
+    ///
+    pub fn set_global(config: Config) -> Arc<Config> {
+        let mut global = global().write().unwrap_or_else(PoisonError::into_inner);
+        ::std::mem::replace(&mut *global, Arc::new(config))
+    }
+
+    /// Make `config` the current configuration of this thread, and only this thread, until the
+    /// returned guard is dropped. Scopes nest: once the inner one ends, the outer one applies
+    /// again.
+    ///
+    /// A scope replaces the configuration as a whole; start from `Config::current()` to only
+    /// override some of its settings.
+    ///
+    pub fn override_scope(config: Config) -> Guard {
+        let depth = SCOPES.with(|scopes| {
+            let mut scopes = scopes.borrow_mut();
+            scopes.push(Arc::new(config));
+            scopes.len() - 1
+        });
+
+        Guard { depth, _not_send: PhantomData }
+    }
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        SCOPES.with(|scopes| scopes.borrow_mut().truncate(self.depth));
+    }
+}
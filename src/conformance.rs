@@ -0,0 +1,508 @@
+use crate::{Result, Error};
+use crate::api_call::ApiCall;
+use crate::parameters::*;
+use crate::query::*;
+use crate::queue::QuerySpec;
+use crate::types::{DatabaseList, DatabaseMetadata, Dataset, DatasetList, DatasetMetadata,
+                   DatasetMetadataLite, Frequency, Order, Transform};
+
+/// The vectors published with this crate, those of `tests/url_vectors.json`.
+///
+const VECTORS: &str = include_str!("../tests/url_vectors.json");
+
+/// A query configuration and the canonical URL it must be sent to.
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Vector {
+    /// What the vector covers, unique among the vectors.
+    ///
+    pub name: String,
+
+    /// The configuration of the query.
+    ///
+    pub query: QueryConfig,
+
+    /// The query as persisted by a `DownloadQueue`, i.e. its URL without any API key, which is
+    /// the canonical URL of the query.
+    ///
+    pub expected: QuerySpec,
+}
+
+/// Kind of query of a `QueryConfig`, named after the query types of this crate.
+///
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryType {
+    DatabaseMetadata,
+    DatasetMetadata,
+    DatabaseSearch,
+    DatasetSearch,
+    DatasetListing,
+    CodeList,
+    DatabaseDownload,
+    Data,
+    DataAndMetadata,
+}
+
+/// Description of a query independent of any implementation: its type, codes and parameters,
+/// each parameter being named after the method of this crate setting it.
+///
+/// Parameters left out are not set. Setting one which does not apply to the type of the query
+/// (e.g. `rows` on a `database_metadata` query) is an error, as are unknown fields.
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct QueryConfig {
+    /// Type of the query.
+    ///
+    #[serde(rename = "type")]
+    pub query_type: QueryType,
+
+    /// Code of the database, for every query but `database_search` and those given an `id`.
+    ///
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub database_code: Option<String>,
+
+    /// Code of the dataset, for the queries of a dataset not given by `id`.
+    ///
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dataset_code: Option<String>,
+
+    /// Numerical identifier of the dataset, see e.g. `DataQuery::by_id`.
+    ///
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<usize>,
+
+    /// See `ApiParameters::api_key`.
+    ///
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+
+    /// See `ApiParameters::base_url`.
+    ///
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,
+
+    /// See `ApiParameters::api_version`.
+    ///
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_version: Option<String>,
+
+    /// See `ApiParameters::auto_uppercase`.
+    ///
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub auto_uppercase: bool,
+
+    /// Keywords given to `SearchParameters::query`, before any phrase or exclusion.
+    ///
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub query: Vec<String>,
+
+    /// Phrases given to `SearchParameters::phrase`, in order.
+    ///
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub phrases: Vec<String>,
+
+    /// Terms given to `SearchParameters::exclude`, after the phrases.
+    ///
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude: Vec<String>,
+
+    /// See `SearchParameters::per_page`.
+    ///
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub per_page: Option<usize>,
+
+    /// See `SearchParameters::page`.
+    ///
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page: Option<usize>,
+
+    /// See `DatasetListingQuery::sort_by_last_updated`.
+    ///
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub sort_by_last_updated: bool,
+
+    /// See `DatabaseDownloadQuery::partial`.
+    ///
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub partial: bool,
+
+    /// See `DataParameters::rows`.
+    ///
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rows: Option<usize>,
+
+    /// See `DataParameters::limit`.
+    ///
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+
+    /// See `DataParameters::order`.
+    ///
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub order: Option<Order>,
+
+    /// See `DataParameters::collapse`.
+    ///
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub collapse: Option<Frequency>,
+
+    /// See `DataParameters::transform`.
+    ///
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transform: Option<Transform>,
+
+    /// See `DataParameters::start_date_str`, as `YYYY-MM-DD`.
+    ///
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_date: Option<String>,
+
+    /// See `DataParameters::end_date_str`, as `YYYY-MM-DD`.
+    ///
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end_date: Option<String>,
+
+    /// See `DataParameters::column_index`.
+    ///
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub column_index: Option<usize>,
+}
+
+/// A vector which an implementation does not conform to, see `run_with`.
+///
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Mismatch {
+    /// Name of the vector.
+    ///
+    pub name: String,
+
+    /// What the vector expects.
+    ///
+    pub expected: QuerySpec,
+
+    /// What the implementation built instead, or why it could not build the query.
+    ///
+    pub actual: ::std::result::Result<QuerySpec, Error>,
+}
+
+fn is_false(x: &bool) -> bool {
+    !*x
+}
+
+/// The vectors published with this crate, also found in `tests/url_vectors.json` of its
+/// repository.
+///
+pub fn vectors() -> Vec<Vector> {
+    serde_json::from_str(VECTORS).expect("the published vectors parse, as tests/conformance.rs \
+                                          checks")
+}
+
+/// Check this crate's query builders against `vectors`, returning the mismatches, see `run_with`.
+///
+pub fn run(vectors: &[Vector]) -> Vec<Mismatch> {
+    run_with(vectors, QueryConfig::spec)
+}
+
+/// Check the implementation `build`, turning query configurations into query specs, against
+/// `vectors`, returning the vectors it does not conform to (none if it conforms to all of them).
+///
+/// This is how code generating Quandl URLs by other means than this crate's builders (e.g. from
+/// templates) makes sure it agrees with them.
+///
+pub fn run_with<F>(vectors: &[Vector], mut build: F) -> Vec<Mismatch>
+    where F: FnMut(&QueryConfig) -> Result<QuerySpec>
+{
+    vectors.iter()
+        .filter_map(|vector| {
+            let actual = build(&vector.query);
+
+            match actual {
+                Ok(ref spec) if *spec == vector.expected => None,
+
+                actual => {
+                    Some(Mismatch {
+                        name: vector.name.clone(),
+                        expected: vector.expected.clone(),
+                        actual,
+                    })
+                },
+            }
+        })
+        .collect()
+}
+
+impl QueryConfig {
+    /// Configuration of a query of type `query_type` without any code nor parameter.
+    ///
+    pub fn new(query_type: QueryType) -> Self {
+        QueryConfig {
+            query_type,
+            database_code: None,
+            dataset_code: None,
+            id: None,
+            api_key: None,
+            base_url: None,
+            api_version: None,
+            auto_uppercase: false,
+            query: vec![],
+            phrases: vec![],
+            exclude: vec![],
+            per_page: None,
+            page: None,
+            sort_by_last_updated: false,
+            partial: false,
+            rows: None,
+            limit: None,
+            order: None,
+            collapse: None,
+            transform: None,
+            start_date: None,
+            end_date: None,
+            column_index: None,
+        }
+    }
+
+    /// Build the query described with this crate's builders, as its spec.
+    ///
+    /// This fails when a code the query needs is missing, a parameter does not apply to it or a
+    /// value is invalid (e.g. a date which does not exist).
+    ///
+    pub fn spec(&self) -> Result<QuerySpec> {
+        let url = {
+            match self.query_type {
+                QueryType::DatabaseMetadata => {
+                    self.applies(&[])?;
+                    let query = self.api(DatabaseMetadataQuery::new(self.database_code()?));
+                    ApiCall::<DatabaseMetadata>::parsed_url(&query)?
+                },
+
+                QueryType::DatasetMetadata => {
+                    self.applies(&["id"])?;
+
+                    let query = {
+                        self.dataset(DatasetMetadataQuery::new, DatasetMetadataQuery::by_id)?
+                    };
+
+                    ApiCall::<DatasetMetadata>::parsed_url(&self.api(query))?
+                },
+
+                QueryType::DatabaseSearch => {
+                    self.applies(SEARCH)?;
+
+                    if self.database_code.is_some() {
+                        return Err(self.error("database_code"));
+                    }
+
+                    let query = self.search(self.api(DatabaseSearch::new()));
+                    ApiCall::<DatabaseList>::parsed_url(&query)?
+                },
+
+                QueryType::DatasetSearch => {
+                    self.applies(SEARCH)?;
+                    let query = self.search(self.api(DatasetSearch::new(self.database_code()?)));
+                    ApiCall::<DatasetList>::parsed_url(&query)?
+                },
+
+                QueryType::DatasetListing => {
+                    self.applies(&[SEARCH, &["sort_by_last_updated"]].concat())?;
+
+                    let mut query = {
+                        self.search(self.api(DatasetListingQuery::new(self.database_code()?)))
+                    };
+
+                    if self.sort_by_last_updated {
+                        query.sort_by_last_updated();
+                    }
+
+                    ApiCall::<Vec<DatasetMetadataLite>>::parsed_url(&query)?
+                },
+
+                QueryType::CodeList => {
+                    self.applies(&[])?;
+                    self.api(CodeListQuery::new(self.database_code()?)).parsed_url()?
+                },
+
+                QueryType::DatabaseDownload => {
+                    self.applies(&["partial"])?;
+                    let mut query = self.api(DatabaseDownloadQuery::new(self.database_code()?));
+
+                    if self.partial {
+                        query.partial();
+                    }
+
+                    query.parsed_url()?
+                },
+
+                QueryType::Data => {
+                    self.applies(&[DATA, &["id"]].concat())?;
+                    let query = self.dataset(DataQuery::new, DataQuery::by_id)?;
+                    ApiCall::<Vec<Vec<String>>>::parsed_url(&self.data(self.api(query))?)?
+                },
+
+                QueryType::DataAndMetadata => {
+                    self.applies(&[DATA, &["id"]].concat())?;
+                    let query = {
+                        self.dataset(DataAndMetadataQuery::new, DataAndMetadataQuery::by_id)?
+                    };
+
+                    let query = self.data(self.api(query))?;
+                    ApiCall::<Dataset<Vec<String>>>::parsed_url(&query)?
+                },
+            }
+        };
+
+        Ok(QuerySpec::from_url(&url))
+    }
+
+    /// Fail unless every parameter set, but those of every query (see `ApiParameters`), is among
+    /// `parameters`.
+    ///
+    fn applies(&self, parameters: &[&str]) -> Result<()> {
+        let set = {
+            [
+                ("id", self.id.is_some()),
+                ("query", !self.query.is_empty()),
+                ("phrases", !self.phrases.is_empty()),
+                ("exclude", !self.exclude.is_empty()),
+                ("per_page", self.per_page.is_some()),
+                ("page", self.page.is_some()),
+                ("sort_by_last_updated", self.sort_by_last_updated),
+                ("partial", self.partial),
+                ("rows", self.rows.is_some()),
+                ("limit", self.limit.is_some()),
+                ("order", self.order.is_some()),
+                ("collapse", self.collapse.is_some()),
+                ("transform", self.transform.is_some()),
+                ("start_date", self.start_date.is_some()),
+                ("end_date", self.end_date.is_some()),
+                ("column_index", self.column_index.is_some()),
+            ]
+        };
+
+        match set.iter().find(|&&(name, set)| set && !parameters.contains(&name)) {
+            Some(&(name, _)) => Err(self.error(name)),
+            None => Ok(()),
+        }
+    }
+
+    fn error(&self, parameter: &str) -> Error {
+        Error::ParsingFailed(format!("parameter '{}' does not apply to a {:?} query.",
+                                     parameter,
+                                     self.query_type))
+    }
+
+    fn database_code(&self) -> Result<&str> {
+        match self.database_code {
+            Some(ref code) => Ok(code),
+            None => Err(Error::ParsingFailed(format!("a {:?} query needs a database_code.",
+                                                     self.query_type))),
+        }
+    }
+
+    /// The query of a dataset, given by its codes (with `new`) or its `id` (with `by_id`).
+    ///
+    fn dataset<'a, Q, N, I>(&'a self, new: N, by_id: I) -> Result<Q>
+        where N: FnOnce(&'a str, &'a str) -> Q,
+              I: FnOnce(usize) -> Q,
+    {
+        match (self.id, &self.database_code, &self.dataset_code) {
+            (Some(id), None, None) => Ok(by_id(id)),
+            (None, Some(database_code), Some(dataset_code)) => Ok(new(database_code, dataset_code)),
+
+            _ => Err(Error::ParsingFailed(format!("a {:?} query needs either an id or both a \
+                                                   database_code and a dataset_code.",
+                                                  self.query_type))),
+        }
+    }
+
+    fn api<Q: ApiParameters>(&self, mut query: Q) -> Q {
+        // Whatever the current `Config`, only what is configured here applies.
+        *::has::HasMut::<ApiArguments>::get_mut(&mut query) = ApiArguments::default();
+
+        if let Some(ref api_key) = self.api_key {
+            query.api_key(api_key);
+        }
+
+        if let Some(ref base_url) = self.base_url {
+            query.base_url(base_url);
+        }
+
+        if let Some(ref api_version) = self.api_version {
+            query.api_version(api_version);
+        }
+
+        query.auto_uppercase(self.auto_uppercase);
+        query
+    }
+
+    fn search<Q: SearchParameters>(&self, mut query: Q) -> Q {
+        if !self.query.is_empty() {
+            query.query(&self.query);
+        }
+
+        for phrase in &self.phrases {
+            query.phrase(phrase);
+        }
+
+        for term in &self.exclude {
+            query.exclude(term);
+        }
+
+        if let Some(n) = self.per_page {
+            query.per_page(n);
+        }
+
+        if let Some(n) = self.page {
+            query.page(n);
+        }
+
+        query
+    }
+
+    fn data<Q: DataParameters>(&self, mut query: Q) -> Result<Q> {
+        if let Some(n) = self.rows {
+            query.rows(n);
+        }
+
+        if let Some(n) = self.limit {
+            query.limit(n);
+        }
+
+        if let Some(order) = self.order {
+            query.order(order);
+        }
+
+        if let Some(collapse) = self.collapse {
+            query.collapse(collapse);
+        }
+
+        if let Some(transform) = self.transform {
+            query.transform(transform);
+        }
+
+        if let Some(ref date) = self.start_date {
+            query.start_date_str(date)?;
+        }
+
+        if let Some(ref date) = self.end_date {
+            query.end_date_str(date)?;
+        }
+
+        if let Some(index) = self.column_index {
+            query.column_index(index);
+        }
+
+        Ok(query)
+    }
+}
+
+/// Parameters of the search queries.
+///
+const SEARCH: &[&str] = &["query", "phrases", "exclude", "per_page", "page"];
+
+/// Parameters of the data queries.
+///
+const DATA: &[&str] = {
+    &["rows", "limit", "order", "collapse", "transform", "start_date", "end_date", "column_index"]
+};
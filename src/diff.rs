@@ -0,0 +1,196 @@
+use std::collections::BTreeMap;
+use std::io::Read;
+
+use crate::api_call::checked_body;
+use crate::download::CSV;
+use crate::query::DataQuery;
+
+use crate::{Result, Error};
+
+/// A row of a dataset: its date (`YYYY-MM-DD`) and the value of each of its other columns.
+///
+/// Missing values (empty CSV fields, JSON `null`) are represented by `NaN`.
+///
+pub type Row = (String, Vec<f64>);
+
+/// How close two values must be to be considered unchanged.
+///
+/// In both cases, two `NaN` (i.e. two missing values) are considered equal while a `NaN` and a
+/// number are not. Infinite values are only equal to the same infinity.
+///
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Tolerance {
+    /// Values are equal when `|old - new| <= tolerance`.
+    ///
+    Absolute(f64),
+
+    /// Values are equal when `|old - new| <= tolerance * max(|old|, |new|)`, e.g. `0.01` accepts
+    /// differences of up to 1%.
+    ///
+    Relative(f64),
+}
+
+impl Tolerance {
+    /// Whether or not `old` and `new` are considered equal under this tolerance.
+    ///
+    pub fn accepts(self, old: f64, new: f64) -> bool {
+        if old.is_nan() || new.is_nan() {
+            return old.is_nan() && new.is_nan();
+        }
+
+        if old.is_infinite() || new.is_infinite() {
+            return old == new;
+        }
+
+        let difference = (old - new).abs();
+
+        match self {
+            Tolerance::Absolute(tolerance) => difference <= tolerance,
+            Tolerance::Relative(tolerance) => difference <= tolerance * old.abs().max(new.abs()),
+        }
+    }
+}
+
+/// A value which differs between two pulls of a dataset.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValueChange {
+    /// Date of the row.
+    ///
+    pub date: String,
+
+    /// Index of the column within the row's values, i.e. 0 is the first column after the date.
+    ///
+    pub column: usize,
+
+    /// Value in the old pull; `NaN` if it was missing.
+    ///
+    pub old: f64,
+
+    /// Value in the new pull; `NaN` if it is missing.
+    ///
+    pub new: f64,
+}
+
+/// Differences between two pulls of a dataset, sorted by date.
+///
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DatasetDiff {
+    /// Dates only present in the new pull.
+    ///
+    pub added: Vec<String>,
+
+    /// Dates only present in the old pull.
+    ///
+    pub removed: Vec<String>,
+
+    /// Values of the dates present in both pulls which changed beyond the tolerance.
+    ///
+    pub changed: Vec<ValueChange>,
+}
+
+impl DatasetDiff {
+    /// Whether or not both pulls are equivalent.
+    ///
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Compare two pulls of the same dataset with an absolute `tolerance`, see `compare_with`.
+///
+pub fn compare(old: &[Row], new: &[Row], tolerance: f64) -> DatasetDiff {
+    compare_with(old, new, Tolerance::Absolute(tolerance))
+}
+
+/// Compare two pulls of the same dataset.
+///
+/// Rows are matched by date, whatever their order. When the rows of a date do not have the same
+/// number of values, the missing ones are considered `NaN`. If a date appears more than once in
+/// a pull, its last row is used.
+///
+pub fn compare_with(old: &[Row], new: &[Row], tolerance: Tolerance) -> DatasetDiff {
+    let (old, new) = (by_date(old), by_date(new));
+    let mut diff = DatasetDiff::default();
+
+    for (date, old_values) in &old {
+        let new_values = {
+            match new.get(date) {
+                Some(new_values) => new_values,
+
+                None => {
+                    diff.removed.push(date.to_string());
+                    continue;
+                },
+            }
+        };
+
+        for column in 0..old_values.len().max(new_values.len()) {
+            let old = old_values.get(column).cloned().unwrap_or(f64::NAN);
+            let new = new_values.get(column).cloned().unwrap_or(f64::NAN);
+
+            if !tolerance.accepts(old, new) {
+                diff.changed.push(ValueChange { date: date.to_string(), column, old, new });
+            }
+        }
+    }
+
+    diff.added = {
+        new.keys().filter(|date| !old.contains_key(*date)).map(|date| date.to_string()).collect()
+    };
+
+    diff
+}
+
+/// Download the current data of `query` and compare it to a `prior` pull of it.
+///
+/// The query should request the columns in the same way as when `prior` was obtained (e.g. the
+/// same `column_index` and `transform`), otherwise every value would be reported as changed.
+///
+pub fn compare_fetched(query: &DataQuery, prior: &[Row], tolerance: Tolerance)
+    -> Result<DatasetDiff>
+{
+    let current = parse_csv(&checked_body::<Vec<Row>, _>(query, CSV)?[..])?;
+    Ok(compare_with(prior, &current, tolerance))
+}
+
+/// Parse rows stored as CSV, in the format returned by Quandl without column names (i.e. the date
+/// followed by the values, empty fields for missing values).
+///
+pub fn parse_csv<R: Read>(reader: R) -> Result<Vec<Row>> {
+    let mut reader = {
+        csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(reader)
+    };
+
+    let mut rows = vec![];
+
+    for record in reader.deserialize() {
+        match record {
+            Ok((date, values)) => rows.push(from_options(date, values)),
+            Err(e) => return Err(Error::from(e)),
+        }
+    }
+
+    Ok(rows)
+}
+
+/// Parse rows stored as JSON, as an array of `[date, [values...]]` pairs like the one produced by
+/// serializing a `Vec<Row>` (missing values as `null`).
+///
+pub fn parse_json<R: Read>(reader: R) -> Result<Vec<Row>> {
+    match serde_json::from_reader::<_, Vec<(String, Vec<Option<f64>>)>>(reader) {
+        Ok(rows) => Ok(rows.into_iter().map(|(date, values)| from_options(date, values)).collect()),
+        Err(e) => Err(Error::from(e)),
+    }
+}
+
+fn by_date(rows: &[Row]) -> BTreeMap<&str, &[f64]> {
+    rows.iter().map(|(date, values)| (&date[..], &values[..])).collect()
+}
+
+fn from_options(date: String, values: Vec<Option<f64>>) -> Row {
+    (date, values.into_iter().map(|x| x.unwrap_or(f64::NAN)).collect())
+}
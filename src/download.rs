@@ -1,39 +1,1069 @@
-use std::io::Read;
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::error::Error as StdError;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, OnceLock, PoisonError};
+use std::time::{Duration, Instant};
 
-use reqwest;
-use serde_json;
+use bytes::Bytes;
 
-use crate::{Result, Error};
+use crate::{Result, ApiErrorResponse, Error, DownloadError, DownloadErrorKind};
+use crate::client::{self, ClientConfig};
 
-pub fn download<S: AsRef<str>>(url: S) -> Result<Vec<u8>> {
-    let (body, is_success) = {
-        match reqwest::blocking::get(url.as_ref()) {
+/// Content types accepted for JSON payloads.
+///
+pub const JSON: &[&str] = &["application/json"];
+
+/// Content types accepted for CSV payloads.
+///
+pub const CSV: &[&str] = &["text/csv", "application/csv", "text/plain"];
+
+/// A successful response from the server.
+///
+/// The body is received once and shared from then on: cloning the response (e.g. for every
+/// caller of a coalesced request) or slicing its body does not copy it.
+///
+#[derive(Clone)]
+pub struct Response {
+    pub content_type: Option<String>,
+    pub body: Bytes,
+}
+
+impl Response {
+    /// Return the body given the response's `Content-Type` is one of `expected`.
+    ///
+    /// Parameters such as `charset` are ignored, and so is a missing `Content-Type` header since
+    /// there is nothing to check then.
+    ///
+    pub fn expect_content_type(self, expected: &[&str]) -> Result<Bytes> {
+        if let Some(ref content_type) = self.content_type {
+            let mime = content_type.split(';').next().unwrap_or("").trim().to_lowercase();
+
+            if !expected.iter().any(|x| *x == mime) {
+                let snippet = String::from_utf8_lossy(&self.body[..self.body.len().min(64)]);
+
+                return Err(Error::ParsingFailed(format!("expected {}, got {}; body starts with: {}",
+                                                        expected[0],
+                                                        content_type,
+                                                        snippet.trim())));
+            }
+        }
+
+        Ok(self.body)
+    }
+}
+
+thread_local! {
+    static RECEIVED_BYTES: Cell<u64> = const { Cell::new(0) };
+    static DEADLINE: Cell<Option<Instant>> = const { Cell::new(None) };
+    static QUOTA: Cell<Option<Quota>> = const { Cell::new(None) };
+}
+
+/// Calls left to the API key of a request, and when they are reset if known, as reported by the
+/// `X-RateLimit-Remaining` and `X-RateLimit-Reset` headers of its response.
+///
+pub(crate) type Quota = (usize, Option<chrono::DateTime<chrono::Utc>>);
+
+/// Number of body bytes received by `Request::fetch` on the current thread since the last call to
+/// this function.
+///
+#[cfg(feature = "batch")]
+pub fn take_received_bytes() -> u64 {
+    RECEIVED_BYTES.with(|bytes| bytes.replace(0))
+}
+
+/// The quota reported by the last response received by `Request::fetch` on the current thread
+/// which reported one, since the last call to this function.
+///
+#[cfg(feature = "batch")]
+pub(crate) fn take_quota() -> Option<Quota> {
+    QUOTA.with(Cell::take)
+}
+
+/// Run `f`, giving up on every request it sends from the current thread at `deadline` (or at the
+/// earlier deadline already in effect, if any).
+///
+/// Each request is only sent if the deadline has not passed yet, with what is left of the budget
+/// as its timeout, so compound calls (pagination, range requests...) are bounded as a whole.
+///
+pub fn with_deadline<R, F: FnOnce() -> R>(deadline: Instant, f: F) -> R {
+    struct Restore(Option<Instant>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            DEADLINE.with(|x| x.set(self.0));
+        }
+    }
+
+    let previous = DEADLINE.with(Cell::get);
+    let _restore = Restore(previous);
+
+    DEADLINE.with(|x| x.set(Some(previous.map_or(deadline, |x| x.min(deadline)))));
+    f()
+}
+
+/// Time left until the deadline in effect on the current thread, if any, or
+/// `Error::DeadlineExceeded` if it already passed.
+///
+fn remaining_budget() -> Result<Option<Duration>> {
+    match DEADLINE.with(Cell::get) {
+        Some(deadline) => {
+            let now = Instant::now();
+
+            if now >= deadline {
+                Err(Error::DeadlineExceeded)
+            } else {
+                Ok(Some(deadline - now))
+            }
+        },
+
+        None => Ok(None),
+    }
+}
+
+/// `Error::DeadlineExceeded` in place of `error` if the deadline in effect on the current thread
+/// passed, since the request then most likely timed out because of it.
+///
+fn or_deadline_exceeded(error: Error) -> Error {
+    match remaining_budget() {
+        Err(deadline_exceeded) => deadline_exceeded,
+        Ok(_) => error,
+    }
+}
+
+/// An HTTP request to be sent by a `Transport`.
+///
+/// Every call path builds one of these rather than passing its options along as parameters, so
+/// that supporting a new option only takes a new field.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct Request {
+    pub url: String,
+    pub method: reqwest::Method,
+    pub headers: Vec<(reqwest::header::HeaderName, String)>,
+    pub timeout: Option<Duration>,
+    pub config: Option<ClientConfig>,
+    pub max_bytes: Option<u64>,
+    pub database_code: Option<String>,
+}
+
+impl Request {
+    /// GET request to `url`, sent with a client configured by `config` or the default one.
+    ///
+    pub fn get<S: AsRef<str>>(url: S, config: Option<&ClientConfig>) -> Self {
+        Request {
+            url: url.as_ref().to_string(),
+            method: reqwest::Method::GET,
+            headers: vec![],
+            timeout: None,
+            config: config.cloned(),
+            max_bytes: None,
+            database_code: None,
+        }
+    }
+
+    pub fn header<S: AsRef<str>>(mut self, name: reqwest::header::HeaderName, value: S) -> Self {
+        self.headers.push((name, value.as_ref().to_string()));
+        self
+    }
+
+    /// Limit the body `fetch` receives to `max_bytes`, if any, see
+    /// `ApiParameters::max_response_bytes`.
+    ///
+    pub fn max_bytes(mut self, max_bytes: Option<u64>) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Report Quandl's refusal to serve premium data to a key which is not subscribed to its
+    /// database as an `Error::SubscriptionRequired` for `database_code`, if any.
+    ///
+    pub fn database_code<S: Into<String>>(mut self, database_code: Option<S>) -> Self {
+        self.database_code = database_code.map(Into::into);
+        self
+    }
+
+    /// Send the request and receive the whole body of a successful response.
+    ///
+    /// With a configuration coalescing requests, the result of the same request in flight on
+    /// another thread is awaited instead, see `ClientConfig::coalesce`. With one archiving the
+    /// payloads, the body is stored in the archive before it is returned, see
+    /// `ClientConfig::write_through`.
+    ///
+    pub fn fetch(&self) -> Result<Response> {
+        if self.config.as_ref().map(ClientConfig::is_coalescing).unwrap_or(false) {
+            coalesced(self, |request| fetch_archived(&HttpTransport, request))
+        } else {
+            fetch_archived(&HttpTransport, self)
+        }
+    }
+
+    /// Send the request and return the `Content-Length` of the response, if it is successful and
+    /// advertises one.
+    ///
+    pub fn content_length(&self) -> Result<Option<u64>> {
+        let response = HttpTransport.execute(self)?;
+
+        if !response.status().is_success() {
+            return Ok(None);
+        }
+
+        Ok(header(&response, reqwest::header::CONTENT_LENGTH).and_then(|x| x.parse().ok()))
+    }
+}
+
+/// `url` without its `api_key` parameter, the others being kept in order.
+///
+pub(crate) fn without_api_key(mut url: url::Url) -> String {
+    let pairs: Vec<(String, String)> = {
+        url.query_pairs().into_owned().filter(|(name, _)| name != "api_key").collect()
+    };
+
+    url.set_query(None);
+
+    if !pairs.is_empty() {
+        url.query_pairs_mut().extend_pairs(pairs);
+    }
+
+    url.to_string()
+}
+
+/// What sends requests over the network.
+///
+pub trait Transport {
+    fn execute(&self, request: &Request) -> Result<reqwest::blocking::Response>;
+}
+
+/// Transport sending requests with a `reqwest` client.
+///
+pub struct HttpTransport;
+
+impl Transport for HttpTransport {
+    fn execute(&self, request: &Request) -> Result<reqwest::blocking::Response> {
+        let config = request.config.as_ref();
+
+        let url = {
+            match (config, url::Url::parse(&request.url)) {
+                (Some(config), Ok(url)) if !config.fallbacks().is_empty() => url,
+                _ => return send(request, &request.url),
+            }
+        };
+
+        let hosts = config.map(|config| config.failover_group(&url)).unwrap_or_default();
+        let active = client::active_host(&hosts);
+        let mut failure = None;
+
+        // From the host which responded last, through the others in order.
+        for index in (active..hosts.len()).chain(0..active) {
+            match send(request, &client::with_origin(&url, &hosts[index])) {
+                Ok(response) => {
+                    client::set_active_host(&hosts, index);
+                    return Ok(response);
+                },
+
+                Err(e) if fails_over(&e) => {
+                    log::warn!("{} could not be reached, failing over: {}", hosts[index], e);
+                    failure = Some(e);
+                },
+
+                Err(e) => return Err(e),
+            }
+        }
+
+        Err(failure.expect("every host was tried"))
+    }
+}
+
+/// Send `request` to `url` (the URL of the request, or that of a fallback host).
+///
+/// The request is not sent if a token in effect on the current thread is cancelled, and
+/// abandoned if one is while waiting for the response, see `CancelToken`.
+///
+fn send(request: &Request, url: &str) -> Result<reqwest::blocking::Response> {
+    crate::cancel::check()?;
+
+    let budget = remaining_budget()?;
+    let config = request.config.as_ref();
+    let mut builder = client(config)?.request(request.method.clone(), url);
+
+    for (name, value) in &request.headers {
+        builder = builder.header(name.clone(), &value[..]);
+    }
+
+    let timeout = {
+        match (request.timeout, budget) {
+            (Some(timeout), Some(budget)) => Some(timeout.min(budget)),
+            (timeout, budget) => timeout.or(budget),
+        }
+    };
+
+    // Without one, the client's default timeout applies.
+    if let Some(timeout) = timeout {
+        builder = builder.timeout(timeout);
+    }
+
+    crate::cancel::interruptible(move || builder.send())?.map_err(|e| request_error(e, config))
+}
+
+/// Whether `error` means the host could not be reached, so another one should be tried, see
+/// `ClientConfig::fallback_hosts`.
+///
+fn fails_over(error: &Error) -> bool {
+    matches!(error.download_kind(),
+             Some(DownloadErrorKind::Dns) |
+             Some(DownloadErrorKind::Connect) |
+             Some(DownloadErrorKind::Tls) |
+             Some(DownloadErrorKind::UntrustedCertificate) |
+             Some(DownloadErrorKind::Timeout))
+}
+
+fn fetch_with<T: Transport>(transport: &T, request: &Request) -> Result<Response> {
+    let (body, content_type, status, retry_after) = {
+        match transport.execute(request) {
             Ok(mut response) => {
-                let mut body: Vec<u8> = vec![];
+                let advertised = {
+                    header(&response, reqwest::header::CONTENT_LENGTH)
+                        .and_then(|x| x.parse::<u64>().ok())
+                };
+
+                if let (Some(limit), Some(observed)) = (request.max_bytes, advertised) {
+                    if observed > limit {
+                        return Err(Error::ResponseTooLarge { limit, observed });
+                    }
+                }
+
+                if let Some(quota) = quota(&response) {
+                    QUOTA.with(|x| x.set(Some(quota)));
+                }
+
+                let max_bytes = request.max_bytes;
+
+                let (response, body, read) = {
+                    crate::cancel::interruptible(move || {
+                        let mut body: Vec<u8> = vec![];
+
+                        // One byte more than allowed is enough to tell the body is too large.
+                        let read = {
+                            match max_bytes {
+                                Some(limit) => {
+                                    (&mut response).take(limit.saturating_add(1)).read_to_end(&mut body)
+                                },
+
+                                None => response.read_to_end(&mut body),
+                            }
+                        };
 
-                if let Err(e) = response.read_to_end(&mut body) {
-                    return Err(Error::IoError(e.to_string()));
+                        (response, body, read)
+                    })?
+                };
+
+                RECEIVED_BYTES.with(|bytes| bytes.set(bytes.get() + body.len() as u64));
+
+                if let Err(e) = read {
+                    return Err(body_error(e));
                 }
 
-                (body, response.status().is_success())
+                if let Some(limit) = request.max_bytes {
+                    if body.len() as u64 > limit {
+                        return Err(Error::ResponseTooLarge { limit, observed: body.len() as u64 });
+                    }
+                }
+
+                let content_type = {
+                    response.headers()
+                        .get(reqwest::header::CONTENT_TYPE)
+                        .and_then(|value| value.to_str().ok())
+                        .map(|value| value.to_string())
+                };
+
+                (body, content_type, response.status(), retry_after(&response))
             },
 
-            Err(e) => return Err(Error::DownloadFailed(e.to_string())),
+            Err(e) => return Err(e),
         }
     };
 
-    if is_success {
-        Ok(body)
+    if status.is_success() {
+        Ok(Response { content_type, body: Bytes::from(body) })
     } else {
-        match String::from_utf8(body) {
-            Ok(encoded_data) => {
-                match serde_json::from_str(&encoded_data[..]) {
-                    Ok(api_error) => Err(Error::ApiCallFailed(api_error)),
-                    Err(e) => Err(Error::ParsingFailed(e.to_string())),
+        Err(response_error(request, status, retry_after, &body))
+    }
+}
+
+/// `fetch_with`, storing the body of the response in the archive of the request's configuration,
+/// if any, see `ClientConfig::write_through`.
+///
+fn fetch_archived<T: Transport>(transport: &T, request: &Request) -> Result<Response> {
+    let response = fetch_with(transport, request)?;
+
+    if let Some(archive) = request.config.as_ref().and_then(ClientConfig::archive) {
+        archive.store(&request.url, &response.body)?;
+    }
+
+    Ok(response)
+}
+
+/// Coalesced requests in flight, by `flight_key`.
+///
+static IN_FLIGHT: OnceLock<Mutex<HashMap<String, Arc<Flight>>>> = OnceLock::new();
+
+/// Result of a coalesced request, published by the thread sending it to those waiting for it.
+///
+#[derive(Default)]
+struct Flight {
+    result: Mutex<Option<Result<Response>>>,
+    done: Condvar,
+}
+
+impl Flight {
+    /// Wait for the result, up to the current deadline if any, or until a token in effect on the
+    /// current thread is cancelled.
+    ///
+    fn wait(&self) -> Result<Response> {
+        let mut result = self.result.lock().unwrap_or_else(PoisonError::into_inner);
+
+        while result.is_none() {
+            crate::cancel::check()?;
+
+            result = {
+                match crate::cancel::poll_interval(remaining_budget()?) {
+                    Some(budget) => {
+                        self.done.wait_timeout(result, budget)
+                            .unwrap_or_else(PoisonError::into_inner)
+                            .0
+                    },
+
+                    None => self.done.wait(result).unwrap_or_else(PoisonError::into_inner),
                 }
+            };
+        }
+
+        result.clone().expect("result published")
+    }
+}
+
+/// Publishes the result of a coalesced request when dropped, an error if the thread sending it
+/// panicked before it got one.
+///
+struct Landing {
+    key: String,
+    flight: Arc<Flight>,
+    result: Option<Result<Response>>,
+}
+
+impl Drop for Landing {
+    fn drop(&mut self) {
+        // Requests from now on are sent again.
+        in_flight().remove(&self.key);
+
+        let result = {
+            self.result.take().unwrap_or_else(|| {
+                let message = "the identical request this one was waiting for panicked.";
+                Err(Error::DownloadFailed(DownloadError::new(DownloadErrorKind::Other, message)))
+            })
+        };
+
+        *self.flight.result.lock().unwrap_or_else(PoisonError::into_inner) = Some(result);
+        self.flight.done.notify_all();
+    }
+}
+
+fn in_flight() -> MutexGuard<'static, HashMap<String, Arc<Flight>>> {
+    IN_FLIGHT.get_or_init(Default::default).lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// What identifies identical requests: their method, URL, headers and limit on the size of the
+/// response.
+///
+fn flight_key(request: &Request) -> String {
+    let mut key = format!("{} {}", request.method, request.url);
+
+    for (name, value) in &request.headers {
+        key.push_str(&format!("\n{}: {}", name, value));
+    }
+
+    if let Some(max_bytes) = request.max_bytes {
+        key.push_str(&format!("\n(at most {} bytes)", max_bytes));
+    }
+
+    key
+}
+
+/// Send `request` with `fetch`, or wait for the result of the identical request in flight if there
+/// is one.
+///
+fn coalesced<F>(request: &Request, fetch: F) -> Result<Response>
+    where F: FnOnce(&Request) -> Result<Response>
+{
+    let key = flight_key(request);
+
+    let (flight, is_leader) = {
+        let mut in_flight = in_flight();
+
+        match in_flight.get(&key) {
+            Some(flight) => (flight.clone(), false),
+
+            None => {
+                let flight = Arc::new(Flight::default());
+                in_flight.insert(key.clone(), flight.clone());
+                (flight, true)
             },
+        }
+    };
+
+    if !is_leader {
+        return flight.wait();
+    }
+
+    let mut landing = Landing { key, flight, result: None };
+    let result = fetch(request);
+    landing.result = Some(result.clone());
+    result
+}
+
+fn client(config: Option<&ClientConfig>) -> Result<reqwest::blocking::Client> {
+    match config {
+        Some(config) => config.build(),
+        None => reqwest::blocking::Client::builder().build().map_err(|e| request_error(e, None)),
+    }
+}
+
+/// Error for a request which could not be sent or got no response, or
+/// `Error::DeadlineExceeded` if it was given up because of the deadline.
+///
+fn request_error(e: reqwest::Error, config: Option<&ClientConfig>) -> Error {
+    let mut kind = classify(&e);
+
+    // The TLS connector only knows the pinned certificates, so any rejected chain is untrusted.
+    if kind == DownloadErrorKind::Tls && config.map(ClientConfig::is_pinned).unwrap_or(false) {
+        let mut source = StdError::source(&e);
+
+        while let Some(cause) = source {
+            if cause.to_string().to_lowercase().contains("certificate") {
+                kind = DownloadErrorKind::UntrustedCertificate;
+                break;
+            }
+
+            source = cause.source();
+        }
+    }
+
+    or_deadline_exceeded(Error::DownloadFailed(DownloadError::with_source(kind, e)))
+}
+
+/// Error for a response whose body could not be received entirely, or
+/// `Error::DeadlineExceeded` if it was given up because of the deadline.
+///
+fn body_error(e: io::Error) -> Error {
+    let kind = {
+        match e.kind() {
+            io::ErrorKind::TimedOut => DownloadErrorKind::Timeout,
+            _ => DownloadErrorKind::BodyRead,
+        }
+    };
+
+    or_deadline_exceeded(Error::DownloadFailed(DownloadError::with_source(kind, e)))
+}
+
+fn classify(e: &reqwest::Error) -> DownloadErrorKind {
+    if e.is_timeout() {
+        return DownloadErrorKind::Timeout;
+    }
+
+    if e.is_redirect() {
+        return DownloadErrorKind::TooManyRedirects;
+    }
+
+    if e.is_body() || e.is_decode() {
+        return DownloadErrorKind::BodyRead;
+    }
+
+    let mut source = StdError::source(e);
+
+    while let Some(cause) = source {
+        if let Some(e) = cause.downcast_ref::<io::Error>() {
+            if e.kind() == io::ErrorKind::TimedOut {
+                return DownloadErrorKind::Timeout;
+            }
+        }
+
+        // Messages of hyper's connector, which resolves the host and opens the TCP connection.
+        let message = cause.to_string();
+
+        if message.starts_with("dns error") {
+            return DownloadErrorKind::Dns;
+        }
+
+        if message.starts_with("tcp connect error") {
+            return DownloadErrorKind::Connect;
+        }
+
+        source = cause.source();
+    }
+
+    if e.is_connect() {
+        // Any other failure to connect comes from the TLS connector wrapping hyper's.
+        DownloadErrorKind::Tls
+    } else if e.is_request() {
+        // Connected, but the connection was reset or closed before a response was received.
+        DownloadErrorKind::Connect
+    } else {
+        DownloadErrorKind::Other
+    }
+}
+
+/// Decode a JSON payload as UTF-8.
+///
+pub fn utf8(body: &[u8]) -> Result<&str> {
+    ::std::str::from_utf8(body).map_err(|e| {
+        let valid = e.valid_up_to();
+        let snippet = String::from_utf8_lossy(&body[valid.saturating_sub(32)..valid]);
+
+        Error::JsonParsing { message: e.to_string(), snippet: snippet.trim().to_string() }
+    })
+}
+
+/// Decode a JSON payload straight from its bytes, without copying them into a string first.
+///
+/// Failures quote the payload around the line and column reported by `serde_json`, only then
+/// decoded as UTF-8: a payload which is not is reported as such, as by `utf8`.
+///
+pub fn json<T: serde::de::DeserializeOwned>(body: &[u8]) -> Result<T> {
+    serde_json::from_slice(body).map_err(|e| {
+        match utf8(body) {
+            Ok(encoded_data) => Error::json(&e, encoded_data),
+            Err(e) => e,
+        }
+    })
+}
+
+/// Error corresponding to the body of an unsuccessful response.
+///
+pub fn api_error(body: &[u8]) -> Error {
+    match ApiErrorResponse::decode(body) {
+        Ok(api_error) => Error::ApiCallFailed(api_error),
+        Err(e) => e,
+    }
+}
+
+/// Code of Quandl's error for a query of premium data by a key not subscribed to its database.
+///
+const SUBSCRIPTION_REQUIRED: &str = "QEPx04";
+
+/// Error corresponding to the unsuccessful response to `request`, received with `status`.
+///
+/// The statuses which tell what went wrong by themselves (429, 401, 403 and 404) are reported as
+/// such whatever the body, which is only kept if it happens to be one of Quandl's error
+/// responses: a proxy in front of Quandl may well reply with an HTML page.
+///
+fn response_error(request: &Request,
+                  status: reqwest::StatusCode,
+                  retry_after: Option<Duration>,
+                  body: &[u8]) -> Error
+{
+    let error = api_error(body);
+
+    let api_error = {
+        match error {
+            Error::ApiCallFailed(ref e) => Some(e.clone()),
+            _ => None,
+        }
+    };
+
+    let snippet = crate::body_snippet(body);
+    let code = api_error.as_ref().map(|e| e.quandl_error.code.as_str());
+
+    match (status.as_u16(), &request.database_code) {
+        (429, _) => Error::RateLimitExceeded { retry_after, api_error, snippet },
+
+        (403, Some(database_code)) if code == Some(SUBSCRIPTION_REQUIRED) => {
+            Error::SubscriptionRequired { database_code: database_code.clone() }
+        },
+
+        (status @ 401, _) | (status @ 403, _) => {
+            Error::AccessDenied { status, api_error, snippet }
+        },
+
+        (404, _) => Error::NotFound { api_error, snippet },
+        _ => error,
+    }
+}
+
+/// Wait advised by the `Retry-After` header of `response`, given either in seconds or as a date.
+///
+fn retry_after(response: &reqwest::blocking::Response) -> Option<Duration> {
+    let value = header(response, reqwest::header::RETRY_AFTER)?;
+    let value = value.trim();
+
+    match value.parse::<u64>() {
+        Ok(seconds) => Some(Duration::from_secs(seconds)),
+        Err(_) => {
+            let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+            let wait = date.signed_duration_since(chrono::Utc::now());
+            Some(wait.to_std().unwrap_or_default())
+        },
+    }
+}
+
+/// The quota reported by the headers of `response`, if any.
+///
+/// `X-RateLimit-Reset` is either the number of seconds until the reset or, from a billion, the
+/// instant of the reset as a Unix timestamp.
+///
+fn quota(response: &reqwest::blocking::Response) -> Option<Quota> {
+    let header = |name: &'static str| {
+        header(response, reqwest::header::HeaderName::from_static(name))
+            .and_then(|value| value.trim().parse::<i64>().ok())
+    };
+
+    let remaining = header("x-ratelimit-remaining").map(|x| x.max(0) as usize)?;
+
+    let reset = {
+        header("x-ratelimit-reset").and_then(|value| {
+            if value >= 1_000_000_000 {
+                chrono::DateTime::from_timestamp(value, 0)
+            } else {
+                Some(chrono::Utc::now() + chrono::Duration::seconds(value.max(0)))
+            }
+        })
+    };
+
+    Some((remaining, reset))
+}
+
+/// Same as `response_error`, reading the body of `response` first.
+///
+fn unsuccessful(request: &Request, response: reqwest::blocking::Response) -> Error {
+    let status = response.status();
+    let retry_after = retry_after(&response);
+
+    match read_body(response) {
+        Ok(body) => response_error(request, status, retry_after, &body),
+        Err(e) => e,
+    }
+}
+
+/// Size of the chunks whose checksums are recorded by `download_to_file_resumable`.
+///
+const RESUME_CHUNK_SIZE: u64 = 1 << 20;
+
+/// Progress of an interrupted download, stored next to the partial file.
+///
+/// `checksums` holds the Adler-32 checksum of every `RESUME_CHUNK_SIZE` bytes long chunk of the
+/// first `offset` bytes, the last one being shorter unless `offset` is a multiple of the size.
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct ResumeState {
+    url: String,
+    etag: Option<String>,
+    offset: u64,
+    checksums: Vec<u32>,
+}
+
+/// Download `url` to `path`, resuming where a previous interrupted call left off.
+///
+/// The data is written to `path` with a `.part` suffix, along with a `.part.json` sidecar
+/// recording how much of it was received. Once complete, the file is renamed to `path` and the
+/// size of the file is returned.
+///
+/// A resumed download asks for the data from the start of the last recorded chunk, whose checksum
+/// must match before anything is appended. The download restarts from scratch when that fails,
+/// when the server ignores the `Range` header or when the resource's `ETag` changed.
+///
+pub fn download_to_file_resumable<P: AsRef<Path>>(request: &Request, path: P) -> Result<u64> {
+    let path = path.as_ref();
+    let partial_path = with_suffix(path, ".part");
+    let state_path = with_suffix(path, ".part.json");
+
+    let state = {
+        read_state(&state_path).filter(|state| {
+            let length = fs::metadata(&partial_path).map(|metadata| metadata.len()).unwrap_or(0);
+            let chunks = state.offset.div_ceil(RESUME_CHUNK_SIZE);
+
+            state.url == request.url && state.offset > 0 && state.offset <= length &&
+                state.checksums.len() as u64 == chunks
+        })
+    };
+
+    let resumed = match state {
+        Some(state) => resume(request, &partial_path, &state_path, state)?,
+        None => None,
+    };
+
+    let length = match resumed {
+        Some(length) => length,
+        None => {
+            let response = HttpTransport.execute(request)?;
+
+            match response.status().as_u16() {
+                200 => restart(&request.url, &partial_path, &state_path, response)?,
+                _ => return Err(unsuccessful(request, response)),
+            }
+        },
+    };
+
+    fs::rename(&partial_path, path).map_err(|e| Error::IoError(e.to_string()))?;
+    let _ = fs::remove_file(&state_path);
+
+    Ok(length)
+}
+
+/// Continue the download described by `state`, returning `None` if it must be restarted.
+///
+fn resume(request: &Request, partial_path: &Path, state_path: &Path, state: ResumeState)
+    -> Result<Option<u64>>
+{
+    let overlap_start = (state.offset - 1) / RESUME_CHUNK_SIZE * RESUME_CHUNK_SIZE;
+    let overlap_length = (state.offset - overlap_start) as usize;
+    let expected = state.checksums.last().cloned();
+
+    let progress = Progress::reopen(partial_path, state_path, state.clone())?;
+
+    // The partial file itself may have been corrupted since it was written.
+    if Some(progress.checksum.value()) != expected {
+        return Ok(None);
+    }
+
+    let mut response = {
+        let mut ranged = request.clone().header(reqwest::header::RANGE, {
+            format!("bytes={}-", overlap_start)
+        });
+
+        if let Some(ref etag) = state.etag {
+            ranged = ranged.header(reqwest::header::IF_RANGE, etag);
+        }
 
-            Err(e) => Err(Error::ParsingFailed(e.to_string())),
+        HttpTransport.execute(&ranged)?
+    };
+
+    match response.status().as_u16() {
+        200 => {
+            drop(progress);
+            return restart(&request.url, partial_path, state_path, response).map(Some);
+        },
+
+        206 => (),
+        416 => return Ok(None),
+        _ => return Err(unsuccessful(request, response)),
+    }
+
+    let content_range_start = {
+        header(&response, reqwest::header::CONTENT_RANGE)
+            .and_then(|range| range.strip_prefix("bytes ").map(|x| x.to_string()))
+            .and_then(|range| range.split('-').next().and_then(|x| x.parse::<u64>().ok()))
+    };
+
+    let etag = header(&response, reqwest::header::ETAG);
+    let etag_changed = etag.is_some() && state.etag.is_some() && etag != state.etag;
+
+    if content_range_start != Some(overlap_start) || etag_changed {
+        return Ok(None);
+    }
+
+    let mut overlap = vec![0; overlap_length];
+
+    if let Err(e) = response.read_exact(&mut overlap) {
+        return Err(body_error(e));
+    }
+
+    let mut checksum = Adler32::new();
+    checksum.update(&overlap);
+
+    if Some(checksum.value()) != expected {
+        return Ok(None);
+    }
+
+    progress.copy_from(response).map(Some)
+}
+
+/// Write the whole body of `response` to the partial file, discarding any previous progress.
+///
+fn restart(url: &str, partial_path: &Path, state_path: &Path, response: reqwest::blocking::Response)
+    -> Result<u64>
+{
+    let state = ResumeState {
+        url: url.to_string(),
+        etag: header(&response, reqwest::header::ETAG),
+        offset: 0,
+        checksums: vec![],
+    };
+
+    Progress::create(partial_path, state_path, state)?.copy_from(response)
+}
+
+pub fn read_body(mut response: reqwest::blocking::Response) -> Result<Vec<u8>> {
+    let mut body = vec![];
+
+    match response.read_to_end(&mut body) {
+        Ok(_) => Ok(body),
+        Err(e) => Err(body_error(e)),
+    }
+}
+
+pub fn header(response: &reqwest::blocking::Response, name: reqwest::header::HeaderName)
+    -> Option<String>
+{
+    response.headers().get(name).and_then(|value| value.to_str().ok()).map(|x| x.to_string())
+}
+
+fn read_state(path: &Path) -> Option<ResumeState> {
+    fs::read(path).ok().and_then(|encoded| serde_json::from_slice(&encoded).ok())
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+/// Partial file being appended to, along with the checksums of what it holds.
+///
+struct Progress<'a> {
+    file: File,
+    state_path: &'a Path,
+    state: ResumeState,
+    checksums: Vec<u32>,
+    checksum: Adler32,
+}
+
+impl<'a> Progress<'a> {
+    fn create(partial_path: &Path, state_path: &'a Path, state: ResumeState) -> Result<Self> {
+        let file = File::create(partial_path).map_err(|e| Error::IoError(e.to_string()))?;
+
+        Ok(Progress { file, state_path, state, checksums: vec![], checksum: Adler32::new() })
+    }
+
+    /// Reopen the partial file at `state.offset`, recomputing the checksum of its last chunk.
+    ///
+    fn reopen(partial_path: &Path, state_path: &'a Path, state: ResumeState) -> Result<Self> {
+        let io_error = |e: io::Error| Error::IoError(e.to_string());
+
+        let mut file = {
+            OpenOptions::new().read(true).write(true).open(partial_path).map_err(io_error)?
+        };
+
+        file.set_len(state.offset).map_err(io_error)?;
+
+        let chunk_start = (state.offset - 1) / RESUME_CHUNK_SIZE * RESUME_CHUNK_SIZE;
+        let mut chunk = vec![0; (state.offset - chunk_start) as usize];
+
+        file.seek(SeekFrom::Start(chunk_start)).map_err(io_error)?;
+        file.read_exact(&mut chunk).map_err(io_error)?;
+
+        let mut checksum = Adler32::new();
+        checksum.update(&chunk);
+
+        let checksums = state.checksums[..state.checksums.len().saturating_sub(1)].to_vec();
+
+        Ok(Progress { file, state_path, state, checksums, checksum })
+    }
+
+    /// Append the rest of `response`'s body, recording the progress made if it is interrupted
+    /// (cancellation included, which is checked between the reads).
+    ///
+    fn copy_from(mut self, mut response: reqwest::blocking::Response) -> Result<u64> {
+        let mut buffer = vec![0; 64 * 1024];
+
+        loop {
+            if let Err(e) = crate::cancel::check() {
+                self.save()?;
+                return Err(e);
+            }
+
+            let read = match response.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(read) => read,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+
+                Err(e) => {
+                    self.save()?;
+                    return Err(body_error(e));
+                },
+            };
+
+            self.append(&buffer[..read])?;
         }
+
+        self.file.sync_all().map_err(|e| Error::IoError(e.to_string()))?;
+        Ok(self.state.offset)
+    }
+
+    fn append(&mut self, mut data: &[u8]) -> Result<()> {
+        while !data.is_empty() {
+            let room = RESUME_CHUNK_SIZE - self.state.offset % RESUME_CHUNK_SIZE;
+            let (head, tail) = data.split_at(data.len().min(room as usize));
+
+            self.file.write_all(head).map_err(|e| Error::IoError(e.to_string()))?;
+            self.checksum.update(head);
+            self.state.offset += head.len() as u64;
+
+            if self.state.offset.is_multiple_of(RESUME_CHUNK_SIZE) {
+                self.checksums.push(self.checksum.value());
+                self.checksum = Adler32::new();
+                self.save()?;
+            }
+
+            data = tail;
+        }
+
+        Ok(())
+    }
+
+    /// Persist the progress made so far, once the data it describes is on disk.
+    ///
+    fn save(&mut self) -> Result<()> {
+        let io_error = |e: io::Error| Error::IoError(e.to_string());
+
+        self.file.sync_data().map_err(io_error)?;
+
+        self.state.checksums = self.checksums.clone();
+
+        if !self.state.offset.is_multiple_of(RESUME_CHUNK_SIZE) {
+            self.state.checksums.push(self.checksum.value());
+        }
+
+        let encoded = {
+            serde_json::to_vec(&self.state).map_err(Error::from)?
+        };
+
+        let temporary = with_suffix(self.state_path, ".tmp");
+
+        fs::write(&temporary, encoded).map_err(io_error)?;
+        fs::rename(&temporary, self.state_path).map_err(io_error)
+    }
+}
+
+/// Adler-32 checksum, as used by zlib.
+///
+#[derive(Debug, Copy, Clone)]
+struct Adler32 {
+    a: u32,
+    b: u32,
+}
+
+impl Adler32 {
+    const MODULUS: u32 = 65521;
+
+    fn new() -> Self {
+        Adler32 { a: 1, b: 0 }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        // 5552 is the longest run of bytes for which `b` cannot overflow before the modulo.
+        for block in data.chunks(5552) {
+            for &x in block {
+                self.a += u32::from(x);
+                self.b += self.a;
+            }
+
+            self.a %= Adler32::MODULUS;
+            self.b %= Adler32::MODULUS;
+        }
+    }
+
+    fn value(self) -> u32 {
+        (self.b << 16) | self.a
     }
 }
@@ -0,0 +1,252 @@
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{ApiErrorResponse, DownloadError, DownloadErrorKind, Error, ValidationError};
+
+/// Version of the JSON shape of serialized errors, see `Error`.
+///
+pub const ERROR_FORMAT_VERSION: u32 = 1;
+
+/// What an `Error` serializes as: its version, then the variant as adjacently tagged by serde.
+///
+#[derive(Serialize, Deserialize)]
+struct Versioned {
+    version: u32,
+
+    #[serde(flatten)]
+    error: Repr,
+}
+
+/// Mirror of `Error`, with the names and layout of the serialized variants.
+///
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "kind", content = "details", rename_all = "snake_case")]
+enum Repr {
+    ApiCallFailed(ApiErrorResponse),
+    DownloadFailed(DownloadError),
+    ParsingFailed(String),
+    JsonParsing { message: String, snippet: String },
+    CsvParsing { row: Option<usize>, message: String },
+    ZipExtraction(String),
+    IoError(String),
+    Skipped(String),
+    QuotaExhausted(String),
+    ValidationFailed(Vec<ValidationError>),
+    TransformFailed(String),
+    BatchInternal { worker: usize, message: String },
+    DeadlineExceeded,
+    Cancelled,
+    ResponseTooLarge { limit: u64, observed: u64 },
+    UnzippedTooLarge { limit: u64, observed: u64 },
+    SubscriptionRequired { database_code: String },
+    RateLimitExceeded {
+        retry_after: Option<Duration>,
+        api_error: Option<ApiErrorResponse>,
+        snippet: String,
+    },
+    AccessDenied { status: u16, api_error: Option<ApiErrorResponse>, snippet: String },
+    NotFound { api_error: Option<ApiErrorResponse>, snippet: String },
+}
+
+impl From<Error> for Repr {
+    fn from(error: Error) -> Self {
+        match error {
+            Error::ApiCallFailed(e) => Repr::ApiCallFailed(e),
+            Error::DownloadFailed(e) => Repr::DownloadFailed(e),
+            Error::ParsingFailed(s) => Repr::ParsingFailed(s),
+            Error::JsonParsing { message, snippet } => Repr::JsonParsing { message, snippet },
+            Error::CsvParsing { row, message } => Repr::CsvParsing { row, message },
+            Error::ZipExtraction(s) => Repr::ZipExtraction(s),
+            Error::IoError(s) => Repr::IoError(s),
+            Error::Skipped(url) => Repr::Skipped(url),
+            Error::QuotaExhausted(url) => Repr::QuotaExhausted(url),
+            Error::ValidationFailed(errors) => Repr::ValidationFailed(errors),
+            Error::TransformFailed(s) => Repr::TransformFailed(s),
+            Error::BatchInternal { worker, message } => Repr::BatchInternal { worker, message },
+            Error::DeadlineExceeded => Repr::DeadlineExceeded,
+            Error::Cancelled => Repr::Cancelled,
+            Error::ResponseTooLarge { limit, observed } => {
+                Repr::ResponseTooLarge { limit, observed }
+            },
+            Error::UnzippedTooLarge { limit, observed } => {
+                Repr::UnzippedTooLarge { limit, observed }
+            },
+            Error::SubscriptionRequired { database_code } => {
+                Repr::SubscriptionRequired { database_code }
+            },
+            Error::RateLimitExceeded { retry_after, api_error, snippet } => {
+                Repr::RateLimitExceeded { retry_after, api_error, snippet }
+            },
+            Error::AccessDenied { status, api_error, snippet } => {
+                Repr::AccessDenied { status, api_error, snippet }
+            },
+            Error::NotFound { api_error, snippet } => Repr::NotFound { api_error, snippet },
+        }
+    }
+}
+
+impl From<Repr> for Error {
+    fn from(repr: Repr) -> Self {
+        match repr {
+            Repr::ApiCallFailed(e) => Error::ApiCallFailed(e),
+            Repr::DownloadFailed(e) => Error::DownloadFailed(e),
+            Repr::ParsingFailed(s) => Error::ParsingFailed(s),
+            Repr::JsonParsing { message, snippet } => Error::JsonParsing { message, snippet },
+            Repr::CsvParsing { row, message } => Error::CsvParsing { row, message },
+            Repr::ZipExtraction(s) => Error::ZipExtraction(s),
+            Repr::IoError(s) => Error::IoError(s),
+            Repr::Skipped(url) => Error::Skipped(url),
+            Repr::QuotaExhausted(url) => Error::QuotaExhausted(url),
+            Repr::ValidationFailed(errors) => Error::ValidationFailed(errors),
+            Repr::TransformFailed(s) => Error::TransformFailed(s),
+            Repr::BatchInternal { worker, message } => Error::BatchInternal { worker, message },
+            Repr::DeadlineExceeded => Error::DeadlineExceeded,
+            Repr::Cancelled => Error::Cancelled,
+            Repr::ResponseTooLarge { limit, observed } => {
+                Error::ResponseTooLarge { limit, observed }
+            },
+            Repr::UnzippedTooLarge { limit, observed } => {
+                Error::UnzippedTooLarge { limit, observed }
+            },
+            Repr::SubscriptionRequired { database_code } => {
+                Error::SubscriptionRequired { database_code }
+            },
+            Repr::RateLimitExceeded { retry_after, api_error, snippet } => {
+                Error::RateLimitExceeded { retry_after, api_error, snippet }
+            },
+            Repr::AccessDenied { status, api_error, snippet } => {
+                Error::AccessDenied { status, api_error, snippet }
+            },
+            Repr::NotFound { api_error, snippet } => Error::NotFound { api_error, snippet },
+        }
+    }
+}
+
+impl Serialize for Error {
+    fn serialize<S: Serializer>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error> {
+        let versioned = Versioned { version: ERROR_FORMAT_VERSION, error: self.clone().into() };
+        versioned.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Error {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> ::std::result::Result<Self, D::Error> {
+        let versioned = Versioned::deserialize(deserializer)?;
+
+        if versioned.version != ERROR_FORMAT_VERSION {
+            return Err(serde::de::Error::custom(format!("unsupported error format version {}, \
+                                                         expected {}.",
+                                                        versioned.version,
+                                                        ERROR_FORMAT_VERSION)));
+        }
+
+        Ok(versioned.error.into())
+    }
+}
+
+/// What a `DownloadError` serializes as, its source reduced to its message.
+///
+#[derive(Serialize, Deserialize)]
+struct DownloadErrorRepr {
+    kind: DownloadErrorKind,
+    message: String,
+
+    #[serde(default)]
+    source: Option<String>,
+}
+
+/// Source of a deserialized `DownloadError`, of which only the message is known.
+///
+#[derive(Debug)]
+struct OpaqueSource(String);
+
+impl ::std::fmt::Display for OpaqueSource {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl ::std::error::Error for OpaqueSource {}
+
+impl Serialize for DownloadError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error> {
+        let repr = {
+            DownloadErrorRepr {
+                kind: self.kind,
+                message: self.message.clone(),
+                source: self.source.as_ref().map(|source| source.to_string()),
+            }
+        };
+
+        repr.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for DownloadError {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> ::std::result::Result<Self, D::Error> {
+        let repr = DownloadErrorRepr::deserialize(deserializer)?;
+
+        Ok(DownloadError {
+            kind: repr.kind,
+            message: repr.message,
+            source: {
+                repr.source.map(|source| {
+                    Arc::new(OpaqueSource(source)) as Arc<dyn ::std::error::Error + Send + Sync>
+                })
+            },
+        })
+    }
+}
+
+/// What a `ValidationError` serializes as.
+///
+#[derive(Serialize, Deserialize)]
+struct ValidationErrorRepr {
+    field: String,
+    message: String,
+}
+
+impl Serialize for ValidationError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error> {
+        let repr = {
+            ValidationErrorRepr { field: self.field.to_string(), message: self.message.clone() }
+        };
+
+        repr.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ValidationError {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> ::std::result::Result<Self, D::Error> {
+        let repr = ValidationErrorRepr::deserialize(deserializer)?;
+        Ok(ValidationError { field: field_name(repr.field), message: repr.message })
+    }
+}
+
+/// Deserialized names of fields, each kept once for the lifetime of the program.
+///
+static FIELD_NAMES: OnceLock<Mutex<HashSet<&'static str>>> = OnceLock::new();
+
+/// `name` as the `&'static str` of `ValidationError::field`. Only a name never seen before is
+/// allocated, and then kept for good: there are only so many names of parameters.
+///
+fn field_name(name: String) -> &'static str {
+    let mut names = {
+        FIELD_NAMES.get_or_init(Default::default)
+            .lock()
+            .unwrap_or_else(::std::sync::PoisonError::into_inner)
+    };
+
+    match names.get(&name[..]) {
+        Some(name) => name,
+
+        None => {
+            let name: &'static str = Box::leak(name.into_boxed_str());
+            names.insert(name);
+            name
+        },
+    }
+}
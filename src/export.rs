@@ -0,0 +1,274 @@
+use std::io::Write;
+
+use serde::Serialize;
+
+use crate::column_info::ColumnInfo;
+use crate::types::Dataset;
+use crate::{Result, Error};
+
+/// Write `rows` to `w` as JSON Lines, i.e. each row serialized with `serde_json` on a line of its
+/// own, and return the number of rows written.
+///
+/// Text fields are serialized as JSON strings, so any newline they contain is escaped and every
+/// line holds exactly one row.
+///
+pub fn write_jsonl<T: Serialize, W: Write>(rows: &[T], w: W) -> Result<u64> {
+    write_jsonl_stream(rows.iter().map(Ok), w)
+}
+
+/// Same as `write_jsonl`, writing the rows as they are yielded by `rows`, e.g. a lazy iterator
+/// fetching pages one at a time.
+///
+/// This stops at the first row which is an error, which is returned once the rows before it have
+/// been written and flushed. Iterators of plain rows can be given as `rows.map(Ok)`.
+///
+pub fn write_jsonl_stream<T, I, W>(rows: I, mut w: W) -> Result<u64>
+    where T: Serialize,
+          I: IntoIterator<Item = Result<T>>,
+          W: Write,
+{
+    let mut written = 0;
+    let mut line = vec![];
+
+    for row in rows {
+        let row = {
+            match row {
+                Ok(row) => row,
+
+                Err(e) => {
+                    w.flush().map_err(io_error)?;
+                    return Err(e);
+                },
+            }
+        };
+
+        line.clear();
+        serde_json::to_writer(&mut line, &row)?;
+        line.push(b'\n');
+
+        w.write_all(&line).map_err(io_error)?;
+        written += 1;
+    }
+
+    w.flush().map_err(io_error)?;
+    Ok(written)
+}
+
+/// A row of a dataset as written by `Dataset::to_jsonl`.
+///
+#[derive(Serialize)]
+struct DatasetLine<'a, T> {
+    database_code: &'a str,
+    dataset_code: &'a str,
+    date: serde_json::Value,
+    row: &'a T,
+}
+
+impl<T: Serialize> Dataset<T> {
+    /// The rows of this dataset as JSON Lines (see `export::write_jsonl`), each wrapped with the
+    /// codes of the dataset and its date so that the stream describes itself:
+    ///
+    /// ```text
+    /// {"database_code":"WIKI","dataset_code":"AAPL","date":"2016-02-10","row":["2016-02-10",9.5]}
+    /// ```
+    ///
+    /// The date is the first element of rows serialized as sequences (e.g. tuples) and the field
+    /// named `date` (whatever its case) of those serialized as maps (e.g. structs), or `null`.
+    ///
+    pub fn to_jsonl(&self) -> Result<String> {
+        let mut lines = vec![];
+
+        let wrapped = self.rows.iter().map(|row| {
+            Ok(DatasetLine {
+                database_code: &self.metadata.database_code,
+                dataset_code: &self.metadata.dataset_code,
+                date: date(serde_json::to_value(row)?),
+                row,
+            })
+        });
+
+        write_jsonl_stream(wrapped, &mut lines)?;
+
+        // Only valid UTF-8 is written by `serde_json`.
+        Ok(String::from_utf8(lines).expect("JSON is UTF-8"))
+    }
+}
+
+/// A single value of a dataset in long format, i.e. one record per date and column, as returned by
+/// `Dataset::to_long`.
+///
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LongRow {
+    pub database_code: String,
+    pub dataset_code: String,
+    pub date: String,
+
+    /// Name of the column, from the metadata of the dataset, or `col_{i}` for the `i`-th column
+    /// (the date being the 0th, as with `DataParameters::column_index`) when the metadata does not
+    /// name it.
+    ///
+    pub column: String,
+
+    /// Unit of the values of the column, as found in its name by `ColumnInfo::parse`, e.g.
+    /// `USD mm` for `Value (USD mm)`.
+    ///
+    pub unit: Option<String>,
+
+    /// The value, if numeric: `None` where Quandl has no value, and for text values.
+    ///
+    pub value: Option<f64>,
+
+    /// The value, if it is neither a number nor empty, e.g. a rating or a note.
+    ///
+    pub text_value: Option<String>,
+}
+
+impl<T: Serialize> Dataset<T> {
+    /// The values of this dataset in long format, as tidy-data tools (e.g. R's tidyverse) expect
+    /// them: one `LongRow` per row and column, the date excluded, in the order of the rows and
+    /// then of the columns.
+    ///
+    /// Rows are expected to serialize as sequences, the date first, like `DataRow` or tuples.
+    /// Rows serialized as maps (e.g. structs) are taken field by field instead, the date being the
+    /// field named `date` (whatever its case) and each other field a column named after it.
+    /// Numbers, and strings holding one (e.g. rows decoded as `Vec<String>`), are values; empty
+    /// strings and nulls are missing values; anything else is kept as the record's `text_value`
+    /// rather than dropped, so that there are always as many records as values.
+    ///
+    pub fn to_long(&self) -> Vec<LongRow> {
+        let mut long = vec![];
+
+        for row in &self.rows {
+            let (date, cells) = {
+                match serde_json::to_value(row) {
+                    Ok(serde_json::Value::Array(mut values)) if !values.is_empty() => {
+                        let date = values.remove(0);
+                        let names = self.metadata.column_names.iter().skip(1).map(Some);
+
+                        let cells: Vec<(String, serde_json::Value)> = {
+                            values.into_iter()
+                                .zip(names.chain(::std::iter::repeat(None)))
+                                .enumerate()
+                                .map(|(i, (value, name))| {
+                                    let name = name.cloned();
+                                    (name.unwrap_or_else(|| format!("col_{}", i + 1)), value)
+                                })
+                                .collect()
+                        };
+
+                        (date, cells)
+                    },
+
+                    Ok(serde_json::Value::Object(fields)) => {
+                        let (dates, cells): (Vec<_>, Vec<_>) = {
+                            fields.into_iter()
+                                .partition(|(name, _)| name.eq_ignore_ascii_case("date"))
+                        };
+
+                        let date = dates.into_iter().next().map(|(_, date)| date);
+                        (date.unwrap_or_default(), cells)
+                    },
+
+                    Ok(_) | Err(_) => {
+                        log::warn!("{}/{}: row not serialized as a sequence nor a map, left out of \
+                                    the long format.",
+                                   self.metadata.database_code,
+                                   self.metadata.dataset_code);
+
+                        continue;
+                    },
+                }
+            };
+
+            let date = {
+                match date {
+                    serde_json::Value::String(date) => date,
+                    serde_json::Value::Null => String::new(),
+                    date => date.to_string(),
+                }
+            };
+
+            for (column, cell) in cells {
+                let (value, text_value) = long_value(cell);
+
+                long.push(LongRow {
+                    database_code: self.metadata.database_code.clone(),
+                    dataset_code: self.metadata.dataset_code.clone(),
+                    date: date.clone(),
+                    unit: ColumnInfo::parse(&column).unit,
+                    column,
+                    value,
+                    text_value,
+                });
+            }
+        }
+
+        long
+    }
+}
+
+/// `cell` as the `value` and `text_value` of a `LongRow`.
+///
+fn long_value(cell: serde_json::Value) -> (Option<f64>, Option<String>) {
+    match cell {
+        serde_json::Value::Null => (None, None),
+        serde_json::Value::Number(n) => (n.as_f64(), None),
+
+        serde_json::Value::String(text) => {
+            if text.trim().is_empty() {
+                (None, None)
+            } else {
+                match text.trim().parse::<f64>() {
+                    Ok(value) => (Some(value), None),
+                    Err(_) => (None, Some(text)),
+                }
+            }
+        },
+
+        other => (None, Some(other.to_string())),
+    }
+}
+
+/// Write `rows` to `w` as CSV, with a header (`database_code,dataset_code,date,column,unit,value,
+/// text_value`), and return the number of rows written. Missing values and units are empty
+/// fields.
+///
+pub fn write_long_csv<W: Write>(rows: &[LongRow], w: W) -> Result<u64> {
+    let csv_error = |e: csv::Error| Error::IoError(e.to_string());
+
+    let mut writer = csv::Writer::from_writer(w);
+
+    for row in rows {
+        writer.serialize(row).map_err(csv_error)?;
+    }
+
+    // The header is only written along with the first row.
+    if rows.is_empty() {
+        writer.write_record(["database_code", "dataset_code", "date", "column", "unit", "value",
+                             "text_value"]).map_err(csv_error)?;
+    }
+
+    writer.flush().map_err(io_error)?;
+    Ok(rows.len() as u64)
+}
+
+/// Date of a row serialized as `row`, see `Dataset::to_jsonl`.
+///
+fn date(row: serde_json::Value) -> serde_json::Value {
+    match row {
+        serde_json::Value::Array(mut values) if !values.is_empty() => values.swap_remove(0),
+
+        serde_json::Value::Object(fields) => {
+            fields.into_iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case("date"))
+                .map(|(_, date)| date)
+                .unwrap_or(serde_json::Value::Null)
+        },
+
+        _ => serde_json::Value::Null,
+    }
+}
+
+fn io_error(e: ::std::io::Error) -> Error {
+    Error::IoError(e.to_string())
+}
@@ -0,0 +1,395 @@
+use std::convert::TryFrom;
+use std::str::FromStr;
+
+use percent_encoding::percent_decode_str;
+use url::{Position, Url};
+
+use crate::{Result, Error};
+use crate::api_call::QUANDL_API_URL;
+use crate::parameters::*;
+use crate::query::*;
+use crate::types::{Frequency, Order, Transform};
+
+/// The parameters of a query URL yet to be mapped back onto the query, see `FromUrl`.
+///
+struct Arguments {
+    kind: &'static str,
+    path: String,
+    pairs: Vec<(String, String)>,
+    lenient: bool,
+}
+
+impl Arguments {
+    /// Take `url` apart as the URL of a `kind` query whose path ends with `pattern`, one element
+    /// per segment: either a literal segment or `{}` followed by the suffix of a segment, e.g.
+    /// `"{}.json"`, which captures the rest of that segment (a database or dataset code).
+    ///
+    /// The captured codes are returned along with the base URL preceding the pattern.
+    ///
+    fn parse(url: &Url, kind: &'static str, pattern: &[&str], lenient: bool)
+        -> Result<(Self, Vec<String>, String)>
+    {
+        let arguments = {
+            Arguments {
+                kind,
+                // Only the path is ever quoted, the query string holds the API key.
+                path: url.path().to_string(),
+                pairs: url.query_pairs().into_owned().collect(),
+                lenient,
+            }
+        };
+
+        if url.scheme() != "http" && url.scheme() != "https" {
+            return Err(arguments.error(&format!("unsupported scheme '{}'", url.scheme())));
+        }
+
+        let segments: Vec<&str> = url.path_segments().map(Iterator::collect).unwrap_or_default();
+
+        if segments.len() < pattern.len() {
+            return Err(arguments.error("not a URL of such a query"));
+        }
+
+        let (base, tail) = segments.split_at(segments.len() - pattern.len());
+        let mut codes = vec![];
+
+        for (segment, expected) in tail.iter().zip(pattern) {
+            let segment = {
+                percent_decode_str(segment).decode_utf8()
+                    .map_err(|_| arguments.error("not a URL of such a query"))?
+            };
+
+            match expected.strip_prefix("{}") {
+                Some(suffix) => {
+                    match segment.strip_suffix(suffix) {
+                        Some(code) if !code.is_empty() => codes.push(code.to_string()),
+                        _ => return Err(arguments.error("not a URL of such a query")),
+                    }
+                },
+
+                None if segment == *expected => (),
+                None => return Err(arguments.error("not a URL of such a query")),
+            }
+        }
+
+        let base_url = {
+            let mut base_url = url[..Position::BeforePath].to_string();
+
+            for segment in base {
+                base_url.push('/');
+                base_url.push_str(segment);
+            }
+
+            base_url
+        };
+
+        Ok((arguments, codes, base_url))
+    }
+
+    /// The query `new` builds from `codes`, of a database and dataset, or `by_id` from the id of
+    /// a dataset.
+    ///
+    fn dataset<Q, N, I>(&self, codes: &[String], new: N, by_id: I) -> Result<Q>
+        where N: FnOnce(&str, &str) -> Q,
+              I: FnOnce(usize) -> Q,
+    {
+        match codes {
+            [database_code, dataset_code] => Ok(new(database_code, dataset_code)),
+
+            [id] => {
+                match id.parse() {
+                    Ok(id) => Ok(by_id(id)),
+                    Err(_) => Err(self.error(&format!("invalid dataset id '{}'", id))),
+                }
+            },
+
+            _ => Err(self.error("not a URL of such a query")),
+        }
+    }
+
+    fn error(&self, reason: &str) -> Error {
+        Error::ParsingFailed(format!("cannot rebuild a {} from the URL of path '{}': {}.",
+                                     self.kind,
+                                     self.path,
+                                     reason))
+    }
+
+    /// Remove the parameter `name`, returning its last value if it was given.
+    ///
+    fn take(&mut self, name: &str) -> Option<String> {
+        let mut value = None;
+
+        self.pairs.retain(|(key, x)| {
+            if key == name {
+                value = Some(x.clone());
+            }
+
+            key != name
+        });
+
+        value
+    }
+
+    /// Remove the parameter `name` and parse its value, if it was given.
+    ///
+    fn parse_value<T, F>(&mut self, name: &str, parse: F) -> Result<Option<T>>
+        where F: FnOnce(&str) -> Option<T>
+    {
+        match self.take(name) {
+            Some(value) => {
+                match parse(&value) {
+                    Some(parsed) => Ok(Some(parsed)),
+                    None => Err(self.error(&format!("invalid {} '{}'", name, value))),
+                }
+            },
+
+            None => Ok(None),
+        }
+    }
+
+    fn number<T: FromStr>(&mut self, name: &str) -> Result<Option<T>> {
+        self.parse_value(name, |value| value.parse().ok())
+    }
+
+    /// Remove the parameter `name`, which must have been given.
+    ///
+    fn required(&mut self, name: &str) -> Result<String> {
+        match self.take(name) {
+            Some(value) => Ok(value),
+            None => Err(self.error(&format!("missing {}", name))),
+        }
+    }
+
+    /// Set the base URL (or API version) and API key of `query`.
+    ///
+    fn api<Q: ApiParameters>(&mut self, base_url: &str, query: &mut Q) {
+        let quandl = QUANDL_API_URL.rsplit_once('/').map(|(host, _)| host);
+
+        match base_url.rsplit_once('/') {
+            _ if base_url == QUANDL_API_URL => (),
+            Some((host, version)) if Some(host) == quandl => { query.api_version(version); },
+            _ => { query.base_url(base_url); },
+        }
+
+        if let Some(api_key) = self.take("api_key") {
+            query.api_key(api_key);
+        }
+    }
+
+    /// Set the search parameters of `query`.
+    ///
+    fn search<Q: SearchParameters>(&mut self, query: &mut Q) -> Result<()> {
+        // The keywords were split into terms, which splitting again leaves as they are.
+        if let Some(keywords) = self.take("query") {
+            query.query([keywords]);
+        }
+
+        if let Some(n) = self.number("per_page")? {
+            query.per_page(n);
+        }
+
+        if let Some(n) = self.number("page")? {
+            query.page(n);
+        }
+
+        Ok(())
+    }
+
+    /// Set the data parameters of `query`, in the order `DataParameters::fmt` lists them.
+    ///
+    fn data<Q: DataParameters>(&mut self, query: &mut Q) -> Result<()> {
+        if let Some(n) = self.number("rows")? {
+            query.rows(n);
+        }
+
+        if let Some(n) = self.number("limit")? {
+            query.limit(n);
+        }
+
+        if let Some(order) = self.parse_value("order", Order::from_api_token)? {
+            query.order(order);
+        }
+
+        if let Some(collapse) = self.parse_value("collapse", Frequency::from_api_token)? {
+            query.collapse(collapse);
+        }
+
+        if let Some(transform) = self.parse_value("transform", Transform::from_api_token)? {
+            query.transform(transform);
+        }
+
+        for name in &["end_date", "start_date"] {
+            if let Some(date) = self.take(name) {
+                let result = {
+                    if *name == "end_date" {
+                        query.end_date_str(&date).map(|_| ())
+                    } else {
+                        query.start_date_str(&date).map(|_| ())
+                    }
+                };
+
+                result.map_err(|_| self.error(&format!("invalid {} '{}'", name, date)))?;
+            }
+        }
+
+        if let Some(index) = self.number("column_index")? {
+            query.column_index(index);
+        }
+
+        Ok(())
+    }
+
+    /// Fail if any parameter is left which the query does not know of, unless lenient.
+    ///
+    fn finish(self) -> Result<()> {
+        match self.pairs.first() {
+            Some((name, _)) if !self.lenient => {
+                Err(self.error(&format!("unknown parameter '{}'", name)))
+            },
+
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Implement `TryFrom<Url>` and a `from_url` constructor for a query, rebuilt by `$build` from
+/// the `Arguments` and codes parsed from the URL according to `$pattern`.
+///
+/// Queries of a dataset which may be addressed by id (see `DataQuery::by_id`) give the pattern
+/// of such URLs as well, `$build` then receiving the id as the only code.
+///
+macro_rules! impl_from_url {
+    ($query:ident, $pattern:expr, $id_pattern:expr, $build:expr) => {
+        impl_from_url!(@impl $query, |url, lenient| {
+            Arguments::parse(url, stringify!($query), &$pattern, lenient).or_else(|e| {
+                Arguments::parse(url, stringify!($query), &$id_pattern, lenient).map_err(|_| e)
+            })
+        }, $build);
+    };
+
+    ($query:ident, $pattern:expr, $build:expr) => {
+        impl_from_url!(@impl $query, |url, lenient| {
+            Arguments::parse(url, stringify!($query), &$pattern, lenient)
+        }, $build);
+    };
+
+    (@impl $query:ident, $parse:expr, $build:expr) => {
+        impl $query {
+            /// Rebuild the query sending `url`, as given by `url` or `parsed_url`.
+            ///
+            /// The path must end like the one of this kind of query, its codes aside, and what
+            /// precedes it is taken as the base URL (see `ApiParameters::base_url`), or as the API
+            /// version when it only differs from `QUANDL_API_URL` by that. The parameters are
+            /// mapped back onto the query, failing on values it cannot hold and, unless `lenient`,
+            /// on parameters it does not know of (which are otherwise ignored). Errors do not
+            /// quote the query string, which holds the API key.
+            ///
+            /// Only what is part of the URL is rebuilt: e.g. `ApiParameters::strict` or
+            /// `ApiParameters::client_config` are left to their defaults.
+            ///
+            pub fn from_url(url: &Url, lenient: bool) -> Result<Self> {
+                let parse: fn(&Url, bool) -> Result<(Arguments, Vec<String>, String)> = $parse;
+                let (mut arguments, codes, base_url) = parse(url, lenient)?;
+
+                let build: fn(&mut Arguments, &[String]) -> Result<$query> = $build;
+                let mut query = build(&mut arguments, &codes)?;
+
+                arguments.api(&base_url, &mut query);
+                arguments.finish()?;
+                Ok(query)
+            }
+        }
+
+        impl TryFrom<Url> for $query {
+            type Error = Error;
+
+            /// Rebuild the query sending `url`, strictly, see `from_url`.
+            ///
+            fn try_from(url: Url) -> Result<Self> {
+                $query::from_url(&url, false)
+            }
+        }
+    };
+}
+
+impl_from_url!(DatabaseMetadataQuery, ["databases", "{}.json"], |_, codes| {
+    Ok(DatabaseMetadataQuery::new(&codes[0]))
+});
+
+impl_from_url!(DatasetMetadataQuery,
+               ["datasets", "{}", "{}", "metadata.json"],
+               ["datasets", "{}", "metadata.json"],
+               |arguments, codes| {
+    arguments.dataset(codes, |a, b| DatasetMetadataQuery::new(a, b), DatasetMetadataQuery::by_id)
+});
+
+impl_from_url!(DatabaseSearch, ["databases.json"], |arguments, _| {
+    let mut query = DatabaseSearch::new();
+    arguments.search(&mut query)?;
+    Ok(query)
+});
+
+impl_from_url!(DatasetSearch, ["datasets.json"], |arguments, _| {
+    let mut query = DatasetSearch::new(arguments.required("database_code")?);
+    arguments.search(&mut query)?;
+    Ok(query)
+});
+
+impl_from_url!(DatasetListingQuery, ["datasets.csv"], |arguments, _| {
+    let mut query = DatasetListingQuery::new(arguments.required("database_code")?);
+    arguments.search(&mut query)?;
+
+    match arguments.take("sort_by").as_deref() {
+        Some("last_updated") => { query.sort_by_last_updated(); },
+        Some(other) => return Err(arguments.error(&format!("invalid sort_by '{}'", other))),
+        None => (),
+    }
+
+    Ok(query)
+});
+
+impl_from_url!(CodeListQuery, ["databases", "{}", "codes"], |_, codes| {
+    Ok(CodeListQuery::new(&codes[0]))
+});
+
+impl_from_url!(DatabaseDownloadQuery, ["databases", "{}", "data"], |arguments, codes| {
+    let mut query = DatabaseDownloadQuery::new(&codes[0]);
+
+    match arguments.take("download_type").as_deref() {
+        Some("partial") => { query.partial(); },
+        Some(other) => return Err(arguments.error(&format!("invalid download_type '{}'", other))),
+        None => (),
+    }
+
+    Ok(query)
+});
+
+impl_from_url!(DataQuery,
+               ["datasets", "{}", "{}", "data.csv"],
+               ["datasets", "{}", "data.csv"],
+               |arguments, codes| {
+    let mut query = arguments.dataset(codes, |a, b| DataQuery::new(a, b), DataQuery::by_id)?;
+
+    // Always set, the rows being decoded without a header.
+    match arguments.take("exclude_column_names").as_deref() {
+        Some("true") | None => (),
+        Some(other) => {
+            return Err(arguments.error(&format!("invalid exclude_column_names '{}'", other)))
+        },
+    }
+
+    arguments.data(&mut query)?;
+    Ok(query)
+});
+
+impl_from_url!(DataAndMetadataQuery,
+               ["datasets", "{}", "{}.json"],
+               ["datasets", "{}.json"],
+               |arguments, codes| {
+    let new = |database_code: &str, dataset_code: &str| {
+        DataAndMetadataQuery::new(database_code, dataset_code)
+    };
+
+    let mut query = arguments.dataset(codes, new, DataAndMetadataQuery::by_id)?;
+    arguments.data(&mut query)?;
+    Ok(query)
+});
@@ -0,0 +1,289 @@
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+
+use has::HasMut;
+
+use serde_json::Value;
+
+use crate::{Result, Error, DownloadErrorKind};
+use crate::api_call::ApiCall;
+use crate::config::Config;
+use crate::download::{self, HttpTransport, Transport};
+use crate::parameters::ApiArguments;
+use crate::query::DatabaseSearch;
+use crate::types::DatabaseList;
+
+/// State of the connection to the API as seen by `healthcheck`.
+///
+/// It displays as a summary for humans, e.g.
+///
+/// ```text
+/// url: https://www.quandl.com/api/v3/databases.json?per_page=1
+/// status: 200 in 84.21ms
+/// tls: valid
+/// clock skew: -2.000s (local clock ahead)
+/// api: responds as expected
+/// ```
+///
+/// and serializes (e.g. to JSON) field by field, for monitoring.
+///
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Health {
+    /// URL requested, which never holds an API key. This is that of the host which responded,
+    /// which may be one of the `ClientConfig::fallback_hosts`.
+    ///
+    pub url: String,
+
+    /// HTTP status of the response, unless none was received.
+    ///
+    pub status: Option<u16>,
+
+    /// Time from sending the request to receiving the head of its response, unless none was
+    /// received.
+    ///
+    pub latency: Option<Duration>,
+
+    /// Whether the certificate of the server was accepted.
+    ///
+    pub tls: TlsCheck,
+
+    /// The `Date` of the response minus the local time it was received at, in milliseconds:
+    /// positive when the local clock is behind the server's. `Date` headers are to the second,
+    /// so skews under a second are not significant. This is `None` without a response or a valid
+    /// `Date` header.
+    ///
+    pub clock_skew_millis: Option<i64>,
+
+    /// Whether the body of the response is what the API would send.
+    ///
+    pub api: ApiCheck,
+
+    /// Why no response was received, if so.
+    ///
+    pub error: Option<Error>,
+}
+
+/// Outcome of the TLS handshake of a `healthcheck`.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TlsCheck {
+    /// The base URL is not `https`.
+    ///
+    NotUsed,
+
+    /// The certificate was accepted, by the system's roots or the certificates pinned with
+    /// `ClientConfig::pin_certificates`.
+    ///
+    Valid,
+
+    /// The handshake failed, e.g. because of an expired or untrusted certificate, for the reason
+    /// given.
+    ///
+    Invalid(String),
+
+    /// The connection failed before the handshake could tell.
+    ///
+    Unknown,
+}
+
+/// What the body of the response to a `healthcheck` looks like.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiCheck {
+    /// A list of databases, as the API sends.
+    ///
+    Expected,
+
+    /// An error in the format of the API, e.g. when anonymous calls are refused: the API responds,
+    /// but not with the data asked.
+    ///
+    QuandlError {
+        code: String,
+        message: String,
+    },
+
+    /// Anything else, e.g. the HTML page of a proxy, as described.
+    ///
+    Unexpected(String),
+
+    /// No response was received.
+    ///
+    NoResponse,
+}
+
+/// Check the connection to the API as configured by `config`, e.g. to tell whether a failure is
+/// caused by this end or by Quandl.
+///
+/// This sends a single search for one database, without any API key whatever `config` holds, so
+/// it never consumes an authenticated call (it counts against the anonymous limits of the IP
+/// address instead). Its base URL, HTTP client and response size limit are those of `config`.
+///
+/// Failing to reach the server is part of the `Health` returned, not an error: only a base URL
+/// which is not a valid URL is.
+///
+pub fn healthcheck(config: &Config) -> Result<Health> {
+    let mut query = DatabaseSearch::new();
+
+    *HasMut::<ApiArguments>::get_mut(&mut query) = {
+        ApiArguments {
+            base_url: config.base_url.clone(),
+            client: config.client.clone(),
+            max_response_bytes: config.max_response_bytes,
+            .. ApiArguments::default()
+        }
+    };
+
+    query.per_page(1);
+
+    let url = ApiCall::<DatabaseList>::parsed_url(&query)?.to_string();
+    let tls_used = url.starts_with("https:");
+    let request = crate::api_call::request::<DatabaseList, _>(&query);
+
+    let sent_at = Instant::now();
+
+    let response = {
+        match HttpTransport.execute(&request) {
+            Ok(response) => response,
+
+            Err(error) => {
+                let tls = {
+                    match error.download_kind() {
+                        Some(DownloadErrorKind::Tls) |
+                        Some(DownloadErrorKind::UntrustedCertificate) => {
+                            TlsCheck::Invalid(error.to_string())
+                        },
+
+                        _ if tls_used => TlsCheck::Unknown,
+                        _ => TlsCheck::NotUsed,
+                    }
+                };
+
+                return Ok(Health {
+                    url,
+                    status: None,
+                    latency: None,
+                    tls,
+                    clock_skew_millis: None,
+                    api: ApiCheck::NoResponse,
+                    error: Some(error),
+                });
+            },
+        }
+    };
+
+    let latency = sent_at.elapsed();
+    let received_at = Utc::now();
+    let url = response.url().to_string();
+
+    let clock_skew_millis = {
+        download::header(&response, reqwest::header::DATE)
+            .and_then(|date| DateTime::parse_from_rfc2822(date.trim()).ok())
+            .map(|date| (date.with_timezone(&Utc) - received_at).num_milliseconds())
+    };
+
+    let status = response.status().as_u16();
+
+    let api = {
+        match download::read_body(response) {
+            Ok(body) => api_check(&body),
+            Err(e) => ApiCheck::Unexpected(format!("the body could not be received: {}", e)),
+        }
+    };
+
+    Ok(Health {
+        url,
+        status: Some(status),
+        latency: Some(latency),
+        tls: if tls_used { TlsCheck::Valid } else { TlsCheck::NotUsed },
+        clock_skew_millis,
+        api,
+        error: None,
+    })
+}
+
+/// What `body` looks like, see `ApiCheck`.
+///
+fn api_check(body: &[u8]) -> ApiCheck {
+    let value: Value = {
+        match serde_json::from_slice(body) {
+            Ok(value) => value,
+
+            Err(e) => {
+                let start = String::from_utf8_lossy(&body[..body.len().min(40)]).into_owned();
+                return ApiCheck::Unexpected(format!("not JSON ({}), starting with {:?}", e, start));
+            },
+        }
+    };
+
+    if value["databases"].is_array() && value["meta"].is_object() {
+        return ApiCheck::Expected;
+    }
+
+    let error = &value["quandl_error"];
+
+    if let (Some(code), Some(message)) = (error["code"].as_str(), error["message"].as_str()) {
+        return ApiCheck::QuandlError { code: code.to_string(), message: message.to_string() };
+    }
+
+    ApiCheck::Unexpected("JSON without a list of databases".to_string())
+}
+
+impl Health {
+    /// Whether the API responded successfully over a valid connection, with what it should have.
+    /// The clock skew is left for the caller to judge.
+    ///
+    pub fn is_healthy(&self) -> bool {
+        let success = self.status.map(|status| (200..300).contains(&status)).unwrap_or(false);
+        success && self.api == ApiCheck::Expected
+    }
+}
+
+impl ::std::fmt::Display for Health {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        writeln!(f, "url: {}", self.url)?;
+
+        match (self.status, self.latency, &self.error) {
+            (Some(status), Some(latency), _) => {
+                writeln!(f, "status: {} in {:.2}ms", status, latency.as_secs_f64() * 1_000.0)?;
+            },
+
+            (_, _, Some(error)) => writeln!(f, "status: no response ({})", error)?,
+            _ => writeln!(f, "status: no response")?,
+        }
+
+        match self.tls {
+            TlsCheck::NotUsed => writeln!(f, "tls: not used")?,
+            TlsCheck::Valid => writeln!(f, "tls: valid")?,
+            TlsCheck::Invalid(ref reason) => writeln!(f, "tls: invalid ({})", reason)?,
+            TlsCheck::Unknown => writeln!(f, "tls: unknown")?,
+        }
+
+        match self.clock_skew_millis {
+            Some(skew) => {
+                let side = {
+                    match skew {
+                        0 => "",
+                        x if x > 0 => " (local clock behind)",
+                        _ => " (local clock ahead)",
+                    }
+                };
+
+                writeln!(f, "clock skew: {:+.3}s{}", skew as f64 / 1_000.0, side)?;
+            },
+
+            None => writeln!(f, "clock skew: unknown")?,
+        }
+
+        match self.api {
+            ApiCheck::Expected => writeln!(f, "api: responds as expected"),
+            ApiCheck::QuandlError { ref code, ref message } => {
+                writeln!(f, "api: responds with error {}: {}", code, message)
+            },
+            ApiCheck::Unexpected(ref what) => writeln!(f, "api: unexpected response, {}", what),
+            ApiCheck::NoResponse => writeln!(f, "api: no response"),
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use std::time::Duration;
+
+use crate::ApiKey;
+use crate::types::QueryKind;
+
+/// An API key of the pool of a batch query, with the limits it is subject to, see
+/// `BatchQuery::key_profile`.
+///
+/// Profiles are usually built from `KeyProfile::new`, e.g.
+///
+/// ```rust
+/// use quandl_v3::prelude::*;
+///
+/// let premium = KeyProfile {
+///     limits: vec![(5_000, 600), (720_000, 86_400)],
+///     .. KeyProfile::new("PREMIUM_KEY")
+/// };
+/// ```
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyProfile {
+    /// The API key.
+    ///
+    pub key: ApiKey,
+
+    /// Limits of the key, as `(calls, seconds)` like with `BatchQuery::limit`. A key without
+    /// limits of its own is subject to those of the batch.
+    ///
+    pub limits: Vec<(usize, u64)>,
+
+    /// Multiplier of the key's budget (see `budget`), 1 by default, to favour some keys over
+    /// others beyond what their limits tell.
+    ///
+    pub weight: f64,
+}
+
+impl KeyProfile {
+    /// Profile of `key` (trimmed, as with `ApiParameters::api_key`), without limits of its own
+    /// and of weight 1.
+    ///
+    pub fn new<S: AsRef<str>>(key: S) -> Self {
+        KeyProfile {
+            key: ApiKey::new(key.as_ref().trim()),
+            limits: vec![],
+            weight: 1.0,
+        }
+    }
+
+    /// Number of calls the limits of this key allow over `horizon`, times its weight: the
+    /// tightest of its limits, prorated to `horizon`, decides. A key without limits has an
+    /// infinite budget.
+    ///
+    pub fn budget(&self, horizon: Duration) -> f64 {
+        let calls = {
+            self.limits.iter()
+                .map(|&(calls, seconds)| calls as f64 * horizon.as_secs_f64() / seconds as f64)
+                .fold(f64::INFINITY, f64::min)
+        };
+
+        self.weight * calls
+    }
+}
+
+/// Index in `profiles` of the key assigned to each query of a batch, given the kind of each.
+///
+/// The budget of every key (see `KeyProfile::budget`) is taken over the longest window among the
+/// limits of `profiles`, e.g. a day with Quandl's limits, and the queries assigned to a key spend
+/// their cost (see `QueryKind::cost`) from it. Queries are assigned by decreasing cost, in order
+/// among those of the same cost, each to the key whose remaining budget per cost of the query is
+/// highest: heavy queries are thus placed first, on the keys with the most to spare, and light
+/// ones spread over what is left. Ties go to the key which spent the least relative to its
+/// weight, then to the first one, so the assignment only depends on its inputs.
+///
+/// `profiles` must not be empty unless `kinds` is.
+///
+/// ```rust
+/// use quandl_v3::prelude::*;
+///
+/// let premium = KeyProfile { limits: vec![(20, 60)], .. KeyProfile::new("PREMIUM") };
+/// let free = KeyProfile { limits: vec![(10, 60)], .. KeyProfile::new("FREE") };
+///
+/// let kinds = [QueryKind::Light, QueryKind::Heavy, QueryKind::Light, QueryKind::Light];
+///
+/// // The heavy query takes half of the premium key's budget, the light ones share the rest.
+/// assert_eq!(assign_keys(&[premium, free], &kinds), vec![1, 0, 0, 1]);
+/// ```
+///
+pub fn assign_keys(profiles: &[KeyProfile], kinds: &[QueryKind]) -> Vec<usize> {
+    assert!(!profiles.is_empty() || kinds.is_empty(), "no key to assign {} queries to",
+            kinds.len());
+
+    let horizon = {
+        profiles.iter()
+            .flat_map(|profile| profile.limits.iter().map(|&(_, seconds)| seconds))
+            .max()
+            .unwrap_or(0)
+    };
+
+    let budgets: Vec<f64> = {
+        profiles.iter().map(|profile| profile.budget(Duration::from_secs(horizon))).collect()
+    };
+
+    let mut spent = vec![0.0; profiles.len()];
+    let mut assigned = vec![0; kinds.len()];
+
+    // Sorting is stable, which keeps queries of the same cost in order.
+    let mut order: Vec<usize> = (0..kinds.len()).collect();
+    order.sort_by(|&a, &b| kinds[b].cost().total_cmp(&kinds[a].cost()));
+
+    for index in order {
+        let cost = kinds[index].cost();
+
+        let score = |key: usize| {
+            ((budgets[key] - spent[key]) / cost, -spent[key] / profiles[key].weight)
+        };
+
+        let best = (1..profiles.len()).fold(0, |best, key| {
+            if score(key) > score(best) { key } else { best }
+        });
+
+        spent[best] += cost;
+        assigned[index] = best;
+    }
+
+    assigned
+}
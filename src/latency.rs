@@ -0,0 +1,185 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::time::Duration;
+
+use serde::{Serialize, Serializer};
+
+/// Upper bounds of the buckets of a `LatencyHistogram` but the last (which is unbounded), in
+/// milliseconds.
+///
+const BOUNDS: [u64; 16] = {
+    [1, 2, 5, 10, 20, 50, 100, 200, 500, 1_000, 2_000, 5_000, 10_000, 20_000, 50_000, 100_000]
+};
+
+/// Distribution of the durations of the calls of a batch query, see `BatchReport::latency`.
+///
+/// The durations are counted in buckets of fixed bounds: 1, 2 and 5 times each power of ten from
+/// 1 ms to 100 s, then a last one for anything longer. The histogram thus takes the same room
+/// whatever the number of calls, and its quantiles are within a bucket (i.e. a factor 2.5 at most)
+/// of the exact ones, which is enough to tell the few slow calls of a batch from the others.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct LatencyHistogram {
+    counts: [usize; BOUNDS.len() + 1],
+    max: Duration,
+}
+
+impl LatencyHistogram {
+    /// Count a call of `duration`.
+    ///
+    pub fn record(&mut self, duration: Duration) {
+        let bucket = {
+            BOUNDS.iter()
+                .position(|&bound| duration <= Duration::from_millis(bound))
+                .unwrap_or(BOUNDS.len())
+        };
+
+        self.counts[bucket] += 1;
+        self.max = self.max.max(duration);
+    }
+
+    /// Number of calls counted.
+    ///
+    pub fn count(&self) -> usize {
+        self.counts.iter().sum()
+    }
+
+    /// Duration of the longest call counted, if any.
+    ///
+    pub fn max(&self) -> Option<Duration> {
+        Some(self.max).filter(|_| self.count() > 0)
+    }
+
+    /// Upper bound (or `None` for the last bucket) and number of calls of each bucket, in order.
+    ///
+    pub fn buckets(&self) -> Vec<(Option<Duration>, usize)> {
+        let bounds = BOUNDS.iter().map(|&bound| Some(Duration::from_millis(bound)));
+        bounds.chain(Some(None)).zip(self.counts.iter().cloned()).collect()
+    }
+
+    /// Duration which a fraction `q` (between 0 and 1) of the calls counted did not exceed, e.g.
+    /// the median for 0.5, or `None` if no call was counted.
+    ///
+    /// This is the upper bound of the bucket of the quantile, or the longest duration counted
+    /// when it is shorter (e.g. for the last bucket).
+    ///
+    pub fn quantile(&self, q: f64) -> Option<Duration> {
+        assert!((0.0..=1.0).contains(&q), "quantile: {}", q);
+
+        let rank = ((q * self.count() as f64).ceil() as usize).max(1);
+        let mut calls = 0;
+
+        for (bound, count) in self.buckets() {
+            calls += count;
+
+            if calls >= rank {
+                return Some(bound.map_or(self.max, |bound| bound.min(self.max)));
+            }
+        }
+
+        None
+    }
+
+    pub(crate) fn add(&mut self, other: &LatencyHistogram) {
+        for (count, other) in self.counts.iter_mut().zip(other.counts.iter()) {
+            *count += other;
+        }
+
+        self.max = self.max.max(other.max);
+    }
+}
+
+impl Serialize for LatencyHistogram {
+    /// Serialized bucket by bucket, along with the longest duration counted.
+    ///
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct Bucket {
+            upper_bound: Option<Duration>,
+            count: usize,
+        }
+
+        #[derive(Serialize)]
+        struct Histogram {
+            buckets: Vec<Bucket>,
+            max: Option<Duration>,
+        }
+
+        let buckets = {
+            self.buckets().into_iter()
+                .map(|(upper_bound, count)| Bucket { upper_bound, count })
+                .collect()
+        };
+
+        Histogram { buckets, max: self.max() }.serialize(serializer)
+    }
+}
+
+/// A call among the slowest of a batch query, see `BatchReport::slowest`.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SlowQuery {
+    /// Code of the database queried, empty for queries which do not target a specific database.
+    ///
+    pub database_code: String,
+
+    /// Code of the dataset queried, empty for queries which do not target a dataset.
+    ///
+    pub dataset_code: String,
+
+    /// Duration of the call, waiting on rate limits excluded.
+    ///
+    pub duration: Duration,
+
+    /// Number of bytes received.
+    ///
+    pub bytes: u64,
+}
+
+impl Ord for SlowQuery {
+    /// Slower calls come last, ties being broken by codes so that the log does not depend on the
+    /// order the calls completed in.
+    ///
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.duration.cmp(&other.duration)
+            .then_with(|| other.database_code.cmp(&self.database_code))
+            .then_with(|| other.dataset_code.cmp(&self.dataset_code))
+            .then_with(|| self.bytes.cmp(&other.bytes))
+    }
+}
+
+impl PartialOrd for SlowQuery {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// The `capacity` slowest calls among those logged, kept in a min-heap whose root is the fastest
+/// of them, so that a call is logged in `O(log capacity)` and the log never holds more.
+///
+#[derive(Debug)]
+pub(crate) struct SlowLog {
+    capacity: usize,
+    heap: BinaryHeap<Reverse<SlowQuery>>,
+}
+
+impl SlowLog {
+    pub(crate) fn new(capacity: usize) -> Self {
+        SlowLog { capacity, heap: BinaryHeap::new() }
+    }
+
+    pub(crate) fn log(&mut self, query: SlowQuery) {
+        if self.heap.len() < self.capacity {
+            self.heap.push(Reverse(query));
+        } else if matches!(self.heap.peek(), Some(Reverse(fastest)) if query > *fastest) {
+            self.heap.pop();
+            self.heap.push(Reverse(query));
+        }
+    }
+
+    /// The calls logged, slowest first.
+    ///
+    pub(crate) fn into_sorted_vec(self) -> Vec<SlowQuery> {
+        self.heap.into_sorted_vec().into_iter().map(|Reverse(query)| query).collect()
+    }
+}
@@ -23,7 +23,14 @@
 //!
 //! * The inclusion of a `batch_query` function that allows users to submit a bunch of query at the
 //!   same time. The function returns an iterator which gives the benefit of multithreading
-//!   downloads and asynchronicity which are indispensable when doing data mining.
+//!   downloads and asynchronicity which are indispensable when doing data mining. It is behind
+//!   the `batch` feature (on by default), which minimal builds making single calls can leave out.
+//!
+//! * Queries are cheap to clone, e.g. when instantiating templates or retrying them: their codes
+//!   and API key are shared (as `Arc<str>` and `ApiKey`) rather than copied. This is why their
+//!   codes are read with `database_code()` and `dataset_code()`, and changed with
+//!   `set_database_code` and `set_dataset_code`, rather than being public `String` fields as they
+//!   used to.
 //!
 //! * We use the JSON Quandl API for everything but data queries as it often returns more
 //!   information. When it comes to the data queries we use the CSV subset of the API as it is
@@ -70,21 +77,53 @@
 //! [Quandl's Terms of Use](https://www.quandl.com/about/terms)
 //!
 
-extern crate zip;
+// Whatever Quandl sends, a malformed response is an error to the caller rather than a panic. The
+// few exceptions are `expect`s, each naming the invariant which makes it unreachable (e.g. that
+// a mutex is only poisoned by a panic elsewhere), so that bare `unwrap`s cannot creep back in.
+#![deny(clippy::unwrap_used)]
+
+extern crate bytes;
 extern crate csv;
 extern crate serde;
+extern crate chrono;
 extern crate reqwest;
-extern crate num_cpus;
+extern crate native_tls;
+extern crate url;
+extern crate percent_encoding;
+#[cfg(feature = "batch")] extern crate num_cpus;
+extern crate log;
+extern crate sha2;
 extern crate serde_json;
+#[cfg(feature = "zip")] extern crate zip;
+#[cfg(feature = "rayon")] extern crate rayon;
+#[cfg(feature = "async")] extern crate tokio;
+#[cfg(feature = "async")] extern crate futures_core;
 #[macro_use] extern crate serde_derive;
 #[macro_use] extern crate has;
 
 mod types;
+mod api_key;
+mod cancel;
 mod query;
 mod api_call;
 mod download;
 mod parameters;
-mod batch_query;
+mod template;
+mod warnings;
+mod schema;
+mod column_info;
+mod error_format;
+mod from_url;
+mod verify;
+mod health;
+#[cfg(any(feature = "aliases", feature = "registry"))] mod toml_sections;
+#[cfg(feature = "rayon")] mod parallel;
+#[cfg(feature = "batch")] mod batch_query;
+#[cfg(all(feature = "batch", feature = "async"))] mod batch_stream;
+#[cfg(feature = "batch")] mod key_pool;
+#[cfg(feature = "batch")] mod latency;
+#[cfg(feature = "batch")] mod typed_fetch;
+#[cfg(feature = "batch")] mod columns;
 
 /// This crate's public interface.
 ///
@@ -93,7 +132,115 @@ mod batch_query;
 ///
 pub mod prelude;
 
+/// Date arithmetic shared by the whole crate: ISO weeks, month/quarter/year boundaries, fiscal
+/// periods and business-day calendars.
+///
+/// These are the boundaries Quandl itself uses when collapsing data, so they can also be used to
+/// interpret the dates of collapsed observations.
+///
+pub mod calendar;
+
+/// Sans-IO rate limiter, with blocking and (behind the `async` feature) asynchronous adapters.
+///
+/// It does not depend on the `batch` feature, although `BatchQuery` is its main user.
+///
+pub mod rate_limit;
+
+/// Helpers combining the responses of several queries into what a single query would have
+/// returned, used by every helper splitting a fetch into multiple calls.
+///
+pub mod merge;
+
+/// Process-wide and scoped defaults of the queries, such as the API key or base URL.
+///
+pub mod config;
+
+/// Exponential backoff policies, with jitter, and a helper retrying transient failures with them.
+///
+pub mod backoff;
+
+/// Comparison of two pulls of a dataset, to detect revisions of its data.
+///
+pub mod diff;
+
+/// Export of decoded rows as JSON Lines, e.g. for streaming pipelines, or in long format (one
+/// record per date and column) for tidy-data tools.
+///
+pub mod export;
+
+/// Queue of queries persisted to a file, drained through a `BatchQuery` over several runs of a
+/// process and resumed after crashes.
+///
+#[cfg(feature = "batch")]
+pub mod queue;
+
+/// Local copy of a whole database, refreshed incrementally by later runs (behind the `batch` and
+/// `zip` features).
+///
+#[cfg(all(feature = "batch", feature = "zip"))]
+pub mod mirror;
+
+/// Test vectors of the canonical URLs of queries, to check this crate's builders or any other
+/// implementation against them (behind the `batch` feature).
+///
+#[cfg(feature = "batch")]
+pub mod conformance;
+
+/// Persistence of the responses kept to revalidate them with conditional requests, with a size
+/// bounded filesystem implementation.
+///
+pub mod cache;
+
+/// Content-addressed archive of the raw payloads received, with an index from queries to their
+/// payloads, to prove later that an analysis used byte-identical inputs.
+///
+pub mod archive;
+
+/// Checks of payloads received by earlier versions of this crate against the types of this one,
+/// e.g. to know whether archived payloads will still decode after an upgrade.
+///
+pub mod compat;
+
+/// Codes of hierarchical databases arranged in a tree by the segments of their dataset codes,
+/// e.g. to navigate them.
+///
+pub mod codes;
+
+/// Generation, typically from a build script, of the structs decoding the rows of a dataset from
+/// its metadata (behind the `codegen` feature).
+///
+#[cfg(feature = "codegen")]
+pub mod codegen;
+
+/// Database codes of Quandl which Nasdaq Data Link renamed or retired, with what became of them
+/// (behind the `aliases` feature).
+///
+#[cfg(feature = "aliases")]
+pub mod aliases;
+
+/// Hints about the columns and frequency of well-known databases, known without fetching their
+/// metadata and extensible at runtime (behind the `registry` feature).
+///
+#[cfg(feature = "registry")]
+pub mod registry;
+
+/// Configuration of the HTTP client sending the queries, e.g. to pin the certificates trusted for
+/// Quandl's endpoint.
+///
+pub mod client;
+
 use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+pub use bytes::Bytes;
+
+pub use crate::api_key::ApiKey;
+pub use crate::cancel::CancelToken;
+pub use crate::warnings::{Warning, Warnings, WithWarnings, ROW_CAPS};
+pub use crate::error_format::ERROR_FORMAT_VERSION;
+pub use crate::verify::{verify_api_key, KeyInfo};
+pub use crate::health::{healthcheck, ApiCheck, Health, TlsCheck};
 
 /// Crate-wide return type for functions which may fail.
 ///
@@ -113,6 +260,15 @@ pub struct ApiErrorResponse {
     pub quandl_error: QuandlError,
 }
 
+impl ApiErrorResponse {
+    /// Decode the body of one of Quandl's unsuccessful replies, as the queries do before
+    /// reporting it (e.g. as an `Error::ApiCallFailed`).
+    ///
+    pub fn decode(payload: &[u8]) -> Result<Self> {
+        crate::download::json(payload)
+    }
+}
+
 /// Struct holding Quandl's error code and corresponding message.
 ///
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -126,45 +282,491 @@ pub struct QuandlError {
     pub message: String,
 }
 
-/// Crate-wide error value. This enumerate the only four possible source of failures in this crate.
+/// Crate-wide error value, enumerating the possible sources of failures in this crate.
+///
+/// Payloads which cannot be decoded are reported according to their format (`JsonParsing`,
+/// `CsvParsing` or `ZipExtraction`), while `ParsingFailed` is left for values this crate rejects
+/// itself. `is_parsing_failure` matches all four, as `ParsingFailed` alone used to.
+///
+/// Errors (de)serialize with serde, e.g. to be queued along with the query which failed, as
+/// objects holding the version of their format (`ERROR_FORMAT_VERSION`), the name of their
+/// variant in snake case as `kind` and the content of the variant, if any, as `details`:
+///
+/// ```text
+/// {"version":1,"kind":"csv_parsing","details":{"row":3,"message":"invalid float literal"}}
+/// {"version":1,"kind":"io_error","details":"disk full"}
+/// {"version":1,"kind":"deadline_exceeded"}
+/// ```
+///
+/// The details of a `DownloadFailed` are its `kind` (again in snake case), `message` and
+/// `source`, the message of the error which caused it or `null`. A deserialized download error
+/// thus only knows its source by its message. Errors of another version are not deserialized.
 ///
 #[derive(Debug, Clone, PartialEq)]
 pub enum Error {
     /// Is returned when Quandl's reply to a query with an error. The contained `ApiErrorResponse`
     /// contains very verbose information about what went wrong with any specific query.
     ///
+    /// Replies whose status tells what went wrong regardless of their body are reported as
+    /// `RateLimitExceeded`, `AccessDenied` or `NotFound` instead.
+    ///
     ApiCallFailed(ApiErrorResponse),
 
     /// Is returned when a problem occurs while exchanging informaiton with the Quandl's servers.
     /// It could mean the Internet connection was lost, that the remote server closed the
     /// connection unexpectedly, etc.
     ///
-    /// Unfortunately, the current implementation for network connection (hyper) has very weak
-    /// error reporting and thus might leave the user confused as to why such an error is returned.
+    /// The contained `DownloadError` tells what kind of failure it was (see `download_kind`) and
+    /// keeps the underlying error as its `source`.
     ///
-    DownloadFailed(String),
+    DownloadFailed(DownloadError),
 
-    /// Is returned when the received value, assuming Quandl didn't respond with an error and that
-    /// there was no download error, breaks one of the parsers' assumption. Most of the time it
-    /// would be an error from `rustc_serialize` (which also does not report very meaningful errors
-    /// unfortunately) or it could also be a custom message from this library for data which didn't
-    /// met the format deserializable by the `rustc_serialize` crate.
+    /// Is returned when a value is rejected by this crate rather than by a parser: a response
+    /// served with an unexpected content type, an invalid base URL or template, a malformed date
+    /// in otherwise valid metadata, etc.
     ///
     ParsingFailed(String),
 
+    /// Is returned when a JSON payload (or Quandl's error response) does not decode into the
+    /// expected structure, typically because Quandl changed its schema.
+    ///
+    /// `snippet` quotes the payload around where decoding failed, and is empty when the payload is
+    /// not available (e.g. when the failure is detected on an already decoded value).
+    ///
+    JsonParsing {
+        message: String,
+        snippet: String,
+    },
+
+    /// Is returned when a CSV payload contains a malformed row, or one which does not decode into
+    /// the requested row type.
+    ///
+    /// `row` is the (1-based) position of the offending record in the payload, when known.
+    ///
+    CsvParsing {
+        row: Option<usize>,
+        message: String,
+    },
+
+    /// Is returned when a zipped payload (i.e. a code list) is not a valid archive or cannot be
+    /// extracted.
+    ///
+    ZipExtraction(String),
+
     /// Is returned when an I/O operation fails. This last error is highly system-dependant and
     /// again, the error message string returned are not always very verbose.
     ///
     IoError(String),
+
+    /// Is yielded by a batch query in place of the result of a query which was not sent because
+    /// its manifest shows it was already completed, see `BatchQuery::manifest`. Contains the URL
+    /// of the query, API key excluded.
+    ///
+    Skipped(String),
+
+    /// Is yielded by a batch query in place of the result of a query which was not sent because
+    /// the quota left to its API key was down to the calls reserved by `BatchQuery::reserve_quota`.
+    /// Contains the URL of the query, API key excluded.
+    ///
+    QuotaExhausted(String),
+
+    /// Is returned instead of sending a query made strict (see `ApiParameters::strict`) which has
+    /// problems, as reported by its `validate` method: every problem found is listed.
+    ///
+    ValidationFailed(Vec<ValidationError>),
+
+    /// Is yielded by a batch query in place of the result of a query when the transformation
+    /// given to `BatchQuery::map_rows` panicked on it. Contains the panic's message.
+    ///
+    TransformFailed(String),
+
+    /// Is yielded by a batch query in place of the result of a query when its worker panicked
+    /// while processing it, e.g. in an `on_throttle` or `on_progress` callback, rather than the
+    /// worker dying with the rest of its queries. Contains the panic's `message` and the number of
+    /// the `worker`, whose thread is named `quandl-batch-{worker}`.
+    ///
+    BatchInternal {
+        worker: usize,
+        message: String,
+    },
+
+    /// Is returned when the deadline given to `ApiCall::send_with_deadline` passes before the
+    /// query is completed, whether that is before it is sent or while waiting for Quandl.
+    ///
+    DeadlineExceeded,
+
+    /// Is returned when the `CancelToken` given to an operation (e.g. to
+    /// `ApiCall::send_with_cancel` or `BatchQuery::cancel_token`) is cancelled before it is
+    /// completed, whether that is before it is sent or while waiting for Quandl.
+    ///
+    /// Unlike `DeadlineExceeded`, this is never the result of a timeout.
+    ///
+    Cancelled,
+
+    /// Is returned when a response is larger than allowed by `ApiParameters::max_response_bytes`
+    /// (or, for a code list, `CodeListQuery::max_compressed_bytes`), the `limit`. `observed` is
+    /// the size advertised by the response's `Content-Length`, or else the number of bytes
+    /// received when it was given up on, which is then only a lower bound of its size.
+    ///
+    ResponseTooLarge {
+        limit: u64,
+        observed: u64,
+    },
+
+    /// Is returned when a code list unzips into more than allowed, the `limit`: as set by
+    /// `CodeListQuery::max_uncompressed_bytes`, or `CodeListQuery::max_unzip_ratio` times the
+    /// size of the archive if that is less, as for a zip bomb. `observed` is the size the archive
+    /// declares for its files, or else the number of bytes unzipped when it was given up on, which
+    /// is then only a lower bound of their size.
+    ///
+    UnzippedTooLarge {
+        limit: u64,
+        observed: u64,
+    },
+
+    /// Is returned in place of Quandl's `403 Forbidden` reply to a query for premium data when
+    /// the API key used (if any) is not subscribed to its database, `database_code`.
+    ///
+    /// Unlike Quandl's other errors this is not reported as an `ApiCallFailed`: the dataset does
+    /// exist, and access to it can be bought.
+    ///
+    SubscriptionRequired {
+        database_code: String,
+    },
+
+    /// Is returned when the reply to a query is `429 Too Many Requests`, whether it comes from
+    /// Quandl or from a proxy in front of it (whose body is then typically an HTML page).
+    ///
+    /// `retry_after` is the wait advised by the reply's `Retry-After` header, if any. The body is
+    /// kept as `api_error` when it is one of Quandl's error responses, and quoted by `snippet`
+    /// either way.
+    ///
+    RateLimitExceeded {
+        retry_after: Option<Duration>,
+        api_error: Option<ApiErrorResponse>,
+        snippet: String,
+    },
+
+    /// Is returned when the reply to a query is `401 Unauthorized` or `403 Forbidden` (`status`),
+    /// e.g. for an invalid API key, whatever its body. `api_error` and `snippet` are as for
+    /// `RateLimitExceeded`.
+    ///
+    AccessDenied {
+        status: u16,
+        api_error: Option<ApiErrorResponse>,
+        snippet: String,
+    },
+
+    /// Is returned when the reply to a query is `404 Not Found`, e.g. for a dataset which does not
+    /// exist, whatever its body. `api_error` and `snippet` are as for `RateLimitExceeded`.
+    ///
+    NotFound {
+        api_error: Option<ApiErrorResponse>,
+        snippet: String,
+    },
+}
+
+impl Error {
+    /// What kind of transport failure this is, if it is an `Error::DownloadFailed`.
+    ///
+    pub fn download_kind(&self) -> Option<DownloadErrorKind> {
+        match self {
+            Error::DownloadFailed(e) => Some(e.kind()),
+            _ => None,
+        }
+    }
+
+    /// Whether or not this is any of the parsing errors (`ParsingFailed`, `JsonParsing`,
+    /// `CsvParsing` or `ZipExtraction`), i.e. what used to be reported as `ParsingFailed` alone.
+    ///
+    pub fn is_parsing_failure(&self) -> bool {
+        matches!(self, Error::ParsingFailed(_) |
+                       Error::JsonParsing { .. } |
+                       Error::CsvParsing { .. } |
+                       Error::ZipExtraction(_))
+    }
+
+    /// Quandl's error response, if this error carries one.
+    ///
+    pub fn api_error(&self) -> Option<&ApiErrorResponse> {
+        match self {
+            Error::ApiCallFailed(e) => Some(e),
+            Error::RateLimitExceeded { api_error, .. } |
+            Error::AccessDenied { api_error, .. } |
+            Error::NotFound { api_error, .. } => api_error.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Whether or not this is an `Error::RateLimitExceeded`.
+    ///
+    pub fn is_rate_limited(&self) -> bool {
+        matches!(self, Error::RateLimitExceeded { .. })
+    }
+
+    /// Whether or not this is an `Error::AccessDenied`.
+    ///
+    pub fn is_access_denied(&self) -> bool {
+        matches!(self, Error::AccessDenied { .. })
+    }
+
+    /// Whether or not this is an `Error::NotFound`.
+    ///
+    pub fn is_not_found(&self) -> bool {
+        matches!(self, Error::NotFound { .. })
+    }
+
+    /// The wait advised by the reply, if this is an `Error::RateLimitExceeded` which has one.
+    ///
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Error::RateLimitExceeded { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+
+    /// Error for a JSON `payload` which failed to decode, quoting it around the failure.
+    ///
+    pub(crate) fn json(e: &serde_json::Error, payload: &str) -> Self {
+        let snippet = snippet(payload, e.line(), e.column());
+        Error::JsonParsing { message: e.to_string(), snippet }
+    }
+
+    /// Error for the record at the (1-based) position `row` of a CSV payload.
+    ///
+    pub(crate) fn csv<S: ToString>(row: usize, message: S) -> Self {
+        Error::CsvParsing { row: Some(row), message: message.to_string() }
+    }
+}
+
+/// Characters quoted on each side of a JSON decoding failure.
+///
+const SNIPPET_RADIUS: usize = 32;
+
+/// Part of `payload` around its `line`-th line and `column`-th byte (both 1-based, as reported by
+/// `serde_json`), or the start of the payload if the position is not known.
+///
+fn snippet(payload: &str, line: usize, column: usize) -> String {
+    let text = payload.lines().nth(line.max(1) - 1).unwrap_or("");
+    let column = column.saturating_sub(1).min(text.len());
+
+    let mut start = column.saturating_sub(SNIPPET_RADIUS);
+    let mut end = (column + SNIPPET_RADIUS).min(text.len());
+
+    while !text.is_char_boundary(start) {
+        start -= 1;
+    }
+
+    while !text.is_char_boundary(end) {
+        end += 1;
+    }
+
+    text[start..end].trim().to_string()
+}
+
+/// Start of the `body` of an unsuccessful reply, its whitespace collapsed (e.g. the lines of an
+/// HTML page) and its bytes which are not UTF-8 replaced.
+///
+pub(crate) fn body_snippet(body: &[u8]) -> String {
+    let text = String::from_utf8_lossy(body);
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    match text.char_indices().nth(2 * SNIPPET_RADIUS) {
+        Some((end, _)) => text[..end].trim_end().to_string(),
+        None => text,
+    }
+}
+
+/// What an unsuccessful reply says, for the `Display` of the errors reporting it: Quandl's
+/// message if it is one of Quandl's error responses, else the start of its body.
+///
+fn reason(api_error: &Option<ApiErrorResponse>, snippet: &str) -> String {
+    match api_error {
+        Some(e) => e.quandl_error.message.clone(),
+        None if snippet.is_empty() => "empty reply".to_string(),
+        None => format!("'{}'", snippet),
+    }
+}
+
+/// Errors of `serde_json`, with no payload to quote.
+///
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::JsonParsing { message: e.to_string(), snippet: String::new() }
+    }
+}
+
+/// Errors of `csv`, located at the record they report if any.
+///
+impl From<csv::Error> for Error {
+    fn from(e: csv::Error) -> Self {
+        let row = e.position().map(|position| position.record() as usize + 1);
+        Error::CsvParsing { row, message: e.to_string() }
+    }
+}
+
+#[cfg(feature = "zip")]
+impl From<zip::result::ZipError> for Error {
+    fn from(e: zip::result::ZipError) -> Self {
+        Error::ZipExtraction(e.to_string())
+    }
+}
+
+/// Kinds of transport failures, as classified from the underlying HTTP client's errors.
+///
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DownloadErrorKind {
+    /// The host name could not be resolved, which usually means a misconfigured base URL.
+    ///
+    Dns,
+
+    /// The connection could not be established, or was reset or closed before a response was
+    /// received.
+    ///
+    Connect,
+
+    /// The TLS handshake failed, e.g. because of an invalid certificate.
+    ///
+    Tls,
+
+    /// The server's certificate chain does not lead to any of the certificates pinned with
+    /// `ClientConfig::pin_certificates`.
+    ///
+    UntrustedCertificate,
+
+    /// The request timed out.
+    ///
+    Timeout,
+
+    /// The connection failed while the body of the response was being received.
+    ///
+    BodyRead,
+
+    /// The server redirected the request too many times, or in a loop.
+    ///
+    TooManyRedirects,
+
+    /// Any other failure.
+    ///
+    Other,
+}
+
+impl DownloadErrorKind {
+    /// Whether or not a failure of this kind is likely to be transient, and thus worth retrying.
+    ///
+    /// DNS, TLS (pinning included) and redirection failures are not: they are caused by the
+    /// configuration of either end and would fail the same way again.
+    ///
+    pub fn is_retryable(self) -> bool {
+        !matches!(self, DownloadErrorKind::Dns |
+                        DownloadErrorKind::Tls |
+                        DownloadErrorKind::UntrustedCertificate |
+                        DownloadErrorKind::TooManyRedirects)
+    }
+}
+
+/// Description of a transport failure, along with the error which caused it if any.
+///
+/// Two download errors are equal when they are of the same kind and have the same message.
+///
+#[derive(Debug, Clone)]
+pub struct DownloadError {
+    kind: DownloadErrorKind,
+    message: String,
+    source: Option<Arc<dyn ::std::error::Error + Send + Sync>>,
+}
+
+impl DownloadError {
+    /// Create an error of the given kind, without any source.
+    ///
+    pub fn new<S: AsRef<str>>(kind: DownloadErrorKind, message: S) -> Self {
+        DownloadError { kind, message: message.as_ref().to_string(), source: None }
+    }
+
+    /// Create an error of the given kind, caused by `source`.
+    ///
+    pub fn with_source<E>(kind: DownloadErrorKind, source: E) -> Self
+        where E: ::std::error::Error + Send + Sync + 'static
+    {
+        DownloadError { kind, message: source.to_string(), source: Some(Arc::new(source)) }
+    }
+
+    /// What kind of failure this is.
+    ///
+    pub fn kind(&self) -> DownloadErrorKind {
+        self.kind
+    }
+
+    /// Description of the failure.
+    ///
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl PartialEq for DownloadError {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind == other.kind && self.message == other.message
+    }
+}
+
+/// Errors created from a bare message are of kind `Other`.
+///
+impl From<String> for DownloadError {
+    fn from(message: String) -> Self {
+        DownloadError { kind: DownloadErrorKind::Other, message, source: None }
+    }
+}
+
+impl<'a> From<&'a str> for DownloadError {
+    fn from(message: &'a str) -> Self {
+        DownloadError::from(message.to_string())
+    }
+}
+
+impl ::std::fmt::Display for DownloadError {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl ::std::error::Error for DownloadError {
+    fn source(&self) -> Option<&(dyn ::std::error::Error + 'static)> {
+        self.source.as_ref().map(|e| &**e as &(dyn ::std::error::Error + 'static))
+    }
 }
 
 impl ::std::error::Error for Error {
-    fn description(&self) -> &str {
+    fn source(&self) -> Option<&(dyn ::std::error::Error + 'static)> {
         match self {
-            &Error::ApiCallFailed(_)  => "Quandl's server responded with an error.",
-            &Error::DownloadFailed(_) => "Download failed.",
-            &Error::ParsingFailed(_)  => "Parsing data failed.",
-            &Error::IoError(_)        => "Underlying system I/O error.",
+            Error::DownloadFailed(e) => Some(e),
+            _ => None,
+        }
+    }
+
+    fn description(&self) -> &str {
+        match *self {
+            Error::ApiCallFailed(_)   => "Quandl's server responded with an error.",
+            Error::DownloadFailed(_)  => "Download failed.",
+            Error::ParsingFailed(_)   => "Parsing data failed.",
+            Error::JsonParsing { .. } => "Parsing JSON data failed.",
+            Error::CsvParsing { .. }  => "Parsing CSV data failed.",
+            Error::ZipExtraction(_)   => "Extracting zipped data failed.",
+            Error::IoError(_)         => "Underlying system I/O error.",
+            Error::Skipped(_)         => "Query already completed by a previous run.",
+            Error::QuotaExhausted(_)  => "Quota of the API key reserved.",
+            Error::ValidationFailed(_) => "Query failed validation.",
+            Error::TransformFailed(_) => "Transforming the result of a query failed.",
+            Error::BatchInternal { .. } => "A batch worker panicked.",
+            Error::DeadlineExceeded   => "Query deadline exceeded.",
+            Error::Cancelled          => "Query cancelled.",
+            Error::ResponseTooLarge { .. } => "Response larger than allowed.",
+            Error::UnzippedTooLarge { .. } => "Unzipped data larger than allowed.",
+            Error::SubscriptionRequired { .. } => "Subscription required.",
+            Error::RateLimitExceeded { .. } => "Rate limit exceeded.",
+            Error::AccessDenied { .. } => "Access denied.",
+            Error::NotFound { .. }    => "Not found.",
         }
     }
 }
@@ -172,29 +774,145 @@ impl ::std::error::Error for Error {
 impl ::std::fmt::Display for Error {
     fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
         match self {
-            &Error::ApiCallFailed(ref e) => {
-                if e.errors.is_some() && !e.errors.as_ref().unwrap().is_empty() {
-                    let (object, what) = e.errors.as_ref().unwrap().iter().next().unwrap();
+            Error::ApiCallFailed(e) => {
+                match e.errors.as_ref().and_then(|errors| errors.iter().next()) {
+                    Some((object, what)) => {
+                        write!(f, "{}", {
+                            what.iter().fold(format!("{} - ", object), |xs, x| {
+                                format!("{} {}", xs, x)
+                            })
+                        })
+                    },
 
-                    write!(f, "{}", {
-                        what.iter().fold(format!("{} - ", object), |xs, x| format!("{} {}", xs, x))
-                    })
-                } else {
-                    write!(f, "{}", e.quandl_error.message)
+                    None => write!(f, "{}", e.quandl_error.message),
                 }
             },
 
-            &Error::DownloadFailed(ref s) => {
+            Error::DownloadFailed(s) => {
                 write!(f, "download failed with error '{}'.", s)
             },
 
-            &Error::ParsingFailed(ref s) => {
+            Error::ParsingFailed(s) => {
                 write!(f, "parsing encoded data failed with error '{}'.", s)
             },
 
-            &Error::IoError(ref s) => {
+            Error::JsonParsing { message, snippet } => {
+                if snippet.is_empty() {
+                    write!(f, "parsing JSON data failed with error '{}'.", message)
+                } else {
+                    write!(f, "parsing JSON data failed with error '{}' near '{}'.",
+                           message,
+                           snippet)
+                }
+            },
+
+            Error::CsvParsing { row: Some(row), message } => {
+                write!(f, "parsing CSV data failed at row {} with error '{}'.", row, message)
+            },
+
+            Error::CsvParsing { row: None, message } => {
+                write!(f, "parsing CSV data failed with error '{}'.", message)
+            },
+
+            Error::ZipExtraction(s) => {
+                write!(f, "extracting zipped data failed with error '{}'.", s)
+            },
+
+            Error::IoError(s) => {
                 write!(f, "I/O operation failed with error '{}'.", s)
             },
+
+            Error::Skipped(url) => {
+                write!(f, "skipped query '{}', already completed according to the manifest.", url)
+            },
+
+            Error::QuotaExhausted(url) => {
+                write!(f, "skipped query '{}', the quota left to its API key being reserved.", url)
+            },
+
+            Error::ValidationFailed(errors) => {
+                let errors: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+                write!(f, "query failed validation: {}", errors.join(" "))
+            },
+
+            Error::TransformFailed(s) => {
+                write!(f, "transforming the result of the query panicked with '{}'.", s)
+            },
+
+            Error::BatchInternal { worker, message } => {
+                write!(f, "batch worker quandl-batch-{} panicked with '{}'.", worker, message)
+            },
+
+            Error::DeadlineExceeded => {
+                write!(f, "the deadline passed before the query could be completed.")
+            },
+
+            Error::Cancelled => {
+                write!(f, "the query was cancelled before it could be completed.")
+            },
+
+            Error::ResponseTooLarge { limit, observed } => {
+                write!(f, "the response is larger than the {} bytes allowed (got {} bytes).",
+                       limit,
+                       observed)
+            },
+
+            Error::UnzippedTooLarge { limit, observed } => {
+                write!(f, "the archive unzips into more than the {} bytes allowed (got {} bytes).",
+                       limit,
+                       observed)
+            },
+
+            Error::SubscriptionRequired { database_code } => {
+                write!(f, "subscription required for database '{}', see \
+                           https://www.quandl.com/data/{} to subscribe to it.",
+                       database_code,
+                       database_code)
+            },
+
+            Error::RateLimitExceeded { retry_after, api_error, snippet } => {
+                write!(f, "rate limit exceeded: {}", reason(api_error, snippet))?;
+
+                match retry_after {
+                    Some(wait) => write!(f, " (retry after {}s)", wait.as_secs()),
+                    None => Ok(()),
+                }
+            },
+
+            Error::AccessDenied { status, api_error, snippet } => {
+                write!(f, "access denied ({}): {}", status, reason(api_error, snippet))
+            },
+
+            Error::NotFound { api_error, snippet } => {
+                write!(f, "not found: {}", reason(api_error, snippet))
+            },
         }
     }
 }
+
+/// Problem with a query, found by its `validate` method before anything is sent.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationError {
+    /// Name of the offending parameter, e.g. `"start_date"` or `"dataset_code"`.
+    ///
+    pub field: &'static str,
+
+    /// What is wrong with it.
+    ///
+    pub message: String,
+}
+
+impl ValidationError {
+    /// Create an error about `field`.
+    ///
+    pub fn new<S: AsRef<str>>(field: &'static str, message: S) -> Self {
+        ValidationError { field, message: message.as_ref().to_string() }
+    }
+}
+
+impl ::std::fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
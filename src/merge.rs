@@ -0,0 +1,100 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+use crate::types::{DataRow, Order};
+
+/// Concatenate chunks of rows, each sorted by `key_fn` in the given `order`, into the rows a single
+/// query covering all of them would have returned.
+///
+/// Chunks may be given in any order and may be empty. Where two chunks overlap (typically when
+/// they share a boundary date), the rows of the chunk starting first are kept and the duplicated
+/// rows of the other are dropped. Rows with equal keys within a single chunk are all kept.
+///
+pub fn concat_ordered<T, K, F, I>(chunks: I, order: Order, key_fn: F) -> Vec<T>
+    where K: Ord,
+          F: Fn(&T) -> K,
+          I: IntoIterator<Item = Vec<T>>,
+{
+    let compare = |a: &K, b: &K| {
+        match order {
+            Order::asc => a.cmp(b),
+            Order::desc => b.cmp(a),
+        }
+    };
+
+    let mut chunks: Vec<Vec<T>> = chunks.into_iter().filter(|chunk| !chunk.is_empty()).collect();
+
+    // Stable, so the first of two chunks starting with the same key wins.
+    chunks.sort_by(|a, b| compare(&key_fn(&a[0]), &key_fn(&b[0])));
+
+    let mut rows: Vec<T> = Vec::with_capacity(chunks.iter().map(Vec::len).sum());
+    let mut last: Option<K> = None;
+
+    for chunk in chunks {
+        let new_rows = chunk.into_iter().skip_while(|row| {
+            match last {
+                Some(ref last) => compare(&key_fn(row), last) != Ordering::Greater,
+                None => false,
+            }
+        });
+
+        rows.extend(new_rows);
+
+        if let Some(row) = rows.last() {
+            last = Some(key_fn(row));
+        }
+    }
+
+    rows
+}
+
+/// Columns of data fetched separately, joined on their dates into a single table.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlignedTable {
+    /// Names of the columns, the date first as in `DatasetMetadata::column_names`.
+    ///
+    pub column_names: Vec<String>,
+
+    /// One row for each date found in any of the columns, with `None` for the columns which have
+    /// no value at that date.
+    ///
+    pub rows: Vec<DataRow>,
+}
+
+impl AlignedTable {
+    /// Join the `(date, value)` rows of each of `columns`, named by `column_names` (the date
+    /// first, so there is one more name than there are columns), into rows sorted by date in the
+    /// given order.
+    ///
+    /// Dates are compared as strings, which sorts dates formatted as `YYYY-MM-DD` chronologically.
+    /// Should a column have several values at the same date, its last one is kept.
+    ///
+    pub fn align(column_names: Vec<String>,
+                 columns: Vec<Vec<(String, Option<f64>)>>,
+                 order: Order) -> AlignedTable
+    {
+        assert_eq!(column_names.len(), columns.len() + 1,
+                   "column_names: {:?}, columns: {}", column_names, columns.len());
+
+        let width = columns.len();
+        let mut dates: BTreeMap<String, Vec<Option<f64>>> = BTreeMap::new();
+
+        for (column, rows) in columns.into_iter().enumerate() {
+            for (date, value) in rows {
+                dates.entry(date).or_insert_with(|| vec![None; width])[column] = value;
+            }
+        }
+
+        let rows = dates.into_iter().map(|(date, values)| DataRow { date, values });
+
+        let rows = {
+            match order {
+                Order::asc => rows.collect(),
+                Order::desc => rows.rev().collect(),
+            }
+        };
+
+        AlignedTable { column_names, rows }
+    }
+}
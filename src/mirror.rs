@@ -0,0 +1,511 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use chrono::Utc;
+
+use has::HasMut;
+
+use serde::de::DeserializeOwned;
+
+use crate::{CancelToken, Result, Error, ValidationError};
+use crate::api_call::ApiCall;
+use crate::batch_query::{BatchQuery, BatchReport};
+use crate::calendar;
+use crate::parameters::{self, ApiArguments, ApiParameters};
+use crate::query::{CodeListQuery, DataQuery, DatasetMetadataQuery};
+use crate::types::DatasetMetadata;
+
+/// Name of the file of a mirrored database recording what was mirrored of its datasets.
+///
+const MANIFEST: &str = "manifest.json";
+
+/// Name of the file holding the metadata of a mirrored dataset.
+///
+const METADATA: &str = "metadata.json";
+
+/// Name of the file holding the rows of a mirrored dataset.
+///
+const DATA: &str = "data.csv";
+
+/// Copy of a whole database kept in a local directory, and refreshed incrementally.
+///
+/// Running the job fetches the code list of the database, the metadata of each of its datasets
+/// and then the full history of those which are new or were refreshed since the last run, i.e.
+/// whose `refreshed_at` advanced. These are laid out as
+///
+/// ```text
+/// {target_dir}/{database_code}/manifest.json
+/// {target_dir}/{database_code}/{dataset_code}/metadata.json
+/// {target_dir}/{database_code}/{dataset_code}/data.csv
+/// ```
+///
+/// where the metadata is a `DatasetMetadata` as JSON and the data is the rows of the dataset with
+/// its column names as header. The manifest records the `refreshed_at` of each dataset mirrored,
+/// so that the next run only downloads the rows of those which changed (or whose files went
+/// missing). Every file is written atomically, and the manifest last: an interrupted run leaves
+/// the previous mirror intact, only sending again the queries it did not complete.
+///
+/// ```rust,no_run
+/// use quandl_v3::mirror::MirrorJob;
+/// use quandl_v3::prelude::*;
+///
+/// let report = MirrorJob::new("WIKI").api_key("KEY").target_dir("mirror").threads(4).run();
+/// println!("{}", report.unwrap());
+/// ```
+///
+/// The calls are made by `BatchQuery`s (one for the metadata, one for the rows), to which the
+/// limits, threads and anonymity of the job are given. The second batch counts the calls of the
+/// first against the limits, as if made when it starts.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct MirrorJob {
+    database_code: String,
+    target_dir: PathBuf,
+    threads: Option<usize>,
+    limits: Vec<(usize, u64)>,
+    concurrent_calls: bool,
+    anonymous: bool,
+    cancel: Option<CancelToken>,
+    request_arguments: ApiArguments,
+}
+
+/// Outcome of `MirrorJob::run`.
+///
+/// It displays as a summary, e.g.
+///
+/// ```text
+/// datasets: 3 (1 downloaded, 1 unchanged, 1 failed)
+/// rows: 250
+/// calls: 5
+/// failed: MSFT: Errors returned by the Quandl API (QECx02).
+/// ```
+///
+/// and serializes (e.g. to JSON) field by field.
+///
+#[derive(Debug, Clone, PartialEq, Default, Serialize)]
+pub struct MirrorReport {
+    /// Number of datasets in the code list of the database.
+    ///
+    pub datasets: usize,
+
+    /// Codes of the datasets whose rows were downloaded by this run, in the order of the code
+    /// list.
+    ///
+    pub downloaded: Vec<String>,
+
+    /// Codes of the datasets left as they were, not having been refreshed since the last run.
+    ///
+    pub unchanged: Vec<String>,
+
+    /// Why the datasets which could not be mirrored failed, by dataset code. These are tried again
+    /// by the next run.
+    ///
+    pub failed: BTreeMap<String, Error>,
+
+    /// Number of rows written, over all the datasets downloaded.
+    ///
+    pub rows: usize,
+
+    /// Report of the batch fetching the metadata of the datasets.
+    ///
+    pub metadata: BatchReport,
+
+    /// Report of the batch fetching the rows of the datasets downloaded.
+    ///
+    pub data: BatchReport,
+}
+
+/// What the manifest of a mirrored database records about one of its datasets.
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Entry {
+    refreshed_at: String,
+    rows: usize,
+    mirrored_at: String,
+}
+
+impl MirrorJob {
+    /// Create a job mirroring the database `database_code` in the current directory.
+    ///
+    pub fn new<S: AsRef<str>>(database_code: S) -> Self {
+        MirrorJob {
+            database_code: database_code.as_ref().to_string(),
+            target_dir: PathBuf::from("."),
+            threads: None,
+            limits: vec![],
+            concurrent_calls: false,
+            anonymous: false,
+            cancel: None,
+            request_arguments: ApiArguments::from_config(),
+        }
+    }
+
+    /// Directory in which the database is mirrored, in a directory named after its code. It is
+    /// created if needed.
+    ///
+    pub fn target_dir<P: AsRef<Path>>(&mut self, target_dir: P) -> &mut Self {
+        self.target_dir = target_dir.as_ref().to_path_buf();
+        self
+    }
+
+    /// Maximum number of threads of the batches, see `BatchQuery::threads`.
+    ///
+    pub fn threads(&mut self, threads: usize) -> &mut Self {
+        assert!(threads > 0, "threads: {}", threads);
+        self.threads = Some(threads);
+        self
+    }
+
+    /// Rate limit of the API key, see `BatchQuery::limit`.
+    ///
+    pub fn limit(&mut self, limit: usize, timeout: u64) -> &mut Self {
+        assert!(limit > 0, "limit: {}", limit);
+        self.limits.push((limit, timeout));
+        self
+    }
+
+    /// Allow concurrent calls with the API key, which only premium keys may make, see
+    /// `BatchQuery::concurrent_calls`. Without it, the threads take turns using the key.
+    ///
+    pub fn concurrent_calls(&mut self) -> &mut Self {
+        self.concurrent_calls = true;
+        self
+    }
+
+    /// Mirror the database without an API key, within Quandl's limits for anonymous usage (see
+    /// `BatchQuery::anonymous`). Without an API key, a job which is not anonymous fails right
+    /// away.
+    ///
+    pub fn anonymous(&mut self) -> &mut Self {
+        self.anonymous = true;
+        self
+    }
+
+    /// Give up on the job once `token` is cancelled, its batches included (see
+    /// `BatchQuery::cancel_token`): the job then fails with `Error::Cancelled`, once the manifest
+    /// recorded the datasets mirrored until then, so that the next run does not download them
+    /// again.
+    ///
+    pub fn cancel_token(&mut self, token: &CancelToken) -> &mut Self {
+        self.cancel = Some(token.clone());
+        self
+    }
+
+    /// Check this job without running it, listing every problem found: a malformed API key or
+    /// database code, or a missing API key.
+    ///
+    pub fn validate(&self) -> ::std::result::Result<(), Vec<ValidationError>> {
+        let mut errors = self.request_arguments.validation_errors();
+        parameters::check_database_code(&self.request_arguments.code(&self.database_code),
+                                        &mut errors);
+
+        if self.request_arguments.api_key.is_none() && !self.anonymous {
+            errors.push(ValidationError::new("api_key", "is required to mirror a database, \
+                                                         unless the job is anonymous."));
+        }
+
+        parameters::validated(errors)
+    }
+
+    /// Mirror the database, as laid out above.
+    ///
+    /// The job fails as a whole when it does not validate, the code list cannot be fetched or the
+    /// mirror's directory or manifest cannot be read or written. Datasets which cannot be
+    /// mirrored (e.g. a failed call or a file which cannot be written) are only reported as such
+    /// in `MirrorReport::failed`, the rest of the database being mirrored regardless.
+    ///
+    pub fn run(&self) -> Result<MirrorReport> {
+        self.validate().map_err(Error::ValidationFailed)?;
+
+        let _scope = self.cancel.as_ref().map(CancelToken::enter);
+        crate::cancel::check()?;
+
+        let database_code = self.request_arguments.code(&self.database_code).into_owned();
+        let root = self.target_dir.join(&database_code);
+        fs::create_dir_all(&root).map_err(|e| io_error(&root, e))?;
+
+        let manifest_path = root.join(MANIFEST);
+        let mut manifest = read_manifest(&manifest_path)?;
+
+        let codes = self.with_arguments(CodeListQuery::new(&database_code)).send()?;
+        let mut report = MirrorReport { datasets: codes.len(), ..MirrorReport::default() };
+
+        // The metadata of every dataset, to tell which ones were refreshed.
+        let mut batch_query = self.batch_query::<DatasetMetadataQuery, DatasetMetadata>(1);
+        let mut queried = vec![];
+
+        for code in &codes {
+            let query = {
+                self.with_arguments(DatasetMetadataQuery::new(&database_code, &code.dataset_code))
+            };
+
+            match dataset_errors(&query) {
+                Some(errors) => {
+                    let error = Error::ValidationFailed(errors);
+                    report.failed.insert(code.dataset_code.clone(), error);
+                },
+
+                None => {
+                    queried.push(code.dataset_code.clone());
+                    batch_query.query(query);
+                },
+            }
+        }
+
+        let mut stale = vec![];
+        let (results, handle) = batch_query.run_with_report();
+
+        for (dataset_code, result) in queried.into_iter().zip(results) {
+            match result {
+                Ok(metadata) => {
+                    if is_current(manifest.get(&dataset_code), &metadata, &root.join(&dataset_code))
+                    {
+                        report.unchanged.push(dataset_code);
+                    } else {
+                        stale.push(metadata);
+                    }
+                },
+
+                Err(e) => { report.failed.insert(dataset_code, e); },
+            }
+        }
+
+        report.metadata = handle.get();
+
+        // The rows of the datasets refreshed, written by the workers as they arrive.
+        let calls = 1 + report.metadata.total().calls;
+        let mut batch_query = self.batch_query::<DataQuery, Vec<Vec<String>>>(calls);
+
+        for metadata in &stale {
+            batch_query.query(self.with_arguments(DataQuery::new(&database_code,
+                                                                 &metadata.dataset_code)));
+        }
+
+        let column_names: Arc<HashMap<String, Vec<String>>> = {
+            Arc::new(stale.iter()
+                .map(|metadata| (metadata.dataset_code.clone(), metadata.column_names.clone()))
+                .collect())
+        };
+
+        let batch_query = {
+            let root = root.clone();
+
+            batch_query.map_rows(move |code, rows| {
+                let directory = root.join(&code.dataset_code);
+                let header = column_names.get(&code.dataset_code).map(|x| &x[..]).unwrap_or(&[]);
+
+                write_rows(&directory, header, &rows).map(|_| rows.len())
+            })
+        };
+
+        let (results, handle) = batch_query.run_with_report();
+
+        for (metadata, result) in stale.into_iter().zip(results) {
+            let directory = root.join(&metadata.dataset_code);
+
+            let written = {
+                result.and_then(|rows| rows).and_then(|rows| {
+                    let json = serde_json::to_vec_pretty(&metadata).map_err(Error::from)?;
+                    write_atomically(&directory.join(METADATA), &json).map(|_| rows)
+                })
+            };
+
+            match written {
+                Ok(rows) => {
+                    let entry = {
+                        Entry {
+                            refreshed_at: metadata.refreshed_at.clone(),
+                            rows,
+                            mirrored_at: calendar::format_timestamp(&Utc::now()),
+                        }
+                    };
+
+                    manifest.insert(metadata.dataset_code.clone(), entry);
+                    report.downloaded.push(metadata.dataset_code);
+                    report.rows += rows;
+                },
+
+                Err(e) => { report.failed.insert(metadata.dataset_code, e); },
+            }
+        }
+
+        report.data = handle.get();
+
+        let json = serde_json::to_vec_pretty(&manifest).map_err(Error::from)?;
+        write_atomically(&manifest_path, &json)?;
+
+        crate::cancel::check()?;
+        Ok(report)
+    }
+
+    /// `query` with the API arguments of this job.
+    ///
+    fn with_arguments<A: HasMut<ApiArguments>>(&self, mut query: A) -> A {
+        *HasMut::<ApiArguments>::get_mut(&mut query) = self.request_arguments.clone();
+        query
+    }
+
+    /// Batch query set up as this job says, given that `calls` were already made with its key.
+    ///
+    fn batch_query<A, T>(&self, calls: usize) -> BatchQuery<A, T>
+        where T: DeserializeOwned + Clone + Send + 'static,
+              A: ApiCall<T> + Clone + Send + 'static,
+    {
+        let mut batch_query = BatchQuery::new();
+        batch_query.offset(calls);
+
+        for &(limit, timeout) in &self.limits {
+            batch_query.limit(limit, timeout);
+        }
+
+        if let Some(threads) = self.threads {
+            batch_query.threads(threads);
+        }
+
+        if self.concurrent_calls {
+            batch_query.concurrent_calls();
+        }
+
+        if self.anonymous {
+            batch_query.anonymous();
+        }
+
+        if let Some(ref token) = self.cancel {
+            batch_query.cancel_token(token);
+        }
+
+        batch_query
+    }
+}
+
+impl MirrorReport {
+    /// Number of calls made by the job, the code list included.
+    ///
+    pub fn calls(&self) -> usize {
+        1 + self.metadata.total().calls + self.data.total().calls
+    }
+}
+
+impl ::std::fmt::Display for MirrorReport {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        writeln!(f, "datasets: {} ({} downloaded, {} unchanged, {} failed)",
+                 self.datasets, self.downloaded.len(), self.unchanged.len(), self.failed.len())?;
+
+        writeln!(f, "rows: {}", self.rows)?;
+        writeln!(f, "calls: {}", self.calls())?;
+
+        for (dataset_code, error) in &self.failed {
+            writeln!(f, "failed: {}: {}", dataset_code, error)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Problems with `query` which would keep its dataset from being mirrored: those of its codes,
+/// and codes which are not names of directories (i.e. `.` and `..`, dots being allowed).
+///
+fn dataset_errors(query: &DatasetMetadataQuery) -> Option<Vec<ValidationError>> {
+    let mut errors = query.validate().err().unwrap_or_default();
+
+    if let "." | ".." = query.dataset_code() {
+        errors.push(ValidationError::new("dataset_code", format!("'{}' cannot be mirrored.",
+                                                                 query.dataset_code())));
+    }
+
+    Some(errors).filter(|errors| !errors.is_empty())
+}
+
+/// Whether the mirror of a dataset in `directory`, recorded in the manifest as `entry`, is still
+/// current given its `metadata` now.
+///
+/// Timestamps are compared as such, so that reformatting one does not count as a refresh. A
+/// `refreshed_at` which is not a timestamp is compared as it is.
+///
+fn is_current(entry: Option<&Entry>, metadata: &DatasetMetadata, directory: &Path) -> bool {
+    let entry = {
+        match entry {
+            Some(entry) => entry,
+            None => return false,
+        }
+    };
+
+    let advanced = {
+        match (calendar::parse_timestamp_utc(&entry.refreshed_at),
+               calendar::parse_timestamp_utc(&metadata.refreshed_at)) {
+            (Some(before), Some(now)) => now > before,
+            _ => entry.refreshed_at != metadata.refreshed_at,
+        }
+    };
+
+    !advanced && directory.join(METADATA).is_file() && directory.join(DATA).is_file()
+}
+
+/// The manifest at `path`, empty if there is none yet.
+///
+fn read_manifest(path: &Path) -> Result<BTreeMap<String, Entry>> {
+    match fs::read(path) {
+        Ok(content) => {
+            serde_json::from_slice(&content).map_err(|e| {
+                Error::IoError(format!("cannot read manifest {}: {}", path.display(), e))
+            })
+        },
+
+        Err(ref e) if e.kind() == ::std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+        Err(e) => Err(io_error(path, e)),
+    }
+}
+
+/// Write `rows` as CSV, headed by `header` unless it is empty, to the data file in `directory`,
+/// which is created if needed.
+///
+fn write_rows(directory: &Path, header: &[String], rows: &[Vec<String>]) -> Result<()> {
+    fs::create_dir_all(directory).map_err(|e| io_error(directory, e))?;
+
+    let mut writer = csv::WriterBuilder::new().flexible(true).from_writer(vec![]);
+
+    if !header.is_empty() {
+        writer.write_record(header)?;
+    }
+
+    for row in rows {
+        writer.write_record(row)?;
+    }
+
+    let data = writer.into_inner().map_err(|e| Error::IoError(e.to_string()))?;
+    write_atomically(&directory.join(DATA), &data)
+}
+
+/// Write `data` to `path` through a temporary file beside it, so that `path` is never seen
+/// partially written.
+///
+fn write_atomically(path: &Path, data: &[u8]) -> Result<()> {
+    let temporary = {
+        let mut name = path.file_name().unwrap_or_default().to_os_string();
+        name.push(".tmp");
+        path.with_file_name(name)
+    };
+
+    let result = {
+        File::create(&temporary)
+            .and_then(|mut file| file.write_all(data).and_then(|_| file.sync_all()))
+            .and_then(|_| fs::rename(&temporary, path))
+    };
+
+    if result.is_err() {
+        let _ = fs::remove_file(&temporary);
+    }
+
+    result.map_err(|e| io_error(path, e))
+}
+
+fn io_error(path: &Path, e: ::std::io::Error) -> Error {
+    Error::IoError(format!("{}: {}", path.display(), e))
+}
+
+impl ApiParameters for MirrorJob {}
+
+impl_has!(MirrorJob, ApiArguments, request_arguments);
@@ -0,0 +1,69 @@
+/// State of the CSV scanner at a given byte, mirroring how the `csv` crate tokenizes fields: a
+/// quote only opens a quoted field at the very start of that field.
+///
+#[derive(Copy, Clone, PartialEq)]
+enum State {
+    StartField,
+    Unquoted,
+    Quoted,
+    QuoteInQuoted,
+}
+
+/// Split a CSV buffer into at most `chunks` slices, cutting only on record boundaries.
+///
+/// Each slice is returned along with the index of its first record in the whole buffer, so
+/// decoding errors can still report absolute row numbers. Newlines inside quoted fields are not
+/// record boundaries; a CRLF terminator stays with the record it ends and a final record without
+/// a terminating newline is kept in the last slice.
+///
+pub fn split_records(data: &[u8], chunks: usize) -> Vec<(usize, &[u8])> {
+    if chunks <= 1 {
+        return vec![(0, data)];
+    }
+
+    let target = data.len() / chunks + 1;
+
+    let mut slices = vec![];
+    let mut state = State::StartField;
+    let mut start = 0;
+    let mut rows = 0;
+    let mut first_row = 0;
+    let mut record_is_empty = true;
+
+    for (index, &byte) in data.iter().enumerate() {
+        let ends_record = byte == b'\n' && state != State::Quoted;
+
+        state = match (state, byte) {
+            (State::Quoted, b'"') => State::QuoteInQuoted,
+            (State::Quoted, _) => State::Quoted,
+            (State::QuoteInQuoted, b'"') => State::Quoted,
+            (State::StartField, b'"') => State::Quoted,
+            (_, b',') | (_, b'\n') => State::StartField,
+            (_, _) => State::Unquoted,
+        };
+
+        if !ends_record {
+            record_is_empty &= byte == b'\r';
+            continue;
+        }
+
+        // Empty lines are skipped by the CSV reader and thus do not count as rows.
+        if !record_is_empty {
+            rows += 1;
+        }
+
+        record_is_empty = true;
+
+        if index + 1 - start >= target && slices.len() + 1 < chunks {
+            slices.push((first_row, &data[start..index + 1]));
+            start = index + 1;
+            first_row = rows;
+        }
+    }
+
+    if start < data.len() || slices.is_empty() {
+        slices.push((first_row, &data[start..]));
+    }
+
+    slices
+}
@@ -1,38 +1,461 @@
+use std::borrow::Cow;
+
 use has::*;
 
+use url::form_urlencoded::{Serializer, byte_serialize};
+
+use crate::{ApiKey, Result, Error, ValidationError};
+use crate::calendar::PeriodSpec;
+use crate::client::ClientConfig;
+use crate::config::Config;
 use crate::types::{Order, Frequency, Transform};
 
-#[derive(Debug, Clone, PartialEq, Default)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
 pub struct ApiArguments {
-    pub api_key: Option<String>,
+    pub api_key: Option<ApiKey>,
+    pub base_url: Option<String>,
+    pub api_version: Option<String>,
+    pub any_content_type: bool,
+    pub client: Option<ClientConfig>,
+    pub strict: bool,
+    pub max_response_bytes: Option<u64>,
+    pub auto_uppercase: bool,
+}
+
+impl ApiArguments {
+    /// Arguments of a new query, taking their defaults from the current `Config`.
+    ///
+    pub fn from_config() -> Self {
+        let config = Config::current();
+
+        ApiArguments {
+            api_key: config.api_key.as_ref().map(ApiKey::trimmed),
+            base_url: config.base_url.clone(),
+            client: config.client.clone(),
+            max_response_bytes: config.max_response_bytes,
+            auto_uppercase: config.auto_uppercase,
+            .. ApiArguments::default()
+        }
+    }
+
+    /// Check the arguments before anything is sent, rejecting API keys which cannot be valid
+    /// (without quoting them, since they are secrets).
+    ///
+    pub fn validate(&self) -> Result<()> {
+        match self.api_key_problem() {
+            Some(reason) => Err(Error::ParsingFailed(format!("invalid API key: {}.", reason))),
+            None => Ok(()),
+        }
+    }
+
+    /// Same as `validate`, also refusing a strict query for which `validate_query` reports any
+    /// problem.
+    ///
+    pub fn ready<F>(&self, validate_query: F) -> Result<()>
+        where F: FnOnce() -> ::std::result::Result<(), Vec<ValidationError>>
+    {
+        self.validate()?;
+
+        if self.strict {
+            validate_query().map_err(Error::ValidationFailed)?;
+        }
+
+        Ok(())
+    }
+
+    /// `code` as sent, i.e. uppercased if told to, see `ApiParameters::auto_uppercase`.
+    ///
+    pub fn code<'a>(&self, code: &'a str) -> Cow<'a, str> {
+        if self.auto_uppercase && code.chars().any(|x| x.is_lowercase()) {
+            Cow::Owned(code.to_uppercase())
+        } else {
+            Cow::Borrowed(code)
+        }
+    }
+
+    /// Problems with these arguments, see `ApiParameters::strict`.
+    ///
+    pub fn validation_errors(&self) -> Vec<ValidationError> {
+        let mut errors: Vec<ValidationError> = {
+            self.api_key_problem()
+                .map(|reason| ValidationError::new("api_key", format!("{}.", reason)))
+                .into_iter()
+                .collect()
+        };
+
+        if let Some(ref version) = self.api_version {
+            if version.is_empty() {
+                errors.push(ValidationError::new("api_version", "must not be empty."));
+            } else if version.contains('/') {
+                errors.push(ValidationError::new("api_version", format!("must not contain '/', \
+                                                                         got '{}'.",
+                                                                        version)));
+            } else if version.chars().any(char::is_whitespace) {
+                errors.push(ValidationError::new("api_version", format!("must not contain \
+                                                                         whitespace, got {:?}.",
+                                                                        version)));
+            }
+        }
+
+        errors
+    }
+
+    fn api_key_problem(&self) -> Option<String> {
+        let key = self.api_key.as_ref()?.expose_secret();
+
+        if key.is_empty() {
+            Some("it is empty".to_string())
+        } else {
+            key.chars()
+                .find(|&x| x.is_whitespace() || x.is_control() || x == '&' || x == '=')
+                .map(|x| format!("it contains {:?}", x))
+        }
+    }
 }
 
-#[derive(Debug, Clone, PartialEq, Default)]
+impl SearchArguments {
+    /// Problems with these arguments, see `ApiParameters::strict`.
+    ///
+    pub fn validation_errors(&self) -> Vec<ValidationError> {
+        let mut errors = vec![];
+
+        match self.per_page {
+            Some(0) => errors.push(ValidationError::new("per_page", "must be at least 1.")),
+
+            Some(n) if n > MAX_PER_PAGE => errors.push({
+                ValidationError::new("per_page", format!("must be at most {}, got {}.",
+                                                         MAX_PER_PAGE,
+                                                         n))
+            }),
+
+            _ => (),
+        }
+
+        if self.page == Some(0) {
+            errors.push(ValidationError::new("page", "must be at least 1, pages start at 1."));
+        }
+
+        errors
+    }
+}
+
+impl DataArguments {
+    /// Problems with these arguments, see `ApiParameters::strict`.
+    ///
+    pub fn validation_errors(&self) -> Vec<ValidationError> {
+        let mut errors = vec![];
+
+        if self.rows.is_some() && self.limit.is_some() {
+            errors.push(ValidationError::new("limit", "is the same parameter as rows, which is \
+                                                       also set."));
+        }
+
+        if self.column_index == Some(0) {
+            errors.push(ValidationError::new("column_index", "0 is the date column, which is always \
+                                                              returned; the first data column is \
+                                                              1."));
+        }
+
+        let start_date = date("start_date", self.start(), &mut errors);
+        let end_date = date("end_date", self.end(), &mut errors);
+
+        if let (Some(start_date), Some(end_date)) = (start_date, end_date) {
+            if start_date > end_date {
+                errors.push(ValidationError::new("start_date", format!("{} is after the end \
+                                                                        date {}.",
+                                                                       start_date,
+                                                                       end_date)));
+            }
+        }
+
+        errors
+    }
+
+    /// The `start_date` and `end_date` set, if any and valid.
+    ///
+    pub fn dates(&self) -> (Option<chrono::NaiveDate>, Option<chrono::NaiveDate>) {
+        let mut errors = vec![];
+
+        (date("start_date", self.start(), &mut errors),
+         date("end_date", self.end(), &mut errors))
+    }
+
+    /// The column selected with `DataParameters::column_index`, if any.
+    ///
+    pub(crate) fn column_index(&self) -> Option<usize> {
+        self.column_index
+    }
+
+    /// Select every column again, undoing `DataParameters::column_index`.
+    ///
+    #[cfg(feature = "batch")]
+    pub(crate) fn clear_column_index(&mut self) {
+        self.column_index = None;
+    }
+
+    /// The `start_date` to send, be it set as a date or as a period.
+    ///
+    fn start(&self) -> Option<(u16, u8, u8)> {
+        self.start_period.map(|period| ymd_of(period.start())).or(self.start_date)
+    }
+
+    /// The `end_date` to send, be it set as a date or as a period.
+    ///
+    fn end(&self) -> Option<(u16, u8, u8)> {
+        self.end_period.map(|period| ymd_of(period.end())).or(self.end_date)
+    }
+}
+
+/// Largest number of results per page Quandl serves for searches.
+///
+const MAX_PER_PAGE: usize = 100;
+
+/// Date of `ymd`, if any and valid; otherwise an error about `field` is pushed to `errors`.
+///
+fn date(field: &'static str, ymd: Option<(u16, u8, u8)>, errors: &mut Vec<ValidationError>)
+    -> Option<chrono::NaiveDate>
+{
+    let (year, month, day) = ymd?;
+    let date = chrono::NaiveDate::from_ymd_opt(year as i32, month as u32, day as u32);
+
+    if date.is_none() {
+        errors.push(ValidationError::new(field, format!("{:04}-{:02}-{:02} is not a valid date.",
+                                                        year,
+                                                        month,
+                                                        day)));
+    }
+
+    date
+}
+
+/// Year, month and day of `date`.
+///
+fn ymd_of(date: chrono::NaiveDate) -> (u16, u8, u8) {
+    use chrono::Datelike;
+
+    (date.year() as u16, date.month() as u8, date.day() as u8)
+}
+
+/// Year, month and day of `date`, given for `field` as a `YYYY-MM-DD` string.
+///
+fn parse_ymd(field: &str, date: &str) -> Result<(u16, u8, u8)> {
+    match crate::calendar::parse_date(date.trim()) {
+        Some(parsed) => Ok(ymd_of(parsed)),
+
+        None => Err(Error::ParsingFailed(format!("invalid {} '{}', expected an existing date \
+                                                  formatted as YYYY-MM-DD.",
+                                                 field,
+                                                 date))),
+    }
+}
+
+/// Longest database code `check_database_code` accepts, Quandl's being at most a dozen
+/// characters long.
+///
+const MAX_DATABASE_CODE_LEN: usize = 32;
+
+/// Longest dataset code `check_dataset_code` accepts.
+///
+const MAX_DATASET_CODE_LEN: usize = 128;
+
+/// Push an error about `database_code` to `errors` unless `code` is a valid database code, i.e.
+/// made of uppercase ASCII letters, digits and underscores.
+///
+pub fn check_database_code(code: &str, errors: &mut Vec<ValidationError>) {
+    check_code("database_code", code, MAX_DATABASE_CODE_LEN, "", errors);
+}
+
+/// Push an error about `dataset_code` to `errors` unless `code` is a valid dataset code: like a
+/// database code (see `check_database_code`), which may also contain dots and dashes.
+///
+pub fn check_dataset_code(code: &str, errors: &mut Vec<ValidationError>) {
+    check_code("dataset_code", code, MAX_DATASET_CODE_LEN, ".-", errors);
+}
+
+/// Push an error about `vendor_code` to `errors` unless `code` is a valid vendor code of the
+/// Tables API, formed as a database code (see `check_database_code`).
+///
+pub fn check_vendor_code(code: &str, errors: &mut Vec<ValidationError>) {
+    check_code("vendor_code", code, MAX_DATABASE_CODE_LEN, "", errors);
+}
+
+/// Push an error about `table_code` to `errors` unless `code` is a valid table code of the Tables
+/// API, formed as a database code (see `check_database_code`).
+///
+pub fn check_table_code(code: &str, errors: &mut Vec<ValidationError>) {
+    check_code("table_code", code, MAX_DATABASE_CODE_LEN, "", errors);
+}
+
+/// Push an error about `field` to `errors` unless `code` is made of at most `max_len` uppercase
+/// ASCII letters, digits, underscores and `extra` characters.
+///
+fn check_code(field: &'static str,
+              code: &str,
+              max_len: usize,
+              extra: &str,
+              errors: &mut Vec<ValidationError>)
+{
+    let allowed = |x: char| {
+        x.is_ascii_uppercase() || x.is_ascii_digit() || x == '_' || extra.contains(x)
+    };
+
+    if code.is_empty() {
+        errors.push(ValidationError::new(field, "must not be empty."));
+    } else if code.contains('/') {
+        errors.push(ValidationError::new(field, format!("must not contain '/', got '{}' (the \
+                                                         database and dataset codes are given \
+                                                         separately).",
+                                                        code)));
+    } else if code.chars().any(char::is_whitespace) {
+        errors.push(ValidationError::new(field, format!("must not contain whitespace, got {:?}.",
+                                                        code)));
+    } else if code.chars().count() > max_len {
+        errors.push(ValidationError::new(field, format!("must be at most {} characters long, got \
+                                                         {}.",
+                                                        max_len,
+                                                        code.chars().count())));
+    } else if code.chars().any(|x| x.is_lowercase() && allowed(x.to_ascii_uppercase())) {
+        errors.push(ValidationError::new(field, format!("must be uppercase, got '{}' (see \
+                                                         ApiParameters::auto_uppercase).",
+                                                        code)));
+    } else if let Some(x) = code.chars().find(|&x| !allowed(x)) {
+        let extra: Vec<String> = extra.chars().map(|x| format!(", '{}'", x)).collect();
+
+        errors.push(ValidationError::new(field, format!("must only contain uppercase letters, \
+                                                         digits, '_'{}, got {:?} in '{}'.",
+                                                        extra.concat(),
+                                                        x,
+                                                        code)));
+    }
+}
+
+/// `Ok` if there are no `errors`.
+///
+pub fn validated(errors: Vec<ValidationError>) -> ::std::result::Result<(), Vec<ValidationError>> {
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
 pub struct SearchArguments {
     keywords: Vec<String>,
-    per_page: Option<usize>,
-    page: Option<usize>,
+    pub per_page: Option<usize>,
+    pub page: Option<usize>,
 }
 
-#[derive(Debug, Clone, PartialEq, Default)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
 pub struct DataArguments {
-    rows: Option<usize>,
-    limit: Option<usize>,
-    order: Option<Order>,
-    collapse: Option<Frequency>,
+    pub rows: Option<usize>,
+    pub limit: Option<usize>,
+    pub order: Option<Order>,
+    pub collapse: Option<Frequency>,
     transform: Option<Transform>,
     end_date: Option<(u16, u8, u8)>,
     start_date: Option<(u16, u8, u8)>,
+    end_period: Option<PeriodSpec>,
+    start_period: Option<PeriodSpec>,
     column_index: Option<usize>,
 }
 
 /// Api parameters implemented by all queries.
 ///
+/// Queries start out with the defaults of the current `config::Config`, which these override.
+///
 pub trait ApiParameters: HasMut<ApiArguments> {
     /// Include your personal Quandl API key with your query.
     ///
+    /// Surrounding whitespace (e.g. the newline ending a key read from a file) is trimmed, so the
+    /// same key is always recognized as such. Keys which cannot be valid, i.e. empty or containing
+    /// whitespace, control characters, `&` or `=`, make the query fail before anything is sent.
+    /// The key is kept as an `ApiKey`, out of the query's `Debug` output.
+    ///
     fn api_key<S: AsRef<str>>(&mut self, api_key: S) -> &mut Self {
-        HasMut::<ApiArguments>::get_mut(self).api_key = Some(api_key.as_ref().to_string());
+        HasMut::<ApiArguments>::get_mut(self).api_key = Some(ApiKey::new(api_key.as_ref().trim()));
+        self
+    }
+
+    /// Send the query to another server than `QUANDL_API_URL`, e.g. a caching proxy or a mirror.
+    ///
+    /// The URL should include the API version segment, as in `"http://localhost:8080/api/v3"`.
+    ///
+    fn base_url<S: AsRef<str>>(&mut self, base_url: S) -> &mut Self {
+        HasMut::<ApiArguments>::get_mut(self).base_url = {
+            Some(base_url.as_ref().trim_end_matches('/').to_string())
+        };
+
+        self
+    }
+
+    /// Query another version of the API than `QUANDL_API_VERSION`, e.g. a beta one.
+    ///
+    /// The version replaces the last segment of the base URL's path, i.e. `v3` in `QUANDL_API_URL`
+    /// or in a base URL such as `"http://localhost:8080/api/v3"` (it is appended to a base URL
+    /// without any path). The rest of the URL is unchanged. A version containing `/` or
+    /// whitespace is reported by `validate`.
+    ///
+    fn api_version<S: AsRef<str>>(&mut self, version: S) -> &mut Self {
+        HasMut::<ApiArguments>::get_mut(self).api_version = Some(version.as_ref().to_string());
+        self
+    }
+
+    /// Skip checking the `Content-Type` of responses before parsing them.
+    ///
+    /// By default, a JSON (or CSV for data queries) response served with another content type
+    /// (e.g. an HTML page from a misbehaving proxy) is rejected with an error describing what was
+    /// received. This restores the old behavior of attempting to parse whatever came back, for
+    /// servers which do not set the header properly.
+    ///
+    fn accept_any_content_type(&mut self) -> &mut Self {
+        HasMut::<ApiArguments>::get_mut(self).any_content_type = true;
+        self
+    }
+
+    /// Send the query with a client configured by `config` rather than the default one, e.g. to
+    /// pin the certificates trusted for Quandl's endpoint.
+    ///
+    fn client_config(&mut self, config: &ClientConfig) -> &mut Self {
+        HasMut::<ApiArguments>::get_mut(self).client = Some(config.clone());
+        self
+    }
+
+    /// Give up on responses larger than `max` bytes, failing with `Error::ResponseTooLarge`
+    /// rather than buffering the whole body, e.g. to protect against a query missing its date
+    /// bounds on a dense dataset.
+    ///
+    /// A response advertising a larger `Content-Length` is rejected before its body is read,
+    /// others are aborted as soon as more than `max` bytes are received. The limit applies to each
+    /// request on its own, so a query sending several (e.g. a listing going through its pages)
+    /// is not limited as a whole. Downloads written to a file rather than kept in memory (see
+    /// `DatabaseDownloadQuery`) are not limited. Responses are not limited by default.
+    ///
+    fn max_response_bytes(&mut self, max: u64) -> &mut Self {
+        HasMut::<ApiArguments>::get_mut(self).max_response_bytes = Some(max);
+        self
+    }
+
+    /// Refuse to send the query when its `validate` method reports any problem (e.g. an
+    /// impossible date or a dataset code given as `"DATABASE/DATASET"`), failing with
+    /// `Error::ValidationFailed` instead.
+    ///
+    /// Queries are not strict by default: Quandl is left to report such problems, as it always
+    /// has been (at the cost of an API call).
+    ///
+    fn strict(&mut self, strict: bool) -> &mut Self {
+        HasMut::<ApiArguments>::get_mut(self).strict = strict;
+        self
+    }
+
+    /// Send the database and dataset codes of the query uppercased, as Quandl expects them, e.g.
+    /// `"wiki"` as `"WIKI"`. The codes are only normalized when the query is validated or sent,
+    /// they are left as given in its fields.
+    ///
+    /// Codes are not normalized by default: lowercase ones are reported by `validate`.
+    ///
+    fn auto_uppercase(&mut self, auto_uppercase: bool) -> &mut Self {
+        HasMut::<ApiArguments>::get_mut(self).auto_uppercase = auto_uppercase;
         self
     }
 
@@ -40,11 +463,9 @@ pub trait ApiParameters: HasMut<ApiArguments> {
     /// provided.
     ///
     fn fmt(&self) -> Option<String> {
-        if let Some(ref key) = Has::<ApiArguments>::get_ref(self).api_key {
-            Some(format!("api_key={}", key))
-        } else {
-            None
-        }
+        Has::<ApiArguments>::get_ref(self).api_key.as_ref().map(|key| {
+            Serializer::new(String::new()).append_pair("api_key", key.expose_secret()).finish()
+        })
     }
 }
 
@@ -52,13 +473,52 @@ pub trait ApiParameters: HasMut<ApiArguments> {
 ///
 pub trait SearchParameters: HasMut<SearchArguments> {
     /// Specify a vector/list of search keywords to retrieve only database/dataset related to those
-    /// search terms.
+    /// search terms, replacing any previous keywords, phrases and exclusions.
+    ///
+    /// Each keyword is split on whitespace into search terms, except within double quotes: quoted
+    /// phrases (e.g. `"\"crude oil\""`) and exclusions (e.g. `"-gasoline"`) are passed on as-is.
     ///
     fn query<V: AsRef<[S]>, S: AsRef<str>>(&mut self, keywords: V) -> &mut Self {
         HasMut::<SearchArguments>::get_mut(self).keywords = {
-            keywords.as_ref().iter().map(|x| x.as_ref().trim().to_string()).collect()
+            keywords.as_ref().iter().flat_map(|x| terms(x.as_ref())).collect()
+        };
+
+        self
+    }
+
+    /// Add a phrase to search for as a whole, i.e. a multi-word search term.
+    ///
+    /// The phrase is quoted for Quandl's search; double quotes it contains are dropped since they
+    /// cannot be escaped.
+    ///
+    fn phrase<S: AsRef<str>>(&mut self, phrase: S) -> &mut Self {
+        let words = words(phrase.as_ref());
+
+        if !words.is_empty() {
+            HasMut::<SearchArguments>::get_mut(self).keywords.push(format!("\"{}\"", words));
+        }
+
+        self
+    }
+
+    /// Exclude the entries matching `term` (or phrase, when it contains whitespace) from the
+    /// results. A leading `-` is optional.
+    ///
+    fn exclude<S: AsRef<str>>(&mut self, term: S) -> &mut Self {
+        let words = words(term.as_ref().trim().trim_start_matches('-'));
+
+        let term = {
+            if words.contains(' ') {
+                format!("-\"{}\"", words)
+            } else {
+                format!("-{}", words)
+            }
         };
 
+        if !words.is_empty() {
+            HasMut::<SearchArguments>::get_mut(self).keywords.push(term);
+        }
+
         self
     }
 
@@ -81,35 +541,70 @@ pub trait SearchParameters: HasMut<SearchArguments> {
     /// search parameters has been specified.
     ///
     fn fmt(&self) -> Option<String> {
-        let mut fmt = String::new();
-
         let arguments = Has::<SearchArguments>::get_ref(self);
 
-        if !arguments.keywords.is_empty() {
-            fmt.push_str(&format!("query={}", arguments.keywords[0]));
-
-            for keyword in arguments.keywords.iter().skip(1) {
-                fmt.push('+');
-                fmt.push_str(&keyword[..]);
+        // Terms are separated by `+`, so the spaces within phrases are encoded as `%20` instead.
+        let query = {
+            if arguments.keywords.is_empty() {
+                String::new()
+            } else {
+                let terms: Vec<String> = {
+                    arguments.keywords.iter()
+                        .map(|term| byte_serialize(term.as_bytes()).collect::<String>())
+                        .map(|term| term.replace('+', "%20"))
+                        .collect()
+                };
+
+                format!("query={}", terms.join("+"))
             }
+        };
 
-            fmt.push('&');
-        }
+        let mut fmt = Serializer::for_suffix(query, 0);
 
         if let Some(n) = arguments.per_page {
-            fmt.push_str(&format!("per_page={}&", n));
+            fmt.append_pair("per_page", &n.to_string());
         }
 
         if let Some(n) = arguments.page {
-            fmt.push_str(&format!("page={}&", n));
+            fmt.append_pair("page", &n.to_string());
         }
 
-        if fmt.pop().is_some() {
-            Some(fmt)
+        Some(fmt.finish()).filter(|fmt| !fmt.is_empty())
+    }
+}
+
+/// Split `keywords` on whitespace outside of double quotes.
+///
+fn terms(keywords: &str) -> Vec<String> {
+    let mut terms = vec![];
+    let mut term = String::new();
+    let mut quoted = false;
+
+    for x in keywords.chars() {
+        if x == '"' {
+            quoted = !quoted;
+        }
+
+        if x.is_whitespace() && !quoted {
+            if !term.is_empty() {
+                terms.push(term.split_off(0));
+            }
         } else {
-            None
+            term.push(x);
         }
     }
+
+    if !term.is_empty() {
+        terms.push(term);
+    }
+
+    terms
+}
+
+/// Words of `phrase` separated by single spaces, without its double quotes.
+///
+fn words(phrase: &str) -> String {
+    phrase.replace('"', "").split_whitespace().collect::<Vec<_>>().join(" ")
 }
 
 /// Data parameters implemented by data fetching queries.
@@ -163,7 +658,9 @@ pub trait DataParameters: HasMut<DataArguments> {
     /// (wasting one api call in the process).
     ///
     fn end_date(&mut self, year: u16, month: u8, day: u8) -> &mut Self {
-        HasMut::<DataArguments>::get_mut(self).end_date = Some((year, month, day));
+        let arguments = HasMut::<DataArguments>::get_mut(self);
+        arguments.end_date = Some((year, month, day));
+        arguments.end_period = None;
         self
     }
 
@@ -173,13 +670,86 @@ pub trait DataParameters: HasMut<DataArguments> {
     /// (wasting one api call in the process).
     ///
     fn start_date(&mut self, year: u16, month: u8, day: u8) -> &mut Self {
-        HasMut::<DataArguments>::get_mut(self).start_date = Some((year, month, day));
+        let arguments = HasMut::<DataArguments>::get_mut(self);
+        arguments.start_date = Some((year, month, day));
+        arguments.start_period = None;
+        self
+    }
+
+    /// Same as `end_date`, with the date given as a `YYYY-MM-DD` string (surrounding whitespace
+    /// aside), e.g. `"2016-02-29"`.
+    ///
+    /// Unlike with `end_date`, the date is checked right away: any other format, or a date which
+    /// does not exist, is an `Error::ParsingFailed` and leaves the query unchanged. Since this
+    /// returns a `Result`, chain it with `?`:
+    ///
+    /// ```rust
+    /// use quandl_v3::prelude::*;
+    ///
+    /// fn query(start: &str, end: &str) -> quandl_v3::Result<DataQuery> {
+    ///     let mut query = DataQuery::new("WIKI", "AAPL");
+    ///     query.start_date_str(start)?.end_date_str(end)?.order(Order::asc);
+    ///     Ok(query)
+    /// }
+    ///
+    /// assert!(query("2016-01-04", "2016-02-29").is_ok());
+    /// assert!(query("2016-01-04", "29/02/2016").is_err());
+    /// ```
+    ///
+    fn end_date_str<S: AsRef<str>>(&mut self, date: S) -> Result<&mut Self> {
+        let (year, month, day) = parse_ymd("end_date", date.as_ref())?;
+        Ok(self.end_date(year, month, day))
+    }
+
+    /// Same as `start_date`, with the date given as a `YYYY-MM-DD` string, see `end_date_str`.
+    ///
+    fn start_date_str<S: AsRef<str>>(&mut self, date: S) -> Result<&mut Self> {
+        let (year, month, day) = parse_ymd("start_date", date.as_ref())?;
+        Ok(self.start_date(year, month, day))
+    }
+
+    /// Specify the newest data point to be returned as the end of a fiscal period, e.g. of the
+    /// second quarter of a fiscal year ending in June:
+    ///
+    /// ```rust
+    /// use quandl_v3::prelude::*;
+    /// use quandl_v3::calendar::PeriodSpec;
+    ///
+    /// let mut query = DataQuery::new("WIKI", "AAPL");
+    /// query.start_period(PeriodSpec::fiscal_quarter(2016, 1).year_end_month(6))
+    ///      .end_period(PeriodSpec::fiscal_quarter(2016, 2).year_end_month(6));
+    ///
+    /// assert_eq!(DataParameters::fmt(&query),
+    ///            Some(String::from("end_date=2015-12-31&start_date=2015-07-01")));
+    /// ```
+    ///
+    /// The period is sent to Quandl as its last day, the `end_date`; whichever of `end_date` and
+    /// `end_period` is set last is the one sent.
+    ///
+    fn end_period(&mut self, period: PeriodSpec) -> &mut Self {
+        let arguments = HasMut::<DataArguments>::get_mut(self);
+        arguments.end_period = Some(period);
+        arguments.end_date = None;
+        self
+    }
+
+    /// Specify the earliest data point to be returned as the start of a fiscal period, sent as
+    /// its first day, the `start_date`; see `end_period`.
+    ///
+    fn start_period(&mut self, period: PeriodSpec) -> &mut Self {
+        let arguments = HasMut::<DataArguments>::get_mut(self);
+        arguments.start_period = Some(period);
+        arguments.start_date = None;
         self
     }
 
     /// Specify which column to be returned.
     ///
-    /// Note that the column 0, i.e. the 'date' column, is always returned.
+    /// Note that the column 0, i.e. the 'date' column, is always returned. Columns are numbered as
+    /// in the dataset's `column_names`, so the first column after the date is 1. Selecting the
+    /// date column on its own is not supported and an index of 0 fails validation (see
+    /// `ApiParameters::strict`); an index beyond the dataset's last column is reported by Quandl as
+    /// an API error.
     ///
     fn column_index(&mut self, index: usize) -> &mut Self {
         HasMut::<DataArguments>::get_mut(self).column_index = Some(index);
@@ -190,46 +760,42 @@ pub trait DataParameters: HasMut<DataArguments> {
     /// data parameters has been specified.
     ///
     fn fmt(&self) -> Option<String> {
-        let mut fmt = String::new();
+        let mut fmt = Serializer::new(String::new());
 
         let arguments = Has::<DataArguments>::get_ref(self);
 
         if let Some(n) = arguments.rows {
-            fmt.push_str(&format!("rows={}&", n)[..]);
+            fmt.append_pair("rows", &n.to_string());
         }
 
         if let Some(n) = arguments.limit {
-            fmt.push_str(&format!("limit={}&", n)[..]);
+            fmt.append_pair("limit", &n.to_string());
         }
 
         if let Some(order) = arguments.order {
-            fmt.push_str(&format!("order={:?}&", order)[..]);
+            fmt.append_pair("order", order.as_api_token());
         }
 
         if let Some(collapse) = arguments.collapse {
-            fmt.push_str(&format!("collapse={:?}&", collapse)[..]);
+            fmt.append_pair("collapse", collapse.as_api_token());
         }
 
         if let Some(transform) = arguments.transform {
-            fmt.push_str(&format!("transform={:?}&", transform)[..]);
+            fmt.append_pair("transform", transform.as_api_token());
         }
 
-        if let Some((year, month, day)) = arguments.end_date {
-            fmt.push_str(&format!("end_date={:#04}-{:#02}-{:#02}&", year, month, day));
+        if let Some((year, month, day)) = arguments.end() {
+            fmt.append_pair("end_date", &format!("{:#04}-{:#02}-{:#02}", year, month, day));
         }
 
-        if let Some((year, month, day)) = arguments.start_date {
-            fmt.push_str(&format!("start_date={:#04}-{:#02}-{:#02}&", year, month, day));
+        if let Some((year, month, day)) = arguments.start() {
+            fmt.append_pair("start_date", &format!("{:#04}-{:#02}-{:#02}", year, month, day));
         }
 
         if let Some(index) = arguments.column_index {
-            fmt.push_str(&format!("column_index={}&", index)[..]);
+            fmt.append_pair("column_index", &index.to_string());
         }
 
-        if fmt.pop().is_some() {
-            Some(fmt)
-        } else {
-            None
-        }
+        Some(fmt.finish()).filter(|fmt| !fmt.is_empty())
     }
 }
@@ -1,8 +1,37 @@
+pub use super::api_key::ApiKey;
+
+pub use super::cancel::CancelToken;
+
 pub use super::api_call::ApiCall;
 pub use super::api_call::QUANDL_API_URL;
+pub use super::api_call::QUANDL_API_VERSION;
+
+#[cfg(feature = "batch")] pub use super::batch_query::BatchQuery;
+#[cfg(feature = "batch")] pub use super::batch_query::Iterator as BatchQueryIterator;
+#[cfg(feature = "batch")] pub use super::batch_query::BatchPlan;
+#[cfg(feature = "batch")] pub use super::batch_query::BatchProgress;
+#[cfg(feature = "batch")] pub use super::batch_query::BatchReport;
+#[cfg(feature = "batch")] pub use super::batch_query::ByteEstimate;
+#[cfg(feature = "batch")] pub use super::batch_query::DatabaseStats;
+#[cfg(feature = "batch")] pub use super::batch_query::ReportHandle;
+#[cfg(feature = "batch")] pub use super::batch_query::SchedulingStrategy;
+#[cfg(feature = "batch")] pub use super::batch_query::ThreadEstimate;
+#[cfg(feature = "batch")] pub use super::batch_query::ThreadLimit;
+#[cfg(feature = "batch")] pub use super::batch_query::ThrottleEvent;
+#[cfg(feature = "batch")] pub use super::batch_query::default_threads;
+
+#[cfg(all(feature = "batch", feature = "async"))] pub use super::batch_stream::BatchEvent;
+#[cfg(all(feature = "batch", feature = "async"))] pub use super::batch_stream::BatchEvents;
+#[cfg(all(feature = "batch", feature = "async"))] pub use super::batch_stream::BatchStream;
+
+#[cfg(feature = "batch")] pub use super::key_pool::assign_keys;
+#[cfg(feature = "batch")] pub use super::key_pool::KeyProfile;
+#[cfg(feature = "batch")] pub use super::latency::LatencyHistogram;
+#[cfg(feature = "batch")] pub use super::latency::SlowQuery;
 
-pub use super::batch_query::BatchQuery;
-pub use super::batch_query::Iterator as BatchQueryIterator;
+pub use super::client::ClientConfig;
+
+pub use super::column_info::ColumnInfo;
 
 pub use super::parameters::ApiParameters;
 pub use super::parameters::DataParameters;
@@ -12,16 +41,40 @@ pub use super::query::DatabaseMetadataQuery;
 pub use super::query::DatasetMetadataQuery;
 pub use super::query::DatabaseSearch;
 pub use super::query::DatasetSearch;
+pub use super::query::DatasetListingQuery;
+pub use super::query::DatasetListingIterator;
 pub use super::query::CodeListQuery;
+pub use super::query::DatabaseDownloadQuery;
 pub use super::query::DataQuery;
 pub use super::query::DataAndMetadataQuery;
+pub use super::query::DatatableQuery;
+
+pub use super::schema::ColType;
+pub use super::schema::ColumnSchema;
+pub use super::schema::Schema;
+
+pub use super::template::QueryTemplate;
+
+#[cfg(feature = "batch")] pub use super::typed_fetch::fetch_typed;
+#[cfg(feature = "batch")] pub use super::typed_fetch::TypedFetch;
+
+#[cfg(feature = "batch")] pub use super::columns::fetch_columns;
 
 pub use super::types::Frequency;
 pub use super::types::Order;
 pub use super::types::Transform;
+pub use super::types::Comparison;
+pub use super::types::QueryKind;
 pub use super::types::DatabaseMetadata;
 pub use super::types::DatasetMetadata;
 pub use super::types::SearchMetadata;
+pub use super::types::PageCursor;
 pub use super::types::DatabaseList;
 pub use super::types::DatasetList;
 pub use super::types::Code;
+pub use super::types::DatasetMetadataLite;
+pub use super::types::Dataset;
+pub use super::types::DataRow;
+pub use super::types::Datatable;
+pub use super::types::DatatableColumn;
+pub use super::types::RowCount;
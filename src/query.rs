@@ -1,34 +1,55 @@
+use std::convert::TryFrom;
 use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Instant;
+
+#[cfg(not(feature = "zip"))]
+use bytes::Bytes;
+
+use has::{Has, HasMut};
 
 use serde::de::DeserializeOwned;
-use serde_json;
 
+use url::Url;
+use url::form_urlencoded::{Serializer, byte_serialize};
+
+use crate::backoff::Backoff;
+use crate::calendar::{self, DateParser};
+use crate::cancel::CancelToken;
 use crate::types::*;
+use crate::schema::Schema;
 use crate::parameters::*;
-use crate::api_call::ApiCall;
+use crate::api_call::{ApiCall, checked_body};
+use crate::download::{CSV, JSON, Request};
 
-use crate::{Result, Error};
+use crate::{Result, Error, ValidationError, Warning, Warnings, WithWarnings, ROW_CAPS};
 
 /// Database metadata query.
 ///
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct DatabaseMetadataQuery {
-    pub database_code: String,
+    database_code: Arc<str>,
+    refresh_sample_size: usize,
     request_arguments: ApiArguments,
 }
 
 /// Dataset metadata query.
 ///
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct DatasetMetadataQuery {
-    pub database_code: String,
-    pub dataset_code: String,
+    database_code: Arc<str>,
+    dataset_code: Arc<str>,
+    /// Quandl's numerical identifier of the dataset (see `DatasetMetadata::id`) when it is
+    /// addressed by it, see `by_id`. The codes are then left empty.
+    ///
+    pub id: Option<usize>,
     request_arguments: ApiArguments,
 }
 
 /// Query to search into a database metadata list.
 ///
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct DatabaseSearch {
     request_arguments: ApiArguments,
     search_arguments: SearchArguments,
@@ -36,49 +57,246 @@ pub struct DatabaseSearch {
 
 /// Query to search into a dataset metadata list.
 ///
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct DatasetSearch {
-    pub database_code: String,
+    database_code: Arc<str>,
+    request_arguments: ApiArguments,
+    search_arguments: SearchArguments,
+}
+
+/// Query the CSV listing of the datasets of a specific database, one page at a time.
+///
+/// This is a richer alternative to `CodeListQuery` (each dataset comes with its description and
+/// the dates it covers) which does not involve any zip archive. `send` fetches the page selected
+/// with `SearchParameters::page`, while `iter` goes through every page in turn.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DatasetListingQuery {
+    database_code: Arc<str>,
+    sorted_by_last_updated: bool,
     request_arguments: ApiArguments,
     search_arguments: SearchArguments,
 }
 
+/// Iterator over every dataset listed by a `DatasetListingQuery`, see `DatasetListingQuery::iter`.
+///
+#[derive(Debug)]
+pub struct DatasetListingIterator {
+    query: DatasetListingQuery,
+    page: usize,
+    per_page: usize,
+    buffer: ::std::vec::IntoIter<DatasetMetadataLite>,
+    deadline: Option<Instant>,
+    cancel: Option<CancelToken>,
+    since: Option<chrono::NaiveDateTime>,
+    cursor: Option<PageCursor>,
+    pending: Option<PageCursor>,
+    warnings: Warnings,
+    done: bool,
+}
+
 /// Query a list of dataset codes from a specific database.
 ///
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct CodeListQuery {
-    pub database_code: String,
+    database_code: Arc<str>,
+    #[cfg(feature = "zip")]
+    max_compressed_bytes: u64,
+    #[cfg(feature = "zip")]
+    max_uncompressed_bytes: u64,
+    #[cfg(feature = "zip")]
+    max_unzip_ratio: u64,
+    request_arguments: ApiArguments,
+}
+
+/// Query downloading the zipped data of a whole database to a file.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DatabaseDownloadQuery {
+    database_code: Arc<str>,
+    partial: bool,
     request_arguments: ApiArguments,
 }
 
 /// Query the data from a specific dataset.
 ///
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct DataQuery {
-    pub database_code: String,
-    pub dataset_code: String,
+    database_code: Arc<str>,
+    dataset_code: Arc<str>,
+    /// Quandl's numerical identifier of the dataset (see `DatasetMetadata::id`) when it is
+    /// addressed by it, see `by_id`. The codes are then left empty.
+    ///
+    pub id: Option<usize>,
+    strict_width: Option<usize>,
+    /// Whether or not Quandl is asked for the line of column names, see `infer_schema`.
+    ///
+    column_names: bool,
     data_arguments: DataArguments,
     request_arguments: ApiArguments,
 }
 
 /// Query the data and metadata from a specific dataset.
 ///
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct DataAndMetadataQuery {
-    pub database_code: String,
-    pub dataset_code: String,
+    database_code: Arc<str>,
+    dataset_code: Arc<str>,
+    /// Quandl's numerical identifier of the dataset (see `DatasetMetadata::id`) when it is
+    /// addressed by it, see `by_id`. The codes are then left empty.
+    ///
+    pub id: Option<usize>,
     data_arguments: DataArguments,
     request_arguments: ApiArguments,
 }
 
+/// Query the rows of a table of Quandl's Tables API (e.g. `SHARADAR/SF1` or `WIKI/PRICES`),
+/// rather than a time series.
+///
+/// Rows are filtered on the values of their columns (see `filter` and `filter_by`) and only some
+/// columns may be selected (see `columns`). Each query returns a page of rows, the next one being
+/// queried with `cursor_id`.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DatatableQuery {
+    vendor_code: Arc<str>,
+    table_code: Arc<str>,
+    filters: Vec<(String, Vec<String>)>,
+    columns: Vec<String>,
+    per_page: Option<usize>,
+    cursor_id: Option<String>,
+    request_arguments: ApiArguments,
+}
+
+/// Accessors of the codes of the queries, which keep them as `Arc<str>` so that cloning a query
+/// (e.g. from a `QueryTemplate`, or to retry it) does not copy them.
+///
+/// The codes used to be public `String` fields; they are now read with `database_code` and
+/// `dataset_code`, and changed with `set_database_code` and `set_dataset_code`.
+///
+macro_rules! code_accessors {
+    ($($query:ident),* ; $($dataset_query:ident),*) => {
+        $(
+            impl $query {
+                /// The code of the database queried, as given (see
+                /// `ApiParameters::auto_uppercase`).
+                ///
+                pub fn database_code(&self) -> &str {
+                    &self.database_code
+                }
+
+                /// Replace the code of the database queried.
+                ///
+                pub fn set_database_code<S: AsRef<str>>(&mut self, database_code: S) -> &mut Self {
+                    self.database_code = Arc::from(database_code.as_ref());
+                    self
+                }
+            }
+        )*
+
+        $(
+            impl $dataset_query {
+                /// The code of the dataset queried, as given (see
+                /// `ApiParameters::auto_uppercase`).
+                ///
+                pub fn dataset_code(&self) -> &str {
+                    &self.dataset_code
+                }
+
+                /// Replace the code of the dataset queried.
+                ///
+                pub fn set_dataset_code<S: AsRef<str>>(&mut self, dataset_code: S) -> &mut Self {
+                    self.dataset_code = Arc::from(dataset_code.as_ref());
+                    self
+                }
+            }
+        )*
+    };
+}
+
+code_accessors!(DatabaseMetadataQuery, DatasetMetadataQuery, DatasetSearch, DatasetListingQuery,
+                CodeListQuery, DatabaseDownloadQuery, DataQuery, DataAndMetadataQuery;
+                DatasetMetadataQuery, DataQuery, DataAndMetadataQuery);
+
 impl DatabaseMetadataQuery {
     /// Create a new database metadata query.
     ///
     pub fn new<S: AsRef<str>>(database_code: S) -> Self {
         DatabaseMetadataQuery {
-            database_code: database_code.as_ref().to_string(),
-            request_arguments: ApiArguments::default(),
+            database_code: Arc::from(database_code.as_ref()),
+            refresh_sample_size: 10,
+            request_arguments: ApiArguments::from_config(),
+        }
+    }
+
+    /// Same as `new`, but failing right away with `Error::ValidationFailed` when `database_code`
+    /// is invalid rather than leaving it to `validate` (see `ApiParameters::auto_uppercase`).
+    ///
+    pub fn from_code<S: AsRef<str>>(database_code: S) -> Result<Self> {
+        checked_codes(DatabaseMetadataQuery::new(database_code), |query, errors| {
+            check_database_code(&query.request_arguments.code(&query.database_code), errors)
+        })
+    }
+
+    /// Check this query without sending it, listing every problem found: a malformed API key or
+    /// database code.
+    ///
+    /// This is what a strict query does before being sent, see `ApiParameters::strict`.
+    ///
+    pub fn validate(&self) -> ::std::result::Result<(), Vec<ValidationError>> {
+        let mut errors = self.request_arguments.validation_errors();
+        check_database_code(&self.request_arguments.code(&self.database_code), &mut errors);
+        validated(errors)
+    }
+
+    /// Number of datasets sampled by `refreshed_since` when the database's metadata does not
+    /// report when it was last refreshed. Defaults to 10.
+    ///
+    pub fn refresh_sample_size(&mut self, n: usize) -> &mut Self {
+        assert!(n > 0, "n: {}", n);
+        self.refresh_sample_size = n;
+        self
+    }
+
+    /// Whether or not the database's content changed after `since` (a UTC timestamp).
+    ///
+    /// This relies on the `last_refreshed_at` field of the database's metadata. When Quandl does
+    /// not report it, the metadata of the first `refresh_sample_size` datasets listed for the
+    /// database are fetched instead (one more API call) and the latest of their `refreshed_at`
+    /// is used (`false` if the database lists no dataset). That is only a heuristic: a dataset
+    /// outside the sample may have been updated.
+    ///
+    pub fn refreshed_since(&self, since: chrono::NaiveDateTime) -> Result<bool> {
+        let metadata: DatabaseMetadata = self.send()?;
+
+        if let Some(timestamp) = metadata.last_refresh()? {
+            return Ok(timestamp > since);
+        }
+
+        let datasets = {
+            let mut search = DatasetSearch::new(&self.database_code);
+
+            search.per_page(self.refresh_sample_size);
+            search.request_arguments = self.request_arguments.clone();
+            search.send()?.datasets
+        };
+
+        let mut latest = None;
+
+        for dataset in &datasets {
+            latest = latest.max(Some(dataset.last_refresh()?));
         }
+
+        Ok(latest.map(|timestamp| timestamp > since).unwrap_or(false))
+    }
+
+    /// Decode a JSON payload as returned by Quandl for this query (e.g. one previously obtained
+    /// through `encoded_data`).
+    ///
+    /// This is what `send` uses once the metadata is downloaded.
+    ///
+    pub fn decode(&self, json_data: &[u8]) -> Result<DatabaseMetadata> {
+        single_element(crate::download::json(json_data)?)
     }
 }
 
@@ -87,11 +305,56 @@ impl DatasetMetadataQuery {
     ///
     pub fn new<S1: AsRef<str>, S2: AsRef<str>>(database_code: S1, dataset_code: S2) -> Self {
         DatasetMetadataQuery {
-            database_code: database_code.as_ref().to_string(),
-            dataset_code: dataset_code.as_ref().to_string(),
-            request_arguments: ApiArguments::default(),
+            database_code: Arc::from(database_code.as_ref()),
+            dataset_code: Arc::from(dataset_code.as_ref()),
+            id: None,
+            request_arguments: ApiArguments::from_config(),
         }
     }
+
+    /// Same as `new`, from the full code of the dataset (e.g. `"WIKI/AAPL"`), but failing right
+    /// away with `Error::ValidationFailed` when either code is invalid rather than leaving it to
+    /// `validate` (see `ApiParameters::auto_uppercase`).
+    ///
+    pub fn from_code<S: AsRef<str>>(code: S) -> Result<Self> {
+        let (database_code, dataset_code) = split_code(code.as_ref())?;
+
+        checked_codes(DatasetMetadataQuery::new(database_code, dataset_code), |query, errors| {
+            check_dataset(None,
+                          &query.database_code,
+                          &query.dataset_code,
+                          &query.request_arguments,
+                          errors)
+        })
+    }
+
+    /// Create a new dataset metadata query for the dataset of numerical identifier `id`, which
+    /// (unlike its codes) remains the same when the dataset is renamed.
+    ///
+    pub fn by_id(id: usize) -> Self {
+        let mut query = DatasetMetadataQuery::new("", "");
+        query.id = Some(id);
+        query
+    }
+
+    /// Check this query without sending it, listing every problem found: a malformed API key,
+    /// database code or dataset code, or codes set along with an `id`.
+    ///
+    pub fn validate(&self) -> ::std::result::Result<(), Vec<ValidationError>> {
+        let mut errors = self.request_arguments.validation_errors();
+        check_dataset(self.id, &self.database_code, &self.dataset_code, &self.request_arguments,
+                      &mut errors);
+        validated(errors)
+    }
+
+    /// Decode a JSON payload as returned by Quandl for this query (e.g. one previously obtained
+    /// through `encoded_data`).
+    ///
+    /// This is what `send` uses once the metadata is downloaded.
+    ///
+    pub fn decode(&self, json_data: &[u8]) -> Result<DatasetMetadata> {
+        single_element(crate::download::json(json_data)?)
+    }
 }
 
 impl DatabaseSearch {
@@ -99,10 +362,50 @@ impl DatabaseSearch {
     ///
     pub fn new() -> Self {
         DatabaseSearch {
-            request_arguments: ApiArguments::default(),
+            request_arguments: ApiArguments::from_config(),
             search_arguments: SearchArguments::default(),
         }
     }
+
+    /// Check this query without sending it, listing every problem found: a malformed API key, or
+    /// a page number or size Quandl does not serve.
+    ///
+    pub fn validate(&self) -> ::std::result::Result<(), Vec<ValidationError>> {
+        let mut errors = self.request_arguments.validation_errors();
+        errors.extend(self.search_arguments.validation_errors());
+        validated(errors)
+    }
+}
+
+impl DatabaseSearch {
+    /// Fetch the page of results following `cursor` (see `DatabaseList::cursor`), which replaces
+    /// the paging parameters of this query.
+    ///
+    /// Databases already listed before the cursor, found at the start of the page because the
+    /// results shifted since, are left out with a `Warning::PageOverlap`. A `Warning::PageSkip`
+    /// tells when there are fewer results than when the cursor was taken, so that some may have
+    /// been skipped.
+    ///
+    pub fn resume(&self, cursor: &PageCursor) -> Result<WithWarnings<DatabaseList>> {
+        let mut query = self.clone();
+        query.page(cursor.next_page()).per_page(cursor.per_page);
+
+        let mut list: DatabaseList = query.send()?;
+
+        let (repeated, warnings) = {
+            let codes = list.databases.iter().map(|x| &x.database_code[..]);
+            drift(cursor, cursor.next_page(), codes, Some(list.meta.total_count))
+        };
+
+        list.databases.drain(..repeated);
+        Ok(WithWarnings { value: list, warnings })
+    }
+}
+
+impl Default for DatabaseSearch {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl DatasetSearch {
@@ -110,283 +413,2377 @@ impl DatasetSearch {
     ///
     pub fn new<S: AsRef<str>>(database_code: S) -> Self {
         DatasetSearch {
-            database_code: database_code.as_ref().to_string(),
-            request_arguments: ApiArguments::default(),
+            database_code: Arc::from(database_code.as_ref()),
+            request_arguments: ApiArguments::from_config(),
             search_arguments: SearchArguments::default(),
         }
     }
-}
 
-impl CodeListQuery {
-    /// Create a new code list query.
+    /// Same as `new`, but failing right away with `Error::ValidationFailed` when `database_code`
+    /// is invalid rather than leaving it to `validate` (see `ApiParameters::auto_uppercase`).
     ///
-    pub fn new<S: AsRef<str>>(database_code: S) -> Self {
-        CodeListQuery {
-            database_code: database_code.as_ref().to_string(),
-            request_arguments: ApiArguments::default(),
-        }
+    pub fn from_code<S: AsRef<str>>(database_code: S) -> Result<Self> {
+        checked_codes(DatasetSearch::new(database_code), |query, errors| {
+            check_database_code(&query.request_arguments.code(&query.database_code), errors)
+        })
+    }
+
+    /// Check this query without sending it, listing every problem found: a malformed API key or
+    /// database code, or a page number or size Quandl does not serve.
+    ///
+    pub fn validate(&self) -> ::std::result::Result<(), Vec<ValidationError>> {
+        let mut errors = self.request_arguments.validation_errors();
+        check_database_code(&self.request_arguments.code(&self.database_code), &mut errors);
+        errors.extend(self.search_arguments.validation_errors());
+        validated(errors)
     }
 }
 
-impl DataQuery {
-    /// Create a new data query.
+impl DatasetSearch {
+    /// Fetch the page of results following `cursor` (see `DatasetList::cursor`), as with
+    /// `DatabaseSearch::resume`.
     ///
-    pub fn new<S1: AsRef<str>, S2: AsRef<str>>(database_code: S1, dataset_code: S2) -> Self {
-        DataQuery {
-            database_code: database_code.as_ref().to_string(),
-            dataset_code: dataset_code.as_ref().to_string(),
-            data_arguments: DataArguments::default(),
-            request_arguments: ApiArguments::default(),
-        }
+    pub fn resume(&self, cursor: &PageCursor) -> Result<WithWarnings<DatasetList>> {
+        let mut query = self.clone();
+        query.page(cursor.next_page()).per_page(cursor.per_page);
+
+        let mut list: DatasetList = query.send()?;
+
+        let (repeated, warnings) = {
+            let codes = list.datasets.iter().map(|x| &x.dataset_code[..]);
+            drift(cursor, cursor.next_page(), codes, Some(list.meta.total_count))
+        };
+
+        list.datasets.drain(..repeated);
+        Ok(WithWarnings { value: list, warnings })
     }
 }
 
-impl DataAndMetadataQuery {
-    /// Create a new data and metadata query.
+impl DatasetListingQuery {
+    /// Create a new dataset listing query.
     ///
-    pub fn new<S1: AsRef<str>, S2: AsRef<str>>(database_code: S1, dataset_code: S2) -> Self {
-        DataAndMetadataQuery {
-            database_code: database_code.as_ref().to_string(),
-            dataset_code: dataset_code.as_ref().to_string(),
-            data_arguments: DataArguments::default(),
-            request_arguments: ApiArguments::default(),
+    pub fn new<S: AsRef<str>>(database_code: S) -> Self {
+        DatasetListingQuery {
+            database_code: Arc::from(database_code.as_ref()),
+            sorted_by_last_updated: false,
+            request_arguments: ApiArguments::from_config(),
+            search_arguments: SearchArguments::default(),
         }
     }
-}
 
-impl ApiCall<DatabaseMetadata> for DatabaseMetadataQuery {
-    fn send(&self) -> Result<DatabaseMetadata> {
-        let json_data = {
-            match String::from_utf8(ApiCall::<DatabaseMetadata>::encoded_data(self)?) {
-                Ok(json) => json,
-                Err(e) => { return Err(Error::ParsingFailed(e.to_string())); }
-            }
-        };
+    /// Same as `new`, but failing right away with `Error::ValidationFailed` when `database_code`
+    /// is invalid rather than leaving it to `validate` (see `ApiParameters::auto_uppercase`).
+    ///
+    pub fn from_code<S: AsRef<str>>(database_code: S) -> Result<Self> {
+        checked_codes(DatasetListingQuery::new(database_code), |query, errors| {
+            check_database_code(&query.request_arguments.code(&query.database_code), errors)
+        })
+    }
 
-        match serde_json::from_str::<BTreeMap<String, DatabaseMetadata>>(&json_data[..]) {
-            Ok(tree) => {
-                if tree.len() == 1 {
-                    Ok(tree.iter().next().unwrap().1.clone())
-                } else {
-                    Err(Error::ParsingFailed(format!("Expected a single element, got {}.",
-                                                     tree.len())))
-                }
-            },
+    /// Check this query without sending it, listing every problem found: a malformed API key or
+    /// database code, or a page number or size Quandl does not serve.
+    ///
+    pub fn validate(&self) -> ::std::result::Result<(), Vec<ValidationError>> {
+        let mut errors = self.request_arguments.validation_errors();
+        check_database_code(&self.request_arguments.code(&self.database_code), &mut errors);
+        errors.extend(self.search_arguments.validation_errors());
+        validated(errors)
+    }
 
-            Err(e) => Err(Error::ParsingFailed(e.to_string())),
+    /// Iterate over every dataset of the listing, fetching the pages lazily starting from the one
+    /// selected with `SearchParameters::page` (the first by default).
+    ///
+    /// Pages hold `per_page` datasets (100 by default); the iteration ends after the first page
+    /// holding fewer than that, or after the first error.
+    ///
+    pub fn iter(&self) -> DatasetListingIterator {
+        DatasetListingIterator {
+            query: self.clone(),
+            page: self.search_arguments.page.unwrap_or(1),
+            per_page: self.search_arguments.per_page.unwrap_or(LISTING_PER_PAGE),
+            buffer: vec![].into_iter(),
+            deadline: None,
+            cancel: None,
+            since: None,
+            cursor: None,
+            pending: None,
+            warnings: vec![],
+            done: false,
         }
     }
 
-    fn fmt_prefix(&self) -> Option<String> {
-        Some(format!("/databases/{}.json", self.database_code))
+    /// Iterate over the datasets of the listing after `cursor` (see
+    /// `DatasetListingIterator::cursor`), as with `iter`.
+    ///
+    /// Datasets already listed before the cursor, found at the start of the next page because the
+    /// listing shifted since, are left out with a `Warning::PageOverlap` (see
+    /// `DatasetListingIterator::warnings`). The same goes from a page to the next one during the
+    /// iteration.
+    ///
+    pub fn resume(&self, cursor: &PageCursor) -> DatasetListingIterator {
+        let mut iterator = self.iter();
+        iterator.page = cursor.next_page();
+        iterator.per_page = cursor.per_page;
+        iterator.cursor = Some(cursor.clone());
+        iterator
     }
 
-    fn fmt_arguments(&self) -> Option<String> {
-        ApiParameters::fmt(self)
+    /// List the most recently updated datasets first (`sort_by=last_updated`).
+    ///
+    pub fn sort_by_last_updated(&mut self) -> &mut Self {
+        self.sorted_by_last_updated = true;
+        self
     }
-}
 
-impl ApiCall<DatasetMetadata> for DatasetMetadataQuery {
-    fn send(&self) -> Result<DatasetMetadata> {
-        let json_data = {
-            match String::from_utf8(ApiCall::<DatasetMetadata>::encoded_data(self)?) {
-                Ok(json) => json,
-                Err(e) => { return Err(Error::ParsingFailed(e.to_string())); }
+    /// Iterate over the datasets of the listing refreshed after `since` (a UTC timestamp), e.g.
+    /// to only update those of a mirror which changed since it was last synchronized.
+    ///
+    /// The listing is sorted by last update (see `sort_by_last_updated`) and gone through as with
+    /// `iter`, skipping older datasets, but the iteration also ends after the first page holding
+    /// none refreshed after `since`: the pages which would follow are only older.
+    ///
+    pub fn refreshed_since(&self, since: chrono::NaiveDateTime) -> DatasetListingIterator {
+        let mut query = self.clone();
+        query.sort_by_last_updated();
+
+        let mut iterator = query.iter();
+        iterator.since = Some(since);
+        iterator
+    }
+
+    /// Decode a page of the CSV listing as returned by Quandl (e.g. one previously obtained
+    /// through `encoded_data`).
+    ///
+    /// Columns are matched by name in the header, so their order does not matter and unknown
+    /// columns are ignored; `dataset_code`, `name` and `refreshed_at` are required. An empty
+    /// payload is an empty page.
+    ///
+    pub fn decode(&self, csv_data: &[u8]) -> Result<Vec<DatasetMetadataLite>> {
+        if csv_data.iter().all(u8::is_ascii_whitespace) {
+            return Ok(vec![]);
+        }
+
+        let mut reader = csv::ReaderBuilder::new().trim(csv::Trim::Headers).from_reader(csv_data);
+
+        {
+            let headers = reader.headers()?;
+
+            for column in &["dataset_code", "name", "refreshed_at"] {
+                if !headers.iter().any(|header| header == *column) {
+                    return Err(Error::csv(1, format!("missing column '{}' in the header of the \
+                                                      dataset listing.",
+                                                     column)));
+                }
             }
-        };
+        }
+
+        let mut datasets = vec![];
 
-        match serde_json::from_str::<BTreeMap<String, DatasetMetadata>>(&json_data[..]) {
-            Ok(tree) => {
-                if tree.len() == 1 {
-                    Ok(tree.iter().next().unwrap().1.clone())
-                } else {
-                    Err(Error::ParsingFailed(format!("Expected a single element, got {}.",
-                                                     tree.len())))
+        for (index, record) in reader.deserialize().enumerate() {
+            // The first record of the listing is its header.
+            let row = index + 2;
+            let record: ListingRecord = record.map_err(|e| Error::csv(row, e))?;
+
+            let date = |field: &str, value: &str| {
+                let value = value.trim();
+
+                if value.is_empty() {
+                    return Ok(None);
                 }
-            },
 
-            Err(e) => Err(Error::ParsingFailed(e.to_string())),
-        }
-    }
+                match calendar::parse_date(value) {
+                    Some(date) => Ok(Some(date)),
+                    None => Err(Error::csv(row, format!("invalid {} '{}' for dataset {}.",
+                                                        field,
+                                                        value,
+                                                        record.dataset_code))),
+                }
+            };
 
-    fn fmt_prefix(&self) -> Option<String> {
-        Some(format!("/datasets/{}/{}/metadata.json", self.database_code, self.dataset_code))
-    }
+            let oldest = date("oldest_available_date", &record.oldest_available_date)?;
+            let newest = date("newest_available_date", &record.newest_available_date)?;
 
-    fn fmt_arguments(&self) -> Option<String> {
-        ApiParameters::fmt(self)
+            let refreshed_at = {
+                match calendar::parse_timestamp(record.refreshed_at.trim()) {
+                    Some(timestamp) => timestamp,
+
+                    None => return Err(Error::csv(row, format!("invalid refreshed_at '{}' for \
+                                                                dataset {}.",
+                                                               record.refreshed_at,
+                                                               record.dataset_code))),
+                }
+            };
+
+            datasets.push(DatasetMetadataLite {
+                code: record.dataset_code,
+                name: record.name,
+                description: record.description,
+                refreshed_at,
+                oldest,
+                newest,
+            });
+        }
+
+        Ok(datasets)
     }
 }
 
-impl ApiCall<DatabaseList> for DatabaseSearch {
-    fn fmt_prefix(&self) -> Option<String> {
-        Some(String::from("/databases.json"))
+impl DatasetListingIterator {
+    /// Position after the last page whose datasets were all yielded, to resume the iteration
+    /// from with `DatasetListingQuery::resume`, unless none was yet.
+    ///
+    pub fn cursor(&self) -> Option<&PageCursor> {
+        self.cursor.as_ref()
     }
 
-    fn fmt_arguments(&self) -> Option<String> {
-        let arg_1 = ApiParameters::fmt(self);
-        let arg_2 = SearchParameters::fmt(self);
-
-        if arg_1.is_some() && arg_2.is_some() {
-            Some(format!("{}&{}", arg_1.unwrap(), arg_2.unwrap()))
-        } else if arg_1.is_some() {
-            arg_1
-        } else if arg_2.is_some() {
-            arg_2
-        } else {
-            None
-        }
+    /// Warnings encountered so far, about the listing shifting between pages.
+    ///
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
     }
-}
 
-impl ApiCall<DatasetList> for DatasetSearch {
-    fn fmt_prefix(&self) -> Option<String> {
-        Some(String::from("/datasets.json"))
+    /// Give up once `deadline` passes, as with `ApiCall::send_with_deadline`: each page is fetched
+    /// with what is left of the budget, and the iteration ends with an `Error::DeadlineExceeded`
+    /// if it is spent before the next page is requested.
+    ///
+    pub fn deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
     }
 
-    fn fmt_arguments(&self) -> Option<String> {
-        let arg_1 = ApiParameters::fmt(self);
-        let arg_2 = SearchParameters::fmt(self);
-
-        if arg_1.is_some() && arg_2.is_some() {
-            Some(format!("{}&{}&database_code={}", arg_1.unwrap(),
-                                                   arg_2.unwrap(),
-                                                   self.database_code))
-        } else if arg_1.is_some() {
-            Some(format!("{}&database_code={}", arg_1.unwrap(), self.database_code))
-        } else if arg_2.is_some() {
-            Some(format!("{}&database_code={}", arg_2.unwrap(), self.database_code))
-        } else {
-            None
-        }
+    /// Give up once `token` is cancelled, as with `ApiCall::send_with_cancel`: the page being
+    /// fetched is abandoned, and the iteration ends with an `Error::Cancelled` when the next page
+    /// is requested, the datasets of the pages already fetched being yielded until then.
+    ///
+    pub fn cancel_token(mut self, token: &CancelToken) -> Self {
+        self.cancel = Some(token.clone());
+        self
     }
 }
 
-impl ApiCall<Vec<Code>> for CodeListQuery {
-    fn send(&self) -> Result<Vec<Code>> {
-        use zip::read::ZipArchive;
-        use std::io::{Cursor, Read};
+impl Iterator for DatasetListingIterator {
+    type Item = Result<DatasetMetadataLite>;
 
-        let zipped_data = self.encoded_data()?;
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(dataset) = self.buffer.next() {
+                if self.buffer.len() == 0 {
+                    self.cursor = self.pending.take().or(self.cursor.take());
+                }
 
-        match ZipArchive::new(Cursor::new(zipped_data)) {
-            Ok(mut files) => {
-                let csv = {
-                    let mut csv = String::new();
+                return Some(Ok(dataset));
+            }
 
-                    for index in 0..files.len() {
-                        if let Err(e) = files.by_index(index).unwrap().read_to_string(&mut csv) {
-                            return Err(Error::ParsingFailed(e.to_string()));
-                        }
-                    }
+            if self.done {
+                return None;
+            }
 
-                    csv
-                };
+            self.query.page(self.page).per_page(self.per_page);
 
-                let mut reader = csv::Reader::from_reader(Cursor::new(csv));
-                let mut codes: Vec<Code> = vec![];
+            let _scope = self.cancel.as_ref().map(CancelToken::enter);
 
-                for record in reader.deserialize() {
-                    let record: (String, String) = {
-                        match record {
-                            Ok(record) => record,
-                            Err(e) => return Err(Error::ParsingFailed(e.to_string())),
-                        }
+            let page = {
+                match self.deadline {
+                    Some(deadline) => self.query.send_with_deadline(deadline),
+                    None => self.query.send(),
+                }
+            };
+
+            match page {
+                Ok(mut datasets) => {
+                    self.done = datasets.len() < self.per_page;
+
+                    let pending = PageCursor {
+                        page: self.page,
+                        per_page: self.per_page,
+                        total_count: None,
+                        last_code: datasets.last().map(|x| x.code.clone()),
                     };
 
-                    let (database_code, dataset_code) = {
-                        let pair: Vec<_> = record.0.split('/').collect();
+                    if let Some(ref cursor) = self.cursor {
+                        let (repeated, warnings) = {
+                            drift(cursor, self.page, datasets.iter().map(|x| &x.code[..]), None)
+                        };
 
-                        if pair.len() != 2 {
-                            let error_message = {
-                                "Invalid format for dataset codes in unzipped code list."
-                            };
+                        datasets.drain(..repeated);
+                        self.warnings.extend(warnings);
+                    }
 
-                            return Err(Error::ParsingFailed(error_message.to_string()));
-                        }
+                    self.page += 1;
 
-                        (pair[0].to_string(), pair[1].to_string())
-                    };
+                    if let Some(since) = self.since {
+                        datasets.retain(|dataset| dataset.refreshed_at > since);
 
-                    codes.push(Code {
-                        database_code: database_code,
-                        dataset_code: dataset_code,
-                        name: record.1,
-                    });
-                }
+                        // A page listing only older datasets, the rest of the listing is too.
+                        self.done |= datasets.is_empty();
+                    }
 
-                Ok(codes)
-            },
+                    if datasets.is_empty() {
+                        self.cursor = Some(pending);
+                    } else {
+                        self.pending = Some(pending);
+                    }
 
-            Err(e) => Err(Error::ParsingFailed(e.to_string())),
+                    self.buffer = datasets.into_iter();
+                },
+
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                },
+            }
         }
     }
+}
 
-    fn fmt_prefix(&self) -> Option<String> {
-        Some(format!("/databases/{}/codes", self.database_code))
+/// Number of results at the start of `page`, listed by their `codes`, which are repeated from
+/// before `cursor` and the warnings about the results having shifted since it was taken, given
+/// their `total_count` now (if known).
+///
+fn drift<'a, I>(cursor: &PageCursor, page: usize, codes: I, total_count: Option<usize>)
+    -> (usize, Warnings)
+    where I: IntoIterator<Item = &'a str>
+{
+    let repeated = cursor.repeated(codes);
+    let mut warnings = vec![];
+
+    if repeated > 0 {
+        warnings.push(Warning::PageOverlap { page, repeated });
     }
 
-    fn fmt_arguments(&self) -> Option<String> {
-        ApiParameters::fmt(self)
+    if let (Some(before), Some(now)) = (cursor.total_count, total_count) {
+        if now < before {
+            warnings.push(Warning::PageSkip { page, missing: before - now });
+        }
     }
+
+    (repeated, warnings)
 }
 
-impl<T: DeserializeOwned + Clone> ApiCall<Vec<T>> for DataQuery {
-    fn send(&self) -> Result<Vec<T>> {
-        let csv_data = ApiCall::<Vec<T>>::encoded_data(self)?;
+/// Number of datasets per page fetched by `DatasetListingQuery::iter`, unless specified otherwise.
+///
+const LISTING_PER_PAGE: usize = 100;
 
-        let data: Vec<T> = {
-            let mut reader = {
-                csv::ReaderBuilder::new()
-                    .has_headers(false)
-                    .from_reader(std::io::Cursor::new(csv_data))
-            };
+/// Default of `CodeListQuery::max_compressed_bytes`.
+///
+#[cfg(feature = "zip")]
+const DEFAULT_MAX_COMPRESSED_BYTES: u64 = 64 << 20;
 
-            match reader.deserialize().next().unwrap() {
-                Ok(data) => data,
-                Err(e) => return Err(Error::ParsingFailed(e.to_string())),
-            }
-        };
+/// Default of `CodeListQuery::max_uncompressed_bytes`.
+///
+#[cfg(feature = "zip")]
+const DEFAULT_MAX_UNCOMPRESSED_BYTES: u64 = 256 << 20;
 
-        Ok(data)
+/// Default of `CodeListQuery::max_unzip_ratio`.
+///
+#[cfg(feature = "zip")]
+const DEFAULT_MAX_UNZIP_RATIO: u64 = 100;
+
+/// A record of the dataset listing, before its dates are parsed.
+///
+#[derive(Deserialize)]
+struct ListingRecord {
+    dataset_code: String,
+    name: String,
+    #[serde(default)]
+    description: String,
+    refreshed_at: String,
+    #[serde(default)]
+    oldest_available_date: String,
+    #[serde(default)]
+    newest_available_date: String,
+}
+
+impl CodeListQuery {
+    /// Create a new code list query.
+    ///
+    pub fn new<S: AsRef<str>>(database_code: S) -> Self {
+        CodeListQuery {
+            database_code: Arc::from(database_code.as_ref()),
+            #[cfg(feature = "zip")]
+            max_compressed_bytes: DEFAULT_MAX_COMPRESSED_BYTES,
+            #[cfg(feature = "zip")]
+            max_uncompressed_bytes: DEFAULT_MAX_UNCOMPRESSED_BYTES,
+            #[cfg(feature = "zip")]
+            max_unzip_ratio: DEFAULT_MAX_UNZIP_RATIO,
+            request_arguments: ApiArguments::from_config(),
+        }
     }
 
-    fn fmt_prefix(&self) -> Option<String> {
-        Some(format!("/datasets/{}/{}/data.csv", self.database_code, self.dataset_code))
+    /// Fail with `Error::ResponseTooLarge` rather than download or unzip an archive of more than
+    /// `bytes` bytes (64 MiB by default, far more than any code list is zipped into).
+    ///
+    /// The download is given up on as for `ApiParameters::max_response_bytes`, whichever of the
+    /// two limits is less applying.
+    ///
+    #[cfg(feature = "zip")]
+    pub fn max_compressed_bytes(&mut self, bytes: u64) -> &mut Self {
+        self.max_compressed_bytes = bytes;
+        self
     }
 
-    fn fmt_arguments(&self) -> Option<String> {
-        let arg_1 = ApiParameters::fmt(self);
-        let arg_2 = DataParameters::fmt(self);
-
-        if arg_1.is_some() && arg_2.is_some() {
-            Some(format!("exclude_column_names=true&{}&{}", arg_1.unwrap(), arg_2.unwrap()))
-        } else if arg_1.is_some() {
-            Some(format!("exclude_column_names=true&{}", arg_1.unwrap()))
-        } else if arg_2.is_some() {
-            Some(format!("exclude_column_names=true&{}", arg_2.unwrap()))
-        } else {
-            Some(String::from("exclude_column_names=true"))
-        }
+    /// Fail with `Error::UnzippedTooLarge` rather than unzip more than `bytes` bytes out of the
+    /// code list (256 MiB by default, far more than any code list holds).
+    ///
+    #[cfg(feature = "zip")]
+    pub fn max_uncompressed_bytes(&mut self, bytes: u64) -> &mut Self {
+        self.max_uncompressed_bytes = bytes;
+        self
     }
-}
+
+    /// Fail with `Error::UnzippedTooLarge` rather than unzip more than `ratio` times the size of
+    /// the archive (100 by default), so that a small archive cannot unzip into gigabytes.
+    ///
+    #[cfg(feature = "zip")]
+    pub fn max_unzip_ratio(&mut self, ratio: u64) -> &mut Self {
+        self.max_unzip_ratio = ratio;
+        self
+    }
+
+    /// Same as `new`, but failing right away with `Error::ValidationFailed` when `database_code`
+    /// is invalid rather than leaving it to `validate` (see `ApiParameters::auto_uppercase`).
+    ///
+    pub fn from_code<S: AsRef<str>>(database_code: S) -> Result<Self> {
+        checked_codes(CodeListQuery::new(database_code), |query, errors| {
+            check_database_code(&query.request_arguments.code(&query.database_code), errors)
+        })
+    }
+
+    /// Check this query without sending it, listing every problem found: a malformed API key or
+    /// database code.
+    ///
+    pub fn validate(&self) -> ::std::result::Result<(), Vec<ValidationError>> {
+        let mut errors = self.request_arguments.validation_errors();
+        check_database_code(&self.request_arguments.code(&self.database_code), &mut errors);
+        validated(errors)
+    }
+
+    /// Returns the URL of the zipped code list.
+    ///
+    /// Without the `zip` feature this query does not implement `ApiCall` (it cannot be unzipped
+    /// and parsed), but the URL and the raw archive remain available.
+    ///
+    #[cfg(not(feature = "zip"))]
+    pub fn url(&self) -> String {
+        crate::api_call::url(&self.request_arguments, Some(self.prefix()), ApiParameters::fmt(self))
+    }
+
+    /// Returns the URL of the zipped code list, parsed.
+    ///
+    #[cfg(not(feature = "zip"))]
+    pub fn parsed_url(&self) -> Result<Url> {
+        let arguments = ApiParameters::fmt(self);
+        crate::api_call::parsed_url(&self.request_arguments, Some(self.prefix()), arguments)
+    }
+
+    /// Download the zipped code list as-is.
+    ///
+    #[cfg(not(feature = "zip"))]
+    pub fn encoded_data(&self) -> Result<Vec<u8>> {
+        self.encoded_bytes().map(|bytes| bytes.to_vec())
+    }
+
+    /// Same as `encoded_data`, without copying the archive out of the buffer it was received in.
+    ///
+    #[cfg(not(feature = "zip"))]
+    pub fn encoded_bytes(&self) -> Result<Bytes> {
+        self.request_arguments.ready(|| self.validate())?;
+        self.request().fetch().map(|response| response.body)
+    }
+
+    #[cfg(not(feature = "zip"))]
+    fn request(&self) -> Request {
+        Request::get(self.url(), self.request_arguments.client.as_ref())
+            .max_bytes(self.request_arguments.max_response_bytes)
+            .database_code(Some(self.request_arguments.code(&self.database_code)))
+    }
+
+    /// Unzip and parse a code list as returned by Quandl for this query (e.g. one previously
+    /// obtained through `encoded_data`).
+    ///
+    /// This is what `send` uses once the archive is downloaded. An archive without any file, or
+    /// with a list of nothing but its header, decodes to no code at all; anything else which is
+    /// not a zipped list of codes (an empty response included) is an error, as is an archive
+    /// larger than `max_compressed_bytes` or unzipping into more than `max_uncompressed_bytes` or
+    /// `max_unzip_ratio` allow.
+    ///
+    /// The list is parsed as it is unzipped, without ever holding it whole in memory.
+    ///
+    #[cfg(feature = "zip")]
+    pub fn decode(&self, zipped_data: &[u8]) -> Result<Vec<Code>> {
+        use zip::read::ZipArchive;
+        use std::io::Cursor;
+
+        if zipped_data.len() as u64 > self.max_compressed_bytes {
+            return Err(Error::ResponseTooLarge {
+                limit: self.max_compressed_bytes,
+                observed: zipped_data.len() as u64,
+            });
+        }
+
+        // The zip crate trusts the sizes and offsets of ZIP64 archives, which it may panic on or
+        // abort the process over (allocating for billions of files). Code lists are never that
+        // large.
+        if is_zip64(zipped_data) {
+            return Err(Error::ZipExtraction("ZIP64 archives are not supported.".to_string()));
+        }
+
+        let mut files = ZipArchive::new(Cursor::new(zipped_data))?;
+
+        let limit = {
+            let ratio = self.max_unzip_ratio.saturating_mul(zipped_data.len() as u64);
+            self.max_uncompressed_bytes.min(ratio)
+        };
+
+        // What the archive declares first, which it may understate: what is actually unzipped is
+        // bounded as well.
+        let mut declared: u64 = 0;
+
+        for index in 0..files.len() {
+            declared = declared.saturating_add(files.by_index(index)?.size());
+        }
+
+        if declared > limit {
+            return Err(Error::UnzippedTooLarge { limit, observed: declared });
+        }
+
+        let mut codes: Vec<Code> = vec![];
+        let mut unzipped: u64 = 0;
+
+        for index in 0..files.len() {
+            let mut file = Unzipped {
+                file: files.by_index(index)?,
+                limit,
+                unzipped,
+                error: None,
+            };
+
+            // The files are read as one list, whose header is at the start of the first.
+            if let Err(e) = parse_codes(&mut file, index == 0, &mut codes) {
+                return Err(file.error.take().unwrap_or(e));
+            }
+
+            unzipped = file.unzipped;
+        }
+
+        Ok(codes)
+    }
+
+    /// Same as `send`, but a database without any dataset is reported as a
+    /// `Warning::EmptyDatabase`.
+    ///
+    #[cfg(feature = "zip")]
+    pub fn send_with_warnings(&self) -> Result<WithWarnings<Vec<Code>>> {
+        let mut result = WithWarnings::new(self.send()?);
+
+        if result.value.is_empty() {
+            result.warnings.push(Warning::EmptyDatabase {
+                database_code: self.database_code.to_string(),
+            });
+        }
+
+        Ok(result)
+    }
+
+    fn prefix(&self) -> String {
+        format!("/databases/{}/codes", self.request_arguments.code(&self.database_code))
+    }
+}
+
+impl DatabaseDownloadQuery {
+    /// Create a new query downloading every dataset of the database.
+    ///
+    pub fn new<S: AsRef<str>>(database_code: S) -> Self {
+        DatabaseDownloadQuery {
+            database_code: Arc::from(database_code.as_ref()),
+            partial: false,
+            request_arguments: ApiArguments::from_config(),
+        }
+    }
+
+    /// Same as `new`, but failing right away with `Error::ValidationFailed` when `database_code`
+    /// is invalid rather than leaving it to `validate` (see `ApiParameters::auto_uppercase`).
+    ///
+    pub fn from_code<S: AsRef<str>>(database_code: S) -> Result<Self> {
+        checked_codes(DatabaseDownloadQuery::new(database_code), |query, errors| {
+            check_database_code(&query.request_arguments.code(&query.database_code), errors)
+        })
+    }
+
+    /// Check this query without sending it, listing every problem found: a malformed API key or
+    /// database code.
+    ///
+    pub fn validate(&self) -> ::std::result::Result<(), Vec<ValidationError>> {
+        let mut errors = self.request_arguments.validation_errors();
+        check_database_code(&self.request_arguments.code(&self.database_code), &mut errors);
+        validated(errors)
+    }
+
+    /// Only download the data points added or updated by the database's last refresh.
+    ///
+    pub fn partial(&mut self) -> &mut Self {
+        self.partial = true;
+        self
+    }
+
+    /// Returns the URL of the zipped database.
+    ///
+    pub fn url(&self) -> String {
+        crate::api_call::url(&self.request_arguments, Some(self.prefix()), self.arguments())
+    }
+
+    /// Returns the URL of the zipped database, parsed.
+    ///
+    pub fn parsed_url(&self) -> Result<Url> {
+        crate::api_call::parsed_url(&self.request_arguments, Some(self.prefix()), self.arguments())
+    }
+
+    /// Download the zipped database to `path`, resuming a previous interrupted download to the
+    /// same path if any, and return the size of the file.
+    ///
+    /// These archives can be several gigabytes large, so the progress is recorded along the way
+    /// in a sidecar file next to `path`; should this fail midway, calling it again only fetches
+    /// the missing data (provided the server supports `Range` requests and the archive did not
+    /// change in-between, otherwise the download starts over).
+    ///
+    pub fn download_to_file_resumable<P: AsRef<Path>>(&self, path: P) -> Result<u64> {
+        self.request_arguments.ready(|| self.validate())?;
+
+        crate::download::download_to_file_resumable(&self.request(), path)
+    }
+
+    /// Download the zipped database to `path` like `download_to_file_resumable`, retrying with
+    /// `backoff` when interrupted by a transient failure (see `backoff::is_retryable`), each
+    /// attempt resuming where the previous one stopped.
+    ///
+    pub fn download_to_file_retrying<P: AsRef<Path>>(&self, path: P, backoff: &Backoff)
+        -> Result<u64>
+    {
+        crate::backoff::retry(backoff, || self.download_to_file_resumable(path.as_ref()))
+    }
+
+    fn request(&self) -> Request {
+        Request::get(self.url(), self.request_arguments.client.as_ref())
+            .database_code(Some(self.request_arguments.code(&self.database_code)))
+    }
+
+    fn prefix(&self) -> String {
+        format!("/databases/{}/data", self.request_arguments.code(&self.database_code))
+    }
+
+    fn arguments(&self) -> Option<String> {
+        match (self.partial, ApiParameters::fmt(self)) {
+            (true, Some(arguments)) => Some(format!("download_type=partial&{}", arguments)),
+            (true, None) => Some(String::from("download_type=partial")),
+            (false, arguments) => arguments,
+        }
+    }
+}
+
+impl DataQuery {
+    /// Create a new data query.
+    ///
+    pub fn new<S1: AsRef<str>, S2: AsRef<str>>(database_code: S1, dataset_code: S2) -> Self {
+        DataQuery {
+            database_code: Arc::from(database_code.as_ref()),
+            dataset_code: Arc::from(dataset_code.as_ref()),
+            id: None,
+            strict_width: None,
+            column_names: false,
+            data_arguments: DataArguments::default(),
+            request_arguments: ApiArguments::from_config(),
+        }
+    }
+
+    /// Same as `new`, from the full code of the dataset (e.g. `"WIKI/AAPL"`), but failing right
+    /// away with `Error::ValidationFailed` when either code is invalid rather than leaving it to
+    /// `validate` (see `ApiParameters::auto_uppercase`).
+    ///
+    pub fn from_code<S: AsRef<str>>(code: S) -> Result<Self> {
+        let (database_code, dataset_code) = split_code(code.as_ref())?;
+
+        checked_codes(DataQuery::new(database_code, dataset_code), |query, errors| {
+            check_dataset(None,
+                          &query.database_code,
+                          &query.dataset_code,
+                          &query.request_arguments,
+                          errors)
+        })
+    }
+
+    /// Create a new data query for the dataset of numerical identifier `id`, which (unlike its
+    /// codes) remains the same when the dataset is renamed.
+    ///
+    pub fn by_id(id: usize) -> Self {
+        let mut query = DataQuery::new("", "");
+        query.id = Some(id);
+        query
+    }
+
+    /// Check this query without sending it, listing every problem found: a malformed API key,
+    /// database code or dataset code, codes set along with an `id`, impossible or inverted dates,
+    /// both `rows` and `limit` being set, or (behind the `registry` feature) a `column_index`
+    /// beyond the columns known of the database's datasets.
+    ///
+    pub fn validate(&self) -> ::std::result::Result<(), Vec<ValidationError>> {
+        let mut errors = self.request_arguments.validation_errors();
+        check_dataset(self.id, &self.database_code, &self.dataset_code, &self.request_arguments,
+                      &mut errors);
+        errors.extend(self.data_arguments.validation_errors());
+        check_column_index(&self.request_arguments.code(&self.database_code),
+                           &self.data_arguments,
+                           &mut errors);
+        validated(errors)
+    }
+
+    /// Require every record of the response to have exactly `width` fields (the date included).
+    ///
+    /// Without this, decoding a record into a tuple or struct silently ignores any extra column,
+    /// so a publisher adding a column to a dataset goes unnoticed. With it, decoding fails with
+    /// the offending row number and its actual width. Such rows being reported rather than
+    /// skipped, `decode_lossy` and `send_lossy` fail on a query with a strict width.
+    ///
+    pub fn strict_width(&mut self, width: usize) -> &mut Self {
+        self.strict_width = Some(width);
+        self
+    }
+
+    /// Same as `strict_width`, but with the width expected from the dataset's metadata.
+    ///
+    /// That is the number of column names listed in the metadata, or 2 (the date and the selected
+    /// column) when a `column_index` has been specified on this query.
+    ///
+    pub fn expected_width_from(&mut self, metadata: &DatasetMetadata) -> &mut Self {
+        let width = {
+            if Has::<DataArguments>::get_ref(self).column_index().is_some() {
+                2
+            } else {
+                metadata.column_names.len()
+            }
+        };
+
+        self.strict_width(width)
+    }
+
+    /// Same as `strict_width`, with the width of `schema`, e.g. as inferred by `infer_schema`.
+    ///
+    pub fn expected_width_from_schema(&mut self, schema: &Schema) -> &mut Self {
+        self.strict_width(schema.width())
+    }
+
+    /// Infer the schema of the rows of this dataset (see `Schema` for how) from its column names
+    /// and first `sample_rows` rows, which are all that is requested for it.
+    ///
+    /// The other parameters of the query apply, e.g. a collapsed dataset or one after a
+    /// `transform` may well have another schema. The sample must not be empty.
+    ///
+    pub fn infer_schema(&self, sample_rows: usize) -> Result<Schema> {
+        assert!(sample_rows > 0, "sample_rows: {}", sample_rows);
+
+        let mut query = self.clone();
+        HasMut::<DataArguments>::get_mut(&mut query).limit = None;
+        query.rows(sample_rows);
+
+        // Unlike when decoding rows, the column names are wanted.
+        query.column_names = true;
+
+        Schema::infer(&checked_body::<Vec<DataRow>, _>(&query, CSV)?)
+    }
+
+    /// Decode a CSV payload as returned by Quandl for this query (e.g. one previously obtained
+    /// through `encoded_data`).
+    ///
+    /// This is what `send` uses once the data is downloaded.
+    ///
+    /// A payload without any row, be it empty or a lone line of column names, decodes to no rows
+    /// at all; a line of column names heading the rows is left out likewise.
+    ///
+    pub fn decode<T: DeserializeOwned>(&self, csv_data: &[u8]) -> Result<Vec<T>> {
+        self.decode_rows(csv_data, 0, None)
+    }
+
+    /// Decode a CSV payload into rows of a date, parsed by `parser`, and the values of the other
+    /// columns.
+    ///
+    /// This parses the dates the same way whatever the values are decoded as (e.g. `f64`, or
+    /// `Option<f64>` for columns with missing values). A date which cannot be parsed fails the
+    /// whole payload with an `Error::CsvParsing` quoting it, as does any value which cannot be
+    /// decoded. Lines of column names and `strict_width` are handled as by `decode`.
+    ///
+    pub fn decode_dated<D, V>(&self, csv_data: &[u8], parser: &dyn DateParser<D>)
+        -> Result<Vec<(D, Vec<V>)>>
+        where V: DeserializeOwned
+    {
+        let mut reader = {
+            csv::ReaderBuilder::new()
+                .has_headers(false)
+                .flexible(true)
+                .from_reader(csv_data)
+        };
+
+        let mut data = vec![];
+
+        for (index, record) in reader.records().enumerate() {
+            let row = index + 1;
+            let record = record.map_err(|e| Error::csv(row, e))?;
+
+            if index == 0 && is_header(&record) {
+                continue;
+            }
+
+            if let Some(width) = self.strict_width {
+                if record.len() != width {
+                    return Err(Error::csv(row, format!("row {} has {} fields, expected {}.",
+                                                       row,
+                                                       record.len(),
+                                                       width)));
+                }
+            }
+
+            let raw_date = record.get(0).unwrap_or_default();
+
+            let date = {
+                parser.parse(raw_date).map_err(|e| {
+                    let reason = {
+                        match e {
+                            Error::ParsingFailed(message) => message,
+                            e => e.to_string(),
+                        }
+                    };
+
+                    Error::csv(row, format!("invalid date '{}': {}", raw_date, reason))
+                })?
+            };
+
+            let values: csv::StringRecord = record.iter().skip(1).collect();
+            let values = values.deserialize(None).map_err(|e| Error::csv(row, e))?;
+
+            data.push((date, values));
+        }
+
+        Ok(data)
+    }
+
+    /// Download the data and decode it with `decode_dated`.
+    ///
+    /// ```rust,no_run
+    /// use quandl_v3::calendar::EpochDays;
+    /// use quandl_v3::prelude::*;
+    ///
+    /// let rows: Vec<(i64, Vec<Option<f64>>)> = {
+    ///     DataQuery::new("WIKI", "AAPL").send_dated(&EpochDays).unwrap()
+    /// };
+    /// ```
+    ///
+    pub fn send_dated<D, V>(&self, parser: &dyn DateParser<D>) -> Result<Vec<(D, Vec<V>)>>
+        where V: DeserializeOwned + Clone
+    {
+        self.decode_dated(&checked_body::<Vec<(String, Vec<V>)>, _>(self, CSV)?[..], parser)
+    }
+
+    /// Same as `send`, but a dataset without any row (within the dates requested) is reported as
+    /// a `Warning::EmptyDataset`, to tell it apart from a query which just was not sent, and rows
+    /// which may have been cut off by Quandl as a `Warning::PossiblyTruncated` (see `ROW_CAPS`).
+    ///
+    /// A payload which cannot be decoded still fails the whole query.
+    ///
+    pub fn send_with_warnings<T: DeserializeOwned + Clone>(&self) -> Result<WithWarnings<Vec<T>>> {
+        let csv_data = checked_body::<Vec<T>, _>(self, CSV)?;
+        let mut result = self.empty_dataset_warning(WithWarnings::new(self.decode(&csv_data[..])?));
+
+        result.warnings.extend(self.truncation_warning(result.len(), &csv_data));
+        Ok(result)
+    }
+
+    /// Same as `decode`, but the payload is split on record boundaries and the chunks are decoded
+    /// in parallel on rayon's thread pool. The rows are returned in their original order.
+    ///
+    #[cfg(feature = "rayon")]
+    pub fn decode_parallel<T: DeserializeOwned + Send>(&self, csv_data: &[u8]) -> Result<Vec<T>> {
+        use rayon::prelude::*;
+
+        let chunks = crate::parallel::split_records(csv_data, rayon::current_num_threads());
+
+        let mut decoded: Vec<Result<Vec<T>>> = {
+            chunks.par_iter()
+                .map(|&(first_row, chunk)| self.decode_rows(chunk, first_row, None))
+                .collect()
+        };
+
+        if decoded.len() == 1 {
+            return decoded.remove(0);
+        }
+
+        let mut data = Vec::with_capacity(decoded.iter().flatten().map(Vec::len).sum());
+
+        for rows in decoded {
+            data.extend(rows?);
+        }
+
+        Ok(data)
+    }
+
+    /// Download the data and decode it with `decode_parallel`.
+    ///
+    /// This is worth it for very large payloads (hundreds of thousands of rows), for which
+    /// decoding becomes the bottleneck.
+    ///
+    #[cfg(feature = "rayon")]
+    pub fn send_parallel<T: DeserializeOwned + Clone + Send>(&self) -> Result<Vec<T>> {
+        self.decode_parallel(&checked_body::<Vec<T>, _>(self, CSV)?[..])
+    }
+
+    /// Same as `decode`, but records which cannot be decoded are skipped instead of failing the
+    /// whole payload.
+    ///
+    /// The number of records skipped, if any, is reported as a `Warning::RowsSkipped`, and a
+    /// payload without any row at all as a `Warning::EmptyDataset`. This fails only when
+    /// `strict_width` is set, which asks for the very rows skipped here to be reported.
+    ///
+    pub fn decode_lossy<T: DeserializeOwned>(&self, csv_data: &[u8])
+        -> Result<WithWarnings<Vec<T>>>
+    {
+        self.check_lossy()?;
+
+        let mut skipped = 0;
+        let mut result = WithWarnings::new(self.decode_rows(csv_data, 0, Some(&mut skipped))?);
+
+        if skipped > 0 {
+            result.warnings.push(Warning::RowsSkipped(skipped));
+        }
+
+        Ok(self.empty_dataset_warning(result))
+    }
+
+    /// Download the data and decode it with `decode_lossy`.
+    ///
+    /// Only the download itself may fail, or the query when `strict_width` is set, in which case
+    /// nothing is sent. Rows which may have been cut off by Quandl are reported as with
+    /// `send_with_warnings`.
+    ///
+    pub fn send_lossy<T: DeserializeOwned + Clone>(&self) -> Result<WithWarnings<Vec<T>>> {
+        self.check_lossy()?;
+
+        let csv_data = checked_body::<Vec<T>, _>(self, CSV)?;
+        let mut result = self.decode_lossy(&csv_data[..])?;
+
+        let rows = {
+            result.len() + result.warnings.iter().map(|warning| {
+                match warning {
+                    Warning::RowsSkipped(n) => *n,
+                    _ => 0,
+                }
+            }).sum::<usize>()
+        };
+
+        result.warnings.extend(self.truncation_warning(rows, &csv_data));
+        Ok(result)
+    }
+
+    /// Point this query at the successor of its database when Nasdaq Data Link renamed it, see
+    /// `aliases::resolve_code` (behind the `aliases` feature).
+    ///
+    /// The rename is reported as a `Warning::DatabaseRenamed`, and logged. A retired database is
+    /// left as is, there being nothing to point at, but is reported (and logged) as a
+    /// `Warning::DatabaseRetired`. Nothing is reported for a database still active.
+    ///
+    /// ```rust
+    /// use quandl_v3::prelude::*;
+    ///
+    /// let mut query = DataQuery::new("EOD", "AAPL");
+    /// query.resolve_aliases();
+    ///
+    /// assert_eq!(query.database_code(), "QUOTEMEDIA");
+    /// ```
+    ///
+    #[cfg(feature = "aliases")]
+    pub fn resolve_aliases(&mut self) -> Option<Warning> {
+        use crate::aliases::{resolve_code, CodeResolution};
+
+        let warning = {
+            match resolve_code(&self.database_code) {
+                CodeResolution::Active => return None,
+
+                CodeResolution::RenamedTo(successor) => {
+                    let code = ::std::mem::replace(&mut self.database_code, Arc::from(&*successor));
+                    Warning::DatabaseRenamed { from: code.to_string(), to: successor }
+                },
+
+                CodeResolution::Retired { since, note } => {
+                    Warning::DatabaseRetired { code: self.database_code.to_string(), since, note }
+                },
+            }
+        };
+
+        log::warn!("{}/{}: {}", self.database_code, self.dataset_code, warning);
+        Some(warning)
+    }
+
+    /// Number of rows this query would return, without downloading them.
+    ///
+    /// Quandl does not report the total number of rows of a query, so this takes 2 API calls:
+    /// one for the first row of the query and one for its last row, each along with the metadata
+    /// of the dataset. The count is then estimated from their dates and the frequency of the data
+    /// (the one collapsed to, if any), see `RowCount::estimate`, and capped by the `rows` or
+    /// `limit` of this query. A query returning no data takes a single call and counts 0 rows,
+    /// exactly.
+    ///
+    pub fn count(&self) -> Result<RowCount> {
+        let (first, frequency) = {
+            match self.boundary(Order::asc)? {
+                Some(boundary) => boundary,
+                None => return Ok(RowCount { rows: 0, exact: true }),
+            }
+        };
+
+        let last = self.boundary(Order::desc)?.map(|(last, _)| last).unwrap_or(first);
+        let mut count = RowCount::estimate(frequency, first, last);
+
+        if let Some(cap) = self.data_arguments.rows.or(self.data_arguments.limit) {
+            if count.rows > cap as u64 {
+                count = RowCount { rows: cap as u64, exact: false };
+            }
+        }
+
+        Ok(count)
+    }
+
+    /// Date of the first row of this query in the given order, along with the frequency of the
+    /// data, or `None` if it has no rows.
+    ///
+    fn boundary(&self, order: Order) -> Result<Option<(chrono::NaiveDate, Frequency)>> {
+        let mut query = {
+            DataAndMetadataQuery {
+                database_code: self.database_code.clone(),
+                dataset_code: self.dataset_code.clone(),
+                id: self.id,
+                data_arguments: self.data_arguments.clone(),
+                request_arguments: self.request_arguments.clone(),
+            }
+        };
+
+        query.data_arguments.limit = None;
+        query.rows(1).order(order);
+
+        let dataset: Dataset<serde_json::Value> = query.send()?;
+
+        let row = {
+            match dataset.rows.first() {
+                Some(row) => row,
+                None => return Ok(None),
+            }
+        };
+
+        let date = {
+            let date = {
+                row.get(0).and_then(serde_json::Value::as_str).and_then(calendar::parse_date)
+            };
+
+            date.ok_or_else(|| {
+                Error::ParsingFailed(format!("invalid date in row {} of dataset {}/{}.",
+                                             row,
+                                             self.database_code,
+                                             self.dataset_code))
+            })?
+        };
+
+        let frequency = {
+            match self.data_arguments.collapse {
+                Some(Frequency::none) | None => dataset.metadata.frequency,
+                Some(collapse) => collapse,
+            }
+        };
+
+        Ok(Some((date, frequency)))
+    }
+
+    /// Report `result` as a `Warning::EmptyDataset` when it has no row, and none was skipped.
+    ///
+    fn empty_dataset_warning<T>(&self, mut result: WithWarnings<Vec<T>>) -> WithWarnings<Vec<T>> {
+        if result.value.is_empty() && result.is_clean() {
+            result.warnings.push(Warning::EmptyDataset {
+                database_code: self.database_code.to_string(),
+                dataset_code: self.dataset_code.to_string(),
+            });
+        }
+
+        result
+    }
+
+    /// The warning for `rows` rows decoded from `csv_data`, see `truncation_warning`.
+    ///
+    fn truncation_warning(&self, rows: usize, csv_data: &[u8]) -> Option<Warning> {
+        truncation_warning(&self.data_arguments, rows, || payload_dates(csv_data), None)
+    }
+
+    /// Fail when `strict_width` is set, strict widths and lossy decoding being mutually exclusive.
+    ///
+    fn check_lossy(&self) -> Result<()> {
+        match self.strict_width {
+            Some(_) => {
+                Err(Error::ValidationFailed(vec![
+                    ValidationError::new("strict_width", "is set on a query decoded lossily, \
+                                                          which would skip the rows of the \
+                                                          wrong width instead of reporting \
+                                                          them."),
+                ]))
+            },
+
+            None => Ok(()),
+        }
+    }
+
+    /// Decode `csv_data`, whose first record is the `first_row`-th of the payload. Records which
+    /// fail to decode are counted in `skipped` if given, and are an error otherwise.
+    ///
+    fn decode_rows<T: DeserializeOwned>(&self,
+                                        csv_data: &[u8],
+                                        first_row: usize,
+                                        mut skipped: Option<&mut usize>) -> Result<Vec<T>>
+    {
+        let mut reader = {
+            csv::ReaderBuilder::new()
+                .has_headers(false)
+                .flexible(true)
+                .from_reader(csv_data)
+        };
+
+        let mut data: Vec<T> = vec![];
+
+        let mut skip = |error: Error| {
+            match skipped {
+                Some(ref mut skipped) => {
+                    **skipped += 1;
+                    Ok(())
+                },
+
+                None => Err(error),
+            }
+        };
+
+        for (index, record) in reader.records().enumerate() {
+            let record = {
+                match record {
+                    Ok(record) => record,
+
+                    Err(e) => {
+                        skip(Error::csv(first_row + index + 1, e))?;
+                        continue;
+                    },
+                }
+            };
+
+            // Quandl is asked to leave the column names out, but may send them all the same.
+            if first_row + index == 0 && is_header(&record) {
+                continue;
+            }
+
+            if let Some(width) = self.strict_width {
+                if record.len() != width {
+                    let row = first_row + index + 1;
+
+                    return Err(Error::csv(row, format!("row {} has {} fields, expected {}.",
+                                                       row,
+                                                       record.len(),
+                                                       width)));
+                }
+            }
+
+            match record.deserialize(None) {
+                Ok(row) => data.push(row),
+                Err(e) => skip(Error::csv(first_row + index + 1, e))?,
+            }
+        }
+
+        Ok(data)
+    }
+}
+
+/// Check the codes of a dataset, unless it is addressed by `id`, in which case they must be left
+/// empty.
+///
+fn check_dataset(id: Option<usize>,
+                 database_code: &str,
+                 dataset_code: &str,
+                 arguments: &ApiArguments,
+                 errors: &mut Vec<ValidationError>)
+{
+    match id {
+        Some(_) => {
+            if !database_code.is_empty() || !dataset_code.is_empty() {
+                errors.push(ValidationError::new("id", "is set along with the database and \
+                                                        dataset codes, only one of which may \
+                                                        address the dataset."));
+            }
+        },
+
+        None => {
+            check_database_code(&arguments.code(database_code), errors);
+            check_dataset_code(&arguments.code(dataset_code), errors);
+        },
+    }
+}
+
+/// Check that the `column_index` of `arguments`, if any, is among the columns the registry knows
+/// of the datasets of `database_code`, see `registry::hints_for`.
+///
+#[cfg(feature = "registry")]
+fn check_column_index(database_code: &str,
+                      arguments: &DataArguments,
+                      errors: &mut Vec<ValidationError>)
+{
+    let columns = {
+        crate::registry::hints_for(database_code).map(|hints| hints.column_names.len())
+            .unwrap_or(0)
+    };
+
+    match arguments.column_index() {
+        Some(index) if columns > 0 && index >= columns => {
+            errors.push(ValidationError::new("column_index", format!("is {}, beyond the {} \
+                                                                      columns of the datasets \
+                                                                      of {}.",
+                                                                     index,
+                                                                     columns,
+                                                                     database_code)));
+        },
+
+        _ => (),
+    }
+}
+
+#[cfg(not(feature = "registry"))]
+fn check_column_index(_: &str, _: &DataArguments, _: &mut Vec<ValidationError>) {}
+
+/// `query`, unless `check` finds any problem with its codes, see e.g. `DataQuery::from_code`.
+///
+fn checked_codes<Q, F>(query: Q, check: F) -> Result<Q>
+    where F: FnOnce(&Q, &mut Vec<ValidationError>)
+{
+    let mut errors = vec![];
+    check(&query, &mut errors);
+    validated(errors).map_err(Error::ValidationFailed)?;
+    Ok(query)
+}
+
+/// The database and dataset codes of `code`, the full code of a dataset such as `"WIKI/AAPL"`.
+///
+fn split_code(code: &str) -> Result<(&str, &str)> {
+    code.split_once('/').ok_or_else(|| {
+        Error::ValidationFailed(vec![
+            ValidationError::new("dataset_code", format!("must be given along with the database \
+                                                          code, as in 'WIKI/AAPL', got '{}'.",
+                                                         code)),
+        ])
+    })
+}
+
+/// Path of a dataset below `/datasets`: its `id` if it is addressed by it, its codes (as sent
+/// with `arguments`) otherwise.
+///
+fn dataset_path(id: Option<usize>,
+                database_code: &str,
+                dataset_code: &str,
+                arguments: &ApiArguments)
+    -> String
+{
+    match id {
+        Some(id) => id.to_string(),
+        None => format!("{}/{}", arguments.code(database_code), arguments.code(dataset_code)),
+    }
+}
+
+/// The warning for `rows` rows covering `actual`, if that many is one of the `ROW_CAPS` and
+/// neither `rows` nor `limit` was requested.
+///
+/// When the `metadata` of the dataset is known, the rows are checked against the dates it
+/// advertises (within those requested): the warning is dropped if they cover them, upgraded to a
+/// `Warning::Truncated` if they clearly do not. Rows are allowed to start or end a period (that of
+/// the data, or of the collapse) and a weekend away from the dates advertised, since collapsed
+/// rows are dated at the end of their period and transformed data may lack its first row.
+///
+fn truncation_warning<F>(arguments: &DataArguments,
+                         rows: usize,
+                         actual: F,
+                         metadata: Option<&DatasetMetadata>) -> Option<Warning>
+    where F: FnOnce() -> Option<calendar::DateRange>
+{
+    if arguments.rows.is_some() || arguments.limit.is_some() || !ROW_CAPS.contains(&rows) {
+        return None;
+    }
+
+    let possibly = Some(Warning::PossiblyTruncated { rows });
+
+    let (metadata, actual) = {
+        match (metadata, actual()) {
+            (Some(metadata), Some(actual)) => (metadata, actual),
+            _ => return possibly,
+        }
+    };
+
+    let expected = {
+        let (oldest, newest) = match metadata.date_range() {
+            Ok(range) => range,
+            Err(_) => return possibly,
+        };
+
+        let (start, end) = arguments.dates();
+        let start = start.map_or(oldest, |start| start.max(oldest));
+        let end = end.map_or(newest, |end| end.min(newest));
+
+        if start > end {
+            return possibly;
+        }
+
+        calendar::DateRange::new(start, end)
+    };
+
+    let period = {
+        match arguments.collapse.filter(|&x| x != Frequency::none).unwrap_or(metadata.frequency) {
+            Frequency::none | Frequency::daily => 1,
+            Frequency::weekly => 7,
+            Frequency::monthly => 31,
+            Frequency::quarterly => 92,
+            Frequency::annual => 366,
+        }
+    };
+
+    let slack = chrono::Duration::days(period + 3);
+
+    if actual.start - slack > expected.start || actual.end + slack < expected.end {
+        Some(Warning::Truncated { rows, expected, actual })
+    } else {
+        None
+    }
+}
+
+/// Range of the dates heading the first and last rows of `csv_data`, if they are dates.
+///
+fn payload_dates(csv_data: &[u8]) -> Option<calendar::DateRange> {
+    let mut reader = {
+        csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(csv_data)
+    };
+
+    let mut records = reader.records().filter_map(|record| record.ok()).skip_while(is_header);
+
+    let first = records.next()?;
+    let last = records.last().unwrap_or_else(|| first.clone());
+
+    dates_range(first.get(0)?, last.get(0)?)
+}
+
+/// A file of a zipped code list, failing once more than `limit` bytes are unzipped out of the
+/// whole archive, `unzipped` of which were before this file.
+///
+/// The error to report in place of the `io::Error` read through the CSV reader is kept in `error`.
+///
+#[cfg(feature = "zip")]
+struct Unzipped<R> {
+    file: R,
+    limit: u64,
+    unzipped: u64,
+    error: Option<Error>,
+}
+
+#[cfg(feature = "zip")]
+impl<R: std::io::Read> std::io::Read for Unzipped<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        // Never more than one byte past the limit, so that going over it is noticed.
+        let allowed = self.limit.saturating_sub(self.unzipped).saturating_add(1);
+        let len = allowed.min(buf.len() as u64) as usize;
+
+        match self.file.read(&mut buf[..len]) {
+            Ok(read) => {
+                self.unzipped += read as u64;
+
+                if self.unzipped > self.limit {
+                    self.error = Some(Error::UnzippedTooLarge {
+                        limit: self.limit,
+                        observed: self.unzipped,
+                    });
+
+                    let message = "unzipped more than allowed";
+                    return Err(std::io::Error::other(message));
+                }
+
+                Ok(read)
+            },
+
+            Err(e) => {
+                self.error = Some(Error::ZipExtraction(e.to_string()));
+                Err(e)
+            },
+        }
+    }
+}
+
+/// Parse the codes listed in `csv_data` (the header first if `has_headers`) into `codes`.
+///
+#[cfg(feature = "zip")]
+fn parse_codes<R: std::io::Read>(csv_data: R, has_headers: bool, codes: &mut Vec<Code>)
+    -> Result<()>
+{
+    let mut reader = csv::ReaderBuilder::new().has_headers(has_headers).from_reader(csv_data);
+
+    // `deserialize` ignores the errors reading the header, e.g. of a damaged archive.
+    if has_headers {
+        reader.byte_headers()?;
+    }
+
+    for record in reader.deserialize() {
+        let record: (String, String) = record?;
+
+        let (database_code, dataset_code) = {
+            let pair: Vec<_> = record.0.split('/').collect();
+
+            if pair.len() != 2 {
+                let error_message = "Invalid format for dataset codes in unzipped code list.";
+
+                // The first record of the list is its header.
+                return Err(Error::csv(codes.len() + 2, error_message));
+            }
+
+            (pair[0].to_string(), pair[1].to_string())
+        };
+
+        codes.push(Code {
+            database_code,
+            dataset_code,
+            name: record.1,
+        });
+    }
+
+    Ok(())
+}
+
+/// Whether `zipped_data` ends as a ZIP64 archive does, i.e. with the locator of a ZIP64 end of
+/// central directory right before its end of central directory record. This is found as the zip
+/// crate does, searching the record backwards from the end of the archive.
+///
+#[cfg(feature = "zip")]
+fn is_zip64(zipped_data: &[u8]) -> bool {
+    const END_SIZE: usize = 22;
+    const LOCATOR_SIZE: usize = 20;
+
+    let last = match zipped_data.len().checked_sub(END_SIZE) {
+        Some(last) => last,
+        None => return false,
+    };
+
+    let first = last.saturating_sub(u16::MAX as usize);
+
+    let end = (first..=last).rev().find(|&position| {
+        let comment_length = {
+            u16::from_le_bytes([zipped_data[position + 20], zipped_data[position + 21]])
+        };
+
+        zipped_data[position..position + 4] == *b"PK\x05\x06"
+            && zipped_data.len() - position - END_SIZE == comment_length as usize
+    });
+
+    match end {
+        Some(end) if end >= LOCATOR_SIZE => {
+            zipped_data[end - LOCATOR_SIZE..end - LOCATOR_SIZE + 4] == *b"PK\x06\x07"
+        },
+        _ => false,
+    }
+}
+
+/// The value of the only field of `tree`, in which Quandl wraps the metadata it sends.
+///
+fn single_element<T>(tree: BTreeMap<String, T>) -> Result<T> {
+    let len = tree.len();
+
+    match tree.into_iter().next() {
+        Some((_, value)) if len == 1 => Ok(value),
+
+        _ => Err(Error::JsonParsing {
+            message: format!("Expected a single element, got {}.", len),
+            snippet: String::new(),
+        }),
+    }
+}
+
+/// Range from either of `a` and `b` to the other, if both are dates.
+///
+fn dates_range(a: &str, b: &str) -> Option<calendar::DateRange> {
+    let (a, b) = (calendar::parse_date(a.trim())?, calendar::parse_date(b.trim())?);
+
+    Some(calendar::DateRange::new(a.min(b), a.max(b)))
+}
+
+/// Whether or not `record` is a line of column names rather than a row: neither its first field
+/// is a date, nor any of its fields a number.
+///
+pub(crate) fn is_header(record: &csv::StringRecord) -> bool {
+    let is_date = |field: &str| calendar::parse_date(field).is_some();
+    let is_number = |field: &str| field.parse::<f64>().is_ok();
+
+    match record.get(0) {
+        Some(first) => !is_date(first.trim()) && !record.iter().any(|x| is_number(x.trim())),
+        None => false,
+    }
+}
+
+impl<'a> TryFrom<&'a DatasetMetadata> for DataQuery {
+    type Error = Error;
+
+    /// Create a query for all the data of the dataset described by `metadata`, i.e. in ascending
+    /// order from its `oldest_available_date` to its `newest_available_date`.
+    ///
+    fn try_from(metadata: &'a DatasetMetadata) -> Result<Self> {
+        use chrono::Datelike;
+
+        let (start, end) = metadata.date_range()?;
+
+        let ymd = |date: chrono::NaiveDate| {
+            match u16::try_from(date.year()) {
+                Ok(year) => Ok((year, date.month() as u8, date.day() as u8)),
+                Err(_) => Err(Error::ParsingFailed(format!("unsupported year in date {}.", date))),
+            }
+        };
+
+        let (start, end) = (ymd(start)?, ymd(end)?);
+
+        let mut query = DataQuery::new(&metadata.database_code, &metadata.dataset_code);
+
+        query.order(Order::asc)
+             .start_date(start.0, start.1, start.2)
+             .end_date(end.0, end.1, end.2);
+
+        Ok(query)
+    }
+}
+
+impl DataAndMetadataQuery {
+    /// Create a new data and metadata query.
+    ///
+    pub fn new<S1: AsRef<str>, S2: AsRef<str>>(database_code: S1, dataset_code: S2) -> Self {
+        DataAndMetadataQuery {
+            database_code: Arc::from(database_code.as_ref()),
+            dataset_code: Arc::from(dataset_code.as_ref()),
+            id: None,
+            data_arguments: DataArguments::default(),
+            request_arguments: ApiArguments::from_config(),
+        }
+    }
+
+    /// Same as `new`, from the full code of the dataset (e.g. `"WIKI/AAPL"`), but failing right
+    /// away with `Error::ValidationFailed` when either code is invalid rather than leaving it to
+    /// `validate` (see `ApiParameters::auto_uppercase`).
+    ///
+    pub fn from_code<S: AsRef<str>>(code: S) -> Result<Self> {
+        let (database_code, dataset_code) = split_code(code.as_ref())?;
+
+        checked_codes(DataAndMetadataQuery::new(database_code, dataset_code), |query, errors| {
+            check_dataset(None,
+                          &query.database_code,
+                          &query.dataset_code,
+                          &query.request_arguments,
+                          errors)
+        })
+    }
+
+    /// Create a new data and metadata query for the dataset of numerical identifier `id`, which
+    /// (unlike its codes) remains the same when the dataset is renamed.
+    ///
+    pub fn by_id(id: usize) -> Self {
+        let mut query = DataAndMetadataQuery::new("", "");
+        query.id = Some(id);
+        query
+    }
+
+    /// Check this query without sending it, listing every problem found: a malformed API key,
+    /// database code or dataset code, codes set along with an `id`, impossible or inverted dates,
+    /// both `rows` and `limit` being set, or (behind the `registry` feature) a `column_index`
+    /// beyond the columns known of the database's datasets.
+    ///
+    pub fn validate(&self) -> ::std::result::Result<(), Vec<ValidationError>> {
+        let mut errors = self.request_arguments.validation_errors();
+        check_dataset(self.id, &self.database_code, &self.dataset_code, &self.request_arguments,
+                      &mut errors);
+        errors.extend(self.data_arguments.validation_errors());
+        check_column_index(&self.request_arguments.code(&self.database_code),
+                           &self.data_arguments,
+                           &mut errors);
+        validated(errors)
+    }
+
+    /// Same as `send`, but rows which may have been cut off by Quandl are reported as with
+    /// `DataQuery::send_with_warnings`. The dates covered by the rows are checked against those
+    /// the metadata advertises, making for a `Warning::Truncated` when the rows clearly fall short
+    /// of them, and no warning at all when they do not.
+    ///
+    pub fn send_with_warnings<T>(&self) -> Result<WithWarnings<Dataset<T>>>
+        where T: DeserializeOwned + Clone
+    {
+        let (dataset, dates) = self.fetch::<T>()?;
+        let mut result = WithWarnings::new(dataset);
+
+        let warning = {
+            truncation_warning(&self.data_arguments,
+                               result.rows.len(),
+                               || dates,
+                               Some(&result.metadata))
+        };
+
+        result.warnings.extend(warning);
+        Ok(result)
+    }
+
+    /// Download and decode the dataset, along with the range of the dates of its first and last
+    /// rows, if they are dates.
+    ///
+    fn fetch<T>(&self) -> Result<(Dataset<T>, Option<calendar::DateRange>)>
+        where T: DeserializeOwned + Clone
+    {
+        let body = checked_body::<Dataset<T>, _>(self, JSON)?;
+
+        let mut tree = crate::download::json::<BTreeMap<String, serde_json::Value>>(&body)?;
+
+        // Only the values are decoded from here on, the payload can go.
+        drop(body);
+
+        let mut dataset = {
+            match tree.remove("dataset") {
+                Some(serde_json::Value::Object(dataset)) => dataset,
+
+                _ => return Err(Error::JsonParsing {
+                    message: "Expected a dataset object.".to_string(),
+                    snippet: String::new(),
+                }),
+            }
+        };
+
+        let data = dataset.remove("data").unwrap_or_default();
+
+        let dates = {
+            data.as_array().and_then(|rows| {
+                let first = rows.first()?.get(0)?.as_str()?;
+                let last = rows.last()?.get(0)?.as_str()?;
+                dates_range(first, last)
+            })
+        };
+
+        // Decoded from values, so there is no payload left to quote.
+        let rows = serde_json::from_value(data)?;
+        let mut metadata: DatasetMetadata = {
+            serde_json::from_value(serde_json::Value::Object(dataset))?
+        };
+
+        // Quandl lists every column of the dataset, while the rows only hold the date and the
+        // selected column.
+        if let Some(index) = self.data_arguments.column_index() {
+            if index < metadata.column_names.len() {
+                metadata.column_names = {
+                    vec![metadata.column_names[0].clone(), metadata.column_names[index].clone()]
+                };
+            }
+        }
+
+        Ok((Dataset { metadata, rows }, dates))
+    }
+}
+
+impl DatatableQuery {
+    /// Create a new query of the table `table_code` of the vendor `vendor_code`, e.g.
+    /// `DatatableQuery::new("WIKI", "PRICES")`.
+    ///
+    pub fn new<S1: AsRef<str>, S2: AsRef<str>>(vendor_code: S1, table_code: S2) -> Self {
+        DatatableQuery {
+            vendor_code: Arc::from(vendor_code.as_ref()),
+            table_code: Arc::from(table_code.as_ref()),
+            filters: vec![],
+            columns: vec![],
+            per_page: None,
+            cursor_id: None,
+            request_arguments: ApiArguments::from_config(),
+        }
+    }
+
+    /// The code of the vendor queried, as given (see `ApiParameters::auto_uppercase`).
+    ///
+    pub fn vendor_code(&self) -> &str {
+        &self.vendor_code
+    }
+
+    /// The code of the table queried, as given (see `ApiParameters::auto_uppercase`).
+    ///
+    pub fn table_code(&self) -> &str {
+        &self.table_code
+    }
+
+    /// Only return the rows whose `column` is one of `values`, e.g. `filter("ticker", ["AAPL",
+    /// "MSFT"])` for `ticker=AAPL,MSFT`, replacing any previous filter of `column` on its own.
+    ///
+    /// Quandl only allows filtering on some of the columns of a table, as listed in its
+    /// documentation.
+    ///
+    pub fn filter<C, V, S>(&mut self, column: C, values: V) -> &mut Self
+        where C: AsRef<str>,
+              V: AsRef<[S]>,
+              S: AsRef<str>,
+    {
+        let values = values.as_ref().iter().map(|x| x.as_ref().to_string()).collect();
+        self.set_filter(column.as_ref().to_string(), values)
+    }
+
+    /// Only return the rows whose `column` compares to `value` as given, e.g.
+    /// `filter_by("date", Comparison::gte, "2018-01-01")` for `date.gte=2018-01-01`, replacing any
+    /// previous filter of `column` with the same comparison.
+    ///
+    pub fn filter_by<C, S>(&mut self, column: C, comparison: Comparison, value: S) -> &mut Self
+        where C: AsRef<str>,
+              S: AsRef<str>,
+    {
+        let key = format!("{}.{}", column.as_ref(), comparison.as_api_token());
+        self.set_filter(key, vec![value.as_ref().to_string()])
+    }
+
+    /// Only return the given `columns` of the rows, in that order (`qopts.columns`).
+    ///
+    pub fn columns<V: AsRef<[S]>, S: AsRef<str>>(&mut self, columns: V) -> &mut Self {
+        self.columns = columns.as_ref().iter().map(|x| x.as_ref().to_string()).collect();
+        self
+    }
+
+    /// Specify how many rows should be returned per page (`qopts.per_page`).
+    ///
+    pub fn per_page(&mut self, n: usize) -> &mut Self {
+        self.per_page = Some(n);
+        self
+    }
+
+    /// Query the page of rows following the one whose `Datatable::next_cursor_id` is `cursor_id`
+    /// (`qopts.cursor_id`).
+    ///
+    pub fn cursor_id<S: AsRef<str>>(&mut self, cursor_id: S) -> &mut Self {
+        self.cursor_id = Some(cursor_id.as_ref().to_string());
+        self
+    }
+
+    /// Check this query without sending it, listing every problem found: a malformed API key,
+    /// vendor code or table code, or a filter without any value.
+    ///
+    /// This is what a strict query does before being sent, see `ApiParameters::strict`.
+    ///
+    pub fn validate(&self) -> ::std::result::Result<(), Vec<ValidationError>> {
+        let mut errors = self.request_arguments.validation_errors();
+        check_vendor_code(&self.request_arguments.code(&self.vendor_code), &mut errors);
+        check_table_code(&self.request_arguments.code(&self.table_code), &mut errors);
+
+        for (column, values) in &self.filters {
+            if values.is_empty() {
+                errors.push(ValidationError::new("filter", format!("'{}' must be given at least \
+                                                                    one value.",
+                                                                   column)));
+            }
+        }
+
+        validated(errors)
+    }
+
+    /// Decode a JSON payload as returned by Quandl for this query (e.g. one previously obtained
+    /// through `encoded_data`).
+    ///
+    /// This is what `send` uses once the rows are downloaded.
+    ///
+    pub fn decode<T: DeserializeOwned>(&self, json_data: &[u8]) -> Result<Datatable<T>> {
+        let response: DatatableResponse<T> = crate::download::json(json_data)?;
+
+        Ok(Datatable {
+            columns: response.datatable.columns,
+            rows: response.datatable.data,
+            next_cursor_id: response.meta.and_then(|meta| meta.next_cursor_id),
+        })
+    }
+
+    fn set_filter(&mut self, key: String, values: Vec<String>) -> &mut Self {
+        match self.filters.iter_mut().find(|(filtered, _)| *filtered == key) {
+            Some(filter) => filter.1 = values,
+            None => self.filters.push((key, values)),
+        }
+
+        self
+    }
+
+    /// The filters and options of the query, as appended to its URL.
+    ///
+    fn arguments(&self) -> Option<String> {
+        let encoded = |x: &str| byte_serialize(x.as_bytes()).collect::<String>();
+        let joined = |values: &[String]| {
+            values.iter().map(|x| encoded(x)).collect::<Vec<_>>().join(",")
+        };
+
+        let mut arguments: Vec<String> = ApiParameters::fmt(self).into_iter().collect();
+
+        for (column, values) in &self.filters {
+            arguments.push(format!("{}={}", encoded(column), joined(values)));
+        }
+
+        if !self.columns.is_empty() {
+            arguments.push(format!("qopts.columns={}", joined(&self.columns)));
+        }
+
+        if let Some(n) = self.per_page {
+            arguments.push(format!("qopts.per_page={}", n));
+        }
+
+        if let Some(ref cursor_id) = self.cursor_id {
+            arguments.push(format!("qopts.cursor_id={}", encoded(cursor_id)));
+        }
+
+        Some(arguments.join("&")).filter(|arguments| !arguments.is_empty())
+    }
+}
+
+/// The body of Quandl's reply to a `DatatableQuery`.
+///
+#[derive(Deserialize)]
+struct DatatableResponse<T> {
+    datatable: DatatableBody<T>,
+    meta: Option<DatatableMeta>,
+}
+
+#[derive(Deserialize)]
+struct DatatableBody<T> {
+    data: Vec<T>,
+    columns: Vec<DatatableColumn>,
+}
+
+#[derive(Deserialize)]
+struct DatatableMeta {
+    next_cursor_id: Option<String>,
+}
+
+impl ApiCall<DatabaseMetadata> for DatabaseMetadataQuery {
+    fn send(&self) -> Result<DatabaseMetadata> {
+        self.decode(&checked_body::<DatabaseMetadata, _>(self, JSON)?)
+    }
+
+    fn fmt_prefix(&self) -> Option<String> {
+        Some(format!("/databases/{}.json", self.request_arguments.code(&self.database_code)))
+    }
+
+    fn database_code(&self) -> Option<&str> {
+        Some(&self.database_code)
+    }
+
+    fn validation_errors(&self) -> Vec<ValidationError> {
+        self.validate().err().unwrap_or_default()
+    }
+
+    fn fmt_arguments(&self) -> Option<String> {
+        ApiParameters::fmt(self)
+    }
+}
+
+impl ApiCall<DatasetMetadata> for DatasetMetadataQuery {
+    fn send(&self) -> Result<DatasetMetadata> {
+        self.decode(&checked_body::<DatasetMetadata, _>(self, JSON)?)
+    }
+
+    fn fmt_prefix(&self) -> Option<String> {
+        let path = {
+            dataset_path(self.id, &self.database_code, &self.dataset_code, &self.request_arguments)
+        };
+        Some(format!("/datasets/{}/metadata.json", path))
+    }
+
+    fn database_code(&self) -> Option<&str> {
+        Some(&self.database_code)
+    }
+
+    fn dataset_code(&self) -> Option<&str> {
+        Some(&self.dataset_code)
+    }
+
+    fn validation_errors(&self) -> Vec<ValidationError> {
+        self.validate().err().unwrap_or_default()
+    }
+
+    fn fmt_arguments(&self) -> Option<String> {
+        ApiParameters::fmt(self)
+    }
+}
+
+impl ApiCall<DatabaseList> for DatabaseSearch {
+    fn fmt_prefix(&self) -> Option<String> {
+        Some(String::from("/databases.json"))
+    }
+
+    fn validation_errors(&self) -> Vec<ValidationError> {
+        self.validate().err().unwrap_or_default()
+    }
+
+    fn fmt_arguments(&self) -> Option<String> {
+        match (ApiParameters::fmt(self), SearchParameters::fmt(self)) {
+            (Some(arg_1), Some(arg_2)) => Some(format!("{}&{}", arg_1, arg_2)),
+            (Some(arg), None) | (None, Some(arg)) => Some(arg),
+            (None, None) => None,
+        }
+    }
+}
+
+impl ApiCall<DatasetList> for DatasetSearch {
+    fn fmt_prefix(&self) -> Option<String> {
+        Some(String::from("/datasets.json"))
+    }
+
+    fn fmt_arguments(&self) -> Option<String> {
+        let database_code = {
+            Serializer::new(String::new())
+                .append_pair("database_code", &self.request_arguments.code(&self.database_code))
+                .finish()
+        };
+
+        match (ApiParameters::fmt(self), SearchParameters::fmt(self)) {
+            (Some(arg_1), Some(arg_2)) => Some(format!("{}&{}&{}", arg_1, arg_2, database_code)),
+            (Some(arg), None) | (None, Some(arg)) => Some(format!("{}&{}", arg, database_code)),
+            (None, None) => Some(database_code),
+        }
+    }
+
+    fn database_code(&self) -> Option<&str> {
+        Some(&self.database_code)
+    }
+
+    fn validation_errors(&self) -> Vec<ValidationError> {
+        self.validate().err().unwrap_or_default()
+    }
+}
+
+impl ApiCall<Vec<DatasetMetadataLite>> for DatasetListingQuery {
+    fn send(&self) -> Result<Vec<DatasetMetadataLite>> {
+        self.decode(&checked_body::<Vec<DatasetMetadataLite>, _>(self, CSV)?[..])
+    }
+
+    fn fmt_prefix(&self) -> Option<String> {
+        Some(String::from("/datasets.csv"))
+    }
+
+    fn fmt_arguments(&self) -> Option<String> {
+        let database_code = {
+            Serializer::new(String::new())
+                .append_pair("database_code", &self.request_arguments.code(&self.database_code))
+                .finish()
+        };
+
+        let sort_by = {
+            if self.sorted_by_last_updated {
+                Some(String::from("sort_by=last_updated"))
+            } else {
+                None
+            }
+        };
+
+        let arguments: Vec<String> = {
+            vec![ApiParameters::fmt(self), SearchParameters::fmt(self), sort_by]
+                .into_iter()
+                .flatten()
+                .collect()
+        };
+
+        if arguments.is_empty() {
+            Some(database_code)
+        } else {
+            Some(format!("{}&{}", arguments.join("&"), database_code))
+        }
+    }
+
+    fn database_code(&self) -> Option<&str> {
+        Some(&self.database_code)
+    }
+
+    fn validation_errors(&self) -> Vec<ValidationError> {
+        self.validate().err().unwrap_or_default()
+    }
+}
+
+#[cfg(feature = "zip")]
+impl ApiCall<Vec<Code>> for CodeListQuery {
+    fn send(&self) -> Result<Vec<Code>> {
+        self.request_arguments.ready(|| self.validate())?;
+
+        let max_bytes = {
+            let max_response_bytes = self.request_arguments.max_response_bytes;
+            max_response_bytes.unwrap_or(u64::MAX).min(self.max_compressed_bytes)
+        };
+
+        let request = crate::api_call::request::<Vec<Code>, _>(self).max_bytes(Some(max_bytes));
+        self.decode(&request.fetch()?.body)
+    }
+
+    fn fmt_prefix(&self) -> Option<String> {
+        Some(self.prefix())
+    }
+
+    fn database_code(&self) -> Option<&str> {
+        Some(&self.database_code)
+    }
+
+    fn validation_errors(&self) -> Vec<ValidationError> {
+        self.validate().err().unwrap_or_default()
+    }
+
+    fn fmt_arguments(&self) -> Option<String> {
+        ApiParameters::fmt(self)
+    }
+}
+
+impl<T: DeserializeOwned + Clone> ApiCall<Vec<T>> for DataQuery {
+    /// Rows returned in a number suggesting they were cut off (see `ROW_CAPS`) are logged as
+    /// such; `send_with_warnings` reports it instead.
+    ///
+    fn send(&self) -> Result<Vec<T>> {
+        let csv_data = checked_body::<Vec<T>, _>(self, CSV)?;
+        let data = self.decode(&csv_data[..])?;
+
+        if let Some(warning) = self.truncation_warning(data.len(), &csv_data) {
+            log::warn!("{}/{}: {}", self.database_code, self.dataset_code, warning);
+        }
+
+        Ok(data)
+    }
+
+    fn fmt_prefix(&self) -> Option<String> {
+        let path = {
+            dataset_path(self.id, &self.database_code, &self.dataset_code, &self.request_arguments)
+        };
+        Some(format!("/datasets/{}/data.csv", path))
+    }
+
+    fn database_code(&self) -> Option<&str> {
+        Some(&self.database_code)
+    }
+
+    fn dataset_code(&self) -> Option<&str> {
+        Some(&self.dataset_code)
+    }
+
+    fn kind(&self) -> QueryKind {
+        QueryKind::Heavy
+    }
+
+    fn validation_errors(&self) -> Vec<ValidationError> {
+        self.validate().err().unwrap_or_default()
+    }
+
+    fn fmt_arguments(&self) -> Option<String> {
+        let exclude_column_names = format!("exclude_column_names={}", !self.column_names);
+
+        match (ApiParameters::fmt(self), DataParameters::fmt(self)) {
+            (Some(arg_1), Some(arg_2)) => {
+                Some(format!("{}&{}&{}", exclude_column_names, arg_1, arg_2))
+            },
+
+            (Some(arg), None) | (None, Some(arg)) => {
+                Some(format!("{}&{}", exclude_column_names, arg))
+            },
+
+            (None, None) => Some(exclude_column_names),
+        }
+    }
+}
+
+impl<T: DeserializeOwned + Clone> ApiCall<Dataset<T>> for DataAndMetadataQuery {
+    fn send(&self) -> Result<Dataset<T>> {
+        self.fetch().map(|(dataset, _)| dataset)
+    }
+
+    fn fmt_prefix(&self) -> Option<String> {
+        let path = {
+            dataset_path(self.id, &self.database_code, &self.dataset_code, &self.request_arguments)
+        };
+        Some(format!("/datasets/{}.json", path))
+    }
+
+    fn fmt_arguments(&self) -> Option<String> {
+        match (ApiParameters::fmt(self), DataParameters::fmt(self)) {
+            (Some(arg_1), Some(arg_2)) => Some(format!("{}&{}", arg_1, arg_2)),
+            (Some(arg), None) | (None, Some(arg)) => Some(arg),
+            (None, None) => None,
+        }
+    }
+
+    fn database_code(&self) -> Option<&str> {
+        Some(&self.database_code)
+    }
+
+    fn dataset_code(&self) -> Option<&str> {
+        Some(&self.dataset_code)
+    }
+
+    fn kind(&self) -> QueryKind {
+        QueryKind::Heavy
+    }
+
+    fn validation_errors(&self) -> Vec<ValidationError> {
+        self.validate().err().unwrap_or_default()
+    }
+}
+
+impl<T: DeserializeOwned + Clone> ApiCall<Datatable<T>> for DatatableQuery {
+    fn send(&self) -> Result<Datatable<T>> {
+        self.decode(&checked_body::<Datatable<T>, _>(self, JSON)?)
+    }
+
+    fn fmt_prefix(&self) -> Option<String> {
+        Some(format!("/datatables/{}/{}.json",
+                     self.request_arguments.code(&self.vendor_code),
+                     self.request_arguments.code(&self.table_code)))
+    }
+
+    fn fmt_arguments(&self) -> Option<String> {
+        self.arguments()
+    }
+
+    fn database_code(&self) -> Option<&str> {
+        Some(&self.vendor_code)
+    }
+
+    fn kind(&self) -> QueryKind {
+        QueryKind::Heavy
+    }
+
+    fn validation_errors(&self) -> Vec<ValidationError> {
+        self.validate().err().unwrap_or_default()
+    }
+}
+
+/// Inherent methods forwarding to those of the parameter traits, so that they can be called
+/// without importing the traits and are listed on the queries' own documentation.
+///
+/// The traits remain what generic code should be written against; these only call into them.
+///
+macro_rules! parameter_methods {
+    (ApiParameters for $($query:ident),*) => {
+        $(
+            impl $query {
+                /// Include your personal Quandl API key with your query, see
+                /// `ApiParameters::api_key`.
+                ///
+                pub fn api_key<S: AsRef<str>>(&mut self, api_key: S) -> &mut Self {
+                    ApiParameters::api_key(self, api_key)
+                }
+
+                /// Send the query to another server than `QUANDL_API_URL`, see
+                /// `ApiParameters::base_url`.
+                ///
+                pub fn base_url<S: AsRef<str>>(&mut self, base_url: S) -> &mut Self {
+                    ApiParameters::base_url(self, base_url)
+                }
+
+                /// Query another version of the API than `QUANDL_API_VERSION`, see
+                /// `ApiParameters::api_version`.
+                ///
+                pub fn api_version<S: AsRef<str>>(&mut self, version: S) -> &mut Self {
+                    ApiParameters::api_version(self, version)
+                }
+
+                /// Skip checking the `Content-Type` of responses before parsing them, see
+                /// `ApiParameters::accept_any_content_type`.
+                ///
+                pub fn accept_any_content_type(&mut self) -> &mut Self {
+                    ApiParameters::accept_any_content_type(self)
+                }
+
+                /// Send the query with a client configured by `config`, see
+                /// `ApiParameters::client_config`.
+                ///
+                pub fn client_config(&mut self, config: &crate::client::ClientConfig)
+                    -> &mut Self
+                {
+                    ApiParameters::client_config(self, config)
+                }
+
+                /// Give up on responses larger than `max` bytes, see
+                /// `ApiParameters::max_response_bytes`.
+                ///
+                pub fn max_response_bytes(&mut self, max: u64) -> &mut Self {
+                    ApiParameters::max_response_bytes(self, max)
+                }
+
+                /// Refuse to send the query when its `validate` method reports any problem, see
+                /// `ApiParameters::strict`.
+                ///
+                pub fn strict(&mut self, strict: bool) -> &mut Self {
+                    ApiParameters::strict(self, strict)
+                }
+
+                /// Send the database and dataset codes of the query uppercased, see
+                /// `ApiParameters::auto_uppercase`.
+                ///
+                pub fn auto_uppercase(&mut self, auto_uppercase: bool) -> &mut Self {
+                    ApiParameters::auto_uppercase(self, auto_uppercase)
+                }
+            }
+        )*
+    };
+
+    (SearchParameters for $($query:ident),*) => {
+        $(
+            impl $query {
+                /// Search for `keywords`, replacing any previous keywords, phrases and
+                /// exclusions, see `SearchParameters::query`.
+                ///
+                pub fn query<V: AsRef<[S]>, S: AsRef<str>>(&mut self, keywords: V) -> &mut Self {
+                    SearchParameters::query(self, keywords)
+                }
+
+                /// Add a phrase to search for as a whole, see `SearchParameters::phrase`.
+                ///
+                pub fn phrase<S: AsRef<str>>(&mut self, phrase: S) -> &mut Self {
+                    SearchParameters::phrase(self, phrase)
+                }
+
+                /// Exclude the entries matching `term` from the results, see
+                /// `SearchParameters::exclude`.
+                ///
+                pub fn exclude<S: AsRef<str>>(&mut self, term: S) -> &mut Self {
+                    SearchParameters::exclude(self, term)
+                }
+
+                /// Specify how many entries should be returned per page, see
+                /// `SearchParameters::per_page`.
+                ///
+                pub fn per_page(&mut self, n: usize) -> &mut Self {
+                    SearchParameters::per_page(self, n)
+                }
+
+                /// Specify which page of entries to query, see `SearchParameters::page`.
+                ///
+                pub fn page(&mut self, n: usize) -> &mut Self {
+                    SearchParameters::page(self, n)
+                }
+            }
+        )*
+    };
+
+    (DataParameters for $($query:ident),*) => {
+        $(
+            impl $query {
+                /// Specify the number of rows of data to be returned, see
+                /// `DataParameters::rows`.
+                ///
+                pub fn rows(&mut self, n: usize) -> &mut Self {
+                    DataParameters::rows(self, n)
+                }
+
+                /// Specify the number of rows of data to be returned, see
+                /// `DataParameters::limit`.
+                ///
+                pub fn limit(&mut self, n: usize) -> &mut Self {
+                    DataParameters::limit(self, n)
+                }
+
+                /// Specify the ordering of the data, see `DataParameters::order`.
+                ///
+                pub fn order(&mut self, order: Order) -> &mut Self {
+                    DataParameters::order(self, order)
+                }
+
+                /// Specify whether the data should be returned at a smaller frequency than
+                /// available, see `DataParameters::collapse`.
+                ///
+                pub fn collapse(&mut self, collapse: Frequency) -> &mut Self {
+                    DataParameters::collapse(self, collapse)
+                }
+
+                /// Specify how the data should be transformed by Quandl's server, see
+                /// `DataParameters::transform`.
+                ///
+                pub fn transform(&mut self, transform: Transform) -> &mut Self {
+                    DataParameters::transform(self, transform)
+                }
+
+                /// Specify the newest data point to be returned, see `DataParameters::end_date`.
+                ///
+                pub fn end_date(&mut self, year: u16, month: u8, day: u8) -> &mut Self {
+                    DataParameters::end_date(self, year, month, day)
+                }
+
+                /// Specify the oldest data point to be returned, see
+                /// `DataParameters::start_date`.
+                ///
+                pub fn start_date(&mut self, year: u16, month: u8, day: u8) -> &mut Self {
+                    DataParameters::start_date(self, year, month, day)
+                }
+
+                /// Same as `end_date`, with the date given as a `YYYY-MM-DD` string, see
+                /// `DataParameters::end_date_str`.
+                ///
+                pub fn end_date_str<S: AsRef<str>>(&mut self, date: S) -> Result<&mut Self> {
+                    DataParameters::end_date_str(self, date)
+                }
+
+                /// Same as `start_date`, with the date given as a `YYYY-MM-DD` string, see
+                /// `DataParameters::start_date_str`.
+                ///
+                pub fn start_date_str<S: AsRef<str>>(&mut self, date: S) -> Result<&mut Self> {
+                    DataParameters::start_date_str(self, date)
+                }
+
+                /// Specify the newest data point to be returned as the end of a fiscal period,
+                /// see `DataParameters::end_period`.
+                ///
+                pub fn end_period(&mut self, period: calendar::PeriodSpec) -> &mut Self {
+                    DataParameters::end_period(self, period)
+                }
+
+                /// Specify the oldest data point to be returned as the start of a fiscal
+                /// period, see `DataParameters::start_period`.
+                ///
+                pub fn start_period(&mut self, period: calendar::PeriodSpec) -> &mut Self {
+                    DataParameters::start_period(self, period)
+                }
+
+                /// Specify which column to be returned, see `DataParameters::column_index`.
+                ///
+                pub fn column_index(&mut self, index: usize) -> &mut Self {
+                    DataParameters::column_index(self, index)
+                }
+            }
+        )*
+    };
+}
+
+parameter_methods!(ApiParameters for DatabaseSearch, DatasetSearch, DatasetListingQuery,
+                   DatabaseMetadataQuery, DatasetMetadataQuery, CodeListQuery,
+                   DatabaseDownloadQuery, DataQuery, DataAndMetadataQuery, DatatableQuery);
+parameter_methods!(SearchParameters for DatabaseSearch, DatasetSearch, DatasetListingQuery);
+parameter_methods!(DataParameters for DataQuery, DataAndMetadataQuery);
 
 impl ApiParameters for DatabaseSearch {}
 impl ApiParameters for DatasetSearch {}
+impl ApiParameters for DatasetListingQuery {}
 impl ApiParameters for DatabaseMetadataQuery {}
 impl ApiParameters for DatasetMetadataQuery {}
 impl ApiParameters for CodeListQuery {}
+impl ApiParameters for DatabaseDownloadQuery {}
 impl ApiParameters for DataQuery {}
+impl ApiParameters for DataAndMetadataQuery {}
+impl ApiParameters for DatatableQuery {}
 impl SearchParameters for DatabaseSearch {}
 impl SearchParameters for DatasetSearch {}
+impl SearchParameters for DatasetListingQuery {}
 impl DataParameters for DataQuery {}
+impl DataParameters for DataAndMetadataQuery {}
 
 impl_has!(DatabaseSearch, ApiArguments, request_arguments);
 impl_has!(DatabaseSearch, SearchArguments, search_arguments);
 impl_has!(DatasetSearch, ApiArguments, request_arguments);
 impl_has!(DatasetSearch, SearchArguments, search_arguments);
+impl_has!(DatasetListingQuery, ApiArguments, request_arguments);
+impl_has!(DatasetListingQuery, SearchArguments, search_arguments);
 impl_has!(DatabaseMetadataQuery, ApiArguments, request_arguments);
 impl_has!(DatasetMetadataQuery, ApiArguments, request_arguments);
 impl_has!(CodeListQuery, ApiArguments, request_arguments);
+impl_has!(DatabaseDownloadQuery, ApiArguments, request_arguments);
 impl_has!(DataQuery, DataArguments, data_arguments);
 impl_has!(DataQuery, ApiArguments, request_arguments);
+impl_has!(DataAndMetadataQuery, DataArguments, data_arguments);
+impl_has!(DataAndMetadataQuery, ApiArguments, request_arguments);
+impl_has!(DatatableQuery, ApiArguments, request_arguments);
@@ -0,0 +1,531 @@
+use std::collections::{BTreeMap, HashSet};
+use std::convert::TryFrom;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use has::Has;
+use serde::de::DeserializeOwned;
+use url::Url;
+
+use crate::{ApiKey, Result, Error};
+use crate::api_call::ApiCall;
+use crate::backoff::is_retryable;
+use crate::batch_query::{self, BatchQuery};
+use crate::key_pool::KeyProfile;
+use crate::parameters::{ApiArguments, ApiParameters};
+use crate::verify::redacted;
+
+/// A query to be sent by a `DownloadQueue`, as persisted in the queue: its URL, API key excluded.
+///
+/// The query is rebuilt from its URL when the queue is drained (see e.g. `DataQuery::from_url`),
+/// so only what is part of the URL is kept: the API key is given by `DrainConfig` instead, and
+/// settings such as `ApiParameters::strict` are left to their defaults.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct QuerySpec {
+    /// URL of the query, without any `api_key` parameter.
+    ///
+    pub url: String,
+}
+
+impl QuerySpec {
+    /// Spec of `query`, failing if its URL cannot be built.
+    ///
+    /// Queries decoding several types of results need to be told which one, e.g.
+    /// `QuerySpec::new::<Vec<(String, f64)>, _>(&DataQuery::new("WIKI", "AAPL"))`.
+    ///
+    pub fn new<T, A>(query: &A) -> Result<Self>
+        where T: DeserializeOwned + Clone,
+              A: ApiCall<T>,
+    {
+        Ok(QuerySpec { url: crate::download::without_api_key(query.parsed_url()?) })
+    }
+
+    /// Spec of the query sending `url`, whose `api_key` parameter (if any) is dropped.
+    ///
+    pub fn from_url(url: &Url) -> Self {
+        QuerySpec { url: crate::download::without_api_key(url.clone()) }
+    }
+}
+
+/// Where an item of a `DownloadQueue` stands.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ItemState {
+    /// Never sent.
+    ///
+    Queued,
+
+    /// Marked as being sent, without any outcome recorded: the worker sending it died (or is
+    /// still sending it, when another process drains the queue).
+    ///
+    InProgress,
+
+    /// Sent successfully.
+    ///
+    Done,
+
+    /// The last attempt failed with `error` (as displayed, API key redacted), which is only worth
+    /// sending again if `retryable` (see `backoff::is_retryable`).
+    ///
+    Failed {
+        error: String,
+        retryable: bool,
+    },
+}
+
+/// An item of a `DownloadQueue`, see `DownloadQueue::items`.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueueItem {
+    /// Identifier of the item, given in order by `DownloadQueue::push`.
+    ///
+    pub id: u64,
+
+    pub spec: QuerySpec,
+    pub state: ItemState,
+
+    /// Number of times the item was marked as being sent, including the attempts interrupted by
+    /// a crash.
+    ///
+    pub attempts: usize,
+}
+
+impl QueueItem {
+    /// Whether or not draining the queue with at most `max_attempts` per item sends this one.
+    ///
+    pub fn is_pending(&self, max_attempts: usize) -> bool {
+        match self.state {
+            ItemState::Queued => true,
+            ItemState::InProgress => self.attempts < max_attempts,
+            ItemState::Done => false,
+            ItemState::Failed { retryable, .. } => retryable && self.attempts < max_attempts,
+        }
+    }
+}
+
+/// How a `DownloadQueue` is drained, see `DownloadQueue::drain`.
+///
+/// This is built like a `config::Config`, e.g.
+///
+/// ```rust
+/// use quandl_v3::ApiKey;
+/// use quandl_v3::queue::DrainConfig;
+///
+/// let config = DrainConfig {
+///     api_key: Some(ApiKey::new("KEY")),
+///     limits: vec![(2_000, 600), (50_000, 86_400)],
+///     .. DrainConfig::default()
+/// };
+/// ```
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct DrainConfig {
+    /// Key the queries are sent with, otherwise that of the current `config::Config`, if any.
+    ///
+    pub api_key: Option<ApiKey>,
+
+    /// Keys the queries left without one are spread over, see `BatchQuery::key_profile`.
+    ///
+    pub key_profiles: Vec<KeyProfile>,
+
+    /// Rate limits, as `(calls, seconds)`, see `BatchQuery::limit`.
+    ///
+    pub limits: Vec<(usize, u64)>,
+
+    /// Number of worker threads, 1 by default, see `BatchQuery::threads`.
+    ///
+    pub threads: usize,
+
+    /// Most times an item is sent, its attempts interrupted by a crash included, 3 by default.
+    ///
+    pub max_attempts: usize,
+
+    /// Send the queries left without an API key anonymously, see `BatchQuery::anonymous`. They
+    /// are otherwise left queued.
+    ///
+    pub anonymous: bool,
+}
+
+impl Default for DrainConfig {
+    fn default() -> Self {
+        DrainConfig {
+            api_key: None,
+            key_profiles: vec![],
+            limits: vec![],
+            threads: 1,
+            max_attempts: 3,
+            anonymous: false,
+        }
+    }
+}
+
+/// What became of an item of a `DownloadQueue` drained by `DownloadQueue::drain`.
+///
+#[derive(Debug)]
+pub struct QueueOutcome<T> {
+    pub id: u64,
+    pub spec: QuerySpec,
+
+    /// Number of times the item was sent, this attempt included.
+    ///
+    pub attempts: usize,
+
+    /// The result of the query.
+    ///
+    pub result: Result<T>,
+}
+
+/// Queue of queries persisted to a file, to be sent over several runs of a process, see `open`.
+///
+/// The queue is a JSON Lines log of what happened to its items: pushed, marked as being sent,
+/// then done or failed. Every record is flushed to disk (`File::sync_data`) before the queue goes
+/// on, and an item is marked as being sent before any request is made for it, so a crash at any
+/// point leaves the queue consistent:
+///
+/// * an item whose push did not return is not in the queue (its record was not written, or only
+///   in part, in which case it is ignored when the queue is opened again);
+/// * an item being sent is found `ItemState::InProgress` once the queue is opened again, and
+///   sent again by the next drain unless it already used up its attempts;
+/// * an item whose outcome was recorded is never sent again, unless it failed in a retryable way.
+///
+/// Items are thus sent at least once, more than once only when a crash hits between a request
+/// and the record of its outcome. A queue is meant to be used by a single process at a time.
+///
+pub struct DownloadQueue {
+    log: Arc<Log>,
+    items: BTreeMap<u64, QueueItem>,
+    next_id: u64,
+}
+
+impl DownloadQueue {
+    /// Open the queue persisted at `path`, created (empty) if needed, and replay its log.
+    ///
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+
+        let io_error = |e: ::std::io::Error| {
+            Error::IoError(format!("cannot open queue {}: {}", path.display(), e))
+        };
+
+        let file = OpenOptions::new().create(true).append(true).read(true).open(path);
+        let mut file = file.map_err(io_error)?;
+        let mut content = String::new();
+        file.read_to_string(&mut content).map_err(io_error)?;
+
+        let mut items = BTreeMap::new();
+        let mut next_id = 0;
+
+        // As with the manifest of a batch, a line which does not parse was being written when a
+        // previous run died: it is ignored, and terminated so the next records start a new line.
+        for record in content.lines().filter_map(|line| serde_json::from_str(line).ok()) {
+            match record {
+                Record::Push { id, url } => {
+                    let spec = QuerySpec { url };
+                    items.insert(id, QueueItem { id, spec, state: ItemState::Queued, attempts: 0 });
+                    next_id = next_id.max(id + 1);
+                },
+
+                Record::Start { id } => if let Some(item) = items.get_mut(&id) {
+                    item.state = ItemState::InProgress;
+                    item.attempts += 1;
+                },
+
+                Record::Done { id } => if let Some(item) = items.get_mut(&id) {
+                    item.state = ItemState::Done;
+                },
+
+                Record::Fail { id, error, retryable } => if let Some(item) = items.get_mut(&id) {
+                    item.state = ItemState::Failed { error, retryable };
+                },
+            }
+        }
+
+        if !content.is_empty() && !content.ends_with('\n') {
+            file.write_all(b"\n").and_then(|_| file.sync_data()).map_err(io_error)?;
+        }
+
+        let log = Log { path: path.to_path_buf(), file: Mutex::new(file) };
+        Ok(DownloadQueue { log: Arc::new(log), items, next_id })
+    }
+
+    /// Add `spec` to the queue, returning the id of the new item once it is on disk.
+    ///
+    pub fn push(&mut self, spec: QuerySpec) -> Result<u64> {
+        Ok(self.push_all(vec![spec])?[0])
+    }
+
+    /// Add every spec of `specs` to the queue, in order, returning the ids of the new items once
+    /// they are on disk. This only flushes the log once, which is much faster than pushing them
+    /// one by one.
+    ///
+    pub fn push_all<I>(&mut self, specs: I) -> Result<Vec<u64>>
+        where I: IntoIterator<Item = QuerySpec>
+    {
+        let specs: Vec<QuerySpec> = specs.into_iter().collect();
+        let ids: Vec<u64> = (self.next_id..).take(specs.len()).collect();
+
+        let records: Vec<Record> = {
+            ids.iter().zip(&specs)
+                .map(|(&id, spec)| Record::Push { id, url: spec.url.clone() })
+                .collect()
+        };
+
+        self.log.append(&records)?;
+
+        for (&id, spec) in ids.iter().zip(specs) {
+            self.items.insert(id, QueueItem { id, spec, state: ItemState::Queued, attempts: 0 });
+        }
+
+        self.next_id += ids.len() as u64;
+        Ok(ids)
+    }
+
+    /// Every item of the queue, by id.
+    ///
+    pub fn items(&self) -> impl ::std::iter::Iterator<Item = &QueueItem> {
+        self.items.values()
+    }
+
+    /// The item of id `id`, if any.
+    ///
+    pub fn get(&self, id: u64) -> Option<&QueueItem> {
+        self.items.get(&id)
+    }
+
+    /// Send the pending items of the queue (see `QueueItem::is_pending`) as queries of type `A`,
+    /// through a `BatchQuery` set up from `config`, yielding their outcomes in the order of their
+    /// ids as they are recorded.
+    ///
+    /// Each item is marked as being sent right before its request is made, once the rate limits
+    /// allow it, and its outcome is recorded when it is yielded: the iterator should thus be
+    /// exhausted, otherwise the items whose outcome was not yielded count as interrupted by a
+    /// crash. Items which fail are left to the next drain, if retryable and not out of attempts.
+    ///
+    /// Items whose URL does not rebuild a query of type `A` (e.g. that of another kind of query,
+    /// to be drained as such) are left queued, as are those without an API key unless
+    /// `DrainConfig::anonymous` is set.
+    ///
+    /// ```rust,no_run
+    /// use quandl_v3::prelude::*;
+    /// use quandl_v3::queue::{DownloadQueue, DrainConfig, QuerySpec};
+    ///
+    /// type Row = (String, f64);
+    ///
+    /// let query = DataQuery::new("WIKI", "AAPL");
+    ///
+    /// let mut queue = DownloadQueue::open("scrape.jsonl").unwrap();
+    /// queue.push(QuerySpec::new::<Vec<Row>, _>(&query).unwrap()).unwrap();
+    ///
+    /// let config = DrainConfig { api_key: Some(ApiKey::new("KEY")), .. DrainConfig::default() };
+    ///
+    /// for outcome in queue.drain::<DataQuery, Vec<Row>>(&config) {
+    ///     println!("{}: {:?}", outcome.spec.url, outcome.result.map(|rows| rows.len()));
+    /// }
+    /// ```
+    ///
+    pub fn drain<A, T>(&mut self, config: &DrainConfig) -> Drain<'_, T>
+        where T: DeserializeOwned + Clone + Send + 'static,
+              A: ApiCall<T> + ApiParameters + TryFrom<Url, Error = Error> + Clone + Send + 'static,
+    {
+        let mut ids = vec![];
+        let mut keys = vec![];
+        let mut foreign = 0;
+        let mut keyless = 0;
+
+        let mut batch_query: BatchQuery<A, T> = BatchQuery::new();
+
+        for item in self.items.values().filter(|item| item.is_pending(config.max_attempts)) {
+            let mut query = {
+                match Url::parse(&item.spec.url).ok().and_then(|url| A::try_from(url).ok()) {
+                    Some(query) => query,
+                    None => {
+                        foreign += 1;
+                        continue;
+                    },
+                }
+            };
+
+            if let Some(ref key) = config.api_key {
+                query.api_key(key.expose_secret());
+            }
+
+            let key = Has::<ApiArguments>::get_ref(&query).api_key.clone();
+
+            if key.is_none() && config.key_profiles.is_empty() && !config.anonymous {
+                keyless += 1;
+                continue;
+            }
+
+            ids.push(item.id);
+            keys.push(key);
+            batch_query.query(query);
+        }
+
+        if foreign > 0 {
+            log::info!("{} items of the queue {} are not queries of the type drained and are left \
+                        queued.",
+                       foreign,
+                       self.log.path.display());
+        }
+
+        if keyless > 0 {
+            log::warn!("{} items of the queue {} have no API key and are left queued, see \
+                        `DrainConfig::anonymous`.",
+                       keyless,
+                       self.log.path.display());
+        }
+
+        for profile in &config.key_profiles {
+            batch_query.key_profile(profile.clone());
+        }
+
+        for &(calls, seconds) in &config.limits {
+            batch_query.limit(calls, seconds);
+        }
+
+        if config.anonymous {
+            batch_query.anonymous();
+        }
+
+        let started = Arc::new(Mutex::new(HashSet::new()));
+
+        {
+            let log = self.log.clone();
+            let ids = ids.clone();
+            let started = started.clone();
+
+            batch_query.threads(config.threads).on_start(move |index| {
+                log.append(&[Record::Start { id: ids[index] }])?;
+                started.lock().expect("Poisoned Mutex").insert(ids[index]);
+                Ok(())
+            });
+        }
+
+        Drain {
+            ids: ids.into_iter(),
+            keys: keys.into_iter(),
+            started,
+            results: batch_query.run(),
+            queue: self,
+        }
+    }
+}
+
+/// Iterator over the outcomes of the items of a `DownloadQueue`, see `DownloadQueue::drain`.
+///
+pub struct Drain<'a, T> {
+    queue: &'a mut DownloadQueue,
+
+    /// Ids of the items sent, in order, and the API keys they are sent with, to redact them from
+    /// the errors recorded.
+    ///
+    ids: ::std::vec::IntoIter<u64>,
+    keys: ::std::vec::IntoIter<Option<ApiKey>>,
+
+    /// Ids of the items marked as being sent, whose outcome is not yet recorded.
+    ///
+    started: Arc<Mutex<HashSet<u64>>>,
+
+    results: batch_query::Iterator<Result<T>>,
+}
+
+impl<'a, T> Drain<'a, T> {
+    /// Record that the item `id` succeeded, or failed with `error` when sent with `key`.
+    ///
+    /// A record which cannot be written is only logged: the item is then found in progress once
+    /// the queue is opened again, and sent again.
+    ///
+    fn record(&mut self, id: u64, error: Option<&Error>, key: Option<&str>) {
+        let (record, state) = {
+            match error {
+                None => (Record::Done { id }, ItemState::Done),
+
+                Some(e) => {
+                    let error = {
+                        match key {
+                            Some(key) => redacted(e.clone(), key).to_string(),
+                            None => e.to_string(),
+                        }
+                    };
+
+                    let retryable = is_retryable(e);
+                    let record = Record::Fail { id, error: error.clone(), retryable };
+
+                    (record, ItemState::Failed { error, retryable })
+                },
+            }
+        };
+
+        if let Err(e) = self.queue.log.append(&[record]) {
+            log::warn!("cannot record the outcome of item {}, which will be sent again: {}", id, e);
+        }
+
+        self.queue.items.get_mut(&id).expect("drained items are in the queue").state = state;
+    }
+}
+
+impl<'a, T> ::std::iter::Iterator for Drain<'a, T> {
+    type Item = QueueOutcome<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let id = self.ids.next()?;
+        let key = self.keys.next().flatten();
+        let result = self.results.next().expect("one result by query sent");
+
+        // Unless the item could not be marked as being sent, in which case nothing was sent for
+        // it and there is nothing to record either.
+        if self.started.lock().expect("Poisoned Mutex").remove(&id) {
+            self.queue.items.get_mut(&id).expect("drained items are in the queue").attempts += 1;
+            self.record(id, result.as_ref().err(), key.as_ref().map(ApiKey::expose_secret));
+        }
+
+        let item = &self.queue.items[&id];
+        Some(QueueOutcome { id, spec: item.spec.clone(), attempts: item.attempts, result })
+    }
+}
+
+/// A record of the log of a `DownloadQueue`, one per line.
+///
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum Record {
+    Push { id: u64, url: String },
+    Start { id: u64 },
+    Done { id: u64 },
+    Fail { id: u64, error: String, retryable: bool },
+}
+
+/// The log of a `DownloadQueue`, shared with the workers draining it.
+///
+struct Log {
+    path: PathBuf,
+    file: Mutex<File>,
+}
+
+impl Log {
+    /// Append `records` to the log and flush them to disk.
+    ///
+    fn append(&self, records: &[Record]) -> Result<()> {
+        let mut lines = String::new();
+
+        for record in records {
+            let line = {
+                serde_json::to_string(record)
+                    .map_err(|e| Error::IoError(format!("cannot serialize queue record: {}", e)))?
+            };
+
+            lines.push_str(&line);
+            lines.push('\n');
+        }
+
+        let mut file = self.file.lock().expect("Poisoned Mutex");
+
+        // Written at once so records appended concurrently are never interleaved.
+        file.write_all(lines.as_bytes()).and_then(|_| file.sync_data()).map_err(|e| {
+            Error::IoError(format!("cannot write to queue {}: {}", self.path.display(), e))
+        })
+    }
+}
@@ -0,0 +1,281 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::ApiKey;
+
+/// Outcome of checking a call against the rate limits.
+///
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Decision {
+    /// The call can be made now; it has been recorded as such.
+    ///
+    Proceed,
+
+    /// The call would break one of the limits; nothing has been recorded and the caller should
+    /// check again after waiting for the given duration.
+    ///
+    WaitFor(Duration),
+}
+
+/// Per-key sliding window rate limiter.
+///
+/// This is a pure state machine: it never sleeps nor reads the clock itself, the current instant
+/// is provided to every `check`. That makes it usable from both blocking and asynchronous code
+/// (see `acquire` and `acquire_async`) and testable without actually waiting.
+///
+/// A limit `(n, window)` means at most `n` calls may be made with a single key within any
+/// `window`-long interval of time. The keys are kept as `ApiKey`s, out of the limiter's `Debug`
+/// output.
+///
+#[derive(Debug, Clone, Default)]
+pub struct RateLimiter {
+    limits: Vec<(usize, Duration)>,
+    history: HashMap<ApiKey, VecDeque<Instant>>,
+}
+
+impl RateLimiter {
+    /// Create a limiter enforcing all of the given `(calls, window)` limits.
+    ///
+    /// Every limit must allow at least one call.
+    ///
+    pub fn new(limits: Vec<(usize, Duration)>) -> Self {
+        for &(calls, _) in &limits {
+            assert!(calls > 0, "calls: {}", calls);
+        }
+
+        RateLimiter {
+            limits,
+            history: HashMap::new(),
+        }
+    }
+
+    /// The limits enforced by this limiter.
+    ///
+    pub fn limits(&self) -> &[(usize, Duration)] {
+        &self.limits
+    }
+
+    /// Check whether a call with `key` can be made at instant `now` and record it if so.
+    ///
+    pub fn check(&mut self, key: &str, now: Instant) -> Decision {
+        let wait = self.wait_time(key, now);
+
+        if wait > Duration::from_secs(0) {
+            return Decision::WaitFor(wait);
+        }
+
+        self.record(key, 1, now);
+        Decision::Proceed
+    }
+
+    /// Time to wait before a call with `key` could be made, counting from `now`, without
+    /// recording anything.
+    ///
+    pub fn wait_time(&self, key: &str, now: Instant) -> Duration {
+        self.binding_limit(key, now).map(|(_, wait)| wait).unwrap_or_default()
+    }
+
+    /// The limit a call with `key` would break at instant `now`, along with the time to wait
+    /// before it could be made, or `None` if it can be made now. When several limits would be
+    /// broken, this is the one imposing the longest wait.
+    ///
+    pub fn binding_limit(&self, key: &str, now: Instant) -> Option<((usize, Duration), Duration)> {
+        let history = self.history.get(&ApiKey::new(key))?;
+        let mut binding = None;
+
+        for &(calls, window) in &self.limits {
+            // Only the `calls`-th most recent call matters: the next one can be made once it is
+            // at least `window` old.
+            if history.len() >= calls {
+                let release = history[history.len() - calls] + window;
+
+                if release > now && binding.map(|(_, wait)| release - now > wait).unwrap_or(true) {
+                    binding = Some(((calls, window), release - now));
+                }
+            }
+        }
+
+        binding
+    }
+
+    /// Record that `calls` calls were made with `key` at instant `now`, regardless of the limits.
+    ///
+    /// This is useful to account for calls made outside of this limiter, e.g. when a key has
+    /// already been used by another process.
+    ///
+    pub fn record(&mut self, key: &str, calls: usize, now: Instant) {
+        let capacity = self.limits.iter().map(|&(calls, _)| calls).max().unwrap_or(0);
+
+        let history = self.history.entry(ApiKey::new(key)).or_default();
+
+        for _ in 0..calls.min(capacity) {
+            history.push_back(now);
+        }
+
+        while history.len() > capacity {
+            history.pop_front();
+        }
+    }
+
+    /// Number of calls currently remembered for `key` (at most the biggest limit).
+    ///
+    pub fn recorded(&self, key: &str) -> usize {
+        self.history.get(&ApiKey::new(key)).map(VecDeque::len).unwrap_or(0)
+    }
+
+    /// What the calls recorded for `key` leave of its quota at instant `now`, i.e. of the limit
+    /// with the longest window (e.g. Quandl's daily limit), or `None` without any limit.
+    ///
+    /// The window sliding, `reset` is the time until the oldest of these calls leaves it, freeing
+    /// a call.
+    ///
+    pub fn status(&self, key: &str, now: Instant) -> Option<RateLimitStatus> {
+        let &(calls, window) = self.limits.iter().max_by_key(|&&(_, window)| window)?;
+
+        let in_window: Vec<Instant> = {
+            self.history.get(&ApiKey::new(key))
+                .map(|history| history.iter().cloned().filter(|&at| at + window > now).collect())
+                .unwrap_or_default()
+        };
+
+        // As for `binding_limit`, only the `calls`-th most recent call matters.
+        let made = in_window.len().min(calls);
+
+        let reset = {
+            match in_window.get(in_window.len() - made) {
+                Some(&at) => at + window - now,
+                None => Duration::from_secs(0),
+            }
+        };
+
+        Some(RateLimitStatus { remaining: calls - made, reset })
+    }
+}
+
+/// What is left of the quota of a key, e.g. as reported by the server from its
+/// `X-RateLimit-Remaining` header and the time left until its `X-RateLimit-Reset`, or as told by
+/// `RateLimiter::status` from the calls made.
+///
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RateLimitStatus {
+    /// Calls which can still be made before the quota is reset.
+    ///
+    pub remaining: usize,
+
+    /// Time until the quota is reset, counting from now.
+    ///
+    pub reset: Duration,
+}
+
+/// Expected time needed to make `remaining_queries` more calls with a key used according to
+/// `status`, subject to `limits` (see `RateLimiter::new`), counting from now.
+///
+/// This is the time the last call can be made at, every call being made as soon as it is allowed
+/// and taking no time itself: only the forced waits add up. Beyond `status.remaining` calls, the
+/// next one has to wait for the quota to be reset; from then on, the calls are only limited by
+/// `limits`, which apply to the calls to make and not to those made before.
+///
+/// ```rust
+/// use std::time::Duration;
+/// use quandl_v3::rate_limit::*;
+///
+/// let limits = [(300, Duration::from_secs(10)), (2_000, Duration::from_secs(600))];
+/// let status = RateLimitStatus { remaining: 0, reset: Duration::from_secs(4) };
+///
+/// // 300 calls once the quota is reset, 300 more 10 seconds later.
+/// assert_eq!(estimate_completion(600, &limits, &status), Duration::from_secs(14));
+/// ```
+///
+pub fn estimate_completion(remaining_queries: usize,
+                           limits: &[(usize, Duration)],
+                           status: &RateLimitStatus) -> Duration
+{
+    let mut limiter = RateLimiter::new(limits.to_vec());
+    let start = Instant::now();
+    let mut now = start;
+
+    for call in 0..remaining_queries {
+        if call == status.remaining {
+            now = now.max(start + status.reset);
+        }
+
+        now += limiter.wait_time("", now);
+        limiter.record("", 1, now);
+    }
+
+    now - start
+}
+
+/// Block the current thread until a call with `key` is allowed by `limiter`, then record it.
+///
+/// The lock is only held while checking, so other threads may use the limiter (e.g. for other
+/// keys) while this one sleeps.
+///
+pub fn acquire(limiter: &Mutex<RateLimiter>, key: &str) {
+    acquire_with(limiter, key, |_, _| ())
+}
+
+/// Same as `acquire`, calling `on_wait` with the time about to be waited and the limit which
+/// forces it (see `RateLimiter::binding_limit`) before each sleep.
+///
+pub fn acquire_with<F>(limiter: &Mutex<RateLimiter>, key: &str, on_wait: F)
+    where F: FnMut(Duration, (usize, Duration))
+{
+    let sleep = |wait| {
+        ::std::thread::sleep(wait);
+        Ok(())
+    };
+
+    acquire_sleeping(limiter, key, on_wait, sleep).expect("sleeping does not fail")
+}
+
+/// Same as `acquire_with`, giving up with `Error::Cancelled` (without recording a call) once a
+/// token in effect on the current thread is cancelled, see `CancelToken`.
+///
+#[cfg(feature = "batch")]
+pub(crate) fn acquire_cancellable<F>(limiter: &Mutex<RateLimiter>, key: &str, on_wait: F)
+    -> crate::Result<()>
+    where F: FnMut(Duration, (usize, Duration))
+{
+    acquire_sleeping(limiter, key, on_wait, crate::cancel::sleep)
+}
+
+fn acquire_sleeping<F, S>(limiter: &Mutex<RateLimiter>, key: &str, mut on_wait: F, mut sleep: S)
+    -> crate::Result<()>
+    where F: FnMut(Duration, (usize, Duration)),
+          S: FnMut(Duration) -> crate::Result<()>,
+{
+    loop {
+        let (limit, wait) = {
+            let mut limiter = limiter.lock().expect("Poisoned Mutex");
+            let now = Instant::now();
+
+            match limiter.binding_limit(key, now) {
+                Some(binding) => binding,
+
+                None => {
+                    limiter.record(key, 1, now);
+                    return Ok(());
+                },
+            }
+        };
+
+        on_wait(wait, limit);
+        sleep(wait)?;
+    }
+}
+
+/// Asynchronous version of `acquire`, waiting on tokio's timer instead of blocking the thread.
+///
+#[cfg(feature = "async")]
+pub async fn acquire_async(limiter: &Mutex<RateLimiter>, key: &str) {
+    loop {
+        let decision = limiter.lock().expect("Poisoned Mutex").check(key, Instant::now());
+
+        match decision {
+            Decision::Proceed => return,
+            Decision::WaitFor(duration) => tokio::time::delay_for(duration).await,
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use std::collections::BTreeMap;
+use std::sync::{OnceLock, PoisonError, RwLock};
+
+use crate::types::Frequency;
+
+/// What is known of the datasets of a database without fetching their metadata, see `hints_for`.
+///
+/// ```rust
+/// use quandl_v3::registry::hints_for;
+///
+/// let wiki = hints_for("WIKI").unwrap();
+///
+/// assert_eq!(wiki.value_column, Some(11));
+/// assert_eq!(wiki.column_index("adj. close"), Some(11));
+/// ```
+///
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DatabaseHints {
+    /// Column names shared by the datasets of the database, the date included, if they all have
+    /// the same.
+    ///
+    pub column_names: Vec<String>,
+
+    /// Index of the column holding the value most users are after (e.g. the adjusted close of
+    /// stock prices), as given to `DataParameters::column_index`.
+    ///
+    pub value_column: Option<usize>,
+
+    /// Frequency of the datasets of the database, if they all have the same.
+    ///
+    pub frequency: Option<Frequency>,
+}
+
+impl DatabaseHints {
+    /// Index of the column named `name` among `column_names`, regardless of case and surrounding
+    /// whitespace.
+    ///
+    pub fn column_index(&self, name: &str) -> Option<usize> {
+        let name = name.trim().to_lowercase();
+        self.column_names.iter().position(|column| column.to_lowercase() == name)
+    }
+}
+
+/// The well-known databases, as maintained in `known_databases.toml`.
+///
+static KNOWN_DATABASES_TOML: &str = include_str!("known_databases.toml");
+
+static REGISTRY: OnceLock<RwLock<BTreeMap<String, DatabaseHints>>> = OnceLock::new();
+
+fn registry() -> &'static RwLock<BTreeMap<String, DatabaseHints>> {
+    REGISTRY.get_or_init(|| RwLock::new(parse(KNOWN_DATABASES_TOML)))
+}
+
+/// The hints known about the database `database_code`, whether embedded in the crate or
+/// registered with `register`.
+///
+/// Codes are compared regardless of case and surrounding whitespace.
+///
+pub fn hints_for(database_code: &str) -> Option<DatabaseHints> {
+    let registry = registry().read().unwrap_or_else(PoisonError::into_inner);
+    registry.get(&database_code.trim().to_uppercase()).cloned()
+}
+
+/// Make `hints` those of the database `database_code` from then on, process-wide, returning
+/// those it replaces (embedded or previously registered), if any.
+///
+pub fn register<S: AsRef<str>>(database_code: S, hints: DatabaseHints) -> Option<DatabaseHints> {
+    let mut registry = registry().write().unwrap_or_else(PoisonError::into_inner);
+    registry.insert(database_code.as_ref().trim().to_uppercase(), hints)
+}
+
+/// Parse `toml`, written in the subset of TOML described in `known_databases.toml`.
+///
+/// The file is embedded in the crate and covered by its tests, so it being malformed is a bug:
+/// this panics and tells where.
+///
+fn parse(toml: &str) -> BTreeMap<String, DatabaseHints> {
+    let sections = crate::toml_sections::sections("known_databases.toml", toml);
+
+    sections.into_iter().map(|(code, mut fields)| {
+        let column_names = {
+            match fields.remove("columns") {
+                Some(columns) => columns.split(',').map(|x| x.trim().to_string()).collect(),
+                None => vec![],
+            }
+        };
+
+        let value_column = fields.remove("value_column").map(|index| {
+            index.parse().unwrap_or_else(|_| {
+                panic!("known_databases.toml: invalid value_column '{}' for {}.", index, code)
+            })
+        });
+
+        let frequency = fields.remove("frequency").map(|token| {
+            Frequency::from_api_token(&token).unwrap_or_else(|| {
+                panic!("known_databases.toml: invalid frequency '{}' for {}.", token, code)
+            })
+        });
+
+        if let (Some(index), false) = (value_column, column_names.is_empty()) {
+            assert!(index < column_names.len(),
+                    "known_databases.toml: value_column {} beyond the columns of {}.", index, code);
+        }
+
+        if let Some(name) = fields.keys().next() {
+            panic!("known_databases.toml: unknown '{}' in the section of {}.", name, code);
+        }
+
+        (code, DatabaseHints { column_names, value_column, frequency })
+    }).collect()
+}
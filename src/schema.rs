@@ -0,0 +1,175 @@
+use crate::calendar;
+use crate::column_info::ColumnInfo;
+use crate::Result;
+
+/// Type of the values of a column, as inferred by `Schema::infer`.
+///
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColType {
+    /// Dates formatted as `YYYY-MM-DD`, like those heading the rows of every dataset.
+    ///
+    Date,
+
+    /// Numbers, i.e. anything `f64` parses (e.g. `-4`, `0.5` or `1e-3`).
+    ///
+    Number,
+
+    /// Anything else.
+    ///
+    Text,
+}
+
+/// Shape of a single column of a `Schema`.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ColumnSchema {
+    /// Name of the column, if the sample started with a line of column names naming it.
+    ///
+    pub name: Option<String>,
+
+    /// Unit of the values of the column, as found in its name by `ColumnInfo::parse`.
+    ///
+    #[serde(default)]
+    pub unit: Option<String>,
+
+    /// Type of the values of the column.
+    ///
+    pub inferred_type: ColType,
+
+    /// Whether or not the column may be missing values.
+    ///
+    pub nullable: bool,
+}
+
+/// Shape of the rows of a dataset, as inferred from a sample of them, e.g. by
+/// `DataQuery::infer_schema` for a dataset never seen before.
+///
+/// The rules are the following:
+///
+/// * The first line of the sample holds the column names when it looks like it, i.e. when
+///   neither its first field is a date nor any of its fields a number (as when decoding rows).
+///   Columns without a name there (blank, or beyond the last one) get `None`. The unit of a
+///   named column is taken from its name, if any (see `ColumnInfo::parse`).
+///
+/// * Values are trimmed. A blank value makes its column nullable, and so does a row too short to
+///   reach the column. The schema is as wide as the widest row.
+///
+/// * A column is a `Date` when all of its values are dates (see `calendar::parse_date`) and a
+///   `Number` when they all are numbers. Any other value (e.g. `N/A`), or a mix of dates and
+///   numbers (e.g. `20160301` along `2016-03-01`), makes it `Text`.
+///
+/// * A column without any value in the sample is a nullable `Number`, what the columns of
+///   Quandl's datasets after the date usually are.
+///
+/// Schemas (de)serialize with serde, e.g. to be kept along the code generated from them with
+/// `codegen::generate_schema_struct` (behind the `codegen` feature). Their `width` is the one to
+/// expect from the rows with `DataQuery::strict_width`.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Schema {
+    /// The columns, the date first.
+    ///
+    pub columns: Vec<ColumnSchema>,
+}
+
+/// What was seen of the values of a column.
+///
+#[derive(Debug, Copy, Clone, Default)]
+struct Seen {
+    dates: bool,
+    numbers: bool,
+    texts: bool,
+    blanks: bool,
+}
+
+impl Seen {
+    fn record(&mut self, value: &str) {
+        if value.is_empty() {
+            self.blanks = true;
+        } else if calendar::parse_date(value).is_some() {
+            self.dates = true;
+        } else if value.parse::<f64>().is_ok() {
+            self.numbers = true;
+        } else {
+            self.texts = true;
+        }
+    }
+
+    fn column(&self, name: Option<String>) -> ColumnSchema {
+        let inferred_type = {
+            match *self {
+                Seen { texts: true, .. } | Seen { dates: true, numbers: true, .. } => ColType::Text,
+                Seen { dates: true, .. } => ColType::Date,
+                _ => ColType::Number,
+            }
+        };
+
+        let nullable = self.blanks || !(self.dates || self.numbers || self.texts);
+
+        let unit = name.as_ref().and_then(|name| ColumnInfo::parse(name).unit);
+        ColumnSchema { name, unit, inferred_type, nullable }
+    }
+}
+
+impl Schema {
+    /// Infer the schema of the rows of a CSV payload, as laid out above.
+    ///
+    /// An empty payload has no columns at all, and one which is nothing but a line of column
+    /// names has nullable `Number` columns. Only malformed CSV is an error.
+    ///
+    pub fn infer(csv_data: &[u8]) -> Result<Schema> {
+        let mut reader = {
+            csv::ReaderBuilder::new()
+                .has_headers(false)
+                .flexible(true)
+                .from_reader(csv_data)
+        };
+
+        let mut names: Vec<Option<String>> = vec![];
+        let mut seen: Vec<Seen> = vec![];
+        let mut rows = 0;
+
+        for (index, record) in reader.records().enumerate() {
+            let record = record.map_err(|e| crate::Error::csv(index + 1, e))?;
+
+            if index == 0 && crate::query::is_header(&record) {
+                names = {
+                    record.iter()
+                        .map(|name| Some(name.trim().to_string()).filter(|name| !name.is_empty()))
+                        .collect()
+                };
+
+                continue;
+            }
+
+            if seen.len() < record.len() {
+                // The previous rows were too short to reach the new columns.
+                seen.resize(record.len(), Seen { blanks: rows > 0, ..Seen::default() });
+            }
+
+            for (column, value) in seen.iter_mut().zip(record.iter()) {
+                column.record(value.trim());
+            }
+
+            for column in seen.iter_mut().skip(record.len()) {
+                column.blanks = true;
+            }
+
+            rows += 1;
+        }
+
+        let width = names.len().max(seen.len());
+        seen.resize(width, Seen::default());
+        names.resize(width, None);
+
+        let columns = seen.iter().zip(names).map(|(seen, name)| seen.column(name)).collect();
+        Ok(Schema { columns })
+    }
+
+    /// Number of columns, the date included.
+    ///
+    pub fn width(&self) -> usize {
+        self.columns.len()
+    }
+}
@@ -0,0 +1,270 @@
+use std::collections::{BTreeSet, HashMap};
+use std::convert::TryFrom;
+
+use chrono::Datelike;
+
+use has::HasMut;
+
+use crate::calendar;
+use crate::parameters::*;
+use crate::query::DataQuery;
+
+use crate::{Result, Error};
+
+/// A data query with named placeholders, to be instantiated with different variables.
+///
+/// Placeholders are written `{name}` (ASCII letters, digits and underscores) and may appear in the
+/// dataset code (given as `"DATABASE/DATASET"`) and in the start and end dates, e.g.:
+///
+/// ```rust,no_run
+/// use std::collections::HashMap;
+/// use quandl_v3::prelude::*;
+///
+/// let mut template = QueryTemplate::new("WIKI/{code}");
+/// template.start_date_template("{start}").order(Order::asc);
+///
+/// let vars: HashMap<&str, &str> = vec![("code", "AAPL"), ("start", "2016-01-01")]
+///     .into_iter()
+///     .collect();
+///
+/// let query = template.instantiate(&vars).unwrap();
+/// ```
+///
+/// The other parameters (api key, ordering, transformation, etc.) are set as usual through the
+/// `ApiParameters` and `DataParameters` traits and copied as-is to every instantiated query.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryTemplate {
+    code: String,
+    start_date: Option<String>,
+    end_date: Option<String>,
+    data_arguments: DataArguments,
+    request_arguments: ApiArguments,
+}
+
+impl QueryTemplate {
+    /// Create a new template for the dataset code `"DATABASE/DATASET"`, which may contain
+    /// placeholders.
+    ///
+    pub fn new<S: AsRef<str>>(code: S) -> Self {
+        QueryTemplate {
+            code: code.as_ref().to_string(),
+            start_date: None,
+            end_date: None,
+            data_arguments: DataArguments::default(),
+            request_arguments: ApiArguments::from_config(),
+        }
+    }
+
+    /// Specify the earliest data point to be returned as a `YYYY-MM-DD` date which may contain
+    /// placeholders.
+    ///
+    /// This takes precedence over `DataParameters::start_date`.
+    ///
+    pub fn start_date_template<S: AsRef<str>>(&mut self, start_date: S) -> &mut Self {
+        self.start_date = Some(start_date.as_ref().to_string());
+        self
+    }
+
+    /// Specify the latest data point to be returned as a `YYYY-MM-DD` date which may contain
+    /// placeholders.
+    ///
+    /// This takes precedence over `DataParameters::end_date`.
+    ///
+    pub fn end_date_template<S: AsRef<str>>(&mut self, end_date: S) -> &mut Self {
+        self.end_date = Some(end_date.as_ref().to_string());
+        self
+    }
+
+    /// Names of the placeholders still present in this template, in alphabetical order.
+    ///
+    pub fn variables(&self) -> Result<BTreeSet<String>> {
+        let mut variables = BTreeSet::new();
+
+        for template in self.templates() {
+            variables.extend(placeholders(template)?);
+        }
+
+        Ok(variables)
+    }
+
+    /// Substitute the given variables, leaving the other placeholders in place.
+    ///
+    /// Variables which do not match any placeholder are an error, as are values containing braces.
+    ///
+    pub fn bind(&self, vars: &HashMap<&str, &str>) -> Result<QueryTemplate> {
+        let variables = self.variables()?;
+
+        let extra: Vec<&str> = {
+            let mut extra: Vec<&str> = {
+                vars.keys().cloned().filter(|name| !variables.contains(*name)).collect()
+            };
+
+            extra.sort();
+            extra
+        };
+
+        if !extra.is_empty() {
+            return Err(Error::ParsingFailed(format!("unknown template variables: {}.",
+                                                    extra.join(", "))));
+        }
+
+        for (name, value) in vars {
+            if value.contains('{') || value.contains('}') {
+                return Err(Error::ParsingFailed(format!("value '{}' of template variable {} \
+                                                         contains braces.", value, name)));
+            }
+        }
+
+        let substitute = |template: &str| substitute(template, vars);
+
+        Ok(QueryTemplate {
+            code: substitute(&self.code)?,
+            start_date: self.start_date.as_ref().map(|x| substitute(x)).transpose()?,
+            end_date: self.end_date.as_ref().map(|x| substitute(x)).transpose()?,
+            data_arguments: self.data_arguments.clone(),
+            request_arguments: self.request_arguments.clone(),
+        })
+    }
+
+    /// Create the data query obtained by substituting all the variables.
+    ///
+    /// Every placeholder must be bound and every variable must match a placeholder; the errors
+    /// list the offending names. The resulting code must be of the form `DATABASE/DATASET` and
+    /// the resulting dates must be valid `YYYY-MM-DD` dates.
+    ///
+    pub fn instantiate(&self, vars: &HashMap<&str, &str>) -> Result<DataQuery> {
+        let bound = self.bind(vars)?;
+        let missing = bound.variables()?;
+
+        if !missing.is_empty() {
+            let missing: Vec<String> = missing.into_iter().collect();
+
+            return Err(Error::ParsingFailed(format!("unbound template variables: {}.",
+                                                    missing.join(", "))));
+        }
+
+        let (database_code, dataset_code) = {
+            let mut parts = bound.code.split('/');
+
+            match (parts.next(), parts.next(), parts.next()) {
+                (Some(database_code), Some(dataset_code), None)
+                    if !database_code.is_empty() && !dataset_code.is_empty() =>
+                {
+                    (database_code, dataset_code)
+                },
+
+                _ => {
+                    return Err(Error::ParsingFailed(format!("code '{}' (from template '{}') is \
+                                                             not of the form DATABASE/DATASET.",
+                                                            bound.code,
+                                                            self.code)));
+                },
+            }
+        };
+
+        let mut query = DataQuery::new(database_code, dataset_code);
+
+        *HasMut::<DataArguments>::get_mut(&mut query) = self.data_arguments.clone();
+        *HasMut::<ApiArguments>::get_mut(&mut query) = self.request_arguments.clone();
+
+        if let (Some(date), Some(template)) = (&bound.start_date, &self.start_date) {
+            let (year, month, day) = parse_date("start date", date, template)?;
+            query.start_date(year, month, day);
+        }
+
+        if let (Some(date), Some(template)) = (&bound.end_date, &self.end_date) {
+            let (year, month, day) = parse_date("end date", date, template)?;
+            query.end_date(year, month, day);
+        }
+
+        Ok(query)
+    }
+
+    fn templates(&self) -> impl Iterator<Item = &str> {
+        Some(&self.code[..]).into_iter()
+            .chain(self.start_date.as_ref().map(|x| &x[..]))
+            .chain(self.end_date.as_ref().map(|x| &x[..]))
+    }
+}
+
+/// Split `template` into literal text and placeholder names, in order.
+///
+fn tokenize(template: &str) -> Result<Vec<(bool, &str)>> {
+    let malformed = |reason: &str| {
+        Err(Error::ParsingFailed(format!("malformed template '{}': {}.", template, reason)))
+    };
+
+    let mut tokens = vec![];
+    let mut rest = template;
+
+    while !rest.is_empty() {
+        match rest.find(['{', '}']) {
+            None => {
+                tokens.push((false, rest));
+                break;
+            },
+
+            Some(index) if rest[index..].starts_with('}') => return malformed("unmatched '}'"),
+
+            Some(index) => {
+                if index > 0 {
+                    tokens.push((false, &rest[..index]));
+                }
+
+                let end = match rest[index..].find('}') {
+                    Some(end) => index + end,
+                    None => return malformed("unmatched '{'"),
+                };
+
+                let name = &rest[index + 1..end];
+
+                if name.is_empty() || !name.chars().all(|x| x.is_ascii_alphanumeric() || x == '_') {
+                    return malformed(&format!("invalid placeholder name '{}'", name));
+                }
+
+                tokens.push((true, name));
+                rest = &rest[end + 1..];
+            },
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn placeholders(template: &str) -> Result<Vec<String>> {
+    Ok(tokenize(template)?.into_iter()
+        .filter(|(is_placeholder, _)| *is_placeholder)
+        .map(|(_, name)| name.to_string())
+        .collect())
+}
+
+fn substitute(template: &str, vars: &HashMap<&str, &str>) -> Result<String> {
+    Ok(tokenize(template)?.into_iter().fold(String::new(), |mut s, (is_placeholder, text)| {
+        match vars.get(text) {
+            Some(value) if is_placeholder => s.push_str(value),
+            _ if is_placeholder => s.push_str(&format!("{{{}}}", text)),
+            _ => s.push_str(text),
+        }
+
+        s
+    }))
+}
+
+fn parse_date(what: &str, date: &str, template: &str) -> Result<(u16, u8, u8)> {
+    let invalid = || {
+        Error::ParsingFailed(format!("{} '{}' (from template '{}') is not a valid YYYY-MM-DD \
+                                      date.", what, date, template))
+    };
+
+    let date = calendar::parse_date(date).ok_or_else(invalid)?;
+    let year = u16::try_from(date.year()).map_err(|_| invalid())?;
+
+    Ok((year, date.month() as u8, date.day() as u8))
+}
+
+impl ApiParameters for QueryTemplate {}
+impl DataParameters for QueryTemplate {}
+
+impl_has!(QueryTemplate, DataArguments, data_arguments);
+impl_has!(QueryTemplate, ApiArguments, request_arguments);
@@ -0,0 +1,40 @@
+use std::collections::BTreeMap;
+
+/// The sections of `toml`, an embedded file named `file` written in the subset of TOML used by
+/// the crate's registries (see e.g. `aliases.toml`): `[name]` headers followed by `key = "value"`
+/// lines, values being double-quoted strings without escapes. Blank lines and comments are
+/// skipped.
+///
+/// The files are embedded in the crate and covered by its tests, so one being malformed is a bug:
+/// this panics and tells where.
+///
+pub fn sections(file: &str, toml: &str) -> Vec<(String, BTreeMap<String, String>)> {
+    let mut sections: Vec<(String, BTreeMap<String, String>)> = vec![];
+
+    for (index, line) in toml.lines().enumerate() {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let malformed = format!("{}, line {}: malformed '{}'.", file, index + 1, line);
+
+        if line.starts_with('[') && line.ends_with(']') {
+            sections.push((line[1..line.len() - 1].trim().to_string(), BTreeMap::new()));
+            continue;
+        }
+
+        let (key, value) = line.split_once('=').expect(&malformed);
+
+        let value = {
+            value.trim().strip_prefix('"').and_then(|value| value.strip_suffix('"'))
+                .expect(&malformed)
+        };
+
+        let fields = &mut sections.last_mut().expect(&malformed).1;
+        fields.insert(key.trim().to_string(), value.to_string());
+    }
+
+    sections
+}
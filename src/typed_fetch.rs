@@ -0,0 +1,142 @@
+use has::HasMut;
+
+use crate::Result;
+use crate::api_call::ApiCall;
+use crate::batch_query::BatchQuery;
+use crate::parameters::*;
+use crate::query::{DataQuery, DatasetMetadataQuery};
+use crate::types::{Code, DataRow, Dataset, DatasetMetadata};
+
+/// Fetch the metadata and data of every dataset of `codes`, each row decoded as a `DataRow` named
+/// by the metadata, see `TypedFetch` for the details. `key` is the API key to use and `threads`
+/// the number of threads of each batch.
+///
+/// ```rust,no_run
+/// use quandl_v3::prelude::*;
+///
+/// let codes: Vec<Code> = {
+///     ["AAPL", "MSFT", "GOOG"].iter()
+///         .map(|code| Code {
+///             database_code: "WIKI".to_string(),
+///             dataset_code: code.to_string(),
+///             name: String::new(),
+///         })
+///         .collect()
+/// };
+///
+/// for (code, dataset) in fetch_typed(&codes, "KEY", 4) {
+///     match dataset {
+///         Ok(dataset) => println!("{}: {:?}", code.dataset_code, dataset.column("Close")),
+///         Err(e) => println!("{}: {}", code.dataset_code, e),
+///     }
+/// }
+/// ```
+///
+pub fn fetch_typed<S: AsRef<str>>(codes: &[Code], key: S, threads: usize)
+    -> Vec<(Code, Result<Dataset<DataRow>>)>
+{
+    TypedFetch::new(key).threads(threads).fetch(codes)
+}
+
+/// Fetch the metadata and data of many datasets at once, in two batch queries.
+///
+/// The first batch fetches the metadata of every dataset, from which the second one knows the
+/// width and the column names of the rows: each dataset is combined from both. Fetching is best
+/// effort: a dataset whose metadata cannot be fetched is reported with that error and its data is
+/// not fetched, while the others carry on.
+///
+/// Both batches are rate limited with Quandl's limits for free API keys, the calls of the first
+/// batch counting towards those of the second. An API key is required since batch queries skip
+/// queries without one; the other `ApiParameters` are given to every query as well.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypedFetch {
+    threads: usize,
+    request_arguments: ApiArguments,
+}
+
+/// Quandl's limits for free API keys, as `(calls, seconds)`, see `BatchQuery::limit`.
+///
+pub const LIMITS: [(usize, u64); 3] = [(300, 10), (2_000, 600), (50_000, 86_400)];
+
+impl TypedFetch {
+    /// Create a new fetch with the API key `api_key`, using as many threads as there are logical
+    /// cores.
+    ///
+    pub fn new<S: AsRef<str>>(api_key: S) -> Self {
+        let mut fetch = TypedFetch {
+            threads: ::num_cpus::get(),
+            request_arguments: ApiArguments::from_config(),
+        };
+
+        fetch.api_key(api_key);
+        fetch
+    }
+
+    /// Specify the maximum number of threads of each batch, which must be bigger than 0.
+    ///
+    pub fn threads(&mut self, threads: usize) -> &mut Self {
+        assert!(threads > 0, "threads: {}", threads);
+        self.threads = threads;
+        self
+    }
+
+    /// Fetch every dataset of `codes`, returning them along with their code in the same order.
+    ///
+    pub fn fetch(&self, codes: &[Code]) -> Vec<(Code, Result<Dataset<DataRow>>)> {
+        let metadata: Vec<Result<DatasetMetadata>> = {
+            let mut batch_query = self.batch(0);
+
+            for code in codes {
+                let mut query = DatasetMetadataQuery::new(&code.database_code, &code.dataset_code);
+                *HasMut::<ApiArguments>::get_mut(&mut query) = self.request_arguments.clone();
+                batch_query.query(query);
+            }
+
+            batch_query.run().collect()
+        };
+
+        let mut data = {
+            let mut batch_query = self.batch(codes.len());
+
+            for metadata in metadata.iter().flatten() {
+                let mut query = DataQuery::new(&metadata.database_code, &metadata.dataset_code);
+                *HasMut::<ApiArguments>::get_mut(&mut query) = self.request_arguments.clone();
+                query.expected_width_from(metadata);
+                batch_query.query(query);
+            }
+
+            batch_query.run()
+        };
+
+        codes.iter().cloned().zip(metadata).map(|(code, metadata)| {
+            let dataset = metadata.and_then(|metadata| {
+                let rows = data.next().expect("one data query by metadata fetched")?;
+                Ok(Dataset { metadata, rows })
+            });
+
+            (code, dataset)
+        }).collect()
+    }
+
+    /// Batch query with this fetch's threads and rate limits, `offset` calls having been made
+    /// already.
+    ///
+    fn batch<A, T>(&self, offset: usize) -> BatchQuery<A, T>
+        where T: serde::de::DeserializeOwned + Clone + Send + 'static,
+              A: ApiCall<T> + Clone + Send + 'static,
+    {
+        let mut batch_query = BatchQuery::new();
+        batch_query.threads(self.threads).offset(offset);
+
+        for &(limit, seconds) in &LIMITS {
+            batch_query.limit(limit, seconds);
+        }
+
+        batch_query
+    }
+}
+
+impl ApiParameters for TypedFetch {}
+
+impl_has!(TypedFetch, ApiArguments, request_arguments);
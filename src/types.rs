@@ -1,8 +1,14 @@
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+
+use crate::calendar;
+use crate::column_info::ColumnInfo;
+use crate::{Result, Error};
+
 /// Parameters to indicate the desired frequency. When you change the frequency of a dataset,
 /// Quandl returns the last observation for the given period.
 ///
 #[allow(non_camel_case_types)]
-#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Frequency {
     /// Unspecified frequency. In a data query, will default to the frequency of the dataset.
     ///
@@ -29,10 +35,39 @@ pub enum Frequency {
     annual
 }
 
+impl Frequency {
+    /// The value of this frequency in Quandl's API, e.g. as the `collapse` parameter of a query.
+    ///
+    pub fn as_api_token(&self) -> &'static str {
+        match *self {
+            Frequency::none      => "none",
+            Frequency::daily     => "daily",
+            Frequency::weekly    => "weekly",
+            Frequency::monthly   => "monthly",
+            Frequency::quarterly => "quarterly",
+            Frequency::annual    => "annual",
+        }
+    }
+
+    /// The frequency whose value in Quandl's API is `token`, see `as_api_token`.
+    ///
+    pub fn from_api_token(token: &str) -> Option<Self> {
+        match token {
+            "none"      => Some(Frequency::none),
+            "daily"     => Some(Frequency::daily),
+            "weekly"    => Some(Frequency::weekly),
+            "monthly"   => Some(Frequency::monthly),
+            "quarterly" => Some(Frequency::quarterly),
+            "annual"    => Some(Frequency::annual),
+            _           => None,
+        }
+    }
+}
+
 /// Select the sort order with this enum. The default sort order is descending.
 ///
 #[allow(non_camel_case_types)]
-#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Order {
     /// Ascending ordering, for time series this means the first entry is the earliest date.
     ///
@@ -43,10 +78,31 @@ pub enum Order {
     desc,
 }
 
+impl Order {
+    /// The value of this order in Quandl's API, i.e. as the `order` parameter of a query.
+    ///
+    pub fn as_api_token(&self) -> &'static str {
+        match *self {
+            Order::asc  => "asc",
+            Order::desc => "desc",
+        }
+    }
+
+    /// The order whose value in Quandl's API is `token`, see `as_api_token`.
+    ///
+    pub fn from_api_token(token: &str) -> Option<Self> {
+        match token {
+            "asc"  => Some(Order::asc),
+            "desc" => Some(Order::desc),
+            _      => None,
+        }
+    }
+}
+
 /// Perform calculations on your data prior to downloading.
 ///
 #[allow(non_camel_case_types)]
-#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Transform {
     /// No transformation, also the default.
     ///
@@ -80,8 +136,113 @@ pub enum Transform {
     normalize,
 }
 
+impl Transform {
+    /// The value of this transformation in Quandl's API, i.e. as the `transform` parameter of a
+    /// query.
+    ///
+    pub fn as_api_token(&self) -> &'static str {
+        match *self {
+            Transform::none       => "none",
+            Transform::diff       => "diff",
+            Transform::rdiff      => "rdiff",
+            Transform::rdiff_from => "rdiff_from",
+            Transform::cumul      => "cumul",
+            Transform::normalize  => "normalize",
+        }
+    }
+
+    /// The transformation whose value in Quandl's API is `token`, see `as_api_token`.
+    ///
+    pub fn from_api_token(token: &str) -> Option<Self> {
+        match token {
+            "none"       => Some(Transform::none),
+            "diff"       => Some(Transform::diff),
+            "rdiff"      => Some(Transform::rdiff),
+            "rdiff_from" => Some(Transform::rdiff_from),
+            "cumul"      => Some(Transform::cumul),
+            "normalize"  => Some(Transform::normalize),
+            _            => None,
+        }
+    }
+}
+
+/// Comparison of a column's values to a bound, filtering the rows of a `DatatableQuery`.
+///
+#[allow(non_camel_case_types)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Comparison {
+    /// Values strictly greater than the bound.
+    ///
+    gt,
+
+    /// Values greater than or equal to the bound.
+    ///
+    gte,
+
+    /// Values strictly less than the bound.
+    ///
+    lt,
+
+    /// Values less than or equal to the bound.
+    ///
+    lte,
+}
+
+impl Comparison {
+    /// The value of this comparison in Quandl's API, i.e. the suffix of the column filtered (as in
+    /// `date.gte`).
+    ///
+    pub fn as_api_token(&self) -> &'static str {
+        match *self {
+            Comparison::gt  => "gt",
+            Comparison::gte => "gte",
+            Comparison::lt  => "lt",
+            Comparison::lte => "lte",
+        }
+    }
+
+    /// The comparison whose value in Quandl's API is `token`, see `as_api_token`.
+    ///
+    pub fn from_api_token(token: &str) -> Option<Self> {
+        match token {
+            "gt"  => Some(Comparison::gt),
+            "gte" => Some(Comparison::gte),
+            "lt"  => Some(Comparison::lt),
+            "lte" => Some(Comparison::lte),
+            _     => None,
+        }
+    }
+}
+
+/// How much a query weighs on the API key it is sent with, see `ApiCall::kind`.
+///
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum QueryKind {
+    /// Queries downloading the rows of a dataset.
+    ///
+    Heavy,
+
+    /// Queries of metadata, searches and listings.
+    ///
+    Light,
+}
+
+impl QueryKind {
+    /// Cost hint of a query of this kind, in calls: 10 for a heavy query, 1 for a light one.
+    ///
+    pub fn cost(&self) -> f64 {
+        match *self {
+            QueryKind::Heavy => 10.0,
+            QueryKind::Light => 1.0,
+        }
+    }
+}
+
 /// Hold the metadata associated to a specific database.
 ///
+/// Its counters are read leniently, since Quandl does not always return them as numbers: a
+/// numeric string (e.g. `"9999"`) is read as the number, and null as 0.
+///
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct DatabaseMetadata {
     /// Quandl's numerical identifier for this database.
@@ -102,10 +263,12 @@ pub struct DatabaseMetadata {
 
     /// Number of datasets in the database.
     ///
+    #[serde(deserialize_with = "lenient_count::deserialize")]
     pub datasets_count: usize,
 
     /// Number of time the database's content was downloaded.
     ///
+    #[serde(deserialize_with = "lenient_count::deserialize")]
     pub downloads: usize,
 
     /// Whether or not this is a premium database.
@@ -115,10 +278,38 @@ pub struct DatabaseMetadata {
     /// URL pointing to the logo of the database.
     ///
     pub image: String,
+
+    /// Last time the database's content was updated, if reported by Quandl.
+    ///
+    #[serde(default)]
+    pub last_refreshed_at: Option<String>,
+}
+
+impl DatabaseMetadata {
+    /// Parse the `last_refreshed_at` field, if present, as a UTC timestamp.
+    ///
+    pub fn last_refresh(&self) -> Result<Option<NaiveDateTime>> {
+        match self.last_refreshed_at {
+            Some(ref value) => {
+                match calendar::parse_timestamp(value) {
+                    Some(timestamp) => Ok(Some(timestamp)),
+                    None => Err(Error::ParsingFailed(format!("invalid last_refreshed_at '{}' in \
+                                                              metadata of {}.",
+                                                             value,
+                                                             self.database_code))),
+                }
+            },
+
+            None => Ok(None),
+        }
+    }
 }
 
 /// Hold the metadata associated to a specific dataset.
 ///
+/// A `DataQuery` fetching everything this dataset has to offer can be created from it with
+/// `DataQuery::try_from(&metadata)`.
+///
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct DatasetMetadata {
     /// Quandl's numerical identifier for this dataset.
@@ -170,46 +361,111 @@ pub struct DatasetMetadata {
     pub database_id: usize,
 }
 
+impl DatasetMetadata {
+    /// Parse the `oldest_available_date` and `newest_available_date` fields, in that order.
+    ///
+    pub fn date_range(&self) -> Result<(NaiveDate, NaiveDate)> {
+        let parse = |field: &str, value: &str| {
+            match calendar::parse_date(value) {
+                Some(date) => Ok(date),
+                None => Err(Error::ParsingFailed(format!("invalid {} '{}' in metadata of {}/{}.",
+                                                         field,
+                                                         value,
+                                                         self.database_code,
+                                                         self.dataset_code))),
+            }
+        };
+
+        Ok((parse("oldest_available_date", &self.oldest_available_date)?,
+            parse("newest_available_date", &self.newest_available_date)?))
+    }
+
+    /// The `column_names` field, each name split into its base name and its unit (see
+    /// `ColumnInfo::parse`), the date included.
+    ///
+    pub fn columns(&self) -> Vec<ColumnInfo> {
+        self.column_names.iter().map(|name| ColumnInfo::parse(name)).collect()
+    }
+
+    /// The `refreshed_at` field in UTC, if it is a timestamp (see `calendar::parse_timestamp_utc`
+    /// for the formats accepted).
+    ///
+    /// Unlike the naive timestamp of `last_refresh`, this compares correctly against local times
+    /// whatever their time zone and daylight saving time.
+    ///
+    pub fn refreshed_at_utc(&self) -> Option<DateTime<Utc>> {
+        calendar::parse_timestamp_utc(&self.refreshed_at)
+    }
+
+    /// Parse the `refreshed_at` field as a UTC timestamp.
+    ///
+    pub fn last_refresh(&self) -> Result<NaiveDateTime> {
+        match calendar::parse_timestamp(&self.refreshed_at) {
+            Some(timestamp) => Ok(timestamp),
+            None => Err(Error::ParsingFailed(format!("invalid refreshed_at '{}' in metadata of \
+                                                      {}/{}.",
+                                                     self.refreshed_at,
+                                                     self.database_code,
+                                                     self.dataset_code))),
+        }
+    }
+}
+
 /// Some queries, namely those which list datasets or databases metadata, often return some
 /// metadata about the search itself. This is a structure to hold that metadata.
 ///
+/// Like those of `DatabaseMetadata`, its counters are read leniently: numeric strings are read as
+/// the number, and null as 0 (or `None`, for the optional ones).
+///
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SearchMetadata {
     /// A string of the search keywords submitted formatted as `format!("{}+{}+...+{}", keyword_1,
     /// keyword_2, ..., keyword_n)`.
     ///
+    /// Empty for plain listings (i.e. searches without keywords), whose `query` Quandl leaves
+    /// out or sets to null.
+    ///
+    #[serde(default, deserialize_with = "null_as_empty::deserialize")]
     pub query: String,
 
     /// The number of search result per page.
     ///
+    #[serde(deserialize_with = "lenient_count::deserialize")]
     pub per_page: usize,
 
     /// The current page of result that was returned by this query.
     ///
+    #[serde(deserialize_with = "lenient_count::deserialize")]
     pub current_page: usize,
 
     /// The number of the previous page, unless there is no previous page.
     ///
+    #[serde(default, deserialize_with = "lenient_count::deserialize_optional")]
     pub prev_page: Option<usize>,
 
     /// The total number of pages that can be queried.
     ///
+    #[serde(deserialize_with = "lenient_count::deserialize")]
     pub total_pages: usize,
 
     /// The total number of search result returned.
     ///
+    #[serde(deserialize_with = "lenient_count::deserialize")]
     pub total_count: usize,
 
     /// The number of the next page, unless there is no next page.
     ///
+    #[serde(default, deserialize_with = "lenient_count::deserialize_optional")]
     pub next_page: Option<usize>,
 
     /// Index of the first result on the current page, with respect to the total number of results.
     ///
+    #[serde(default, deserialize_with = "lenient_count::deserialize_optional")]
     pub current_first_item: Option<usize>,
 
     /// Index of the last result on the current page, with respect to the total number of results.
     ///
+    #[serde(default, deserialize_with = "lenient_count::deserialize_optional")]
     pub current_last_item: Option<usize>,
 }
 
@@ -239,6 +495,80 @@ pub struct DatasetList {
     pub meta: SearchMetadata,
 }
 
+impl DatabaseList {
+    /// Position after this page of results, to resume the search from (see
+    /// `DatabaseSearch::resume`).
+    ///
+    pub fn cursor(&self) -> PageCursor {
+        PageCursor::after(&self.meta, self.databases.last().map(|x| &x.database_code[..]))
+    }
+}
+
+impl DatasetList {
+    /// Position after this page of results, to resume the search from (see
+    /// `DatasetSearch::resume`).
+    ///
+    pub fn cursor(&self) -> PageCursor {
+        PageCursor::after(&self.meta, self.datasets.last().map(|x| &x.dataset_code[..]))
+    }
+}
+
+/// Position in a paginated listing, after the last page fetched, to resume it from later on.
+///
+/// Results may shift from a page to another while a listing is gone through (e.g. when datasets
+/// are added), so the cursor also holds what is needed to detect it: the code of the last result
+/// seen and, for searches, their total number at the time. A cursor (de)serializes with serde,
+/// so that a long pagination can resume after a restart.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PageCursor {
+    /// The last page fetched.
+    ///
+    pub page: usize,
+
+    /// The number of results per page.
+    ///
+    pub per_page: usize,
+
+    /// The total number of results when the page was fetched, if known.
+    ///
+    pub total_count: Option<usize>,
+
+    /// The code of the last result of the page, unless it was empty.
+    ///
+    pub last_code: Option<String>,
+}
+
+impl PageCursor {
+    fn after(meta: &SearchMetadata, last_code: Option<&str>) -> Self {
+        PageCursor {
+            page: meta.current_page,
+            per_page: meta.per_page,
+            total_count: Some(meta.total_count),
+            last_code: last_code.map(|x| x.to_string()),
+        }
+    }
+
+    /// The page to fetch next.
+    ///
+    pub fn next_page(&self) -> usize {
+        self.page + 1
+    }
+
+    /// Number of results at the start of the next page, listed by their codes, which were already
+    /// seen: those up to the last one of the cursor's page, if found.
+    ///
+    pub fn repeated<'a, I: IntoIterator<Item = &'a str>>(&self, codes: I) -> usize {
+        match self.last_code {
+            Some(ref last_code) => {
+                codes.into_iter().position(|code| code == last_code).map(|x| x + 1).unwrap_or(0)
+            },
+
+            None => 0,
+        }
+    }
+}
+
 /// Data structure to hold the result of a code list query.
 ///
 /// It should be noted that I slightly changed the meaning of a "dataset list" in this crate for
@@ -260,3 +590,674 @@ pub struct Code {
     ///
     pub name: String,
 }
+
+/// Summary of a dataset, as listed by a `DatasetListingQuery`.
+///
+/// This holds more than a `Code` (a description and the dates covered, already parsed) but less
+/// than a full `DatasetMetadata`. It serializes with the dates formatted the way Quandl does.
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DatasetMetadataLite {
+    /// The dataset code, without the database code.
+    ///
+    pub code: String,
+
+    /// The title of this dataset.
+    ///
+    pub name: String,
+
+    /// An explanation of the contents of the data in this dataset, possibly empty.
+    ///
+    pub description: String,
+
+    /// The last time the data in this dataset and metadata of this dataset was refreshed (UTC).
+    ///
+    #[serde(with = "timestamp")]
+    pub refreshed_at: NaiveDateTime,
+
+    /// The earliest date of all available data points in this dataset, if it has any.
+    ///
+    #[serde(with = "optional_date")]
+    pub oldest: Option<NaiveDate>,
+
+    /// The most recent date of all available data points in this dataset, if it has any.
+    ///
+    #[serde(with = "optional_date")]
+    pub newest: Option<NaiveDate>,
+}
+
+/// A dataset's rows along with its metadata, as returned by a `DataAndMetadataQuery`.
+///
+/// Each row is decoded into `T` from a JSON array (date first, then one element per column), so
+/// tuples and structs deriving `Deserialize` can both be used. The whole object serializes to (and
+/// deserializes from) a single JSON document, e.g. to persist it:
+///
+/// ```rust,no_run
+/// use quandl_v3::prelude::*;
+///
+/// let query = DataAndMetadataQuery::new("WIKI", "AAPL");
+/// let dataset: Dataset<(String, f64)> = query.send().unwrap();
+///
+/// println!("{} ({} rows)", dataset.metadata.name, dataset.len());
+///
+/// for (date, open) in &dataset {
+///     println!("{}: {}", date, open);
+/// }
+///
+/// // Keep only the opening prices, the metadata is preserved.
+/// let opens: Dataset<f64> = dataset.map_rows(|(_, open)| open);
+/// ```
+///
+/// When a column is selected with `DataParameters::column_index`, `metadata.column_names` is
+/// pruned to the date column and the selected one, so the names always line up with the values
+/// of the rows.
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Dataset<T> {
+    /// Metadata of the dataset.
+    ///
+    pub metadata: DatasetMetadata,
+
+    /// Rows of data, in the order returned by Quandl.
+    ///
+    pub rows: Vec<T>,
+}
+
+impl<T> Dataset<T> {
+    /// Quandl's numerical identifier of the dataset, whichever way it was queried.
+    ///
+    pub fn id(&self) -> usize {
+        self.metadata.id
+    }
+
+    /// Database and dataset codes of the dataset, in that order, whichever way it was queried.
+    ///
+    pub fn codes(&self) -> (&str, &str) {
+        (&self.metadata.database_code, &self.metadata.dataset_code)
+    }
+
+    /// Names of the dataset's columns, the date included.
+    ///
+    /// Only the date column and the selected one are listed when the query used `column_index`.
+    ///
+    pub fn column_names(&self) -> &[String] {
+        &self.metadata.column_names
+    }
+
+    /// Frequency of the dataset's data points.
+    ///
+    pub fn frequency(&self) -> Frequency {
+        self.metadata.frequency
+    }
+
+    /// Oldest and newest dates available for the dataset, see `DatasetMetadata::date_range`.
+    ///
+    /// Note that these are the dates available on Quandl, not necessarily those of the rows
+    /// returned if the query was restricted.
+    ///
+    pub fn date_range(&self) -> Result<(NaiveDate, NaiveDate)> {
+        self.metadata.date_range()
+    }
+
+    /// Number of rows.
+    ///
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Whether or not there is no row.
+    ///
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// Iterate over the rows.
+    ///
+    pub fn iter(&self) -> ::std::slice::Iter<'_, T> {
+        self.rows.iter()
+    }
+
+    /// Transform every row, keeping the metadata.
+    ///
+    pub fn map_rows<U, F: FnMut(T) -> U>(self, f: F) -> Dataset<U> {
+        Dataset {
+            metadata: self.metadata,
+            rows: self.rows.into_iter().map(f).collect(),
+        }
+    }
+}
+
+impl<T: serde::Serialize> Dataset<T> {
+    /// Aligned table of the first and last `n` rows, for a quick look at the data (e.g. when
+    /// exploring a new dataset).
+    ///
+    /// Columns are named after the dataset's column names when the rows have as many values,
+    /// after the fields of the rows otherwise (for structs), then after the column names known of
+    /// its database (see `registry::hints_for`, behind the `registry` feature), and numbered as a
+    /// last resort. Rows
+    /// left out are marked with a row of ellipses, numeric columns are right-aligned and cells
+    /// wider than 20 characters are truncated:
+    ///
+    /// ```text
+    /// Date         Open  Close
+    /// 2016-02-10  95.92  94.99
+    /// ...         ...    ...
+    /// 2016-02-08  93.13  95.01
+    /// [3 rows x 3 columns]
+    /// ```
+    ///
+    pub fn preview(&self, n: usize) -> String {
+        let shown: Vec<&T> = {
+            if self.rows.len() > 2 * n {
+                self.rows[..n].iter().chain(&self.rows[self.rows.len() - n..]).collect()
+            } else {
+                self.rows.iter().collect()
+            }
+        };
+
+        let mut fields = None;
+        let mut table: Vec<Vec<String>> = vec![];
+
+        for row in shown {
+            let (names, cells) = preview_cells(row);
+            fields = fields.or(names);
+            table.push(cells);
+        }
+
+        let width = table.iter().map(Vec::len).max().unwrap_or(self.metadata.column_names.len());
+
+        let header: Vec<String> = {
+            if self.metadata.column_names.len() == width {
+                self.metadata.column_names.clone()
+            } else {
+                match fields {
+                    Some(ref fields) if fields.len() == width => fields.clone(),
+
+                    _ => {
+                        known_column_names(&self.metadata.database_code, width).unwrap_or_else(|| {
+                            (1..=width).map(|i| format!("column {}", i)).collect()
+                        })
+                    },
+                }
+            }
+        };
+
+        let numeric: Vec<bool> = {
+            (0..width).map(|column| {
+                let values: Vec<&String> = {
+                    table.iter().filter_map(|row| row.get(column)).filter(|x| !x.is_empty())
+                        .collect()
+                };
+
+                !values.is_empty() && values.iter().all(|x| x.parse::<f64>().is_ok())
+            }).collect()
+        };
+
+        if self.rows.len() > 2 * n {
+            table.insert(n, vec!["...".to_string(); width]);
+        }
+
+        table.insert(0, header);
+
+        let table: Vec<Vec<String>> = {
+            table.into_iter()
+                .map(|row| row.into_iter().map(truncated).collect())
+                .collect()
+        };
+
+        let widths: Vec<usize> = {
+            (0..width).map(|column| {
+                table.iter().filter_map(|row| row.get(column)).map(|x| x.chars().count()).max()
+                    .unwrap_or(0)
+            }).collect()
+        };
+
+        let mut preview = String::new();
+
+        for row in &table {
+            let line: Vec<String> = {
+                row.iter().enumerate().map(|(column, cell)| {
+                    if numeric[column] {
+                        format!("{:>1$}", cell, widths[column])
+                    } else {
+                        format!("{:<1$}", cell, widths[column])
+                    }
+                }).collect()
+            };
+
+            preview.push_str(line.join("  ").trim_end());
+            preview.push('\n');
+        }
+
+        preview.push_str(&format!("[{} rows x {} columns]", self.rows.len(), width));
+        preview
+    }
+}
+
+/// The column names shared by the datasets of `database_code`, if known to the registry and
+/// `width` of them.
+///
+#[cfg(feature = "registry")]
+fn known_column_names(database_code: &str, width: usize) -> Option<Vec<String>> {
+    crate::registry::hints_for(database_code)
+        .map(|hints| hints.column_names)
+        .filter(|names| names.len() == width)
+}
+
+#[cfg(not(feature = "registry"))]
+fn known_column_names(_: &str, _: usize) -> Option<Vec<String>> {
+    None
+}
+
+/// Widest cell shown by `Dataset::preview`, in characters.
+///
+const PREVIEW_CELL_WIDTH: usize = 20;
+
+/// Values of `row` as written to a CSV record, along with the names of its fields if it has any.
+///
+fn preview_cells<T: serde::Serialize>(row: &T) -> (Option<Vec<String>>, Vec<String>) {
+    let mut writer = csv::WriterBuilder::new().flexible(true).from_writer(vec![]);
+
+    let encoded = {
+        match writer.serialize(row).ok().and_then(|_| writer.into_inner().ok()) {
+            Some(encoded) => encoded,
+            None => return (None, vec!["<unprintable row>".to_string()]),
+        }
+    };
+
+    let mut records: Vec<Vec<String>> = {
+        csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(&encoded[..])
+            .records()
+            .filter_map(|record| record.ok())
+            .map(|record| record.iter().map(|x| x.to_string()).collect())
+            .collect()
+    };
+
+    // Structs are written after a header naming their fields.
+    match records.len() {
+        2 => {
+            let cells = records.remove(1);
+            (Some(records.remove(0)), cells)
+        },
+
+        _ => (None, records.pop().unwrap_or_default()),
+    }
+}
+
+/// `cell`, cut to at most `PREVIEW_CELL_WIDTH` characters with an ellipsis if it is longer.
+///
+fn truncated(cell: String) -> String {
+    if cell.chars().count() > PREVIEW_CELL_WIDTH {
+        cell.chars().take(PREVIEW_CELL_WIDTH - 1).chain(Some('…')).collect()
+    } else {
+        cell
+    }
+}
+
+impl Dataset<DataRow> {
+    /// Values of the column named `name` (as listed in `column_names`, the date excluded), one per
+    /// row, or `None` if the dataset has no such column.
+    ///
+    pub fn column(&self, name: &str) -> Option<Vec<Option<f64>>> {
+        let index = self.metadata.column_names.iter().skip(1).position(|x| x == name)?;
+        Some(self.rows.iter().map(|row| row.values.get(index).cloned().flatten()).collect())
+    }
+}
+
+/// A row of any dataset with numeric columns, decoded without knowing them beforehand: its date,
+/// then the value of each column (`None` where Quandl has no value).
+///
+/// The columns are named by the metadata of the dataset, see `Dataset::column`. A row
+/// (de)serializes as a sequence, the date first, which is how Quandl sends the rows in both CSV
+/// and JSON.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataRow {
+    /// Date of the row, formatted as `YYYY-MM-DD`.
+    ///
+    pub date: String,
+
+    /// Value of each column of the row, in order.
+    ///
+    pub values: Vec<Option<f64>>,
+}
+
+impl serde::Serialize for DataRow {
+    fn serialize<S: serde::Serializer>(&self, serializer: S)
+        -> ::std::result::Result<S::Ok, S::Error>
+    {
+        use serde::ser::SerializeSeq;
+
+        let mut seq = serializer.serialize_seq(Some(self.values.len() + 1))?;
+        seq.serialize_element(&self.date)?;
+
+        for value in &self.values {
+            seq.serialize_element(value)?;
+        }
+
+        seq.end()
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for DataRow {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D)
+        -> ::std::result::Result<Self, D::Error>
+    {
+        struct Visitor;
+
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = DataRow;
+
+            fn expecting(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+                write!(f, "a date followed by numeric values")
+            }
+
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(self, mut seq: A)
+                -> ::std::result::Result<DataRow, A::Error>
+            {
+                let date = {
+                    seq.next_element()?
+                        .ok_or_else(|| serde::de::Error::invalid_length(0, &self))?
+                };
+
+                let mut values = vec![];
+
+                while let Some(value) = seq.next_element()? {
+                    values.push(value);
+                }
+
+                Ok(DataRow { date, values })
+            }
+        }
+
+        deserializer.deserialize_seq(Visitor)
+    }
+}
+
+/// Number of rows of a data query, see `DataQuery::count`.
+///
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RowCount {
+    /// Number of rows, exact or estimated.
+    ///
+    pub rows: u64,
+
+    /// Whether or not `rows` is the actual number of rows rather than an estimate.
+    ///
+    pub exact: bool,
+}
+
+impl RowCount {
+    /// Estimate the number of rows from the first to the last one, dated `first` and `last`, of
+    /// data at the given frequency: one row per period of the frequency, both included.
+    ///
+    /// Daily data is expected on business days only, unless one of `first` and `last` falls on a
+    /// weekend. `Frequency::none` is counted as daily. A single row (`first == last`) is exact,
+    /// and so is `last` being before `first`, which gives no rows.
+    ///
+    pub fn estimate(frequency: Frequency, first: NaiveDate, last: NaiveDate) -> RowCount {
+        use chrono::Datelike;
+
+        if last <= first {
+            return RowCount { rows: (last == first) as u64, exact: true };
+        }
+
+        // Periods of `months_per_period` months from `first` to `last`, both included.
+        let months = |months_per_period: i32| {
+            let index = |date: NaiveDate| {
+                (date.year() * 12 + date.month0() as i32) / months_per_period
+            };
+
+            (index(last) - index(first)) as u64 + 1
+        };
+
+        let rows = {
+            match frequency {
+                Frequency::none | Frequency::daily => {
+                    if calendar::is_weekend(first) || calendar::is_weekend(last) {
+                        (last - first).num_days() as u64 + 1
+                    } else {
+                        calendar::business_days_between(first, last, &calendar::WeekendsOnly) as u64
+                    }
+                },
+
+                Frequency::weekly => {
+                    let weeks = calendar::iso_week_start(last) - calendar::iso_week_start(first);
+                    weeks.num_days() as u64 / 7 + 1
+                },
+
+                Frequency::monthly => months(1),
+                Frequency::quarterly => months(3),
+                Frequency::annual => months(12),
+            }
+        };
+
+        RowCount { rows, exact: false }
+    }
+}
+
+impl<T> IntoIterator for Dataset<T> {
+    type Item = T;
+    type IntoIter = ::std::vec::IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.rows.into_iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Dataset<T> {
+    type Item = &'a T;
+    type IntoIter = ::std::slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.rows.iter()
+    }
+}
+
+/// Rows of a datatable along with its columns, as returned by a `DatatableQuery`.
+///
+/// Quandl sends each row as an array of values, in the order of `columns`: rows are decoded
+/// into tuples, or `Vec<serde_json::Value>` to keep them as received.
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Datatable<T> {
+    /// Columns of the rows, only those selected if the query selected any.
+    ///
+    pub columns: Vec<DatatableColumn>,
+
+    /// Rows of the page received, in the order returned by Quandl.
+    ///
+    pub rows: Vec<T>,
+
+    /// Cursor of the next page of rows, if any, see `DatatableQuery::cursor_id`.
+    ///
+    pub next_cursor_id: Option<String>,
+}
+
+impl<T> Datatable<T> {
+    /// Names of the columns, in order.
+    ///
+    pub fn column_names(&self) -> Vec<&str> {
+        self.columns.iter().map(|column| &column.name[..]).collect()
+    }
+
+    /// Number of rows.
+    ///
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Whether or not there is no row.
+    ///
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+}
+
+/// A column of a datatable, as described by Quandl.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DatatableColumn {
+    /// Name of the column, as used to filter or select it.
+    ///
+    pub name: String,
+
+    /// Quandl's type of the column's values, e.g. `"String"`, `"Date"` or `"BigDecimal(34,12)"`.
+    ///
+    #[serde(rename = "type")]
+    pub column_type: String,
+}
+
+/// (De)serialization of a UTC timestamp as an RFC 3339 string, e.g. `2016-03-01T21:47:01.686Z`.
+///
+mod timestamp {
+    use chrono::NaiveDateTime;
+    use serde::{Deserialize, Deserializer, Serializer};
+    use serde::de::Error;
+
+    pub fn serialize<S: Serializer>(timestamp: &NaiveDateTime, serializer: S)
+        -> ::std::result::Result<S::Ok, S::Error>
+    {
+        serializer.collect_str(&crate::calendar::format_timestamp(&timestamp.and_utc()))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D)
+        -> ::std::result::Result<NaiveDateTime, D::Error>
+    {
+        let timestamp = String::deserialize(deserializer)?;
+
+        crate::calendar::parse_timestamp(&timestamp).ok_or_else(|| {
+            D::Error::custom(format!("invalid timestamp '{}'.", timestamp))
+        })
+    }
+}
+
+/// (De)serialization of an optional date as a `YYYY-MM-DD` string or null.
+///
+mod optional_date {
+    use chrono::NaiveDate;
+    use serde::{Deserialize, Deserializer, Serializer};
+    use serde::de::Error;
+
+    pub fn serialize<S: Serializer>(date: &Option<NaiveDate>, serializer: S)
+        -> ::std::result::Result<S::Ok, S::Error>
+    {
+        match *date {
+            Some(date) => serializer.collect_str(&date),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D)
+        -> ::std::result::Result<Option<NaiveDate>, D::Error>
+    {
+        match Option::<String>::deserialize(deserializer)? {
+            Some(date) => match crate::calendar::parse_date(&date) {
+                Some(date) => Ok(Some(date)),
+                None => Err(D::Error::custom(format!("invalid date '{}'.", date))),
+            },
+
+            None => Ok(None),
+        }
+    }
+}
+
+/// Deserialization of a string which may be null, as an empty string.
+///
+mod null_as_empty {
+    use serde::{Deserialize, Deserializer};
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D)
+        -> ::std::result::Result<String, D::Error>
+    {
+        Ok(Option::<String>::deserialize(deserializer)?.unwrap_or_default())
+    }
+}
+
+/// Lenient deserialization of the counters of metadata (e.g. `DatabaseMetadata::datasets_count`),
+/// which Quandl sometimes returns as numeric strings (e.g. `"9999"`), or as null for brand-new
+/// databases. They still serialize as plain numbers.
+///
+mod lenient_count {
+    use std::convert::TryFrom;
+    use std::fmt;
+
+    use serde::Deserializer;
+    use serde::de::{self, Unexpected, Visitor};
+
+    /// A counter, null being 0.
+    ///
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D)
+        -> ::std::result::Result<usize, D::Error>
+    {
+        Ok(deserializer.deserialize_any(Count)?.unwrap_or(0))
+    }
+
+    /// An optional counter, null being `None`.
+    ///
+    pub fn deserialize_optional<'de, D: Deserializer<'de>>(deserializer: D)
+        -> ::std::result::Result<Option<usize>, D::Error>
+    {
+        deserializer.deserialize_any(Count)
+    }
+
+    struct Count;
+
+    impl<'de> Visitor<'de> for Count {
+        type Value = Option<usize>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "a non-negative integer, as a number or a string, or null")
+        }
+
+        fn visit_u64<E: de::Error>(self, value: u64) -> ::std::result::Result<Self::Value, E> {
+            usize::try_from(value)
+                .map(Some)
+                .map_err(|_| E::invalid_value(Unexpected::Unsigned(value), &self))
+        }
+
+        fn visit_i64<E: de::Error>(self, value: i64) -> ::std::result::Result<Self::Value, E> {
+            usize::try_from(value)
+                .map(Some)
+                .map_err(|_| E::invalid_value(Unexpected::Signed(value), &self))
+        }
+
+        fn visit_f64<E: de::Error>(self, value: f64) -> ::std::result::Result<Self::Value, E> {
+            // e.g. `9999.0`, which some JSON encoders emit for integers.
+            if value >= 0.0 && value.fract() == 0.0 && value <= usize::MAX as f64 {
+                Ok(Some(value as usize))
+            } else {
+                Err(E::invalid_value(Unexpected::Float(value), &self))
+            }
+        }
+
+        fn visit_str<E: de::Error>(self, value: &str) -> ::std::result::Result<Self::Value, E> {
+            match value.trim() {
+                "" => Ok(None),
+
+                trimmed => {
+                    trimmed.parse()
+                        .map(Some)
+                        .map_err(|_| E::invalid_value(Unexpected::Str(value), &self))
+                },
+            }
+        }
+
+        fn visit_unit<E: de::Error>(self) -> ::std::result::Result<Self::Value, E> {
+            Ok(None)
+        }
+
+        fn visit_none<E: de::Error>(self) -> ::std::result::Result<Self::Value, E> {
+            Ok(None)
+        }
+
+        fn visit_some<D: Deserializer<'de>>(self, deserializer: D)
+            -> ::std::result::Result<Self::Value, D::Error>
+        {
+            deserializer.deserialize_any(self)
+        }
+    }
+}
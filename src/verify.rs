@@ -0,0 +1,90 @@
+use url::form_urlencoded::byte_serialize;
+
+use crate::{Result, Error, DownloadError};
+use crate::download::{self, HttpTransport, Transport};
+use crate::query::DatabaseSearch;
+use crate::types::DatabaseList;
+
+/// What Quandl makes of an API key, see `verify_api_key`.
+///
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum KeyInfo {
+    /// The key was accepted. The quota of the key, and what is left of it, are those reported by
+    /// the `X-RateLimit-Limit` and `X-RateLimit-Remaining` headers, if any.
+    ///
+    Valid {
+        limit: Option<usize>,
+        remaining: Option<usize>,
+    },
+
+    /// The key is malformed, unknown to Quandl or not allowed to use the API.
+    ///
+    Invalid,
+}
+
+/// Check that `key` is a valid API key, e.g. before setting up anything relying on it.
+///
+/// This sends the cheapest authenticated call there is (a search for a single database), to the
+/// base URL of the current `config::Config`. The key is invalid if it is malformed (which is
+/// found without any call), if Quandl answers with a 401 or 403 status, or if it reports the key
+/// as unknown (error `QEAx01`); any other failure is an error. The key is never part of the
+/// errors returned, nor logged.
+///
+pub fn verify_api_key<S: AsRef<str>>(key: S) -> Result<KeyInfo> {
+    let key = key.as_ref().trim();
+
+    let mut query = DatabaseSearch::new();
+    query.api_key(key).per_page(1);
+
+    if query.validate().is_err() {
+        return Ok(KeyInfo::Invalid);
+    }
+
+    let request = crate::api_call::request::<DatabaseList, _>(&query);
+    let response = HttpTransport.execute(&request).map_err(|e| redacted(e, key))?;
+    let status = response.status();
+
+    if status.as_u16() == 401 || status.as_u16() == 403 {
+        return Ok(KeyInfo::Invalid);
+    }
+
+    if !status.is_success() {
+        let body = download::read_body(response).map_err(|e| redacted(e, key))?;
+
+        return match download::api_error(&body) {
+            Error::ApiCallFailed(ref e) if e.quandl_error.code == "QEAx01" => Ok(KeyInfo::Invalid),
+            e => Err(redacted(e, key)),
+        };
+    }
+
+    let header = |name: &str| {
+        download::header(&response, reqwest::header::HeaderName::from_bytes(name.as_bytes()).ok()?)
+            .and_then(|value| value.trim().parse().ok())
+    };
+
+    Ok(KeyInfo::Valid {
+        limit: header("x-ratelimit-limit"),
+        remaining: header("x-ratelimit-remaining"),
+    })
+}
+
+/// `error` without any mention of `key`, which transport failures quote as part of the URL.
+///
+pub(crate) fn redacted(error: Error, key: &str) -> Error {
+    let encoded: String = byte_serialize(key.as_bytes()).collect();
+
+    let redact = |message: &str| {
+        message.replace(&encoded, "REDACTED").replace(key, "REDACTED")
+    };
+
+    match error {
+        Error::DownloadFailed(e) => {
+            // The source is dropped along the way, since it quotes the URL too.
+            Error::DownloadFailed(DownloadError::new(e.kind(), redact(e.message())))
+        },
+
+        Error::IoError(message) => Error::IoError(redact(&message)),
+        Error::ParsingFailed(message) => Error::ParsingFailed(redact(&message)),
+        error => error,
+    }
+}
@@ -0,0 +1,223 @@
+use std::ops::{Deref, DerefMut};
+
+use chrono::NaiveDate;
+
+use crate::Error;
+use crate::calendar::DateRange;
+
+/// Row counts at which Quandl is known to silently cut off some responses (e.g. of anonymous or
+/// mis-parameterized requests), see `Warning::PossiblyTruncated`.
+///
+pub const ROW_CAPS: [usize; 2] = [100, 10_000];
+
+/// A non-fatal issue encountered while producing an otherwise successful result.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub enum Warning {
+    /// This many rows of the response could not be decoded and were left out.
+    ///
+    RowsSkipped(usize),
+
+    /// No data point was found within this range of dates, although some were expected.
+    ///
+    DateGap(DateRange),
+
+    /// The data was retrieved, but its metadata could not be.
+    ///
+    MetadataUnavailable(Error),
+
+    /// The data returned covers less than the range of dates requested. `actual` is `None` when
+    /// no data at all was returned.
+    ///
+    CoverageShortfall {
+        requested: DateRange,
+        actual: Option<DateRange>,
+    },
+
+    /// The database `from` was renamed `to` by Nasdaq Data Link, and queried as such.
+    ///
+    DatabaseRenamed {
+        from: String,
+        to: String,
+    },
+
+    /// The database `code` was retired by Nasdaq Data Link, its data no longer updated since
+    /// `since` when that is known. `note` tells what became of it.
+    ///
+    DatabaseRetired {
+        code: String,
+        since: Option<NaiveDate>,
+        note: String,
+    },
+
+    /// The first `repeated` results of `page` had already been listed on the previous page, the
+    /// results having shifted since: they were left out.
+    ///
+    PageOverlap {
+        page: usize,
+        repeated: usize,
+    },
+
+    /// There were `missing` fewer results when `page` was fetched than when the previous page
+    /// was: as many results may have shifted to the pages before and been skipped.
+    ///
+    PageSkip {
+        page: usize,
+        missing: usize,
+    },
+
+    /// The data of the dataset was retrieved successfully but had no rows, at least within the
+    /// dates requested.
+    ///
+    EmptyDataset {
+        database_code: String,
+        dataset_code: String,
+    },
+
+    /// The code list of the database was retrieved successfully but had no dataset.
+    ///
+    EmptyDatabase {
+        database_code: String,
+    },
+
+    /// Exactly as many rows as one of the `ROW_CAPS` were returned, although neither `rows` nor
+    /// `limit` was requested: the response may have been cut off.
+    ///
+    PossiblyTruncated {
+        rows: usize,
+    },
+
+    /// The response was cut off at `rows` rows (one of the `ROW_CAPS`): the metadata of the
+    /// dataset shows data from `expected`, but the rows only cover `actual`.
+    ///
+    Truncated {
+        rows: usize,
+        expected: DateRange,
+        actual: DateRange,
+    },
+}
+
+/// Warnings attached to a result, in the order they were encountered.
+///
+pub type Warnings = Vec<Warning>;
+
+/// A successful result along with the warnings encountered while producing it.
+///
+/// It dereferences to the value, and `into_value` discards the warnings for callers which do not
+/// care about them.
+///
+#[must_use = "this result may carry warnings which should be checked or explicitly discarded"]
+#[derive(Debug, Clone, PartialEq)]
+pub struct WithWarnings<T> {
+    pub value: T,
+    pub warnings: Warnings,
+}
+
+impl<T> WithWarnings<T> {
+    /// Wrap `value` without any warning.
+    ///
+    pub fn new(value: T) -> Self {
+        WithWarnings {
+            value,
+            warnings: vec![],
+        }
+    }
+
+    /// Whether or not no warning was attached.
+    ///
+    pub fn is_clean(&self) -> bool {
+        self.warnings.is_empty()
+    }
+
+    /// Discard the warnings.
+    ///
+    pub fn into_value(self) -> T {
+        self.value
+    }
+
+    /// Split into the value and its warnings.
+    ///
+    pub fn into_parts(self) -> (T, Warnings) {
+        (self.value, self.warnings)
+    }
+
+    /// Transform the value, keeping the warnings.
+    ///
+    pub fn map<U, F: FnOnce(T) -> U>(self, f: F) -> WithWarnings<U> {
+        WithWarnings {
+            value: f(self.value),
+            warnings: self.warnings,
+        }
+    }
+}
+
+impl<T> Deref for WithWarnings<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T> DerefMut for WithWarnings<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+impl ::std::fmt::Display for Warning {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        match self {
+            Warning::RowsSkipped(n) => {
+                write!(f, "{} rows could not be decoded and were skipped.", n)
+            },
+
+            Warning::DateGap(range) => write!(f, "no data from {}.", range),
+            Warning::MetadataUnavailable(e) => write!(f, "metadata unavailable: {}", e),
+
+            Warning::CoverageShortfall { requested, actual: Some(actual) } => {
+                write!(f, "requested data from {} but got data from {}.", requested, actual)
+            },
+
+            Warning::CoverageShortfall { requested, actual: None } => {
+                write!(f, "requested data from {} but got none.", requested)
+            },
+
+            Warning::DatabaseRenamed { from, to } => {
+                write!(f, "database {} was renamed {}.", from, to)
+            },
+
+            Warning::DatabaseRetired { code, since: Some(since), note } => {
+                write!(f, "database {} was retired on {}: {}", code, since, note)
+            },
+
+            Warning::DatabaseRetired { code, since: None, note } => {
+                write!(f, "database {} was retired: {}", code, note)
+            },
+
+            Warning::PageOverlap { page, repeated } => {
+                write!(f, "the first {} results of page {} were already listed.", repeated, page)
+            },
+
+            Warning::PageSkip { page, missing } => {
+                write!(f, "{} results may have been skipped before page {}.", missing, page)
+            },
+
+            Warning::EmptyDataset { database_code, dataset_code } => {
+                write!(f, "dataset {}/{} has no data.", database_code, dataset_code)
+            },
+
+            Warning::EmptyDatabase { database_code } => {
+                write!(f, "database {} has no dataset.", database_code)
+            },
+
+            Warning::PossiblyTruncated { rows } => {
+                write!(f, "exactly {} rows were returned, which may have been cut off.", rows)
+            },
+
+            Warning::Truncated { rows, expected, actual } => {
+                write!(f, "cut off at {} rows, covering {} instead of {}.", rows, actual, expected)
+            },
+        }
+    }
+}
@@ -0,0 +1,91 @@
+#![cfg(feature = "aliases")]
+
+extern crate chrono;
+extern crate quandl_v3;
+
+use chrono::NaiveDate;
+
+use quandl_v3::aliases::*;
+use quandl_v3::prelude::*;
+use quandl_v3::Warning;
+
+#[test]
+fn active() {
+    assert_eq!(resolve_code("FRED"), CodeResolution::Active);
+    assert_eq!(resolve_code(""), CodeResolution::Active);
+}
+
+#[test]
+fn renamed() {
+    let quotemedia = CodeResolution::RenamedTo("QUOTEMEDIA".to_string());
+
+    assert_eq!(resolve_code("EOD"), quotemedia);
+    assert_eq!(resolve_code(" eod "), quotemedia);
+}
+
+#[test]
+fn retired() {
+    match resolve_code("WIKI") {
+        CodeResolution::Retired { since, note } => {
+            assert_eq!(since, Some(NaiveDate::from_ymd_opt(2018, 3, 27).unwrap()));
+            assert!(note.contains("WIKI Prices"), "{}", note);
+        },
+
+        other => panic!("{:?}", other),
+    }
+
+    match resolve_code("YAHOO") {
+        CodeResolution::Retired { since: None, note } => assert!(!note.is_empty()),
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn successors_are_active() {
+    for code in &["WIKI", "YAHOO", "GOOG", "EOD"] {
+        if let CodeResolution::RenamedTo(successor) = resolve_code(code) {
+            assert_eq!(resolve_code(&successor), CodeResolution::Active, "{}", code);
+        }
+    }
+}
+
+#[test]
+fn renamed_queries_are_rewritten() {
+    let mut query = DataQuery::new("EOD", "AAPL");
+
+    assert_eq!(query.resolve_aliases(), Some(Warning::DatabaseRenamed {
+        from: "EOD".to_string(),
+        to: "QUOTEMEDIA".to_string(),
+    }));
+
+    assert_eq!(query.database_code(), "QUOTEMEDIA");
+    assert_eq!(query.dataset_code(), "AAPL");
+
+    // Nothing left to resolve.
+    assert_eq!(query.resolve_aliases(), None);
+}
+
+#[test]
+fn retired_queries_are_left_as_is() {
+    let mut query = DataQuery::new("WIKI", "AAPL");
+
+    match query.resolve_aliases() {
+        Some(warning @ Warning::DatabaseRetired { .. }) => {
+            assert!(warning.to_string().starts_with("database WIKI was retired on 2018-03-27: "),
+                    "{}",
+                    warning);
+        },
+
+        other => panic!("{:?}", other),
+    }
+
+    assert_eq!(query.database_code(), "WIKI");
+}
+
+#[test]
+fn active_queries_are_left_as_is() {
+    let mut query = DataQuery::new("FRED", "GDP");
+
+    assert_eq!(query.resolve_aliases(), None);
+    assert_eq!(query, DataQuery::new("FRED", "GDP"));
+}
@@ -0,0 +1,180 @@
+extern crate quandl_v3;
+
+mod common;
+
+use std::collections::HashSet;
+
+use quandl_v3::Error;
+use quandl_v3::config::Config;
+use quandl_v3::prelude::*;
+
+use common::{MockServer, Response};
+
+static DATABASE_METADATA: &str = include_str!("fixtures/database_metadata.json");
+
+fn server() -> MockServer {
+    MockServer::start(|_| Response::json(DATABASE_METADATA))
+}
+
+fn rejection<T: std::fmt::Debug>(result: quandl_v3::Result<T>) -> String {
+    match result {
+        Err(Error::ParsingFailed(message)) => message,
+        other => panic!("expected the key to be rejected, got {:?}", other),
+    }
+}
+
+#[test]
+fn keys_are_trimmed() {
+    for key in &["KEY", "KEY\n", " KEY ", "\tKEY\r\n"] {
+        let mut query = DatabaseMetadataQuery::new("WIKI");
+        query.api_key(key);
+
+        assert_eq!(query.url(), "https://www.quandl.com/api/v3/databases/WIKI.json?api_key=KEY");
+    }
+
+    let server = server();
+    let mut query = DatabaseMetadataQuery::new("WIKI");
+    query.base_url(server.url()).api_key(String::from("KEY\n"));
+
+    assert!(query.send().is_ok());
+    assert_eq!(server.requests()[0].query, "api_key=KEY");
+}
+
+#[test]
+fn malformed_keys_are_rejected_before_sending() {
+    let cases = [
+        ("", "invalid API key: it is empty."),
+        (" \n", "invalid API key: it is empty."),
+        ("MY KEY", "invalid API key: it contains ' '."),
+        ("MY\tKEY", "invalid API key: it contains '\\t'."),
+        ("KEY&rows=1", "invalid API key: it contains '&'."),
+        ("api_key=KEY", "invalid API key: it contains '='."),
+        ("KEY\u{7}", "invalid API key: it contains '\\u{7}'."),
+    ];
+
+    let server = server();
+
+    for &(key, expected) in &cases {
+        let mut query = DatabaseMetadataQuery::new("WIKI");
+        query.base_url(server.url()).api_key(key);
+
+        assert_eq!(rejection(query.send()), expected, "key: {:?}", key);
+        assert_eq!(rejection(query.encoded_data()), expected, "key: {:?}", key);
+    }
+
+    let mut data = DataQuery::new("WIKI", "AAPL");
+    data.base_url(server.url()).api_key("MY KEY");
+    rejection(ApiCall::<Vec<(String, f64)>>::send(&data));
+
+    let mut download = DatabaseDownloadQuery::new("WIKI");
+    download.base_url(server.url()).api_key("");
+    rejection(download.download_to_file_resumable(std::env::temp_dir().join("quandl-v3-no-key")));
+
+    assert_eq!(server.hits(), 0);
+}
+
+#[test]
+fn keys_are_redacted_from_debug() {
+    let key = ApiKey::new("0123456789SECRET");
+    assert_eq!(format!("{:?}", key), "ApiKey(****)");
+    assert_eq!(format!("{:#?}", Some(key.clone())), "Some(\n    ApiKey(****),\n)");
+
+    let mut query = DatabaseMetadataQuery::new("WIKI");
+    query.api_key("0123456789SECRET");
+
+    let config = Config { api_key: Some(key), .. Config::default() };
+
+    for debug in &[format!("{:?}", query), format!("{:?}", config)] {
+        assert!(debug.contains("ApiKey(****)"), "{}", debug);
+        assert!(!debug.contains("SECRET"), "{}", debug);
+    }
+
+    // The URL is where the key is sent, so it is still there.
+    assert!(query.url().ends_with("api_key=0123456789SECRET"));
+}
+
+#[test]
+fn keys_compare_on_their_value() {
+    let key = ApiKey::new("KEY");
+
+    assert_eq!(key, ApiKey::from("KEY"));
+    assert_eq!(key, ApiKey::from(String::from("KEY")));
+    assert_eq!(key, key.clone());
+    assert_ne!(key, ApiKey::new("OTHER"));
+    assert_ne!(key, ApiKey::new(" KEY"));
+    assert_eq!(ApiKey::new(" KEY ").expose_secret(), " KEY ");
+
+    let keys: HashSet<ApiKey> = {
+        vec![ApiKey::new("KEY"), key.clone(), ApiKey::new("OTHER"), ApiKey::from("KEY")]
+            .into_iter()
+            .collect()
+    };
+
+    assert_eq!(keys.len(), 2);
+    assert!(keys.contains(&key));
+
+    // Queries compare on their keys too, once trimmed.
+    let query = |key: &str| DatabaseMetadataQuery::new("WIKI").api_key(key).clone();
+    assert_eq!(query("KEY"), query("KEY\n"));
+    assert_ne!(query("KEY"), query("OTHER"));
+
+    let config = Config { api_key: Some(ApiKey::new(" KEY\n")), .. Config::default() };
+    let _guard = Config::override_scope(config);
+    assert_eq!(DatabaseMetadataQuery::new("WIKI"), query("KEY"));
+}
+
+#[test]
+fn no_key_is_not_an_error() {
+    let server = server();
+    assert!(DatabaseMetadataQuery::new("WIKI").base_url(server.url()).send().is_ok());
+}
+
+#[cfg(feature = "batch")]
+#[test]
+fn batch_buckets_normalized_keys_together() {
+    use std::time::{Duration, Instant};
+
+    let server = server();
+    let start = Instant::now();
+
+    let results: Vec<_> = {
+        let mut batch_query = BatchQuery::new();
+
+        for key in &["KEY", "KEY\n", " KEY "] {
+            let mut query = DatabaseMetadataQuery::new("WIKI");
+            query.base_url(server.url()).api_key(key);
+            batch_query.query(query);
+        }
+
+        batch_query.limit(2, 1).threads(3);
+        batch_query.run().collect()
+    };
+
+    // A single bucket allows two calls within the first second, so the third one had to wait.
+    assert!(results.iter().all(|result| result.is_ok()));
+    assert!(start.elapsed() >= Duration::from_secs(1));
+    assert_eq!(server.hits(), 3);
+}
+
+#[cfg(feature = "batch")]
+#[test]
+fn keys_from_every_source_share_a_bucket() {
+    let key = "0123456789KEY";
+
+    let mut batch_query = BatchQuery::new();
+    batch_query.query(DatabaseMetadataQuery::new("POOL"));
+    batch_query.query(DatabaseMetadataQuery::new("OWN").api_key(key).clone());
+
+    {
+        let api_key = Some(ApiKey::from(format!(" {}\n", key)));
+        let config = Config { api_key, .. Config::default() };
+        let _guard = Config::override_scope(config);
+        batch_query.query(DatabaseMetadataQuery::new("CONFIG"));
+    }
+
+    batch_query.key_profile(KeyProfile::new(key)).threads(1);
+
+    let plan = batch_query.plan();
+    assert_eq!(plan.keyless, 0);
+    assert_eq!(plan.keys.into_iter().collect::<Vec<_>>(), vec![("****9KEY".to_string(), 3)]);
+}
@@ -0,0 +1,225 @@
+extern crate quandl_v3;
+
+mod common;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+
+use quandl_v3::Error;
+use quandl_v3::archive::{Archive, Corruption, Hash};
+use quandl_v3::prelude::*;
+
+use common::{MockServer, Response};
+
+static DATABASE_METADATA: &str = include_str!("fixtures/database_metadata.json");
+
+fn directory(name: &str) -> PathBuf {
+    let name = format!("quandl-v3-archive-{}-{}", name, std::process::id());
+    let path = std::env::temp_dir().join(name);
+    let _ = fs::remove_dir_all(&path);
+    path
+}
+
+/// Files of the payloads stored in `archive`, temporary files included.
+///
+fn objects(archive: &Archive) -> Vec<PathBuf> {
+    fn walk(path: &Path, files: &mut Vec<PathBuf>) {
+        for entry in fs::read_dir(path).unwrap() {
+            let path = entry.unwrap().path();
+
+            if path.is_dir() {
+                walk(&path, files);
+            } else {
+                files.push(path);
+            }
+        }
+    }
+
+    let mut files = vec![];
+    walk(&archive.directory().join("objects"), &mut files);
+    files
+}
+
+#[test]
+fn sha256() {
+    assert_eq!(Hash::of(b"").to_string(),
+               "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+    assert_eq!(Hash::of(b"abc").to_string(),
+               "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+    assert_eq!(Hash::of(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq").to_string(),
+               "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1");
+    assert_eq!(Hash::of(&[b'a'; 1_000_000]).to_string(),
+               "cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0");
+
+    let hash = Hash::of(b"abc");
+    assert_eq!(hash.to_string().parse::<Hash>().unwrap(), hash);
+    assert!(matches!("abc".parse::<Hash>(), Err(Error::ParsingFailed(_))));
+    assert!(hash.to_string().replace('b', "g").parse::<Hash>().is_err());
+}
+
+#[test]
+fn identical_payloads_are_stored_once() {
+    let archive = Archive::open(directory("dedup")).unwrap();
+
+    let aapl = "https://www.quandl.com/api/v3/datasets/WIKI/AAPL.json?api_key=SECRET&rows=1";
+    let msft = "https://www.quandl.com/api/v3/datasets/WIKI/MSFT.json?rows=1";
+
+    let hash = archive.store(aapl, b"[]").unwrap();
+    assert_eq!(archive.store(msft, b"[]").unwrap(), hash);
+    assert_eq!(archive.store(aapl, b"[]").unwrap(), hash);
+
+    assert_eq!(objects(&archive), vec![archive.path(&hash)]);
+    assert_eq!(fs::read(archive.path(&hash)).unwrap(), b"[]");
+
+    let entries = archive.entries().unwrap();
+    assert_eq!(entries.len(), 3);
+    assert_eq!(entries[0].url, "https://www.quandl.com/api/v3/datasets/WIKI/AAPL.json?rows=1");
+    assert_eq!((entries[0].sha256, entries[0].size), (hash, 2));
+    assert!(entries[0].stored_at.ends_with('Z'), "{}", entries[0].stored_at);
+
+    let index = fs::read_to_string(archive.directory().join("index.jsonl")).unwrap();
+    assert!(!index.contains("SECRET"), "{}", index);
+
+    // Whatever the key of the query, the payload is found.
+    let url = "https://www.quandl.com/api/v3/datasets/WIKI/AAPL.json?api_key=OTHER&rows=1";
+    assert_eq!(archive.lookup(url).unwrap(), Some((hash, archive.path(&hash))));
+    assert_eq!(archive.lookup(msft).unwrap().unwrap().0, hash);
+    assert_eq!(archive.lookup("https://www.quandl.com/api/v3/datasets/WIKI/IBM.json").unwrap(),
+               None);
+
+    // The last payload stored for a query is the one looked up.
+    let revised = archive.store(aapl, b"[1]").unwrap();
+    assert_eq!(archive.lookup(aapl).unwrap().unwrap().0, revised);
+    assert_eq!(objects(&archive).len(), 2);
+}
+
+#[test]
+fn corruptions_are_detected() {
+    let archive = Archive::open(directory("corruption")).unwrap();
+
+    let url = |code: &str| format!("https://www.quandl.com/api/v3/databases/{}.json", code);
+
+    let first = archive.store(url("WIKI"), b"WIKI").unwrap();
+    let second = archive.store(url("FRED"), b"FRED").unwrap();
+    archive.store(url("ICE"), b"ICE").unwrap();
+
+    assert_eq!(archive.verify().unwrap(), vec![]);
+
+    fs::write(archive.path(&first), b"WIKJ").unwrap();
+    fs::remove_file(archive.path(&second)).unwrap();
+
+    let mut expected = vec![
+        Corruption::Altered { hash: first, actual: Hash::of(b"WIKJ"), path: archive.path(&first) },
+        Corruption::Missing { hash: second, path: archive.path(&second) },
+    ];
+
+    expected.sort_by_key(|corruption| match *corruption {
+        Corruption::Altered { hash, .. } | Corruption::Missing { hash, .. } => hash,
+    });
+
+    assert_eq!(archive.verify().unwrap(), expected);
+
+    let missing = Corruption::Missing { hash: second, path: archive.path(&second) };
+    assert_eq!(missing.to_string(),
+               format!("payload {} is missing ({})", second, archive.path(&second).display()));
+
+    assert_eq!(archive.lookup(url("FRED")).unwrap(), None);
+
+    // Storing the payload again repairs it.
+    archive.store(url("FRED"), b"FRED").unwrap();
+    assert_eq!(archive.verify().unwrap().len(), 1);
+}
+
+#[test]
+fn torn_index_lines_are_skipped() {
+    let archive = Archive::open(directory("torn")).unwrap();
+    archive.store("https://www.quandl.com/api/v3/databases/WIKI.json", b"WIKI").unwrap();
+
+    {
+        use std::io::Write;
+
+        let path = archive.directory().join("index.jsonl");
+        let mut file = fs::OpenOptions::new().append(true).open(path).unwrap();
+        write!(file, "{{\"url\":\"https://www.quandl.com/api/v3/data").unwrap();
+    }
+
+    archive.store("https://www.quandl.com/api/v3/databases/FRED.json", b"FRED").unwrap();
+
+    let urls: Vec<String> = archive.entries().unwrap().into_iter().map(|x| x.url).collect();
+
+    assert_eq!(urls, vec!["https://www.quandl.com/api/v3/databases/WIKI.json",
+                          "https://www.quandl.com/api/v3/databases/FRED.json"]);
+}
+
+#[test]
+fn concurrent_writers() {
+    let path = directory("concurrent");
+
+    let writers: Vec<_> = {
+        (0..8).map(|writer| {
+            let archive = Archive::open(&path).unwrap();
+
+            thread::spawn(move || {
+                for n in 0..25 {
+                    let url = format!("https://www.quandl.com/api/v3/databases/DB{}.json", n);
+                    let payload = format!("payload {} of {}", n % 5, writer % 2);
+                    archive.store(url, payload.as_bytes()).unwrap();
+                }
+            })
+        }).collect()
+    };
+
+    for writer in writers {
+        writer.join().unwrap();
+    }
+
+    let archive = Archive::open(&path).unwrap();
+
+    assert_eq!(archive.entries().unwrap().len(), 200);
+    assert_eq!(archive.verify().unwrap(), vec![]);
+    assert_eq!(objects(&archive).len(), 10);
+}
+
+#[test]
+fn queries_write_through() {
+    let server = {
+        MockServer::routes(vec![
+            ("/api/v3/databases/WIKI.json", Response::json(DATABASE_METADATA)),
+            ("/api/v3/databases/COPY.json", Response::json(DATABASE_METADATA)),
+        ])
+    };
+
+    let archive = Archive::open(directory("write-through")).unwrap();
+
+    let mut config = ClientConfig::new();
+    config.write_through(archive.clone());
+    assert_eq!(config.archive(), Some(&archive));
+
+    let query = |code: &str| {
+        let mut query = DatabaseMetadataQuery::new(code);
+        query.base_url(server.url()).api_key("SECRET").client_config(&config);
+        query
+    };
+
+    query("WIKI").send().unwrap();
+    query("COPY").send().unwrap();
+    assert!(query("MISSING").send().is_err());
+
+    let hash = Hash::of(DATABASE_METADATA.as_bytes());
+    let url = query("WIKI").url();
+
+    assert_eq!(archive.lookup(&url).unwrap(), Some((hash, archive.path(&hash))));
+    assert_eq!(archive.lookup(query("COPY").url()).unwrap().unwrap().0, hash);
+    assert_eq!(archive.lookup(query("MISSING").url()).unwrap(), None);
+
+    assert_eq!(archive.entries().unwrap()[0].url, format!("{}/databases/WIKI.json", server.url()));
+    assert_eq!(objects(&archive).len(), 1);
+
+    // Nothing is returned which could not be archived.
+    fs::remove_dir_all(archive.directory()).unwrap();
+    fs::write(archive.directory(), b"not a directory").unwrap();
+
+    assert!(matches!(query("WIKI").send(), Err(Error::IoError(_))));
+    fs::remove_file(archive.directory()).unwrap();
+}
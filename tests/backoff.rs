@@ -0,0 +1,241 @@
+extern crate quandl_v3;
+extern crate serde_json;
+
+use std::cell::Cell;
+use std::time::Duration;
+
+use quandl_v3::{DownloadError, DownloadErrorKind, Error};
+use quandl_v3::backoff::*;
+
+fn millis(n: u64) -> Duration {
+    Duration::from_millis(n)
+}
+
+/// Policies spanning the range of every setting, drawn from a fixed sequence (a linear
+/// congruential generator) so that failures are reproducible.
+///
+fn policies() -> Vec<(Backoff, u64)> {
+    let mut state = 0x853c_49e6_748f_ea9bu64;
+
+    let mut next = move |n: u64| {
+        state = {
+            state.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1_442_695_040_888_963_407)
+        };
+        (state >> 33) % n
+    };
+
+    (0..500).map(|_| {
+        let mut backoff = Backoff::default();
+
+        backoff.base(millis(next(2_000)))
+               .multiplier(1.0 + next(4_000) as f64 / 1_000.0)
+               .max_delay(millis(next(120_000)))
+               .jitter([Jitter::None, Jitter::Full, Jitter::Decorrelated][next(3) as usize])
+               .max_attempts(Some(1 + next(40) as usize));
+
+        (backoff, next(u64::MAX))
+    }).collect()
+}
+
+fn settings(backoff: &Backoff) -> serde_json::Value {
+    serde_json::to_value(backoff).unwrap()
+}
+
+fn millis_of(backoff: &Backoff, field: &str) -> Duration {
+    serde_json::from_value(settings(backoff)[field].clone()).unwrap()
+}
+
+#[test]
+fn delays_without_jitter() {
+    let mut backoff = Backoff::default();
+    backoff.base(millis(100)).multiplier(3.0).max_delay(millis(2_000)).jitter(Jitter::None);
+
+    let delays: Vec<Duration> = backoff.delays().collect();
+    assert_eq!(delays, vec![millis(100), millis(300), millis(900), millis(2_000)]);
+
+    backoff.max_attempts(Some(1));
+    assert_eq!(backoff.delays().next_delay(), None);
+}
+
+#[test]
+fn unlimited_attempts() {
+    let mut backoff = Backoff::default();
+    backoff.max_attempts(None).max_delay(millis(10));
+
+    let mut delays = backoff.delays();
+
+    assert_eq!(delays.by_ref().take(10_000).count(), 10_000);
+    assert_eq!(delays.retries(), 10_000);
+    assert!(delays.next_delay().unwrap() <= millis(10));
+}
+
+#[test]
+fn attempts_are_bounded() {
+    for (backoff, seed) in policies() {
+        let max_attempts = settings(&backoff)["max_attempts"].as_u64().unwrap() as usize;
+        assert_eq!(backoff.delays_with_seed(seed).count(), max_attempts - 1, "{:?}", backoff);
+    }
+}
+
+#[test]
+fn delays_are_capped() {
+    for (backoff, seed) in policies() {
+        let max_delay = millis_of(&backoff, "max_delay");
+
+        for delay in backoff.delays_with_seed(seed) {
+            assert!(delay <= max_delay, "{:?}: {:?}", backoff, delay);
+        }
+    }
+}
+
+#[test]
+fn delays_without_jitter_never_decrease() {
+    for (mut backoff, seed) in policies() {
+        backoff.jitter(Jitter::None);
+
+        let delays: Vec<Duration> = backoff.delays_with_seed(seed).collect();
+
+        for pair in delays.windows(2) {
+            assert!(pair[0] <= pair[1], "{:?}: {:?}", backoff, delays);
+        }
+    }
+}
+
+#[test]
+fn full_jitter_is_bounded_by_the_delays_without_it() {
+    for (mut backoff, seed) in policies() {
+        backoff.jitter(Jitter::Full);
+        let jittered: Vec<Duration> = backoff.delays_with_seed(seed).collect();
+
+        backoff.jitter(Jitter::None);
+        let exact: Vec<Duration> = backoff.delays_with_seed(seed).collect();
+
+        assert_eq!(jittered.len(), exact.len());
+
+        for (jittered, exact) in jittered.iter().zip(&exact) {
+            assert!(jittered <= exact, "{:?}: {:?} > {:?}", backoff, jittered, exact);
+        }
+    }
+}
+
+#[test]
+fn decorrelated_jitter_is_bounded_by_the_previous_delay() {
+    for (mut backoff, seed) in policies() {
+        backoff.jitter(Jitter::Decorrelated);
+
+        let base = millis_of(&backoff, "base");
+        let max_delay = millis_of(&backoff, "max_delay");
+        let multiplier = settings(&backoff)["multiplier"].as_f64().unwrap();
+        let low = base.min(max_delay);
+
+        let mut previous = base;
+
+        for delay in backoff.delays_with_seed(seed) {
+            let high = previous.mul_f64(multiplier).min(max_delay).max(low);
+
+            // A microsecond of slack for rounding.
+            assert!(delay + Duration::from_micros(1) >= low, "{:?}: {:?}", backoff, delay);
+            assert!(delay <= high + Duration::from_micros(1), "{:?}: {:?}", backoff, delay);
+
+            previous = delay;
+        }
+    }
+}
+
+#[test]
+fn jitter_spreads_delays() {
+    let backoff = Backoff::default();
+
+    let first: Vec<Duration> = (0..100).filter_map(|seed| backoff.delays_with_seed(seed).next())
+                                       .collect();
+
+    assert!(first.iter().any(|&x| x != first[0]), "{:?}", first);
+
+    // The same seed gives the same delays.
+    let delays = |seed| backoff.delays_with_seed(seed).collect::<Vec<_>>();
+    assert_eq!(delays(42), delays(42));
+}
+
+#[test]
+fn serialization() {
+    let mut backoff = Backoff::default();
+    backoff.base(millis(250)).jitter(Jitter::Decorrelated).max_attempts(None);
+
+    let json = serde_json::to_string(&backoff).unwrap();
+    assert_eq!(serde_json::from_str::<Backoff>(&json).unwrap(), backoff);
+
+    // Missing settings keep their default.
+    let partial: Backoff = {
+        serde_json::from_str(r#"{"jitter": "none", "max_attempts": 3}"#).unwrap()
+    };
+
+    let mut expected = Backoff::default();
+    expected.jitter(Jitter::None).max_attempts(Some(3));
+
+    assert_eq!(partial, expected);
+    assert_eq!(serde_json::from_str::<Backoff>("{}").unwrap(), Backoff::default());
+}
+
+#[test]
+fn deserialized_settings_are_brought_within_range() {
+    let backoff: Backoff = {
+        serde_json::from_str(r#"{"multiplier": 0.5, "jitter": "none"}"#).unwrap()
+    };
+
+    let delays: Vec<Duration> = backoff.delays().collect();
+    assert_eq!(delays, vec![millis(500); 4]);
+
+    let zero: Backoff = serde_json::from_str(r#"{"base": {"secs": 0, "nanos": 0}}"#).unwrap();
+    assert!(zero.delays().all(|delay| delay == Duration::from_secs(0)));
+}
+
+fn quick() -> Backoff {
+    let mut backoff = Backoff::default();
+    backoff.base(millis(1)).max_delay(millis(5)).max_attempts(Some(4));
+    backoff
+}
+
+#[test]
+fn retry_until_success() {
+    let calls = Cell::new(0);
+
+    let result = retry(&quick(), || {
+        calls.set(calls.get() + 1);
+
+        if calls.get() < 3 {
+            Err(Error::DownloadFailed(DownloadError::new(DownloadErrorKind::Timeout, "timed out")))
+        } else {
+            Ok(calls.get())
+        }
+    });
+
+    assert_eq!(result.unwrap(), 3);
+}
+
+#[test]
+fn retry_gives_up() {
+    let calls = Cell::new(0);
+    let error = Error::DownloadFailed(DownloadError::new(DownloadErrorKind::Connect, "refused"));
+
+    let result: Result<(), Error> = retry(&quick(), || {
+        calls.set(calls.get() + 1);
+        Err(error.clone())
+    });
+
+    assert_eq!(result.unwrap_err(), error);
+    assert_eq!(calls.get(), 4);
+
+    // Permanent failures are not retried.
+    let calls = Cell::new(0);
+    let error = Error::DownloadFailed(DownloadError::new(DownloadErrorKind::Dns, "unknown host"));
+
+    let result: Result<(), Error> = retry(&quick(), || {
+        calls.set(calls.get() + 1);
+        Err(error.clone())
+    });
+
+    assert_eq!(result.unwrap_err(), error);
+    assert_eq!(calls.get(), 1);
+
+    assert!(!is_retryable(&Error::ParsingFailed("bad".to_string())));
+}
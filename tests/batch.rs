@@ -0,0 +1,649 @@
+#![cfg(feature = "batch")]
+
+extern crate quandl_v3;
+
+mod common;
+
+use std::sync::{Arc, Mutex};
+use std::thread::{sleep, spawn};
+use std::time::{Duration, Instant};
+
+use quandl_v3::{Error, Result};
+use quandl_v3::prelude::*;
+
+use common::{MockServer, Response};
+
+static DATA: &str = include_str!("fixtures/data_3_columns.csv");
+static DATABASE_METADATA: &str = include_str!("fixtures/database_metadata.json");
+
+fn metadata_server() -> MockServer {
+    MockServer::start(|request| {
+        let code = request.path.trim_start_matches("/api/v3/databases/").trim_end_matches(".json");
+        Response::json(DATABASE_METADATA.replace("\"WIKI\"", &format!("\"{}\"", code)))
+    })
+}
+
+fn query(server: &MockServer, code: &str, key: &str) -> DatabaseMetadataQuery {
+    let mut query = DatabaseMetadataQuery::new(code);
+    query.base_url(server.url()).api_key(key);
+    query
+}
+
+#[test]
+fn results_keep_query_order() {
+    let server = metadata_server();
+    let codes = ["WIKI", "FRED", "JODI", "EIA", "ICE"];
+
+    for threads in 1..4 {
+        let mut batch_query = BatchQuery::new();
+
+        for code in &codes {
+            batch_query.query(query(&server, code, "key"));
+        }
+
+        batch_query.threads(threads);
+
+        let results: Vec<_> = {
+            batch_query.run().map(|result| result.unwrap().database_code).collect()
+        };
+        assert_eq!(results, codes, "threads: {}", threads);
+    }
+}
+
+#[test]
+fn limits_throttle_each_key() {
+    let server = metadata_server();
+    let start = Instant::now();
+
+    let results: Vec<_> = {
+        let mut batch_query = BatchQuery::new();
+
+        batch_query
+            .query(query(&server, "WIKI", "key"))
+            .query(query(&server, "FRED", "key"))
+            .query(query(&server, "JODI", "key"))
+            .limit(2, 1)
+            .threads(3);
+
+        batch_query.run().collect()
+    };
+
+    assert_eq!(results.len(), 3);
+    assert!(results.iter().all(|result| result.is_ok()));
+    assert!(start.elapsed() >= Duration::from_secs(1));
+    assert_eq!(server.hits(), 3);
+}
+
+#[test]
+fn limits_do_not_throttle_other_keys() {
+    let server = metadata_server();
+    let start = Instant::now();
+
+    let results: Vec<_> = {
+        let mut batch_query = BatchQuery::new();
+
+        batch_query
+            .query(query(&server, "WIKI", "a"))
+            .query(query(&server, "FRED", "b"))
+            .query(query(&server, "JODI", "c"))
+            .limit(1, 5)
+            .threads(3);
+
+        batch_query.run().collect()
+    };
+
+    assert!(results.iter().all(|result| result.is_ok()));
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn throttles_are_reported() {
+    let server = metadata_server();
+    let events = Arc::new(Mutex::new(vec![]));
+
+    let mut batch_query = BatchQuery::new();
+
+    for code in &["WIKI", "FRED", "JODI"] {
+        batch_query.query(query(&server, code, "a_secret_key"));
+    }
+
+    batch_query.query(query(&server, "WIKI", "another_key")).limit(1, 1).threads(1);
+
+    let recorded = events.clone();
+    batch_query.on_throttle(move |event| recorded.lock().unwrap().push(event.clone()));
+
+    let (results, report) = batch_query.run_with_report();
+    assert!(results.collect::<Vec<_>>().iter().all(|result| result.is_ok()));
+
+    // A call right away, then a call a second for the same key; the other key never waits.
+    let report = report.get();
+    assert_eq!(report.throttles, *events.lock().unwrap());
+    assert_eq!(report.throttles.len(), 2);
+
+    let mut at = Duration::from_secs(0);
+
+    for event in &report.throttles {
+        assert_eq!(event.key_fingerprint.len(), 8);
+        assert!(event.key_fingerprint.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_eq!(event.threshold_hit, (1, Duration::from_secs(1)));
+        assert!(event.waited > Duration::from_millis(500));
+        assert!(event.waited <= Duration::from_secs(1));
+        assert!(event.at >= at);
+
+        at = event.at + event.waited;
+    }
+
+    assert_eq!(report.throttles[0].key_fingerprint, report.throttles[1].key_fingerprint);
+    assert!(report.to_string().contains("throttled: 2 times for "));
+}
+
+#[test]
+fn unthrottled_batches_report_no_throttles() {
+    let server = metadata_server();
+    let mut batch_query = BatchQuery::new();
+    batch_query.query(query(&server, "WIKI", "key")).limit(2, 1);
+
+    let (results, report) = batch_query.run_with_report();
+    assert_eq!(results.count(), 1);
+    assert!(report.get().throttles.is_empty());
+}
+
+#[test]
+fn offset_counts_as_previous_calls() {
+    let server = metadata_server();
+    let start = Instant::now();
+
+    let results: Vec<_> = {
+        let mut batch_query = BatchQuery::new();
+
+        batch_query
+            .query(query(&server, "WIKI", "key"))
+            .limit(2, 1)
+            .offset(2);
+
+        batch_query.run().collect()
+    };
+
+    assert!(results[0].is_ok());
+    assert!(start.elapsed() >= Duration::from_secs(1));
+}
+
+fn data_server() -> MockServer {
+    MockServer::start(|request| {
+        sleep(Duration::from_millis(20));
+
+        match &request.path[..] {
+            "/api/v3/datasets/WIKI/AAPL/data.csv" | "/api/v3/datasets/WIKI/MSFT/data.csv" => {
+                Response::csv(DATA)
+            },
+
+            "/api/v3/datasets/FRED/GDP/data.csv" => {
+                Response::csv(&DATA[..DATA.find('\n').unwrap() + 1])
+            },
+
+            _ => Response::not_found(),
+        }
+    })
+}
+
+fn data_batch(server: &MockServer) -> BatchQuery<DataQuery, Vec<(String, f64, f64)>> {
+    let mut batch_query = BatchQuery::new();
+
+    for &(database_code, dataset_code) in &[("WIKI", "AAPL"),
+                                            ("FRED", "GDP"),
+                                            ("WIKI", "MSFT"),
+                                            ("FRED", "MISSING")]
+    {
+        let mut query = DataQuery::new(database_code, dataset_code);
+        query.base_url(server.url()).api_key("key");
+        batch_query.query(query);
+    }
+
+    batch_query.threads(2);
+    batch_query
+}
+
+#[test]
+fn map_rows_in_workers() {
+    let server = data_server();
+
+    let results: Vec<_> = {
+        data_batch(&server)
+            .map_rows(|code, rows| {
+                let scale = if code.database_code == "WIKI" { 100.0 } else { 1.0 };
+
+                let rows: Vec<(String, f64)> = {
+                    rows.into_iter().map(|(date, x, _)| (date, x * scale)).collect()
+                };
+
+                (format!("{}/{}", code.database_code, code.dataset_code), rows)
+            })
+            .run()
+            .collect()
+    };
+
+    let rows: Vec<(String, f64, f64)> = DataQuery::new("WIKI", "AAPL").decode(DATA.as_bytes())
+        .unwrap();
+
+    let (ref code, ref scaled) = *results[0].as_ref().unwrap();
+    assert_eq!(code, "WIKI/AAPL");
+    assert_eq!(scaled.len(), rows.len());
+    assert_eq!(scaled[0], (rows[0].0.clone(), rows[0].1 * 100.0));
+
+    assert_eq!(results[1].as_ref().unwrap().0, "FRED/GDP");
+    assert_eq!(results[2].as_ref().unwrap().0, "WIKI/MSFT");
+    assert!(matches!(results[3], Err(Error::NotFound { .. })));
+}
+
+#[test]
+fn panicking_map_rows() {
+    let server = data_server();
+
+    let (iterator, handle) = {
+        let mut batch_query = data_batch(&server);
+        batch_query.threads(1);
+
+        batch_query
+            .map_rows(|code, rows| {
+                if code.dataset_code == "AAPL" {
+                    panic!("no exchange rate for {}", code.dataset_code);
+                }
+
+                rows.len()
+            })
+            .run_with_report()
+    };
+
+    let results: Vec<Result<usize>> = iterator.collect();
+
+    let lines = DATA.lines().count();
+
+    // The single worker carried on with the queries which followed.
+    assert_eq!(results[0], Err(Error::TransformFailed("no exchange rate for AAPL".to_string())));
+    assert_eq!(results[1], Ok(1));
+    assert_eq!(results[2], Ok(lines));
+    assert!(matches!(results[3], Err(Error::NotFound { .. })));
+    assert_eq!(handle.get().errors["TransformFailed"], 1);
+}
+
+#[test]
+fn panicking_workers() {
+    let server = metadata_server();
+
+    // Two calls per key, on the same worker: the second waits for the limit.
+    let keys = ["KEY0", "KEY1", "KEY0", "KEY1", "KEY2", "KEY3"];
+    let mut batch_query = BatchQuery::new();
+
+    for (code, key) in ["WIKI", "FRED", "JODI", "EIA", "ICE", "CME"].iter().zip(&keys) {
+        batch_query.query(query(&server, code, key));
+    }
+
+    let progress = Arc::new(Mutex::new(vec![]));
+    let events = progress.clone();
+
+    batch_query
+        .threads(2)
+        .limit(1, 60)
+        .on_throttle(|_| panic!("{} throttled", std::thread::current().name().unwrap()))
+        .on_progress(move |p| {
+            let name = std::thread::current().name().map(String::from);
+            events.lock().unwrap().push((p.worker, name));
+        });
+
+    let (iterator, handle) = batch_query.run_with_report();
+
+    let results: Vec<Result<DatabaseMetadata>> = iterator.collect();
+
+    let internal = |worker: usize| Error::BatchInternal {
+        worker,
+        message: format!("quandl-batch-{} throttled", worker),
+    };
+
+    // Each worker carried on with the queries which followed its panic.
+    assert!(results[0].is_ok() && results[1].is_ok());
+    assert_eq!(results[2].as_ref().unwrap_err(), &internal(0));
+    assert_eq!(results[3].as_ref().unwrap_err(), &internal(1));
+    assert!(results[4].is_ok() && results[5].is_ok());
+
+    assert_eq!(internal(1).to_string(),
+               "batch worker quandl-batch-1 panicked with 'quandl-batch-1 throttled'.");
+
+    let report = handle.get();
+    assert_eq!(report.errors["BatchInternal"], 2);
+    assert_eq!(report.databases.values().map(|stats| stats.calls).sum::<usize>(), 4);
+
+    let mut events = progress.lock().unwrap().clone();
+    events.sort();
+
+    assert_eq!(events, vec![(0, Some("quandl-batch-0".to_string())),
+                            (0, Some("quandl-batch-0".to_string())),
+                            (1, Some("quandl-batch-1".to_string())),
+                            (1, Some("quandl-batch-1".to_string()))]);
+}
+
+#[test]
+fn report_groups_by_database() {
+    let server = data_server();
+    let not_found = Response::not_found().body.len() as u64;
+    let first_line = DATA.find('\n').unwrap() as u64 + 1;
+
+    let (iterator, handle) = data_batch(&server).run_with_report();
+    let results: Vec<_> = iterator.collect();
+
+    assert_eq!(results.len(), 4);
+    assert!(results[3].is_err());
+
+    let report = handle.get();
+    assert_eq!(report.databases.len(), 2);
+
+    let wiki = report.databases["WIKI"];
+    assert_eq!(wiki.calls, 2);
+    assert_eq!(wiki.errors, 0);
+    assert_eq!(wiki.bytes, 2 * DATA.len() as u64);
+    assert!(wiki.total_duration >= Duration::from_millis(40));
+
+    let fred = report.databases["FRED"];
+    assert_eq!(fred.calls, 2);
+    assert_eq!(fred.errors, 1);
+    assert_eq!(fred.bytes, first_line + not_found);
+    assert!(fred.total_duration >= Duration::from_millis(40));
+
+    let total = report.total();
+    assert_eq!(total.calls, 4);
+    assert_eq!(total.errors, 1);
+    assert_eq!(total.bytes, wiki.bytes + fred.bytes);
+    assert_eq!(total.total_duration, wiki.total_duration + fred.total_duration);
+}
+
+#[test]
+fn report_breaks_errors_down() {
+    let server = data_server();
+
+    let (iterator, handle) = data_batch(&server).run_with_report();
+    assert_eq!(iterator.count(), 4);
+
+    let report = handle.get();
+    assert_eq!(report.queries, 4);
+    assert_eq!(report.skipped, 0);
+    assert_eq!(report.errors.iter().collect::<Vec<_>>(), vec![(&"NotFound".to_string(), &1)]);
+    assert_eq!(report.quandl_errors.iter().collect::<Vec<_>>(), vec![(&"QECx02".to_string(), &1)]);
+    assert_eq!(report.keys.iter().collect::<Vec<_>>(), vec![(&"****".to_string(), &4)]);
+    assert!(report.elapsed >= Duration::from_millis(40));
+}
+
+#[test]
+fn report_from_exhausted_iterator() {
+    let server = data_server();
+
+    let mut iterator = data_batch(&server).run();
+    while iterator.next().is_some() {}
+
+    let report = iterator.report();
+    assert_eq!(report.total().calls, 4);
+    assert_eq!(report.databases["WIKI"].bytes, 2 * DATA.len() as u64);
+}
+
+#[test]
+fn report_of_empty_batch() {
+    let batch_query: BatchQuery<DataQuery, Vec<(String, f64)>> = BatchQuery::new();
+    let (iterator, handle) = batch_query.run_with_report();
+
+    assert_eq!(iterator.count(), 0);
+
+    let report = handle.get();
+    assert!(report.started_at.is_some());
+    assert_eq!(report, BatchReport {
+        threads: 1,
+        thread_limit: Some(ThreadLimit::Queries),
+        started_at: report.started_at,
+        ..BatchReport::default()
+    });
+}
+
+/// Server answering metadata queries after a delay given by the database code, e.g. `SLOW400` is
+/// answered after 400ms.
+///
+fn delayed_server() -> MockServer {
+    MockServer::start(|request| {
+        let code = request.path.trim_start_matches("/api/v3/databases/").trim_end_matches(".json");
+        let delay: u64 = code.trim_start_matches(char::is_alphabetic).parse().unwrap_or(0);
+
+        sleep(Duration::from_millis(delay));
+        Response::json(DATABASE_METADATA.replace("\"WIKI\"", &format!("\"{}\"", code)))
+    })
+}
+
+fn delayed_batch(server: &MockServer,
+                 codes: &[&str],
+                 scheduling: SchedulingStrategy<DatabaseMetadataQuery>)
+    -> BatchQuery<DatabaseMetadataQuery, DatabaseMetadata>
+{
+    let mut batch_query = BatchQuery::new();
+
+    for code in codes {
+        batch_query.query(query(server, code, "key"));
+    }
+
+    batch_query.threads(3).concurrent_calls().scheduling(scheduling);
+    batch_query
+}
+
+fn first_worker(_: &DatabaseMetadataQuery, _: usize) -> usize {
+    0
+}
+
+fn by_code_length(query: &DatabaseMetadataQuery, threads: usize) -> usize {
+    query.database_code().len() % threads
+}
+
+#[test]
+fn strategies_yield_identical_results() {
+    let server = delayed_server();
+    let mut rng = 0x9e37_79b9_7f4a_7c15_u64;
+
+    for _ in 0..3 {
+        let codes: Vec<String> = {
+            (0..8).map(|index| {
+                rng ^= rng << 13;
+                rng ^= rng >> 7;
+                rng ^= rng << 17;
+
+                format!("{}{}", ["A", "BB", "CCC"][index % 3], rng % 30)
+            }).collect()
+        };
+
+        let codes: Vec<&str> = codes.iter().map(|code| &code[..]).collect();
+
+        let results: Vec<Vec<String>> = {
+            [SchedulingStrategy::RoundRobinStatic,
+             SchedulingStrategy::WorkStealing,
+             SchedulingStrategy::Custom(first_worker),
+             SchedulingStrategy::Custom(by_code_length)]
+                .iter()
+                .map(|&scheduling| {
+                    delayed_batch(&server, &codes, scheduling).run()
+                        .map(|result| result.unwrap().database_code)
+                        .collect()
+                })
+                .collect()
+        };
+
+        for result in &results {
+            assert_eq!(result, &codes);
+        }
+    }
+}
+
+#[test]
+fn work_stealing_balances_skewed_batches() {
+    let server = delayed_server();
+    let codes = ["SLOW600", "FAST0", "FAST0", "SLOW600", "FAST0", "FAST0", "SLOW600"];
+
+    let elapsed = |scheduling| {
+        let start = Instant::now();
+        let results = delayed_batch(&server, &codes, scheduling).run();
+
+        assert_eq!(results.filter(|result| result.is_ok()).count(), codes.len());
+        start.elapsed()
+    };
+
+    // Round robin assigns every slow query to the first thread, so they run one after the other
+    // (at least 1.8s) whereas they run in parallel when stealing work (about 0.6s).
+    let round_robin = elapsed(SchedulingStrategy::RoundRobinStatic);
+    let work_stealing = elapsed(SchedulingStrategy::WorkStealing);
+
+    assert!(round_robin >= Duration::from_millis(1800));
+    assert!(work_stealing + Duration::from_millis(600) < round_robin,
+            "round robin: {:?}, work stealing: {:?}", round_robin, work_stealing);
+}
+
+#[test]
+fn keyless_queries_are_skipped_with_every_strategy() {
+    let server = metadata_server();
+
+    for &scheduling in &[SchedulingStrategy::RoundRobinStatic, SchedulingStrategy::WorkStealing] {
+        let mut batch_query = BatchQuery::new();
+
+        let mut keyless = DatabaseMetadataQuery::new("FRED");
+        keyless.base_url(server.url());
+
+        batch_query
+            .query(query(&server, "WIKI", "key"))
+            .query(keyless)
+            .query(query(&server, "JODI", "key"))
+            .threads(2)
+            .scheduling(scheduling);
+
+        let results: Vec<_> = {
+            batch_query.run().map(|result| result.unwrap().database_code).collect()
+        };
+
+        assert_eq!(results, ["WIKI", "JODI"]);
+    }
+}
+
+fn keyless(server: &MockServer, code: &str) -> DatabaseMetadataQuery {
+    let mut query = DatabaseMetadataQuery::new(code);
+    query.base_url(server.url());
+    query
+}
+
+#[test]
+fn anonymous_queries_are_limited_by_default() {
+    let server = metadata_server();
+    let mut batch_query = BatchQuery::new();
+
+    // The limits of keyed queries do not apply to anonymous ones.
+    batch_query.query(query(&server, "WIKI", "key")).limit(1, 600).anonymous().threads(1);
+
+    for _ in 0..21 {
+        batch_query.query(keyless(&server, "FRED"));
+    }
+
+    let mut results = batch_query.run();
+    assert_eq!(results.next().unwrap().unwrap().database_code, "WIKI");
+
+    for _ in 0..20 {
+        assert_eq!(results.next().unwrap().unwrap().database_code, "FRED");
+    }
+
+    // The 21st anonymous call has to wait for 10 minutes.
+    sleep(Duration::from_millis(300));
+    assert_eq!(server.hits(), 21);
+}
+
+#[test]
+fn anonymous_limits_can_be_overridden() {
+    let server = metadata_server();
+    let mut batch_query = BatchQuery::new();
+
+    batch_query
+        .query(keyless(&server, "FRED"))
+        .query(keyless(&server, "JODI"))
+        .query(query(&server, "WIKI", "key"))
+        .query(keyless(&server, "EIA"))
+        .anonymous()
+        .anonymous_limits(&[(2, 1)])
+        .threads(3);
+
+    let start = Instant::now();
+    let results: Vec<_> = batch_query.run().map(|x| x.unwrap().database_code).collect();
+
+    assert_eq!(results, ["FRED", "JODI", "WIKI", "EIA"]);
+    assert!(start.elapsed() >= Duration::from_secs(1), "{:?}", start.elapsed());
+
+    // Unlimited.
+    let mut batch_query = BatchQuery::new();
+
+    for _ in 0..30 {
+        batch_query.query(keyless(&server, "FRED"));
+    }
+
+    batch_query.anonymous().anonymous_limits(&[]);
+    assert_eq!(batch_query.run().filter(|x| x.is_ok()).count(), 30);
+}
+
+#[test]
+#[should_panic(expected = "limit: 0")]
+fn anonymous_limits_must_allow_calls() {
+    BatchQuery::<DatabaseMetadataQuery, DatabaseMetadata>::new().anonymous_limits(&[(0, 600)]);
+}
+
+fn out_of_range(_: &DatabaseMetadataQuery, threads: usize) -> usize {
+    threads
+}
+
+#[test]
+#[should_panic(expected = "worker: 2, threads: 2")]
+fn custom_strategy_must_stay_in_range() {
+    let server = metadata_server();
+    let mut batch_query = BatchQuery::new();
+
+    batch_query
+        .query(query(&server, "WIKI", "key"))
+        .threads(2)
+        .scheduling(SchedulingStrategy::Custom(out_of_range));
+
+    batch_query.run();
+}
+
+fn assert_send<T: Send>() {}
+
+#[test]
+fn iterators_are_send() {
+    assert_send::<BatchQueryIterator<Result<Vec<(String, f64)>>>>();
+    assert_send::<BatchQueryIterator<Result<Dataset<(String, f64)>>>>();
+    assert_send::<BatchQueryIterator<Result<DatabaseMetadata>>>();
+    assert_send::<BatchQueryIterator<Result<DatasetMetadata>>>();
+    assert_send::<BatchQueryIterator<Result<DatabaseList>>>();
+    assert_send::<BatchQueryIterator<Result<DatasetList>>>();
+    assert_send::<BatchQueryIterator<Result<Vec<Code>>>>();
+    assert_send::<Error>();
+
+    assert_send::<BatchQuery<DataQuery, Vec<(String, f64)>>>();
+    assert_send::<BatchQuery<DatabaseMetadataQuery, DatabaseMetadata>>();
+    assert_send::<ReportHandle>();
+}
+
+#[test]
+fn results_consumed_on_another_thread() {
+    let server = metadata_server();
+
+    for &scheduling in &[SchedulingStrategy::RoundRobinStatic, SchedulingStrategy::WorkStealing] {
+        let mut batch_query = BatchQuery::new();
+
+        for code in &["WIKI", "FRED", "JODI"] {
+            batch_query.query(query(&server, code, "key"));
+        }
+
+        batch_query.threads(2).scheduling(scheduling);
+
+        let iterator = batch_query.run();
+
+        let consumer = spawn(move || {
+            iterator.map(|result| result.unwrap().database_code).collect::<Vec<_>>()
+        });
+
+        assert_eq!(consumer.join().unwrap(), ["WIKI", "FRED", "JODI"]);
+    }
+}
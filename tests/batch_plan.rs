@@ -0,0 +1,289 @@
+#![cfg(feature = "batch")]
+
+extern crate quandl_v3;
+extern crate serde_json;
+
+mod common;
+
+use std::fs;
+use std::time::Duration;
+
+use quandl_v3::config::Config;
+use quandl_v3::prelude::*;
+
+use common::{MockServer, Response};
+
+static DATABASE_METADATA: &str = include_str!("fixtures/database_metadata.json");
+
+type Batch = BatchQuery<DatabaseMetadataQuery, DatabaseMetadata>;
+
+fn secs(n: u64) -> Duration {
+    Duration::from_secs(n)
+}
+
+fn query(code: &str, api_key: Option<&str>) -> DatabaseMetadataQuery {
+    let mut query = DatabaseMetadataQuery::new(code);
+
+    if let Some(api_key) = api_key {
+        query.api_key(api_key);
+    }
+
+    query
+}
+
+/// Batch of `n` queries with `api_key`, on a single thread.
+///
+fn batch(n: usize, api_key: Option<&str>) -> Batch {
+    let mut batch_query = BatchQuery::new();
+
+    for index in 0..n {
+        batch_query.query(query(&format!("DB{}", index), api_key));
+    }
+
+    batch_query.threads(1);
+    batch_query
+}
+
+fn estimate(threads: usize, duration: u64, throttles: usize, waited: u64) -> ThreadEstimate {
+    ThreadEstimate { threads, duration: secs(duration), throttles, waited: secs(waited) }
+}
+
+#[test]
+fn nothing_is_sent() {
+    let server = MockServer::start(|_| Response::not_found());
+    let mut batch_query = Batch::new();
+    let mut query = query("WIKI", Some("secret-key"));
+    query.base_url(server.url());
+
+    batch_query.query(query).threads(1);
+    let plan = batch_query.plan();
+
+    assert_eq!(server.hits(), 0);
+    assert_eq!(plan.queries, 1);
+    assert_eq!(plan.call_duration, secs(1));
+    assert!(!plan.call_duration_measured);
+    assert_eq!(plan.bytes, None);
+    assert_eq!(plan.estimates, vec![estimate(1, 1, 0, 0)]);
+}
+
+#[test]
+fn free_tier() {
+    let mut batch_query = batch(10, Some("secret-key"));
+    batch_query.limit(300, 10).limit(2_000, 600).limit(50_000, 86_400).offset(295);
+
+    // 5 calls until the 300 calls within 10 seconds are reached, the other 5 from 10 seconds on.
+    assert_eq!(batch_query.plan().estimate(), Some(&estimate(1, 15, 1, 5)));
+}
+
+#[test]
+fn premium_tier() {
+    let mut batch_query = batch(5_001, Some("secret-key"));
+    batch_query.limit(5_000, 600).limit(720_000, 86_400).threads(16).concurrent_calls();
+
+    let plan = batch_query.plan();
+
+    // 16 calls a second until the 5,000 calls within 10 minutes are reached after 312 seconds,
+    // the last one waiting for the first to be 10 minutes old.
+    assert_eq!(plan.estimates, vec![
+        estimate(1, 5_001, 0, 0),
+        estimate(2, 2_501, 0, 0),
+        estimate(4, 1_251, 0, 0),
+        estimate(8, 626, 0, 0),
+        estimate(16, 601, 1, 288),
+    ]);
+
+    assert_eq!(plan.estimate(), plan.estimates.last());
+}
+
+#[test]
+fn anonymous_tier() {
+    let mut batch_query = batch(60, None);
+    batch_query.anonymous();
+
+    let plan = batch_query.plan();
+
+    // 20 calls every 10 minutes, up to 50 a day.
+    assert_eq!(plan.estimate(), Some(&estimate(1, 86_410, 3, 580 + 580 + 85_190)));
+    assert_eq!(plan.keys["anonymous"], 60);
+}
+
+#[test]
+fn keys_are_used_once_at_a_time() {
+    let mut batch_query = batch(4, Some("secret-key"));
+    batch_query.threads(2);
+
+    assert_eq!(batch_query.plan().estimate(), Some(&estimate(2, 4, 0, 0)));
+
+    batch_query.concurrent_calls();
+    assert_eq!(batch_query.plan().estimate(), Some(&estimate(2, 2, 0, 0)));
+}
+
+#[test]
+fn keys_and_pool() {
+    let mut batch_query = batch(3, None);
+
+    batch_query
+        .query(query("WIKI", Some("first-secret-key")))
+        .key_profile(KeyProfile { limits: vec![(1, 60)], ..KeyProfile::new("pool-secret-key") })
+        .threads(2);
+
+    let plan = batch_query.plan();
+
+    assert_eq!(plan.queries, 4);
+    assert_eq!(plan.keyless, 0);
+    assert_eq!(plan.keys["****-key"], 4);
+
+    // The pool key's own limit: one call a minute.
+    assert_eq!(plan.estimates, vec![estimate(1, 122, 2, 118), estimate(2, 121, 2, 118)]);
+}
+
+#[test]
+fn keyless_and_skipped() {
+    let path = std::env::temp_dir().join(format!("quandl-v3-plan-{}.jsonl", std::process::id()));
+    let url = query("DB0", None).url();
+    fs::write(&path, format!("{}\n", serde_json::json!({ "url": url }))).unwrap();
+
+    let mut batch_query = batch(3, Some("secret-key"));
+    batch_query.query(query("WIKI", None)).manifest(&path);
+
+    let plan = batch_query.plan();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!((plan.queries, plan.skipped, plan.keyless), (3, 1, 1));
+    assert_eq!(plan.estimate(), Some(&estimate(1, 2, 0, 0)));
+
+    assert_eq!(plan.to_string(), "\
+queries: 3 (2 to send, 1 skipped, 1 without an API key)
+keys: ****-key 2
+threads: 1 (set explicitly)
+calls: 1.00s each (assumed)
+1 thread: 2.00s, throttled 0 times for 0.00s
+");
+}
+
+#[test]
+fn thread_sizing() {
+    use ThreadLimit::*;
+
+    let cases = [
+        // (cpus, keys, queries, concurrent calls), expected
+        ((8, 1, 100, false), (1, Keys)),
+        ((8, 1, 100, true), (8, Cpus)),
+        ((8, 3, 100, false), (3, Keys)),
+        ((2, 3, 100, false), (2, Cpus)),
+        ((8, 3, 2, false), (2, Queries)),
+        ((8, 2, 2, false), (2, Queries)),
+        ((4, 4, 100, false), (4, Keys)),
+        ((1, 1, 1, false), (1, Queries)),
+        ((8, 1, 5, true), (5, Queries)),
+        ((8, 0, 0, false), (1, Queries)),
+        ((16, 50, 1_000, false), (16, Cpus)),
+    ];
+
+    for &((cpus, keys, queries, concurrent_calls), expected) in &cases {
+        assert_eq!(default_threads(cpus, keys, queries, concurrent_calls), expected,
+                   "cpus: {}, keys: {}, queries: {}, concurrent calls: {}",
+                   cpus, keys, queries, concurrent_calls);
+    }
+}
+
+#[test]
+fn thread_count_of_plans_and_runs() {
+    let server = MockServer::start(|_| Response::json(DATABASE_METADATA));
+
+    let batch = || {
+        let mut batch_query = Batch::new();
+
+        for code in &["WIKI", "FRED", "ICE", "EOD"] {
+            let mut query = query(code, Some("secret-key"));
+            query.base_url(server.url());
+            batch_query.query(query);
+        }
+
+        batch_query
+    };
+
+    // A single key: whatever the number of cores, one thread does.
+    let plan = batch().plan();
+    assert_eq!((plan.threads, plan.thread_limit), (1, Some(ThreadLimit::Keys)));
+    assert_eq!(plan.estimates, vec![estimate(1, 4, 0, 0)]);
+    assert!(plan.to_string().contains("\nthreads: 1 (one per API key)\n"), "{}", plan);
+
+    let (iterator, report) = batch().run_with_report();
+    assert!(iterator.into_iter().all(|result| result.is_ok()));
+    assert_eq!((report.get().threads, report.get().thread_limit), (1, Some(ThreadLimit::Keys)));
+
+    let plan = batch().concurrent_calls().plan();
+    assert!(plan.threads <= 4, "{}", plan);
+    assert!(matches!(plan.thread_limit, Some(ThreadLimit::Queries) | Some(ThreadLimit::Cpus)));
+
+    // The setter always wins, but in the reproducibility mode.
+    let mut batch_query = batch();
+    batch_query.threads(3);
+
+    let plan = batch_query.plan();
+    assert_eq!((plan.threads, plan.thread_limit), (3, Some(ThreadLimit::Explicit)));
+    assert_eq!(plan.estimates.last(), Some(&estimate(3, 4, 0, 0)));
+
+    let _guard = Config::override_scope(Config::deterministic(7));
+    let plan = batch_query.plan();
+    assert_eq!((plan.threads, plan.thread_limit), (1, Some(ThreadLimit::Deterministic)));
+}
+
+#[test]
+fn missing_manifest_is_not_created() {
+    let path = std::env::temp_dir().join(format!("quandl-v3-plan-missing-{}.jsonl",
+                                                 std::process::id()));
+
+    let mut batch_query = batch(2, Some("secret-key"));
+    batch_query.manifest(&path);
+
+    assert_eq!(batch_query.plan().skipped, 0);
+    assert!(!path.exists());
+}
+
+#[test]
+fn probe() {
+    let server = MockServer::start(|request| {
+        match &request.path[..] {
+            "/api/v3/databases/WIKI.json" => Response::json("{}"),
+            "/api/v3/databases/GOOG.json" => Response::new(405),
+            _ => Response::not_found(),
+        }
+    });
+
+    let mut batch_query = Batch::new();
+
+    for code in &["WIKI", "GOOG", "WIKI"] {
+        let mut query = query(code, Some("secret-key"));
+        query.base_url(server.url());
+        batch_query.query(query);
+    }
+
+    let plan = batch_query.threads(3).concurrent_calls().plan_with_probe();
+
+    assert_eq!(server.hits(), 3);
+    assert!(server.requests().iter().all(|x| x.method == "HEAD"));
+    assert_eq!(plan.bytes, Some(ByteEstimate { bytes: 4, known: 2, unknown: 1 }));
+    assert!(plan.call_duration_measured);
+    assert!(plan.call_duration < secs(1));
+    assert_eq!(plan.estimates.iter().map(|x| x.threads).collect::<Vec<_>>(), vec![1, 2, 3]);
+    assert_eq!(plan.estimate().map(|x| x.duration), Some(plan.call_duration));
+    assert!(plan.to_string().contains("(measured)\n"));
+    assert!(plan.to_string().ends_with("size: 4 B (2 known, 1 unknown)\n"));
+}
+
+#[test]
+fn serialization() {
+    let mut batch_query = batch(1, Some("secret-key"));
+    batch_query.threads(2);
+
+    let value = serde_json::to_value(batch_query.plan()).unwrap();
+
+    assert_eq!(value["queries"], 1);
+    assert_eq!(value["keys"]["****-key"], 1);
+    assert_eq!(value["call_duration"]["secs"], 1);
+    assert_eq!(value["estimates"][1]["threads"], 2);
+    assert_eq!(value["estimates"][1]["duration"]["secs"], 1);
+    assert_eq!(value["bytes"], serde_json::Value::Null);
+}
@@ -0,0 +1,191 @@
+#![cfg(feature = "batch")]
+
+extern crate quandl_v3;
+extern crate serde_json;
+
+use std::time::Duration;
+
+use quandl_v3::prelude::*;
+
+fn stats(calls: usize, bytes: u64, errors: usize) -> DatabaseStats {
+    let total_duration = Duration::from_millis(100 * calls as u64);
+    DatabaseStats { calls, bytes, total_duration, errors }
+}
+
+/// Report of an overnight run: one dataset was already done, two queries hit a missing dataset
+/// and one timed out.
+///
+fn report() -> BatchReport {
+    let mut report = BatchReport::default();
+
+    report.databases.insert("WIKI".to_string(), stats(8, 1_150_000, 1));
+    report.databases.insert("FRED".to_string(), stats(3, 50_000, 2));
+    report.queries = 12;
+    report.skipped = 1;
+    report.threads = 2;
+    report.thread_limit = Some(ThreadLimit::Keys);
+    report.errors.insert("ApiCallFailed".to_string(), 2);
+    report.errors.insert("DownloadFailed".to_string(), 1);
+    report.quandl_errors.insert("QECx02".to_string(), 2);
+    report.keys.insert("****wxyz".to_string(), 7);
+    report.keys.insert("****".to_string(), 4);
+    report.started_at = quandl_v3::calendar::parse_timestamp_utc("2016-03-01T16:47:01.686-05:00");
+    report.elapsed = Duration::from_millis(2500);
+    report
+}
+
+#[test]
+fn summary() {
+    assert_eq!(report().to_string(), "\
+queries: 12 (8 succeeded, 3 failed, 1 skipped)
+calls: 11 in 2.50s (4.40 calls/s)
+threads: 2 (one per API key)
+received: 1.2 MB (480.0 kB/s)
+errors: ApiCallFailed 2, DownloadFailed 1
+quandl errors: QECx02 2
+keys: **** 4, ****wxyz 7
+");
+}
+
+#[test]
+fn summary_without_errors() {
+    let mut report = BatchReport::default();
+    report.databases.insert("WIKI".to_string(), stats(2, 640, 0));
+    report.queries = 2;
+    report.keys.insert("****".to_string(), 2);
+    report.elapsed = Duration::from_millis(500);
+
+    assert_eq!(report.to_string(), "\
+queries: 2 (2 succeeded, 0 failed, 0 skipped)
+calls: 2 in 0.50s (4.00 calls/s)
+received: 640 B (1.3 kB/s)
+errors: none
+keys: **** 2
+");
+}
+
+#[test]
+fn summary_with_throttles() {
+    let mut report = report();
+
+    for &(key_fingerprint, millis) in &[("1a2b3c4d", 750), ("1a2b3c4d", 1_000)] {
+        report.throttles.push(ThrottleEvent {
+            key_fingerprint: key_fingerprint.to_string(),
+            waited: Duration::from_millis(millis),
+            threshold_hit: (300, Duration::from_secs(10)),
+            at: Duration::from_millis(500),
+            worker: 1,
+        });
+    }
+
+    let summary = report.to_string();
+    assert!(summary.ends_with("keys: **** 4, ****wxyz 7\nthrottled: 2 times for 1.75s\n"));
+
+    let value = serde_json::to_value(report).unwrap();
+
+    assert_eq!(value["throttles"][1], serde_json::json!({
+        "key_fingerprint": "1a2b3c4d",
+        "waited": {"secs": 1, "nanos": 0},
+        "threshold_hit": [300, {"secs": 10, "nanos": 0}],
+        "at": {"secs": 0, "nanos": 500_000_000},
+        "worker": 1,
+    }));
+}
+
+#[test]
+fn summary_of_empty_batch() {
+    assert_eq!(BatchReport::default().to_string(), "\
+queries: 0 (0 succeeded, 0 failed, 0 skipped)
+calls: 0 in 0.00s (0.00 calls/s)
+received: 0 B (0 B/s)
+errors: none
+");
+}
+
+#[test]
+fn serialized() {
+    let value = serde_json::to_value(report()).unwrap();
+
+    assert_eq!(value["queries"], 12);
+    assert_eq!(value["skipped"], 1);
+    assert_eq!(value["threads"], 2);
+    assert_eq!(value["thread_limit"], "keys");
+    assert_eq!(value["errors"], serde_json::json!({"ApiCallFailed": 2, "DownloadFailed": 1}));
+    assert_eq!(value["quandl_errors"], serde_json::json!({"QECx02": 2}));
+    assert_eq!(value["keys"], serde_json::json!({"****": 4, "****wxyz": 7}));
+    assert_eq!(value["elapsed"], serde_json::json!({"secs": 2, "nanos": 500_000_000}));
+    assert_eq!(value["started_at"], "2016-03-01T21:47:01.686Z");
+
+    assert_eq!(value["databases"]["FRED"], serde_json::json!({
+        "calls": 3,
+        "bytes": 50_000,
+        "total_duration": {"secs": 0, "nanos": 300_000_000},
+        "errors": 2,
+    }));
+    let value = serde_json::to_value(BatchReport::default()).unwrap();
+    assert_eq!(value["started_at"], serde_json::Value::Null);
+    assert_eq!(value["thread_limit"], serde_json::Value::Null);
+}
+
+#[test]
+fn summary_with_latencies() {
+    let mut report = report();
+
+    for &millis in &[30, 80, 90, 150, 180, 190, 240, 480, 1_840] {
+        report.latency.record(Duration::from_millis(millis));
+    }
+
+    report.slowest.push(SlowQuery {
+        database_code: "WIKI".to_string(),
+        dataset_code: "AAPL".to_string(),
+        duration: Duration::from_millis(1_840),
+        bytes: 640_200,
+    });
+
+    report.slowest.push(SlowQuery {
+        database_code: "FRED".to_string(),
+        dataset_code: String::new(),
+        duration: Duration::from_millis(480),
+        bytes: 3_100,
+    });
+
+    let summary = report.to_string();
+
+    assert!(summary.ends_with("keys: **** 4, ****wxyz 7\n\
+                               latency: p50 200ms, p90 1.84s, p99 1.84s, max 1.84s\n\
+                               slowest: WIKI/AAPL 1.84s (640.2 kB), FRED 480ms (3.1 kB)\n"),
+            "{}",
+            summary);
+
+    let value = serde_json::to_value(&report).unwrap();
+
+    assert_eq!(value["latency"]["buckets"][6], serde_json::json!({
+        "upper_bound": {"secs": 0, "nanos": 100_000_000},
+        "count": 2,
+    }));
+
+    assert_eq!(value["latency"]["buckets"][16]["upper_bound"], serde_json::Value::Null);
+    assert_eq!(value["latency"]["max"], serde_json::json!({"secs": 1, "nanos": 840_000_000}));
+    assert_eq!(value["slowest"][1]["bytes"], 3_100);
+}
+
+#[test]
+fn quantiles() {
+    let mut latency = LatencyHistogram::default();
+    assert_eq!((latency.quantile(0.5), latency.max()), (None, None));
+
+    latency.record(Duration::from_millis(3));
+    assert_eq!(latency.quantile(0.0), Some(Duration::from_millis(3)));
+
+    for _ in 0..98 {
+        latency.record(Duration::from_millis(15));
+    }
+
+    latency.record(Duration::from_secs(150));
+
+    assert_eq!(latency.count(), 100);
+    assert_eq!(latency.quantile(0.01), Some(Duration::from_millis(5)));
+    assert_eq!(latency.quantile(0.5), Some(Duration::from_millis(20)));
+    assert_eq!(latency.quantile(0.99), Some(Duration::from_millis(20)));
+    assert_eq!(latency.quantile(1.0), Some(Duration::from_secs(150)));
+}
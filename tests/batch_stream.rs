@@ -0,0 +1,214 @@
+#![cfg(all(feature = "batch", feature = "async"))]
+
+extern crate quandl_v3;
+extern crate tokio;
+
+mod common;
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tokio::stream::StreamExt;
+
+use quandl_v3::prelude::*;
+
+use common::{MockServer, Response};
+
+static DATABASE_METADATA: &str = include_str!("fixtures/database_metadata.json");
+
+fn metadata_server() -> MockServer {
+    MockServer::start(|request| {
+        let code = request.path.trim_start_matches("/api/v3/databases/").trim_end_matches(".json");
+        Response::json(DATABASE_METADATA.replace("\"WIKI\"", &format!("\"{}\"", code)))
+    })
+}
+
+fn batch(server: &MockServer, codes: &[&str], key: Option<&str>)
+    -> BatchQuery<DatabaseMetadataQuery, DatabaseMetadata>
+{
+    let mut batch_query = BatchQuery::new();
+
+    for code in codes {
+        let mut query = DatabaseMetadataQuery::new(code);
+        query.base_url(server.url());
+
+        if let Some(key) = key {
+            query.api_key(key);
+        }
+
+        batch_query.query(query);
+    }
+
+    batch_query
+}
+
+/// Run `f` on a runtime whose clock is paused, so the waits take no real time.
+///
+fn paused<F: std::future::Future>(f: F) -> F::Output {
+    let mut runtime = {
+        tokio::runtime::Builder::new().basic_scheduler().enable_time().build().unwrap()
+    };
+
+    runtime.block_on(async {
+        tokio::time::pause();
+        f.await
+    })
+}
+
+fn now() -> Instant {
+    tokio::time::Instant::now().into_std()
+}
+
+#[test]
+fn results_only_by_default() {
+    let server = metadata_server();
+    let mut batch_query = batch(&server, &["WIKI", "FRED", "JODI"], Some("key"));
+    batch_query.limit(2, 600);
+
+    let (codes, elapsed) = paused(async {
+        let start = now();
+        let codes: Vec<_> = {
+            batch_query.stream().map(|result| result.unwrap().database_code).collect().await
+        };
+
+        (codes, now() - start)
+    });
+
+    assert_eq!(codes, ["WIKI", "FRED", "JODI"]);
+    assert!(elapsed >= Duration::from_secs(600), "{:?}", elapsed);
+    assert_eq!(server.hits(), 3);
+}
+
+#[test]
+fn throttles_are_interleaved_with_results() {
+    let server = metadata_server();
+    let mut batch_query = batch(&server, &["WIKI", "FRED", "JODI"], Some("key"));
+    batch_query.limit(2, 600);
+
+    paused(async {
+        let start = now();
+        let mut events = batch_query.stream().events();
+
+        for code in &["WIKI", "FRED"] {
+            match events.next().await {
+                Some(BatchEvent::Result(Ok(metadata))) => assert_eq!(metadata.database_code, *code),
+                other => panic!("{:?}", other),
+            }
+        }
+
+        let until = {
+            match events.next().await {
+                Some(BatchEvent::Throttled { key_fingerprint, until, threshold_hit }) => {
+                    assert_eq!(key_fingerprint.len(), 8);
+                    assert_eq!(threshold_hit, (2, Duration::from_secs(600)));
+                    assert_eq!(until, start + Duration::from_secs(600));
+                    until
+                },
+
+                other => panic!("{:?}", other),
+            }
+        };
+
+        // The wait is announced before it happens: nothing more has been sent yet.
+        assert!(now() < until);
+        assert_eq!(server.hits(), 2);
+
+        match events.next().await {
+            Some(BatchEvent::Result(Ok(metadata))) => assert_eq!(metadata.database_code, "JODI"),
+            other => panic!("{:?}", other),
+        }
+
+        assert!(now() >= until);
+        assert!(events.next().await.is_none());
+    });
+
+    assert_eq!(server.hits(), 3);
+}
+
+#[test]
+fn throttles_match_the_callback() {
+    let server = metadata_server();
+    let mut batch_query = batch(&server, &["WIKI", "FRED", "JODI", "EIA"], Some("key"));
+    let seen = Arc::new(Mutex::new(vec![]));
+
+    {
+        let seen = seen.clone();
+        batch_query.limit(1, 10).on_throttle(move |event| seen.lock().unwrap().push(event.clone()));
+    }
+
+    let throttles: Vec<_> = paused(async {
+        batch_query.stream().events()
+            .filter_map(|event| {
+                match event {
+                    BatchEvent::Throttled { key_fingerprint, threshold_hit, .. } => {
+                        Some((key_fingerprint, threshold_hit))
+                    },
+
+                    BatchEvent::Result(result) => {
+                        result.unwrap();
+                        None
+                    },
+                }
+            })
+            .collect().await
+    });
+
+    let seen = seen.lock().unwrap();
+    assert_eq!(throttles.len(), 3);
+    assert_eq!(seen.len(), 3);
+
+    for (event, (key_fingerprint, threshold_hit)) in seen.iter().zip(&throttles) {
+        assert_eq!(event.key_fingerprint, *key_fingerprint);
+        assert_eq!(event.threshold_hit, *threshold_hit);
+        assert_eq!(event.waited, Duration::from_secs(10));
+    }
+}
+
+#[test]
+fn offset_throttles_the_first_call() {
+    let server = metadata_server();
+    let mut batch_query = batch(&server, &["WIKI"], Some("key"));
+    batch_query.limit(3, 60).offset(3);
+
+    paused(async {
+        let mut events = batch_query.stream().events();
+
+        match events.next().await {
+            Some(BatchEvent::Throttled { threshold_hit, .. }) => {
+                assert_eq!(threshold_hit, (3, Duration::from_secs(60)));
+            },
+
+            other => panic!("{:?}", other),
+        }
+
+        assert!(matches!(events.next().await, Some(BatchEvent::Result(Ok(_)))));
+        assert!(events.next().await.is_none());
+    });
+}
+
+#[test]
+fn keyless_queries() {
+    let server = metadata_server();
+
+    let skipped = {
+        paused(batch(&server, &["WIKI", "FRED"], None).stream().collect::<Vec<_>>())
+    };
+
+    assert!(skipped.is_empty());
+    assert_eq!(server.hits(), 0);
+
+    let mut batch_query = batch(&server, &["WIKI", "FRED"], None);
+    batch_query.anonymous().anonymous_limits(&[(1, 30)]);
+
+    let events = paused(batch_query.stream().events().collect::<Vec<_>>());
+    assert_eq!(events.len(), 3);
+
+    match events[1] {
+        BatchEvent::Throttled { ref key_fingerprint, threshold_hit, .. } => {
+            assert_eq!(key_fingerprint, "anonymous");
+            assert_eq!(threshold_hit, (1, Duration::from_secs(30)));
+        },
+
+        ref other => panic!("{:?}", other),
+    }
+}
@@ -0,0 +1,310 @@
+extern crate quandl_v3;
+
+mod common;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use quandl_v3::Error;
+use quandl_v3::prelude::*;
+
+use common::{MockServer, Request, Response};
+
+const CHUNK: usize = 1 << 20;
+const PATH: &str = "/api/v3/databases/WIKI/data";
+
+/// Archive served by the mock and how it misbehaves.
+///
+struct Archive {
+    body: Vec<u8>,
+    etag: Option<String>,
+    honor_range: bool,
+    honor_if_range: bool,
+    // Number of bytes sent before hanging up, for the next response only.
+    cut_after: Option<usize>,
+}
+
+fn archive(body: Vec<u8>) -> Arc<Mutex<Archive>> {
+    Arc::new(Mutex::new(Archive {
+        body,
+        etag: Some("\"v1\"".to_string()),
+        honor_range: true,
+        honor_if_range: true,
+        cut_after: None,
+    }))
+}
+
+fn serve(archive: &Arc<Mutex<Archive>>) -> MockServer {
+    let archive = archive.clone();
+
+    MockServer::start(move |request| {
+        if request.path != PATH {
+            return Response::not_found();
+        }
+
+        let mut archive = archive.lock().unwrap();
+
+        let range_start = {
+            request.header("Range")
+                .filter(|_| archive.honor_range)
+                .and_then(|range| range.strip_prefix("bytes="))
+                .and_then(|range| range.trim_end_matches('-').parse::<usize>().ok())
+        };
+
+        let stale = {
+            archive.honor_if_range &&
+                request.header("If-Range").is_some() &&
+                request.header("If-Range") != archive.etag.as_deref()
+        };
+
+        let length = archive.body.len();
+
+        let mut response = match range_start {
+            Some(start) if !stale => {
+                Response::new(206)
+                    .header("Content-Range", &format!("bytes {}-{}/{}", start, length - 1, length))
+                    .body(&archive.body[start..])
+            },
+
+            _ => Response::new(200).body(&archive.body),
+        };
+
+        if let Some(ref etag) = archive.etag {
+            response = response.header("ETag", etag);
+        }
+
+        if let Some(cut_after) = archive.cut_after.take() {
+            let full = response.body.len().to_string();
+            response.body.truncate(cut_after);
+            response = response.header("Content-Length", &full);
+        }
+
+        response.header("Content-Type", "application/zip")
+    })
+}
+
+fn bytes(n: usize, mut seed: u64) -> Vec<u8> {
+    (0..n).map(|_| {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        seed as u8
+    }).collect()
+}
+
+fn target(name: &str) -> PathBuf {
+    let directory = {
+        std::env::temp_dir().join(format!("quandl-v3-bulk-{}-{}", std::process::id(), name))
+    };
+
+    let _ = fs::remove_dir_all(&directory);
+    fs::create_dir_all(&directory).unwrap();
+    directory.join("WIKI.zip")
+}
+
+fn sidecars(path: &Path) -> (PathBuf, PathBuf) {
+    let path = path.to_str().unwrap();
+    (PathBuf::from(format!("{}.part", path)), PathBuf::from(format!("{}.part.json", path)))
+}
+
+fn query(server: &MockServer) -> DatabaseDownloadQuery {
+    let mut query = DatabaseDownloadQuery::new("WIKI");
+    query.base_url(server.url());
+    query
+}
+
+fn ranges(requests: &[Request]) -> Vec<Option<String>> {
+    requests.iter().map(|request| request.header("Range").map(|x| x.to_string())).collect()
+}
+
+/// Download to `path`, failing once after `cut_after` bytes.
+///
+fn interrupted(archive: &Arc<Mutex<Archive>>, query: &DatabaseDownloadQuery, path: &Path,
+               cut_after: usize)
+{
+    archive.lock().unwrap().cut_after = Some(cut_after);
+
+    match query.download_to_file_resumable(path) {
+        Err(Error::DownloadFailed(_)) => (),
+        other => panic!("expected a download failure, got {:?}", other),
+    }
+
+    assert!(!path.exists());
+}
+
+#[test]
+fn urls() {
+    let mut query = DatabaseDownloadQuery::new("WIKI");
+    assert_eq!(query.url(), "https://www.quandl.com/api/v3/databases/WIKI/data");
+
+    query.partial();
+    assert_eq!(query.url(), "https://www.quandl.com/api/v3/databases/WIKI/data?\
+                             download_type=partial");
+
+    query.api_key("KEY");
+    assert_eq!(query.url(), "https://www.quandl.com/api/v3/databases/WIKI/data?\
+                             download_type=partial&api_key=KEY");
+}
+
+#[test]
+fn downloads_in_one_go() {
+    let body = bytes(CHUNK + 1000, 1);
+    let archive = archive(body.clone());
+    let server = serve(&archive);
+    let path = target("one_go");
+
+    assert_eq!(query(&server).download_to_file_resumable(&path).unwrap(), body.len() as u64);
+    assert_eq!(fs::read(&path).unwrap(), body);
+
+    let (partial, state) = sidecars(&path);
+    assert!(!partial.exists() && !state.exists());
+    assert_eq!(ranges(&server.requests()), [None]);
+}
+
+#[test]
+fn resumes_from_the_last_recorded_chunk() {
+    let body = bytes(3 * CHUNK + 12345, 2);
+    let archive = archive(body.clone());
+    let server = serve(&archive);
+    let query = query(&server);
+    let path = target("resume");
+
+    interrupted(&archive, &query, &path, CHUNK + CHUNK / 2);
+
+    let (partial, state) = sidecars(&path);
+    assert!(partial.exists() && state.exists());
+
+    // Interrupted again while resuming, this time past the third chunk.
+    interrupted(&archive, &query, &path, CHUNK + CHUNK / 2 + 100);
+
+    assert_eq!(query.download_to_file_resumable(&path).unwrap(), body.len() as u64);
+    assert_eq!(fs::read(&path).unwrap(), body);
+    assert!(!partial.exists() && !state.exists());
+
+    let requests = server.requests();
+    let expected = format!("bytes={}-", CHUNK);
+    let expected_again = format!("bytes={}-", 2 * CHUNK);
+
+    assert_eq!(ranges(&requests), [None, Some(expected), Some(expected_again)]);
+    assert!(requests[1..].iter().all(|request| request.header("If-Range") == Some("\"v1\"")));
+}
+
+#[test]
+fn restarts_when_range_is_ignored() {
+    let body = bytes(2 * CHUNK, 3);
+    let archive = archive(body.clone());
+    let server = serve(&archive);
+    let query = query(&server);
+    let path = target("ignored_range");
+
+    interrupted(&archive, &query, &path, CHUNK + 10);
+    archive.lock().unwrap().honor_range = false;
+
+    assert_eq!(query.download_to_file_resumable(&path).unwrap(), body.len() as u64);
+    assert_eq!(fs::read(&path).unwrap(), body);
+    assert_eq!(server.hits(), 2);
+}
+
+#[test]
+fn restarts_when_the_etag_changed() {
+    for &honor_if_range in &[true, false] {
+        let body = bytes(2 * CHUNK, 4);
+        let archive = archive(body);
+        let server = serve(&archive);
+        let query = query(&server);
+        let path = target(&format!("etag_{}", honor_if_range));
+
+        interrupted(&archive, &query, &path, CHUNK + 10);
+
+        let updated = bytes(2 * CHUNK + 50, 5);
+
+        {
+            let mut archive = archive.lock().unwrap();
+            archive.body = updated.clone();
+            archive.etag = Some("\"v2\"".to_string());
+            archive.honor_if_range = honor_if_range;
+        }
+
+        assert_eq!(query.download_to_file_resumable(&path).unwrap(), updated.len() as u64);
+        assert_eq!(fs::read(&path).unwrap(), updated);
+
+        // Without `If-Range` support, the partial content is discarded for a new full download.
+        let expected: Vec<Option<String>> = {
+            if honor_if_range {
+                vec![None, Some(format!("bytes={}-", CHUNK))]
+            } else {
+                vec![None, Some(format!("bytes={}-", CHUNK)), None]
+            }
+        };
+
+        assert_eq!(ranges(&server.requests()), expected);
+    }
+}
+
+#[test]
+fn restarts_when_the_overlap_differs() {
+    let body = bytes(3 * CHUNK, 6);
+    let archive = archive(body.clone());
+    let server = serve(&archive);
+    let query = query(&server);
+    let path = target("overlap");
+
+    archive.lock().unwrap().etag = None;
+    interrupted(&archive, &query, &path, 2 * CHUNK + 10);
+
+    // Revised within the chunk sent again, without any ETag to tell.
+    let mut updated = body;
+    updated[2 * CHUNK + 5] ^= 0xff;
+    archive.lock().unwrap().body = updated.clone();
+
+    assert_eq!(query.download_to_file_resumable(&path).unwrap(), updated.len() as u64);
+    assert_eq!(fs::read(&path).unwrap(), updated);
+    assert_eq!(ranges(&server.requests()), [None, Some(format!("bytes={}-", 2 * CHUNK)), None]);
+}
+
+#[test]
+fn restarts_when_the_partial_file_is_corrupted() {
+    let body = bytes(2 * CHUNK, 7);
+    let archive = archive(body.clone());
+    let server = serve(&archive);
+    let query = query(&server);
+    let path = target("corrupted");
+
+    interrupted(&archive, &query, &path, CHUNK + 100);
+
+    let (partial, state) = sidecars(&path);
+    let mut data = fs::read(&partial).unwrap();
+    data[CHUNK + 50] ^= 0xff;
+    fs::write(&partial, data).unwrap();
+
+    assert_eq!(query.download_to_file_resumable(&path).unwrap(), body.len() as u64);
+    assert_eq!(fs::read(&path).unwrap(), body);
+    assert_eq!(ranges(&server.requests()), [None, None]);
+
+    // An unreadable sidecar is ignored as well.
+    fs::remove_file(&path).unwrap();
+    interrupted(&archive, &query, &path, CHUNK + 100);
+    fs::write(&state, "{\"offset\":").unwrap();
+
+    assert_eq!(query.download_to_file_resumable(&path).unwrap(), body.len() as u64);
+    assert_eq!(fs::read(&path).unwrap(), body);
+}
+
+#[test]
+fn api_errors() {
+    let server = MockServer::start(|_| Response::not_found());
+    let path = target("error");
+
+    let mut query = DatabaseDownloadQuery::new("NOPE");
+    query.base_url(server.url());
+
+    match query.download_to_file_resumable(&path) {
+        Err(Error::NotFound { api_error: Some(error), .. }) => {
+            assert_eq!(error.quandl_error.code, "QECx02")
+        },
+        other => panic!("expected an API error, got {:?}", other),
+    }
+
+    assert!(!path.exists());
+}
@@ -0,0 +1,236 @@
+extern crate quandl_v3;
+extern crate serde_json;
+
+use std::fs;
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+use quandl_v3::cache::*;
+
+fn directory(name: &str) -> PathBuf {
+    let directory = {
+        std::env::temp_dir().join(format!("quandl-v3-cache-{}-{}", std::process::id(), name))
+    };
+
+    let _ = fs::remove_dir_all(&directory);
+    directory
+}
+
+fn entry(body: &str) -> CacheEntry {
+    CacheEntry {
+        etag: Some(format!("\"{}\"", body.len())),
+        content_type: Some("text/csv".to_string()),
+        body: body.as_bytes().to_vec(),
+    }
+}
+
+fn files(cache: &FileCache, extension: &str) -> Vec<PathBuf> {
+    fs::read_dir(cache.directory()).unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().map(|x| x == extension).unwrap_or(false))
+        .collect()
+}
+
+#[test]
+fn round_trip() {
+    let cache = FileCache::new(directory("round_trip")).unwrap();
+
+    assert_eq!(cache.get("a").unwrap(), None);
+
+    cache.put("a", &entry("first")).unwrap();
+    cache.put("b", &CacheEntry::default()).unwrap();
+    assert_eq!(cache.get("a").unwrap(), Some(entry("first")));
+    assert_eq!(cache.get("b").unwrap(), Some(CacheEntry::default()));
+
+    // Replacing an entry deletes its previous body.
+    cache.put("a", &entry("second")).unwrap();
+    assert_eq!(cache.get("a").unwrap(), Some(entry("second")));
+    assert_eq!(files(&cache, "body").len(), 2);
+    assert_eq!(cache.usage().unwrap(), (2, 6));
+
+    assert!(cache.remove("a").unwrap());
+    assert!(!cache.remove("a").unwrap());
+    assert_eq!(cache.get("a").unwrap(), None);
+    assert_eq!(files(&cache, "body").len(), 1);
+
+    // Entries persist across instances.
+    let reopened = FileCache::new(cache.directory()).unwrap();
+    assert_eq!(reopened.keys().unwrap(), ["b"]);
+}
+
+#[test]
+fn access_times_are_utc_timestamps() {
+    let cache = FileCache::new(directory("access_times")).unwrap();
+    cache.put("a", &entry("a")).unwrap();
+
+    let path = files(&cache, "meta").remove(0);
+    let mut index: serde_json::Value = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+
+    let accessed = index["accessed"].as_str().unwrap().to_string();
+    assert!(accessed.ends_with('Z') && accessed.len() == 30, "{}", accessed);
+    assert!(quandl_v3::calendar::parse_timestamp_utc(&accessed).is_some(), "{}", accessed);
+
+    // Indexes written by earlier versions, in nanoseconds since the epoch, are still read.
+    index["accessed"] = serde_json::json!(1_456_868_821_686_000_000u64);
+    fs::write(&path, serde_json::to_vec(&index).unwrap()).unwrap();
+
+    assert_eq!(cache.get("a").unwrap(), Some(entry("a")));
+    assert_eq!(cache.keys().unwrap(), ["a"]);
+}
+
+#[test]
+fn evicts_least_recently_used_entries() {
+    let mut cache = FileCache::new(directory("max_entries")).unwrap();
+    cache.max_entries(3);
+
+    for key in &["a", "b", "c"] {
+        cache.put(key, &entry(key)).unwrap();
+    }
+
+    assert!(cache.get("a").unwrap().is_some());
+    cache.put("d", &entry("d")).unwrap();
+    assert_eq!(cache.keys().unwrap(), ["c", "a", "d"]);
+
+    cache.put("c", &entry("c")).unwrap();
+    cache.put("e", &entry("e")).unwrap();
+    assert_eq!(cache.keys().unwrap(), ["d", "c", "e"]);
+    assert_eq!(files(&cache, "body").len(), 3);
+}
+
+#[test]
+fn evicts_down_to_max_bytes() {
+    let mut cache = FileCache::new(directory("max_bytes")).unwrap();
+    cache.max_bytes(10);
+
+    cache.put("a", &entry("aaaa")).unwrap();
+    cache.put("b", &entry("bbbb")).unwrap();
+    assert_eq!(cache.usage().unwrap(), (2, 8));
+
+    // A single big entry may evict several.
+    cache.put("c", &entry("cccccccc")).unwrap();
+    assert_eq!(cache.keys().unwrap(), ["c"]);
+
+    cache.put("d", &entry("dd")).unwrap();
+    assert_eq!(cache.usage().unwrap(), (2, 10));
+
+    // Too big to ever fit: not stored, and the stale entry it would have replaced is dropped.
+    cache.put("d", &entry("ddddddddddd")).unwrap();
+    assert_eq!(cache.get("d").unwrap(), None);
+    assert_eq!(cache.keys().unwrap(), ["c"]);
+}
+
+#[test]
+fn purges_old_entries() {
+    let cache = FileCache::new(directory("purge")).unwrap();
+
+    cache.put("a", &entry("a")).unwrap();
+    cache.put("b", &entry("b")).unwrap();
+    thread::sleep(Duration::from_millis(300));
+    cache.put("c", &entry("c")).unwrap();
+    assert!(cache.get("b").unwrap().is_some());
+
+    assert_eq!(cache.purge_older_than(Duration::from_millis(150)).unwrap(), 1);
+    assert_eq!(cache.keys().unwrap(), ["c", "b"]);
+
+    assert_eq!(cache.purge_older_than(Duration::from_secs(0)).unwrap(), 2);
+    assert_eq!(fs::read_dir(cache.directory()).unwrap().count(), 0);
+}
+
+#[test]
+fn survives_interrupted_writes() {
+    let mut cache = FileCache::new(directory("crash")).unwrap();
+    cache.max_entries(2);
+
+    cache.put("a", &entry("a")).unwrap();
+    cache.put("b", &entry("b")).unwrap();
+
+    // Simulate a torn index, a body lost after its index was written and the leftovers of a
+    // writer killed before renaming its files.
+    let indexes = files(&cache, "meta");
+    let (torn, lost) = {
+        if fs::read_to_string(&indexes[0]).unwrap().contains("\"key\":\"a\"") {
+            (&indexes[0], &indexes[1])
+        } else {
+            (&indexes[1], &indexes[0])
+        }
+    };
+
+    let encoded = fs::read_to_string(torn).unwrap();
+    fs::write(torn, &encoded[..encoded.len() / 2]).unwrap();
+
+    let lost: serde_json::Value = serde_json::from_slice(&fs::read(lost).unwrap()).unwrap();
+    fs::remove_file(cache.directory().join(lost["body"].as_str().unwrap())).unwrap();
+
+    fs::write(cache.directory().join("123-0-0.tmp"), b"partial").unwrap();
+    fs::write(cache.directory().join("123-1-0.body"), b"orphan").unwrap();
+
+    assert_eq!(cache.get("a").unwrap(), None);
+    assert_eq!(cache.get("b").unwrap(), None);
+    assert_eq!(cache.keys().unwrap(), ["b"]);
+
+    cache.put("c", &entry("c")).unwrap();
+    cache.put("d", &entry("d")).unwrap();
+    assert_eq!(cache.keys().unwrap(), ["c", "d"]);
+    assert_eq!(cache.get("c").unwrap(), Some(entry("c")));
+
+    // Recent leftovers may belong to writes in progress and are kept.
+    assert_eq!(cache.purge_older_than(Duration::from_secs(3600)).unwrap(), 0);
+    assert_eq!(files(&cache, "tmp").len(), 1);
+
+    thread::sleep(Duration::from_millis(50));
+    cache.get("c").unwrap();
+    cache.get("d").unwrap();
+
+    assert_eq!(cache.purge_older_than(Duration::from_millis(20)).unwrap(), 0);
+    assert_eq!(files(&cache, "meta").len(), 2);
+    assert_eq!(files(&cache, "body").len(), 2);
+    assert_eq!(files(&cache, "tmp").len(), 0);
+}
+
+#[test]
+fn concurrent_instances_share_a_directory() {
+    let directory = directory("concurrent");
+
+    let workers: Vec<_> = (0..4).map(|worker| {
+        let directory = directory.clone();
+
+        thread::spawn(move || {
+            let mut cache = FileCache::new(&directory).unwrap();
+            cache.max_entries(5).max_bytes(4096);
+
+            for round in 0..25 {
+                let key = format!("key-{}", (worker + round) % 8);
+                let body = format!("{}:{}", key, "x".repeat(round * 10));
+
+                cache.put(&key, &entry(&body)).unwrap();
+
+                // Other instances may have evicted or replaced it, but never corrupted it.
+                if let Some(cached) = cache.get(&key).unwrap() {
+                    let cached = String::from_utf8(cached.body).unwrap();
+                    let padding = &cached[key.len() + 1..];
+
+                    assert!(cached.starts_with(&format!("{}:", key)), "{}", cached);
+                    assert!(padding.len().is_multiple_of(10) && padding.bytes().all(|x| x == b'x'));
+                }
+            }
+        })
+    }).collect();
+
+    for worker in workers {
+        worker.join().unwrap();
+    }
+
+    let mut cache = FileCache::new(&directory).unwrap();
+    cache.max_entries(5).max_bytes(4096);
+
+    for path in files(&cache, "meta") {
+        let _: serde_json::Value = serde_json::from_slice(&fs::read(path).unwrap()).unwrap();
+    }
+
+    cache.put("last", &entry("last")).unwrap();
+
+    let (entries, bytes) = cache.usage().unwrap();
+    assert!(entries <= 5 && bytes <= 4096, "entries: {}, bytes: {}", entries, bytes);
+    assert_eq!(cache.get("last").unwrap(), Some(entry("last")));
+}
@@ -0,0 +1,361 @@
+extern crate chrono;
+extern crate quandl_v3;
+
+use std::collections::BTreeSet;
+
+use chrono::{Datelike, Duration};
+
+use quandl_v3::prelude::*;
+use quandl_v3::calendar::*;
+
+fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(year, month, day).unwrap()
+}
+
+#[test]
+fn leap_years() {
+    assert!(is_leap_year(2016));
+    assert!(is_leap_year(2000));
+    assert!(!is_leap_year(1900));
+    assert!(!is_leap_year(2100));
+    assert!(!is_leap_year(2015));
+
+    assert_eq!(days_in_month(2016, 2), 29);
+    assert_eq!(days_in_month(2015, 2), 28);
+    assert_eq!(days_in_month(2000, 2), 29);
+    assert_eq!(days_in_month(1900, 2), 28);
+    assert_eq!(days_in_month(2016, 4), 30);
+    assert_eq!(days_in_month(2016, 12), 31);
+}
+
+#[test]
+fn iso_week_numbers_around_year_boundaries() {
+    assert_eq!(iso_week(date(2021, 1, 3)), (2020, 53));
+    assert_eq!(iso_week(date(2021, 1, 4)), (2021, 1));
+    assert_eq!(iso_week(date(2019, 12, 30)), (2020, 1));
+    assert_eq!(iso_week(date(2015, 12, 31)), (2015, 53));
+    assert_eq!(iso_week(date(2016, 1, 1)), (2015, 53));
+    assert_eq!(iso_week(date(2016, 1, 4)), (2016, 1));
+    assert_eq!(iso_week(date(2018, 1, 1)), (2018, 1));
+    assert_eq!(iso_week(date(2018, 12, 31)), (2019, 1));
+}
+
+#[test]
+fn weeks_per_iso_year() {
+    for year in &[2004, 2009, 2015, 2020, 2026] {
+        assert_eq!(weeks_in_iso_year(*year), 53, "year: {}", year);
+    }
+
+    for year in &[2014, 2016, 2017, 2018, 2019, 2021] {
+        assert_eq!(weeks_in_iso_year(*year), 52, "year: {}", year);
+    }
+}
+
+#[test]
+fn iso_week_bounds() {
+    // Thursday 2020-12-31 is in week 53 which spans the year boundary.
+    assert_eq!(iso_week_start(date(2020, 12, 31)), date(2020, 12, 28));
+    assert_eq!(iso_week_end(date(2020, 12, 31)), date(2021, 1, 3));
+
+    assert_eq!(iso_week_start(date(2016, 2, 29)), date(2016, 2, 29));
+    assert_eq!(iso_week_end(date(2016, 2, 29)), date(2016, 3, 6));
+
+    assert_eq!(iso_week_start(date(2016, 3, 6)), date(2016, 2, 29));
+    assert_eq!(iso_week_end(date(2016, 3, 6)), date(2016, 3, 6));
+}
+
+#[test]
+fn month_quarter_and_year_bounds() {
+    assert_eq!(month_start(date(2016, 2, 17)), date(2016, 2, 1));
+    assert_eq!(month_end(date(2016, 2, 17)), date(2016, 2, 29));
+    assert_eq!(month_end(date(2015, 2, 1)), date(2015, 2, 28));
+    assert_eq!(month_end(date(2015, 12, 1)), date(2015, 12, 31));
+
+    assert_eq!(quarter(date(2016, 1, 1)), 1);
+    assert_eq!(quarter(date(2016, 3, 31)), 1);
+    assert_eq!(quarter(date(2016, 4, 1)), 2);
+    assert_eq!(quarter(date(2016, 12, 31)), 4);
+
+    assert_eq!(quarter_start(date(2016, 8, 15)), date(2016, 7, 1));
+    assert_eq!(quarter_end(date(2016, 8, 15)), date(2016, 9, 30));
+    assert_eq!(quarter_end(date(2016, 2, 1)), date(2016, 3, 31));
+    assert_eq!(quarter_end(date(2016, 11, 1)), date(2016, 12, 31));
+
+    assert_eq!(year_start(date(2016, 8, 15)), date(2016, 1, 1));
+    assert_eq!(year_end(date(2016, 8, 15)), date(2016, 12, 31));
+}
+
+#[test]
+fn period_bounds_by_frequency() {
+    let day = date(2016, 2, 10);
+
+    assert_eq!(period_end(day, Frequency::none), day);
+    assert_eq!(period_end(day, Frequency::daily), day);
+    assert_eq!(period_end(day, Frequency::weekly), date(2016, 2, 14));
+    assert_eq!(period_end(day, Frequency::monthly), date(2016, 2, 29));
+    assert_eq!(period_end(day, Frequency::quarterly), date(2016, 3, 31));
+    assert_eq!(period_end(day, Frequency::annual), date(2016, 12, 31));
+
+    assert_eq!(period_start(day, Frequency::daily), day);
+    assert_eq!(period_start(day, Frequency::weekly), date(2016, 2, 8));
+    assert_eq!(period_start(day, Frequency::monthly), date(2016, 2, 1));
+    assert_eq!(period_start(day, Frequency::quarterly), date(2016, 1, 1));
+    assert_eq!(period_start(day, Frequency::annual), date(2016, 1, 1));
+
+    assert!(same_period(date(2020, 12, 31), date(2021, 1, 3), Frequency::weekly));
+    assert!(!same_period(date(2020, 12, 31), date(2021, 1, 3), Frequency::annual));
+    assert!(!same_period(date(2016, 3, 31), date(2016, 4, 1), Frequency::quarterly));
+}
+
+#[test]
+fn business_days_with_weekends_only() {
+    assert!(is_weekend(date(2016, 2, 6)));
+    assert!(is_weekend(date(2016, 2, 7)));
+    assert!(!is_weekend(date(2016, 2, 8)));
+
+    assert_eq!(next_business_day(date(2016, 2, 5), &WeekendsOnly), date(2016, 2, 8));
+    assert_eq!(next_business_day(date(2016, 2, 8), &WeekendsOnly), date(2016, 2, 9));
+    assert_eq!(previous_business_day(date(2016, 2, 8), &WeekendsOnly), date(2016, 2, 5));
+
+    // February 2016 has 29 days and starts on a Monday.
+    let days = business_days_between(date(2016, 2, 1), date(2016, 2, 29), &WeekendsOnly);
+    assert_eq!(days, 21);
+
+    assert_eq!(business_days_between(date(2016, 2, 6), date(2016, 2, 7), &WeekendsOnly), 0);
+    assert_eq!(business_days_between(date(2016, 2, 9), date(2016, 2, 8), &WeekendsOnly), 0);
+}
+
+#[test]
+fn business_days_with_holidays() {
+    let holidays: BTreeSet<NaiveDate> = {
+        [date(2015, 12, 25), date(2016, 1, 1), date(2016, 1, 18)].iter().cloned().collect()
+    };
+
+    assert!(!is_business_day(date(2016, 1, 1), &holidays));
+    assert!(is_business_day(date(2015, 12, 31), &holidays));
+
+    // Across the year boundary: Thursday 2015-12-31 -> holiday -> weekend -> Monday.
+    assert_eq!(next_business_day(date(2015, 12, 31), &holidays), date(2016, 1, 4));
+    assert_eq!(previous_business_day(date(2016, 1, 4), &holidays), date(2015, 12, 31));
+
+    let days = business_days_between(date(2015, 12, 21), date(2016, 1, 22), &holidays);
+    assert_eq!(days, 22);
+
+    let slice: &[NaiveDate] = &[date(2016, 2, 29)];
+    assert_eq!(next_business_day(date(2016, 2, 26), slice), date(2016, 3, 1));
+}
+
+#[test]
+fn parse_iso_dates() {
+    assert_eq!(parse_date("2016-02-29"), Some(date(2016, 2, 29)));
+    assert_eq!(parse_date("1980-12-12"), Some(date(1980, 12, 12)));
+    assert_eq!(parse_date(String::from("0001-01-01")), Some(date(1, 1, 1)));
+
+    for s in &["2015-02-29", "2016-13-01", "2016-00-10", "2016-2-1", "16-02-01", "2016/02/01",
+               " 2016-02-01", "2016-02-01 ", "2016-02-0a", "", "+016-02-01"] {
+        assert_eq!(parse_date(s), None, "{:?}", s);
+    }
+}
+
+#[test]
+fn parse_timestamps() {
+    let utc = |s: &str| {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.3f").unwrap().and_utc()
+    };
+
+    // As found in the metadata, dataset listings and database metadata served over the years.
+    for (s, expected) in &[
+        ("2016-03-01T21:47:01.686Z", "2016-03-01 21:47:01.686"),
+        ("2016-03-01T21:47:01Z", "2016-03-01 21:47:01.000"),
+        ("2016-03-01T21:47:01.686", "2016-03-01 21:47:01.686"),
+        ("2016-03-01T21:47:01", "2016-03-01 21:47:01.000"),
+        ("2016-03-01T16:47:01.686-05:00", "2016-03-01 21:47:01.686"),
+        ("2016-03-01 21:47:01 UTC", "2016-03-01 21:47:01.000"),
+        ("2016-03-01 21:47:01.686", "2016-03-01 21:47:01.686"),
+        (" 2016-03-01T21:47:01.686Z\n", "2016-03-01 21:47:01.686"),
+    ] {
+        assert_eq!(parse_timestamp_utc(s), Some(utc(expected)), "{:?}", s);
+        assert_eq!(parse_timestamp(s), Some(utc(expected).naive_utc()), "{:?}", s);
+    }
+
+    for s in &["", "yesterday", "2016-03-01", "2016-03-01T25:00:00Z", "2016-03-01T21:47Z"] {
+        assert_eq!(parse_timestamp_utc(s), None, "{:?}", s);
+    }
+
+    // Across a change to daylight saving time, both ends being in UTC.
+    let before = parse_timestamp_utc("2016-03-13T01:59:59-05:00").unwrap();
+    let after = parse_timestamp_utc("2016-03-13T03:00:00-04:00").unwrap();
+    assert_eq!((after - before).num_seconds(), 1);
+
+    assert_eq!(format_timestamp(&utc("2016-03-01 21:47:01.686")), "2016-03-01T21:47:01.686Z");
+    assert_eq!(format_timestamp(&utc("2016-03-01 21:47:01.000")), "2016-03-01T21:47:01.000Z");
+}
+
+#[test]
+fn date_ranges() {
+    let range = DateRange::new(date(2016, 2, 27), date(2016, 3, 1));
+
+    assert_eq!(range.days(), 4);
+    assert!(range.contains(date(2016, 2, 29)));
+    assert!(range.contains(date(2016, 3, 1)));
+    assert!(!range.contains(date(2016, 3, 2)));
+    assert_eq!(range.to_string(), "2016-02-27 to 2016-03-01");
+
+    assert_eq!(DateRange::new(date(2016, 1, 1), date(2016, 1, 1)).days(), 1);
+}
+
+fn range(period: PeriodSpec) -> (NaiveDate, NaiveDate) {
+    (period.start(), period.end())
+}
+
+#[test]
+fn fiscal_years() {
+    let june = |year| PeriodSpec::fiscal_year(year).year_end_month(6);
+
+    assert_eq!(range(PeriodSpec::fiscal_year(2016)), (date(2016, 1, 1), date(2016, 12, 31)));
+    assert_eq!(range(june(2016)), (date(2015, 7, 1), date(2016, 6, 30)));
+    assert_eq!(range(june(2016).year_end_month(9)), (date(2015, 10, 1), date(2016, 9, 30)));
+    assert_eq!(range(june(2016).year_end_month(3)), (date(2015, 4, 1), date(2016, 3, 31)));
+    assert_eq!(range(june(2016).year_end_month(1)), (date(2015, 2, 1), date(2016, 1, 31)));
+    assert_eq!(range(june(2016).year_end_month(12)), range(PeriodSpec::fiscal_year(2016)));
+
+    // Years ending in February end on the 29th of leap years.
+    let february = |year| PeriodSpec::fiscal_year(year).year_end_month(2);
+    assert_eq!(range(february(2016)), (date(2015, 3, 1), date(2016, 2, 29)));
+    assert_eq!(range(february(2017)), (date(2016, 3, 1), date(2017, 2, 28)));
+    assert_eq!(range(february(2000)).1, date(2000, 2, 29));
+    assert_eq!(range(february(1900)).1, date(1900, 2, 28));
+
+    assert_eq!(range(PeriodSpec::fiscal_year(1).year_end_month(6)).0, date(0, 7, 1));
+
+    let period = june(2016);
+    assert_eq!((period.year(), period.quarter(), period.fiscal_year_end_month()), (2016, None, 6));
+    assert_eq!(period.range(), DateRange::new(date(2015, 7, 1), date(2016, 6, 30)));
+}
+
+#[test]
+fn fiscal_quarters() {
+    let quarters = |year_end_month| {
+        (1..=4)
+            .map(|quarter| range(PeriodSpec::fiscal_quarter(2016, quarter)
+                                     .year_end_month(year_end_month)))
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(quarters(12), vec![(date(2016, 1, 1), date(2016, 3, 31)),
+                                  (date(2016, 4, 1), date(2016, 6, 30)),
+                                  (date(2016, 7, 1), date(2016, 9, 30)),
+                                  (date(2016, 10, 1), date(2016, 12, 31))]);
+
+    assert_eq!(quarters(6), vec![(date(2015, 7, 1), date(2015, 9, 30)),
+                                 (date(2015, 10, 1), date(2015, 12, 31)),
+                                 (date(2016, 1, 1), date(2016, 3, 31)),
+                                 (date(2016, 4, 1), date(2016, 6, 30))]);
+
+    assert_eq!(quarters(1), vec![(date(2015, 2, 1), date(2015, 4, 30)),
+                                 (date(2015, 5, 1), date(2015, 7, 31)),
+                                 (date(2015, 8, 1), date(2015, 10, 31)),
+                                 (date(2015, 11, 1), date(2016, 1, 31))]);
+
+    assert_eq!(quarters(11), vec![(date(2015, 12, 1), date(2016, 2, 29)),
+                                  (date(2016, 3, 1), date(2016, 5, 31)),
+                                  (date(2016, 6, 1), date(2016, 8, 31)),
+                                  (date(2016, 9, 1), date(2016, 11, 30))]);
+
+    let period = PeriodSpec::fiscal_quarter(2016, 3).year_end_month(9);
+    assert_eq!((period.year(), period.quarter(), period.fiscal_year_end_month()),
+               (2016, Some(3), 9));
+}
+
+#[test]
+fn fiscal_periods_tile_the_calendar() {
+    for year_end_month in 1..=12 {
+        let year = |year| PeriodSpec::fiscal_year(year).year_end_month(year_end_month);
+        let quarter = |year, quarter| {
+            PeriodSpec::fiscal_quarter(year, quarter).year_end_month(year_end_month)
+        };
+
+        for fiscal_year in 1895..=2105 {
+            let period = year(fiscal_year);
+
+            // Years end with the month given, in the calendar year they are named after, and
+            // the next one starts the day after.
+            assert_eq!(period.end().year(), i32::from(fiscal_year), "{}", period);
+            assert_eq!(period.end().month(), u32::from(year_end_month), "{}", period);
+            assert_eq!(period.end().succ_opt(), Some(year(fiscal_year + 1).start()), "{}", period);
+
+            let days = period.range().days();
+            assert!(days == 365 || days == 366, "{}: {} days", period, days);
+
+            // Quarters are three whole months, in order and back to back.
+            assert_eq!(quarter(fiscal_year, 1).start(), period.start(), "{}", period);
+            assert_eq!(quarter(fiscal_year, 4).end(), period.end(), "{}", period);
+
+            for n in 1..=4 {
+                let q = quarter(fiscal_year, n);
+
+                assert_eq!(q.start(), month_start(q.start()), "{}", q);
+                assert_eq!(q.end(), month_end(q.end()), "{}", q);
+                assert_eq!(month_start(q.start() + Duration::days(95)) - Duration::days(1),
+                           q.end(),
+                           "{}",
+                           q);
+
+                if n < 4 {
+                    assert_eq!(q.end().succ_opt(), Some(quarter(fiscal_year, n + 1).start()));
+                }
+            }
+
+            let total: usize = (1..=4).map(|n| quarter(fiscal_year, n).range().days()).sum();
+            assert_eq!(total, days, "{}", period);
+        }
+    }
+}
+
+#[test]
+fn fiscal_quarters_containing_dates() {
+    assert_eq!(PeriodSpec::containing(date(2015, 7, 1), 6),
+               PeriodSpec::fiscal_quarter(2016, 1).year_end_month(6));
+    assert_eq!(PeriodSpec::containing(date(2016, 6, 30), 6),
+               PeriodSpec::fiscal_quarter(2016, 4).year_end_month(6));
+    assert_eq!(PeriodSpec::containing(date(2016, 7, 1), 6),
+               PeriodSpec::fiscal_quarter(2017, 1).year_end_month(6));
+    assert_eq!(PeriodSpec::containing(date(2016, 2, 29), 12), PeriodSpec::fiscal_quarter(2016, 1));
+    assert_eq!(PeriodSpec::containing(date(2016, 12, 31), 12),
+               PeriodSpec::fiscal_quarter(2016, 4));
+    assert_eq!(PeriodSpec::containing(date(2016, 1, 31), 1),
+               PeriodSpec::fiscal_quarter(2016, 4).year_end_month(1));
+    assert_eq!(PeriodSpec::containing(date(2016, 2, 1), 1),
+               PeriodSpec::fiscal_quarter(2017, 1).year_end_month(1));
+
+    // Every day is in the quarter containing it, whatever the end of the fiscal years.
+    for year_end_month in 1..=12 {
+        for day in date(1999, 1, 1).iter_days().take_while(|day| day.year() < 2031) {
+            let period = PeriodSpec::containing(day, year_end_month);
+
+            assert!(period.range().contains(day), "{} {}", day, period);
+            assert_eq!(period.fiscal_year_end_month(), year_end_month);
+        }
+    }
+}
+
+#[test]
+fn fiscal_period_names() {
+    assert_eq!(PeriodSpec::fiscal_year(2016).to_string(), "FY2016");
+    assert_eq!(PeriodSpec::fiscal_quarter(2016, 2).to_string(), "FY2016Q2");
+    assert_eq!(PeriodSpec::fiscal_quarter(2016, 2).year_end_month(6).to_string(),
+               "FY2016Q2 (June)");
+    assert_eq!(PeriodSpec::fiscal_year(2016).year_end_month(9).to_string(), "FY2016 (September)");
+}
+
+#[test]
+#[should_panic(expected = "fiscal quarter: 5")]
+fn fiscal_quarters_are_at_most_4() {
+    PeriodSpec::fiscal_quarter(2016, 5);
+}
+
+#[test]
+#[should_panic(expected = "year end month: 13")]
+fn fiscal_years_end_with_a_month() {
+    PeriodSpec::fiscal_year(2016).year_end_month(13);
+}
@@ -0,0 +1,249 @@
+extern crate quandl_v3;
+
+mod common;
+
+use std::thread::{sleep, spawn};
+use std::time::{Duration, Instant};
+
+use quandl_v3::{CancelToken, Error};
+use quandl_v3::backoff::{retry, Backoff, Jitter};
+use quandl_v3::prelude::*;
+
+use common::{MockServer, Response};
+
+static DATABASE_METADATA: &str = include_str!("fixtures/database_metadata.json");
+static LISTING: &str = include_str!("fixtures/dataset_listing_1.csv");
+
+/// Server answering metadata queries, after `delay` for those of databases other than WIKI.
+///
+fn server(delay: Duration) -> MockServer {
+    MockServer::start(move |request| {
+        match &request.path[..] {
+            "/api/v3/databases/WIKI.json" => Response::json(DATABASE_METADATA),
+            "/api/v3/datasets.csv" => Response::csv(LISTING),
+
+            path if path.starts_with("/api/v3/databases/") => {
+                sleep(delay);
+                Response::json(DATABASE_METADATA)
+            },
+
+            _ => Response::not_found(),
+        }
+    })
+}
+
+fn query(server: &MockServer, database_code: &str) -> DatabaseMetadataQuery {
+    let mut query = DatabaseMetadataQuery::new(database_code);
+    query.base_url(server.url()).api_key("KEY");
+    query
+}
+
+/// Cancel `token` from another thread once `delay` passed.
+///
+fn cancel_after(token: &CancelToken, delay: Duration) {
+    let token = token.clone();
+
+    spawn(move || {
+        sleep(delay);
+        token.cancel();
+    });
+}
+
+#[test]
+fn tokens() {
+    let token = CancelToken::new();
+    let clone = token.clone();
+
+    assert!(!token.is_cancelled());
+    assert_eq!(token, clone);
+    assert_ne!(token, CancelToken::new());
+
+    clone.cancel();
+    assert!(token.is_cancelled());
+    assert_eq!(format!("{:?}", token), "CancelToken { cancelled: true }");
+
+    assert_eq!(Error::Cancelled.to_string(),
+               "the query was cancelled before it could be completed.");
+}
+
+#[test]
+fn cancelled_before_dispatch() {
+    let server = server(Duration::from_secs(0));
+    let token = CancelToken::new();
+    token.cancel();
+
+    assert_eq!(query(&server, "WIKI").send_with_cancel(&token), Err(Error::Cancelled));
+    assert_eq!(token.scope(|| query(&server, "WIKI").send()), Err(Error::Cancelled));
+    assert_eq!(server.hits(), 0);
+
+    assert!(query(&server, "WIKI").send_with_cancel(&CancelToken::new()).is_ok());
+    assert_eq!(server.hits(), 1);
+}
+
+#[test]
+fn requests_in_flight_are_abandoned() {
+    let server = server(Duration::from_secs(3));
+    let token = CancelToken::new();
+    cancel_after(&token, Duration::from_millis(200));
+
+    let start = Instant::now();
+    assert_eq!(query(&server, "FRED").send_with_cancel(&token), Err(Error::Cancelled));
+    assert!(start.elapsed() < Duration::from_secs(1), "{:?}", start.elapsed());
+    assert_eq!(server.hits(), 1);
+}
+
+#[test]
+fn cancellation_is_not_a_timeout() {
+    let server = server(Duration::from_secs(3));
+
+    let token = CancelToken::new();
+    let deadline = Instant::now() + Duration::from_millis(200);
+    let result = token.scope(|| query(&server, "FRED").send_with_deadline(deadline));
+    assert_eq!(result, Err(Error::DeadlineExceeded));
+
+    cancel_after(&token, Duration::from_millis(200));
+    let deadline = Instant::now() + Duration::from_secs(2);
+    let result = token.scope(|| query(&server, "FRED").send_with_deadline(deadline));
+    assert_eq!(result, Err(Error::Cancelled));
+}
+
+#[test]
+fn cancelled_between_pages() {
+    let server = server(Duration::from_secs(0));
+    let token = CancelToken::new();
+
+    let mut query = DatasetListingQuery::new("WIKI");
+    query.base_url(server.url()).per_page(2);
+
+    let mut datasets = query.iter().cancel_token(&token);
+    assert_eq!(datasets.next().unwrap().unwrap().code, "AAPL");
+
+    // The page already fetched is gone through, but the next one is not requested.
+    token.cancel();
+    assert_eq!(datasets.next().unwrap().unwrap().code, "MSFT");
+    assert_eq!(datasets.next().map(|x| x.map(|x| x.code)), Some(Err(Error::Cancelled)));
+    assert!(datasets.next().is_none());
+    assert_eq!(server.hits(), 1);
+}
+
+#[test]
+fn retries_are_cancelled() {
+    let server = server(Duration::from_secs(0));
+    server.set_down(true);
+
+    let mut backoff = Backoff::default();
+    backoff.base(Duration::from_secs(10)).jitter(Jitter::None);
+
+    let token = CancelToken::new();
+    cancel_after(&token, Duration::from_millis(200));
+
+    let start = Instant::now();
+    let result = token.scope(|| retry(&backoff, || query(&server, "WIKI").send()));
+    assert_eq!(result, Err(Error::Cancelled));
+    assert!(start.elapsed() < Duration::from_secs(2), "{:?}", start.elapsed());
+}
+
+#[test]
+fn endless_retries_are_cancelled() {
+    let server = server(Duration::from_secs(0));
+    server.set_down(true);
+
+    // Delays beyond what an `Instant` can represent.
+    let mut backoff = Backoff::default();
+    backoff.base(Duration::MAX).max_delay(Duration::MAX).jitter(Jitter::None);
+
+    let token = CancelToken::new();
+    cancel_after(&token, Duration::from_millis(200));
+
+    let start = Instant::now();
+    let result = token.scope(|| retry(&backoff, || query(&server, "WIKI").send()));
+    assert_eq!(result, Err(Error::Cancelled));
+    assert!(start.elapsed() < Duration::from_secs(2), "{:?}", start.elapsed());
+}
+
+#[cfg(feature = "batch")]
+#[test]
+fn cancelled_mid_batch() {
+    let server = server(Duration::from_millis(100));
+    let token = CancelToken::new();
+
+    let mut batch_query = BatchQuery::new();
+    batch_query.threads(1).cancel_token(&token);
+
+    for _ in 0..20 {
+        batch_query.query(query(&server, "FRED"));
+    }
+
+    {
+        let token = token.clone();
+
+        batch_query.on_progress(move |progress| {
+            if progress.completed == 2 {
+                token.cancel();
+            }
+        });
+    }
+
+    let start = Instant::now();
+    let (results, report) = batch_query.run_with_report();
+    let results: Vec<_> = results.collect();
+
+    // The 18 queries left are not sent, rather than taking another 1.8 seconds.
+    assert!(start.elapsed() < Duration::from_secs(1), "{:?}", start.elapsed());
+    assert!(results[..2].iter().all(Result::is_ok));
+    assert!(results[2..].iter().all(|result| *result == Err(Error::Cancelled)));
+    assert_eq!(server.hits(), 2);
+
+    let report = report.get();
+    assert_eq!(report.total().calls, 2);
+    assert_eq!(report.errors.get("Cancelled"), Some(&18));
+}
+
+#[cfg(feature = "batch")]
+#[test]
+fn rate_limited_batches_are_cancelled() {
+    let server = server(Duration::from_secs(0));
+    let token = CancelToken::new();
+
+    let mut batch_query = BatchQuery::new();
+    batch_query.limit(1, 60).cancel_token(&token);
+
+    for _ in 0..3 {
+        batch_query.query(query(&server, "WIKI"));
+    }
+
+    cancel_after(&token, Duration::from_millis(200));
+
+    let start = Instant::now();
+    let results: Vec<_> = batch_query.run().collect();
+
+    assert!(start.elapsed() < Duration::from_secs(2), "{:?}", start.elapsed());
+    assert!(results[0].is_ok());
+    assert_eq!(&results[1..], &[Err(Error::Cancelled), Err(Error::Cancelled)]);
+    assert_eq!(server.hits(), 1);
+}
+
+#[cfg(all(feature = "batch", feature = "zip"))]
+#[test]
+fn cancelled_mirror_jobs() {
+    use quandl_v3::mirror::MirrorJob;
+
+    let server = server(Duration::from_secs(0));
+    let token = CancelToken::new();
+    token.cancel();
+
+    let directory = std::env::temp_dir().join(format!("quandl-v3-cancel-{}", std::process::id()));
+
+    let result = {
+        MirrorJob::new("WIKI")
+            .api_key("KEY")
+            .base_url(server.url())
+            .target_dir(&directory)
+            .cancel_token(&token)
+            .run()
+    };
+
+    assert_eq!(result, Err(Error::Cancelled));
+    assert_eq!(server.hits(), 0);
+    assert!(!directory.exists());
+}
@@ -0,0 +1,144 @@
+#![cfg(feature = "cli")]
+
+extern crate serde_json;
+
+mod common;
+
+use std::process::{Command, Output};
+
+use common::{MockServer, Response};
+
+static DATA: &str = include_str!("fixtures/data_3_columns.csv");
+static CODES: &[u8] = include_bytes!("fixtures/codes.zip");
+static DATABASE_SEARCH: &str = include_str!("fixtures/database_search.json");
+static DATASET_SEARCH: &str = include_str!("fixtures/dataset_search.json");
+
+fn server() -> MockServer {
+    MockServer::routes(vec![
+        ("/api/v3/datasets/WIKI/AAPL/data.csv", Response::csv(DATA)),
+        ("/api/v3/databases/WIKI/codes", Response::new(200).body(CODES)),
+        ("/api/v3/databases.json", Response::json(DATABASE_SEARCH)),
+        ("/api/v3/datasets.json", Response::json(DATASET_SEARCH)),
+    ])
+}
+
+/// Run `quandl-fetch` against `server` with `args`, and the API key `key` if any.
+///
+fn run(server: &MockServer, key: Option<&str>, args: &[&str]) -> Output {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_quandl-fetch"));
+    command.arg("--base-url").arg(server.url()).args(args).env_remove("QUANDL_API_KEY");
+
+    if let Some(key) = key {
+        command.env("QUANDL_API_KEY", key);
+    }
+
+    command.output().unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+#[test]
+fn data_as_csv() {
+    let server = server();
+    let output = run(&server, Some("KEY"), &["data", "WIKI/AAPL", "--rows", "3"]);
+
+    assert_eq!(stdout(&output), DATA);
+
+    assert_eq!(server.requests()[0].query, "exclude_column_names=true&api_key=KEY&rows=3");
+}
+
+#[test]
+fn data_as_json_lines() {
+    let server = server();
+
+    let output = run(&server, None, &[
+        "data", "WIKI/AAPL",
+        "--start", "2016-01-01",
+        "--end", "2016-02-10",
+        "--order", "desc",
+        "--collapse", "daily",
+        "--transform", "rdiff",
+        "--column", "4",
+        "--format", "jsonl",
+    ]);
+
+    let lines: Vec<serde_json::Value> = {
+        stdout(&output).lines().map(|line| serde_json::from_str(line).unwrap()).collect()
+    };
+
+    assert_eq!(lines.len(), DATA.lines().count());
+    assert_eq!(lines[0], serde_json::json!(["2016-02-10", 94.27, 95.7]));
+
+    // Every flag maps to a parameter of the query.
+    assert_eq!(server.requests()[0].query,
+               "exclude_column_names=true&order=desc&collapse=daily&transform=rdiff&\
+                end_date=2016-02-10&start_date=2016-01-01&column_index=4");
+}
+
+#[test]
+fn codes() {
+    let server = server();
+    let output = run(&server, Some("KEY"), &["codes", "WIKI"]);
+
+    assert_eq!(stdout(&output),
+               "WIKI/AAPL,Apple Inc (AAPL) Prices\n\
+                WIKI/MSFT,\"Microsoft Corporation, (MSFT) Prices\"\n");
+}
+
+#[test]
+fn searches() {
+    let server = server();
+
+    let output = run(&server, None, &["search", "--db", "WIKI", "--per-page", "1", "apple"]);
+    assert!(stdout(&output).starts_with("WIKI/"), "{}", stdout(&output));
+    assert_eq!(server.requests()[0].query, "query=apple&per_page=1&database_code=WIKI");
+
+    let output = run(&server, None, &["search", "stock", "prices"]);
+    assert!(!stdout(&output).is_empty());
+    assert_eq!(server.requests()[1].query, "query=stock+prices");
+}
+
+#[test]
+fn invocation_errors() {
+    let server = server();
+
+    for args in &[
+        &[][..],
+        &["download", "WIKI"][..],
+        &["data", "WIKI"][..],
+        &["data", "WIKI/AAPL", "--rows", "ten"][..],
+        &["data", "WIKI/AAPL", "--format", "xml"][..],
+        &["data", "WIKI/AAPL", "--start"][..],
+        &["codes", "WIKI", "--rows", "3"][..],
+    ] {
+        let output = run(&server, None, args);
+
+        assert_eq!(output.status.code(), Some(2), "{:?}", args);
+        assert!(String::from_utf8_lossy(&output.stderr).contains("usage: quandl-fetch"));
+    }
+
+    // Nothing was sent.
+    assert_eq!(server.hits(), 0);
+
+    let output = run(&server, None, &["--help"]);
+    assert!(stdout(&output).starts_with("usage: quandl-fetch"));
+}
+
+#[test]
+fn query_errors() {
+    let server = server();
+    let output = run(&server, None, &["data", "WIKI/MSFT"]);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("quandl-fetch: "));
+
+    // Invalid arguments are reported without being sent.
+    let output = run(&server, None, &["data", "WIKI/AAPL", "--column", "0"]);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("column_index"));
+    assert_eq!(server.hits(), 1);
+}
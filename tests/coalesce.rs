@@ -0,0 +1,82 @@
+extern crate quandl_v3;
+
+mod common;
+
+use std::sync::{Arc, Barrier};
+use std::thread::{sleep, spawn};
+use std::time::Duration;
+
+use quandl_v3::prelude::*;
+
+use common::{MockServer, Response};
+
+static DATASET_METADATA: &str = include_str!("fixtures/dataset_metadata.json");
+
+/// Server answering metadata queries of WIKI/AAPL slowly enough for concurrent queries to overlap.
+///
+fn server() -> MockServer {
+    MockServer::start(|request| {
+        sleep(Duration::from_millis(300));
+
+        match &request.path[..] {
+            "/api/v3/datasets/WIKI/AAPL/metadata.json" => Response::json(DATASET_METADATA),
+            _ => Response::not_found(),
+        }
+    })
+}
+
+/// Send the metadata query of WIKI/`code` from 10 threads at once.
+///
+fn send_concurrently(server: &MockServer, code: &str, coalesce: bool)
+    -> Vec<quandl_v3::Result<DatasetMetadata>>
+{
+    let mut query = DatasetMetadataQuery::new("WIKI", code);
+    query.base_url(server.url()).client_config(ClientConfig::new().coalesce(coalesce));
+
+    let barrier = Arc::new(Barrier::new(10));
+
+    let threads: Vec<_> = {
+        (0..10).map(|_| {
+            let query = query.clone();
+            let barrier = barrier.clone();
+
+            spawn(move || {
+                barrier.wait();
+                query.send()
+            })
+        }).collect()
+    };
+
+    threads.into_iter().map(|x| x.join().unwrap()).collect()
+}
+
+#[test]
+fn identical_requests_are_sent_once() {
+    let server = server();
+    let results = send_concurrently(&server, "AAPL", true);
+
+    assert_eq!(server.hits(), 1);
+    assert!(results.iter().all(|x| x.as_ref().unwrap().dataset_code == "AAPL"));
+
+    // Nothing is kept once the request completed.
+    send_concurrently(&server, "AAPL", true);
+    assert_eq!(server.hits(), 2);
+}
+
+#[test]
+fn errors_are_shared_as_well() {
+    let server = server();
+    let results = send_concurrently(&server, "MSFT", true);
+
+    assert_eq!(server.hits(), 1);
+    assert!(results.iter().all(|x| x == &results[0] && x.is_err()));
+}
+
+#[test]
+fn coalescing_is_opt_in() {
+    assert!(!ClientConfig::new().is_coalescing());
+
+    let server = server();
+    send_concurrently(&server, "AAPL", false);
+    assert_eq!(server.hits(), 10);
+}
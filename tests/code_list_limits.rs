@@ -0,0 +1,163 @@
+#![cfg(feature = "zip")]
+
+extern crate quandl_v3;
+extern crate zip;
+
+mod common;
+
+use std::io::{Cursor, Write};
+
+use quandl_v3::Error;
+use quandl_v3::prelude::*;
+
+use common::{MockServer, Response};
+
+/// 2 codes, 93 bytes once unzipped.
+///
+static CODES: &[u8] = include_bytes!("fixtures/codes.zip");
+
+/// Codes of the synthetic code list, and the length of their names.
+///
+const LARGE_CODES: usize = 5_000;
+const NAME_LENGTH: usize = 10_000;
+
+/// A code list of `LARGE_CODES` codes, whose names make it 50 MB once unzipped but only tens of
+/// kilobytes zipped.
+///
+fn large_code_list() -> Vec<u8> {
+    let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    writer.start_file("WIKI-datasets-codes.csv", zip::write::FileOptions::default()).unwrap();
+
+    let name = "x".repeat(NAME_LENGTH);
+    writeln!(writer, "code,name").unwrap();
+
+    for index in 0..LARGE_CODES {
+        writeln!(writer, "WIKI/C{:05},{}", index, name).unwrap();
+    }
+
+    writer.finish().unwrap().into_inner()
+}
+
+fn send(server: &MockServer, configure: impl Fn(&mut CodeListQuery)) -> Result<Vec<Code>, Error> {
+    let mut query = CodeListQuery::new("WIKI");
+    query.base_url(server.url());
+    configure(&mut query);
+    query.send()
+}
+
+/// Resident set size of the process, and its peak, in kilobytes.
+///
+#[cfg(target_os = "linux")]
+fn memory() -> (u64, u64) {
+    let status = std::fs::read_to_string("/proc/self/status").unwrap();
+
+    let field = |name: &str| -> u64 {
+        let line = status.lines().find(|line| line.starts_with(name)).unwrap();
+        line.split_whitespace().nth(1).unwrap().parse().unwrap()
+    };
+
+    (field("VmRSS:"), field("VmHWM:"))
+}
+
+#[test]
+fn compressed_size() {
+    let len = CODES.len() as u64;
+
+    let codes = CodeListQuery::new("WIKI").max_compressed_bytes(len).decode(CODES).unwrap();
+    assert_eq!(codes.len(), 2);
+
+    assert_eq!(CodeListQuery::new("WIKI").max_compressed_bytes(len - 1).decode(CODES),
+               Err(Error::ResponseTooLarge { limit: len - 1, observed: len }));
+}
+
+#[test]
+fn compressed_size_of_downloads() {
+    let server = MockServer::routes(vec![
+        ("/api/v3/databases/WIKI/codes", Response::new(200).body(CODES)),
+    ]);
+
+    let len = CODES.len() as u64;
+
+    assert_eq!(send(&server, |_| ()).unwrap().len(), 2);
+
+    // The advertised size of the archive is checked before it is downloaded.
+    match send(&server, |query| { query.max_compressed_bytes(100); }) {
+        Err(Error::ResponseTooLarge { limit: 100, observed }) => assert_eq!(observed, len),
+        other => panic!("{:?}", other),
+    }
+
+    // The lesser of the two limits applies.
+    match send(&server, |query| { query.max_compressed_bytes(100).max_response_bytes(50); }) {
+        Err(Error::ResponseTooLarge { limit: 50, observed }) => assert_eq!(observed, len),
+        other => panic!("{:?}", other),
+    }
+
+    match send(&server, |query| { query.max_compressed_bytes(50).max_response_bytes(100); }) {
+        Err(Error::ResponseTooLarge { limit: 50, observed }) => assert_eq!(observed, len),
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn largest_limits() {
+    let server = MockServer::routes(vec![
+        ("/api/v3/databases/WIKI/codes", Response::new(200).body(CODES)),
+    ]);
+
+    let codes = {
+        send(&server, |query| {
+            query.max_compressed_bytes(u64::MAX)
+                 .max_uncompressed_bytes(u64::MAX)
+                 .max_unzip_ratio(u64::MAX);
+        })
+    };
+
+    assert_eq!(codes.unwrap().len(), 2);
+
+    let mut query = CodeListQuery::new("WIKI");
+    query.max_compressed_bytes(u64::MAX)
+         .max_uncompressed_bytes(u64::MAX)
+         .max_unzip_ratio(u64::MAX);
+
+    let archive = large_code_list();
+    assert_eq!(query.decode(&archive).unwrap().len(), LARGE_CODES);
+}
+
+#[test]
+fn large_code_lists() {
+    let archive = large_code_list();
+    let unzipped = (10 + LARGE_CODES * (13 + NAME_LENGTH)) as u64;
+
+    // Far more than the default ratio allows, as for a zip bomb.
+    match CodeListQuery::new("WIKI").decode(&archive) {
+        Err(Error::UnzippedTooLarge { observed, .. }) => assert_eq!(observed, unzipped),
+        other => panic!("{:?}", other.map(|codes| codes.len())),
+    }
+
+    let mut query = CodeListQuery::new("WIKI");
+    query.max_unzip_ratio(u64::MAX).max_uncompressed_bytes(unzipped - 1);
+
+    assert_eq!(query.decode(&archive).map(|codes| codes.len()),
+               Err(Error::UnzippedTooLarge { limit: unzipped - 1, observed: unzipped }));
+
+    // Overridden for a genuinely huge database.
+    query.max_uncompressed_bytes(unzipped);
+
+    #[cfg(target_os = "linux")]
+    let (before, _) = memory();
+
+    let codes = query.decode(&archive).unwrap();
+
+    assert_eq!(codes.len(), LARGE_CODES);
+    assert_eq!(codes[LARGE_CODES - 1].dataset_code, "C04999");
+    assert_eq!(codes[LARGE_CODES - 1].name.len(), NAME_LENGTH);
+
+    // The names are held by the codes, but the list is never held whole besides them.
+    #[cfg(target_os = "linux")]
+    {
+        let (_, peak) = memory();
+        let names = (LARGE_CODES * NAME_LENGTH / 1_024) as u64;
+
+        assert!(peak - before < names * 3 / 2, "{} KiB for {} KiB of names", peak - before, names);
+    }
+}
@@ -0,0 +1,155 @@
+#![cfg(feature = "codegen")]
+
+extern crate quandl_v3;
+extern crate serde_json;
+#[macro_use] extern crate serde_derive;
+
+use std::collections::BTreeMap;
+
+use quandl_v3::codegen::{field_names, generate_row_struct, generate_schema_struct};
+use quandl_v3::prelude::*;
+
+static DATASET_METADATA: &str = include_str!("fixtures/dataset_metadata.json");
+static WIKI_AAPL: &str = include_str!("fixtures/codegen_wiki_aapl.rs");
+
+// The snapshot is compiled as well, to make sure the generated code is valid.
+include!("fixtures/codegen_wiki_aapl.rs");
+
+fn dataset_metadata() -> DatasetMetadata {
+    let tree: BTreeMap<String, DatasetMetadata> = serde_json::from_str(DATASET_METADATA).unwrap();
+    tree["dataset"].clone()
+}
+
+fn names(column_names: &[&str]) -> Vec<String> {
+    field_names(column_names)
+}
+
+#[test]
+fn snapshot() {
+    assert_eq!(generate_row_struct(&dataset_metadata(), "WikiAaplRow"), WIKI_AAPL);
+}
+
+#[test]
+fn generated_struct_decodes_rows() {
+    let data = {
+        b"2016-02-29,96.86,98.23,96.65,96.69,35216277.0,0.0,1.0,\
+          96.86,98.23,96.65,96.69,35216277.0\n\
+          2016-02-26,97.2,98.02,96.58,96.91,28991136.0,,1.0,\
+          97.2,98.02,96.58,96.91,28991136.0\n"
+    };
+
+    let rows: Vec<WikiAaplRow> = DataQuery::new("WIKI", "AAPL").decode(&data[..]).unwrap();
+
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0].date, "2016-02-29");
+    assert_eq!(rows[0].adj_close, Some(96.69));
+    assert_eq!(rows[0].split_ratio, Some(1.0));
+    assert_eq!(rows[1].ex_dividend, None);
+}
+
+#[test]
+fn separators_and_case() {
+    assert_eq!(names(&["Adj. Close", "AdjClose", "adj_close", "ADJ CLOSE", "  Adj.--Close  "]),
+               ["adj_close", "adj_close_2", "adj_close_3", "adj_close_4", "adj_close_5"]);
+
+    assert_eq!(names(&["PERatio", "peRatio", "EPS (TTM)", "Open Interest (OI)", "USDollar"]),
+               ["pe_ratio", "pe_ratio_2", "eps_ttm", "open_interest_oi", "us_dollar"]);
+
+    assert_eq!(names(&["Value/Share", "Net\tIncome\n", "a__b", "x1Y2"]),
+               ["value_share", "net_income", "a_b", "x1y2"]);
+}
+
+#[test]
+fn symbols() {
+    assert_eq!(names(&["% Change", "Change %", "# of Trades", "S&P 500", "Total+Tax", "Rate%"]),
+               ["pct_change", "change_pct", "num_of_trades", "s_and_p_500", "total_plus_tax",
+                "rate_pct"]);
+}
+
+#[test]
+fn non_ascii() {
+    assert_eq!(names(&["Prix (€)", "Größe", "日付", "Value €"]),
+               ["prix", "gr_e", "column_2", "value"]);
+}
+
+#[test]
+fn leading_digits() {
+    assert_eq!(names(&["52 Week High", "10Y", "1", "2-Year Yield"]),
+               ["col_52_week_high", "col_10y", "col_1", "col_2_year_yield"]);
+}
+
+#[test]
+fn keywords() {
+    assert_eq!(names(&["Type", "Match", "Self", "Async", "Types", "Type 2"]),
+               ["type_", "match_", "self_", "async_", "types", "type_2"]);
+}
+
+#[test]
+fn empty_names() {
+    assert_eq!(names(&["", "---", " ", "Value"]), ["column_0", "column_1", "column_2", "value"]);
+}
+
+#[test]
+fn duplicates_never_collide() {
+    // `value_2` is already taken when `Value 2` comes, and so is `value_2` for the last `Value`.
+    assert_eq!(names(&["Value", "Value", "Value 2", "Value"]),
+               ["value", "value_2", "value_2_2", "value_3"]);
+
+    assert_eq!(names(&["", "column_0"]), ["column_0", "column_0_2"]);
+    assert_eq!(names(&["type", "type_"]), ["type_", "type_2"]);
+}
+
+#[test]
+fn awkward_metadata() {
+    let mut metadata = dataset_metadata();
+    metadata.name = "Prices\nand */ comments".to_string();
+    metadata.column_names = vec!["Date".to_string(), "Type".to_string(), "Type".to_string()];
+
+    assert_eq!(generate_row_struct(&metadata, "Row"), "\
+/// Row of the Quandl dataset `WIKI/AAPL`: Prices and */ comments
+///
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Row {
+    /// Date
+    ///
+    pub date: String,
+
+    /// Type
+    ///
+    pub type_: Option<f64>,
+
+    /// Type
+    ///
+    pub type_2: Option<f64>,
+}
+");
+}
+
+#[test]
+fn schema_struct() {
+    let data = b"Date,Close,Note\n2016-03-01,100.5,\n2016-02-29,,split,x\n";
+    let schema = Schema::infer(data).unwrap();
+
+    assert_eq!(generate_schema_struct(&schema, "Row"), "\
+/// Row of a Quandl dataset, as inferred from a sample of its rows.
+///
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Row {
+    /// Date
+    ///
+    pub date: String,
+
+    /// Close
+    ///
+    pub close: Option<f64>,
+
+    /// Note
+    ///
+    pub note: Option<String>,
+
+    /// Column 3 (unnamed).
+    ///
+    pub column_3: Option<String>,
+}
+");
+}
@@ -0,0 +1,79 @@
+extern crate quandl_v3;
+extern crate serde_json;
+
+use quandl_v3::prelude::*;
+
+static COLUMN_NAMES: &str = include_str!("fixtures/column_names.json");
+
+/// Column names found in Quandl's datasets, each with its expected base name and unit.
+///
+fn corpus() -> Vec<(String, String, Option<String>)> {
+    serde_json::from_str(COLUMN_NAMES).unwrap()
+}
+
+#[test]
+fn corpus_of_column_names() {
+    let mismatches: Vec<String> = {
+        corpus().into_iter()
+            .filter_map(|(raw, base_name, unit)| {
+                let info = ColumnInfo::parse(&raw);
+                let expected = ColumnInfo { base_name, unit, raw };
+
+                if info == expected {
+                    None
+                } else {
+                    Some(format!("{:?}: expected {:?}", info, expected))
+                }
+            })
+            .collect()
+    };
+
+    assert!(mismatches.is_empty(), "{}", mismatches.join("\n"));
+}
+
+#[test]
+fn names_without_units_are_kept_whole() {
+    for (raw, base_name, unit) in corpus() {
+        if unit.is_none() {
+            assert_eq!(base_name, raw.trim());
+        }
+    }
+
+    assert_eq!(ColumnInfo::parse(""), ColumnInfo {
+        base_name: String::new(),
+        unit: None,
+        raw: String::new(),
+    });
+}
+
+#[test]
+fn units_are_as_written() {
+    let info = ColumnInfo::parse("Revenue ( usd  MM )");
+    assert_eq!(info.unit, None);
+
+    let info = ColumnInfo::parse("Revenue ( USD  MM )");
+    assert_eq!((&info.base_name[..], info.unit.as_deref()), ("Revenue", Some("USD  MM")));
+    assert_eq!(info.raw, "Revenue ( USD  MM )");
+}
+
+#[test]
+fn columns_of_dataset_metadata() {
+    let mut metadata: DatasetMetadata = {
+        let json: serde_json::Value = {
+            serde_json::from_str(include_str!("fixtures/dataset_metadata.json")).unwrap()
+        };
+
+        serde_json::from_value(json["dataset"].clone()).unwrap()
+    };
+
+    metadata.column_names = vec!["Date".to_string(), "Value (USD mm)".to_string()];
+
+    assert_eq!(metadata.columns(), vec![
+        ColumnInfo { base_name: "Date".to_string(), unit: None, raw: "Date".to_string() },
+        ColumnInfo {
+            base_name: "Value".to_string(),
+            unit: Some("USD mm".to_string()),
+            raw: "Value (USD mm)".to_string(),
+        },
+    ]);
+}
@@ -0,0 +1,191 @@
+#![cfg(feature = "batch")]
+
+extern crate quandl_v3;
+extern crate serde_json;
+
+mod common;
+
+use quandl_v3::Error;
+use quandl_v3::merge::AlignedTable;
+use quandl_v3::prelude::*;
+
+use common::{MockServer, Response};
+
+static DATASET_DATA: &str = include_str!("fixtures/dataset_data.json");
+static DATASET_METADATA: &str = include_str!("fixtures/dataset_metadata.json");
+
+const COLUMNS: [&str; 4] = ["Date", "Open", "High", "Close"];
+
+/// Rows of WIKI/AAPL, the highest price missing on 2016-02-09.
+///
+const DATA: &str = "2016-02-10,94.27,95.7,94.99\n\
+                    2016-02-09,94.29,,95.01\n\
+                    2016-02-08,93.13,95.7,95.01\n";
+
+fn json(fixture: &str, edit: impl FnOnce(&mut serde_json::Value)) -> String {
+    let mut json: serde_json::Value = serde_json::from_str(fixture).unwrap();
+    json["dataset"]["column_names"] = serde_json::json!(COLUMNS);
+    edit(&mut json);
+    json.to_string()
+}
+
+/// Response to the query of the column at `index` of `DATA`, leaving out the dates without value.
+///
+fn column(index: usize) -> Response {
+    let rows: Vec<serde_json::Value> = {
+        DATA.lines()
+            .map(|line| line.split(',').collect::<Vec<_>>())
+            .filter(|fields| !fields[index].is_empty())
+            .map(|fields| serde_json::json!([fields[0], fields[index].parse::<f64>().unwrap()]))
+            .collect()
+    };
+
+    Response::json(json(DATASET_DATA, |json| json["dataset"]["data"] = rows.into()))
+}
+
+/// Server with the data of WIKI/AAPL, and its metadata if `with_metadata`.
+///
+fn server(with_metadata: bool) -> MockServer {
+    MockServer::start(move |request| {
+        match &request.path[..] {
+            "/api/v3/datasets/WIKI/AAPL/metadata.json" if with_metadata => {
+                Response::json(json(DATASET_METADATA, |_| ()))
+            },
+
+            "/api/v3/datasets/WIKI/AAPL/data.csv" if !request.query.contains("column_index") => {
+                Response::csv(DATA)
+            },
+
+            "/api/v3/datasets/WIKI/AAPL.json" => {
+                match (1..4).find(|x| request.query.contains(&format!("column_index={}", x))) {
+                    Some(index) => column(index),
+                    None => Response::not_found(),
+                }
+            },
+
+            _ => Response::not_found(),
+        }
+    })
+}
+
+fn query(server: &MockServer) -> DataQuery {
+    let mut query = DataQuery::new("WIKI", "AAPL");
+    query.base_url(server.url()).api_key("key");
+    query
+}
+
+fn paths(server: &MockServer) -> Vec<String> {
+    let mut paths: Vec<String> = server.requests().iter().map(|x| x.path.clone()).collect();
+    paths.sort();
+    paths
+}
+
+fn row(date: &str, values: Vec<Option<f64>>) -> DataRow {
+    DataRow { date: date.to_string(), values }
+}
+
+#[test]
+fn columns_fetched_one_by_one() {
+    let server = server(false);
+    let table = fetch_columns(&query(&server), &[3, 2], 2).unwrap();
+
+    assert_eq!(table.column_names, ["Date", "Close", "High"]);
+
+    assert_eq!(table.rows, [
+        row("2016-02-10", vec![Some(94.99), Some(95.7)]),
+        row("2016-02-09", vec![Some(95.01), None]),
+        row("2016-02-08", vec![Some(95.01), Some(95.7)]),
+    ]);
+
+    assert_eq!(paths(&server), [
+        "/api/v3/datasets/WIKI/AAPL.json",
+        "/api/v3/datasets/WIKI/AAPL.json",
+        "/api/v3/datasets/WIKI/AAPL/metadata.json",
+    ]);
+}
+
+#[test]
+fn columns_picked_from_the_whole_dataset() {
+    let server = server(true);
+    let mut query = query(&server);
+    query.order(Order::asc);
+
+    let table = fetch_columns(&query, &[3, 2], 2).unwrap();
+
+    assert_eq!(table.column_names, ["Date", "Close", "High"]);
+
+    assert_eq!(table.rows, [
+        row("2016-02-08", vec![Some(95.01), Some(95.7)]),
+        row("2016-02-09", vec![Some(95.01), None]),
+        row("2016-02-10", vec![Some(94.99), Some(95.7)]),
+    ]);
+
+    assert_eq!(paths(&server), [
+        "/api/v3/datasets/WIKI/AAPL/data.csv",
+        "/api/v3/datasets/WIKI/AAPL/metadata.json",
+    ]);
+
+    assert!(server.requests().iter().all(|x| x.query.contains("api_key=key")));
+}
+
+#[test]
+fn single_column() {
+    let server = server(true);
+    let table = fetch_columns(&query(&server), &[2], 1).unwrap();
+
+    assert_eq!(table.column_names, ["Date", "High"]);
+    assert_eq!(table.rows.len(), 2);
+
+    assert_eq!(paths(&server), [
+        "/api/v3/datasets/WIKI/AAPL.json",
+        "/api/v3/datasets/WIKI/AAPL/metadata.json",
+    ]);
+}
+
+#[test]
+fn columns_beyond_the_dataset() {
+    let server = server(true);
+
+    match fetch_columns(&query(&server), &[1, 4], 2) {
+        Err(Error::ValidationFailed(errors)) => {
+            assert_eq!(errors.len(), 1);
+            assert_eq!(errors[0].field, "column_index");
+            assert_eq!(errors[0].message, "4 is beyond the last column of WIKI/AAPL, 3.");
+        },
+
+        other => panic!("{:?}", other),
+    }
+
+    assert_eq!(server.hits(), 1);
+}
+
+#[test]
+#[should_panic(expected = "0 is the date column")]
+fn the_date_is_not_a_column() {
+    let server = server(true);
+    let _ = fetch_columns(&query(&server), &[0], 1);
+}
+
+#[test]
+fn align() {
+    let names = vec!["Date".to_string(), "A".to_string(), "B".to_string()];
+
+    let columns = vec![
+        vec![("2016-01-02".to_string(), Some(2.0)), ("2016-01-01".to_string(), Some(1.0))],
+        vec![("2016-01-03".to_string(), None), ("2016-01-02".to_string(), Some(20.0))],
+    ];
+
+    let table = AlignedTable::align(names.clone(), columns.clone(), Order::asc);
+
+    assert_eq!(table.column_names, names);
+
+    assert_eq!(table.rows, [
+        row("2016-01-01", vec![Some(1.0), None]),
+        row("2016-01-02", vec![Some(2.0), Some(20.0)]),
+        row("2016-01-03", vec![None, None]),
+    ]);
+
+    let mut reversed = table.rows.clone();
+    reversed.reverse();
+    assert_eq!(AlignedTable::align(names, columns, Order::desc).rows, reversed);
+}
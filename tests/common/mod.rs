@@ -0,0 +1,271 @@
+//! Minimal HTTP/1.1 server used to test the crate against canned responses.
+
+#![allow(dead_code)]
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::spawn;
+
+/// A request as received by the mock server.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub query: String,
+    pub headers: Vec<(String, String)>,
+}
+
+impl Request {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| &value[..])
+    }
+}
+
+/// A canned response.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn new(status: u16) -> Self {
+        Response { status, headers: vec![], body: vec![] }
+    }
+
+    pub fn json<S: AsRef<str>>(body: S) -> Self {
+        Response::new(200)
+            .header("Content-Type", "application/json; charset=utf-8")
+            .body(body.as_ref())
+    }
+
+    pub fn csv<S: AsRef<str>>(body: S) -> Self {
+        Response::new(200).header("Content-Type", "text/csv").body(body.as_ref())
+    }
+
+    pub fn not_found() -> Self {
+        Response::new(404)
+            .header("Content-Type", "application/json; charset=utf-8")
+            .body(concat!(r#"{"quandl_error":{"code":"QECx02","message":"#,
+                          r#""You have submitted an incorrect Quandl code. "#,
+                          r#"Please check your Quandl codes and try again."}}"#))
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn body<B: AsRef<[u8]>>(mut self, body: B) -> Self {
+        self.body = body.as_ref().to_vec();
+        self
+    }
+}
+
+/// Server answering every request with the response returned by its handler.
+///
+pub struct MockServer {
+    scheme: &'static str,
+    address: String,
+    requests: Arc<Mutex<Vec<Request>>>,
+    down: Arc<AtomicBool>,
+}
+
+impl MockServer {
+    pub fn start<F>(handler: F) -> Self
+        where F: Fn(&Request) -> Response + Send + Sync + 'static
+    {
+        MockServer::listen(None, handler)
+    }
+
+    /// Server answering over TLS with the certificate (and private key) of `identity`.
+    ///
+    pub fn start_tls<F>(identity: native_tls::Identity, handler: F) -> Self
+        where F: Fn(&Request) -> Response + Send + Sync + 'static
+    {
+        MockServer::listen(Some(native_tls::TlsAcceptor::new(identity).unwrap()), handler)
+    }
+
+    fn listen<F>(acceptor: Option<native_tls::TlsAcceptor>, handler: F) -> Self
+        where F: Fn(&Request) -> Response + Send + Sync + 'static
+    {
+        let scheme = if acceptor.is_some() { "https" } else { "http" };
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let requests = Arc::new(Mutex::new(vec![]));
+        let down = Arc::new(AtomicBool::new(false));
+        let handler = Arc::new(handler);
+
+        {
+            let requests = requests.clone();
+            let down = down.clone();
+
+            spawn(move || {
+                for stream in listener.incoming() {
+                    let stream = match stream {
+                        Ok(stream) => stream,
+                        Err(_) => continue,
+                    };
+
+                    // Closed right away, before anything is read.
+                    if down.load(Ordering::SeqCst) {
+                        continue;
+                    }
+
+                    let requests = requests.clone();
+                    let handler = handler.clone();
+                    let acceptor = acceptor.clone();
+
+                    spawn(move || {
+                        match acceptor {
+                            // Failed handshakes (e.g. an untrusted certificate) are not requests.
+                            Some(acceptor) => if let Ok(stream) = acceptor.accept(stream) {
+                                serve(stream, &*handler, &requests)
+                            },
+
+                            None => serve(stream, &*handler, &requests),
+                        }
+                    });
+                }
+            });
+        }
+
+        MockServer { scheme, address, requests, down }
+    }
+
+    /// Server answering requests by path (query string excluded), with a Quandl-like 404 error for
+    /// unknown paths.
+    ///
+    pub fn routes(routes: Vec<(&str, Response)>) -> Self {
+        let routes: Vec<(String, Response)> = {
+            routes.into_iter().map(|(path, response)| (path.to_string(), response)).collect()
+        };
+
+        MockServer::start(move |request| {
+            routes.iter()
+                .find(|(path, _)| *path == request.path)
+                .map(|(_, response)| response.clone())
+                .unwrap_or_else(Response::not_found)
+        })
+    }
+
+    /// Base URL to give to `ApiParameters::base_url`.
+    ///
+    pub fn url(&self) -> String {
+        format!("{}://{}/api/v3", self.scheme, self.address)
+    }
+
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// Every request received so far, in order of arrival.
+    ///
+    pub fn requests(&self) -> Vec<Request> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// Close the connections as soon as they are accepted while `down`, as an unreachable host
+    /// would (short of refusing them).
+    ///
+    pub fn set_down(&self, down: bool) {
+        self.down.store(down, Ordering::SeqCst);
+    }
+
+    pub fn hits(&self) -> usize {
+        self.requests.lock().unwrap().len()
+    }
+}
+
+fn serve<S, F>(mut stream: S, handler: &F, requests: &Mutex<Vec<Request>>)
+    where S: Read + Write,
+          F: Fn(&Request) -> Response
+{
+    let mut reader = BufReader::new(&mut stream);
+
+    let mut line = String::new();
+
+    if reader.read_line(&mut line).is_err() || line.is_empty() {
+        return;
+    }
+
+    let mut parts = line.split_whitespace();
+    let method = parts.next().unwrap_or("").to_string();
+    let target = parts.next().unwrap_or("").to_string();
+
+    let mut headers = vec![];
+
+    loop {
+        let mut line = String::new();
+
+        if reader.read_line(&mut line).is_err() || line.trim().is_empty() {
+            break;
+        }
+
+        if let Some(index) = line.find(':') {
+            headers.push((line[..index].trim().to_string(), line[index + 1..].trim().to_string()));
+        }
+    }
+
+    let content_length: usize = {
+        headers.iter()
+            .find(|(key, _): &&(String, String)| key.eq_ignore_ascii_case("content-length"))
+            .and_then(|(_, value)| value.parse().ok())
+            .unwrap_or(0)
+    };
+
+    let mut body = vec![0; content_length];
+    let _ = reader.read_exact(&mut body);
+
+    let (path, query) = {
+        match target.find('?') {
+            Some(index) => (target[..index].to_string(), target[index + 1..].to_string()),
+            None => (target.clone(), String::new()),
+        }
+    };
+
+    drop(reader);
+
+    let request = Request { method, path, query, headers };
+    requests.lock().unwrap().push(request.clone());
+
+    let response = handler(&request);
+    write_response(&mut stream, &request, &response);
+}
+
+fn write_response<S: Write>(stream: &mut S, request: &Request, response: &Response) {
+    let mut head = format!("HTTP/1.1 {} Mock\r\nConnection: close\r\n", response.status);
+
+    // Responses with a transfer encoding are sent without any length, e.g. to test its absence.
+    let has_length = {
+        response.headers.iter().any(|(key, _)| {
+            key.eq_ignore_ascii_case("content-length") ||
+                key.eq_ignore_ascii_case("transfer-encoding")
+        })
+    };
+
+    if !has_length {
+        head.push_str(&format!("Content-Length: {}\r\n", response.body.len()));
+    }
+
+    for (key, value) in &response.headers {
+        head.push_str(&format!("{}: {}\r\n", key, value));
+    }
+
+    head.push_str("\r\n");
+
+    let _ = stream.write_all(head.as_bytes());
+
+    if request.method != "HEAD" {
+        let _ = stream.write_all(&response.body);
+    }
+
+    let _ = stream.flush();
+}
@@ -0,0 +1,185 @@
+extern crate quandl_v3;
+extern crate serde_json;
+
+mod common;
+
+use std::fs;
+use std::path::PathBuf;
+
+use quandl_v3::Error;
+use quandl_v3::compat::{check_payload, Incompatibility, PayloadKind};
+use quandl_v3::prelude::*;
+
+use common::{MockServer, Response};
+
+static DATASET_SEARCH: &str = include_str!("fixtures/dataset_search.json");
+static DATABASE_METADATA: &str = include_str!("fixtures/database_metadata.json");
+
+fn versioned() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/versioned")
+}
+
+/// Kind, date and path of each capture of `versioned/index.json`, in its order.
+///
+fn captures() -> Vec<(PayloadKind, String, PathBuf)> {
+    let index: serde_json::Value = {
+        serde_json::from_slice(&fs::read(versioned().join("index.json")).unwrap()).unwrap()
+    };
+
+    index.as_array().unwrap().iter().map(|capture| {
+        let kind = capture["kind"].as_str().unwrap();
+        let date = capture["date"].as_str().unwrap().to_string();
+        assert!(!capture["note"].as_str().unwrap().is_empty(), "{}", capture);
+
+        let path = versioned().join(kind).join(format!("{}.json", date));
+        (PayloadKind::from_name(kind).unwrap(), date, path)
+    }).collect()
+}
+
+/// Decode `payload` as the crate does when replying to a query, through a server sending it.
+///
+fn send(kind: PayloadKind, payload: Vec<u8>) -> quandl_v3::Result<()> {
+    let server = {
+        MockServer::start(move |_| {
+            let status = if kind == PayloadKind::ErrorResponse { 422 } else { 200 };
+
+            Response::new(status)
+                .header("Content-Type", "application/json; charset=utf-8")
+                .body(&payload)
+        })
+    };
+
+    match kind {
+        PayloadKind::DatabaseMetadata => {
+            DatabaseMetadataQuery::new("WIKI").base_url(server.url()).send().map(drop)
+        },
+
+        PayloadKind::DatasetMetadata => {
+            DatasetMetadataQuery::new("WIKI", "AAPL").base_url(server.url()).send().map(drop)
+        },
+
+        PayloadKind::DatabaseList => {
+            DatabaseSearch::new().base_url(server.url()).send().map(drop)
+        },
+
+        PayloadKind::DatasetList => {
+            DatasetSearch::new("WIKI").base_url(server.url()).send().map(drop)
+        },
+
+        PayloadKind::Dataset => {
+            let mut query = DataAndMetadataQuery::new("WIKI", "AAPL");
+            query.base_url(server.url());
+            let dataset: quandl_v3::Result<Dataset<Vec<serde_json::Value>>> = query.send();
+            dataset.map(drop)
+        },
+
+        PayloadKind::ErrorResponse => {
+            match DatabaseMetadataQuery::new("WIKI").base_url(server.url()).send() {
+                Err(Error::ApiCallFailed(_)) => Ok(()),
+                Err(e) => Err(e),
+                Ok(_) => panic!("error response decoded as metadata"),
+            }
+        },
+    }
+}
+
+#[test]
+fn every_capture_is_indexed() {
+    let indexed: Vec<PathBuf> = captures().into_iter().map(|(_, _, path)| path).collect();
+
+    for kind in &PayloadKind::ALL {
+        let mut dates = vec![];
+
+        for entry in fs::read_dir(versioned().join(kind.name())).unwrap() {
+            let path = entry.unwrap().path();
+            assert!(indexed.contains(&path), "{} is not in index.json", path.display());
+            dates.push(path);
+        }
+
+        assert!(!dates.is_empty(), "no capture of {}", kind);
+    }
+
+    for path in &indexed {
+        assert!(path.is_file(), "{} is indexed but missing", path.display());
+    }
+}
+
+#[test]
+fn historical_payloads_still_decode() {
+    for (kind, date, path) in captures() {
+        let payload = fs::read(&path).unwrap();
+
+        assert_eq!(check_payload(kind, &payload), vec![], "{} of {}", kind, date);
+
+        if let Err(e) = send(kind, payload) {
+            panic!("{} of {} no longer decodes: {}", kind, date, e);
+        }
+    }
+}
+
+#[test]
+fn incompatibilities_are_located() {
+    let at = |path: &str, message: &str| {
+        Incompatibility { path: path.to_string(), message: message.to_string() }
+    };
+
+    let renamed = DATASET_SEARCH.replace("\"name\":", "\"title\":");
+    assert_eq!(check_payload(PayloadKind::DatasetList, renamed.as_bytes()),
+               vec![at("/datasets/0", "missing field `name`")]);
+
+    let null = DATASET_SEARCH.replace("\"column_names\":[\"Date\"", "\"column_names\":[null");
+    assert_eq!(check_payload(PayloadKind::DatasetList, null.as_bytes()),
+               vec![at("/datasets/0/column_names",
+                       "invalid type: null, expected a string")]);
+
+    let count = DATABASE_METADATA.replace("3179", "\"many\"");
+    let found = check_payload(PayloadKind::DatabaseMetadata, count.as_bytes());
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].path, "/database/datasets_count");
+    assert!(!found[0].message.contains(" at line "), "{}", found[0]);
+
+    // Every incompatible element is listed, not just the first.
+    let mut listing: serde_json::Value = serde_json::from_str(DATASET_SEARCH).unwrap();
+    listing["datasets"] = serde_json::json!([null, 3, {}]);
+
+    let found = check_payload(PayloadKind::DatasetList, listing.to_string().as_bytes());
+    let paths: Vec<&str> = found.iter().map(|x| &x.path[..]).collect();
+    assert_eq!(paths, vec!["/datasets/0", "/datasets/1", "/datasets/2"]);
+
+    let found = check_payload(PayloadKind::DatabaseList, DATASET_SEARCH.as_bytes());
+    assert_eq!(found, vec![at("", "missing field `databases`")]);
+
+    let found = check_payload(PayloadKind::Dataset, br#"{"dataset":{"data":[[], 1]}}"#);
+    assert_eq!(found[0], at("/dataset/data/1", "expected an array."));
+    assert_eq!(found[1].path, "/dataset");
+
+    let found = check_payload(PayloadKind::DatabaseMetadata, b"<html>Maintenance</html>");
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].path, "");
+    assert!(found[0].to_string().starts_with("payload: "), "{}", found[0]);
+
+    assert_eq!(check_payload(PayloadKind::ErrorResponse, DATABASE_METADATA.as_bytes()),
+               vec![at("", "missing field `quandl_error`")]);
+}
+
+#[test]
+fn payload_kinds() {
+    let kind = |url: &str| PayloadKind::of_url(format!("https://www.quandl.com/api/v3{}", url));
+
+    assert_eq!(kind("/databases.json?page=2"), Some(PayloadKind::DatabaseList));
+    assert_eq!(kind("/databases/WIKI.json"), Some(PayloadKind::DatabaseMetadata));
+    assert_eq!(kind("/datasets.json?query=oil"), Some(PayloadKind::DatasetList));
+    assert_eq!(kind("/datasets/WIKI/AAPL/metadata.json"), Some(PayloadKind::DatasetMetadata));
+    assert_eq!(kind("/datasets/WIKI/AAPL.json?rows=1"), Some(PayloadKind::Dataset));
+    assert_eq!(kind("/datasets/WIKI/AAPL/data.csv"), None);
+    assert_eq!(kind("/databases/WIKI/codes"), None);
+    assert_eq!(PayloadKind::of_url("not a url"), None);
+
+    for kind in &PayloadKind::ALL {
+        let name = serde_json::to_string(kind).unwrap();
+        assert_eq!(name, format!("\"{}\"", kind));
+        assert_eq!(PayloadKind::from_name(kind.name()), Some(*kind));
+    }
+
+    assert_eq!(PayloadKind::from_name("databases"), None);
+}
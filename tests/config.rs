@@ -0,0 +1,117 @@
+extern crate quandl_v3;
+
+use std::sync::{Arc, Barrier};
+use std::thread;
+
+use quandl_v3::config::Config;
+use quandl_v3::prelude::*;
+
+fn with_key(api_key: &str) -> Config {
+    Config { api_key: Some(ApiKey::new(api_key)), .. Config::default() }
+}
+
+fn current_key() -> Option<String> {
+    Config::current().api_key.as_ref().map(|key| key.expose_secret().to_string())
+}
+
+#[test]
+fn nested_scopes() {
+    let outer = Config::override_scope(with_key("OUTER"));
+    assert_eq!(current_key().as_deref(), Some("OUTER"));
+
+    {
+        let _inner = Config::override_scope(with_key("INNER"));
+        assert_eq!(current_key().as_deref(), Some("INNER"));
+
+        {
+            let _empty = Config::override_scope(Config::default());
+            assert_eq!(*Config::current(), Config::default());
+        }
+
+        assert_eq!(current_key().as_deref(), Some("INNER"));
+    }
+
+    assert_eq!(current_key().as_deref(), Some("OUTER"));
+
+    // Ending a scope ends those nested in it.
+    let _inner = Config::override_scope(with_key("INNER"));
+    drop(outer);
+
+    assert_ne!(current_key().as_deref(), Some("OUTER"));
+    assert_ne!(current_key().as_deref(), Some("INNER"));
+}
+
+#[test]
+fn queries_take_their_defaults_from_the_scope() {
+    let _guard = Config::override_scope(Config {
+        api_key: Some(ApiKey::new(" KEY ")),
+        base_url: Some("http://localhost:8080/api/v3".to_string()),
+        max_response_bytes: Some(1_000),
+        .. Config::default()
+    });
+
+    let query = DatabaseMetadataQuery::new("WIKI");
+    assert_eq!(query.url(), "http://localhost:8080/api/v3/databases/WIKI.json?api_key=KEY");
+
+    // The parameters of a query override them.
+    let mut query = DataQuery::new("WIKI", "AAPL");
+    query.base_url("https://example.com/api/v3").api_key("OTHER");
+
+    assert!(ApiCall::<Vec<(String, f64)>>::url(&query).starts_with("https://example.com/"));
+    assert!(ApiCall::<Vec<(String, f64)>>::url(&query).ends_with("&api_key=OTHER"));
+}
+
+#[test]
+fn queries_keep_the_defaults_they_were_created_with() {
+    let query = {
+        let _guard = Config::override_scope(with_key("KEY"));
+        DatasetSearch::new("WIKI")
+    };
+
+    assert!(query.url().contains("api_key=KEY"), "{}", query.url());
+    assert!(!DatasetSearch::new("WIKI").url().contains("api_key=KEY"));
+}
+
+#[test]
+fn scopes_are_isolated_between_threads() {
+    let barrier = Arc::new(Barrier::new(2));
+
+    let other = {
+        let barrier = barrier.clone();
+
+        thread::spawn(move || {
+            let _guard = Config::override_scope(with_key("THEIRS"));
+            barrier.wait();
+            let key = current_key();
+            barrier.wait();
+            key
+        })
+    };
+
+    let _guard = Config::override_scope(with_key("MINE"));
+    barrier.wait();
+    assert_eq!(current_key().as_deref(), Some("MINE"));
+    barrier.wait();
+
+    assert_eq!(other.join().unwrap().as_deref(), Some("THEIRS"));
+
+    // A thread outside any scope does not see the overrides of others.
+    let key = thread::spawn(current_key).join().unwrap();
+    assert_ne!(key.as_deref(), Some("MINE"));
+}
+
+#[test]
+fn global_configuration() {
+    let previous = Config::set_global(with_key("GLOBAL"));
+
+    assert_eq!(current_key().as_deref(), Some("GLOBAL"));
+    assert_eq!(thread::spawn(current_key).join().unwrap().as_deref(), Some("GLOBAL"));
+
+    {
+        let _guard = Config::override_scope(with_key("SCOPED"));
+        assert_eq!(current_key().as_deref(), Some("SCOPED"));
+    }
+
+    assert_eq!(current_key().as_deref(), Some("GLOBAL"));
+    assert_eq!(*Config::set_global((*previous).clone()), with_key("GLOBAL"));
+}
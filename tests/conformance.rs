@@ -0,0 +1,174 @@
+#![cfg(feature = "batch")]
+
+extern crate quandl_v3;
+extern crate serde_json;
+extern crate url;
+
+use std::collections::HashSet;
+use std::convert::TryFrom;
+
+use url::Url;
+
+use quandl_v3::Error;
+use quandl_v3::conformance::{self, QueryConfig, QueryType, Vector};
+use quandl_v3::prelude::*;
+use quandl_v3::queue::QuerySpec;
+
+fn published() -> Vec<Vector> {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/url_vectors.json");
+    serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+}
+
+/// Spec of the query of type `query_type` rebuilt from `url` with `TryFrom<Url>`.
+///
+fn rebuilt(query_type: QueryType, url: Url) -> QuerySpec {
+    let url = {
+        match query_type {
+            QueryType::DatabaseMetadata => {
+                ApiCall::<DatabaseMetadata>::parsed_url(&DatabaseMetadataQuery::try_from(url)
+                    .unwrap())
+            },
+
+            QueryType::DatasetMetadata => {
+                ApiCall::<DatasetMetadata>::parsed_url(&DatasetMetadataQuery::try_from(url)
+                    .unwrap())
+            },
+
+            QueryType::DatabaseSearch => {
+                ApiCall::<DatabaseList>::parsed_url(&DatabaseSearch::try_from(url).unwrap())
+            },
+
+            QueryType::DatasetSearch => {
+                ApiCall::<DatasetList>::parsed_url(&DatasetSearch::try_from(url).unwrap())
+            },
+
+            QueryType::DatasetListing => {
+                ApiCall::<Vec<DatasetMetadataLite>>::parsed_url(&DatasetListingQuery::try_from(url)
+                    .unwrap())
+            },
+
+            QueryType::CodeList => CodeListQuery::try_from(url).unwrap().parsed_url(),
+
+            QueryType::DatabaseDownload => {
+                DatabaseDownloadQuery::try_from(url).unwrap().parsed_url()
+            },
+
+            QueryType::Data => {
+                ApiCall::<Vec<Vec<String>>>::parsed_url(&DataQuery::try_from(url).unwrap())
+            },
+
+            QueryType::DataAndMetadata => {
+                ApiCall::<Dataset<Vec<String>>>::parsed_url(&DataAndMetadataQuery::try_from(url)
+                    .unwrap())
+            },
+        }
+    };
+
+    QuerySpec::from_url(&url.unwrap())
+}
+
+#[test]
+fn builders_conform_to_the_published_vectors() {
+    let vectors = published();
+    assert!(vectors.len() >= 100);
+
+    let mismatches = conformance::run(&vectors);
+    assert!(mismatches.is_empty(), "{}", serde_json::to_string_pretty(&mismatches).unwrap());
+}
+
+#[test]
+fn embedded_vectors_are_the_published_ones() {
+    assert_eq!(conformance::vectors(), published());
+}
+
+#[test]
+fn vectors_cover_every_query_type_and_parameter() {
+    let vectors = published();
+
+    let names: HashSet<_> = vectors.iter().map(|vector| &vector.name).collect();
+    assert_eq!(names.len(), vectors.len(), "names are unique");
+
+    let types: HashSet<_> = vectors.iter().map(|vector| vector.query.query_type).collect();
+    assert_eq!(types.len(), 9);
+
+    let fields: HashSet<String> = {
+        vectors.iter()
+            .flat_map(|vector| {
+                match serde_json::to_value(&vector.query).unwrap() {
+                    serde_json::Value::Object(fields) => fields.into_iter().map(|(k, _)| k),
+                    _ => unreachable!(),
+                }
+            })
+            .collect()
+    };
+
+    for field in &["type", "database_code", "dataset_code", "id", "api_key", "base_url",
+                   "api_version", "auto_uppercase", "query", "phrases", "exclude", "per_page",
+                   "page", "sort_by_last_updated", "partial", "rows", "limit", "order",
+                   "collapse", "transform", "start_date", "end_date", "column_index"] {
+        assert!(fields.contains(*field), "no vector sets {}", field);
+    }
+}
+
+#[test]
+fn expected_urls_are_canonical() {
+    for vector in published() {
+        let url = Url::parse(&vector.expected.url).unwrap();
+        assert!(url.query_pairs().all(|(k, _)| k != "api_key"), "{}", vector.name);
+        assert_eq!(rebuilt(vector.query.query_type, url), vector.expected, "{}", vector.name);
+    }
+}
+
+#[test]
+fn mismatches_are_reported() {
+    let vectors = published();
+
+    let mismatches = {
+        conformance::run_with(&vectors, |query| {
+            match query.query_type {
+                QueryType::DatabaseSearch => {
+                    Ok(QuerySpec { url: "https://www.quandl.com/api/v3/databases".to_string() })
+                },
+
+                QueryType::CodeList => Err(Error::ParsingFailed("unsupported".to_string())),
+                _ => query.spec(),
+            }
+        })
+    };
+
+    let count = |query_type| {
+        vectors.iter().filter(|vector| vector.query.query_type == query_type).count()
+    };
+
+    assert_eq!(mismatches.len(), count(QueryType::DatabaseSearch) + count(QueryType::CodeList));
+
+    let mismatch = &mismatches[0];
+    let vector = vectors.iter().find(|vector| vector.name == mismatch.name).unwrap();
+    assert_eq!(mismatch.expected, vector.expected);
+
+    assert!(mismatches.iter().any(|mismatch| {
+        mismatch.actual == Err(Error::ParsingFailed("unsupported".to_string()))
+    }));
+}
+
+#[test]
+fn invalid_configurations() {
+    let spec = |json: &str| serde_json::from_str::<QueryConfig>(json).unwrap().spec();
+
+    match spec(r#"{"type": "database_metadata", "database_code": "WIKI", "rows": 1}"#) {
+        Err(Error::ParsingFailed(message)) => assert!(message.contains("'rows'"), "{}", message),
+        other => panic!("{:?}", other),
+    }
+
+    match spec(r#"{"type": "data", "database_code": "WIKI"}"#) {
+        Err(Error::ParsingFailed(message)) => assert!(message.contains("id"), "{}", message),
+        other => panic!("{:?}", other),
+    }
+
+    assert!(spec(r#"{"type": "dataset_search"}"#).is_err());
+    assert!(spec(r#"{"type": "database_search", "database_code": "WIKI"}"#).is_err());
+    assert!(spec(r#"{"type": "data", "id": 1, "start_date": "2017-02-29"}"#).is_err());
+
+    let unknown = r#"{"type": "data", "id": 1, "row": 1}"#;
+    assert!(serde_json::from_str::<QueryConfig>(unknown).is_err());
+}
@@ -0,0 +1,95 @@
+extern crate quandl_v3;
+
+mod common;
+
+use quandl_v3::prelude::*;
+
+use common::{MockServer, Response};
+
+static DATA: &str = include_str!("fixtures/data_3_columns.csv");
+
+fn query(server: &MockServer, dataset_code: &str) -> DataQuery {
+    let mut query = DataQuery::new("WIKI", dataset_code);
+    query.base_url(server.url()).api_key("key");
+    query
+}
+
+fn content_length(query: &DataQuery) -> quandl_v3::Result<Option<u64>> {
+    ApiCall::<Vec<(String, f64, f64)>>::content_length(query)
+}
+
+/// Server answering HEAD requests for AAPL, rejecting them for MSFT and omitting the length for
+/// GOOG.
+///
+fn server() -> MockServer {
+    MockServer::start(|request| {
+        match &request.path[..] {
+            "/api/v3/datasets/WIKI/AAPL/data.csv" => Response::csv(DATA),
+            "/api/v3/datasets/WIKI/MSFT/data.csv" if request.method == "HEAD" => Response::new(405),
+            "/api/v3/datasets/WIKI/MSFT/data.csv" => Response::csv(DATA),
+
+            "/api/v3/datasets/WIKI/GOOG/data.csv" => {
+                Response::new(200).header("Transfer-Encoding", "chunked")
+            },
+
+            _ => Response::not_found(),
+        }
+    })
+}
+
+#[test]
+fn advertised_length() {
+    let server = server();
+
+    assert_eq!(content_length(&query(&server, "AAPL")).unwrap(), Some(DATA.len() as u64));
+
+    let request = &server.requests()[0];
+    assert_eq!(request.method, "HEAD");
+    assert_eq!(request.path, "/api/v3/datasets/WIKI/AAPL/data.csv");
+    assert_eq!(request.query, "exclude_column_names=true&api_key=key");
+}
+
+#[test]
+fn head_rejected() {
+    let server = server();
+
+    assert_eq!(content_length(&query(&server, "MSFT")).unwrap(), None);
+    assert_eq!(content_length(&query(&server, "MISSING")).unwrap(), None);
+}
+
+#[test]
+fn length_omitted() {
+    let server = server();
+    assert_eq!(content_length(&query(&server, "GOOG")).unwrap(), None);
+}
+
+#[test]
+fn unreachable_server() {
+    let mut query = DataQuery::new("WIKI", "AAPL");
+    query.base_url("http://127.0.0.1:1/api/v3");
+
+    assert!(content_length(&query).is_err());
+}
+
+#[cfg(feature = "batch")]
+#[test]
+fn batch_estimate() {
+    let server = server();
+    let mut batch_query: BatchQuery<DataQuery, Vec<(String, f64, f64)>> = BatchQuery::new();
+
+    for code in &["AAPL", "MSFT", "AAPL", "GOOG"] {
+        batch_query.query(query(&server, code));
+    }
+
+    // Not sent, as when running the batch.
+    batch_query.query(DataQuery::new("WIKI", "AAPL"));
+
+    assert_eq!(batch_query.estimate_bytes(), ByteEstimate {
+        bytes: 2 * DATA.len() as u64,
+        known: 2,
+        unknown: 2,
+    });
+
+    assert_eq!(server.hits(), 4);
+    assert!(server.requests().iter().all(|x| x.method == "HEAD"));
+}
@@ -0,0 +1,166 @@
+extern crate quandl_v3;
+
+mod common;
+
+use quandl_v3::Error;
+use quandl_v3::prelude::*;
+
+use common::{MockServer, Response};
+
+static THREE_COLUMNS: &[u8] = include_bytes!("fixtures/data_3_columns.csv");
+static DRIFTED: &[u8] = include_bytes!("fixtures/data_drifted.csv");
+static COLUMN_1: &str = include_str!("fixtures/data_column_1.csv");
+static COLUMN_INDEX_ERROR: &str = include_str!("fixtures/error_column_index.json");
+
+fn metadata(column_names: &[&str]) -> DatasetMetadata {
+    DatasetMetadata {
+        id: 9775409,
+        dataset_code: "AAPL".to_string(),
+        database_code: "WIKI".to_string(),
+        name: "Apple Inc (AAPL) Prices, Dividends, Splits and Trading Volume".to_string(),
+        description: String::new(),
+        refreshed_at: "2016-03-01T21:47:01.686Z".to_string(),
+        newest_available_date: "2016-02-29".to_string(),
+        oldest_available_date: "1980-12-12".to_string(),
+        column_names: column_names.iter().map(|x| x.to_string()).collect(),
+        frequency: Frequency::daily,
+        premium: false,
+        database_id: 4922,
+    }
+}
+
+#[test]
+fn decode_every_record() {
+    let query = DataQuery::new("WIKI", "AAPL");
+    let data: Vec<(String, f64, f64)> = query.decode(THREE_COLUMNS).unwrap();
+
+    assert_eq!(data.len(), 3);
+    assert_eq!(data[0], ("2016-02-10".to_string(), 94.27, 95.7));
+    assert_eq!(data[2], ("2016-02-08".to_string(), 93.13, 95.7));
+}
+
+#[test]
+fn lenient_width_ignores_extra_fields() {
+    let query = DataQuery::new("WIKI", "AAPL");
+    let data: Vec<(String, f64, f64)> = query.decode(DRIFTED).unwrap();
+
+    assert_eq!(data.len(), 4);
+}
+
+#[test]
+fn strict_width_accepts_matching_records() {
+    let mut query = DataQuery::new("WIKI", "AAPL");
+    query.strict_width(3);
+
+    let data: Vec<(String, f64, f64)> = query.decode(THREE_COLUMNS).unwrap();
+    assert_eq!(data.len(), 3);
+}
+
+#[test]
+fn strict_width_reports_drifted_row() {
+    let mut query = DataQuery::new("WIKI", "AAPL");
+    query.strict_width(3);
+
+    let data = query.decode::<(String, f64, f64)>(DRIFTED);
+
+    assert_eq!(data, Err(Error::CsvParsing {
+        row: Some(3),
+        message: "row 3 has 4 fields, expected 3.".to_string(),
+    }));
+}
+
+#[test]
+fn expected_width_from_metadata() {
+    let mut query = DataQuery::new("WIKI", "AAPL");
+    query.expected_width_from(&metadata(&["Date", "Open", "High"]));
+
+    assert!(query.decode::<(String, f64, f64)>(THREE_COLUMNS).is_ok());
+    assert!(query.decode::<(String, f64, f64)>(DRIFTED).is_err());
+
+    query.expected_width_from(&metadata(&["Date", "Open", "High", "Low"]));
+
+    let data = query.decode::<(String, f64, f64)>(THREE_COLUMNS);
+    assert_eq!(data, Err(Error::CsvParsing {
+        row: Some(1),
+        message: "row 1 has 3 fields, expected 4.".to_string(),
+    }));
+}
+
+#[test]
+fn expected_width_from_metadata_with_column_index() {
+    let mut query = DataQuery::new("WIKI", "AAPL");
+
+    query.column_index(1)
+         .expected_width_from(&metadata(&["Date", "Open", "High"]));
+
+    let data = query.decode::<(String, f64)>(b"2016-02-10,94.27\n2016-02-09,95.29\n");
+    assert_eq!(data.unwrap().len(), 2);
+}
+
+#[test]
+fn column_index_zero_is_rejected() {
+    let server = MockServer::start(|_| Response::new(200).body(THREE_COLUMNS));
+
+    let mut query = DataQuery::new("WIKI", "AAPL");
+    query.base_url(server.url()).column_index(0);
+
+    let errors = query.validate().unwrap_err();
+
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].field, "column_index");
+    assert!(errors[0].message.contains("0 is the date column"), "{}", errors[0].message);
+
+    query.strict(true);
+
+    let result: quandl_v3::Result<Vec<(String, f64, f64)>> = query.send();
+
+    match result {
+        Err(Error::ValidationFailed(errors)) => assert_eq!(errors[0].field, "column_index"),
+        other => panic!("{:?}", other),
+    }
+
+    assert_eq!(server.hits(), 0);
+}
+
+#[test]
+fn column_index_returns_date_and_column() {
+    let server = MockServer::routes(vec![
+        ("/api/v3/datasets/WIKI/AAPL/data.csv", Response::csv(COLUMN_1)),
+    ]);
+
+    let mut query = DataQuery::new("WIKI", "AAPL");
+    query.base_url(server.url()).column_index(1).strict_width(2);
+
+    let data: Vec<(String, f64)> = query.send().unwrap();
+
+    assert_eq!(data, vec![("2016-02-10".to_string(), 94.27),
+                          ("2016-02-09".to_string(), 95.29),
+                          ("2016-02-08".to_string(), 93.13)]);
+
+    assert_eq!(server.requests()[0].query, "exclude_column_names=true&column_index=1");
+}
+
+#[test]
+fn column_index_out_of_range_passes_api_error_through() {
+    let server = MockServer::routes(vec![
+        ("/api/v3/datasets/WIKI/AAPL/data.csv", {
+            Response::new(422)
+                .header("Content-Type", "application/json; charset=utf-8")
+                .body(COLUMN_INDEX_ERROR)
+        }),
+    ]);
+
+    let mut query = DataQuery::new("WIKI", "AAPL");
+    query.base_url(server.url()).column_index(13);
+
+    let result: quandl_v3::Result<Vec<(String, f64)>> = query.send();
+    let error = result.unwrap_err();
+
+    match error {
+        Error::ApiCallFailed(ref e) => assert_eq!(e.quandl_error.code, "QEPx04"),
+        ref e => panic!("unexpected error: {:?}", e),
+    }
+
+    assert!(error.to_string().starts_with("column_index - "));
+    assert!(error.to_string().ends_with("must be less than or equal to 12"));
+}
@@ -0,0 +1,239 @@
+extern crate quandl_v3;
+extern crate serde_json;
+
+#[macro_use] extern crate serde_derive;
+
+mod common;
+
+use quandl_v3::calendar::NaiveDate;
+use quandl_v3::prelude::*;
+
+use common::{MockServer, Response};
+
+static DATASET_DATA: &str = include_str!("fixtures/dataset_data.json");
+static DATASET_COLUMN_2: &str = include_str!("fixtures/dataset_column_2.json");
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Price {
+    date: String,
+    open: f64,
+    close: f64,
+}
+
+fn server() -> MockServer {
+    MockServer::routes(vec![("/api/v3/datasets/WIKI/AAPL.json", Response::json(DATASET_DATA))])
+}
+
+fn send<T: serde::de::DeserializeOwned + Clone>(server: &MockServer) -> Dataset<T> {
+    let mut query = DataAndMetadataQuery::new("WIKI", "AAPL");
+    query.base_url(server.url());
+    query.send().unwrap()
+}
+
+#[test]
+fn data_and_metadata_from_single_call() {
+    let server = server();
+    let dataset: Dataset<(String, f64, f64)> = send(&server);
+
+    assert_eq!(dataset.metadata.dataset_code, "AAPL");
+    assert_eq!(dataset.column_names(), ["Date", "Open", "Close"]);
+    assert_eq!(dataset.frequency(), Frequency::daily);
+    assert_eq!(dataset.len(), 3);
+    assert!(!dataset.is_empty());
+    assert_eq!(dataset.rows[0], ("2016-02-10".to_string(), 95.92, 94.99));
+
+    assert_eq!(dataset.date_range().unwrap(), (NaiveDate::from_ymd_opt(1980, 12, 12).unwrap(),
+                                               NaiveDate::from_ymd_opt(2016, 2, 29).unwrap()));
+
+    assert_eq!(server.requests()[0].path, "/api/v3/datasets/WIKI/AAPL.json");
+}
+
+#[test]
+fn datasets_by_id() {
+    let server = {
+        MockServer::routes(vec![("/api/v3/datasets/9775409.json", Response::json(DATASET_DATA))])
+    };
+
+    let mut query = DataAndMetadataQuery::by_id(9775409);
+    query.base_url(server.url());
+
+    let dataset: Dataset<(String, f64, f64)> = query.send().unwrap();
+
+    // Both ways to address the dataset are known, whichever was used.
+    assert_eq!(dataset.id(), 9775409);
+    assert_eq!(dataset.codes(), ("WIKI", "AAPL"));
+    assert_eq!(dataset.len(), 3);
+}
+
+#[test]
+fn rows_into_structs() {
+    let dataset: Dataset<Price> = send(&server());
+
+    assert_eq!(dataset.rows[2], Price {
+        date: "2016-02-08".to_string(),
+        open: 93.13,
+        close: 95.01,
+    });
+}
+
+#[test]
+fn query_arguments() {
+    let server = server();
+
+    let mut query = DataAndMetadataQuery::new("WIKI", "AAPL");
+
+    query
+        .base_url(server.url())
+        .api_key("key")
+        .start_date(2016, 2, 8)
+        .end_date(2016, 2, 10)
+        .column_index(1);
+
+    let _: Dataset<(String, f64, f64)> = query.send().unwrap();
+
+    assert_eq!(server.requests()[0].query,
+               "api_key=key&end_date=2016-02-10&start_date=2016-02-08&column_index=1");
+}
+
+#[test]
+fn column_names_match_rows() {
+    let dataset: Dataset<(String, f64, f64)> = send(&server());
+    assert_eq!(dataset.metadata.column_names, ["Date", "Open", "Close"]);
+
+    let server = {
+        MockServer::routes(vec![("/api/v3/datasets/WIKI/AAPL.json", {
+            Response::json(DATASET_COLUMN_2)
+        })])
+    };
+
+    let mut query = DataAndMetadataQuery::new("WIKI", "AAPL");
+    query.base_url(server.url()).column_index(2);
+
+    let dataset: Dataset<(String, f64)> = query.send().unwrap();
+
+    assert_eq!(dataset.column_names(), ["Date", "Close"]);
+    assert_eq!(dataset.metadata.column_names, ["Date", "Close"]);
+    assert_eq!(dataset.rows[0], ("2016-02-10".to_string(), 94.99));
+}
+
+#[test]
+fn iteration_and_mapping() {
+    let dataset: Dataset<Price> = send(&server());
+
+    let dates: Vec<&str> = dataset.iter().map(|price| &price.date[..]).collect();
+    assert_eq!(dates, ["2016-02-10", "2016-02-09", "2016-02-08"]);
+
+    let mut opens = vec![];
+
+    for price in &dataset {
+        opens.push(price.open);
+    }
+
+    let metadata = dataset.metadata.clone();
+    let changes = dataset.map_rows(|price| price.close - price.open);
+
+    assert_eq!(changes.metadata, metadata);
+    assert_eq!(changes.len(), opens.len());
+
+    let changes: Vec<f64> = changes.into_iter().map(|x| (x * 100.0).round() / 100.0).collect();
+    assert_eq!(changes, [-0.93, 0.72, 1.88]);
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct Change {
+    date: String,
+    change_in_percent: f64,
+}
+
+#[test]
+fn preview_first_and_last_rows() {
+    let dataset: Dataset<(String, f64, f64)> = send(&server());
+
+    assert_eq!(dataset.preview(1), "\
+Date         Open  Close
+2016-02-10  95.92  94.99
+...           ...    ...
+2016-02-08  93.13  95.01
+[3 rows x 3 columns]");
+
+    assert_eq!(dataset.preview(2), dataset.preview(5));
+    assert_eq!(dataset.preview(2), "\
+Date         Open  Close
+2016-02-10  95.92  94.99
+2016-02-09  94.29  95.01
+2016-02-08  93.13  95.01
+[3 rows x 3 columns]");
+
+    assert_eq!(dataset.preview(0), "\
+Date  Open  Close
+...   ...   ...
+[3 rows x 3 columns]");
+}
+
+#[test]
+fn preview_names_columns() {
+    let dataset: Dataset<Price> = send(&server());
+    assert!(dataset.preview(1).starts_with("Date         Open  Close\n"));
+
+    let changes = dataset.clone().map_rows(|price| Change {
+        date: price.date,
+        change_in_percent: ((price.close / price.open - 1.0) * 1e4).round() / 100.0,
+    });
+
+    assert_eq!(changes.preview(1), "\
+date        change_in_percent
+2016-02-10              -0.97
+...                       ...
+2016-02-08               2.02
+[3 rows x 2 columns]");
+
+    let opens = dataset.map_rows(|price| price.open);
+
+    assert_eq!(opens.preview(1), "\
+column 1
+   95.92
+     ...
+   93.13
+[3 rows x 1 columns]");
+}
+
+#[test]
+fn preview_truncates_wide_cells() {
+    let dataset: Dataset<Price> = send(&server());
+    let name = "Apple Inc. (AAPL) Prices, Dividends, Splits";
+    let names = dataset.map_rows(|price| (price.date, name));
+
+    assert_eq!(names.preview(1), "\
+column 1    column 2
+2016-02-10  Apple Inc. (AAPL) P…
+...         ...
+2016-02-08  Apple Inc. (AAPL) P…
+[3 rows x 2 columns]");
+}
+
+#[test]
+fn single_json_document() {
+    let dataset: Dataset<Price> = send(&server());
+
+    let json = serde_json::to_string(&dataset).unwrap();
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+    assert_eq!(value["metadata"]["dataset_code"], "AAPL");
+    assert_eq!(value["rows"][0]["open"], 95.92);
+
+    let restored: Dataset<Price> = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored, dataset);
+}
+
+#[test]
+fn malformed_rows() {
+    let server = MockServer::routes(vec![("/api/v3/datasets/WIKI/AAPL.json", {
+        Response::json(DATASET_DATA.replace("95.92", "\"n/a\""))
+    })]);
+
+    let mut query = DataAndMetadataQuery::new("WIKI", "AAPL");
+    query.base_url(server.url());
+
+    let result: quandl_v3::Result<Dataset<Price>> = query.send();
+    assert!(result.is_err());
+}
@@ -0,0 +1,266 @@
+extern crate quandl_v3;
+
+mod common;
+
+use quandl_v3::Error;
+use quandl_v3::calendar::{NaiveDate, NaiveDateTime};
+use quandl_v3::prelude::*;
+
+use common::{MockServer, Response};
+
+static PAGE_1: &str = include_str!("fixtures/dataset_listing_1.csv");
+static PAGE_2: &str = include_str!("fixtures/dataset_listing_2.csv");
+
+/// Server listing `pages` in turn, then only the header.
+///
+fn server<S: AsRef<str> + Send + Sync + 'static>(pages: Vec<S>) -> MockServer {
+    MockServer::start(move |request| {
+        if request.path != "/api/v3/datasets.csv" {
+            return Response::not_found();
+        }
+
+        let page: usize = {
+            request.query.split('&')
+                .find(|x| x.starts_with("page="))
+                .map(|x| x["page=".len()..].parse().unwrap())
+                .unwrap_or(1)
+        };
+
+        match pages.get(page - 1) {
+            Some(page) => Response::csv(page.as_ref()),
+            None => Response::csv(PAGE_1.lines().next().unwrap()),
+        }
+    })
+}
+
+fn query(server: &MockServer) -> DatasetListingQuery {
+    let mut query = DatasetListingQuery::new("WIKI");
+    query.base_url(server.url()).api_key("key");
+    query
+}
+
+fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(year, month, day).unwrap()
+}
+
+fn timestamp(s: &str) -> NaiveDateTime {
+    NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.3f").unwrap()
+}
+
+fn codes(query: &DatasetListingQuery) -> Vec<String> {
+    query.iter().map(|x| x.unwrap().code).collect()
+}
+
+fn pages_requested(server: &MockServer) -> Vec<String> {
+    server.requests().iter().map(|x| x.query.clone()).collect()
+}
+
+#[test]
+fn fields_are_parsed() {
+    let server = server(vec![PAGE_1]);
+    let datasets = query(&server).send().unwrap();
+
+    assert_eq!(datasets, vec![
+        DatasetMetadataLite {
+            code: "AAPL".to_string(),
+            name: "Apple Inc (AAPL) Prices".to_string(),
+            description: "End of day open, high, low, close and volume for Apple Inc. (AAPL)."
+                .to_string(),
+            refreshed_at: timestamp("2016-03-01 21:47:01.686"),
+            oldest: Some(date(1980, 12, 12)),
+            newest: Some(date(2016, 2, 29)),
+        },
+
+        DatasetMetadataLite {
+            code: "MSFT".to_string(),
+            name: "Microsoft Corporation (MSFT) Prices".to_string(),
+            description: String::new(),
+            refreshed_at: timestamp("2016-03-01 21:47:02.347"),
+            oldest: Some(date(1986, 3, 13)),
+            newest: Some(date(2016, 2, 29)),
+        },
+    ]);
+
+    assert_eq!(pages_requested(&server), vec!["api_key=key&database_code=WIKI"]);
+}
+
+#[test]
+fn header_handling() {
+    let query = DatasetListingQuery::new("WIKI");
+
+    // Columns are matched by their trimmed name, optional ones may be missing and unknown ones
+    // are ignored.
+    let datasets = query.decode(PAGE_2.as_bytes()).unwrap();
+
+    assert_eq!(datasets, vec![DatasetMetadataLite {
+        code: "DLST".to_string(),
+        name: "Delisted Corp (DLST) Prices".to_string(),
+        description: String::new(),
+        refreshed_at: timestamp("2015-06-30 10:00:00.000"),
+        oldest: None,
+        newest: None,
+    }]);
+
+    // An empty page, with or without its header.
+    assert_eq!(query.decode(PAGE_1.lines().next().unwrap().as_bytes()).unwrap(), vec![]);
+    assert_eq!(query.decode(b"").unwrap(), vec![]);
+
+    match query.decode(b"dataset_code,description,refreshed_at\nAAPL,,2016-03-01T21:47:01Z\n") {
+        Err(Error::CsvParsing { row: Some(1), ref message }) => {
+            assert_eq!(message, "missing column 'name' in the header of the dataset listing.");
+        },
+
+        other => panic!("expected a header error, got {:?}", other),
+    }
+}
+
+#[test]
+fn invalid_fields() {
+    let query = DatasetListingQuery::new("WIKI");
+
+    let csv = "dataset_code,name,refreshed_at,oldest_available_date\n\
+               AAPL,Apple,2016-03-01T21:47:01Z,1980-12-12\n\
+               MSFT,Microsoft,2016-03-01T21:47:01Z,1986-02-30\n";
+
+    match query.decode(csv.as_bytes()) {
+        Err(Error::CsvParsing { row: Some(3), ref message }) => {
+            assert_eq!(message, "invalid oldest_available_date '1986-02-30' for dataset MSFT.");
+        },
+
+        other => panic!("expected a parsing error, got {:?}", other),
+    }
+
+    let csv = "dataset_code,name,refreshed_at\nAAPL,Apple,yesterday\n";
+
+    match query.decode(csv.as_bytes()) {
+        Err(Error::CsvParsing { row: Some(2), ref message }) => {
+            assert_eq!(message, "invalid refreshed_at 'yesterday' for dataset AAPL.");
+        },
+
+        other => panic!("expected a parsing error, got {:?}", other),
+    }
+}
+
+#[test]
+fn paging_stops_on_a_short_page() {
+    let server = server(vec![PAGE_1, PAGE_2]);
+    let mut query = query(&server);
+    query.per_page(2);
+
+    assert_eq!(codes(&query), vec!["AAPL", "MSFT", "DLST"]);
+
+    assert_eq!(pages_requested(&server), vec![
+        "api_key=key&per_page=2&page=1&database_code=WIKI",
+        "api_key=key&per_page=2&page=2&database_code=WIKI",
+    ]);
+}
+
+#[test]
+fn paging_stops_on_an_empty_page() {
+    let server = server(vec![PAGE_1]);
+    let mut query = query(&server);
+    query.per_page(2);
+
+    assert_eq!(codes(&query), vec!["AAPL", "MSFT"]);
+    assert_eq!(server.hits(), 2);
+
+    // Starting from the selected page, with Quandl's largest page size by default.
+    let mut query = self::query(&server);
+    query.page(2);
+
+    assert_eq!(codes(&query), Vec::<String>::new());
+    assert_eq!(server.requests()[2].query, "api_key=key&per_page=100&page=2&database_code=WIKI");
+}
+
+#[test]
+fn paging_stops_after_an_error() {
+    let server = server(vec![PAGE_1, "dataset_code,name\nAAPL,Apple\n"]);
+    let mut query = query(&server);
+    query.per_page(2);
+
+    let results: Vec<_> = query.iter().collect();
+
+    assert_eq!(results.len(), 3);
+    assert!(results[..2].iter().all(|x| x.is_ok()));
+    assert!(matches!(results[2], Err(Error::CsvParsing { row: Some(1), .. })));
+    assert_eq!(server.hits(), 2);
+}
+
+/// A page of the listing, each dataset given by its code and the day it was refreshed in
+/// March 2016.
+///
+fn refreshed_page(datasets: &[(&str, u32)]) -> String {
+    let header = PAGE_1.lines().next().unwrap();
+
+    let rows: String = datasets.iter().map(|(code, day)| {
+        format!("{},{},,2016-03-{:02}T12:00:00.000Z,,,WIKI\n", code, code, day)
+    }).collect();
+
+    format!("{}\n{}", header, rows)
+}
+
+fn refreshed_since(query: &DatasetListingQuery, day: u32) -> Vec<String> {
+    let since = timestamp(&format!("2016-03-{:02} 12:00:00.000", day));
+    query.refreshed_since(since).map(|x| x.unwrap().code).collect()
+}
+
+#[test]
+fn refreshed_since_stops_after_a_stale_page() {
+    let server = server(vec![
+        refreshed_page(&[("A", 20), ("B", 18)]),
+        // Straddles the cutoff.
+        refreshed_page(&[("C", 16), ("D", 10)]),
+        refreshed_page(&[("E", 9), ("F", 5)]),
+        refreshed_page(&[("G", 4), ("H", 3)]),
+    ]);
+
+    let mut query = query(&server);
+    query.per_page(2);
+
+    assert_eq!(refreshed_since(&query, 12), vec!["A", "B", "C"]);
+
+    // Sorted by last update, and not a page more than the stale one.
+    assert_eq!(pages_requested(&server), vec![
+        "api_key=key&per_page=2&page=1&sort_by=last_updated&database_code=WIKI",
+        "api_key=key&per_page=2&page=2&sort_by=last_updated&database_code=WIKI",
+        "api_key=key&per_page=2&page=3&sort_by=last_updated&database_code=WIKI",
+    ]);
+}
+
+#[test]
+fn refreshed_since_cutoffs() {
+    let pages = vec![
+        refreshed_page(&[("A", 20), ("B", 18)]),
+        refreshed_page(&[("C", 16), ("D", 10)]),
+        refreshed_page(&[("E", 9)]),
+    ];
+
+    let server = server(pages);
+    let mut query = query(&server);
+    query.per_page(2);
+
+    // Between two pages.
+    assert_eq!(refreshed_since(&query, 17), vec!["A", "B"]);
+    assert_eq!(server.hits(), 2);
+
+    // Only strictly later refreshes count.
+    assert_eq!(refreshed_since(&query, 20), Vec::<String>::new());
+    assert_eq!(server.hits(), 3);
+
+    // Before every refresh, the short page ends the listing as usual.
+    assert_eq!(refreshed_since(&query, 1), vec!["A", "B", "C", "D", "E"]);
+    assert_eq!(server.hits(), 6);
+
+    // The query itself is left unsorted.
+    assert_eq!(codes(&query), vec!["A", "B", "C", "D", "E"]);
+    assert!(!server.requests()[6].query.contains("sort_by"));
+}
+
+#[test]
+fn validation() {
+    let mut query = DatasetListingQuery::new("WIKI/AAPL");
+    query.per_page(0);
+
+    let fields: Vec<_> = query.validate().unwrap_err().iter().map(|x| x.field).collect();
+    assert_eq!(fields, vec!["database_code", "per_page"]);
+}
@@ -0,0 +1,195 @@
+extern crate quandl_v3;
+extern crate serde_json;
+
+mod common;
+
+use quandl_v3::{Error, Result};
+use quandl_v3::prelude::*;
+
+use common::{MockServer, Response};
+
+static PRICES: &str = include_str!("fixtures/datatable_prices.json");
+static PRICES_PAGE: &str = include_str!("fixtures/datatable_prices_page.json");
+static PREMIUM_DENIED: &str = include_str!("fixtures/error_premium_eod.json");
+
+type Price = (String, String, f64);
+
+fn server() -> MockServer {
+    MockServer::start(|request| {
+        match &request.path[..] {
+            "/api/v3/datatables/WIKI/PRICES.json" => {
+                if request.query.contains("qopts.per_page=1") {
+                    Response::json(PRICES_PAGE)
+                } else {
+                    Response::json(PRICES)
+                }
+            },
+
+            "/api/v3/datatables/SHARADAR/SF1.json" => {
+                Response::new(403).header("Content-Type", "application/json").body(PREMIUM_DENIED)
+            },
+
+            _ => Response::not_found(),
+        }
+    })
+}
+
+fn prices(server: &MockServer) -> DatatableQuery {
+    let mut query = DatatableQuery::new("WIKI", "PRICES");
+
+    query.base_url(server.url())
+         .api_key("KEY")
+         .filter("ticker", ["AAPL", "MSFT"])
+         .filter_by("date", Comparison::gte, "2018-03-26")
+         .columns(["ticker", "date", "close"]);
+
+    query
+}
+
+#[test]
+fn urls() {
+    let mut query = DatatableQuery::new("WIKI", "PRICES");
+    assert_eq!(ApiCall::<Datatable<Price>>::url(&query),
+               "https://www.quandl.com/api/v3/datatables/WIKI/PRICES.json");
+
+    query.api_key("KEY")
+         .filter("ticker", ["AAPL", "BRK.B"])
+         .filter_by("date", Comparison::gte, "2018-01-01")
+         .filter_by("date", Comparison::lt, "2018-04-01")
+         .columns(["ticker", "date", "close"])
+         .per_page(100)
+         .cursor_id("abc=");
+
+    assert_eq!(ApiCall::<Datatable<Price>>::url(&query),
+               "https://www.quandl.com/api/v3/datatables/WIKI/PRICES.json?api_key=KEY&\
+                ticker=AAPL,BRK.B&date.gte=2018-01-01&date.lt=2018-04-01&\
+                qopts.columns=ticker,date,close&qopts.per_page=100&qopts.cursor_id=abc%3D");
+
+    // Filtering a column again replaces its values, and values are encoded on their own.
+    query.filter("ticker", ["A&B", "C,D"]).filter_by("date", Comparison::gte, "2018-02-01");
+
+    assert!(ApiCall::<Datatable<Price>>::url(&query)
+                .contains("ticker=A%26B,C%2CD&date.gte=2018-02-01&date.lt=2018-04-01&"));
+}
+
+#[test]
+fn filtered_prices() {
+    let server = server();
+    let query = prices(&server);
+
+    let prices: Datatable<Price> = query.send().unwrap();
+
+    assert_eq!(prices.column_names(), ["ticker", "date", "close"]);
+    assert_eq!(prices.columns[2].column_type, "BigDecimal(34,12)");
+    assert_eq!(prices.len(), 4);
+    assert_eq!(prices.rows[0], ("AAPL".to_string(), "2018-03-26".to_string(), 172.77));
+    assert_eq!(prices.rows[3], ("MSFT".to_string(), "2018-03-27".to_string(), 89.47));
+    assert_eq!(prices.next_cursor_id, None);
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].query, "api_key=KEY&ticker=AAPL,MSFT&date.gte=2018-03-26&\
+                                   qopts.columns=ticker,date,close");
+}
+
+#[test]
+fn untyped_rows() {
+    let server = server();
+
+    let prices: Datatable<Vec<serde_json::Value>> = prices(&server).send().unwrap();
+
+    assert_eq!(prices.rows[1], vec![serde_json::json!("AAPL"),
+                                    serde_json::json!("2018-03-27"),
+                                    serde_json::json!(168.34)]);
+}
+
+#[test]
+fn pages() {
+    let server = server();
+
+    let mut query = prices(&server);
+    query.per_page(1);
+
+    let page: Datatable<Price> = query.send().unwrap();
+
+    assert_eq!(page.len(), 1);
+    assert_eq!(page.next_cursor_id.as_ref().map(|x| &x[..]), Some("dGlja2VyPUFBUEw="));
+
+    query.cursor_id(page.next_cursor_id.unwrap());
+    assert_eq!(server.requests().len(), 1);
+    assert!(ApiCall::<Datatable<Price>>::url(&query)
+                .ends_with("&qopts.per_page=1&qopts.cursor_id=dGlja2VyPUFBUEw%3D"));
+}
+
+#[test]
+fn decode() {
+    let query = DatatableQuery::new("WIKI", "PRICES");
+    let prices: Datatable<Price> = query.decode(PRICES.as_bytes()).unwrap();
+
+    assert_eq!(prices.len(), 4);
+    assert_eq!(serde_json::from_str::<Datatable<Price>>(&serde_json::to_string(&prices).unwrap())
+                   .unwrap(),
+               prices);
+
+    assert!(query.decode::<Price>(b"{\"datatable\": {}}").is_err());
+    assert!(query.decode::<(String, f64)>(PRICES.as_bytes()).is_err());
+}
+
+#[test]
+fn validation() {
+    let mut query = DatatableQuery::new("wiki", "PRICES/AAPL");
+    query.filter("ticker", Vec::<String>::new());
+
+    let fields: Vec<_> = query.validate().unwrap_err().into_iter().map(|e| e.field).collect();
+    assert_eq!(fields, ["vendor_code", "table_code", "filter"]);
+
+    query.strict(true);
+
+    match ApiCall::<Datatable<Price>>::send(&query) {
+        Err(Error::ValidationFailed(errors)) => assert_eq!(errors.len(), 3),
+        other => panic!("{:?}", other),
+    }
+
+    let mut query = DatatableQuery::new("wiki", "prices");
+    query.auto_uppercase(true).filter("ticker", ["AAPL"]);
+
+    assert!(query.validate().is_ok());
+    assert_eq!(ApiCall::<Datatable<Price>>::url(&query),
+               "https://www.quandl.com/api/v3/datatables/WIKI/PRICES.json?ticker=AAPL");
+    assert_eq!(query.vendor_code(), "wiki");
+}
+
+#[test]
+fn premium_tables() {
+    let server = server();
+
+    let mut query = DatatableQuery::new("SHARADAR", "SF1");
+    query.base_url(server.url());
+
+    let result: Result<Datatable<Vec<serde_json::Value>>> = query.send();
+
+    match result {
+        Err(Error::SubscriptionRequired { database_code }) => assert_eq!(database_code, "SHARADAR"),
+        other => panic!("{:?}", other),
+    }
+}
+
+#[cfg(feature = "batch")]
+#[test]
+fn batches() {
+    let server = server();
+
+    let mut batch_query: BatchQuery<DatatableQuery, Datatable<Price>> = BatchQuery::new();
+
+    for _ in 0..3 {
+        batch_query.query(prices(&server));
+    }
+
+    batch_query.threads(2);
+
+    let results: Vec<_> = batch_query.run().collect();
+
+    assert_eq!(results.len(), 3);
+    assert!(results.iter().all(|result| result.as_ref().map(|prices| prices.len()) == Ok(4)));
+    assert_eq!(server.hits(), 3);
+}
@@ -0,0 +1,125 @@
+extern crate quandl_v3;
+
+mod common;
+
+use quandl_v3::calendar::{DateParser, EpochDays, IsoDate, NaiveDate};
+use quandl_v3::prelude::*;
+use quandl_v3::{Error, Result};
+
+use common::{MockServer, Response};
+
+static THREE_COLUMNS: &str = include_str!("fixtures/data_3_columns.csv");
+static HEADER_ONLY: &str = include_str!("fixtures/data_header_only.csv");
+static MISSING_VALUES: &[u8] = include_bytes!("fixtures/data_missing_values.csv");
+
+fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(year, month, day).unwrap()
+}
+
+#[test]
+fn iso_dates() {
+    let server = MockServer::routes(vec![
+        ("/api/v3/datasets/WIKI/AAPL/data.csv", Response::csv(THREE_COLUMNS)),
+    ]);
+
+    let mut query = DataQuery::new("WIKI", "AAPL");
+    query.base_url(server.url());
+
+    let rows: Vec<(NaiveDate, Vec<f64>)> = query.send_dated(&IsoDate).unwrap();
+
+    assert_eq!(rows.len(), THREE_COLUMNS.lines().count());
+    assert_eq!(rows[0], (date(2016, 2, 10), vec![94.27, 95.7]));
+
+    // Decoded the same as any other row type.
+    let tuples: Vec<(String, f64, f64)> = query.send().unwrap();
+
+    for ((date, values), tuple) in rows.iter().zip(&tuples) {
+        assert_eq!(date.to_string(), tuple.0);
+        assert_eq!(values, &vec![tuple.1, tuple.2]);
+    }
+}
+
+#[test]
+fn epoch_days() {
+    let query = DataQuery::new("WIKI", "AAPL");
+    let rows: Vec<(i64, Vec<Option<f64>>)> = {
+        query.decode_dated(MISSING_VALUES, &EpochDays).unwrap()
+    };
+
+    assert_eq!(rows, vec![
+        (16_841, vec![Some(94.27), None]),
+        (16_840, vec![None, Some(95.94)]),
+        (-1, vec![Some(93.13), Some(95.7)]),
+    ]);
+
+    // Missing values cannot be decoded as numbers.
+    match query.decode_dated::<i64, f64>(MISSING_VALUES, &EpochDays) {
+        Err(Error::CsvParsing { row: Some(1), .. }) => (),
+        other => panic!("expected a parsing error, got {:?}", other),
+    }
+
+    assert_eq!(EpochDays.parse("1970-01-01").unwrap(), 0);
+}
+
+#[test]
+fn column_names_and_widths() {
+    let mut query = DataQuery::new("WIKI", "AAPL");
+    let payload = format!("{}{}", HEADER_ONLY, THREE_COLUMNS);
+
+    let rows: Vec<(NaiveDate, Vec<f64>)> = {
+        query.decode_dated(payload.as_bytes(), &IsoDate).unwrap()
+    };
+    assert_eq!(rows.len(), THREE_COLUMNS.lines().count());
+
+    query.strict_width(4);
+
+    match query.decode_dated::<NaiveDate, f64>(THREE_COLUMNS.as_bytes(), &IsoDate) {
+        Err(Error::CsvParsing { row: Some(1), message }) => {
+            assert_eq!(message, "row 1 has 3 fields, expected 4.");
+        },
+
+        other => panic!("expected a width error, got {:?}", other),
+    }
+}
+
+/// Custom parser of the dates, to month numbers since year 0.
+///
+fn months(s: &str) -> Result<u32> {
+    match (s.get(0..4).map(str::parse::<u32>), s.get(5..7).map(str::parse::<u32>)) {
+        (Some(Ok(year)), Some(Ok(month))) => Ok(year * 12 + month - 1),
+        _ => Err(Error::ParsingFailed("not a month.".to_string())),
+    }
+}
+
+#[test]
+fn custom_parsers() {
+    let query = DataQuery::new("WIKI", "AAPL");
+
+    let rows: Vec<(u32, Vec<f64>)> = query.decode_dated(THREE_COLUMNS.as_bytes(), &months).unwrap();
+    assert_eq!(rows[0].0, 2016 * 12 + 1);
+
+    let payload = b"2016-02-10,94.27\nlast week,95.29\n";
+
+    assert_eq!(query.decode_dated::<u32, f64>(&payload[..], &months), Err(Error::CsvParsing {
+        row: Some(2),
+        message: "invalid date 'last week': not a month.".to_string(),
+    }));
+
+    let failing = |_: &str| -> Result<u32> { Err(Error::IoError("unavailable".to_string())) };
+
+    match query.decode_dated::<u32, f64>(&payload[..], &failing) {
+        Err(Error::CsvParsing { row: Some(1), message }) => {
+            assert!(message.starts_with("invalid date '2016-02-10': I/O operation failed"),
+                    "{}", message);
+        },
+
+        other => panic!("expected a parsing error, got {:?}", other),
+    }
+
+    // The built-in parsers are strict.
+    assert_eq!(query.decode_dated::<NaiveDate, f64>(b"2016-2-10,94.27\n", &IsoDate),
+               Err(Error::CsvParsing {
+                   row: Some(1),
+                   message: "invalid date '2016-2-10': expected a YYYY-MM-DD date.".to_string(),
+               }));
+}
@@ -0,0 +1,87 @@
+extern crate quandl_v3;
+
+mod common;
+
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use quandl_v3::Error;
+use quandl_v3::prelude::*;
+
+use common::{MockServer, Response};
+
+static DATABASE_METADATA: &str = include_str!("fixtures/database_metadata.json");
+static LISTING: &str = include_str!("fixtures/dataset_listing_1.csv");
+
+/// Server answering metadata queries, after `delay` for the MSFT dataset.
+///
+fn server(delay: Duration) -> MockServer {
+    MockServer::start(move |request| {
+        match &request.path[..] {
+            "/api/v3/databases/WIKI.json" => Response::json(DATABASE_METADATA),
+            "/api/v3/datasets.csv" => Response::csv(LISTING),
+
+            "/api/v3/datasets/WIKI/MSFT/data.csv" => {
+                sleep(delay);
+                Response::csv("2016-02-29,52.0\n")
+            },
+
+            _ => Response::not_found(),
+        }
+    })
+}
+
+#[test]
+fn expired_deadline() {
+    let server = server(Duration::from_secs(0));
+    let mut query = DatabaseMetadataQuery::new("WIKI");
+    query.base_url(server.url());
+
+    assert_eq!(query.send_with_deadline(Instant::now()), Err(Error::DeadlineExceeded));
+    assert_eq!(server.hits(), 0);
+
+    assert!(query.send_with_deadline(Instant::now() + Duration::from_secs(30)).is_ok());
+    assert_eq!(server.hits(), 1);
+}
+
+#[test]
+fn deadline_bounds_the_wait() {
+    let server = server(Duration::from_secs(3));
+    let mut query = DataQuery::new("WIKI", "MSFT");
+    query.base_url(server.url());
+
+    let start = Instant::now();
+    let deadline = start + Duration::from_millis(300);
+    let result = ApiCall::<Vec<(String, f64)>>::send_with_deadline(&query, deadline);
+
+    assert_eq!(result, Err(Error::DeadlineExceeded));
+    assert!(start.elapsed() < Duration::from_secs(3), "{:?}", start.elapsed());
+    assert_eq!(server.hits(), 1);
+}
+
+#[test]
+fn deadline_expires_between_pages() {
+    let server = server(Duration::from_secs(0));
+    let mut query = DatasetListingQuery::new("WIKI");
+    query.base_url(server.url()).per_page(2);
+
+    let deadline = Instant::now() + Duration::from_secs(2);
+    let mut datasets = query.iter().deadline(deadline);
+
+    // The first page is fetched in time...
+    assert_eq!(datasets.next().unwrap().unwrap().code, "AAPL");
+    assert_eq!(datasets.next().unwrap().unwrap().code, "MSFT");
+
+    // ...but the budget is spent before the second one is requested.
+    sleep(deadline.saturating_duration_since(Instant::now()));
+
+    assert_eq!(datasets.next().map(|x| x.map(|x| x.code)), Some(Err(Error::DeadlineExceeded)));
+    assert!(datasets.next().is_none());
+    assert_eq!(server.hits(), 1);
+}
+
+#[test]
+fn display() {
+    assert_eq!(Error::DeadlineExceeded.to_string(),
+               "the deadline passed before the query could be completed.");
+}
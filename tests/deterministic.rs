@@ -0,0 +1,100 @@
+extern crate quandl_v3;
+
+mod common;
+
+use std::time::Duration;
+
+use quandl_v3::ApiKey;
+use quandl_v3::backoff::{Backoff, Jitter};
+use quandl_v3::config::Config;
+
+#[cfg(feature = "batch")]
+use quandl_v3::prelude::*;
+
+#[cfg(feature = "batch")]
+use common::{MockServer, Response};
+
+fn delays(backoff: &Backoff) -> Vec<Duration> {
+    backoff.delays().collect()
+}
+
+#[test]
+fn jitter_is_drawn_from_the_seed() {
+    let mut backoff = Backoff::default();
+    backoff.jitter(Jitter::Decorrelated).max_attempts(Some(8));
+
+    let (first, second) = {
+        let _guard = Config::override_scope(Config::deterministic(7));
+        (delays(&backoff), delays(&backoff))
+    };
+
+    assert_eq!(first, second);
+    assert_eq!(first, backoff.delays_with_seed(7).collect::<Vec<_>>());
+
+    let other = {
+        let _guard = Config::override_scope(Config::deterministic(8));
+        delays(&backoff)
+    };
+
+    assert_ne!(first, other);
+
+    // Out of the mode, the delays are drawn from the clock again.
+    assert_eq!(Config::current().seed, None);
+    assert_ne!(delays(&backoff), delays(&backoff));
+}
+
+#[test]
+fn the_other_settings_are_kept() {
+    let config = Config { api_key: Some(ApiKey::new("KEY")), .. Config::deterministic(1) };
+    assert_eq!(config.seed, Some(1));
+    assert_eq!(config.api_key, Some(ApiKey::new("KEY")));
+    assert_eq!(Config::deterministic(1).base_url, None);
+}
+
+#[cfg(feature = "batch")]
+#[test]
+fn batches_make_the_same_calls_in_the_same_order() {
+    static DATABASE_METADATA: &str = include_str!("fixtures/database_metadata.json");
+
+    let codes: Vec<String> = (0..16).map(|n| format!("DB{}", n)).collect();
+
+    let run = |strategy: SchedulingStrategy<DatabaseMetadataQuery>| {
+        let server = {
+            MockServer::start(|request| {
+                let code = {
+                    request.path.trim_start_matches("/api/v3/databases/").trim_end_matches(".json")
+                };
+
+                Response::json(DATABASE_METADATA.replace("\"WIKI\"", &format!("\"{}\"", code)))
+            })
+        };
+
+        let mut batch_query = BatchQuery::new();
+
+        for code in &codes {
+            let mut query = DatabaseMetadataQuery::new(code);
+            query.base_url(server.url()).api_key(if code.len() % 2 == 0 { "even" } else { "odd" });
+            batch_query.query(query);
+        }
+
+        batch_query.threads(4).concurrent_calls().scheduling(strategy);
+
+        let _guard = Config::override_scope(Config::deterministic(42));
+
+        let results: Vec<String> = {
+            batch_query.run().map(|result| result.unwrap().database_code).collect()
+        };
+
+        assert_eq!(results, codes);
+        server.requests().into_iter().map(|request| request.path).collect::<Vec<_>>()
+    };
+
+    let expected: Vec<String> = {
+        codes.iter().map(|code| format!("/api/v3/databases/{}.json", code)).collect()
+    };
+
+    for _ in 0..2 {
+        assert_eq!(run(SchedulingStrategy::WorkStealing), expected);
+        assert_eq!(run(SchedulingStrategy::RoundRobinStatic), expected);
+    }
+}
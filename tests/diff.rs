@@ -0,0 +1,142 @@
+extern crate quandl_v3;
+extern crate serde_json;
+
+mod common;
+
+const NAN: f64 = f64::NAN;
+const INFINITY: f64 = f64::INFINITY;
+
+use quandl_v3::diff::*;
+use quandl_v3::prelude::*;
+
+use common::{MockServer, Response};
+
+static PRIOR_CSV: &[u8] = include_bytes!("fixtures/prior.csv");
+static PRIOR_JSON: &[u8] = include_bytes!("fixtures/prior.json");
+static REVISED: &str = include_str!("fixtures/revised.csv");
+
+fn row(date: &str, values: &[f64]) -> Row {
+    (date.to_string(), values.to_vec())
+}
+
+fn change(date: &str, column: usize, old: f64, new: f64) -> ValueChange {
+    ValueChange { date: date.to_string(), column, old, new }
+}
+
+#[test]
+fn identical_pulls() {
+    let rows = vec![row("2016-01-04", &[1.0, NAN]), row("2016-01-05", &[2.0, 3.0])];
+    assert!(compare(&rows, &rows, 0.0).is_empty());
+}
+
+#[test]
+fn added_removed_and_changed() {
+    let old = vec![row("2016-01-06", &[3.0]), row("2016-01-05", &[2.0]), row("2016-01-04", &[1.0])];
+    let new = vec![row("2016-01-07", &[4.0]), row("2016-01-06", &[3.5]), row("2016-01-05", &[2.0])];
+
+    let diff = compare(&old, &new, 0.1);
+
+    assert_eq!(diff.added, ["2016-01-07"]);
+    assert_eq!(diff.removed, ["2016-01-04"]);
+    assert_eq!(diff.changed, vec![change("2016-01-06", 0, 3.0, 3.5)]);
+    assert!(!diff.is_empty());
+}
+
+#[test]
+fn absolute_tolerance_is_inclusive() {
+    let tolerance = Tolerance::Absolute(0.5);
+
+    assert!(tolerance.accepts(1.0, 1.5));
+    assert!(tolerance.accepts(1.5, 1.0));
+    assert!(!tolerance.accepts(1.0, 1.5001));
+    assert!(!tolerance.accepts(1000.0, 1001.0));
+
+    assert!(Tolerance::Absolute(0.0).accepts(2.5, 2.5));
+    assert!(!Tolerance::Absolute(0.0).accepts(2.5, 2.5000001));
+}
+
+#[test]
+fn relative_tolerance_scales_with_values() {
+    let tolerance = Tolerance::Relative(0.01);
+
+    assert!(tolerance.accepts(1000.0, 1010.0));
+    assert!(!tolerance.accepts(1000.0, 1011.0));
+    assert!(!tolerance.accepts(1.0, 1.02));
+    assert!(tolerance.accepts(0.0, 0.0));
+    assert!(!tolerance.accepts(0.0, 1e-12));
+    assert!(tolerance.accepts(-100.0, -100.5));
+}
+
+#[test]
+fn nan_and_infinity_semantics() {
+    for &tolerance in &[Tolerance::Absolute(1e9), Tolerance::Relative(1.0)] {
+        assert!(tolerance.accepts(NAN, NAN), "{:?}", tolerance);
+        assert!(!tolerance.accepts(NAN, 1.0), "{:?}", tolerance);
+        assert!(!tolerance.accepts(1.0, NAN), "{:?}", tolerance);
+        assert!(tolerance.accepts(INFINITY, INFINITY), "{:?}", tolerance);
+        assert!(!tolerance.accepts(INFINITY, -INFINITY), "{:?}", tolerance);
+        assert!(!tolerance.accepts(INFINITY, 1.0), "{:?}", tolerance);
+    }
+
+    let old = vec![row("2016-01-04", &[NAN, 1.0])];
+    let new = vec![row("2016-01-04", &[2.0, NAN])];
+    let diff = compare(&old, &new, 0.0);
+
+    assert_eq!(diff.changed.len(), 2);
+    assert!(diff.changed[0].old.is_nan() && diff.changed[0].new == 2.0);
+    assert!(diff.changed[1].old == 1.0 && diff.changed[1].new.is_nan());
+}
+
+#[test]
+fn different_widths_compare_against_nan() {
+    let old = vec![row("2016-01-04", &[1.0])];
+    let new = vec![row("2016-01-04", &[1.0, 2.0])];
+
+    let diff = compare(&old, &new, 0.0);
+    assert_eq!(diff.changed.len(), 1);
+    assert_eq!((diff.changed[0].column, diff.changed[0].new), (1, 2.0));
+    assert!(diff.changed[0].old.is_nan());
+
+    assert!(compare(&old, &[row("2016-01-04", &[1.0, NAN])], 0.0).is_empty());
+}
+
+#[test]
+fn stored_pulls_load_identically() {
+    let csv = parse_csv(PRIOR_CSV).unwrap();
+    let json = parse_json(PRIOR_JSON).unwrap();
+
+    assert_eq!(csv.len(), 4);
+    assert!(csv[1].1[1].is_nan());
+    assert!(compare(&csv, &json, 0.0).is_empty());
+
+    // Serializing rows produces the JSON format read back by `parse_json`.
+    let serialized = serde_json::to_string(&csv).unwrap();
+    assert!(compare(&parse_json(serialized.as_bytes()).unwrap(), &csv, 0.0).is_empty());
+
+    assert!(parse_csv(&b"2016-01-04,abc\n"[..]).is_err());
+    assert!(parse_json(&b"{}"[..]).is_err());
+}
+
+#[test]
+fn compare_with_fetched_data() {
+    let server = MockServer::routes(vec![
+        ("/api/v3/datasets/WIKI/AAPL/data.csv", Response::csv(REVISED)),
+    ]);
+
+    let mut query = DataQuery::new("WIKI", "AAPL");
+    query.base_url(server.url());
+
+    let prior = parse_csv(PRIOR_CSV).unwrap();
+
+    let diff = compare_fetched(&query, &prior, Tolerance::Absolute(1e-3)).unwrap();
+
+    assert_eq!(diff.added, ["2016-02-11"]);
+    assert_eq!(diff.removed, ["2016-02-05"]);
+    assert_eq!(diff.changed.len(), 1);
+    assert_eq!(diff.changed[0].date, "2016-02-09");
+    assert_eq!(diff.changed[0].new, 95.94);
+
+    let diff = compare_fetched(&query, &prior, Tolerance::Absolute(0.0)).unwrap();
+    assert_eq!(diff.changed.len(), 2);
+    assert_eq!(diff.changed[0], change("2016-02-08", 0, 93.13, 93.1301));
+}
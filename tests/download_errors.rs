@@ -0,0 +1,121 @@
+extern crate quandl_v3;
+
+mod common;
+
+use std::error::Error as StdError;
+use std::io::{BufRead, BufReader};
+use std::net::TcpListener;
+use std::thread::spawn;
+
+use quandl_v3::{DownloadError, DownloadErrorKind, Error};
+use quandl_v3::prelude::*;
+
+use common::{MockServer, Response};
+
+fn download_kind(base_url: &str) -> DownloadErrorKind {
+    let error = DatabaseMetadataQuery::new("WIKI").base_url(base_url).send().unwrap_err();
+
+    match error {
+        Error::DownloadFailed(ref e) => assert!(e.source().is_some(), "{:?}", e),
+        ref other => panic!("expected a download failure, got {:?}", other),
+    }
+
+    error.download_kind().unwrap()
+}
+
+/// Server reading the request line and closing the connection without answering it.
+///
+fn hang_up() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+
+    spawn(move || {
+        for stream in listener.incoming() {
+            let mut line = String::new();
+            let _ = BufReader::new(stream.unwrap()).read_line(&mut line);
+        }
+    });
+
+    format!("http://{}/api/v3", address)
+}
+
+#[test]
+fn dns() {
+    assert_eq!(download_kind("http://quandl-v3.invalid/api/v3"), DownloadErrorKind::Dns);
+}
+
+#[test]
+fn connection_refused() {
+    let address = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+    assert_eq!(download_kind(&format!("http://{}/api/v3", address)), DownloadErrorKind::Connect);
+}
+
+#[test]
+fn connection_closed_before_response() {
+    assert_eq!(download_kind(&hang_up()), DownloadErrorKind::Connect);
+}
+
+#[test]
+fn tls_handshake() {
+    let server = MockServer::start(|_| Response::not_found());
+    let base_url = format!("https://{}/api/v3", server.address());
+
+    assert_eq!(download_kind(&base_url), DownloadErrorKind::Tls);
+}
+
+#[test]
+fn body_read() {
+    let response = {
+        Response::json(r#"{"database": {"id": 4922, "na"#).header("Content-Length", "4096")
+    };
+
+    let server = MockServer::start(move |_| response.clone());
+    assert_eq!(download_kind(&server.url()), DownloadErrorKind::BodyRead);
+}
+
+#[test]
+fn redirect_loop() {
+    let server = MockServer::start(|request| {
+        Response::new(302).header("Location", &request.path)
+    });
+
+    assert_eq!(download_kind(&server.url()), DownloadErrorKind::TooManyRedirects);
+}
+
+#[test]
+fn other_errors() {
+    assert_eq!(Error::ParsingFailed("bad".to_string()).download_kind(), None);
+    assert_eq!(Error::IoError("bad".to_string()).download_kind(), None);
+
+    let error = Error::DownloadFailed("offline".into());
+    assert_eq!(error.download_kind(), Some(DownloadErrorKind::Other));
+    assert_eq!(error.to_string(), "download failed with error 'offline'.");
+
+    let error = DownloadError::new(DownloadErrorKind::Timeout, "deadline exceeded");
+    assert_eq!(error.message(), "deadline exceeded");
+    assert!(error.source().is_none());
+    assert_eq!(Error::DownloadFailed(error).download_kind(), Some(DownloadErrorKind::Timeout));
+}
+
+#[test]
+fn retryability() {
+    let retryable: Vec<DownloadErrorKind> = {
+        [DownloadErrorKind::Dns,
+         DownloadErrorKind::Connect,
+         DownloadErrorKind::Tls,
+         DownloadErrorKind::UntrustedCertificate,
+         DownloadErrorKind::Timeout,
+         DownloadErrorKind::BodyRead,
+         DownloadErrorKind::TooManyRedirects,
+         DownloadErrorKind::Other]
+            .iter()
+            .cloned()
+            .filter(|kind| kind.is_retryable())
+            .collect()
+    };
+
+    assert_eq!(retryable, [DownloadErrorKind::Connect,
+                           DownloadErrorKind::Timeout,
+                           DownloadErrorKind::BodyRead,
+                           DownloadErrorKind::Other]);
+}
@@ -0,0 +1,129 @@
+extern crate quandl_v3;
+
+mod common;
+
+use quandl_v3::prelude::*;
+use quandl_v3::{Error, Warning};
+
+use common::{MockServer, Response};
+
+static EMPTY: &str = include_str!("fixtures/data_empty.csv");
+static HEADER_ONLY: &str = include_str!("fixtures/data_header_only.csv");
+static THREE_COLUMNS: &str = include_str!("fixtures/data_3_columns.csv");
+
+fn server() -> MockServer {
+    MockServer::routes(vec![
+        ("/api/v3/datasets/WIKI/EMPTY/data.csv", Response::csv(EMPTY)),
+        ("/api/v3/datasets/WIKI/HEADER/data.csv", Response::csv(HEADER_ONLY)),
+        ("/api/v3/datasets/WIKI/BROKEN/data.csv", Response::csv("2016-02-10,n/a,95.7\n")),
+    ])
+}
+
+fn empty_dataset(dataset_code: &str) -> Warning {
+    Warning::EmptyDataset {
+        database_code: "WIKI".to_string(),
+        dataset_code: dataset_code.to_string(),
+    }
+}
+
+#[test]
+fn datasets_without_rows() {
+    let server = server();
+
+    for &code in &["EMPTY", "HEADER"] {
+        let mut query = DataQuery::new("WIKI", code);
+        query.base_url(server.url());
+
+        let data: Vec<(String, f64, f64)> = query.send().unwrap();
+        assert!(data.is_empty(), "{}", code);
+
+        let data = query.send_with_warnings::<(String, f64, f64)>().unwrap();
+        assert!(data.is_empty(), "{}", code);
+        assert_eq!(data.warnings, vec![empty_dataset(code)]);
+
+        let data = query.send_lossy::<(String, f64, f64)>().unwrap();
+        assert_eq!(data.warnings, vec![empty_dataset(code)]);
+    }
+}
+
+#[test]
+fn column_names_heading_rows() {
+    let query = DataQuery::new("WIKI", "AAPL");
+    let payload = format!("{}{}", HEADER_ONLY, THREE_COLUMNS);
+
+    let with_header: Vec<(String, f64, f64)> = query.decode(payload.as_bytes()).unwrap();
+    let without: Vec<(String, f64, f64)> = query.decode(THREE_COLUMNS.as_bytes()).unwrap();
+
+    assert_eq!(with_header, without);
+    assert!(query.decode_lossy::<(String, f64, f64)>(payload.as_bytes()).unwrap().is_clean());
+}
+
+#[test]
+fn malformed_data_is_still_an_error() {
+    let server = server();
+
+    let mut query = DataQuery::new("WIKI", "BROKEN");
+    query.base_url(server.url());
+
+    match query.send_with_warnings::<(String, f64, f64)>() {
+        Err(Error::CsvParsing { row: Some(1), .. }) => (),
+        other => panic!("expected a parsing error, got {:?}", other),
+    }
+
+    // Rows skipped are not an empty dataset.
+    let data = query.send_lossy::<(String, f64, f64)>().unwrap();
+    assert_eq!(data.warnings, vec![Warning::RowsSkipped(1)]);
+}
+
+#[cfg(feature = "zip")]
+mod code_lists {
+    use super::*;
+
+    static HEADER_ONLY: &[u8] = include_bytes!("fixtures/codes_header_only.zip");
+    static CODES: &[u8] = include_bytes!("fixtures/codes.zip");
+
+    fn server() -> MockServer {
+        MockServer::routes(vec![
+            ("/api/v3/databases/EMPTY/codes", Response::new(200).body(HEADER_ONLY)),
+            ("/api/v3/databases/WIKI/codes", Response::new(200).body(CODES)),
+            ("/api/v3/databases/BROKEN/codes", Response::new(200).body(&b"PK\x03\x04"[..])),
+        ])
+    }
+
+    fn query(server: &MockServer, database_code: &str) -> CodeListQuery {
+        let mut query = CodeListQuery::new(database_code);
+        query.base_url(server.url());
+        query
+    }
+
+    #[test]
+    fn databases_without_datasets() {
+        let server = server();
+
+        let codes: Vec<Code> = query(&server, "EMPTY").send().unwrap();
+        assert!(codes.is_empty());
+
+        let codes = query(&server, "EMPTY").send_with_warnings().unwrap();
+        assert!(codes.is_empty());
+
+        assert_eq!(codes.warnings, vec![Warning::EmptyDatabase {
+            database_code: "EMPTY".to_string(),
+        }]);
+
+        assert_eq!(codes.warnings[0].to_string(), "database EMPTY has no dataset.");
+
+        let codes = query(&server, "WIKI").send_with_warnings().unwrap();
+        assert!(codes.is_clean());
+        assert_eq!(codes.len(), 2);
+    }
+
+    #[test]
+    fn malformed_archives_are_still_errors() {
+        let server = server();
+
+        match query(&server, "BROKEN").send_with_warnings() {
+            Err(Error::ZipExtraction(_)) => (),
+            other => panic!("expected a zip error, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,118 @@
+extern crate quandl_v3;
+
+mod common;
+
+use quandl_v3::{Bytes, Error};
+use quandl_v3::prelude::*;
+
+use common::{MockServer, Response};
+
+static DATA: &str = include_str!("fixtures/data_3_columns.csv");
+static DATASET_DATA: &str = include_str!("fixtures/dataset_data.json");
+static CODES: &[u8] = include_bytes!("fixtures/codes.zip");
+
+type Row = (String, f64, f64);
+
+fn server() -> MockServer {
+    MockServer::routes(vec![
+        ("/api/v3/datasets/WIKI/AAPL/data.csv", Response::csv(DATA)),
+        ("/api/v3/datasets/WIKI/AAPL.json", Response::json(DATASET_DATA)),
+        ("/api/v3/databases/WIKI/codes", Response::new(200).body(CODES)),
+    ])
+}
+
+#[test]
+fn same_payload_as_encoded_data() {
+    let server = server();
+
+    let mut data = DataQuery::new("WIKI", "AAPL");
+    data.base_url(server.url());
+
+    let bytes = ApiCall::<Vec<Row>>::encoded_bytes(&data).unwrap();
+    assert_eq!(bytes, DATA.as_bytes());
+    assert_eq!(&bytes[..], &ApiCall::<Vec<Row>>::encoded_data(&data).unwrap()[..]);
+
+    // Through a reference too.
+    assert_eq!(ApiCall::<Vec<Row>>::encoded_bytes(&&data).unwrap(), bytes);
+
+    let mut dataset = DataAndMetadataQuery::new("WIKI", "AAPL");
+    dataset.base_url(server.url());
+
+    let bytes = ApiCall::<Dataset<Row>>::encoded_bytes(&dataset).unwrap();
+    assert_eq!(bytes, DATASET_DATA.as_bytes());
+    assert_eq!(&bytes[..], &ApiCall::<Dataset<Row>>::encoded_data(&dataset).unwrap()[..]);
+}
+
+#[cfg(feature = "zip")]
+#[test]
+fn same_archive_as_encoded_data() {
+    let server = server();
+
+    let mut codes = CodeListQuery::new("WIKI");
+    codes.base_url(server.url());
+
+    let bytes = ApiCall::<Vec<Code>>::encoded_bytes(&codes).unwrap();
+    assert_eq!(bytes, CODES);
+    assert_eq!(&bytes[..], &ApiCall::<Vec<Code>>::encoded_data(&codes).unwrap()[..]);
+    assert_eq!(codes.decode(&bytes).unwrap(), codes.send().unwrap());
+}
+
+#[cfg(not(feature = "zip"))]
+#[test]
+fn same_archive_as_encoded_data() {
+    let server = server();
+
+    let mut codes = CodeListQuery::new("WIKI");
+    codes.base_url(server.url());
+
+    let bytes = codes.encoded_bytes().unwrap();
+    assert_eq!(bytes, CODES);
+    assert_eq!(&bytes[..], &codes.encoded_data().unwrap()[..]);
+}
+
+#[test]
+fn decoded_from_shared_slices() {
+    let server = server();
+
+    let mut query = DataQuery::new("WIKI", "AAPL");
+    query.base_url(server.url());
+
+    let bytes: Bytes = ApiCall::<Vec<Row>>::encoded_bytes(&query).unwrap();
+    let copy = bytes.clone();
+
+    // Clones and slices point into the same buffer.
+    assert_eq!(copy.as_ptr(), bytes.as_ptr());
+
+    let first_row = bytes.slice(..DATA.find('\n').unwrap() + 1);
+    assert_eq!(first_row.as_ptr(), bytes.as_ptr());
+
+    let rows: Vec<Row> = query.decode(&first_row).unwrap();
+    assert_eq!(rows, vec![("2016-02-10".to_string(), 94.27, 95.7)]);
+
+    let all: Vec<Row> = query.decode(&copy).unwrap();
+    assert_eq!(all, query.send().unwrap());
+}
+
+#[test]
+fn same_errors_as_encoded_data() {
+    let server = server();
+
+    let mut query = DataQuery::new("WIKI", "MSFT");
+    query.base_url(server.url());
+
+    match ApiCall::<Vec<Row>>::encoded_bytes(&query) {
+        Err(Error::NotFound { api_error: Some(e), .. }) => {
+            assert_eq!(e.quandl_error.code, "QECx02")
+        },
+
+        other => panic!("{:?}", other),
+    }
+
+    // Nothing is sent for an invalid query.
+    let mut query = DataQuery::new("", "WIKI/AAPL");
+    query.base_url(server.url()).strict(true);
+
+    let sent = ApiCall::<Vec<Row>>::encoded_bytes(&query);
+    assert!(matches!(sent, Err(Error::ValidationFailed(_))), "{:?}", sent);
+    assert_eq!(server.hits(), 1);
+}
@@ -0,0 +1,139 @@
+extern crate quandl_v3;
+extern crate serde_json;
+
+use std::collections::BTreeMap;
+use std::error::Error as StdError;
+use std::io;
+use std::time::Duration;
+
+use quandl_v3::*;
+
+fn api_error() -> ApiErrorResponse {
+    let mut errors = BTreeMap::new();
+    errors.insert("start_date".to_string(), vec!["is out of range.".to_string()]);
+
+    ApiErrorResponse {
+        errors: Some(errors),
+        quandl_error: QuandlError {
+            code: "QECx02".to_string(),
+            message: "You have submitted an incorrect Quandl code.".to_string(),
+        },
+    }
+}
+
+fn every_variant() -> Vec<Error> {
+    vec![
+        Error::ApiCallFailed(api_error()),
+        Error::DownloadFailed(DownloadError::new(DownloadErrorKind::Timeout, "timed out")),
+        Error::DownloadFailed(DownloadError::with_source(DownloadErrorKind::BodyRead,
+                                                         io::Error::other("reset by peer"))),
+        Error::ParsingFailed("unexpected content type".to_string()),
+        Error::JsonParsing { message: "missing field `id`".to_string(), snippet: "{}".to_string() },
+        Error::CsvParsing { row: Some(3), message: "invalid float literal".to_string() },
+        Error::CsvParsing { row: None, message: "unequal lengths".to_string() },
+        Error::ZipExtraction("invalid archive".to_string()),
+        Error::IoError("disk full".to_string()),
+        Error::Skipped("https://www.quandl.com/api/v3/databases/WIKI.json".to_string()),
+        Error::QuotaExhausted("https://www.quandl.com/api/v3/databases/WIKI.json".to_string()),
+        Error::ValidationFailed(vec![
+            ValidationError::new("start_date", "must not be after end_date."),
+            ValidationError::new("a_new_parameter", "is unknown."),
+        ]),
+        Error::TransformFailed("attempt to divide by zero".to_string()),
+        Error::BatchInternal { worker: 3, message: "index out of bounds".to_string() },
+        Error::DeadlineExceeded,
+        Error::Cancelled,
+        Error::ResponseTooLarge { limit: 1_000, observed: 1_001 },
+        Error::UnzippedTooLarge { limit: 100_000, observed: 4_294_967_295 },
+        Error::SubscriptionRequired { database_code: "EOD".to_string() },
+        Error::RateLimitExceeded {
+            retry_after: Some(Duration::from_secs(30)),
+            api_error: None,
+            snippet: "<html><head><title>429 Too Many Requests</title></head>".to_string(),
+        },
+        Error::AccessDenied { status: 401, api_error: Some(api_error()), snippet: "{".to_string() },
+        Error::NotFound { api_error: None, snippet: String::new() },
+    ]
+}
+
+#[test]
+fn every_variant_round_trips() {
+    for error in every_variant() {
+        let json = serde_json::to_string(&error).unwrap();
+        let decoded: Error = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(decoded, error, "{}", json);
+        assert_eq!(decoded.to_string(), error.to_string());
+    }
+}
+
+#[test]
+fn documented_shape() {
+    let json = |error: Error| serde_json::to_value(error).unwrap();
+
+    assert_eq!(json(Error::CsvParsing { row: Some(3), message: "invalid".to_string() }),
+               serde_json::json!({
+                   "version": 1,
+                   "kind": "csv_parsing",
+                   "details": {"row": 3, "message": "invalid"},
+               }));
+
+    assert_eq!(json(Error::IoError("disk full".to_string())),
+               serde_json::json!({"version": 1, "kind": "io_error", "details": "disk full"}));
+
+    assert_eq!(json(Error::DeadlineExceeded),
+               serde_json::json!({"version": 1, "kind": "deadline_exceeded"}));
+
+    assert_eq!(json(Error::ApiCallFailed(api_error()))["details"]["quandl_error"]["code"],
+               "QECx02");
+
+    assert_eq!(json(Error::ValidationFailed(vec![ValidationError::new("rows", "is 0.")])),
+               serde_json::json!({
+                   "version": 1,
+                   "kind": "validation_failed",
+                   "details": [{"field": "rows", "message": "is 0."}],
+               }));
+
+    assert_eq!(ERROR_FORMAT_VERSION, 1);
+}
+
+#[test]
+fn download_sources_degrade_to_their_message() {
+    let source = io::Error::other("reset by peer");
+    let error = DownloadError::with_source(DownloadErrorKind::BodyRead, source);
+
+    assert_eq!(serde_json::to_value(Error::DownloadFailed(error)).unwrap(), serde_json::json!({
+        "version": 1,
+        "kind": "download_failed",
+        "details": {"kind": "body_read", "message": "reset by peer", "source": "reset by peer"},
+    }));
+
+    let decoded: Error = {
+        serde_json::from_str("{\"version\":1,\"kind\":\"download_failed\",\"details\":\
+                              {\"kind\":\"tls\",\"message\":\"handshake\",\"source\":\"expired\"}}")
+            .unwrap()
+    };
+
+    assert_eq!(decoded.download_kind(), Some(DownloadErrorKind::Tls));
+
+    let source = decoded.source().and_then(|e| e.source()).map(|e| e.to_string());
+    assert_eq!(source, Some("expired".to_string()));
+
+    // No source at all.
+    let decoded: Error = {
+        serde_json::from_str("{\"version\":1,\"kind\":\"download_failed\",\"details\":\
+                              {\"kind\":\"timeout\",\"message\":\"timed out\"}}")
+            .unwrap()
+    };
+
+    assert!(decoded.source().unwrap().source().is_none());
+}
+
+#[test]
+fn unknown_versions_and_kinds_are_rejected() {
+    let error = serde_json::from_str::<Error>("{\"version\":2,\"kind\":\"deadline_exceeded\"}");
+    assert!(error.unwrap_err().to_string().contains("unsupported error format version 2"));
+
+    assert!(serde_json::from_str::<Error>("{\"version\":1,\"kind\":\"exploded\"}").is_err());
+    assert!(serde_json::from_str::<Error>("{\"kind\":\"deadline_exceeded\"}").is_err());
+}
@@ -0,0 +1,242 @@
+extern crate quandl_v3;
+extern crate serde_json;
+
+#[macro_use] extern crate serde_derive;
+
+mod common;
+
+use std::io::{self, Write};
+
+use quandl_v3::Error;
+use quandl_v3::export::*;
+use quandl_v3::prelude::*;
+
+use common::{MockServer, Response};
+
+static DATASET_DATA: &str = include_str!("fixtures/dataset_data.json");
+static DATASET_MIXED: &str = include_str!("fixtures/dataset_mixed.json");
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Note {
+    #[serde(rename = "Date")]
+    date: String,
+    text: String,
+}
+
+fn dataset<T: serde::de::DeserializeOwned + Clone>() -> Dataset<T> {
+    fetched("WIKI", DATASET_DATA)
+}
+
+fn fetched<T: serde::de::DeserializeOwned + Clone>(database_code: &str, json: &str) -> Dataset<T> {
+    let path = format!("/api/v3/datasets/{}/AAPL.json", database_code);
+    let server = MockServer::routes(vec![(&path[..], Response::json(json))]);
+
+    let mut query = DataAndMetadataQuery::new(database_code, "AAPL");
+    query.base_url(server.url());
+    query.send().unwrap()
+}
+
+fn long(date: &str, column: &str, value: Option<f64>, text_value: Option<&str>) -> LongRow {
+    LongRow {
+        database_code: "ZACKS".to_string(),
+        dataset_code: "AAPL".to_string(),
+        date: date.to_string(),
+        column: column.to_string(),
+        unit: None,
+        value,
+        text_value: text_value.map(|x| x.to_string()),
+    }
+}
+
+fn written<F: FnOnce(&mut Vec<u8>) -> quandl_v3::Result<u64>>(write: F) -> (u64, String) {
+    let mut output = vec![];
+    let rows = write(&mut output).unwrap();
+    (rows, String::from_utf8(output).unwrap())
+}
+
+#[test]
+fn one_row_per_line() {
+    let rows = [("2016-02-10".to_string(), 95.92), ("2016-02-09".to_string(), 94.29)];
+
+    assert_eq!(written(|w| write_jsonl(&rows, w)),
+               (2, "[\"2016-02-10\",95.92]\n[\"2016-02-09\",94.29]\n".to_string()));
+
+    assert_eq!(written(|w| write_jsonl::<(String, f64), _>(&[], w)), (0, String::new()));
+}
+
+#[test]
+fn embedded_newlines_are_escaped() {
+    let notes = [
+        Note { date: "2016-02-10".to_string(), text: "first line\nsecond line".to_string() },
+        Note { date: "2016-02-09".to_string(), text: "tab\tquote\" crlf\r\n".to_string() },
+    ];
+
+    let (rows, output) = written(|w| write_jsonl(&notes, w));
+
+    assert_eq!(rows, 2);
+    assert_eq!(output.lines().count(), 2);
+    assert_eq!(output.lines().next().unwrap(),
+               "{\"Date\":\"2016-02-10\",\"text\":\"first line\\nsecond line\"}");
+
+    let decoded: Vec<Note> = output.lines().map(|x| serde_json::from_str(x).unwrap()).collect();
+    assert_eq!(decoded, notes);
+}
+
+#[test]
+fn streaming_stops_at_the_first_error() {
+    let rows = vec![Ok(1), Ok(2), Err(Error::DeadlineExceeded), Ok(3)];
+    let mut output = vec![];
+
+    assert_eq!(write_jsonl_stream(rows, &mut output), Err(Error::DeadlineExceeded));
+    assert_eq!(output, b"1\n2\n");
+
+    assert_eq!(written(|w| write_jsonl_stream((0..3).map(Ok), w)), (3, "0\n1\n2\n".to_string()));
+}
+
+#[test]
+fn write_failures() {
+    struct Full;
+
+    impl Write for Full {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::Error::other("disk full"))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    assert_eq!(write_jsonl(&[1], Full), Err(Error::IoError("disk full".to_string())));
+}
+
+#[test]
+fn self_describing_dataset_lines() {
+    let prices: Dataset<(String, f64, f64)> = dataset();
+    let jsonl = prices.to_jsonl().unwrap();
+
+    assert_eq!(jsonl.lines().count(), prices.len());
+
+    assert_eq!(jsonl.lines().next().unwrap(),
+               "{\"database_code\":\"WIKI\",\"dataset_code\":\"AAPL\",\"date\":\"2016-02-10\",\
+                \"row\":[\"2016-02-10\",95.92,94.99]}");
+
+    // Structs have their date looked up by name.
+    let dataset: Dataset<Note> = prices.map_rows(|(date, open, _)| {
+        Note { date, text: format!("opened at\n{}", open) }
+    });
+
+    let line: serde_json::Value = {
+        serde_json::from_str(dataset.to_jsonl().unwrap().lines().last().unwrap()).unwrap()
+    };
+
+    assert_eq!(line, serde_json::json!({
+        "database_code": "WIKI",
+        "dataset_code": "AAPL",
+        "date": "2016-02-08",
+        "row": {"Date": "2016-02-08", "text": "opened at\n93.13"},
+    }));
+
+    // Rows without a date.
+    let dataset = dataset.map_rows(|note| note.text.len());
+    assert!(dataset.to_jsonl().unwrap().contains("\"date\":null,\"row\":15}"));
+}
+
+#[test]
+fn long_format_of_mixed_rows() {
+    let dataset: Dataset<Vec<serde_json::Value>> = fetched("ZACKS", DATASET_MIXED);
+    let rows = dataset.to_long();
+
+    // One record per value, text included.
+    assert_eq!(rows.len(), dataset.len() * 3);
+
+    assert_eq!(rows[..6], [
+        long("2016-02-10", "Close", Some(94.99), None),
+        long("2016-02-10", "Rating", None, Some("Buy")),
+        long("2016-02-10", "Analysts", Some(41.0), None),
+        long("2016-02-09", "Close", Some(95.01), None),
+        long("2016-02-09", "Rating", None, Some("Strong Buy")),
+        long("2016-02-09", "Analysts", None, None),
+    ]);
+
+    assert_eq!(rows[7], long("2016-02-08", "Rating", None, None));
+
+    // Numbers decoded as strings are still values, and empty strings missing ones.
+    let strings: Dataset<Vec<String>> = dataset.map_rows(|row| {
+        row.into_iter().map(|x| {
+            match x {
+                serde_json::Value::String(x) => x,
+                serde_json::Value::Null => String::new(),
+                x => x.to_string(),
+            }
+        }).collect()
+    });
+
+    assert_eq!(strings.to_long(), rows);
+}
+
+#[test]
+fn long_format_of_numeric_rows() {
+    let dataset: Dataset<DataRow> = dataset();
+    let rows = dataset.to_long();
+
+    assert_eq!(rows.len(), 3 * 2);
+    assert_eq!(rows.iter().map(|x| &x.column[..]).collect::<Vec<_>>(),
+               vec!["Open", "Close", "Open", "Close", "Open", "Close"]);
+
+    assert!(rows.iter().all(|x| x.database_code == "WIKI" && x.text_value.is_none()));
+    assert_eq!(rows[5].value, Some(95.01));
+}
+
+#[test]
+fn long_format_column_names() {
+    let mut dataset: Dataset<DataRow> = dataset();
+
+    // Columns the metadata does not name are numbered.
+    dataset.metadata.column_names.truncate(2);
+    let columns: Vec<String> = dataset.to_long().into_iter().map(|x| x.column).take(2).collect();
+    assert_eq!(columns, vec!["Open", "col_2"]);
+
+    dataset.metadata.column_names.clear();
+    let columns: Vec<String> = dataset.to_long().into_iter().map(|x| x.column).take(2).collect();
+    assert_eq!(columns, vec!["col_1", "col_2"]);
+
+    // Structs are taken field by field.
+    let notes = dataset.map_rows(|row| Note { date: row.date, text: "n/a".to_string() });
+    let rows = notes.to_long();
+
+    assert_eq!(rows.len(), 3);
+    assert_eq!((&rows[0].date[..], &rows[0].column[..]), ("2016-02-10", "text"));
+    assert_eq!(rows[0].text_value.as_deref(), Some("n/a"));
+}
+
+#[test]
+fn long_format_units() {
+    let mut dataset: Dataset<DataRow> = dataset();
+    dataset.metadata.column_names = vec!["Date".to_string(), "Open (USD)".to_string()];
+
+    let rows = dataset.to_long();
+    assert_eq!((&rows[0].column[..], rows[0].unit.as_deref()), ("Open (USD)", Some("USD")));
+    assert_eq!((&rows[1].column[..], rows[1].unit.as_deref()), ("col_2", None));
+
+    let (_, csv) = written(|w| write_long_csv(&rows[..1], w));
+    assert_eq!(csv, "database_code,dataset_code,date,column,unit,value,text_value\n\
+                     WIKI,AAPL,2016-02-10,Open (USD),USD,95.92,\n");
+}
+
+#[test]
+fn long_format_as_csv() {
+    let dataset: Dataset<Vec<serde_json::Value>> = fetched("ZACKS", DATASET_MIXED);
+    let rows = dataset.to_long();
+
+    let (written_rows, csv) = written(|w| write_long_csv(&rows[..3], w));
+
+    assert_eq!(written_rows, 3);
+    assert_eq!(csv, "database_code,dataset_code,date,column,unit,value,text_value\n\
+                     ZACKS,AAPL,2016-02-10,Close,,94.99,\n\
+                     ZACKS,AAPL,2016-02-10,Rating,,,Buy\n\
+                     ZACKS,AAPL,2016-02-10,Analysts,,41.0,\n");
+
+    assert_eq!(written(|w| write_long_csv(&[], w)),
+               (0, "database_code,dataset_code,date,column,unit,value,text_value\n".to_string()));
+}
@@ -0,0 +1,161 @@
+extern crate quandl_v3;
+
+mod common;
+
+use std::net::TcpListener;
+
+use quandl_v3::{healthcheck, DownloadErrorKind, Error};
+use quandl_v3::config::Config;
+use quandl_v3::prelude::*;
+
+use common::{MockServer, Response};
+
+static DATABASE_METADATA: &str = include_str!("fixtures/database_metadata.json");
+
+fn metadata_server() -> MockServer {
+    MockServer::routes(vec![("/api/v3/databases/WIKI.json", Response::json(DATABASE_METADATA))])
+}
+
+/// Base URL of a host refusing connections, nothing listening on its port anymore.
+///
+fn refusing() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    format!("http://{}/api/v3", listener.local_addr().unwrap())
+}
+
+fn origin(server: &MockServer) -> String {
+    format!("http://{}", server.address())
+}
+
+fn query(base_url: &str, config: &ClientConfig) -> DatabaseMetadataQuery {
+    let mut query = DatabaseMetadataQuery::new("WIKI");
+    query.base_url(base_url).api_key("key").client_config(config);
+    query
+}
+
+#[test]
+fn unreachable_hosts_fail_over() {
+    let primary = refusing();
+    let secondary = metadata_server();
+
+    let mut config = ClientConfig::new();
+    config.fallback_hosts(vec!["http://127.0.0.1:1", &secondary.url()]);
+    assert_eq!(config.fallbacks(), ["http://127.0.0.1:1".to_string(), origin(&secondary)]);
+
+    let metadata = query(&primary, &config).send().unwrap();
+    assert_eq!(metadata.database_code, "WIKI");
+
+    assert_eq!(secondary.hits(), 1);
+    assert_eq!(secondary.requests()[0].path, "/api/v3/databases/WIKI.json");
+    assert_eq!(secondary.requests()[0].query, "api_key=key");
+    assert_eq!(config.active_host(&primary).unwrap(), origin(&secondary));
+
+    // Without fallbacks, the same request fails.
+    match query(&primary, &ClientConfig::new()).send() {
+        Err(e) => assert_eq!(e.download_kind(), Some(DownloadErrorKind::Connect)),
+        Ok(_) => panic!("the primary host refuses connections"),
+    }
+}
+
+#[test]
+fn responding_host_is_sticky() {
+    let primary = metadata_server();
+    let secondary = metadata_server();
+
+    let mut config = ClientConfig::new();
+    config.fallback_hosts(vec![secondary.url()]);
+
+    assert_eq!(config.active_host(primary.url()).unwrap(), origin(&primary));
+
+    primary.set_down(true);
+    query(&primary.url(), &config).send().unwrap();
+    assert_eq!(secondary.hits(), 1);
+
+    // The primary host is back, but the secondary one keeps the requests until it fails.
+    primary.set_down(false);
+    query(&primary.url(), &config).send().unwrap();
+    assert_eq!((primary.hits(), secondary.hits()), (0, 2));
+    assert_eq!(config.active_host(primary.url()).unwrap(), origin(&secondary));
+
+    // Other configurations with the same fallbacks share the host.
+    let mut same = ClientConfig::new();
+    same.fallback_hosts(vec![secondary.url()]).coalesce(true);
+    query(&primary.url(), &same).send().unwrap();
+    assert_eq!((primary.hits(), secondary.hits()), (0, 3));
+
+    secondary.set_down(true);
+    query(&primary.url(), &config).send().unwrap();
+    query(&primary.url(), &config).send().unwrap();
+    assert_eq!((primary.hits(), secondary.hits()), (2, 3));
+    assert_eq!(config.active_host(primary.url()).unwrap(), origin(&primary));
+}
+
+#[test]
+fn every_host_unreachable() {
+    let primary = metadata_server();
+    let secondary = metadata_server();
+    primary.set_down(true);
+    secondary.set_down(true);
+
+    let mut config = ClientConfig::new();
+    config.fallback_hosts(vec![secondary.url()]);
+
+    match query(&primary.url(), &config).send() {
+        Err(e) => assert_eq!(e.download_kind(), Some(DownloadErrorKind::Connect)),
+        Ok(_) => panic!("no host is up"),
+    }
+
+    assert_eq!(config.active_host(primary.url()).unwrap(), origin(&primary));
+}
+
+#[test]
+fn api_errors_never_fail_over() {
+    let secondary = metadata_server();
+
+    let mut config = ClientConfig::new();
+    config.fallback_hosts(vec![secondary.url()]);
+
+    let primary = MockServer::routes(vec![]);
+
+    match query(&primary.url(), &config).send() {
+        Err(Error::NotFound { api_error: Some(e), .. }) => {
+            assert_eq!(e.quandl_error.code, "QECx02")
+        },
+
+        other => panic!("{:?}", other),
+    }
+
+    let primary = MockServer::start(|_| Response::new(503).body("Service Unavailable"));
+    assert!(query(&primary.url(), &config).send().is_err());
+
+    assert_eq!(primary.hits(), 1);
+    assert_eq!(secondary.hits(), 0);
+    assert_eq!(config.active_host(primary.url()).unwrap(), origin(&primary));
+}
+
+#[test]
+fn healthcheck_reports_the_responding_host() {
+    let secondary = MockServer::start(|_| Response::json(r#"{"databases": [], "meta": {}}"#));
+    let primary = refusing();
+
+    let mut client = ClientConfig::new();
+    client.fallback_hosts(vec![secondary.url()]);
+
+    let config = {
+        Config {
+            base_url: Some(primary.clone()),
+            client: Some(client),
+            .. Config::default()
+        }
+    };
+
+    let health = healthcheck(&config).unwrap();
+    assert!(health.is_healthy(), "{}", health);
+    assert_eq!(health.url, format!("{}/databases.json?per_page=1", secondary.url()));
+}
+
+#[test]
+#[should_panic(expected = "fallback host")]
+fn fallback_hosts_are_urls() {
+    ClientConfig::new().fallback_hosts(vec!["mirror.example.com"]);
+}
@@ -0,0 +1,84 @@
+//! Checks of what remains available depending on cargo features.
+//!
+//! The crate is expected to build and pass its (offline) tests with each of:
+//!
+//! * `cargo test` (default features, i.e. `zip` and `batch`);
+//! * `cargo test --no-default-features` (no zip support, `CodeListQuery` only builds its URL);
+//! * `cargo test --no-default-features --features zip` (single calls only, without `BatchQuery`,
+//!   `fetch_typed`, `fetch_columns`, `mirror`, `conformance` nor their threads);
+//! * `cargo test --features rayon` (parallel CSV decoding);
+//! * `cargo test --features async` (asynchronous rate limiting and `BatchQuery::stream`);
+//! * `cargo test --features codegen` (row struct generation from dataset metadata);
+//! * `cargo test --features aliases` (renamed and retired database codes);
+//! * `cargo test --features registry` (hints about well-known databases);
+//! * `cargo test --features cli` (the `quandl-fetch` binary).
+
+extern crate quandl_v3;
+
+mod common;
+
+use quandl_v3::prelude::*;
+
+use common::{MockServer, Response};
+
+fn code_list_query(server: &MockServer) -> CodeListQuery {
+    let mut query = CodeListQuery::new("WIKI");
+    query.base_url(server.url()).api_key("key");
+    query
+}
+
+#[test]
+fn code_list_url_without_zip_support() {
+    let server = MockServer::routes(vec![]);
+
+    assert_eq!(code_list_query(&server).url(),
+               format!("{}/databases/WIKI/codes?api_key=key", server.url()));
+}
+
+#[test]
+fn code_list_archive_without_zip_support() {
+    let server = MockServer::routes(vec![
+        ("/api/v3/databases/WIKI/codes", Response::new(200).body(b"PK\x05\x06not really a zip")),
+    ]);
+
+    let archive = code_list_query(&server).encoded_data().unwrap();
+    assert_eq!(&archive[..2], b"PK");
+}
+
+#[cfg(feature = "zip")]
+#[test]
+fn code_list_parsing_requires_zip() {
+    let server = MockServer::routes(vec![
+        ("/api/v3/databases/WIKI/codes", Response::new(200).body(b"not a zip")),
+    ]);
+
+    let result: quandl_v3::Result<Vec<Code>> = code_list_query(&server).send();
+
+    match result {
+        Err(quandl_v3::Error::ZipExtraction(_)) => {},
+        result => panic!("unexpected result: {:?}", result),
+    }
+}
+
+#[cfg(not(feature = "batch"))]
+#[test]
+fn single_calls_without_batch_support() {
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    use quandl_v3::rate_limit::{acquire, RateLimiter};
+
+    let server = MockServer::routes(vec![
+        ("/api/v3/datasets/WIKI/AAPL/data.csv", Response::csv("2016-02-10,94.27\n")),
+    ]);
+
+    let limiter = Mutex::new(RateLimiter::new(vec![(10, Duration::from_secs(1))]));
+
+    let mut query = DataQuery::new("WIKI", "AAPL");
+    query.base_url(server.url());
+
+    acquire(&limiter, "");
+    let data: Vec<(String, f64)> = query.send().unwrap();
+
+    assert_eq!(data, vec![("2016-02-10".to_string(), 94.27)]);
+}
@@ -0,0 +1,56 @@
+/// Row of the Quandl dataset `WIKI/AAPL`: Apple Inc (AAPL) Prices, Dividends, Splits and Trading Volume
+///
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct WikiAaplRow {
+    /// Date
+    ///
+    pub date: String,
+
+    /// Open
+    ///
+    pub open: Option<f64>,
+
+    /// High
+    ///
+    pub high: Option<f64>,
+
+    /// Low
+    ///
+    pub low: Option<f64>,
+
+    /// Close
+    ///
+    pub close: Option<f64>,
+
+    /// Volume
+    ///
+    pub volume: Option<f64>,
+
+    /// Ex-Dividend
+    ///
+    pub ex_dividend: Option<f64>,
+
+    /// Split Ratio
+    ///
+    pub split_ratio: Option<f64>,
+
+    /// Adj. Open
+    ///
+    pub adj_open: Option<f64>,
+
+    /// Adj. High
+    ///
+    pub adj_high: Option<f64>,
+
+    /// Adj. Low
+    ///
+    pub adj_low: Option<f64>,
+
+    /// Adj. Close
+    ///
+    pub adj_close: Option<f64>,
+
+    /// Adj. Volume
+    ///
+    pub adj_volume: Option<f64>,
+}
@@ -0,0 +1,248 @@
+extern crate quandl_v3;
+extern crate url;
+
+use std::convert::TryFrom;
+
+use url::Url;
+
+use quandl_v3::Error;
+use quandl_v3::prelude::*;
+
+type Rows = Vec<(String, f64)>;
+
+/// Check that `$query` is rebuilt identically from its URL, `url()` being given by `$url`.
+///
+macro_rules! round_trip {
+    ($t:ident, $query:expr, $url:expr) => {{
+        let query = $query;
+        let url: String = $url(&query);
+        let rebuilt = $t::try_from(Url::parse(&url).unwrap()).unwrap();
+
+        assert_eq!($url(&rebuilt), url);
+        assert_eq!(rebuilt, query, "{}", url);
+    }};
+
+    ($t:ident, $query:expr) => {
+        round_trip!($t, $query, |query: &$t| query.url())
+    };
+}
+
+fn data_url(query: &DataQuery) -> String {
+    ApiCall::<Rows>::url(query)
+}
+
+fn data_and_metadata_url(query: &DataAndMetadataQuery) -> String {
+    ApiCall::<Dataset<(String, f64)>>::url(query)
+}
+
+#[test]
+fn metadata_queries() {
+    round_trip!(DatabaseMetadataQuery, DatabaseMetadataQuery::new("WIKI"));
+    round_trip!(DatabaseMetadataQuery, DatabaseMetadataQuery::new("WIKI").api_key("KEY").clone());
+
+    round_trip!(DatasetMetadataQuery, {
+        DatasetMetadataQuery::new("WIKI", "AAPL").api_key("KEY").clone()
+    });
+}
+
+#[test]
+fn base_urls_and_versions() {
+    round_trip!(DatabaseMetadataQuery, {
+        DatabaseMetadataQuery::new("WIKI").base_url("http://localhost:8080/api/v3").clone()
+    });
+
+    round_trip!(DatabaseMetadataQuery, {
+        DatabaseMetadataQuery::new("WIKI").base_url("http://localhost:8080").clone()
+    });
+
+    round_trip!(DatabaseMetadataQuery, {
+        DatabaseMetadataQuery::new("WIKI").api_version("v4").clone()
+    });
+
+    // The version of another server is part of its base URL.
+    let mut query = DatabaseMetadataQuery::new("WIKI");
+    query.base_url("http://localhost:8080/api/v3").api_version("beta");
+
+    let rebuilt = DatabaseMetadataQuery::try_from(Url::parse(&query.url()).unwrap()).unwrap();
+    assert_eq!(rebuilt.url(), query.url());
+}
+
+#[test]
+fn search_queries() {
+    round_trip!(DatabaseSearch, DatabaseSearch::new());
+
+    round_trip!(DatabaseSearch, {
+        DatabaseSearch::new()
+            .api_key("KEY")
+            .query(["stock", "price"])
+            .phrase("crude oil")
+            .exclude("gasoline")
+            .exclude("natural gas")
+            .per_page(5)
+            .page(2)
+            .clone()
+    });
+
+    round_trip!(DatasetSearch, DatasetSearch::new("WIKI"));
+    round_trip!(DatasetSearch, DatasetSearch::new("WIKI").query(["apple"]).page(3).clone());
+
+    round_trip!(DatasetListingQuery, {
+        DatasetListingQuery::new("WIKI").per_page(100).page(2).clone()
+    });
+
+    round_trip!(DatasetListingQuery, {
+        DatasetListingQuery::new("WIKI").sort_by_last_updated().api_key("KEY").clone()
+    });
+}
+
+#[test]
+fn database_queries() {
+    round_trip!(CodeListQuery, CodeListQuery::new("WIKI").api_key("KEY").clone());
+
+    round_trip!(DatabaseDownloadQuery, DatabaseDownloadQuery::new("WIKI"));
+    round_trip!(DatabaseDownloadQuery, DatabaseDownloadQuery::new("WIKI").partial().clone());
+
+    round_trip!(DatabaseDownloadQuery, {
+        DatabaseDownloadQuery::new("WIKI").partial().api_key("KEY").clone()
+    });
+}
+
+#[test]
+fn data_queries() {
+    round_trip!(DataQuery, DataQuery::new("WIKI", "AAPL"), data_url);
+
+    round_trip!(DataQuery, {
+        DataQuery::new("WIKI", "AAPL")
+            .api_key("KEY")
+            .rows(10)
+            .order(Order::asc)
+            .collapse(Frequency::quarterly)
+            .transform(Transform::rdiff_from)
+            .end_date(2016, 12, 31)
+            .start_date(2016, 2, 9)
+            .column_index(4)
+            .clone()
+    }, data_url);
+
+    round_trip!(DataQuery, DataQuery::new("WIKI", "AAPL").limit(5).clone(), data_url);
+
+    round_trip!(DataAndMetadataQuery, DataAndMetadataQuery::new("WIKI", "AAPL"),
+                data_and_metadata_url);
+
+    round_trip!(DataAndMetadataQuery, {
+        DataAndMetadataQuery::new("FRED", "GDP")
+            .base_url("http://localhost:8080/api/v3")
+            .api_key("KEY")
+            .collapse(Frequency::annual)
+            .transform(Transform::diff)
+            .clone()
+    }, data_and_metadata_url);
+}
+
+#[test]
+fn dataset_ids() {
+    round_trip!(DatasetMetadataQuery, DatasetMetadataQuery::by_id(9775409).api_key("KEY").clone());
+    round_trip!(DataQuery, DataQuery::by_id(9775409).rows(3).clone(), data_url);
+    round_trip!(DataAndMetadataQuery, DataAndMetadataQuery::by_id(9775409), data_and_metadata_url);
+
+    let query = DataQuery::try_from(url("https://www.quandl.com/api/v3/datasets/42/data.csv"));
+    assert_eq!(query.unwrap().id, Some(42));
+
+    let message = {
+        parsing_error(DataQuery::try_from(url("https://www.quandl.com/api/v3/datasets/AAPL/\
+                                                data.csv")))
+    };
+
+    assert_eq!(message, "cannot rebuild a DataQuery from the URL of path \
+                         '/api/v3/datasets/AAPL/data.csv': invalid dataset id 'AAPL'.");
+}
+
+fn parsing_error<T: ::std::fmt::Debug>(result: Result<T, Error>) -> String {
+    match result {
+        Err(Error::ParsingFailed(message)) => message,
+        other => panic!("{:?}", other),
+    }
+}
+
+fn url(url: &str) -> Url {
+    Url::parse(url).unwrap()
+}
+
+#[test]
+fn foreign_urls() {
+    let message = parsing_error(DataQuery::try_from(url("https://example.com/index.html")));
+    assert!(message.contains("not a URL of such a query"), "{}", message);
+
+    let message = parsing_error(DataQuery::try_from(url("ftp://www.quandl.com/api/v3/datasets/\
+                                                          WIKI/AAPL/data.csv")));
+    assert!(message.contains("unsupported scheme 'ftp'"), "{}", message);
+
+    assert!(DataQuery::try_from(url("mailto:someone@example.com")).is_err());
+
+    // Queries of another kind.
+    let metadata = url("https://www.quandl.com/api/v3/datasets/WIKI/AAPL/metadata.json");
+
+    assert!(DataQuery::try_from(metadata.clone()).is_err());
+    assert!(DataAndMetadataQuery::try_from(metadata.clone()).is_err());
+    assert!(DatasetMetadataQuery::try_from(metadata).is_ok());
+
+    let search = url("https://www.quandl.com/api/v3/databases.json");
+    assert!(DatabaseMetadataQuery::try_from(search.clone()).is_err());
+    assert!(DatabaseSearch::try_from(search).is_ok());
+}
+
+#[test]
+fn unknown_parameters() {
+    let with_unknown = url("https://www.quandl.com/api/v3/datasets/WIKI/AAPL/data.csv?\
+                            api_key=SECRET&rows=2&download_type=full");
+
+    let message = parsing_error(DataQuery::try_from(with_unknown.clone()));
+
+    assert_eq!(message, "cannot rebuild a DataQuery from the URL of path \
+                         '/api/v3/datasets/WIKI/AAPL/data.csv': unknown parameter \
+                         'download_type'.");
+
+    let query = DataQuery::from_url(&with_unknown, true).unwrap();
+
+    assert_eq!(data_url(&query),
+               "https://www.quandl.com/api/v3/datasets/WIKI/AAPL/data.csv?\
+                exclude_column_names=true&api_key=SECRET&rows=2");
+}
+
+#[test]
+fn invalid_parameters() {
+    let data = |query: &str| {
+        let url = url(&format!("https://www.quandl.com/api/v3/datasets/WIKI/AAPL.json?{}", query));
+        parsing_error(DataAndMetadataQuery::from_url(&url, true))
+    };
+
+    assert!(data("order=sideways").ends_with(": invalid order 'sideways'."));
+    assert!(data("rows=ten").ends_with(": invalid rows 'ten'."));
+    assert!(data("start_date=2016-02-30").ends_with(": invalid start_date '2016-02-30'."));
+
+    // Rebuilt as sent, to be rejected by validation.
+    let query = DataQuery::from_url(&url("https://www.quandl.com/api/v3/datasets/WIKI/AAPL/\
+                                          data.csv?column_index=0"), true).unwrap();
+
+    assert_eq!(query.validate().unwrap_err()[0].field, "column_index");
+
+    // Only data files are requested without column names.
+    let metadata_url = url("https://www.quandl.com/api/v3/datasets/WIKI/AAPL.json?\
+                            exclude_column_names=true");
+
+    assert!(DataAndMetadataQuery::try_from(metadata_url).is_err());
+
+    let csv_url = url("https://www.quandl.com/api/v3/datasets/WIKI/AAPL/data.csv?\
+                       exclude_column_names=false");
+
+    assert!(parsing_error(DataQuery::try_from(csv_url)).ends_with("exclude_column_names 'false'."));
+
+    let search = url("https://www.quandl.com/api/v3/datasets.json?api_key=SECRET&page=2");
+    let message = parsing_error(DatasetSearch::try_from(search));
+
+    assert!(message.ends_with(": missing database_code."), "{}", message);
+    assert!(!message.contains("SECRET"), "{}", message);
+
+    let download = url("https://www.quandl.com/api/v3/databases/WIKI/data?download_type=full");
+    assert!(DatabaseDownloadQuery::try_from(download).is_err());
+}
@@ -0,0 +1,114 @@
+#![cfg(all(feature = "batch", feature = "zip"))]
+
+extern crate quandl_v3;
+extern crate serde_json;
+
+mod common;
+
+#[path = "../examples/full_scrape.rs"]
+#[allow(dead_code)]
+mod full_scrape;
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use quandl_v3::Error;
+
+use common::{MockServer, Response};
+use full_scrape::{scrape, Scrape};
+
+static CODES: &[u8] = include_bytes!("fixtures/codes.zip");
+static DATASET_DATA: &str = include_str!("fixtures/dataset_data.json");
+
+fn directory(name: &str) -> PathBuf {
+    let name = format!("quandl-v3-full-scrape-{}-{}", name, std::process::id());
+    let path = std::env::temp_dir().join(name);
+    let _ = fs::remove_dir_all(&path);
+    path
+}
+
+/// Server of the WIKI codes (AAPL and MSFT) and of their datasets, MSFT's only once `up` is set.
+///
+fn server(up: Arc<AtomicBool>) -> MockServer {
+    MockServer::start(move |request| {
+        match &request.path[..] {
+            "/api/v3/databases/WIKI/codes" => Response::new(200).body(CODES),
+            "/api/v3/datasets/WIKI/AAPL.json" => Response::json(DATASET_DATA),
+
+            "/api/v3/datasets/WIKI/MSFT.json" if up.load(Ordering::SeqCst) => {
+                Response::json(DATASET_DATA.replace("\"AAPL\"", "\"MSFT\""))
+            },
+
+            _ => Response::not_found(),
+        }
+    })
+}
+
+fn lines(config: &Scrape) -> Vec<serde_json::Value> {
+    fs::read_to_string(config.data_path()).unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+#[test]
+fn interrupted_scrapes_resume() {
+    let up = Arc::new(AtomicBool::new(false));
+    let server = server(up.clone());
+
+    let mut config = Scrape::new("WIKI", vec!["KEY1".to_string(), "KEY2".to_string()], {
+        directory("resume")
+    });
+
+    config.base_url = Some(server.url());
+    config.limits = vec![(100, 1)];
+
+    let progress = Arc::new(Mutex::new(vec![]));
+    let events = progress.clone();
+
+    let summary = scrape(&config, move |p| events.lock().unwrap().push(p.clone())).unwrap();
+
+    assert_eq!((summary.datasets, summary.stored, summary.rows, summary.skipped), (2, 1, 3, 0));
+    assert_eq!(summary.failed.len(), 1);
+    assert_eq!(summary.failed[0].0.dataset_code, "MSFT");
+    assert!(matches!(summary.failed[0].1, Error::NotFound { .. }), "{:?}", summary.failed[0].1);
+    assert_eq!(summary.report.queries, 2);
+
+    let events = progress.lock().unwrap().clone();
+    let counts: Vec<_> = events.iter().map(|p| (p.completed, p.total, p.skipped)).collect();
+    assert_eq!(counts, vec![(1, 2, 0), (2, 2, 0)]);
+    assert_eq!(events[1].failed, 1);
+
+    let stored = lines(&config);
+    assert_eq!(stored.len(), 3);
+    assert_eq!(stored[0]["dataset_code"], "AAPL");
+    assert_eq!(stored[0]["row"], serde_json::json!(["2016-02-10", 95.92, 94.99]));
+
+    // Every call but the code list's went through the pool of keys.
+    let requests = server.requests();
+    assert_eq!(requests[0].query, "api_key=KEY1");
+    assert!(requests.iter().all(|r| r.query == "api_key=KEY1" || r.query == "api_key=KEY2"));
+
+    // The next run only fetches what the first one could not.
+    up.store(true, Ordering::SeqCst);
+    let summary = scrape(&config, |_| {}).unwrap();
+
+    assert_eq!((summary.stored, summary.rows, summary.skipped), (1, 3, 1));
+    assert!(summary.failed.is_empty());
+
+    let stored = lines(&config);
+    assert_eq!(stored.len(), 6);
+    assert!(stored[3..].iter().all(|line| line["dataset_code"] == "MSFT"));
+
+    let aapl = server.requests().iter().filter(|r| r.path.ends_with("AAPL.json")).count();
+    assert_eq!(aapl, 1);
+
+    // Once everything is stored, runs only fetch the code list.
+    let hits = server.hits();
+    let summary = scrape(&config, |_| {}).unwrap();
+    assert_eq!((summary.stored, summary.skipped), (0, 2));
+    assert_eq!(server.hits(), hits + 1);
+    assert_eq!(lines(&config).len(), 6);
+}
@@ -0,0 +1,238 @@
+extern crate chrono;
+extern crate quandl_v3;
+extern crate serde_json;
+
+mod common;
+
+use chrono::{Duration, Utc};
+
+use quandl_v3::{healthcheck, ApiCheck, DownloadErrorKind, Error, Health, TlsCheck};
+use quandl_v3::config::Config;
+use quandl_v3::prelude::*;
+
+use common::{MockServer, Response};
+
+static PINNED: &[u8] = include_bytes!("fixtures/tls/pinned.der");
+static PINNED_PEM: &[u8] = include_bytes!("fixtures/tls/pinned.pem");
+static PINNED_KEY: &[u8] = include_bytes!("fixtures/tls/pinned.key");
+static DATABASE_SEARCH: &str = include_str!("fixtures/database_search.json");
+
+/// `Date` header `offset` from now.
+///
+fn date(offset: Duration) -> String {
+    (Utc::now() + offset).format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
+fn check(server: &MockServer) -> Health {
+    let config = Config { base_url: Some(server.url()), .. Config::default() };
+    healthcheck(&config).unwrap()
+}
+
+/// Whether `skew` is `expected` milliseconds, give or take the second `Date` headers round to.
+///
+fn about(skew: Option<i64>, expected: i64) -> bool {
+    skew.map(|skew| (skew - expected).abs() <= 1_500).unwrap_or(false)
+}
+
+#[test]
+fn healthy() {
+    let server = MockServer::start(|_| {
+        Response::json(DATABASE_SEARCH).header("Date", &date(Duration::zero()))
+    });
+
+    let config = {
+        Config {
+            api_key: Some(ApiKey::new("SECRET")),
+            base_url: Some(server.url()),
+            .. Config::default()
+        }
+    };
+
+    let health = healthcheck(&config).unwrap();
+
+    assert!(health.is_healthy(), "{}", health);
+    assert_eq!(health.url, format!("{}/databases.json?per_page=1", server.url()));
+    assert_eq!(health.status, Some(200));
+    assert!(health.latency.is_some());
+    assert_eq!(health.tls, TlsCheck::NotUsed);
+    assert!(about(health.clock_skew_millis, 0), "{:?}", health.clock_skew_millis);
+    assert_eq!(health.api, ApiCheck::Expected);
+    assert_eq!(health.error, None);
+
+    // A single call, never authenticated.
+    let requests = server.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].path, "/api/v3/databases.json");
+    assert_eq!(requests[0].query, "per_page=1");
+}
+
+#[test]
+fn clock_skew() {
+    for &(offset, side) in &[(3_600, "(local clock behind)"), (-90, "(local clock ahead)")] {
+        let header = date(Duration::seconds(offset));
+        let server = {
+            MockServer::start(move |_| Response::json(DATABASE_SEARCH).header("Date", &header))
+        };
+        let health = check(&server);
+
+        assert!(about(health.clock_skew_millis, offset * 1_000), "{:?}", health.clock_skew_millis);
+        assert!(health.to_string().contains(side), "{}", health);
+
+        // Only the caller knows how much skew is too much.
+        assert!(health.is_healthy());
+    }
+}
+
+#[test]
+fn missing_or_malformed_date() {
+    for header in &[None, Some("yesterday")] {
+        let header = header.map(|x| x.to_string());
+
+        let server = MockServer::start(move |_| {
+            let response = Response::json(DATABASE_SEARCH);
+
+            match header {
+                Some(ref date) => response.header("Date", date),
+                None => response,
+            }
+        });
+
+        let health = check(&server);
+
+        assert_eq!(health.clock_skew_millis, None);
+        assert!(health.to_string().contains("\nclock skew: unknown\n"), "{}", health);
+    }
+}
+
+#[test]
+fn quandl_error() {
+    let server = MockServer::start(|_| {
+        Response::new(403)
+            .header("Content-Type", "application/json")
+            .body(r#"{"quandl_error":{"code":"QELx01","message":"Anonymous calls are refused."}}"#)
+    });
+
+    let health = check(&server);
+
+    assert!(!health.is_healthy());
+    assert_eq!(health.status, Some(403));
+    assert_eq!(health.api, ApiCheck::QuandlError {
+        code: "QELx01".to_string(),
+        message: "Anonymous calls are refused.".to_string(),
+    });
+
+    assert!(health.to_string().contains("\napi: responds with error QELx01: Anonymous calls"));
+}
+
+#[test]
+fn unexpected_responses() {
+    let server = MockServer::routes(vec![]);
+    let health = check(&server);
+
+    // Quandl's 404 has the format of its errors.
+    assert_eq!(health.status, Some(404));
+    assert!(matches!(health.api, ApiCheck::QuandlError { .. }));
+
+    let server = MockServer::start(|_| {
+        Response::new(200).header("Content-Type", "text/html").body("<html>Proxy login</html>")
+    });
+
+    match check(&server).api {
+        ApiCheck::Unexpected(ref what) => {
+            assert!(what.starts_with("not JSON ("), "{}", what);
+            assert!(what.ends_with("starting with \"<html>Proxy login</html>\""), "{}", what);
+        },
+
+        ref other => panic!("expected an unexpected response, got {:?}", other),
+    }
+
+    let server = MockServer::start(|_| Response::json(r#"{"datasets":[]}"#));
+    let health = check(&server);
+
+    assert_eq!(health.api, ApiCheck::Unexpected("JSON without a list of databases".to_string()));
+    assert!(!health.is_healthy());
+}
+
+#[test]
+fn tls() {
+    let identity = native_tls::Identity::from_pkcs8(PINNED_PEM, PINNED_KEY).unwrap();
+    let server = MockServer::start_tls(identity, |_| Response::json(DATABASE_SEARCH));
+
+    // The self-signed certificate is not among the system's roots.
+    let health = check(&server);
+
+    match health.tls {
+        TlsCheck::Invalid(ref reason) => assert!(!reason.is_empty()),
+        ref other => panic!("expected an invalid certificate, got {:?}", other),
+    }
+
+    assert_eq!(health.status, None);
+    assert_eq!(health.api, ApiCheck::NoResponse);
+    assert_eq!(health.error.as_ref().and_then(Error::download_kind), Some(DownloadErrorKind::Tls));
+    assert!(health.to_string().contains("\ntls: invalid ("), "{}", health);
+    assert!(!health.is_healthy());
+    assert_eq!(server.hits(), 0);
+
+    let client = {
+        let mut client = ClientConfig::new();
+        client.pin_certificates(vec![PINNED.to_vec()]);
+        client
+    };
+
+    let config = {
+        Config { base_url: Some(server.url()), client: Some(client), .. Config::default() }
+    };
+
+    let health = healthcheck(&config).unwrap();
+
+    assert_eq!(health.tls, TlsCheck::Valid);
+    assert!(health.is_healthy(), "{}", health);
+}
+
+#[test]
+fn unreachable() {
+    let address = {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.local_addr().unwrap()
+    };
+
+    let config = {
+        Config { base_url: Some(format!("http://{}/api/v3", address)), .. Config::default() }
+    };
+
+    let health = healthcheck(&config).unwrap();
+
+    assert_eq!(health.tls, TlsCheck::NotUsed);
+    assert_eq!(health.latency, None);
+    assert_eq!(health.error.as_ref().and_then(Error::download_kind),
+               Some(DownloadErrorKind::Connect));
+    assert!(health.to_string().contains("\nstatus: no response ("), "{}", health);
+
+    let config = Config { base_url: Some("not a URL".to_string()), .. Config::default() };
+
+    match healthcheck(&config) {
+        Err(Error::ParsingFailed(ref message)) => assert!(message.starts_with("invalid base URL")),
+        other => panic!("expected a parsing failure, got {:?}", other),
+    }
+}
+
+#[test]
+fn serialization() {
+    let server = MockServer::start(|_| {
+        Response::json(DATABASE_SEARCH).header("Date", "Tue, 01 Mar 2016 21:47:01 GMT")
+    });
+
+    let value = serde_json::to_value(check(&server)).unwrap();
+
+    assert_eq!(value["status"], 200);
+    assert_eq!(value["tls"], "not_used");
+    assert_eq!(value["api"], "expected");
+    assert!(value["latency"]["nanos"].is_number());
+    assert!(value["clock_skew_millis"].as_i64().unwrap() < 0);
+    assert_eq!(value["error"], serde_json::Value::Null);
+
+    let server = MockServer::start(|_| Response::json("{}"));
+    let value = serde_json::to_value(check(&server)).unwrap();
+
+    assert_eq!(value["api"]["unexpected"], "JSON without a list of databases");
+}
@@ -0,0 +1,219 @@
+extern crate quandl_v3;
+
+use quandl_v3::prelude::*;
+
+type Rows = Vec<(String, f64)>;
+
+/// The queries configured through their inherent methods, without any parameter trait in scope.
+///
+mod inherent {
+    use quandl_v3::prelude::{Order, Frequency, Transform, ClientConfig};
+    use quandl_v3::prelude::{DatabaseSearch, DatasetSearch, DatasetListingQuery};
+    use quandl_v3::prelude::{DatabaseMetadataQuery, DatasetMetadataQuery, CodeListQuery};
+    use quandl_v3::prelude::{DatabaseDownloadQuery, DataQuery, DataAndMetadataQuery};
+
+    pub fn database_search() -> DatabaseSearch {
+        let mut query = DatabaseSearch::new();
+        query.api_key("KEY").query(["oil"]).phrase("crude oil").exclude("gas").per_page(10)
+             .page(2);
+        query
+    }
+
+    pub fn dataset_search() -> DatasetSearch {
+        let mut query = DatasetSearch::new("WIKI");
+        query.query(["apple"]).per_page(5).api_version("v3").strict(true);
+        query
+    }
+
+    pub fn dataset_listing() -> DatasetListingQuery {
+        let mut query = DatasetListingQuery::new("WIKI");
+        query.query(["apple"]).auto_uppercase(true).max_response_bytes(1_000);
+        query
+    }
+
+    pub fn database_metadata() -> DatabaseMetadataQuery {
+        let mut query = DatabaseMetadataQuery::new("WIKI");
+        query.api_key("KEY").base_url("http://localhost:8080/api/v3");
+        query
+    }
+
+    pub fn dataset_metadata() -> DatasetMetadataQuery {
+        let mut query = DatasetMetadataQuery::new("wiki", "aapl");
+        query.auto_uppercase(true).accept_any_content_type();
+        query
+    }
+
+    pub fn code_list() -> CodeListQuery {
+        let mut query = CodeListQuery::new("WIKI");
+        query.api_key("KEY").client_config(&ClientConfig::new());
+        query
+    }
+
+    pub fn database_download() -> DatabaseDownloadQuery {
+        let mut query = DatabaseDownloadQuery::new("WIKI");
+        query.api_key("KEY").partial();
+        query
+    }
+
+    pub fn data() -> DataQuery {
+        let mut query = DataQuery::new("WIKI", "AAPL");
+        query.api_key("KEY").rows(10).limit(10).order(Order::asc).collapse(Frequency::monthly)
+             .transform(Transform::rdiff).start_date(2016, 1, 1).end_date(2016, 12, 31)
+             .column_index(4);
+        query
+    }
+
+    pub fn data_and_metadata() -> quandl_v3::Result<DataAndMetadataQuery> {
+        let mut query = DataAndMetadataQuery::new("WIKI", "AAPL");
+        query.start_date_str("2016-01-04")?.end_date_str("2016-02-29")?.order(Order::desc);
+        Ok(query)
+    }
+}
+
+/// The same queries configured through the parameter traits, as generic code does.
+///
+mod generic {
+    use quandl_v3::prelude::*;
+
+    fn api<Q: ApiParameters>(query: &mut Q) -> &mut Q {
+        query.api_key("KEY")
+    }
+
+    fn search<Q: ApiParameters + SearchParameters>(mut query: Q) -> Q {
+        api(&mut query).query(["oil"]).phrase("crude oil").exclude("gas").per_page(10).page(2);
+        query
+    }
+
+    fn data<Q: ApiParameters + DataParameters>(mut query: Q) -> Q {
+        api(&mut query).rows(10).limit(10).order(Order::asc).collapse(Frequency::monthly)
+                       .transform(Transform::rdiff).start_date(2016, 1, 1).end_date(2016, 12, 31)
+                       .column_index(4);
+        query
+    }
+
+    pub fn database_search() -> DatabaseSearch {
+        search(DatabaseSearch::new())
+    }
+
+    pub fn dataset_search() -> DatasetSearch {
+        let mut query = DatasetSearch::new("WIKI");
+        SearchParameters::query(&mut query, ["apple"]).per_page(5);
+        ApiParameters::api_version(&mut query, "v3").strict(true);
+        query
+    }
+
+    pub fn dataset_listing() -> DatasetListingQuery {
+        let mut query = DatasetListingQuery::new("WIKI");
+        SearchParameters::query(&mut query, ["apple"]);
+        ApiParameters::auto_uppercase(&mut query, true).max_response_bytes(1_000);
+        query
+    }
+
+    pub fn database_metadata() -> DatabaseMetadataQuery {
+        let mut query = DatabaseMetadataQuery::new("WIKI");
+        api(&mut query).base_url("http://localhost:8080/api/v3");
+        query
+    }
+
+    pub fn dataset_metadata() -> DatasetMetadataQuery {
+        let mut query = DatasetMetadataQuery::new("wiki", "aapl");
+        ApiParameters::auto_uppercase(&mut query, true).accept_any_content_type();
+        query
+    }
+
+    pub fn code_list() -> CodeListQuery {
+        let mut query = CodeListQuery::new("WIKI");
+        api(&mut query).client_config(&ClientConfig::new());
+        query
+    }
+
+    pub fn database_download() -> DatabaseDownloadQuery {
+        let mut query = DatabaseDownloadQuery::new("WIKI");
+        api(&mut query);
+        query.partial();
+        query
+    }
+
+    pub fn data_query() -> DataQuery {
+        data(DataQuery::new("WIKI", "AAPL"))
+    }
+
+    pub fn data_and_metadata() -> quandl_v3::Result<DataAndMetadataQuery> {
+        let mut query = DataAndMetadataQuery::new("WIKI", "AAPL");
+        DataParameters::start_date_str(&mut query, "2016-01-04")?.end_date_str("2016-02-29")?
+            .order(Order::desc);
+        Ok(query)
+    }
+}
+
+#[test]
+fn identical_queries() {
+    assert_eq!(inherent::database_search(), generic::database_search());
+    assert_eq!(inherent::dataset_search(), generic::dataset_search());
+    assert_eq!(inherent::dataset_listing(), generic::dataset_listing());
+    assert_eq!(inherent::database_metadata(), generic::database_metadata());
+    assert_eq!(inherent::dataset_metadata(), generic::dataset_metadata());
+    assert_eq!(inherent::code_list(), generic::code_list());
+    assert_eq!(inherent::database_download(), generic::database_download());
+    assert_eq!(inherent::data(), generic::data_query());
+    assert_eq!(inherent::data_and_metadata().unwrap(), generic::data_and_metadata().unwrap());
+}
+
+#[test]
+fn identical_urls() {
+    let pairs = vec![
+        (ApiCall::<DatabaseList>::url(&inherent::database_search()),
+         ApiCall::<DatabaseList>::url(&generic::database_search())),
+
+        (ApiCall::<DatasetList>::url(&inherent::dataset_search()),
+         ApiCall::<DatasetList>::url(&generic::dataset_search())),
+
+        (ApiCall::<Vec<DatasetMetadataLite>>::url(&inherent::dataset_listing()),
+         ApiCall::<Vec<DatasetMetadataLite>>::url(&generic::dataset_listing())),
+
+        (inherent::database_metadata().url(), generic::database_metadata().url()),
+        (inherent::dataset_metadata().url(), generic::dataset_metadata().url()),
+        (inherent::database_download().url(), generic::database_download().url()),
+
+        (ApiCall::<Rows>::url(&inherent::data()), ApiCall::<Rows>::url(&generic::data_query())),
+
+        (ApiCall::<Dataset<(String, f64)>>::url(&inherent::data_and_metadata().unwrap()),
+         ApiCall::<Dataset<(String, f64)>>::url(&generic::data_and_metadata().unwrap())),
+    ];
+
+    for (inherent, generic) in pairs {
+        assert_eq!(inherent, generic);
+    }
+
+    assert_eq!(ApiCall::<Rows>::url(&inherent::data()),
+               "https://www.quandl.com/api/v3/datasets/WIKI/AAPL/data.csv?\
+                exclude_column_names=true&api_key=KEY&rows=10&limit=10&order=asc&\
+                collapse=monthly&transform=rdiff&end_date=2016-12-31&start_date=2016-01-01&\
+                column_index=4");
+}
+
+#[cfg(feature = "zip")]
+#[test]
+fn identical_code_list_urls() {
+    assert_eq!(ApiCall::<Vec<Code>>::url(&inherent::code_list()),
+               ApiCall::<Vec<Code>>::url(&generic::code_list()));
+}
+
+/// The inherent methods are those of the traits: the query given to generic code is configured
+/// the same whichever way it was called.
+///
+#[test]
+fn inherent_methods_in_generic_code() {
+    fn rows<Q: DataParameters>(query: &mut Q) {
+        query.rows(3);
+    }
+
+    let mut through_trait = DataQuery::new("WIKI", "AAPL");
+    rows(&mut through_trait);
+
+    let mut inherent = DataQuery::new("WIKI", "AAPL");
+    inherent.rows(3);
+
+    assert_eq!(through_trait, inherent);
+    assert_eq!(DataParameters::fmt(&inherent), Some("rows=3".to_string()));
+}
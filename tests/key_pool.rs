@@ -0,0 +1,211 @@
+#![cfg(feature = "batch")]
+
+extern crate quandl_v3;
+
+mod common;
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use quandl_v3::prelude::*;
+use quandl_v3::prelude::QueryKind::{Heavy, Light};
+
+use common::{MockServer, Request, Response};
+
+static DATA: &str = include_str!("fixtures/data_3_columns.csv");
+static DATABASE_METADATA: &str = include_str!("fixtures/database_metadata.json");
+
+fn profile(key: &str, limits: &[(usize, u64)], weight: f64) -> KeyProfile {
+    KeyProfile { limits: limits.to_vec(), weight, .. KeyProfile::new(key) }
+}
+
+#[test]
+fn query_kinds() {
+    type Row = (String, f64);
+
+    assert_eq!(ApiCall::<Vec<Row>>::kind(&DataQuery::new("WIKI", "AAPL")), Heavy);
+    assert_eq!(ApiCall::<Dataset<Row>>::kind(&DataAndMetadataQuery::new("WIKI", "AAPL")), Heavy);
+    assert_eq!(DatasetMetadataQuery::new("WIKI", "AAPL").kind(), Light);
+    assert_eq!(DatabaseSearch::new().kind(), Light);
+    assert!(Heavy.cost() > Light.cost());
+}
+
+#[test]
+fn budgets() {
+    let day = Duration::from_secs(86_400);
+    let free = profile("FREE", &[(300, 10), (2_000, 600), (50_000, 86_400)], 1.0);
+
+    assert_eq!(free.budget(day), 50_000.0);
+    assert_eq!(free.budget(Duration::from_secs(600)), 50_000.0 * 600.0 / 86_400.0);
+    assert_eq!(profile("FREE", &free.limits, 2.0).budget(day), 100_000.0);
+    assert_eq!(KeyProfile::new(" KEY\n").budget(day), f64::INFINITY);
+    assert_eq!(KeyProfile::new(" KEY\n").key.expose_secret(), "KEY");
+}
+
+#[test]
+fn heavy_queries_go_first_to_the_biggest_budgets() {
+    let profiles = [
+        profile("PREMIUM", &[(60, 60)], 1.0),
+        profile("FREE_1", &[(20, 60)], 1.0),
+        profile("FREE_2", &[(20, 60)], 1.0),
+    ];
+
+    // The heavy queries fill the premium key down to the budget of the free ones, the light
+    // ones then alternate between the two keys with the most left.
+    let mut kinds = vec![Light; 10];
+    kinds.splice(0..0, vec![Heavy; 5]);
+
+    assert_eq!(assign_keys(&profiles, &kinds), vec![0, 0, 0, 0, 1, 2, 0, 2, 0, 2, 0, 2, 0, 2, 0]);
+
+    // Wherever they are in the batch.
+    kinds.rotate_left(5);
+    assert_eq!(assign_keys(&profiles, &kinds), vec![2, 0, 2, 0, 2, 0, 2, 0, 2, 0, 0, 0, 0, 0, 1]);
+}
+
+#[test]
+fn budgets_are_prorated_to_the_longest_window() {
+    let profiles = [
+        profile("DAILY", &[(100, 86_400)], 1.0),
+        profile("BURSTY", &[(10, 600)], 1.0),
+    ];
+
+    // 1,440 calls a day for the second key.
+    assert_eq!(assign_keys(&profiles, &[Heavy; 3]), vec![1, 1, 1]);
+}
+
+#[test]
+fn weights_scale_budgets() {
+    let profiles = [
+        profile("PREMIUM", &[(60, 60)], 1.0),
+        profile("FREE", &[(20, 60)], 3.0),
+    ];
+
+    assert_eq!(assign_keys(&profiles, &[Light; 4]), vec![0, 1, 1, 0]);
+
+    // Keys without limits are told apart by what they spent relative to their weight.
+    let profiles = [profile("A", &[], 1.0), profile("B", &[], 3.0)];
+    let assigned = assign_keys(&profiles, &[Light; 8]);
+
+    assert_eq!(assigned, vec![0, 1, 1, 1, 0, 1, 1, 1]);
+}
+
+#[test]
+fn assignments_are_deterministic() {
+    let profiles: Vec<KeyProfile> = {
+        (0..5).map(|i| profile(&format!("KEY_{}", i), &[(10 + i, 60)], 1.0)).collect()
+    };
+
+    let kinds: Vec<QueryKind> = (0..200).map(|i| if i % 3 == 0 { Heavy } else { Light }).collect();
+    let assigned = assign_keys(&profiles, &kinds);
+
+    for _ in 0..10 {
+        assert_eq!(assign_keys(&profiles, &kinds), assigned);
+    }
+
+    assert!(assign_keys(&profiles, &[]).is_empty());
+    assert!(assign_keys(&[], &[]).is_empty());
+    assert_eq!(assign_keys(&profiles[..1], &kinds), vec![0; 200]);
+}
+
+fn server() -> MockServer {
+    MockServer::start(|request| {
+        if request.path.ends_with("/data.csv") {
+            Response::csv(DATA)
+        } else {
+            Response::json(DATABASE_METADATA)
+        }
+    })
+}
+
+fn key(request: &Request) -> &str {
+    request.query.split('&')
+        .find(|x| x.starts_with("api_key="))
+        .map(|x| &x["api_key=".len()..])
+        .unwrap_or("")
+}
+
+/// Number of requests received by `server` for each key, and for each kind of path.
+///
+fn calls_by_key(server: &MockServer) -> BTreeMap<(String, bool), usize> {
+    let mut calls = BTreeMap::new();
+
+    for request in server.requests() {
+        let is_data = request.path.ends_with("/data.csv");
+        *calls.entry((key(&request).to_string(), is_data)).or_default() += 1;
+    }
+
+    calls
+}
+
+#[test]
+fn heavy_batches_spread_over_the_pool() {
+    let server = server();
+
+    let mut batch_query = BatchQuery::new();
+    batch_query.limit(20, 60).threads(3);
+
+    // The third key is subject to the batch's limits, i.e. the same as the second one.
+    batch_query
+        .key_profile(profile("PREMIUM", &[(60, 60)], 1.0))
+        .key_profile(profile("FREE_1", &[(20, 60)], 1.0))
+        .key_profile(profile("FREE_2", &[], 1.0));
+
+    for i in 0..6 {
+        let mut query = DataQuery::new("WIKI", format!("D{}", i));
+        query.base_url(server.url());
+        batch_query.query(query);
+    }
+
+    // Queries with a key of their own keep it.
+    let mut query = DataQuery::new("WIKI", "OWN");
+    query.base_url(server.url()).api_key("OWN");
+    batch_query.query(query);
+
+    let results: Vec<quandl_v3::Result<Vec<(String, f64, f64)>>> = batch_query.run().collect();
+    assert!(results.iter().all(|x| x.is_ok()), "{:?}", results);
+
+    assert_eq!(calls_by_key(&server), vec![
+        (("FREE_1".to_string(), true), 1),
+        (("FREE_2".to_string(), true), 1),
+        (("OWN".to_string(), true), 1),
+        (("PREMIUM".to_string(), true), 4),
+    ].into_iter().collect());
+}
+
+#[test]
+fn light_batches_spread_over_the_pool() {
+    let server = server();
+
+    // The free keys are weighted up to the budget of the premium one.
+    let mut batch_query = BatchQuery::new();
+
+    batch_query
+        .key_profile(profile("PREMIUM", &[(60, 60)], 1.0))
+        .key_profile(profile("FREE_1", &[(20, 60)], 3.0))
+        .key_profile(profile("FREE_2", &[(20, 60)], 3.0));
+
+    for code in &["A", "B", "C", "D", "E", "F"] {
+        let mut query = DatabaseMetadataQuery::new(*code);
+        query.base_url(server.url());
+        batch_query.query(query);
+    }
+
+    let (mut iterator, report) = batch_query.run_with_report();
+    assert!(iterator.all(|x| x.is_ok()));
+
+    assert_eq!(calls_by_key(&server), vec![
+        (("FREE_1".to_string(), false), 2),
+        (("FREE_2".to_string(), false), 2),
+        (("PREMIUM".to_string(), false), 2),
+    ].into_iter().collect());
+
+    // Nothing is skipped for lack of a key.
+    assert_eq!(report.get().queries, 6);
+}
+
+#[test]
+#[should_panic(expected = "weight: 0")]
+fn weights_must_be_positive() {
+    let mut batch_query: BatchQuery<DataQuery, Vec<(String, f64)>> = BatchQuery::new();
+    batch_query.key_profile(profile("KEY", &[], 0.0));
+}
@@ -1,8 +1,11 @@
+#![allow(clippy::needless_borrows_for_generic_args)]
+
 extern crate quandl_v3;
 
 use quandl_v3::Result;
 use quandl_v3::prelude::*;
 
+#[cfg(feature = "zip")]
 static SKIP_CODE_LIST_QUERY: bool = true; // Necessary to pass build on travis-cl
 static API_KEY: Option<&'static str> = Some("x3E2BsxsYR1V9iNuAw6m"); // quandl.tester@gmail.com
 
@@ -94,6 +97,7 @@ fn dataset_search() {
     assert!(list.is_ok());
 }
 
+#[cfg(feature = "zip")]
 #[test]
 fn code_list_query() {
     if !SKIP_CODE_LIST_QUERY {
@@ -144,6 +148,34 @@ fn data_query() {
     assert!(data.is_ok());
 }
 
+#[test]
+fn datatable_query() {
+    let query = {
+        let mut query = DatatableQuery::new("WIKI", "PRICES");
+
+        query.filter("ticker", ["AAPL", "MSFT"])
+             .filter_by("date", Comparison::gte, "2018-03-26")
+             .columns(["ticker", "date", "close"]);
+
+        if let Some(key) = API_KEY {
+            query.api_key(key);
+        }
+
+        query
+    };
+
+    let prices: Result<Datatable<(String, String, f64)>> = query.send();
+
+    println!("{}", ApiCall::<Datatable<(String, String, f64)>>::url(&query));
+    println!("{:?}", prices);
+
+    let prices = prices.unwrap();
+
+    assert_eq!(prices.column_names(), ["ticker", "date", "close"]);
+    assert!(prices.rows.iter().all(|(ticker, _, _)| ticker == "AAPL" || ticker == "MSFT"));
+}
+
+#[cfg(feature = "batch")]
 #[test]
 fn batch_querying() {
     let query_1 = {
@@ -0,0 +1,114 @@
+extern crate quandl_v3;
+
+use quandl_v3::ApiErrorResponse;
+use quandl_v3::prelude::*;
+
+#[cfg(feature = "zip")]
+use quandl_v3::Error;
+
+static DATABASE_METADATA: &[u8] = include_bytes!("fixtures/database_metadata.json");
+static DATASET_METADATA: &[u8] = include_bytes!("fixtures/dataset_metadata.json");
+
+#[cfg(feature = "zip")]
+static CODES: &[u8] = include_bytes!("fixtures/codes.zip");
+
+/// 1,200,010 bytes of codes, zipped into 3,103.
+///
+#[cfg(feature = "zip")]
+static BOMB: &[u8] = include_bytes!("fixtures/fuzz/bomb.zip");
+
+/// Same as `BOMB`, declaring its file 100 bytes long.
+///
+#[cfg(feature = "zip")]
+static BOMB_UNDERSTATED: &[u8] = include_bytes!("fixtures/fuzz/bomb_understated.zip");
+
+/// ZIP64 archives, without any file, whose end of central directory declares 2^64 - 1 files (which
+/// the zip crate panicked on), 2^40 files (which it aborted the process over, allocating for them)
+/// and a central directory at offset 2^64 - 1 (which overflowed).
+///
+#[cfg(feature = "zip")]
+static ZIP64: &[(&str, &[u8])] = &[
+    ("file count", include_bytes!("fixtures/fuzz/zip64_file_count.zip")),
+    ("file count allocation", include_bytes!("fixtures/fuzz/zip64_file_count_alloc.zip")),
+    ("directory offset", include_bytes!("fixtures/fuzz/zip64_directory_offset.zip")),
+];
+
+#[test]
+fn truncated_json() {
+    for payload in &[DATABASE_METADATA, DATASET_METADATA] {
+        for len in 0..payload.len() - 1 {
+            let truncated = &payload[..len];
+
+            assert!(DatabaseMetadataQuery::new("WIKI").decode(truncated).is_err());
+            assert!(DatasetMetadataQuery::new("WIKI", "AAPL").decode(truncated).is_err());
+            assert!(ApiErrorResponse::decode(truncated).is_err());
+        }
+    }
+
+    assert!(DatabaseMetadataQuery::new("WIKI").decode(DATABASE_METADATA).is_ok());
+    assert!(DatasetMetadataQuery::new("WIKI", "AAPL").decode(DATASET_METADATA).is_ok());
+}
+
+#[test]
+fn api_errors() {
+    let payload = br#"{"quandl_error": {"code": "QECx02", "message": "Bad code."}}"#;
+    let response = ApiErrorResponse::decode(payload).unwrap();
+
+    assert_eq!(response.quandl_error.code, "QECx02");
+    assert_eq!(response.quandl_error.message, "Bad code.");
+
+    for payload in &[&b""[..], b"null", b"{\"quandl_error\": 1}", b"\xff\xfe"] {
+        assert!(ApiErrorResponse::decode(payload).is_err(), "{:?}", payload);
+    }
+}
+
+#[cfg(feature = "zip")]
+#[test]
+fn zip64_archives() {
+    for &(name, archive) in ZIP64 {
+        assert_eq!(CodeListQuery::new("WIKI").decode(archive),
+                   Err(Error::ZipExtraction("ZIP64 archives are not supported.".to_string())),
+                   "{}", name);
+    }
+}
+
+#[cfg(feature = "zip")]
+#[test]
+fn unzip_ratio() {
+    let limit = 100 * BOMB.len() as u64;
+
+    assert_eq!(CodeListQuery::new("WIKI").decode(BOMB),
+               Err(Error::UnzippedTooLarge { limit, observed: 1_200_010 }));
+
+    // The bytes actually unzipped are counted as well.
+    assert_eq!(CodeListQuery::new("WIKI").decode(BOMB_UNDERSTATED),
+               Err(Error::UnzippedTooLarge { limit, observed: limit + 1 }));
+
+    let codes = CodeListQuery::new("WIKI").max_unzip_ratio(1_000).decode(BOMB).unwrap();
+    assert_eq!(codes.len(), 60_000);
+    assert_eq!((&codes[0].database_code[..], &codes[0].dataset_code[..]), ("WIKI", "AAPL"));
+}
+
+#[cfg(feature = "zip")]
+#[test]
+fn unzipped_size() {
+    let mut query = CodeListQuery::new("WIKI");
+    query.max_unzip_ratio(1_000).max_uncompressed_bytes(1_000_000);
+
+    assert_eq!(query.decode(BOMB),
+               Err(Error::UnzippedTooLarge { limit: 1_000_000, observed: 1_200_010 }));
+
+    assert_eq!(query.decode(BOMB_UNDERSTATED),
+               Err(Error::UnzippedTooLarge { limit: 1_000_000, observed: 1_000_001 }));
+
+    // The code list of the other tests, 93 bytes once unzipped.
+    assert!(CodeListQuery::new("WIKI").decode(CODES).is_ok());
+    assert!(CodeListQuery::new("WIKI").max_uncompressed_bytes(93).decode(CODES).is_ok());
+
+    assert_eq!(CodeListQuery::new("WIKI").max_uncompressed_bytes(92).decode(CODES),
+               Err(Error::UnzippedTooLarge { limit: 92, observed: 93 }));
+
+    let error = Error::UnzippedTooLarge { limit: 92, observed: 93 };
+    assert_eq!(error.to_string(), "the archive unzips into more than the 92 bytes allowed (got 93 \
+                                   bytes).");
+}
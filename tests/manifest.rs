@@ -0,0 +1,227 @@
+#![cfg(feature = "batch")]
+
+extern crate quandl_v3;
+extern crate serde_json;
+
+mod common;
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use quandl_v3::Error;
+use quandl_v3::prelude::*;
+
+use common::{MockServer, Response};
+
+static DATABASE_METADATA: &str = include_str!("fixtures/database_metadata.json");
+static CODES: [&str; 6] = ["WIKI", "FRED", "JODI", "EIA", "ICE", "CME"];
+
+/// Server answering metadata queries, except for every other code while `outage` is set.
+///
+fn server(outage: Arc<AtomicBool>) -> MockServer {
+    MockServer::start(move |request| {
+        let code = request.path.trim_start_matches("/api/v3/databases/").trim_end_matches(".json");
+        let index = CODES.iter().position(|x| *x == code).unwrap();
+
+        if outage.load(Ordering::SeqCst) && index % 2 == 1 {
+            Response::not_found()
+        } else {
+            Response::json(DATABASE_METADATA.replace("\"WIKI\"", &format!("\"{}\"", code)))
+        }
+    })
+}
+
+fn batch(server: &MockServer, manifest: &Path) -> BatchQuery<DatabaseMetadataQuery,
+                                                            DatabaseMetadata> {
+    let mut batch_query = BatchQuery::new();
+
+    for code in &CODES {
+        let mut query = DatabaseMetadataQuery::new(code);
+        query.base_url(server.url()).api_key("secret-key");
+        batch_query.query(query);
+    }
+
+    batch_query.threads(2).manifest(manifest);
+    batch_query
+}
+
+fn manifest_path(name: &str) -> PathBuf {
+    let name = format!("quandl-v3-{}-{}.jsonl", name, std::process::id());
+    let path = std::env::temp_dir().join(name);
+    let _ = fs::remove_file(&path);
+    path
+}
+
+fn requested_codes(server: &MockServer, from: usize) -> Vec<String> {
+    let mut codes: Vec<String> = {
+        server.requests()[from..].iter()
+            .map(|x| x.path.trim_start_matches("/api/v3/databases/").trim_end_matches(".json"))
+            .map(String::from)
+            .collect()
+    };
+
+    codes.sort();
+    codes
+}
+
+#[test]
+fn rerun_only_sends_what_is_missing() {
+    let outage = Arc::new(AtomicBool::new(true));
+    let server = server(outage.clone());
+    let path = manifest_path("rerun");
+
+    let results: Vec<_> = batch(&server, &path).run().collect();
+
+    for (index, result) in results.iter().enumerate() {
+        assert_eq!(result.is_ok(), index % 2 == 0, "{:?}", result);
+    }
+
+    assert_eq!(server.hits(), 6);
+
+    let content = fs::read_to_string(&path).unwrap();
+    assert_eq!(content.lines().count(), 3);
+    assert!(!content.contains("secret-key"), "{}", content);
+
+    for line in content.lines() {
+        let entry: serde_json::Value = serde_json::from_str(line).unwrap();
+        let completed_at = entry["completed_at"].as_str().unwrap();
+
+        assert!(completed_at.ends_with('Z'), "{}", completed_at);
+        assert!(quandl_v3::calendar::parse_timestamp_utc(completed_at).is_some(), "{}", line);
+    }
+
+    // The outage is over: only the failed half is sent again.
+    outage.store(false, Ordering::SeqCst);
+
+    let results: Vec<_> = batch(&server, &path).run().collect();
+    assert_eq!(results.len(), 6);
+
+    for (index, result) in results.iter().enumerate() {
+        match *result {
+            Err(Error::Skipped(ref url)) if index % 2 == 0 => {
+                assert_eq!(*url, format!("{}/databases/{}.json", server.url(), CODES[index]));
+            },
+
+            Ok(ref metadata) if index % 2 == 1 => assert_eq!(metadata.database_code, CODES[index]),
+            ref other => panic!("unexpected result for {}: {:?}", CODES[index], other),
+        }
+    }
+
+    assert_eq!(requested_codes(&server, 6), vec!["CME", "EIA", "FRED"]);
+
+    // Everything is done now.
+    let results: Vec<_> = batch(&server, &path).run().collect();
+    assert!(results.iter().all(|x| matches!(x, Err(Error::Skipped(_)))));
+    assert_eq!(server.hits(), 9);
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn torn_entries_are_ignored() {
+    let server = server(Arc::new(AtomicBool::new(false)));
+    let path = manifest_path("torn");
+
+    {
+        let mut file = fs::File::create(&path).unwrap();
+        writeln!(file, "{{\"url\": \"{}/databases/WIKI.json\"}}", server.url()).unwrap();
+        write!(file, "{{\"url\": \"{}/databases/FR", server.url()).unwrap();
+    }
+
+    let results: Vec<_> = batch(&server, &path).run().collect();
+
+    assert!(matches!(results[0], Err(Error::Skipped(_))));
+    assert!(results[1..].iter().all(|x| x.is_ok()));
+    assert_eq!(server.hits(), 5);
+
+    // New entries start on a line of their own, so only the torn one is lost.
+    let results: Vec<_> = batch(&server, &path).run().collect();
+    assert!(results.iter().all(|x| matches!(x, Err(Error::Skipped(_)))));
+    assert_eq!(server.hits(), 5);
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn unreadable_manifest() {
+    let server = server(Arc::new(AtomicBool::new(false)));
+    let path = std::env::temp_dir().join("quandl-v3-no-such-directory").join("manifest.jsonl");
+
+    let results: Vec<_> = batch(&server, &path).run().collect();
+
+    assert_eq!(results.len(), 6);
+    assert!(results.iter().all(|x| matches!(x, Err(Error::IoError(_)))));
+    assert_eq!(server.hits(), 0);
+}
+
+#[test]
+fn failed_transformations_are_not_recorded() {
+    let server = server(Arc::new(AtomicBool::new(false)));
+    let path = manifest_path("transformations");
+
+    let stored = Arc::new(AtomicBool::new(false));
+    let can_store = stored.clone();
+
+    let results: Vec<_> = {
+        batch(&server, &path)
+            .try_map_rows(move |code, metadata| {
+                if code.database_code == "FRED" && !can_store.load(Ordering::SeqCst) {
+                    return Err(Error::IoError("disk full".to_string()));
+                }
+
+                Ok(metadata.database_code)
+            })
+            .run()
+            .collect()
+    };
+
+    assert_eq!(results[1], Err(Error::IoError("disk full".to_string())));
+    assert!(results.iter().enumerate().all(|(index, x)| index == 1 || x.is_ok()));
+    assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 5);
+
+    // FRED is sent again, rather than skipped with its result lost.
+    stored.store(true, Ordering::SeqCst);
+
+    let results: Vec<_> = batch(&server, &path).try_map_rows(|_, x| Ok(x.database_code)).run()
+        .collect();
+
+    assert_eq!(results[1], Ok("FRED".to_string()));
+    assert_eq!(requested_codes(&server, 6), vec!["FRED"]);
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn progress_leaves_out_skipped_queries() {
+    let server = server(Arc::new(AtomicBool::new(true)));
+    let path = manifest_path("progress");
+
+    let _: Vec<_> = batch(&server, &path).run().collect();
+
+    let progress = Arc::new(Mutex::new(vec![]));
+    let events = progress.clone();
+
+    let mut batch_query = batch(&server, &path);
+    batch_query.on_progress(move |p| events.lock().unwrap().push(p.clone()));
+    let _: Vec<_> = batch_query.run().collect();
+
+    let mut events = progress.lock().unwrap().clone();
+    events.sort_by_key(|p| p.completed);
+
+    let counts: Vec<_> = events.iter().map(|p| (p.completed, p.total, p.skipped)).collect();
+    assert_eq!(counts, vec![(1, 3, 3), (2, 3, 3), (3, 3, 3)]);
+    assert_eq!(events[2].failed, 3);
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn display() {
+    let error = Error::Skipped("https://www.quandl.com/api/v3/databases/WIKI.json".to_string());
+
+    assert_eq!(error.to_string(), "skipped query 'https://www.quandl.com/api/v3/databases/\
+                                   WIKI.json', already completed according to the manifest.");
+}
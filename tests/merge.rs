@@ -0,0 +1,127 @@
+extern crate quandl_v3;
+
+use quandl_v3::merge::concat_ordered;
+use quandl_v3::prelude::*;
+
+/// Small xorshift generator, good enough to draw random chunk boundaries reproducibly.
+///
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+/// A series of `(day, value)` rows with increasing, unique days, in the given order.
+///
+fn series(rng: &mut Rng, len: usize, order: Order) -> Vec<(u32, u64)> {
+    let mut day = 0;
+
+    let mut rows: Vec<_> = {
+        (0..len).map(|_| {
+            day += 1 + rng.below(3) as u32;
+            (day, rng.next())
+        }).collect()
+    };
+
+    if order == Order::desc {
+        rows.reverse();
+    }
+
+    rows
+}
+
+/// Split `rows` at random boundaries, randomly repeating the boundary rows in both neighbouring
+/// chunks, inserting empty chunks and shuffling the chunks.
+///
+fn split(rng: &mut Rng, rows: &[(u32, u64)]) -> Vec<Vec<(u32, u64)>> {
+    let mut chunks = vec![];
+    let mut start = 0;
+
+    while start < rows.len() {
+        let end = (start + 1 + rng.below(10)).min(rows.len());
+        let overlap = if start > 0 { rng.below(3).min(start) } else { 0 };
+
+        chunks.push(rows[start - overlap..end].to_vec());
+
+        if rng.below(4) == 0 {
+            chunks.push(vec![]);
+        }
+
+        start = end;
+    }
+
+    for index in (1..chunks.len()).rev() {
+        let other = rng.below(index + 1);
+        chunks.swap(index, other);
+    }
+
+    chunks
+}
+
+#[test]
+fn random_chunks_match_single_query() {
+    let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+
+    for iteration in 0..2000 {
+        for &order in &[Order::asc, Order::desc] {
+            let rows = series(&mut rng, iteration % 50, order);
+            let chunks = split(&mut rng, &rows);
+
+            assert_eq!(concat_ordered(chunks.clone(), order, |row| row.0), rows,
+                       "order: {:?}, chunks: {:?}", order, chunks);
+        }
+    }
+}
+
+#[test]
+fn empty_input() {
+    let chunks: Vec<Vec<(u32, u64)>> = vec![];
+    assert_eq!(concat_ordered(chunks, Order::asc, |row| row.0), vec![]);
+
+    let chunks: Vec<Vec<(u32, u64)>> = vec![vec![], vec![]];
+    assert_eq!(concat_ordered(chunks, Order::desc, |row| row.0), vec![]);
+}
+
+#[test]
+fn boundary_rows_come_from_the_earliest_chunk() {
+    let chunks = vec![vec![(3, 'b'), (4, 'b')], vec![(1, 'a'), (2, 'a'), (3, 'a')]];
+
+    assert_eq!(concat_ordered(chunks, Order::asc, |row| row.0),
+               vec![(1, 'a'), (2, 'a'), (3, 'a'), (4, 'b')]);
+
+    let chunks = vec![vec![(2, 'b'), (1, 'b')], vec![(4, 'a'), (3, 'a'), (2, 'a')]];
+
+    assert_eq!(concat_ordered(chunks, Order::desc, |row| row.0),
+               vec![(4, 'a'), (3, 'a'), (2, 'a'), (1, 'b')]);
+}
+
+#[test]
+fn contained_chunks_are_dropped() {
+    let chunks = vec![vec![1, 2, 3, 4, 5], vec![2, 3], vec![5, 6]];
+    assert_eq!(concat_ordered(chunks, Order::asc, |x| *x), vec![1, 2, 3, 4, 5, 6]);
+}
+
+#[test]
+fn duplicates_within_a_chunk_are_kept() {
+    let chunks = vec![vec![(1, 'a'), (1, 'b'), (2, 'a')], vec![(2, 'x'), (3, 'a'), (3, 'b')]];
+
+    assert_eq!(concat_ordered(chunks, Order::asc, |row| row.0),
+               vec![(1, 'a'), (1, 'b'), (2, 'a'), (3, 'a'), (3, 'b')]);
+}
+
+#[test]
+fn string_dates_as_keys() {
+    let chunks = vec![vec!["2016-01-06", "2016-01-05"], vec!["2016-01-05", "2016-01-04"]];
+
+    assert_eq!(concat_ordered(chunks, Order::desc, |date| date.to_string()),
+               vec!["2016-01-06", "2016-01-05", "2016-01-04"]);
+}
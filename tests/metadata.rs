@@ -0,0 +1,296 @@
+extern crate quandl_v3;
+extern crate serde_json;
+
+mod common;
+
+use std::convert::TryFrom;
+use std::collections::BTreeMap;
+
+use quandl_v3::Error;
+use quandl_v3::prelude::*;
+use quandl_v3::calendar::{NaiveDate, NaiveDateTime};
+
+use common::{MockServer, Response};
+
+static DATASET_METADATA: &str = include_str!("fixtures/dataset_metadata.json");
+static DATABASE_METADATA: &str = include_str!("fixtures/database_metadata.json");
+static DATABASE_METADATA_REFRESHED: &str = {
+    include_str!("fixtures/database_metadata_refreshed.json")
+};
+static DATASET_SAMPLE: &str = include_str!("fixtures/dataset_sample.json");
+
+fn dataset_metadata() -> DatasetMetadata {
+    let tree: BTreeMap<String, DatasetMetadata> = serde_json::from_str(DATASET_METADATA).unwrap();
+    tree["dataset"].clone()
+}
+
+#[test]
+fn date_range_from_fixture() {
+    let range = dataset_metadata().date_range().unwrap();
+
+    assert_eq!(range, (NaiveDate::from_ymd_opt(1980, 12, 12).unwrap(),
+                       NaiveDate::from_ymd_opt(2016, 2, 29).unwrap()));
+}
+
+#[test]
+fn date_range_rejects_malformed_dates() {
+    for date in &["2016-2-29", "", "2016-02-30", "29/02/2016", "2016-02-29T00:00:00"] {
+        let mut metadata = dataset_metadata();
+        metadata.newest_available_date = date.to_string();
+
+        let message = {
+            format!("invalid newest_available_date '{}' in metadata of WIKI/AAPL.", date)
+        };
+
+        assert_eq!(metadata.date_range(), Err(Error::ParsingFailed(message)));
+    }
+
+    let mut metadata = dataset_metadata();
+    metadata.oldest_available_date = "null".to_string();
+
+    assert!(metadata.date_range().is_err());
+}
+
+#[test]
+fn data_query_from_metadata() {
+    let query = DataQuery::try_from(&dataset_metadata()).unwrap();
+
+    assert_eq!(query.database_code(), "WIKI");
+    assert_eq!(query.dataset_code(), "AAPL");
+
+    assert_eq!(ApiCall::<Vec<(String, f64)>>::url(&query),
+               "https://www.quandl.com/api/v3/datasets/WIKI/AAPL/data.csv?\
+                exclude_column_names=true&order=asc&end_date=2016-02-29&start_date=1980-12-12");
+}
+
+#[test]
+fn data_query_from_malformed_metadata() {
+    let mut metadata = dataset_metadata();
+    metadata.oldest_available_date = "1980-12".to_string();
+
+    assert!(DataQuery::try_from(&metadata).is_err());
+}
+
+fn timestamp(s: &str) -> NaiveDateTime {
+    NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.3f").unwrap()
+}
+
+fn database_metadata(json: &str) -> DatabaseMetadata {
+    let tree: BTreeMap<String, DatabaseMetadata> = serde_json::from_str(json).unwrap();
+    tree["database"].clone()
+}
+
+fn refresh_query(server: &MockServer) -> DatabaseMetadataQuery {
+    let mut query = DatabaseMetadataQuery::new("WIKI");
+    query.base_url(server.url()).api_key("key");
+    query
+}
+
+#[test]
+fn last_refresh_of_database() {
+    assert_eq!(database_metadata(DATABASE_METADATA).last_refresh(), Ok(None));
+
+    assert_eq!(database_metadata(DATABASE_METADATA_REFRESHED).last_refresh(),
+               Ok(Some(timestamp("2016-03-01 21:47:01.686"))));
+
+    let mut metadata = database_metadata(DATABASE_METADATA);
+    metadata.last_refreshed_at = Some("yesterday".to_string());
+
+    assert_eq!(metadata.last_refresh(), {
+        Err(Error::ParsingFailed("invalid last_refreshed_at 'yesterday' in metadata of WIKI."
+                                     .to_string()))
+    });
+}
+
+#[test]
+fn last_refresh_of_dataset() {
+    assert_eq!(dataset_metadata().last_refresh(), Ok(timestamp("2016-03-01 21:47:01.686")));
+
+    // Offsets are converted to UTC.
+    let mut metadata = dataset_metadata();
+    metadata.refreshed_at = "2016-03-01T23:47:01+02:00".to_string();
+    assert_eq!(metadata.last_refresh(), Ok(timestamp("2016-03-01 21:47:01.000")));
+}
+
+#[test]
+fn refreshed_at_utc_of_dataset() {
+    let utc = |s: &str| timestamp(s).and_utc();
+
+    assert_eq!(dataset_metadata().refreshed_at_utc(), Some(utc("2016-03-01 21:47:01.686")));
+
+    let mut metadata = dataset_metadata();
+
+    for (refreshed_at, expected) in &[("2016-03-01T21:47:01Z", "2016-03-01 21:47:01.000"),
+                                      ("2016-03-01T21:47:01.686", "2016-03-01 21:47:01.686"),
+                                      ("2016-03-01T23:47:01+02:00", "2016-03-01 21:47:01.000")]
+    {
+        metadata.refreshed_at = refreshed_at.to_string();
+        assert_eq!(metadata.refreshed_at_utc(), Some(utc(expected)), "{}", refreshed_at);
+    }
+
+    metadata.refreshed_at = "yesterday".to_string();
+    assert_eq!(metadata.refreshed_at_utc(), None);
+}
+
+#[test]
+fn refreshed_since_uses_database_timestamp() {
+    let server = MockServer::routes(vec![
+        ("/api/v3/databases/WIKI.json", Response::json(DATABASE_METADATA_REFRESHED)),
+    ]);
+
+    let query = refresh_query(&server);
+
+    assert_eq!(query.refreshed_since(timestamp("2016-03-01 00:00:00.000")), Ok(true));
+    assert_eq!(query.refreshed_since(timestamp("2016-03-01 21:47:01.686")), Ok(false));
+    assert_eq!(query.refreshed_since(timestamp("2016-03-02 00:00:00.000")), Ok(false));
+
+    // No dataset was sampled.
+    assert_eq!(server.hits(), 3);
+}
+
+#[test]
+fn refreshed_since_samples_datasets() {
+    let server = MockServer::routes(vec![
+        ("/api/v3/databases/WIKI.json", Response::json(DATABASE_METADATA)),
+        ("/api/v3/datasets.json", Response::json(DATASET_SAMPLE)),
+    ]);
+
+    let mut query = refresh_query(&server);
+    query.refresh_sample_size(3);
+
+    // The latest dataset of the sample (MSFT) was refreshed on 2016-03-04.
+    assert_eq!(query.refreshed_since(timestamp("2016-03-03 00:00:00.000")), Ok(true));
+    assert_eq!(query.refreshed_since(timestamp("2016-03-04 09:12:40.021")), Ok(false));
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 4);
+    assert_eq!(requests[1].path, "/api/v3/datasets.json");
+    assert_eq!(requests[1].query, "api_key=key&per_page=3&database_code=WIKI");
+}
+
+#[test]
+fn refreshed_since_empty_sample() {
+    let empty = {
+        let mut list: serde_json::Value = serde_json::from_str(DATASET_SAMPLE).unwrap();
+        list["datasets"] = serde_json::Value::Array(vec![]);
+        list.to_string()
+    };
+
+    let server = MockServer::routes(vec![
+        ("/api/v3/databases/WIKI.json", Response::json(DATABASE_METADATA)),
+        ("/api/v3/datasets.json", Response::json(empty)),
+    ]);
+
+    let query = refresh_query(&server);
+    assert_eq!(query.refreshed_since(timestamp("2000-01-01 00:00:00.000")), Ok(false));
+}
+
+static DATABASE_METADATA_STRING_COUNTS: &str = {
+    include_str!("fixtures/database_metadata_string_counts.json")
+};
+static DATABASE_METADATA_NULL_COUNTS: &str = {
+    include_str!("fixtures/database_metadata_null_counts.json")
+};
+static DATABASE_SEARCH_LENIENT: &str = include_str!("fixtures/database_search_lenient.json");
+static DATABASE_LIST: &str = include_str!("fixtures/database_list.json");
+static DATABASE_LIST_EMPTY_PAGE: &str = include_str!("fixtures/database_list_empty_page.json");
+
+#[test]
+fn counters_as_numbers() {
+    let metadata = database_metadata(DATABASE_METADATA);
+    assert_eq!((metadata.datasets_count, metadata.downloads), (3179, 138_448_389));
+}
+
+#[test]
+fn counters_as_strings() {
+    let metadata = database_metadata(DATABASE_METADATA_STRING_COUNTS);
+
+    assert_eq!(metadata, database_metadata(DATABASE_METADATA));
+    assert_eq!((metadata.datasets_count, metadata.downloads), (3179, 138_448_389));
+}
+
+#[test]
+fn counters_as_null() {
+    let metadata = database_metadata(DATABASE_METADATA_NULL_COUNTS);
+    assert_eq!((metadata.datasets_count, metadata.downloads), (0, 0));
+}
+
+#[test]
+fn counters_which_are_not_counts() {
+    for count in &["\"many\"", "-1", "1.5", "true"] {
+        let json = DATABASE_METADATA.replace("\"datasets_count\":3179",
+                                             &format!("\"datasets_count\":{}", count));
+
+        let result = serde_json::from_str::<BTreeMap<String, DatabaseMetadata>>(&json);
+        assert!(result.is_err(), "{}", count);
+    }
+}
+
+#[test]
+fn search_counters() {
+    let list: DatabaseList = serde_json::from_str(DATABASE_SEARCH_LENIENT).unwrap();
+
+    assert_eq!(list.meta.per_page, 1);
+    assert_eq!(list.meta.current_page, 1);
+    assert_eq!(list.meta.prev_page, None);
+    assert_eq!(list.meta.total_pages, 7);
+    assert_eq!(list.meta.total_count, 0);
+    assert_eq!(list.meta.next_page, Some(2));
+    assert_eq!(list.meta.current_first_item, Some(1));
+    assert_eq!(list.meta.current_last_item, None);
+}
+
+#[test]
+fn listings_without_a_query() {
+    let list: DatabaseList = serde_json::from_str(DATABASE_LIST).unwrap();
+
+    assert_eq!(list.meta.query, "");
+    assert_eq!((list.meta.current_page, list.meta.total_pages, list.meta.total_count), (1, 3, 5));
+    assert_eq!((list.meta.current_first_item, list.meta.current_last_item), (Some(1), Some(2)));
+
+    let codes: Vec<_> = list.databases.iter().map(|x| &x.database_code[..]).collect();
+    assert_eq!(codes, vec!["WIKI", "ORS"]);
+    assert_eq!(list.cursor().last_code, Some("ORS".to_string()));
+
+    let json = serde_json::to_value(&list).unwrap();
+    assert_eq!(json["meta"]["query"], "");
+    assert_eq!(serde_json::from_value::<DatabaseList>(json).unwrap(), list);
+}
+
+#[test]
+fn empty_last_page() {
+    let list: DatabaseList = serde_json::from_str(DATABASE_LIST_EMPTY_PAGE).unwrap();
+
+    assert!(list.databases.is_empty());
+    assert_eq!(list.meta.query, "");
+    assert_eq!((list.meta.prev_page, list.meta.next_page), (Some(3), None));
+    assert_eq!((list.meta.current_first_item, list.meta.current_last_item), (None, None));
+    assert_eq!(list.cursor().last_code, None);
+}
+
+#[test]
+fn lenient_counters_serialize_as_numbers() {
+    let metadata = database_metadata(DATABASE_METADATA_STRING_COUNTS);
+    let json = serde_json::to_value(&metadata).unwrap();
+
+    assert_eq!(json["datasets_count"], serde_json::json!(3179));
+    assert_eq!(json["downloads"], serde_json::json!(138_448_389));
+    assert_eq!(serde_json::from_value::<DatabaseMetadata>(json).unwrap(), metadata);
+
+    let list: DatabaseList = serde_json::from_str(DATABASE_SEARCH_LENIENT).unwrap();
+    let json = serde_json::to_value(&list).unwrap();
+
+    assert_eq!(json["meta"]["per_page"], serde_json::json!(1));
+    assert_eq!(json["meta"]["total_pages"], serde_json::json!(7));
+    assert_eq!(json["meta"]["next_page"], serde_json::json!(2));
+    assert_eq!(serde_json::from_value::<DatabaseList>(json).unwrap(), list);
+}
+
+#[test]
+fn metadata_query_with_string_counters() {
+    let server = MockServer::routes(vec![
+        ("/api/v3/databases/WIKI.json", Response::json(DATABASE_METADATA_STRING_COUNTS)),
+    ]);
+
+    assert_eq!(refresh_query(&server).send().map(|x| x.datasets_count), Ok(3179));
+}
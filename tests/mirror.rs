@@ -0,0 +1,277 @@
+#![cfg(all(feature = "batch", feature = "zip"))]
+
+extern crate quandl_v3;
+extern crate serde_json;
+
+mod common;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use quandl_v3::Error;
+use quandl_v3::mirror::MirrorJob;
+use quandl_v3::prelude::*;
+
+use common::{MockServer, Response};
+
+static CODES: &[u8] = include_bytes!("fixtures/codes_mirror.zip");
+
+fn metadata(dataset_code: &str, refreshed_at: &str) -> String {
+    serde_json::json!({
+        "dataset": {
+            "id": 1,
+            "dataset_code": dataset_code,
+            "database_code": "FAKE",
+            "name": format!("{} dataset", dataset_code),
+            "description": "",
+            "refreshed_at": refreshed_at,
+            "newest_available_date": "2016-03-02",
+            "oldest_available_date": "2016-03-01",
+            "column_names": ["Date", "Value"],
+            "frequency": "daily",
+            "type": "Time Series",
+            "premium": false,
+            "database_id": 1,
+        }
+    }).to_string()
+}
+
+/// Fake database of three datasets, `TWO` being refreshed (with a new row) once `updated` is set
+/// and the rows of `THREE` missing while `broken` is.
+///
+fn server(updated: Arc<AtomicBool>, broken: Arc<AtomicBool>) -> MockServer {
+    MockServer::start(move |request| {
+        let updated = updated.load(Ordering::SeqCst);
+        let broken = broken.load(Ordering::SeqCst);
+
+        match &request.path[..] {
+            "/api/v3/databases/FAKE/codes" => Response::new(200).body(CODES),
+
+            "/api/v3/datasets/FAKE/ONE/metadata.json" => {
+                Response::json(metadata("ONE", "2016-03-02T21:47:01.686Z"))
+            },
+
+            "/api/v3/datasets/FAKE/TWO/metadata.json" if updated => {
+                Response::json(metadata("TWO", "2016-03-03T21:47:01.686Z"))
+            },
+
+            "/api/v3/datasets/FAKE/TWO/metadata.json" => {
+                Response::json(metadata("TWO", "2016-03-02T21:47:01.686Z"))
+            },
+
+            "/api/v3/datasets/FAKE/THREE/metadata.json" => {
+                Response::json(metadata("THREE", "2016-03-02T21:47:01.686Z"))
+            },
+
+            "/api/v3/datasets/FAKE/ONE/data.csv" => {
+                Response::csv("Date,Value\n2016-03-02,2.0\n2016-03-01,1.0\n")
+            },
+
+            "/api/v3/datasets/FAKE/TWO/data.csv" if updated => {
+                Response::csv("Date,Value\n2016-03-03,30.5\n2016-03-02,20.5\n2016-03-01,10.5\n")
+            },
+
+            "/api/v3/datasets/FAKE/TWO/data.csv" => {
+                Response::csv("Date,Value\n2016-03-02,20.5\n2016-03-01,10.5\n")
+            },
+
+            "/api/v3/datasets/FAKE/THREE/data.csv" if broken => Response::not_found(),
+
+            "/api/v3/datasets/FAKE/THREE/data.csv" => {
+                Response::csv("Date,Value\n2016-03-02,\"1,000\"\n2016-03-01,\n")
+            },
+
+            _ => Response::not_found(),
+        }
+    })
+}
+
+fn target_dir(name: &str) -> PathBuf {
+    let name = format!("quandl-v3-mirror-{}-{}", name, std::process::id());
+    let path = std::env::temp_dir().join(name);
+    let _ = fs::remove_dir_all(&path);
+    path
+}
+
+fn job(server: &MockServer, target_dir: &Path) -> MirrorJob {
+    let mut job = MirrorJob::new("FAKE");
+    job.api_key("KEY").base_url(server.url()).target_dir(target_dir).threads(2);
+    job
+}
+
+fn read(path: PathBuf) -> String {
+    fs::read_to_string(path).unwrap()
+}
+
+fn data_requests(server: &MockServer) -> Vec<String> {
+    let mut paths: Vec<String> = {
+        server.requests().into_iter()
+            .map(|request| request.path)
+            .filter(|path| path.ends_with("/data.csv"))
+            .collect()
+    };
+
+    paths.sort();
+    paths
+}
+
+#[test]
+fn mirror_and_refresh() {
+    let updated = Arc::new(AtomicBool::new(false));
+    let server = server(updated.clone(), Arc::new(AtomicBool::new(false)));
+    let target_dir = target_dir("refresh");
+    let root = target_dir.join("FAKE");
+
+    let report = job(&server, &target_dir).run().unwrap();
+
+    assert_eq!(report.datasets, 3);
+    assert_eq!(report.downloaded, vec!["ONE", "TWO", "THREE"]);
+    assert!(report.unchanged.is_empty());
+    assert!(report.failed.is_empty(), "{:?}", report.failed);
+    assert_eq!(report.rows, 6);
+    assert_eq!(report.calls(), 7);
+    assert_eq!(server.hits(), 7);
+
+    assert_eq!(read(root.join("ONE/data.csv")), "Date,Value\n2016-03-02,2.0\n2016-03-01,1.0\n");
+    assert_eq!(read(root.join("THREE/data.csv")),
+               "Date,Value\n2016-03-02,\"1,000\"\n2016-03-01,\n");
+
+    let metadata: DatasetMetadata = serde_json::from_str(&read(root.join("TWO/metadata.json")))
+        .unwrap();
+    assert_eq!(metadata.dataset_code, "TWO");
+    assert_eq!(metadata.refreshed_at, "2016-03-02T21:47:01.686Z");
+
+    let manifest: serde_json::Value = serde_json::from_str(&read(root.join("manifest.json")))
+        .unwrap();
+    assert_eq!(manifest["TWO"]["refreshed_at"], "2016-03-02T21:47:01.686Z");
+    assert_eq!(manifest["TWO"]["rows"], 2);
+
+    // Only the dataset refreshed in between is downloaded again.
+    updated.store(true, Ordering::SeqCst);
+    let report = job(&server, &target_dir).run().unwrap();
+
+    assert_eq!(report.downloaded, vec!["TWO"]);
+    assert_eq!(report.unchanged, vec!["ONE", "THREE"]);
+    assert_eq!(report.rows, 3);
+    assert_eq!(report.calls(), 5);
+    assert_eq!(server.hits(), 12);
+
+    assert_eq!(data_requests(&server), vec![
+        "/api/v3/datasets/FAKE/ONE/data.csv",
+        "/api/v3/datasets/FAKE/THREE/data.csv",
+        "/api/v3/datasets/FAKE/TWO/data.csv",
+        "/api/v3/datasets/FAKE/TWO/data.csv",
+    ]);
+
+    assert_eq!(read(root.join("TWO/data.csv")),
+               "Date,Value\n2016-03-03,30.5\n2016-03-02,20.5\n2016-03-01,10.5\n");
+
+    let manifest: serde_json::Value = serde_json::from_str(&read(root.join("manifest.json")))
+        .unwrap();
+    assert_eq!(manifest["TWO"]["refreshed_at"], "2016-03-03T21:47:01.686Z");
+    assert_eq!(manifest["ONE"]["rows"], 2);
+
+    // Nothing changed since.
+    let report = job(&server, &target_dir).run().unwrap();
+
+    assert!(report.downloaded.is_empty());
+    assert_eq!(report.unchanged.len(), 3);
+    assert_eq!(report.to_string(), "\
+datasets: 3 (0 downloaded, 3 unchanged, 0 failed)
+rows: 0
+calls: 4
+");
+
+    assert!(fs::read_dir(&root).unwrap().all(|entry| {
+        !entry.unwrap().file_name().to_string_lossy().ends_with(".tmp")
+    }));
+
+    fs::remove_dir_all(&target_dir).unwrap();
+}
+
+#[test]
+fn missing_files_are_downloaded_again() {
+    let server = server(Arc::new(AtomicBool::new(false)), Arc::new(AtomicBool::new(false)));
+    let target_dir = target_dir("missing");
+
+    job(&server, &target_dir).run().unwrap();
+    fs::remove_file(target_dir.join("FAKE/ONE/data.csv")).unwrap();
+
+    let report = job(&server, &target_dir).run().unwrap();
+
+    assert_eq!(report.downloaded, vec!["ONE"]);
+    assert!(target_dir.join("FAKE/ONE/data.csv").is_file());
+
+    fs::remove_dir_all(&target_dir).unwrap();
+}
+
+#[test]
+fn failed_datasets_are_tried_again() {
+    let broken = Arc::new(AtomicBool::new(true));
+    let server = server(Arc::new(AtomicBool::new(false)), broken.clone());
+    let target_dir = target_dir("failed");
+
+    let report = job(&server, &target_dir).run().unwrap();
+
+    assert_eq!(report.downloaded, vec!["ONE", "TWO"]);
+    assert_eq!(report.failed.keys().collect::<Vec<_>>(), vec!["THREE"]);
+
+    match report.failed["THREE"] {
+        Error::NotFound { api_error: Some(ref response), .. } => {
+            assert_eq!(response.quandl_error.code, "QECx02")
+        },
+        ref other => panic!("expected an API error, got {:?}", other),
+    }
+
+    assert!(report.to_string().starts_with("\
+datasets: 3 (2 downloaded, 0 unchanged, 1 failed)
+rows: 4
+calls: 7
+failed: THREE: "), "{}", report);
+
+    assert!(!target_dir.join("FAKE/THREE").exists());
+
+    broken.store(false, Ordering::SeqCst);
+    let report = job(&server, &target_dir).run().unwrap();
+
+    assert_eq!(report.downloaded, vec!["THREE"]);
+    assert_eq!(report.unchanged, vec!["ONE", "TWO"]);
+    assert!(report.failed.is_empty());
+
+    fs::remove_dir_all(&target_dir).unwrap();
+}
+
+#[test]
+fn api_key_is_required() {
+    let target_dir = target_dir("keyless");
+
+    match MirrorJob::new("FAKE").target_dir(&target_dir).run() {
+        Err(Error::ValidationFailed(ref errors)) => assert_eq!(errors[0].field, "api_key"),
+        other => panic!("expected a validation error, got {:?}", other),
+    }
+
+    assert!(!target_dir.exists());
+}
+
+#[test]
+fn anonymous() {
+    let server = server(Arc::new(AtomicBool::new(false)), Arc::new(AtomicBool::new(false)));
+    let target_dir = target_dir("anonymous");
+
+    let report = {
+        MirrorJob::new("FAKE")
+            .base_url(server.url())
+            .target_dir(&target_dir)
+            .anonymous()
+            .run()
+            .unwrap()
+    };
+
+    assert_eq!(report.downloaded.len(), 3);
+    assert_eq!(report.metadata.keys["anonymous"], 3);
+    assert!(server.requests().iter().all(|request| !request.query.contains("api_key")));
+
+    fs::remove_dir_all(&target_dir).unwrap();
+}
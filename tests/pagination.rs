@@ -0,0 +1,226 @@
+extern crate quandl_v3;
+extern crate serde_json;
+
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use quandl_v3::{Warning, WithWarnings};
+use quandl_v3::prelude::*;
+
+use common::{MockServer, Request, Response};
+
+static DATASET_SEARCH: &str = include_str!("fixtures/dataset_search.json");
+static LISTING: &str = include_str!("fixtures/dataset_listing_1.csv");
+
+type Codes = Arc<Mutex<Vec<&'static str>>>;
+
+fn parameter(request: &Request, name: &str) -> Option<usize> {
+    request.query.split('&')
+        .find(|x| x.starts_with(&format!("{}=", name)))
+        .map(|x| x[name.len() + 1..].parse().unwrap())
+}
+
+/// Page of a search for `codes`, as Quandl would serve it.
+///
+fn search_page(codes: &[&str], page: usize, per_page: usize) -> String {
+    let mut json: serde_json::Value = serde_json::from_str(DATASET_SEARCH).unwrap();
+    let template = json["datasets"][0].clone();
+
+    let datasets: Vec<serde_json::Value> = {
+        codes.iter().skip((page - 1) * per_page).take(per_page).map(|code| {
+            let mut dataset = template.clone();
+            dataset["dataset_code"] = serde_json::json!(code);
+            dataset
+        }).collect()
+    };
+
+    json["datasets"] = serde_json::json!(datasets);
+    json["meta"]["per_page"] = serde_json::json!(per_page);
+    json["meta"]["current_page"] = serde_json::json!(page);
+    json["meta"]["total_count"] = serde_json::json!(codes.len());
+    json.to_string()
+}
+
+/// Page of the CSV listing of `codes`.
+///
+fn listing_page(codes: &[&str], page: usize, per_page: usize) -> String {
+    let header = LISTING.lines().next().unwrap();
+
+    let rows: String = {
+        codes.iter().skip((page - 1) * per_page).take(per_page).map(|code| {
+            format!("{},{},,2016-03-01T21:47:01.686Z,,,WIKI\n", code, code)
+        }).collect()
+    };
+
+    format!("{}\n{}", header, rows)
+}
+
+/// Server listing the datasets `codes`, which can be changed in-between requests.
+///
+fn server(codes: &[&'static str]) -> (MockServer, Codes) {
+    let codes = Arc::new(Mutex::new(codes.to_vec()));
+
+    let server = {
+        let codes = codes.clone();
+
+        MockServer::start(move |request| {
+            let codes = codes.lock().unwrap();
+            let page = parameter(request, "page").unwrap_or(1);
+            let per_page = parameter(request, "per_page").unwrap_or(100);
+
+            match &request.path[..] {
+                "/api/v3/datasets.json" => Response::json(search_page(&codes, page, per_page)),
+                "/api/v3/datasets.csv" => Response::csv(listing_page(&codes, page, per_page)),
+                _ => Response::not_found(),
+            }
+        })
+    };
+
+    (server, codes)
+}
+
+fn search(server: &MockServer) -> DatasetSearch {
+    let mut query = DatasetSearch::new("WIKI");
+    query.base_url(server.url()).per_page(2);
+    query
+}
+
+fn search_codes(list: &DatasetList) -> Vec<&str> {
+    list.datasets.iter().map(|x| &x.dataset_code[..]).collect()
+}
+
+#[test]
+fn cursors_of_search_results() {
+    let (server, _) = server(&["A", "B", "C", "D", "E"]);
+    let cursor = search(&server).send().unwrap().cursor();
+
+    assert_eq!(cursor, PageCursor {
+        page: 1,
+        per_page: 2,
+        total_count: Some(5),
+        last_code: Some("B".to_string()),
+    });
+
+    let json = serde_json::to_string(&cursor).unwrap();
+    assert_eq!(serde_json::from_str::<PageCursor>(&json).unwrap(), cursor);
+
+    // Resumed with the paging of the cursor.
+    let mut query = search(&server);
+    query.per_page(50).page(7);
+
+    let resumed = query.resume(&cursor).unwrap();
+
+    assert!(resumed.is_clean());
+    assert_eq!(search_codes(&resumed), vec!["C", "D"]);
+    assert_eq!(server.requests()[1].query, "per_page=2&page=2&database_code=WIKI");
+}
+
+#[test]
+fn search_results_shifting_forward() {
+    let (server, codes) = server(&["A", "B", "C", "D", "E"]);
+    let cursor = search(&server).send().unwrap().cursor();
+
+    // B moves to the second page.
+    codes.lock().unwrap().insert(0, "0");
+
+    let WithWarnings { value, warnings } = search(&server).resume(&cursor).unwrap();
+
+    assert_eq!(search_codes(&value), vec!["C"]);
+    assert_eq!(warnings, vec![Warning::PageOverlap { page: 2, repeated: 1 }]);
+    assert_eq!(value.cursor().last_code.as_deref(), Some("C"));
+}
+
+#[test]
+fn search_results_shifting_backward() {
+    let (server, codes) = server(&["A", "B", "C", "D", "E"]);
+    let cursor = search(&server).send().unwrap().cursor();
+
+    // C moves to the first page, and would be skipped.
+    codes.lock().unwrap().remove(0);
+
+    let WithWarnings { value, warnings } = search(&server).resume(&cursor).unwrap();
+
+    assert_eq!(search_codes(&value), vec!["D", "E"]);
+    assert_eq!(warnings, vec![Warning::PageSkip { page: 2, missing: 1 }]);
+    assert_eq!(warnings[0].to_string(), "1 results may have been skipped before page 2.");
+}
+
+fn listing(server: &MockServer) -> DatasetListingQuery {
+    let mut query = DatasetListingQuery::new("WIKI");
+    query.base_url(server.url()).per_page(2);
+    query
+}
+
+#[test]
+fn listing_cursors() {
+    let (server, _) = server(&["A", "B", "C", "D", "E"]);
+    let query = listing(&server);
+    let mut iterator = query.iter();
+
+    assert_eq!(iterator.cursor(), None);
+    assert_eq!(iterator.next().unwrap().unwrap().code, "A");
+    assert_eq!(iterator.cursor(), None);
+
+    // Once the last dataset of the page is yielded.
+    assert_eq!(iterator.next().unwrap().unwrap().code, "B");
+
+    let cursor = iterator.cursor().unwrap().clone();
+
+    assert_eq!(cursor, PageCursor {
+        page: 1,
+        per_page: 2,
+        total_count: None,
+        last_code: Some("B".to_string()),
+    });
+
+    let json = serde_json::to_string(&cursor).unwrap();
+    let cursor: PageCursor = serde_json::from_str(&json).unwrap();
+    let codes: Vec<String> = query.resume(&cursor).map(|x| x.unwrap().code).collect();
+
+    // The first page is not fetched again.
+    assert_eq!(codes, vec!["C", "D", "E"]);
+    assert_eq!(server.hits(), 3);
+}
+
+#[test]
+fn listing_shifting_between_pages() {
+    let (server, codes) = server(&["A", "B", "C", "D", "E", "F"]);
+    let mut iterator = listing(&server).iter();
+
+    let mut seen = vec![];
+
+    for dataset in &mut iterator {
+        let code = dataset.unwrap().code;
+
+        // D moves to the third page while the second one is gone through.
+        if code == "C" {
+            codes.lock().unwrap().insert(0, "0");
+        }
+
+        seen.push(code);
+    }
+
+    assert_eq!(seen, vec!["A", "B", "C", "D", "E", "F"]);
+    assert_eq!(iterator.warnings(), &[Warning::PageOverlap { page: 3, repeated: 1 }][..]);
+}
+
+#[test]
+fn listing_resumed_after_a_shift() {
+    let (server, codes) = server(&["A", "B", "C", "D", "E"]);
+    let query = listing(&server);
+
+    let cursor = {
+        let mut iterator = query.iter();
+        iterator.by_ref().take(2).for_each(drop);
+        iterator.cursor().unwrap().clone()
+    };
+
+    codes.lock().unwrap().splice(0..0, vec!["0", "1"]);
+
+    let mut iterator = query.resume(&cursor);
+    let resumed: Vec<String> = iterator.by_ref().map(|x| x.unwrap().code).collect();
+
+    assert_eq!(resumed, vec!["C", "D", "E"]);
+    assert_eq!(iterator.warnings(), &[Warning::PageOverlap { page: 2, repeated: 2 }][..]);
+}
@@ -0,0 +1,91 @@
+#![cfg(feature = "rayon")]
+
+extern crate rayon;
+extern crate quandl_v3;
+
+use quandl_v3::Error;
+use quandl_v3::prelude::*;
+
+fn decode_with<T>(threads: usize, query: &DataQuery, csv: &[u8]) -> quandl_v3::Result<Vec<T>>
+    where T: serde::de::DeserializeOwned + Send
+{
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+    pool.install(|| query.decode_parallel(csv))
+}
+
+fn assert_same_as_serial(csv: &[u8]) {
+    let query = DataQuery::new("WIKI", "AAPL");
+    let serial: Vec<(String, String, f64)> = query.decode(csv).unwrap();
+
+    for threads in 1..12 {
+        let parallel: Vec<(String, String, f64)> = decode_with(threads, &query, csv).unwrap();
+        assert_eq!(serial, parallel, "threads: {}", threads);
+    }
+}
+
+#[test]
+fn plain_records() {
+    let mut csv = String::new();
+
+    for day in 1..29 {
+        csv.push_str(&format!("2016-02-{:02},note {},{}.5\n", day, day, day));
+    }
+
+    assert_same_as_serial(csv.as_bytes());
+}
+
+#[test]
+fn quoted_newlines() {
+    let csv = b"2016-02-01,\"first\nline\",1.0\n\
+                2016-02-02,\"he said \"\"hi\"\"\n,\nbye\",2.0\n\
+                2016-02-03,plain,3.0\n\
+                2016-02-04,\"\n\n\n\",4.0\n\
+                2016-02-05,\"a,b\",5.0\n";
+
+    assert_same_as_serial(csv);
+}
+
+#[test]
+fn crlf_terminators() {
+    let csv = b"2016-02-01,one,1.0\r\n2016-02-02,\"two\r\nlines\",2.0\r\n2016-02-03,three,3.0\r\n";
+
+    assert_same_as_serial(csv);
+}
+
+#[test]
+fn final_record_without_newline() {
+    assert_same_as_serial(b"2016-02-01,one,1.0\n2016-02-02,two,2.0\n2016-02-03,three,3.0");
+    assert_same_as_serial(b"2016-02-01,\"one\nquoted\",1.0");
+}
+
+#[test]
+fn empty_payload() {
+    assert_same_as_serial(b"");
+    assert_same_as_serial(b"\n\n");
+}
+
+#[test]
+fn strict_width_rows_are_absolute() {
+    let mut csv = String::new();
+
+    for day in 1..29 {
+        if day == 20 {
+            csv.push_str(&format!("2016-02-{:02},\"note\n{}\",{}.5,extra\n", day, day, day));
+        } else {
+            csv.push_str(&format!("2016-02-{:02},\"note\n{}\",{}.5\n", day, day, day));
+        }
+    }
+
+    let mut query = DataQuery::new("WIKI", "AAPL");
+    query.strict_width(3);
+
+    for threads in 1..12 {
+        let data = decode_with::<(String, String, f64)>(threads, &query, csv.as_bytes());
+        let error = Error::CsvParsing {
+            row: Some(20),
+            message: "row 20 has 4 fields, expected 3.".to_string(),
+        };
+
+        assert_eq!(data, Err(error), "threads: {}", threads);
+    }
+}
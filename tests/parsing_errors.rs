@@ -0,0 +1,217 @@
+extern crate quandl_v3;
+
+mod common;
+
+use quandl_v3::Error;
+use quandl_v3::prelude::*;
+
+use common::{MockServer, Response};
+
+static DRIFTED_METADATA: &str = include_str!("fixtures/database_metadata_drifted.json");
+static TWO_ELEMENTS_METADATA: &str = include_str!("fixtures/database_metadata_two_elements.json");
+static MALFORMED_DATA: &str = include_str!("fixtures/data_malformed.csv");
+#[cfg(feature = "zip")]
+static CODES_BAD_FORMAT: &[u8] = include_bytes!("fixtures/codes_bad_format.zip");
+
+fn server(path: &str, response: Response) -> MockServer {
+    MockServer::routes(vec![(path, response)])
+}
+
+#[test]
+fn json_schema_change() {
+    let server = server("/api/v3/databases/WIKI.json", Response::json(DRIFTED_METADATA));
+    let error = DatabaseMetadataQuery::new("WIKI").base_url(server.url()).send().unwrap_err();
+
+    match error {
+        Error::JsonParsing { ref message, ref snippet } => {
+            // Numeric strings are accepted, but not those with thousands separators.
+            assert!(message.starts_with("invalid value: string \"3,179\", expected a \
+                                         non-negative integer"),
+                    "{}", message);
+
+            assert!(snippet.contains("\"datasets_count\":\"3,179\""), "{}", snippet);
+        },
+
+        ref other => panic!("expected a JSON error, got {:?}", other),
+    }
+
+    assert!(error.is_parsing_failure());
+}
+
+#[test]
+fn metadata_without_a_single_element() {
+    for (body, len) in &[("{}", 0), (TWO_ELEMENTS_METADATA, 2)] {
+        let server = server("/api/v3/databases/WIKI.json", Response::json(*body));
+
+        assert_eq!(DatabaseMetadataQuery::new("WIKI").base_url(server.url()).send(),
+                   Err(Error::JsonParsing {
+                       message: format!("Expected a single element, got {}.", len),
+                       snippet: String::new(),
+                   }));
+    }
+
+    let server = server("/api/v3/datasets/WIKI/AAPL/metadata.json", Response::json("{}"));
+
+    assert_eq!(DatasetMetadataQuery::new("WIKI", "AAPL").base_url(server.url()).send(),
+               Err(Error::JsonParsing {
+                   message: "Expected a single element, got 0.".to_string(),
+                   snippet: String::new(),
+               }));
+}
+
+#[test]
+fn json_error_past_the_first_line() {
+    let body = "{\n  \"database\": {\n    \"id\": 4922,\n    \"name\": \"Wiki\" \"EOD\"\n  }\n}";
+    let server = server("/api/v3/databases/WIKI.json", Response::json(body));
+
+    match DatabaseMetadataQuery::new("WIKI").base_url(server.url()).send() {
+        Err(Error::JsonParsing { message, snippet }) => {
+            assert!(message.ends_with("at line 4 column 20"), "{}", message);
+            assert_eq!(snippet, "\"name\": \"Wiki\" \"EOD\"");
+        },
+
+        other => panic!("expected a JSON error, got {:?}", other),
+    }
+}
+
+#[test]
+fn json_which_is_not_utf8() {
+    let server = {
+        server("/api/v3/databases/WIKI.json", Response::json("").body(b"{\"database\": \"\xff\"}"))
+    };
+
+    match DatabaseMetadataQuery::new("WIKI").base_url(server.url()).send() {
+        Err(Error::JsonParsing { message, snippet }) => {
+            assert!(message.starts_with("invalid utf-8 sequence"), "{}", message);
+            assert_eq!(snippet, "{\"database\": \"");
+        },
+
+        other => panic!("expected a JSON error, got {:?}", other),
+    }
+}
+
+#[test]
+fn json_of_dataset_with_data() {
+    let body = r#"{"dataset": {"id": 1, "data": [["2016-02-10", "high"]]}}"#;
+    let server = server("/api/v3/datasets/WIKI/AAPL.json", Response::json(body));
+
+    let mut query = DataAndMetadataQuery::new("WIKI", "AAPL");
+    query.base_url(server.url());
+
+    match ApiCall::<Dataset<(String, f64)>>::send(&query) {
+        Err(Error::JsonParsing { message, snippet }) => {
+            assert!(message.starts_with("invalid type: string \"high\""), "{}", message);
+            assert_eq!(snippet, "");
+        },
+
+        other => panic!("expected a JSON error, got {:?}", other),
+    }
+}
+
+#[test]
+fn undecodable_error_response() {
+    let server = server("/api/v3/databases/WIKI.json", Response::new(500).body("Oops"));
+
+    match DatabaseMetadataQuery::new("WIKI").base_url(server.url()).send() {
+        Err(Error::JsonParsing { snippet, .. }) => assert_eq!(snippet, "Oops"),
+        other => panic!("expected a JSON error, got {:?}", other),
+    }
+}
+
+#[test]
+fn malformed_csv_row() {
+    let server = server("/api/v3/datasets/WIKI/AAPL/data.csv", Response::csv(MALFORMED_DATA));
+
+    let mut query = DataQuery::new("WIKI", "AAPL");
+    query.base_url(server.url());
+
+    match ApiCall::<Vec<(String, f64, f64)>>::send(&query) {
+        Err(Error::CsvParsing { row, message }) => {
+            assert_eq!(row, Some(2));
+            assert!(message.contains("field 2"), "{}", message);
+        },
+
+        other => panic!("expected a CSV error, got {:?}", other),
+    }
+
+    // Skipped rows are counted rather than reported.
+    let data = query.decode_lossy::<(String, f64, f64)>(MALFORMED_DATA.as_bytes()).unwrap();
+    assert_eq!(data.value.len(), 2);
+}
+
+#[cfg(feature = "zip")]
+#[test]
+fn corrupt_zip() {
+    let path = "/api/v3/databases/WIKI/codes";
+    let result: quandl_v3::Result<Vec<Code>> = {
+        CodeListQuery::new("WIKI").base_url(server(path, Response::new(200)).url()).send()
+    };
+
+    match result {
+        Err(Error::ZipExtraction(_)) => (),
+        other => panic!("expected a zip error, got {:?}", other),
+    }
+
+    // A valid archive whose compressed content is damaged.
+    let mut damaged = CODES_BAD_FORMAT.to_vec();
+
+    for byte in &mut damaged[60..80] {
+        *byte ^= 0x55;
+    }
+
+    let result: quandl_v3::Result<Vec<Code>> = {
+        CodeListQuery::new("WIKI").base_url(server(path, Response::new(200).body(damaged)).url())
+            .send()
+    };
+
+    match result {
+        Err(Error::ZipExtraction(_)) => (),
+        other => panic!("expected a zip error, got {:?}", other),
+    }
+}
+
+#[cfg(feature = "zip")]
+#[test]
+fn malformed_code_list() {
+    let server = {
+        server("/api/v3/databases/WIKI/codes", Response::new(200).body(CODES_BAD_FORMAT))
+    };
+
+    let result: quandl_v3::Result<Vec<Code>> = CodeListQuery::new("WIKI").base_url(server.url())
+        .send();
+
+    assert_eq!(result, Err(Error::CsvParsing {
+        row: Some(3),
+        message: "Invalid format for dataset codes in unzipped code list.".to_string(),
+    }));
+}
+
+#[test]
+fn display() {
+    let json = Error::JsonParsing {
+        message: "expected value at line 1 column 3".to_string(),
+        snippet: "{\"a".to_string(),
+    };
+
+    assert_eq!(json.to_string(), "parsing JSON data failed with error 'expected value at line 1 \
+                                  column 3' near '{\"a'.");
+
+    let json = Error::JsonParsing { message: "bad".to_string(), snippet: String::new() };
+    assert_eq!(json.to_string(), "parsing JSON data failed with error 'bad'.");
+
+    let csv = Error::CsvParsing { row: Some(4), message: "bad".to_string() };
+    assert_eq!(csv.to_string(), "parsing CSV data failed at row 4 with error 'bad'.");
+
+    let csv = Error::CsvParsing { row: None, message: "bad".to_string() };
+    assert_eq!(csv.to_string(), "parsing CSV data failed with error 'bad'.");
+
+    let zip = Error::ZipExtraction("bad".to_string());
+    assert_eq!(zip.to_string(), "extracting zipped data failed with error 'bad'.");
+
+    for error in &[json, csv, zip, Error::ParsingFailed("bad".to_string())] {
+        assert!(error.is_parsing_failure());
+        assert!(std::error::Error::source(error).is_none());
+    }
+
+    assert!(!Error::IoError("bad".to_string()).is_parsing_failure());
+}
@@ -0,0 +1,119 @@
+extern crate quandl_v3;
+extern crate serde_json;
+
+use std::time::Instant;
+
+use quandl_v3::codes::*;
+use quandl_v3::prelude::*;
+
+fn code(dataset_code: &str) -> Code {
+    Code {
+        dataset_code: dataset_code.to_string(),
+        database_code: "FRED".to_string(),
+        name: format!("Series {}", dataset_code),
+    }
+}
+
+fn dataset_codes(codes: Vec<&Code>) -> Vec<&str> {
+    codes.into_iter().map(|code| &code.dataset_code[..]).collect()
+}
+
+fn sample() -> PrefixTree {
+    let codes: Vec<Code> = ["GDP_Q", "GDP", "GDP_A", "CA_UR", "GDPC1", "CA_POP_M", "TX_UR"]
+        .iter()
+        .map(|dataset_code| code(dataset_code))
+        .collect();
+
+    group_by_prefix(&codes, '_')
+}
+
+#[test]
+fn construction() {
+    let tree = sample();
+
+    assert_eq!(tree.len(), 7);
+    assert_eq!(tree.delimiter(), '_');
+    assert_eq!(tree.children(""), ["CA", "GDP", "GDPC1", "TX"]);
+    assert_eq!(tree.children("CA"), ["POP", "UR"]);
+    assert_eq!(tree.children("CA_POP"), ["M"]);
+
+    assert_eq!(tree.count("GDP"), 3);
+    assert_eq!(tree.count("CA"), 2);
+    assert_eq!(tree.count("CA_POP_M"), 1);
+
+    // "GDP" is a dataset as well as the prefix of two others.
+    assert_eq!(dataset_codes(tree.node("GDP").unwrap().datasets().iter().collect()), ["GDP"]);
+    assert_eq!(dataset_codes(tree.datasets_under("GDP")), ["GDP", "GDP_A", "GDP_Q"]);
+    assert!(tree.node("CA").unwrap().datasets().is_empty());
+
+    let root = tree.root();
+    assert_eq!(root.count(), 7);
+    assert_eq!(root.child("TX").and_then(|tx| tx.child("UR")).map(PrefixNode::count), Some(1));
+    assert_eq!(root.children().map(|(segment, node)| (segment, node.count())).collect::<Vec<_>>(),
+               [("CA", 2), ("GDP", 3), ("GDPC1", 1), ("TX", 1)]);
+}
+
+#[test]
+fn unknown_paths() {
+    let tree = sample();
+
+    for path in &["NY", "GDP_M", "CA_POP_M_X", "GD"] {
+        assert!(tree.node(path).is_none(), "{}", path);
+        assert!(tree.children(path).is_empty(), "{}", path);
+        assert!(tree.datasets_under(path).is_empty(), "{}", path);
+        assert_eq!(tree.count(path), 0, "{}", path);
+    }
+
+    assert_eq!(tree.datasets_under("").len(), 7);
+}
+
+#[test]
+fn codes_without_delimiter() {
+    let codes = vec![code("GDPC1"), code("UNRATE"), code("GDPC1")];
+    let tree = group_by_prefix(&codes, '/');
+
+    assert_eq!(tree.children(""), ["GDPC1", "UNRATE"]);
+    assert!(tree.children("GDPC1").is_empty());
+    assert_eq!(tree.node("GDPC1").unwrap().datasets(), &[code("GDPC1"), code("GDPC1")][..]);
+    assert_eq!(tree.count("GDPC1"), 2);
+
+    // Leading, trailing and repeated delimiters make empty segments.
+    let tree = group_by_prefix(&[code("_A"), code("A_"), code("A__B")], '_');
+
+    assert_eq!(tree.children(""), ["", "A"]);
+    assert_eq!(tree.children("A"), [""]);
+    assert_eq!(tree.children("A_"), ["B"]);
+    assert_eq!(dataset_codes(tree.datasets_under("A_")), ["A_", "A__B"]);
+
+    let tree = group_by_prefix(&[], '_');
+    assert!(tree.is_empty());
+    assert!(tree.children("").is_empty());
+}
+
+#[test]
+fn serialization() {
+    let tree = sample();
+    let json = serde_json::to_string(&tree).unwrap();
+
+    assert_eq!(serde_json::from_str::<PrefixTree>(&json).unwrap(), tree);
+}
+
+#[test]
+fn large_input() {
+    let codes: Vec<Code> = (0..100_000)
+        .map(|index| code(&format!("S{}_C{}_{}", index % 50, index % 1_000, index)))
+        .collect();
+
+    let start = Instant::now();
+    let tree = group_by_prefix(&codes, '_');
+
+    assert_eq!(tree.len(), 100_000);
+    assert_eq!(tree.children("").len(), 50);
+    assert_eq!(tree.count("S7"), 2_000);
+    assert_eq!(tree.children("S7").len(), 20);
+    assert_eq!(tree.count("S7_C7"), 100);
+    assert_eq!(tree.datasets_under("S7").len(), 2_000);
+
+    // Generous, even for debug builds.
+    assert!(start.elapsed().as_secs() < 10);
+}
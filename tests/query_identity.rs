@@ -0,0 +1,93 @@
+extern crate quandl_v3;
+
+use std::collections::HashSet;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use quandl_v3::prelude::*;
+
+fn hash<T: Hash>(value: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn query(database_code: &str, dataset_code: &str, api_key: &str) -> DataQuery {
+    let mut query = DataQuery::new(database_code, dataset_code);
+    query.api_key(api_key);
+    query
+}
+
+#[test]
+fn clones_are_equal() {
+    let query = query("WIKI", "AAPL", "key");
+    let clone = query.clone();
+
+    assert_eq!(clone, query);
+    assert_eq!(hash(&clone), hash(&query));
+    assert_eq!((clone.database_code(), clone.dataset_code()), ("WIKI", "AAPL"));
+}
+
+#[test]
+fn equality_is_by_value() {
+    // Built from separately allocated strings, which are not shared.
+    let owned = DataQuery::new(String::from("WIKI"), format!("{}{}", "AA", "PL"));
+    let borrowed = DataQuery::new("WIKI", "AAPL");
+
+    assert_eq!(owned, borrowed);
+    assert_eq!(hash(&owned), hash(&borrowed));
+
+    assert_ne!(query("WIKI", "AAPL", "key"), query("WIKI", "MSFT", "key"));
+    assert_ne!(query("WIKI", "AAPL", "key"), query("EOD", "AAPL", "key"));
+    assert_ne!(query("WIKI", "AAPL", "key"), query("WIKI", "AAPL", "other-key"));
+    assert_ne!(query("WIKI", "AAPL", "key"), DataQuery::new("WIKI", "AAPL"));
+}
+
+#[test]
+fn changing_a_clone_leaves_the_original() {
+    let original = query("WIKI", "AAPL", "key");
+    let mut clone = original.clone();
+
+    clone.set_database_code("EOD").set_dataset_code("MSFT").api_key("other-key");
+
+    assert_eq!((original.database_code(), original.dataset_code()), ("WIKI", "AAPL"));
+    assert_eq!((clone.database_code(), clone.dataset_code()), ("EOD", "MSFT"));
+    assert_eq!(original, query("WIKI", "AAPL", "key"));
+    assert_eq!(clone, query("EOD", "MSFT", "other-key"));
+}
+
+#[test]
+fn dedup() {
+    let queries = {
+        vec![
+            query("WIKI", "AAPL", "key"),
+            query("WIKI", "MSFT", "key"),
+            query("WIKI", "AAPL", "key").clone(),
+            query("WIKI", "AAPL", "other-key"),
+            DataQuery::new("WIKI", "AAPL").api_key("key").clone(),
+        ]
+    };
+
+    let unique: HashSet<DataQuery> = queries.into_iter().collect();
+    assert_eq!(unique.len(), 3);
+}
+
+#[test]
+fn every_query_has_code_accessors() {
+    let mut metadata = DatabaseMetadataQuery::new("WIKI");
+    metadata.set_database_code("FRED");
+    assert_eq!(metadata, DatabaseMetadataQuery::new("FRED"));
+
+    let mut dataset = DatasetMetadataQuery::new("WIKI", "AAPL");
+    dataset.set_dataset_code("MSFT");
+    assert_eq!((dataset.database_code(), dataset.dataset_code()), ("WIKI", "MSFT"));
+
+    let mut both = DataAndMetadataQuery::new("WIKI", "AAPL");
+    both.set_database_code("EOD");
+    assert_eq!(hash(&both), hash(&DataAndMetadataQuery::new("EOD", "AAPL")));
+
+    assert_eq!(DatasetSearch::new("WIKI").database_code(), "WIKI");
+    assert_eq!(DatasetListingQuery::new("WIKI").database_code(), "WIKI");
+    assert_eq!(CodeListQuery::new("WIKI").database_code(), "WIKI");
+    assert_eq!(DatabaseDownloadQuery::new("WIKI").database_code(), "WIKI");
+}
@@ -0,0 +1,341 @@
+#![cfg(feature = "batch")]
+
+extern crate quandl_v3;
+
+mod common;
+
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use quandl_v3::Error;
+use quandl_v3::prelude::*;
+use quandl_v3::queue::{DownloadQueue, DrainConfig, ItemState, QuerySpec};
+
+use common::{MockServer, Response};
+
+static DATA: &str = include_str!("fixtures/data_3_columns.csv");
+
+type Row = (String, f64, f64);
+
+/// Variable telling `queue_worker` the path of the queue to drain, when run as a child process.
+///
+const WORKER: &str = "QUANDL_V3_QUEUE_WORKER";
+
+/// Server answering data queries, which hangs on the dataset `HANG` while `hang` is set, fails
+/// for `LIMIT` as if a limit was exceeded and for `MISSING` as if it did not exist.
+///
+fn server(hang: Arc<AtomicBool>) -> MockServer {
+    MockServer::start(move |request| {
+        let code = request.path.trim_start_matches("/api/v3/datasets/WIKI/");
+
+        match code.trim_end_matches("/data.csv") {
+            "HANG" => {
+                while hang.load(Ordering::SeqCst) {
+                    sleep(Duration::from_millis(10));
+                }
+
+                Response::csv(DATA)
+            },
+
+            "LIMIT" => {
+                Response::new(429)
+                    .header("Content-Type", "application/json; charset=utf-8")
+                    .body(r#"{"quandl_error":{"code":"QELx01","message":"Slow down."}}"#)
+            },
+
+            "MISSING" => Response::not_found(),
+            _ => Response::csv(DATA),
+        }
+    })
+}
+
+fn queue_path(name: &str) -> PathBuf {
+    let name = format!("quandl-v3-queue-{}-{}.jsonl", name, std::process::id());
+    let path = env::temp_dir().join(name);
+    let _ = fs::remove_file(&path);
+    path
+}
+
+fn spec(server: &MockServer, code: &str) -> QuerySpec {
+    let mut query = DataQuery::new("WIKI", code);
+    query.base_url(server.url()).api_key("secret-key");
+    QuerySpec::new::<Vec<Row>, _>(&query).unwrap()
+}
+
+fn config() -> DrainConfig {
+    DrainConfig { api_key: Some(ApiKey::new("secret-key")), .. DrainConfig::default() }
+}
+
+fn states(queue: &DownloadQueue) -> Vec<(ItemState, usize)> {
+    queue.items().map(|item| (item.state.clone(), item.attempts)).collect()
+}
+
+#[test]
+fn specs_leave_out_the_api_key() {
+    let server = server(Arc::new(AtomicBool::new(false)));
+    let spec = spec(&server, "AAPL");
+
+    assert!(!spec.url.contains("secret-key"), "{}", spec.url);
+    assert!(spec.url.starts_with(&format!("{}/datasets/WIKI/AAPL/data.csv?", server.url())));
+
+    let url = url::Url::parse(&format!("{}/databases/WIKI.json?api_key=KEY", server.url()));
+    assert_eq!(QuerySpec::from_url(&url.unwrap()).url,
+               format!("{}/databases/WIKI.json", server.url()));
+}
+
+#[test]
+fn items_persist_across_opens() {
+    let server = server(Arc::new(AtomicBool::new(false)));
+    let path = queue_path("persist");
+
+    {
+        let mut queue = DownloadQueue::open(&path).unwrap();
+        assert_eq!(queue.items().count(), 0);
+
+        assert_eq!(queue.push(spec(&server, "AAPL")).unwrap(), 0);
+        assert_eq!(queue.push_all(vec![spec(&server, "MSFT"), spec(&server, "GOOG")]).unwrap(),
+                   vec![1, 2]);
+    }
+
+    let mut queue = DownloadQueue::open(&path).unwrap();
+    assert_eq!(states(&queue), vec![(ItemState::Queued, 0); 3]);
+    assert_eq!(queue.get(1).unwrap().spec, spec(&server, "MSFT"));
+
+    // Ids keep increasing.
+    assert_eq!(queue.push(spec(&server, "IBM")).unwrap(), 3);
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn drained_items_are_not_sent_again() {
+    let server = server(Arc::new(AtomicBool::new(false)));
+    let path = queue_path("drain");
+
+    let mut queue = DownloadQueue::open(&path).unwrap();
+    queue.push_all(vec![spec(&server, "AAPL"), spec(&server, "MSFT")]).unwrap();
+
+    let outcomes: Vec<_> = queue.drain::<DataQuery, Vec<Row>>(&config()).collect();
+
+    assert_eq!(outcomes.iter().map(|x| x.id).collect::<Vec<_>>(), vec![0, 1]);
+    assert!(outcomes.iter().all(|x| x.attempts == 1 && x.result.as_ref().unwrap().len() == 3));
+    assert_eq!(states(&queue), vec![(ItemState::Done, 1); 2]);
+    assert_eq!(server.hits(), 2);
+
+    // Neither in this process nor in the next one.
+    assert_eq!(queue.drain::<DataQuery, Vec<Row>>(&config()).count(), 0);
+
+    let mut queue = DownloadQueue::open(&path).unwrap();
+    assert_eq!(states(&queue), vec![(ItemState::Done, 1); 2]);
+    assert_eq!(queue.drain::<DataQuery, Vec<Row>>(&config()).count(), 0);
+    assert_eq!(server.hits(), 2);
+
+    // The key is given when draining.
+    assert!(server.requests().iter().all(|x| x.query.contains("api_key=secret-key")));
+
+    let content = fs::read_to_string(&path).unwrap();
+    assert!(!content.contains("secret-key"), "{}", content);
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn failures_are_retried_while_retryable() {
+    let server = server(Arc::new(AtomicBool::new(false)));
+    let path = queue_path("retry");
+
+    let mut queue = DownloadQueue::open(&path).unwrap();
+    queue.push_all(vec![spec(&server, "LIMIT"), spec(&server, "MISSING")]).unwrap();
+
+    let config = DrainConfig { max_attempts: 2, .. config() };
+
+    for attempt in 1..3 {
+        let outcomes: Vec<_> = queue.drain::<DataQuery, Vec<Row>>(&config).collect();
+
+        // The missing dataset is not worth sending again.
+        let expected = if attempt == 1 { vec![0, 1] } else { vec![0] };
+        assert_eq!(outcomes.iter().map(|x| x.id).collect::<Vec<_>>(), expected);
+
+        match outcomes[0].result {
+            Err(Error::RateLimitExceeded { api_error: Some(ref e), .. }) => {
+                assert_eq!(e.quandl_error.code, "QELx01")
+            },
+            ref other => panic!("{:?}", other),
+        }
+
+        assert_eq!(outcomes[0].attempts, attempt);
+    }
+
+    // Out of attempts.
+    assert_eq!(queue.drain::<DataQuery, Vec<Row>>(&config).count(), 0);
+    assert_eq!(server.hits(), 3);
+
+    let queue = DownloadQueue::open(&path).unwrap();
+    let items: Vec<_> = queue.items().collect();
+
+    assert_eq!(items[0].attempts, 2);
+    assert!(matches!(items[0].state, ItemState::Failed { retryable: true, .. }));
+    assert_eq!(items[1].attempts, 1);
+
+    match items[1].state {
+        ItemState::Failed { ref error, retryable: false } => {
+            assert!(error.contains("incorrect Quandl code"), "{}", error);
+        },
+
+        ref other => panic!("{:?}", other),
+    }
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn other_kinds_of_queries_are_left_queued() {
+    let server = server(Arc::new(AtomicBool::new(false)));
+    let kinds = queue_path("kinds");
+
+    let mut queue = DownloadQueue::open(&kinds).unwrap();
+    queue.push(QuerySpec::new(&DatabaseMetadataQuery::new("WIKI")).unwrap()).unwrap();
+    queue.push(spec(&server, "AAPL")).unwrap();
+
+    let outcomes: Vec<_> = queue.drain::<DataQuery, Vec<Row>>(&config()).collect();
+    assert_eq!(outcomes.iter().map(|x| x.id).collect::<Vec<_>>(), vec![1]);
+    assert_eq!(queue.get(0).unwrap().state, ItemState::Queued);
+
+    // As is everything, without any key.
+    let keyless = queue_path("keyless");
+    let mut queue = DownloadQueue::open(&keyless).unwrap();
+    queue.push(spec(&server, "AAPL")).unwrap();
+
+    assert_eq!(queue.drain::<DataQuery, Vec<Row>>(&DrainConfig::default()).count(), 0);
+    assert_eq!(server.hits(), 1);
+
+    fs::remove_file(&kinds).unwrap();
+    fs::remove_file(&keyless).unwrap();
+}
+
+#[test]
+fn crashed_attempts_count() {
+    let server = server(Arc::new(AtomicBool::new(false)));
+    let path = queue_path("crashed");
+
+    {
+        let mut queue = DownloadQueue::open(&path).unwrap();
+        queue.push_all(vec![spec(&server, "AAPL"), spec(&server, "MSFT")]).unwrap();
+    }
+
+    // Both were being sent by a worker which died twice, the second one while writing.
+    {
+        let mut file = fs::OpenOptions::new().append(true).open(&path).unwrap();
+        writeln!(file, r#"{{"op":"start","id":0}}"#).unwrap();
+        writeln!(file, r#"{{"op":"start","id":1}}"#).unwrap();
+        writeln!(file, r#"{{"op":"start","id":1}}"#).unwrap();
+        write!(file, r#"{{"op":"done","i"#).unwrap();
+    }
+
+    let mut queue = DownloadQueue::open(&path).unwrap();
+    assert_eq!(states(&queue), vec![(ItemState::InProgress, 1), (ItemState::InProgress, 2)]);
+
+    let config = DrainConfig { max_attempts: 2, .. config() };
+    let outcomes: Vec<_> = queue.drain::<DataQuery, Vec<Row>>(&config).collect();
+
+    assert_eq!(outcomes.len(), 1);
+    assert_eq!((outcomes[0].id, outcomes[0].attempts), (0, 2));
+    assert!(outcomes[0].result.is_ok());
+
+    // The torn record was terminated, so the next ones parsed.
+    let queue = DownloadQueue::open(&path).unwrap();
+    assert_eq!(states(&queue), vec![(ItemState::Done, 2), (ItemState::InProgress, 2)]);
+
+    fs::remove_file(&path).unwrap();
+}
+
+/// Drain the queue named by `WORKER` for `killed_workers_are_resumed`, and nothing otherwise.
+///
+#[test]
+fn queue_worker() {
+    let path = {
+        match env::var(WORKER) {
+            Ok(path) => path,
+            Err(_) => return,
+        }
+    };
+
+    let mut queue = DownloadQueue::open(&path).unwrap();
+
+    for outcome in queue.drain::<DataQuery, Vec<Row>>(&config()) {
+        outcome.result.unwrap();
+    }
+}
+
+#[test]
+fn killed_workers_are_resumed() {
+    let hang = Arc::new(AtomicBool::new(true));
+    let server = server(hang.clone());
+    let path = queue_path("killed");
+
+    {
+        let mut queue = DownloadQueue::open(&path).unwrap();
+        let codes = ["AAPL", "HANG", "MSFT"];
+        queue.push_all(codes.iter().map(|code| spec(&server, code))).unwrap();
+    }
+
+    let mut worker = {
+        Command::new(env::current_exe().unwrap())
+            .args(["queue_worker", "--exact", "--test-threads", "1"])
+            .env(WORKER, &path)
+            .stdout(Stdio::null())
+            .spawn()
+            .unwrap()
+    };
+
+    // Killed once it is stuck on the second item, the first one being done.
+    let start = Instant::now();
+
+    while !server.requests().iter().any(|x| x.path.ends_with("/HANG/data.csv")) {
+        assert!(start.elapsed() < Duration::from_secs(30), "the worker never sent HANG");
+        sleep(Duration::from_millis(10));
+    }
+
+    worker.kill().unwrap();
+    worker.wait().unwrap();
+
+    let mut queue = DownloadQueue::open(&path).unwrap();
+
+    assert_eq!(states(&queue), vec![
+        (ItemState::Done, 1),
+        (ItemState::InProgress, 1),
+        (ItemState::Queued, 0),
+    ]);
+
+    hang.store(false, Ordering::SeqCst);
+
+    let outcomes: Vec<_> = queue.drain::<DataQuery, Vec<Row>>(&config()).collect();
+
+    assert_eq!(outcomes.iter().map(|x| (x.id, x.attempts)).collect::<Vec<_>>(),
+               vec![(1, 2), (2, 1)]);
+    assert!(outcomes.iter().all(|x| x.result.is_ok()));
+
+    let queue = DownloadQueue::open(&path).unwrap();
+    assert_eq!(states(&queue), vec![
+        (ItemState::Done, 1),
+        (ItemState::Done, 2),
+        (ItemState::Done, 1),
+    ]);
+
+    // The first item was only ever sent once.
+    let sent = |code: &str| {
+        let path = format!("/api/v3/datasets/WIKI/{}/data.csv", code);
+        server.requests().iter().filter(|x| x.path == path).count()
+    };
+
+    assert_eq!((sent("AAPL"), sent("HANG"), sent("MSFT")), (1, 2, 1));
+
+    fs::remove_file(&path).unwrap();
+}
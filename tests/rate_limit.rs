@@ -0,0 +1,247 @@
+extern crate quandl_v3;
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use quandl_v3::rate_limit::*;
+
+fn secs(n: u64) -> Duration {
+    Duration::from_secs(n)
+}
+
+#[test]
+fn no_limits_always_proceed() {
+    let mut limiter = RateLimiter::new(vec![]);
+    let now = Instant::now();
+
+    for _ in 0..1000 {
+        assert_eq!(limiter.check("key", now), Decision::Proceed);
+    }
+
+    assert_eq!(limiter.recorded("key"), 0);
+}
+
+#[test]
+fn burst_then_wait_for_window() {
+    let mut limiter = RateLimiter::new(vec![(3, secs(10))]);
+    let t0 = Instant::now();
+
+    for _ in 0..3 {
+        assert_eq!(limiter.check("key", t0), Decision::Proceed);
+    }
+
+    assert_eq!(limiter.check("key", t0), Decision::WaitFor(secs(10)));
+    assert_eq!(limiter.check("key", t0 + secs(4)), Decision::WaitFor(secs(6)));
+    assert_eq!(limiter.check("key", t0 + secs(10)), Decision::Proceed);
+}
+
+#[test]
+fn window_slides_with_spaced_calls() {
+    let mut limiter = RateLimiter::new(vec![(3, secs(10))]);
+    let t0 = Instant::now();
+
+    assert_eq!(limiter.check("key", t0), Decision::Proceed);
+    assert_eq!(limiter.check("key", t0 + secs(2)), Decision::Proceed);
+    assert_eq!(limiter.check("key", t0 + secs(4)), Decision::Proceed);
+
+    assert_eq!(limiter.check("key", t0 + secs(5)), Decision::WaitFor(secs(5)));
+    assert_eq!(limiter.check("key", t0 + secs(10)), Decision::Proceed);
+
+    // The oldest call in the window is now the one made at t0 + 2.
+    assert_eq!(limiter.check("key", t0 + secs(10)), Decision::WaitFor(secs(2)));
+    assert_eq!(limiter.check("key", t0 + secs(12)), Decision::Proceed);
+}
+
+#[test]
+fn multiple_tiers_wait_for_the_longest() {
+    let mut limiter = RateLimiter::new(vec![(2, secs(1)), (3, secs(10))]);
+    let t0 = Instant::now();
+
+    assert_eq!(limiter.check("key", t0), Decision::Proceed);
+    assert_eq!(limiter.check("key", t0), Decision::Proceed);
+    assert_eq!(limiter.check("key", t0), Decision::WaitFor(secs(1)));
+    assert_eq!(limiter.check("key", t0 + secs(1)), Decision::Proceed);
+
+    // Both tiers are exhausted, the daily-like one dominates.
+    assert_eq!(limiter.check("key", t0 + secs(1)), Decision::WaitFor(secs(9)));
+    assert_eq!(limiter.wait_time("key", t0 + secs(9)), secs(1));
+    assert_eq!(limiter.check("key", t0 + secs(10)), Decision::Proceed);
+}
+
+#[test]
+fn binding_limit_is_the_longest_wait() {
+    let mut limiter = RateLimiter::new(vec![(2, secs(1)), (3, secs(10))]);
+    let t0 = Instant::now();
+
+    assert_eq!(limiter.binding_limit("key", t0), None);
+
+    limiter.record("key", 2, t0);
+    assert_eq!(limiter.binding_limit("key", t0), Some(((2, secs(1)), secs(1))));
+    assert_eq!(limiter.binding_limit("key", t0 + secs(1)), None);
+
+    limiter.record("key", 1, t0 + secs(1));
+    assert_eq!(limiter.binding_limit("key", t0 + secs(1)), Some(((3, secs(10)), secs(9))));
+}
+
+#[test]
+fn keys_are_independent() {
+    let mut limiter = RateLimiter::new(vec![(1, secs(60))]);
+    let t0 = Instant::now();
+
+    assert_eq!(limiter.check("a", t0), Decision::Proceed);
+    assert_eq!(limiter.check("b", t0), Decision::Proceed);
+    assert_eq!(limiter.check("a", t0), Decision::WaitFor(secs(60)));
+    assert_eq!(limiter.check("c", t0), Decision::Proceed);
+}
+
+#[test]
+fn recorded_calls_count_against_limits() {
+    let mut limiter = RateLimiter::new(vec![(3, secs(10)), (2, secs(1))]);
+    let t0 = Instant::now();
+
+    limiter.record("key", 5, t0);
+
+    assert_eq!(limiter.recorded("key"), 3);
+    assert_eq!(limiter.check("key", t0 + secs(1)), Decision::WaitFor(secs(9)));
+    assert_eq!(limiter.check("other", t0), Decision::Proceed);
+}
+
+#[test]
+fn status_of_the_longest_limit() {
+    let mut limiter = RateLimiter::new(vec![(2, secs(1)), (5, secs(100))]);
+    let t0 = Instant::now();
+
+    let status = |remaining, reset| Some(RateLimitStatus { remaining, reset: secs(reset) });
+
+    assert_eq!(limiter.status("key", t0), status(5, 0));
+
+    limiter.record("key", 1, t0);
+    limiter.record("key", 2, t0 + secs(10));
+
+    assert_eq!(limiter.status("key", t0 + secs(10)), status(2, 90));
+    assert_eq!(limiter.status("key", t0 + secs(100)), status(3, 10));
+    assert_eq!(limiter.status("key", t0 + secs(110)), status(5, 0));
+    assert_eq!(limiter.status("other", t0), status(5, 0));
+
+    limiter.record("key", 5, t0 + secs(20));
+    assert_eq!(limiter.status("key", t0 + secs(20)), status(0, 100));
+
+    assert_eq!(RateLimiter::new(vec![]).status("key", t0), None);
+}
+
+#[test]
+fn waiting_does_not_record() {
+    let mut limiter = RateLimiter::new(vec![(1, secs(10))]);
+    let t0 = Instant::now();
+
+    assert_eq!(limiter.check("key", t0), Decision::Proceed);
+
+    for _ in 0..10 {
+        assert_eq!(limiter.check("key", t0 + secs(5)), Decision::WaitFor(secs(5)));
+    }
+
+    assert_eq!(limiter.recorded("key"), 1);
+    assert_eq!(limiter.wait_time("key", t0 + secs(20)), secs(0));
+}
+
+#[test]
+#[should_panic]
+fn zero_calls_limit_is_rejected() {
+    RateLimiter::new(vec![(0, secs(10))]);
+}
+
+#[test]
+fn blocking_adapter_sleeps() {
+    let limiter = Mutex::new(RateLimiter::new(vec![(2, Duration::from_millis(300))]));
+    let start = Instant::now();
+
+    acquire(&limiter, "key");
+    acquire(&limiter, "key");
+    assert!(start.elapsed() < Duration::from_millis(300));
+
+    acquire(&limiter, "key");
+    assert!(start.elapsed() >= Duration::from_millis(300));
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn async_adapter_waits() {
+    let limiter = Mutex::new(RateLimiter::new(vec![(1, Duration::from_millis(200))]));
+
+    let mut runtime = {
+        tokio::runtime::Builder::new().basic_scheduler().enable_time().build().unwrap()
+    };
+
+    let start = Instant::now();
+
+    runtime.block_on(async {
+        acquire_async(&limiter, "key").await;
+        acquire_async(&limiter, "key").await;
+    });
+
+    assert!(start.elapsed() >= Duration::from_millis(200));
+}
+
+#[test]
+fn estimates_within_the_quota() {
+    let limits = [(3, secs(10))];
+    let status = RateLimitStatus { remaining: 5, reset: secs(30) };
+
+    assert_eq!(estimate_completion(0, &limits, &status), secs(0));
+    assert_eq!(estimate_completion(3, &limits, &status), secs(0));
+    assert_eq!(estimate_completion(4, &limits, &status), secs(10));
+    assert_eq!(estimate_completion(3, &[], &status), secs(0));
+}
+
+#[test]
+fn estimates_over_the_quota() {
+    let limits = [(3, secs(10))];
+    let over = RateLimitStatus { remaining: 0, reset: secs(4) };
+
+    assert_eq!(estimate_completion(0, &limits, &over), secs(0));
+    assert_eq!(estimate_completion(1, &limits, &over), secs(4));
+    assert_eq!(estimate_completion(3, &limits, &over), secs(4));
+    assert_eq!(estimate_completion(4, &limits, &over), secs(14));
+
+    // Just reset.
+    let reset = RateLimitStatus { remaining: 0, reset: secs(0) };
+    assert_eq!(estimate_completion(3, &limits, &reset), secs(0));
+}
+
+#[test]
+fn estimates_at_the_quota() {
+    let limits = [(3, secs(10))];
+    let status = RateLimitStatus { remaining: 2, reset: secs(4) };
+
+    // Exactly the remaining calls make it before the reset...
+    assert_eq!(estimate_completion(2, &limits, &status), secs(0));
+
+    // ...the next ones have to wait for it, and then for the window of the first calls.
+    assert_eq!(estimate_completion(3, &limits, &status), secs(4));
+    assert_eq!(estimate_completion(4, &limits, &status), secs(10));
+    assert_eq!(estimate_completion(6, &limits, &status), secs(14));
+
+    // The quota is reset later than the limits would allow.
+    let status = RateLimitStatus { remaining: 2, reset: secs(40) };
+    assert_eq!(estimate_completion(4, &limits, &status), secs(40));
+}
+
+#[test]
+fn estimates_with_multiple_tiers() {
+    let limits = [(2, secs(1)), (5, secs(10))];
+    let status = RateLimitStatus { remaining: usize::MAX, reset: secs(0) };
+
+    assert_eq!(estimate_completion(2, &limits, &status), secs(0));
+    assert_eq!(estimate_completion(3, &limits, &status), secs(1));
+    assert_eq!(estimate_completion(5, &limits, &status), secs(2));
+
+    // The longest window takes over once its calls are spent, the shortest within it.
+    assert_eq!(estimate_completion(6, &limits, &status), secs(10));
+    assert_eq!(estimate_completion(8, &limits, &status), secs(11));
+    assert_eq!(estimate_completion(11, &limits, &status), secs(20));
+
+    let over = RateLimitStatus { remaining: 1, reset: secs(5) };
+    assert_eq!(estimate_completion(2, &limits, &over), secs(5));
+    assert_eq!(estimate_completion(4, &limits, &over), secs(6));
+    assert_eq!(estimate_completion(6, &limits, &over), secs(10));
+}
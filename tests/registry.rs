@@ -0,0 +1,118 @@
+#![cfg(feature = "registry")]
+
+extern crate quandl_v3;
+
+mod common;
+
+use quandl_v3::ValidationError;
+use quandl_v3::prelude::*;
+use quandl_v3::registry::*;
+
+use common::{MockServer, Response};
+
+static DATASET_DATA: &str = include_str!("fixtures/dataset_data.json");
+
+#[test]
+fn wiki() {
+    let hints = hints_for("WIKI").unwrap();
+
+    assert_eq!(hints.column_names.len(), 13);
+    assert_eq!(hints.column_names[11], "Adj. Close");
+    assert_eq!(hints.value_column, Some(11));
+    assert_eq!(hints.frequency, Some(Frequency::daily));
+}
+
+#[test]
+fn fred() {
+    assert_eq!(hints_for("FRED"), Some(DatabaseHints {
+        column_names: vec!["Date".to_string(), "Value".to_string()],
+        value_column: Some(1),
+        frequency: None,
+    }));
+}
+
+#[test]
+fn eod() {
+    let hints = hints_for("EOD").unwrap();
+
+    assert_eq!(hints.column_index("Adj_Close"), hints.value_column);
+    assert_eq!(hints.value_column, Some(11));
+    assert_eq!(hints.frequency, Some(Frequency::daily));
+}
+
+#[test]
+fn lookups() {
+    assert_eq!(hints_for(" wiki "), hints_for("WIKI"));
+    assert_eq!(hints_for("NO_SUCH_DATABASE"), None);
+    assert_eq!(hints_for(""), None);
+
+    let wiki = hints_for("WIKI").unwrap();
+
+    assert_eq!(wiki.column_index(" ADJ. CLOSE "), Some(11));
+    assert_eq!(wiki.column_index("Date"), Some(0));
+    assert_eq!(wiki.column_index("Adjusted Close"), None);
+}
+
+#[test]
+fn registered_at_runtime() {
+    let hints = {
+        DatabaseHints {
+            column_names: vec!["Date".to_string(), "Rate".to_string()],
+            value_column: Some(1),
+            frequency: Some(Frequency::monthly),
+        }
+    };
+
+    assert_eq!(register("acme", hints.clone()), None);
+    assert_eq!(hints_for("ACME"), Some(hints.clone()));
+
+    let replaced = DatabaseHints { frequency: None, ..hints.clone() };
+    assert_eq!(register("ACME", replaced.clone()), Some(hints));
+    assert_eq!(hints_for("acme"), Some(replaced));
+}
+
+#[test]
+fn embedded_database_overridden() {
+    let eod = hints_for("EOD").unwrap();
+    assert_eq!(register("YAHOO", eod.clone()), None);
+    assert_eq!(hints_for("YAHOO"), Some(eod));
+}
+
+#[test]
+fn preview_names_columns_after_registry() {
+    let server = MockServer::routes(vec![
+        ("/api/v3/datasets/WIKI/AAPL.json", Response::json(DATASET_DATA)),
+    ]);
+
+    let mut query = DataAndMetadataQuery::new("WIKI", "AAPL");
+    query.base_url(server.url());
+
+    let dataset: Dataset<(String, f64, f64)> = query.send().unwrap();
+    let mut opens = dataset.map_rows(|(date, open, _)| (date, open));
+
+    opens.metadata.database_code = "FRED".to_string();
+    assert!(opens.preview(1).starts_with("Date        Value\n"), "{}", opens.preview(1));
+
+    // The registry is only used when it knows as many columns as the rows have.
+    opens.metadata.database_code = "WIKI".to_string();
+    assert!(opens.preview(1).starts_with("column 1    column 2\n"), "{}", opens.preview(1));
+}
+
+#[test]
+fn column_index_validated_against_registry() {
+    let mut query = DataQuery::new("WIKI", "AAPL");
+    query.column_index(11);
+    assert_eq!(query.validate(), Ok(()));
+
+    query.column_index(13);
+
+    assert_eq!(query.validate(), Err(vec![
+        ValidationError::new("column_index", "is 13, beyond the 13 columns of the datasets of \
+                                              WIKI."),
+    ]));
+
+    // Datasets of databases unknown to the registry may have any number of columns.
+    let mut query = DataAndMetadataQuery::new("XYZ", "AAPL");
+    query.column_index(42);
+    assert_eq!(query.validate(), Ok(()));
+}
@@ -0,0 +1,179 @@
+#![cfg(feature = "batch")]
+
+extern crate chrono;
+extern crate quandl_v3;
+
+mod common;
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use chrono::{Duration, Utc};
+
+use quandl_v3::Error;
+use quandl_v3::prelude::*;
+
+use common::{MockServer, Response};
+
+static DATABASE_METADATA: &str = include_str!("fixtures/database_metadata.json");
+
+/// Server reporting `quota` calls a day for each key, down by one with each call, and their reset
+/// as `reset` tells from the query string of the calls (if at all).
+///
+fn server(quota: usize, reset: fn(&str) -> Option<&'static str>) -> MockServer {
+    let calls = Mutex::new(HashMap::<String, usize>::new());
+
+    MockServer::start(move |request| {
+        let made = {
+            let mut calls = calls.lock().unwrap();
+            let made = calls.entry(request.query.clone()).or_default();
+            *made += 1;
+            *made
+        };
+
+        let response = {
+            Response::json(DATABASE_METADATA)
+                .header("X-RateLimit-Limit", &quota.to_string())
+                .header("X-RateLimit-Remaining", &quota.saturating_sub(made).to_string())
+        };
+
+        match reset(&request.query) {
+            Some(reset) => response.header("X-RateLimit-Reset", reset),
+            None => response,
+        }
+    })
+}
+
+fn query(server: &MockServer, api_key: &str) -> DatabaseMetadataQuery {
+    let mut query = DatabaseMetadataQuery::new("WIKI");
+    query.base_url(server.url()).api_key(api_key);
+    query
+}
+
+fn quota_exhausted(server: &MockServer) -> Result<DatabaseMetadata, Error> {
+    let url = format!("{}/databases/WIKI.json", server.url());
+    Err(Error::QuotaExhausted(url))
+}
+
+#[test]
+fn dispatch_stops_at_the_reserve() {
+    let server = server(5, |_| Some("3600"));
+
+    let mut batch_query = BatchQuery::new();
+    batch_query.reserve_quota(2);
+
+    for _ in 0..10 {
+        batch_query.query(query(&server, "KEY"));
+    }
+
+    let (results, report) = batch_query.run_with_report();
+    let results: Vec<_> = results.collect();
+
+    // 4, 3 then 2 calls left: the 7 queries after those are kept from using the 2 reserved.
+    assert!(results[..3].iter().all(Result::is_ok));
+    assert!(results[3..].iter().all(|result| *result == quota_exhausted(&server)));
+    assert_eq!(server.hits(), 3);
+
+    let report = report.get();
+    assert_eq!((report.queries, report.skipped, report.quota_skipped), (10, 7, 7));
+    assert_eq!(report.total().calls, 3);
+    assert!(report.errors.is_empty());
+
+    let reset = report.quota_resets_at.unwrap() - Utc::now();
+    assert!(reset > Duration::seconds(3590) && reset <= Duration::seconds(3600), "{}", reset);
+
+    let summary = report.to_string();
+    assert!(summary.starts_with("queries: 10 (3 succeeded, 0 failed, 7 skipped)\n"));
+    assert!(summary.contains("\nquota reserved: 7 skipped, reset at "), "{}", summary);
+}
+
+#[test]
+fn earliest_reset_of_the_keys_skipped() {
+    let server = {
+        server(3, |query| {
+            if query.contains("FIRST") { Some("1900003600") } else { Some("1900000000") }
+        })
+    };
+
+    let mut batch_query = BatchQuery::new();
+    batch_query.reserve_quota(1);
+
+    for api_key in &["FIRST", "SECOND", "THIRD"] {
+        for _ in 0..3 {
+            batch_query.query(query(&server, api_key));
+        }
+    }
+
+    let (results, report) = batch_query.run_with_report();
+    let results: Vec<_> = results.collect();
+
+    for key in 0..3 {
+        assert!(results[3 * key..3 * key + 2].iter().all(Result::is_ok));
+        assert_eq!(results[3 * key + 2], quota_exhausted(&server));
+    }
+
+    let report = report.get();
+    assert_eq!((report.skipped, report.quota_skipped), (3, 3));
+    assert_eq!(report.keys.values().sum::<usize>(), 6);
+
+    let reset = report.quota_resets_at.map(|at| quandl_v3::calendar::format_timestamp(&at));
+    assert_eq!(reset.as_ref().map(|x| &x[..]), Some("2030-03-17T17:46:40.000Z"));
+}
+
+#[test]
+fn keys_without_headers_fall_back_on_their_calls() {
+    let server = MockServer::start(|_| Response::json(DATABASE_METADATA));
+
+    let mut batch_query = BatchQuery::new();
+    batch_query.limit(6, 86_400).offset(1).reserve_quota(2);
+
+    for _ in 0..6 {
+        batch_query.query(query(&server, "KEY"));
+    }
+
+    let (results, report) = batch_query.run_with_report();
+    let results: Vec<_> = results.collect();
+
+    // One call of the 6 already made elsewhere, 3 more here, and 2 kept.
+    assert!(results[..3].iter().all(Result::is_ok));
+    assert!(results[3..].iter().all(|result| *result == quota_exhausted(&server)));
+    assert_eq!(server.hits(), 3);
+
+    let report = report.get();
+    assert_eq!(report.quota_skipped, 3);
+
+    let reset = report.quota_resets_at.unwrap() - Utc::now();
+    assert!(reset > Duration::seconds(86_390) && reset <= Duration::seconds(86_400), "{}", reset);
+}
+
+#[test]
+fn quota_is_spent_without_a_reserve() {
+    let server = server(2, |_| None);
+
+    let mut batch_query = BatchQuery::new();
+
+    for _ in 0..3 {
+        batch_query.query(query(&server, "KEY"));
+    }
+
+    let (results, report) = batch_query.run_with_report();
+    assert!(results.collect::<Vec<_>>().iter().all(Result::is_ok));
+    assert_eq!(server.hits(), 3);
+
+    // Once used up, the quota only stops the batch with a reserve, whose reset is unknown here.
+    let mut batch_query = BatchQuery::new();
+    batch_query.reserve_quota(0);
+    batch_query.query(query(&server, "KEY")).query(query(&server, "KEY"));
+
+    let (results, handle) = batch_query.run_with_report();
+    let results: Vec<_> = results.collect();
+
+    assert!(results[0].is_ok());
+    assert_eq!(results[1], quota_exhausted(&server));
+    assert_eq!(server.hits(), 4);
+
+    let (report, handle) = (report.get(), handle.get());
+    assert_eq!(report.quota_skipped, 0);
+    assert_eq!((handle.quota_skipped, handle.quota_resets_at), (1, None));
+    assert!(handle.to_string().contains("\nquota reserved: 1 skipped\n"), "{}", handle);
+}
@@ -0,0 +1,129 @@
+extern crate quandl_v3;
+extern crate serde_json;
+
+mod common;
+
+use quandl_v3::Error;
+use quandl_v3::prelude::*;
+
+use common::{MockServer, Response};
+
+static DATASET_DATA: &str = include_str!("fixtures/dataset_data.json");
+
+/// 100 rows of 2 columns, 2000 bytes.
+///
+fn data() -> String {
+    (0..100).map(|x| format!("2016-01-01,{:08}\n", x)).collect()
+}
+
+/// `body` sent in a single chunk, i.e. without a `Content-Length`.
+///
+fn chunked(body: &str) -> Response {
+    Response::new(200)
+        .header("Content-Type", "text/csv")
+        .header("Transfer-Encoding", "chunked")
+        .body(format!("{:x}\r\n{}\r\n0\r\n\r\n", body.len(), body))
+}
+
+fn server() -> MockServer {
+    MockServer::start(|request| {
+        match &request.path[..] {
+            "/api/v3/datasets/WIKI/AAPL/data.csv" => Response::csv(data()),
+            "/api/v3/datasets/WIKI/MSFT/data.csv" => chunked(&data()),
+            _ => Response::not_found(),
+        }
+    })
+}
+
+fn query(server: &MockServer, dataset_code: &str, max: u64) -> DataQuery {
+    let mut query = DataQuery::new("WIKI", dataset_code);
+    query.base_url(server.url()).max_response_bytes(max);
+    query
+}
+
+fn send(query: &DataQuery) -> Result<Vec<(String, f64)>, Error> {
+    query.send()
+}
+
+#[test]
+fn advertised_sizes_are_checked_upfront() {
+    let server = server();
+
+    match send(&query(&server, "AAPL", 1_000)) {
+        Err(Error::ResponseTooLarge { limit: 1_000, observed: 2_000 }) => (),
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn sizes_are_counted_without_content_length() {
+    let server = server();
+
+    // The transfer is aborted once a byte more than allowed is received.
+    match send(&query(&server, "MSFT", 1_000)) {
+        Err(Error::ResponseTooLarge { limit: 1_000, observed: 1_001 }) => (),
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn responses_within_the_limit() {
+    let server = server();
+
+    for &dataset_code in &["AAPL", "MSFT"] {
+        assert_eq!(send(&query(&server, dataset_code, 2_000)).unwrap().len(), 100);
+    }
+
+    let error = send(&query(&server, "AAPL", 1_999)).unwrap_err();
+    assert_eq!(error.to_string(), "the response is larger than the 1999 bytes allowed (got 2000 \
+                                   bytes).");
+}
+
+#[test]
+fn largest_limit() {
+    let server = server();
+
+    for &dataset_code in &["AAPL", "MSFT"] {
+        assert_eq!(send(&query(&server, dataset_code, u64::MAX)).unwrap().len(), 100);
+    }
+}
+
+#[test]
+fn unlimited_by_default() {
+    let server = server();
+    let mut query = DataQuery::new("WIKI", "MSFT");
+    query.base_url(server.url());
+
+    assert_eq!(send(&query).unwrap().len(), 100);
+}
+
+#[test]
+fn compound_operations_limit_each_request() {
+    let body = {
+        let mut json: serde_json::Value = serde_json::from_str(DATASET_DATA).unwrap();
+        json["dataset"]["data"] = serde_json::json!([["2016-02-01", 1.0]]);
+        json.to_string()
+    };
+
+    let size = body.len() as u64;
+    let server = MockServer::start(move |_| Response::json(&body));
+
+    // `count` takes 2 calls, together larger than the limit.
+    let mut query = DataQuery::new("WIKI", "AAPL");
+    query.base_url(server.url()).max_response_bytes(size);
+
+    assert_eq!(query.count().unwrap().rows, 1);
+    assert_eq!(server.hits(), 2);
+
+    query.max_response_bytes(size - 1);
+
+    match query.count() {
+        Err(Error::ResponseTooLarge { limit, observed }) => {
+            assert_eq!((limit, observed), (size - 1, size));
+        },
+
+        other => panic!("{:?}", other),
+    }
+
+    assert_eq!(server.hits(), 3);
+}
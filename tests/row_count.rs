@@ -0,0 +1,125 @@
+extern crate quandl_v3;
+extern crate serde_json;
+
+mod common;
+
+use quandl_v3::calendar::NaiveDate;
+use quandl_v3::prelude::*;
+
+use common::{MockServer, Response};
+
+static DATASET_DATA: &str = include_str!("fixtures/dataset_data.json");
+
+fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(year, month, day).unwrap()
+}
+
+fn estimate(frequency: Frequency, first: NaiveDate, last: NaiveDate) -> u64 {
+    let count = RowCount::estimate(frequency, first, last);
+    assert!(!count.exact);
+    count.rows
+}
+
+/// Dataset response of WIKI/AAPL holding the rows `data`.
+///
+fn dataset(data: serde_json::Value) -> String {
+    let mut json: serde_json::Value = serde_json::from_str(DATASET_DATA).unwrap();
+    json["dataset"]["data"] = data;
+    json.to_string()
+}
+
+/// Server with the rows of WIKI/AAPL from 2016-02-01 (a Monday) to 2016-02-29, unless `empty`.
+///
+fn server(empty: bool) -> MockServer {
+    MockServer::start(move |request| {
+        if request.path != "/api/v3/datasets/WIKI/AAPL.json" {
+            return Response::not_found();
+        }
+
+        let data = {
+            if empty {
+                serde_json::json!([])
+            } else if request.query.contains("order=asc") {
+                serde_json::json!([["2016-02-01", 1.0]])
+            } else {
+                serde_json::json!([["2016-02-29", 2.0]])
+            }
+        };
+
+        Response::json(dataset(data))
+    })
+}
+
+#[test]
+fn daily_estimates() {
+    // Monday to Friday, then to the next Monday.
+    assert_eq!(estimate(Frequency::daily, date(2016, 2, 1), date(2016, 2, 5)), 5);
+    assert_eq!(estimate(Frequency::daily, date(2016, 2, 1), date(2016, 2, 8)), 6);
+    assert_eq!(estimate(Frequency::none, date(2016, 2, 1), date(2016, 2, 29)), 21);
+
+    // Data on weekends.
+    assert_eq!(estimate(Frequency::daily, date(2016, 2, 6), date(2016, 2, 8)), 3);
+}
+
+#[test]
+fn periodic_estimates() {
+    assert_eq!(estimate(Frequency::weekly, date(2016, 1, 3), date(2016, 1, 10)), 2);
+    assert_eq!(estimate(Frequency::weekly, date(2016, 1, 4), date(2016, 1, 10)), 1);
+    assert_eq!(estimate(Frequency::weekly, date(2015, 12, 31), date(2016, 12, 29)), 53);
+
+    assert_eq!(estimate(Frequency::monthly, date(2015, 12, 31), date(2016, 1, 31)), 2);
+    assert_eq!(estimate(Frequency::monthly, date(2015, 1, 31), date(2016, 12, 31)), 24);
+
+    assert_eq!(estimate(Frequency::quarterly, date(2015, 3, 31), date(2015, 4, 1)), 2);
+    assert_eq!(estimate(Frequency::quarterly, date(2015, 1, 1), date(2016, 12, 31)), 8);
+
+    assert_eq!(estimate(Frequency::annual, date(2015, 12, 31), date(2016, 1, 1)), 2);
+    assert_eq!(estimate(Frequency::annual, date(1980, 12, 31), date(2015, 12, 31)), 36);
+}
+
+#[test]
+fn exact_estimates() {
+    let day = date(2016, 2, 1);
+
+    for &frequency in &[Frequency::daily, Frequency::weekly, Frequency::annual] {
+        assert_eq!(RowCount::estimate(frequency, day, day), RowCount { rows: 1, exact: true });
+
+        assert_eq!(RowCount::estimate(frequency, day, date(2016, 1, 1)),
+                   RowCount { rows: 0, exact: true });
+    }
+}
+
+#[test]
+fn count_fetches_the_first_and_last_rows() {
+    let server = server(false);
+    let mut query = DataQuery::new("WIKI", "AAPL");
+    query.base_url(server.url()).api_key("key").limit(1000).order(Order::desc);
+
+    assert_eq!(query.count(), Ok(RowCount { rows: 21, exact: false }));
+
+    let queries: Vec<String> = server.requests().iter().map(|x| x.query.clone()).collect();
+
+    assert_eq!(queries.len(), 2);
+    assert!(queries[0].contains("rows=1") && queries[0].contains("order=asc"), "{}", queries[0]);
+    assert!(queries[1].contains("rows=1") && queries[1].contains("order=desc"), "{}", queries[1]);
+    assert!(queries.iter().all(|x| x.contains("api_key=key") && !x.contains("limit=")));
+
+    // Collapsed data.
+    query.collapse(Frequency::weekly);
+    assert_eq!(query.count(), Ok(RowCount { rows: 5, exact: false }));
+
+    // Capped by the query.
+    let mut query = DataQuery::new("WIKI", "AAPL");
+    query.base_url(server.url()).rows(3);
+    assert_eq!(query.count(), Ok(RowCount { rows: 3, exact: false }));
+}
+
+#[test]
+fn count_of_no_data() {
+    let server = server(true);
+    let mut query = DataQuery::new("WIKI", "AAPL");
+    query.base_url(server.url());
+
+    assert_eq!(query.count(), Ok(RowCount { rows: 0, exact: true }));
+    assert_eq!(server.hits(), 1);
+}
@@ -0,0 +1,141 @@
+extern crate quandl_v3;
+extern crate serde_json;
+
+mod common;
+
+use quandl_v3::Error;
+use quandl_v3::prelude::*;
+use quandl_v3::prelude::ColType::{Date, Number, Text};
+
+use common::{MockServer, Response};
+
+static TRICKY: &str = include_str!("fixtures/schema_tricky.csv");
+static HEADERLESS: &str = include_str!("fixtures/schema_headerless.csv");
+static HEADER_ONLY: &str = include_str!("fixtures/data_header_only.csv");
+static DATA: &str = include_str!("fixtures/data_3_columns.csv");
+
+fn column(name: Option<&str>, inferred_type: ColType, nullable: bool) -> ColumnSchema {
+    ColumnSchema { name: name.map(|x| x.to_string()), unit: None, inferred_type, nullable }
+}
+
+#[test]
+fn tricky_columns() {
+    assert_eq!(Schema::infer(TRICKY.as_bytes()).unwrap().columns, vec![
+        column(Some("Date"), Date, false),
+        column(Some("Open"), Number, false),
+        // A blank and text.
+        column(Some("Note"), Text, true),
+        // A value of nothing but spaces is blank.
+        column(Some("Ex-Date"), Date, true),
+        // Numbers, dates and text.
+        column(Some("Code"), Text, false),
+        // Unnamed, and out of reach of the last row.
+        column(None, Text, true),
+        // Never a value.
+        column(Some("Empty"), Number, true),
+    ]);
+}
+
+#[test]
+fn mixed_dates_and_numbers_are_text() {
+    let schema = Schema::infer(b"2016-03-01,20160301\n2016-02-29,2016-02-29\n").unwrap();
+    assert_eq!(schema.columns[1], column(None, Text, false));
+
+    let schema = Schema::infer(b"2016-03-01,20160301\n2016-02-29,20160229\n").unwrap();
+    assert_eq!(schema.columns[1], column(None, Number, false));
+}
+
+#[test]
+fn headerless_and_ragged_rows() {
+    assert_eq!(Schema::infer(HEADERLESS.as_bytes()).unwrap().columns, vec![
+        column(None, Date, false),
+        column(None, Number, false),
+        // The first row is too short to reach it.
+        column(None, Number, true),
+    ]);
+}
+
+#[test]
+fn samples_without_rows() {
+    assert_eq!(Schema::infer(b"").unwrap(), Schema::default());
+
+    let schema = Schema::infer(HEADER_ONLY.as_bytes()).unwrap();
+    let names: Vec<_> = schema.columns.iter().map(|x| x.name.clone().unwrap()).collect();
+
+    assert_eq!(names, ["Date", "Open", "High", "Low", "Close"]);
+    assert!(schema.columns.iter().all(|x| x.inferred_type == Number && x.nullable));
+}
+
+#[test]
+fn malformed_samples() {
+    match Schema::infer(b"Date,Open\n2016-03-01,\xff\n") {
+        Err(Error::CsvParsing { row: Some(2), .. }) => (),
+        other => panic!("expected a CSV error, got {:?}", other),
+    }
+}
+
+#[test]
+fn serialized() {
+    let schema = Schema::infer(b"Date,Close\n2016-03-01,\n").unwrap();
+
+    assert_eq!(serde_json::to_value(&schema).unwrap(), serde_json::json!({
+        "columns": [
+            {"name": "Date", "unit": null, "inferred_type": "date", "nullable": false},
+            {"name": "Close", "unit": null, "inferred_type": "number", "nullable": true},
+        ],
+    }));
+
+    let json = serde_json::to_string(&schema).unwrap();
+    assert_eq!(serde_json::from_str::<Schema>(&json).unwrap(), schema);
+
+    // Schemas serialized before units were inferred.
+    let json = r#"{"columns": [{"name": "Close", "inferred_type": "number", "nullable": true}]}"#;
+    assert_eq!(serde_json::from_str::<Schema>(json).unwrap().columns[0].unit, None);
+}
+
+#[test]
+fn units_from_column_names() {
+    let schema = Schema::infer(b"Date,Value (USD mm),Rate %,Price (Close)
+2016-03-01,1,2,3
+");
+    let units: Vec<_> = schema.unwrap().columns.into_iter().map(|x| x.unit).collect();
+    assert_eq!(units, vec![None, Some("USD mm".to_string()), Some("%".to_string()), None]);
+}
+
+#[test]
+fn inferred_from_a_sample_with_column_names() {
+    let server = MockServer::start(|_| Response::csv(format!("Date,Open,Close\n{}", DATA)));
+
+    let mut query = DataQuery::new("WIKI", "AAPL");
+    query.base_url(server.url()).api_key("KEY").limit(100);
+
+    let schema = query.infer_schema(5).unwrap();
+
+    assert_eq!(schema.columns, vec![
+        column(Some("Date"), Date, false),
+        column(Some("Open"), Number, false),
+        column(Some("Close"), Number, false),
+    ]);
+
+    let request = &server.requests()[0];
+    assert_eq!(request.path, "/api/v3/datasets/WIKI/AAPL/data.csv");
+    assert_eq!(request.query, "exclude_column_names=false&api_key=KEY&rows=5");
+
+    // The query itself still asks for the rows alone.
+    assert!(ApiCall::<Vec<DataRow>>::url(&query).contains("exclude_column_names=true&"));
+
+    // Rows of another width are then caught.
+    query.expected_width_from_schema(&schema);
+    assert!(query.decode::<(String, f64, f64)>(DATA.as_bytes()).is_ok());
+
+    match query.decode::<(String, f64)>(b"2016-03-01,1.5\n") {
+        Err(Error::CsvParsing { row: Some(1), .. }) => (),
+        other => panic!("expected a CSV error, got {:?}", other),
+    }
+}
+
+#[test]
+#[should_panic(expected = "sample_rows: 0")]
+fn samples_must_not_be_empty() {
+    let _ = DataQuery::new("WIKI", "AAPL").infer_schema(0);
+}
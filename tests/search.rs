@@ -0,0 +1,95 @@
+extern crate quandl_v3;
+extern crate serde_json;
+extern crate url;
+
+mod common;
+
+use quandl_v3::prelude::*;
+
+use common::{MockServer, Response};
+
+static DATASET_SEARCH: &str = include_str!("fixtures/dataset_search.json");
+static DATABASE_LIST: &str = include_str!("fixtures/database_list.json");
+
+#[test]
+fn server_sees_phrases_and_exclusions() {
+    let server = {
+        MockServer::routes(vec![("/api/v3/datasets.json", Response::json(DATASET_SEARCH))])
+    };
+
+    let mut search = DatasetSearch::new("WIKI");
+    search.base_url(server.url()).query(["oil"]).phrase("crude oil").exclude("gasoline");
+
+    assert!(search.send().is_ok());
+
+    let request = &server.requests()[0];
+    assert_eq!(request.query, "query=oil+%22crude%20oil%22+-gasoline&database_code=WIKI");
+
+    let query: Vec<(String, String)> = {
+        url::form_urlencoded::parse(request.query.as_bytes()).into_owned().collect()
+    };
+
+    assert_eq!(query[0], ("query".to_string(), "oil \"crude oil\" -gasoline".to_string()));
+}
+
+#[test]
+fn database_search_without_keywords() {
+    let server = {
+        MockServer::routes(vec![("/api/v3/databases.json", Response::json(DATABASE_LIST))])
+    };
+
+    let mut search = DatabaseSearch::new();
+    search.base_url(server.url()).per_page(2);
+
+    let list = search.send().unwrap();
+    assert_eq!(list.meta.query, "");
+    assert_eq!(list.databases.len(), 2);
+    assert_eq!(server.requests()[0].query, "per_page=2");
+}
+
+/// A page of `per_page` datasets, each copied from the fixture with a description of about
+/// `description` bytes, non-ASCII characters and escapes included.
+///
+fn large_page(per_page: usize, description: usize) -> String {
+    let mut page: serde_json::Value = serde_json::from_str(DATASET_SEARCH).unwrap();
+    let dataset = page["datasets"][0].clone();
+    let line = "Prix \u{e0} la cl\u{f4}ture, \"ajust\u{e9}s\" \u{2014} see quandl.com.\n";
+
+    page["datasets"] = {
+        (0..per_page)
+            .map(|n| {
+                let mut dataset = dataset.clone();
+                dataset["dataset_code"] = serde_json::json!(format!("CODE{}", n));
+                dataset["description"] = serde_json::json!(line.repeat(description / line.len()));
+                dataset
+            })
+            .collect()
+    };
+
+    page["meta"]["per_page"] = serde_json::json!(per_page);
+    serde_json::to_string(&page).unwrap()
+}
+
+#[test]
+fn large_pages_decode_as_strings_do() {
+    let body = large_page(100, 40_000);
+    assert!(body.len() > 4_000_000, "{}", body.len());
+
+    let server = MockServer::routes(vec![("/api/v3/datasets.json", Response::json(&body[..]))]);
+
+    let mut search = DatasetSearch::new("WIKI");
+    search.base_url(server.url()).per_page(100);
+
+    let list = search.send().unwrap();
+    assert_eq!(list, serde_json::from_str::<DatasetList>(&body).unwrap());
+    assert_eq!(list.datasets.len(), 100);
+    assert!(list.datasets[99].description.starts_with("Prix \u{e0} la cl\u{f4}ture, \"ajust"));
+
+    // So do the listings of databases.
+    let server = {
+        MockServer::routes(vec![("/api/v3/databases.json", Response::json(DATABASE_LIST))])
+    };
+
+    assert_eq!(DatabaseSearch::new().base_url(server.url()).send().unwrap(),
+               serde_json::from_str::<DatabaseList>(DATABASE_LIST).unwrap());
+}
@@ -0,0 +1,137 @@
+#![cfg(feature = "batch")]
+
+extern crate quandl_v3;
+
+mod common;
+
+use std::thread::sleep;
+use std::time::Duration;
+
+use quandl_v3::prelude::*;
+
+use common::{MockServer, Response};
+
+static DATABASE_METADATA: &str = include_str!("fixtures/database_metadata.json");
+
+/// Delay of the server before answering for each database, in milliseconds. The first call of
+/// each worker takes longer than the others (to set up its client), hence the fast ones first.
+///
+static DELAYS: [(&str, u64); 6] = {
+    [("WIKI", 0), ("ICE", 0), ("JODI", 0), ("FRED", 900), ("EIA", 600), ("CME", 400)]
+};
+
+fn metadata(code: &str) -> String {
+    DATABASE_METADATA.replace("\"WIKI\"", &format!("\"{}\"", code))
+}
+
+fn server() -> MockServer {
+    MockServer::start(|request| {
+        let code = request.path.trim_start_matches("/api/v3/databases/").trim_end_matches(".json");
+        let &(_, delay) = DELAYS.iter().find(|(x, _)| *x == code).unwrap();
+
+        sleep(Duration::from_millis(delay));
+        Response::json(metadata(code))
+    })
+}
+
+fn report(server: &MockServer, slow_queries: Option<usize>) -> BatchReport {
+    let mut batch_query = BatchQuery::new();
+
+    for &(code, _) in &DELAYS {
+        let mut query = DatabaseMetadataQuery::new(code);
+        query.base_url(server.url()).api_key("key");
+        batch_query.query(query);
+    }
+
+    batch_query.threads(3).concurrent_calls();
+
+    if let Some(n) = slow_queries {
+        batch_query.slow_queries(n);
+    }
+
+    let mut iterator = batch_query.run();
+    assert!(iterator.by_ref().all(|result| result.is_ok()));
+    iterator.report()
+}
+
+#[test]
+fn slowest_queries_are_logged() {
+    let server = server();
+    let report = report(&server, Some(3));
+
+    let codes: Vec<&str> = report.slowest.iter().map(|x| &x.database_code[..]).collect();
+    assert_eq!(codes, vec!["FRED", "EIA", "CME"]);
+
+    for (query, &delay) in report.slowest.iter().zip(&[900, 600, 400]) {
+        assert!(query.duration >= Duration::from_millis(delay), "{:?}", query);
+        assert_eq!(query.dataset_code, "");
+        assert_eq!(query.bytes, metadata(&query.database_code).len() as u64);
+    }
+
+    let summary = report.to_string();
+    assert!(summary.contains("\nslowest: FRED "), "{}", summary);
+    assert!(summary.contains("\nlatency: p50 "), "{}", summary);
+}
+
+#[test]
+fn latency_histogram() {
+    let server = server();
+    let latency = report(&server, None).latency;
+
+    assert_eq!(latency.count(), 6);
+    assert!(latency.max().unwrap() >= Duration::from_millis(900));
+    assert_eq!(latency.quantile(1.0), latency.max());
+    assert_eq!(latency.buckets().iter().map(|&(_, count)| count).sum::<usize>(), 6);
+}
+
+#[test]
+fn latency_buckets() {
+    let mut latency = LatencyHistogram::default();
+
+    assert_eq!(latency.count(), 0);
+    assert_eq!(latency.max(), None);
+    assert_eq!(latency.quantile(0.5), None);
+
+    for &millis in &[0, 1, 150, 200, 201, 400, 600, 900, 250_000] {
+        latency.record(Duration::from_millis(millis));
+    }
+
+    let buckets = latency.buckets();
+    let count = |bound: Option<u64>| {
+        let bound = bound.map(Duration::from_millis);
+        buckets.iter().find(|&&(upper_bound, _)| upper_bound == bound).unwrap().1
+    };
+
+    assert_eq!(buckets.len(), 17);
+    assert_eq!(buckets[7].0, Some(Duration::from_millis(200)));
+
+    // Bounds are inclusive.
+    assert_eq!(count(Some(1)), 2);
+    assert_eq!(count(Some(200)), 2);
+    assert_eq!(count(Some(500)), 2);
+    assert_eq!(count(Some(1_000)), 2);
+    assert_eq!(count(None), 1);
+
+    assert_eq!(latency.count(), 9);
+    assert_eq!(latency.max(), Some(Duration::from_millis(250_000)));
+
+    // Quantiles are the upper bound of their bucket, or the longest duration counted.
+    assert_eq!(latency.quantile(0.0), Some(Duration::from_millis(1)));
+    assert_eq!(latency.quantile(0.5), Some(Duration::from_millis(500)));
+    assert_eq!(latency.quantile(0.8), Some(Duration::from_millis(1_000)));
+    assert_eq!(latency.quantile(1.0), latency.max());
+
+    let mut single = LatencyHistogram::default();
+    single.record(Duration::from_millis(3));
+
+    assert_eq!(single.quantile(0.5), Some(Duration::from_millis(3)));
+}
+
+#[test]
+fn log_size() {
+    let server = server();
+
+    assert_eq!(report(&server, None).slowest.len(), 6);
+    assert!(report(&server, Some(0)).slowest.is_empty());
+    assert_eq!(report(&server, Some(1)).slowest[0].database_code, "FRED");
+}
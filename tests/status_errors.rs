@@ -0,0 +1,173 @@
+extern crate chrono;
+extern crate quandl_v3;
+
+mod common;
+
+use std::time::Duration;
+
+use quandl_v3::Error;
+use quandl_v3::backoff::is_retryable;
+use quandl_v3::prelude::*;
+
+use common::{MockServer, Response};
+
+static RATE_LIMITED: &str = r#"{"quandl_error":{"code":"QELx01","message":"Slow down."}}"#;
+
+static INVALID_KEY: &str = {
+    r#"{"quandl_error":{"code":"QEAx01","message":"Incorrect authentication credentials."}}"#
+};
+
+/// Page served by a proxy in front of Quandl, rather than by Quandl itself.
+///
+fn html(status: u16, title: &str) -> Response {
+    Response::new(status)
+        .header("Content-Type", "text/html; charset=utf-8")
+        .body(format!("<html>\n  <head><title>{}</title></head>\n  <body>\n    <h1>{}</h1>\n  \
+                       </body>\n</html>\n",
+                      title,
+                      title))
+}
+
+fn json(status: u16, body: &str) -> Response {
+    Response::new(status).header("Content-Type", "application/json").body(body)
+}
+
+fn send(response: Response) -> Error {
+    let server = MockServer::start(move |_| response.clone());
+    DatasetMetadataQuery::new("WIKI", "AAPL").base_url(server.url()).send().unwrap_err()
+}
+
+#[test]
+fn rate_limits_with_json_bodies() {
+    let error = send(json(429, RATE_LIMITED).header("Retry-After", "30"));
+
+    match error {
+        Error::RateLimitExceeded { retry_after, api_error: Some(ref e), ref snippet } => {
+            assert_eq!(retry_after, Some(Duration::from_secs(30)));
+            assert_eq!(e.quandl_error.code, "QELx01");
+            assert_eq!(snippet, RATE_LIMITED);
+        },
+
+        ref other => panic!("{:?}", other),
+    }
+
+    assert!(error.is_rate_limited());
+    assert!(is_retryable(&error));
+    assert_eq!(error.to_string(), "rate limit exceeded: Slow down. (retry after 30s)");
+}
+
+#[test]
+fn rate_limits_with_html_bodies() {
+    let error = send(html(429, "429 Too Many Requests"));
+
+    match error {
+        Error::RateLimitExceeded { retry_after: None, api_error: None, ref snippet } => {
+            assert_eq!(snippet, "<html> <head><title>429 Too Many Requests</title></head> <body>");
+        },
+
+        ref other => panic!("{:?}", other),
+    }
+
+    assert!(is_retryable(&error));
+    assert_eq!(error.api_error(), None);
+    assert!(error.to_string().starts_with("rate limit exceeded: '<html> <head>"), "{}", error);
+}
+
+#[test]
+fn retry_after_dates() {
+    let date = chrono::Utc::now() + chrono::Duration::seconds(120);
+    let error = send(html(429, "Slow down").header("Retry-After", &date.to_rfc2822()));
+
+    let retry_after = error.retry_after().unwrap();
+    assert!(retry_after > Duration::from_secs(110) && retry_after <= Duration::from_secs(120));
+
+    // A date already passed needs no wait, and an unreadable value is ignored.
+    let error = send(html(429, "Slow down").header("Retry-After", "Wed, 21 Oct 2015 07:28:00 GMT"));
+    assert_eq!(error.retry_after(), Some(Duration::from_secs(0)));
+
+    let error = send(html(429, "Slow down").header("Retry-After", "soon"));
+    assert!(error.is_rate_limited());
+    assert_eq!(error.retry_after(), None);
+}
+
+#[test]
+fn access_denied_with_json_bodies() {
+    for status in &[401, 403] {
+        let error = send(json(*status, INVALID_KEY));
+
+        match error {
+            Error::AccessDenied { status: reported, api_error: Some(ref e), .. } => {
+                assert_eq!(reported, *status);
+                assert_eq!(e.quandl_error.code, "QEAx01");
+            },
+
+            ref other => panic!("{:?}", other),
+        }
+
+        assert!(error.is_access_denied());
+        assert!(!is_retryable(&error));
+    }
+}
+
+#[test]
+fn access_denied_with_html_bodies() {
+    let error = send(html(403, "403 Forbidden"));
+
+    match error {
+        Error::AccessDenied { status: 403, api_error: None, ref snippet } => {
+            assert!(snippet.starts_with("<html> <head><title>403 Forbidden"), "{}", snippet);
+        },
+
+        ref other => panic!("{:?}", other),
+    }
+
+    assert!(error.to_string().starts_with("access denied (403): '<html>"), "{}", error);
+}
+
+#[test]
+fn not_found_with_json_bodies() {
+    let error = send(Response::not_found());
+
+    match error {
+        Error::NotFound { api_error: Some(ref e), .. } => assert_eq!(e.quandl_error.code, "QECx02"),
+        ref other => panic!("{:?}", other),
+    }
+
+    assert!(error.is_not_found());
+    assert_eq!(error.api_error().unwrap().quandl_error.code, "QECx02");
+    assert_eq!(error.to_string(), "not found: You have submitted an incorrect Quandl code. \
+                                   Please check your Quandl codes and try again.");
+}
+
+#[test]
+fn not_found_with_html_bodies() {
+    let error = send(html(404, "404 Not Found"));
+    assert!(matches!(error, Error::NotFound { api_error: None, .. }), "{:?}", error);
+
+    // Empty bodies alike.
+    let error = send(Response::new(404));
+    assert_eq!(error, Error::NotFound { api_error: None, snippet: String::new() });
+    assert_eq!(error.to_string(), "not found: empty reply");
+}
+
+#[test]
+fn other_statuses_keep_the_api_error() {
+    let error = send(json(422, INVALID_KEY));
+    assert!(matches!(error, Error::ApiCallFailed(_)), "{:?}", error);
+
+    let error = send(html(500, "500 Internal Server Error"));
+    assert!(matches!(error, Error::JsonParsing { .. }), "{:?}", error);
+}
+
+#[test]
+fn downloads_to_files() {
+    let server = MockServer::start(|_| html(429, "Slow down").header("Retry-After", "5"));
+    let path = std::env::temp_dir().join("quandl-v3-status-errors.zip");
+
+    let mut query = DatabaseDownloadQuery::new("WIKI");
+    query.base_url(server.url());
+
+    let error = query.download_to_file_resumable(&path).unwrap_err();
+    assert!(error.is_rate_limited());
+    assert_eq!(error.retry_after(), Some(Duration::from_secs(5)));
+}
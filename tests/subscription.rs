@@ -0,0 +1,150 @@
+extern crate quandl_v3;
+
+mod common;
+
+use quandl_v3::Error;
+use quandl_v3::prelude::*;
+
+use common::{MockServer, Response};
+
+/// Quandl's reply to a query of `EOD/AAPL`, End of Day US Stock Prices being a premium database,
+/// with a key which is not subscribed to it.
+///
+static PREMIUM_DENIED: &str = include_str!("fixtures/error_premium_eod.json");
+
+fn denied() -> Response {
+    Response::new(403).header("Content-Type", "application/json").body(PREMIUM_DENIED)
+}
+
+fn subscription_required(database_code: &str) -> Error {
+    Error::SubscriptionRequired { database_code: database_code.to_string() }
+}
+
+#[test]
+fn premium_datasets_require_a_subscription() {
+    let server = MockServer::start(|_| denied());
+
+    let mut data = DataQuery::new("EOD", "AAPL");
+    data.base_url(server.url()).api_key("FREE_KEY");
+
+    let mut metadata = DatasetMetadataQuery::new("EOD", "AAPL");
+    metadata.base_url(server.url()).api_key("FREE_KEY");
+
+    let mut dataset = DataAndMetadataQuery::new("EOD", "AAPL");
+    dataset.base_url(server.url()).api_key("FREE_KEY");
+
+    assert_eq!(ApiCall::<Vec<(String, f64)>>::send(&data), Err(subscription_required("EOD")));
+    assert_eq!(metadata.send(), Err(subscription_required("EOD")));
+    let result = ApiCall::<Dataset<(String, f64)>>::send(&dataset);
+    assert_eq!(result, Err(subscription_required("EOD")));
+    assert_eq!(metadata.encoded_data(), Err(subscription_required("EOD")));
+
+    assert_eq!(subscription_required("EOD").to_string(),
+               "subscription required for database 'EOD', see https://www.quandl.com/data/EOD to \
+                subscribe to it.");
+}
+
+#[test]
+fn premium_bulk_downloads_require_a_subscription() {
+    let server = MockServer::start(|_| denied());
+
+    let path = std::env::temp_dir().join(format!("quandl-v3-premium-{}.zip", std::process::id()));
+
+    let mut query = DatabaseDownloadQuery::new("EOD");
+    query.base_url(server.url()).api_key("FREE_KEY");
+
+    assert_eq!(query.download_to_file_resumable(&path), Err(subscription_required("EOD")));
+    assert!(!path.exists());
+}
+
+#[test]
+fn database_is_reported_as_sent() {
+    let server = MockServer::start(|_| denied());
+
+    let mut query = DatasetMetadataQuery::new("eod", "aapl");
+    query.base_url(server.url()).auto_uppercase(true);
+
+    assert_eq!(query.send(), Err(subscription_required("EOD")));
+}
+
+#[test]
+fn other_parameter_errors_are_not_premium() {
+    let server = MockServer::start(|_| {
+        Response::new(422).header("Content-Type", "application/json").body(PREMIUM_DENIED)
+    });
+
+    let mut query = DatasetMetadataQuery::new("EOD", "AAPL");
+    query.base_url(server.url());
+
+    match query.send() {
+        Err(Error::ApiCallFailed(e)) => assert_eq!(e.quandl_error.code, "QEPx04"),
+        x => panic!("{:?}", x),
+    }
+}
+
+#[test]
+fn missing_datasets_are_not_premium() {
+    let server = MockServer::start(|_| Response::not_found());
+
+    let mut query = DatasetMetadataQuery::new("EOD", "NOT_A_TICKER");
+    query.base_url(server.url());
+
+    match query.send() {
+        Err(Error::NotFound { api_error: Some(e), .. }) => {
+            assert_eq!(e.quandl_error.code, "QECx02")
+        },
+
+        x => panic!("{:?}", x),
+    }
+}
+
+#[test]
+fn queries_without_a_database_keep_the_api_error() {
+    let server = MockServer::start(|_| denied());
+
+    let mut query = DatabaseSearch::new();
+    query.base_url(server.url());
+
+    match query.send() {
+        Err(Error::AccessDenied { status: 403, api_error: Some(e), .. }) => {
+            assert_eq!(e.quandl_error.code, "QEPx04")
+        },
+
+        x => panic!("{:?}", x),
+    }
+}
+
+#[cfg(feature = "batch")]
+#[test]
+fn batch_reports_count_subscriptions_required() {
+    let server = MockServer::start(|request| {
+        if request.path.contains("/EOD/") {
+            denied()
+        } else {
+            Response::not_found()
+        }
+    });
+
+    let mut batch_query = BatchQuery::new();
+
+    for (database_code, dataset_code) in &[("EOD", "AAPL"), ("EOD", "MSFT"), ("WIKI", "NOPE")] {
+        let mut query = DatasetMetadataQuery::new(*database_code, *dataset_code);
+        query.base_url(server.url()).api_key("FREE_KEY");
+        batch_query.query(query);
+    }
+
+    let (iterator, handle) = batch_query.run_with_report();
+    assert_eq!(iterator.filter(|x| *x == Err(subscription_required("EOD"))).count(), 2);
+
+    let report = handle.get();
+
+    assert_eq!(report.errors.iter().collect::<Vec<_>>(), vec![
+        (&"NotFound".to_string(), &1),
+        (&"SubscriptionRequired".to_string(), &2),
+    ]);
+
+    assert_eq!(report.quandl_errors.iter().collect::<Vec<_>>(), vec![(&"QECx02".to_string(), &1)]);
+    let subscriptions_required: Vec<_> = report.subscriptions_required.iter().collect();
+    assert_eq!(subscriptions_required, vec![(&"EOD".to_string(), &2)]);
+    assert!(report.to_string().contains("\nsubscriptions required: EOD 2\n"));
+}
@@ -0,0 +1,185 @@
+extern crate quandl_v3;
+
+use std::collections::HashMap;
+
+use quandl_v3::prelude::*;
+use quandl_v3::Error;
+
+fn vars<'a>(pairs: &[(&'a str, &'a str)]) -> HashMap<&'a str, &'a str> {
+    pairs.iter().cloned().collect()
+}
+
+fn message(error: Error) -> String {
+    match error {
+        Error::ParsingFailed(message) => message,
+        e => panic!("unexpected error: {:?}", e),
+    }
+}
+
+fn template() -> QueryTemplate {
+    let mut template = QueryTemplate::new("WIKI/{code}");
+
+    template
+        .start_date_template("{start}")
+        .end_date_template("{end}")
+        .order(Order::asc)
+        .api_key("secret");
+
+    template
+}
+
+#[test]
+fn substitutes_all_fields() {
+    let query = {
+        template()
+            .instantiate(&vars(&[("code", "AAPL"), ("start", "2016-01-01"), ("end", "2016-12-31")]))
+            .unwrap()
+    };
+
+    let expected = {
+        let mut query = DataQuery::new("WIKI", "AAPL");
+
+        query
+            .order(Order::asc)
+            .api_key("secret")
+            .start_date(2016, 1, 1)
+            .end_date(2016, 12, 31);
+
+        query
+    };
+
+    assert_eq!(query, expected);
+}
+
+#[test]
+fn placeholders_inside_literals() {
+    let mut template = QueryTemplate::new("{database}/{ticker}_{suffix}");
+    template.start_date_template("{year}-01-01");
+
+    assert_eq!(template.variables().unwrap().into_iter().collect::<Vec<_>>(),
+               vec!["database", "suffix", "ticker", "year"]);
+
+    let query = {
+        template
+            .instantiate(&vars(&[("database", "FRED"),
+                                 ("ticker", "GDP"),
+                                 ("suffix", "Q"),
+                                 ("year", "2001")]))
+            .unwrap()
+    };
+
+    let expected = {
+        let mut query = DataQuery::new("FRED", "GDP_Q");
+        query.start_date(2001, 1, 1);
+        query
+    };
+
+    assert_eq!(query, expected);
+}
+
+#[test]
+fn template_without_placeholders() {
+    let query = QueryTemplate::new("WIKI/AAPL").instantiate(&HashMap::new()).unwrap();
+    assert_eq!(query, DataQuery::new("WIKI", "AAPL"));
+}
+
+#[test]
+fn missing_variables_are_named() {
+    let error = template().instantiate(&vars(&[("code", "AAPL")])).unwrap_err();
+    assert_eq!(message(error), "unbound template variables: end, start.");
+}
+
+#[test]
+fn extra_variables_are_named() {
+    let error = {
+        template()
+            .instantiate(&vars(&[("code", "AAPL"),
+                                 ("start", "2016-01-01"),
+                                 ("end", "2016-12-31"),
+                                 ("ticker", "AAPL"),
+                                 ("collapse", "weekly")]))
+            .unwrap_err()
+    };
+
+    assert_eq!(message(error), "unknown template variables: collapse, ticker.");
+}
+
+#[test]
+fn partial_binding() {
+    let partial = {
+        template().bind(&vars(&[("start", "2016-01-01"), ("end", "2016-12-31")])).unwrap()
+    };
+
+    assert_eq!(partial.variables().unwrap().into_iter().collect::<Vec<_>>(), vec!["code"]);
+
+    let aapl = partial.instantiate(&vars(&[("code", "AAPL")])).unwrap();
+    let msft = partial.instantiate(&vars(&[("code", "MSFT")])).unwrap();
+
+    assert_eq!(aapl.dataset_code(), "AAPL");
+    assert_eq!(msft.dataset_code(), "MSFT");
+
+    assert_eq!(aapl, {
+        template()
+            .instantiate(&vars(&[("code", "AAPL"), ("start", "2016-01-01"), ("end", "2016-12-31")]))
+            .unwrap()
+    });
+
+    // Variables already bound are no longer placeholders.
+    let error = partial.instantiate(&vars(&[("code", "AAPL"), ("start", "2017-01-01")]));
+    assert_eq!(message(error.unwrap_err()), "unknown template variables: start.");
+}
+
+#[test]
+fn codes_must_have_a_single_separator() {
+    let all = |code| vars(&[("code", code), ("start", "2016-01-01"), ("end", "2016-12-31")]);
+
+    for code in &["AAPL/X", "", "/"] {
+        let error = message(template().instantiate(&all(code)).unwrap_err());
+        assert!(error.contains("not of the form DATABASE/DATASET"), "{}", error);
+        assert!(error.contains(&format!("'WIKI/{}'", code)), "{}", error);
+    }
+
+    let error = QueryTemplate::new("{code}").instantiate(&vars(&[("code", "AAPL")])).unwrap_err();
+    assert!(message(error).contains("code 'AAPL' (from template '{code}')"));
+
+    let query = QueryTemplate::new("{code}").instantiate(&vars(&[("code", "WIKI/AAPL")])).unwrap();
+    assert_eq!(query, DataQuery::new("WIKI", "AAPL"));
+}
+
+#[test]
+fn dates_must_be_valid() {
+    for date in &["2016-02-30", "2016-1-01", "yesterday", "2016-01-01T00:00"] {
+        let error = {
+            template()
+                .instantiate(&vars(&[("code", "AAPL"), ("start", date), ("end", "2016-12-31")]))
+                .unwrap_err()
+        };
+
+        assert_eq!(message(error),
+                   format!("start date '{}' (from template '{{start}}') is not a valid \
+                            YYYY-MM-DD date.", date));
+    }
+}
+
+#[test]
+fn values_cannot_introduce_placeholders() {
+    let error = {
+        template()
+            .bind(&vars(&[("code", "{start}")]))
+            .unwrap_err()
+    };
+
+    assert_eq!(message(error), "value '{start}' of template variable code contains braces.");
+}
+
+#[test]
+fn malformed_templates() {
+    for (code, reason) in &[("WIKI/{code", "unmatched '{'"),
+                            ("WIKI/code}", "unmatched '}'"),
+                            ("WIKI/{}", "invalid placeholder name ''"),
+                            ("WIKI/{co de}", "invalid placeholder name 'co de'")]
+    {
+        let error = QueryTemplate::new(code).instantiate(&HashMap::new()).unwrap_err();
+        assert_eq!(message(error), format!("malformed template '{}': {}.", code, reason));
+    }
+}
@@ -0,0 +1,122 @@
+extern crate quandl_v3;
+
+mod common;
+
+use quandl_v3::{DownloadErrorKind, Error};
+use quandl_v3::prelude::*;
+
+use common::{MockServer, Response};
+
+static PINNED: &[u8] = include_bytes!("fixtures/tls/pinned.der");
+static PINNED_PEM: &[u8] = include_bytes!("fixtures/tls/pinned.pem");
+static PINNED_KEY: &[u8] = include_bytes!("fixtures/tls/pinned.key");
+static OTHER: &[u8] = include_bytes!("fixtures/tls/other.der");
+static DATABASE_METADATA: &str = include_str!("fixtures/database_metadata.json");
+
+/// Server presenting the self-signed `pinned` certificate, issued for `localhost` and `127.0.0.1`.
+///
+fn server() -> MockServer {
+    let identity = native_tls::Identity::from_pkcs8(PINNED_PEM, PINNED_KEY).unwrap();
+    MockServer::start_tls(identity, |_| Response::json(DATABASE_METADATA))
+}
+
+fn query(server: &MockServer, config: Option<&ClientConfig>) -> DatabaseMetadataQuery {
+    let mut query = DatabaseMetadataQuery::new("WIKI");
+    query.base_url(server.url()).api_key("key");
+
+    if let Some(config) = config {
+        query.client_config(config);
+    }
+
+    query
+}
+
+fn pinned(certificates: &[&[u8]]) -> ClientConfig {
+    let mut config = ClientConfig::new();
+    config.pin_certificates(certificates.iter().map(|x| x.to_vec()).collect());
+    config
+}
+
+#[test]
+fn pinned_and_matching() {
+    let server = server();
+
+    let metadata = query(&server, Some(&pinned(&[PINNED]))).send().unwrap();
+    assert_eq!(metadata.database_code, "WIKI");
+
+    // Any of the pinned certificates will do.
+    let metadata = query(&server, Some(&pinned(&[OTHER, PINNED]))).send().unwrap();
+    assert_eq!(metadata.database_code, "WIKI");
+
+    assert_eq!(server.hits(), 2);
+}
+
+#[test]
+fn pinned_and_mismatched() {
+    let server = server();
+    let error = query(&server, Some(&pinned(&[OTHER]))).send().unwrap_err();
+
+    assert_eq!(error.download_kind(), Some(DownloadErrorKind::UntrustedCertificate));
+    assert!(!DownloadErrorKind::UntrustedCertificate.is_retryable());
+    assert_eq!(server.hits(), 0);
+}
+
+#[test]
+fn unpinned_uses_system_roots() {
+    let server = server();
+
+    // The self-signed certificate is not among the system's roots.
+    for config in &[None, Some(ClientConfig::new())] {
+        let error = query(&server, config.as_ref()).send().unwrap_err();
+        assert_eq!(error.download_kind(), Some(DownloadErrorKind::Tls));
+    }
+
+    assert_eq!(server.hits(), 0);
+}
+
+#[test]
+fn pinning_applies_to_every_call_path() {
+    let server = server();
+    let config = pinned(&[OTHER]);
+
+    let mut data = DataQuery::new("WIKI", "AAPL");
+    data.base_url(server.url()).client_config(&config);
+
+    match ApiCall::<Vec<(String, f64)>>::encoded_data(&data) {
+        Err(Error::DownloadFailed(e)) => {
+            assert_eq!(e.kind(), DownloadErrorKind::UntrustedCertificate);
+        },
+
+        other => panic!("expected a download failure, got {:?}", other),
+    }
+
+    let mut download = DatabaseDownloadQuery::new("WIKI");
+    download.base_url(server.url()).client_config(&config);
+
+    let path = std::env::temp_dir().join(format!("quandl-v3-tls-{}.zip", std::process::id()));
+    let error = download.download_to_file_resumable(&path).unwrap_err();
+    assert_eq!(error.download_kind(), Some(DownloadErrorKind::UntrustedCertificate));
+}
+
+#[test]
+fn invalid_pinned_certificate() {
+    let server = server();
+    let error = query(&server, Some(&pinned(&[PINNED, b"not DER"]))).send().unwrap_err();
+
+    match error {
+        Error::DownloadFailed(ref e) => {
+            assert_eq!(e.kind(), DownloadErrorKind::Tls);
+            assert!(e.message().starts_with("invalid pinned certificate 1: "), "{}", e);
+        },
+
+        ref other => panic!("expected a download failure, got {:?}", other),
+    }
+
+    assert_eq!(server.hits(), 0);
+}
+
+#[test]
+#[should_panic(expected = "certificates: []")]
+fn at_least_one_certificate_is_pinned() {
+    ClientConfig::new().pin_certificates(vec![]);
+}
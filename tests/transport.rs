@@ -0,0 +1,135 @@
+extern crate quandl_v3;
+
+mod common;
+
+use quandl_v3::Error;
+use quandl_v3::prelude::*;
+
+use common::{MockServer, Response};
+
+static DATABASE_METADATA: &str = include_str!("fixtures/database_metadata.json");
+static DATASET_SEARCH: &str = include_str!("fixtures/dataset_search.json");
+static HTML: &str = "<!DOCTYPE html><html><head><title>Proxy error</title></head></html>";
+
+fn html<B: AsRef<[u8]>>(body: B) -> Response {
+    Response::new(200).header("Content-Type", "text/html").body(body)
+}
+
+fn parsing_error<T: std::fmt::Debug>(result: quandl_v3::Result<T>) -> String {
+    match result {
+        Err(Error::ParsingFailed(message)) => message,
+        other => panic!("expected a parsing error, got {:?}", other),
+    }
+}
+
+#[test]
+fn base_url_is_used_for_queries() {
+    let mut query = DatabaseMetadataQuery::new("WIKI");
+    query.base_url("http://localhost:8080/api/v3/");
+
+    assert_eq!(query.url(), "http://localhost:8080/api/v3/databases/WIKI.json");
+}
+
+#[test]
+fn json_with_json_content_type() {
+    let server = MockServer::routes(vec![
+        ("/api/v3/databases/WIKI.json", Response::json(DATABASE_METADATA)),
+        ("/api/v3/datasets.json", Response::json(DATASET_SEARCH)),
+    ]);
+
+    let metadata = DatabaseMetadataQuery::new("WIKI").base_url(server.url()).send().unwrap();
+    assert_eq!(metadata.database_code, "WIKI");
+
+    let list = DatasetSearch::new("WIKI").base_url(server.url()).send().unwrap();
+    assert_eq!(list.datasets[0].dataset_code, "AAPL");
+}
+
+#[test]
+fn default_send_rejects_html() {
+    let server = MockServer::routes(vec![
+        ("/api/v3/datasets.json", html(HTML)),
+    ]);
+
+    let message = parsing_error(DatasetSearch::new("WIKI").base_url(server.url()).send());
+
+    assert_eq!(message, "expected application/json, got text/html; body starts with: \
+                         <!DOCTYPE html><html><head><title>Proxy error</title></head></ht");
+}
+
+#[test]
+fn metadata_send_rejects_html() {
+    let server = MockServer::routes(vec![
+        ("/api/v3/databases/WIKI.json", html(HTML)),
+    ]);
+
+    let metadata = DatabaseMetadataQuery::new("WIKI").base_url(server.url()).send();
+    let message = parsing_error(metadata);
+
+    assert!(message.starts_with("expected application/json, got text/html;"), "{}", message);
+}
+
+#[test]
+fn data_send_checks_for_csv() {
+    let server = MockServer::routes(vec![
+        ("/api/v3/datasets/WIKI/AAPL/data.csv", Response::csv("2016-02-10,94\n2016-02-09,95\n")),
+        ("/api/v3/datasets/WIKI/MSFT/data.csv", Response::json(r#"{"dataset_data":{}}"#)),
+    ]);
+
+    let data: Vec<(String, f64)> = {
+        DataQuery::new("WIKI", "AAPL").base_url(server.url()).send().unwrap()
+    };
+
+    assert_eq!(data.len(), 2);
+
+    let data: quandl_v3::Result<Vec<(String, f64)>> = {
+        DataQuery::new("WIKI", "MSFT").base_url(server.url()).send()
+    };
+
+    let message = parsing_error(data);
+    assert!(message.starts_with("expected text/csv, got application/json"), "{}", message);
+}
+
+#[test]
+fn missing_content_type_is_not_checked() {
+    let server = MockServer::routes(vec![
+        ("/api/v3/databases/WIKI.json", Response::new(200).body(DATABASE_METADATA)),
+    ]);
+
+    assert!(DatabaseMetadataQuery::new("WIKI").base_url(server.url()).send().is_ok());
+}
+
+#[test]
+fn permissive_flag_skips_the_check() {
+    let server = MockServer::routes(vec![
+        ("/api/v3/databases/WIKI.json", html(DATABASE_METADATA)),
+        ("/api/v3/datasets/WIKI/AAPL/data.csv", html("2016-02-10,94.27\n")),
+    ]);
+
+    let metadata = {
+        DatabaseMetadataQuery::new("WIKI")
+            .base_url(server.url())
+            .accept_any_content_type()
+            .send()
+    };
+
+    assert!(metadata.is_ok());
+
+    let data: quandl_v3::Result<Vec<(String, f64)>> = {
+        DataQuery::new("WIKI", "AAPL").base_url(server.url()).accept_any_content_type().send()
+    };
+
+    assert_eq!(data.unwrap().len(), 1);
+}
+
+#[test]
+fn api_errors_pass_through() {
+    let server = MockServer::routes(vec![]);
+
+    match DatabaseMetadataQuery::new("NOPE").base_url(server.url()).send() {
+        Err(Error::NotFound { api_error: Some(e), .. }) => {
+            assert_eq!(e.quandl_error.code, "QECx02")
+        },
+
+        other => panic!("expected an API error, got {:?}", other),
+    }
+}
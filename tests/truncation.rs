@@ -0,0 +1,154 @@
+extern crate chrono;
+extern crate quandl_v3;
+extern crate serde_json;
+
+mod common;
+
+use quandl_v3::calendar::{DateRange, NaiveDate};
+use quandl_v3::prelude::*;
+use quandl_v3::{Warning, ROW_CAPS};
+
+use common::{MockServer, Response};
+
+static DATASET_DATA: &str = include_str!("fixtures/dataset_data.json");
+
+fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(year, month, day).unwrap()
+}
+
+/// The `n` daily rows up to 2016-02-29, newest first as Quandl returns them by default.
+///
+fn rows(n: usize) -> Vec<(String, f64)> {
+    let newest = date(2016, 2, 29);
+
+    (0..n).map(|i| {
+        ((newest - chrono::Duration::days(i as i64)).to_string(), 100.0 + i as f64)
+    }).collect()
+}
+
+fn csv(n: usize) -> String {
+    rows(n).iter().map(|(date, close)| format!("{},{}\n", date, close)).collect()
+}
+
+/// Dataset with `n` rows, for which Quandl advertises data from `oldest`.
+///
+fn dataset(n: usize, oldest: NaiveDate) -> String {
+    let mut json: serde_json::Value = serde_json::from_str(DATASET_DATA).unwrap();
+
+    json["dataset"]["column_names"] = serde_json::json!(["Date", "Close"]);
+    json["dataset"]["oldest_available_date"] = serde_json::json!(oldest.to_string());
+    json["dataset"]["data"] = serde_json::json!(rows(n));
+    json.to_string()
+}
+
+fn server() -> MockServer {
+    MockServer::start(|request| {
+        let n = {
+            match &request.path[..] {
+                "/api/v3/datasets/WIKI/CAPPED/data.csv" => 100,
+                "/api/v3/datasets/WIKI/LARGE/data.csv" => 10_000,
+                "/api/v3/datasets/WIKI/FULL/data.csv" => 99,
+                "/api/v3/datasets/WIKI/CAPPED.json" => 100,
+                "/api/v3/datasets/WIKI/COMPLETE.json" => 100,
+                _ => return Response::not_found(),
+            }
+        };
+
+        if request.path.ends_with(".csv") {
+            Response::csv(csv(n))
+        } else if request.path.contains("COMPLETE") {
+            Response::json(dataset(n, date(2015, 11, 22)))
+        } else {
+            Response::json(dataset(n, date(1980, 12, 12)))
+        }
+    })
+}
+
+fn data_query(server: &MockServer, dataset_code: &str) -> DataQuery {
+    let mut query = DataQuery::new("WIKI", dataset_code);
+    query.base_url(server.url());
+    query
+}
+
+#[test]
+fn row_counts_at_a_cap() {
+    let server = server();
+
+    for &(code, rows) in &[("CAPPED", 100), ("LARGE", 10_000)] {
+        assert!(ROW_CAPS.contains(&rows));
+
+        let query = data_query(&server, code);
+        let data = query.send_with_warnings::<(String, f64)>().unwrap();
+
+        assert_eq!(data.len(), rows);
+        assert_eq!(data.warnings, vec![Warning::PossiblyTruncated { rows }]);
+
+        let data = query.send_lossy::<(String, f64)>().unwrap();
+        assert_eq!(data.warnings, vec![Warning::PossiblyTruncated { rows }]);
+
+        // Only logged by a plain `send`.
+        let data: Vec<(String, f64)> = query.send().unwrap();
+        assert_eq!(data.len(), rows);
+    }
+
+    assert_eq!(Warning::PossiblyTruncated { rows: 100 }.to_string(),
+               "exactly 100 rows were returned, which may have been cut off.");
+}
+
+#[test]
+fn row_counts_off_a_cap() {
+    let server = server();
+
+    let data = data_query(&server, "FULL").send_with_warnings::<(String, f64)>().unwrap();
+    assert!(data.is_clean());
+
+    // As many rows as requested.
+    for &limit in &[true, false] {
+        let mut query = data_query(&server, "CAPPED");
+
+        if limit {
+            query.limit(100);
+        } else {
+            query.rows(100);
+        }
+
+        assert!(query.send_with_warnings::<(String, f64)>().unwrap().is_clean());
+    }
+}
+
+fn dataset_query(server: &MockServer, dataset_code: &str) -> DataAndMetadataQuery {
+    let mut query = DataAndMetadataQuery::new("WIKI", dataset_code);
+    query.base_url(server.url());
+    query
+}
+
+#[test]
+fn truncation_confirmed_by_metadata() {
+    let server = server();
+    let dataset = dataset_query(&server, "CAPPED").send_with_warnings::<(String, f64)>().unwrap();
+
+    let expected = DateRange::new(date(1980, 12, 12), date(2016, 2, 29));
+    let actual = DateRange::new(date(2015, 11, 22), date(2016, 2, 29));
+
+    assert_eq!(dataset.rows.len(), 100);
+    assert_eq!(dataset.warnings, vec![Warning::Truncated { rows: 100, expected, actual }]);
+
+    assert_eq!(dataset.warnings[0].to_string(),
+               "cut off at 100 rows, covering 2015-11-22 to 2016-02-29 instead of 1980-12-12 to \
+                2016-02-29.");
+
+    // Rows covering the dates requested are not truncated.
+    let mut query = dataset_query(&server, "CAPPED");
+    query.start_date(2015, 11, 22);
+
+    assert!(query.send_with_warnings::<(String, f64)>().unwrap().is_clean());
+}
+
+#[test]
+fn complete_history_at_a_cap() {
+    let server = server();
+    let dataset = dataset_query(&server, "COMPLETE").send_with_warnings::<(String, f64)>().unwrap();
+
+    assert_eq!(dataset.rows.len(), 100);
+    assert!(dataset.is_clean(), "{:?}", dataset.warnings);
+}
@@ -0,0 +1,121 @@
+#![cfg(feature = "batch")]
+
+extern crate quandl_v3;
+extern crate serde_json;
+
+mod common;
+
+use quandl_v3::Error;
+use quandl_v3::prelude::*;
+
+use common::{MockServer, Response};
+
+static DATASET_METADATA: &str = include_str!("fixtures/dataset_metadata.json");
+static DATA: &str = include_str!("fixtures/data_3_columns.csv");
+
+/// Metadata of WIKI/`code`, listing the three columns of `DATA`.
+///
+fn metadata(code: &str) -> String {
+    let mut json: serde_json::Value = serde_json::from_str(DATASET_METADATA).unwrap();
+
+    json["dataset"]["dataset_code"] = code.into();
+    json["dataset"]["column_names"] = serde_json::json!(["Date", "Open", "High"]);
+    json.to_string()
+}
+
+/// Server knowing the metadata of AAPL and GOOG, but only the data of AAPL.
+///
+fn server() -> MockServer {
+    MockServer::routes(vec![
+        ("/api/v3/datasets/WIKI/AAPL/metadata.json", Response::json(metadata("AAPL"))),
+        ("/api/v3/datasets/WIKI/GOOG/metadata.json", Response::json(metadata("GOOG"))),
+        ("/api/v3/datasets/WIKI/AAPL/data.csv", Response::csv(DATA)),
+    ])
+}
+
+fn code(dataset_code: &str) -> Code {
+    Code {
+        database_code: "WIKI".to_string(),
+        dataset_code: dataset_code.to_string(),
+        name: format!("{} prices", dataset_code),
+    }
+}
+
+#[test]
+fn fetch_everything_about_codes() {
+    let server = server();
+    let codes = vec![code("AAPL"), code("MSFT"), code("GOOG")];
+
+    let results = {
+        let mut fetch = TypedFetch::new("key");
+        fetch.base_url(server.url()).threads(2);
+        fetch.fetch(&codes)
+    };
+
+    assert_eq!(results.iter().map(|x| x.0.clone()).collect::<Vec<_>>(), codes);
+
+    let dataset = results[0].1.as_ref().unwrap();
+
+    assert_eq!(dataset.metadata.dataset_code, "AAPL");
+    assert_eq!(dataset.column_names(), ["Date", "Open", "High"]);
+    assert_eq!(dataset.len(), DATA.lines().count());
+
+    assert_eq!(dataset.rows[0], DataRow {
+        date: "2016-02-10".to_string(),
+        values: vec![Some(94.27), Some(95.7)],
+    });
+
+    let highs = dataset.column("High").unwrap();
+    assert_eq!(&highs[..3], [Some(95.7), Some(95.94), Some(95.7)]);
+    assert_eq!(dataset.column("Date"), None);
+    assert_eq!(dataset.column("Volume"), None);
+
+    // MSFT's metadata is missing, so its data is not even asked for.
+    assert!(matches!(results[1].1, Err(Error::NotFound { .. })));
+    assert!(matches!(results[2].1, Err(Error::NotFound { .. })));
+
+    let mut paths: Vec<String> = server.requests().iter().map(|x| x.path.clone()).collect();
+    paths.sort();
+
+    assert_eq!(paths, vec![
+        "/api/v3/datasets/WIKI/AAPL/data.csv",
+        "/api/v3/datasets/WIKI/AAPL/metadata.json",
+        "/api/v3/datasets/WIKI/GOOG/data.csv",
+        "/api/v3/datasets/WIKI/GOOG/metadata.json",
+        "/api/v3/datasets/WIKI/MSFT/metadata.json",
+    ]);
+
+    assert!(server.requests().iter().all(|x| x.query.contains("api_key=key")));
+}
+
+#[test]
+fn rows_of_the_wrong_width_are_rejected() {
+    let server = MockServer::routes(vec![
+        ("/api/v3/datasets/WIKI/AAPL/metadata.json", Response::json(metadata("AAPL"))),
+        ("/api/v3/datasets/WIKI/AAPL/data.csv", Response::csv("2016-02-10,94.27\n")),
+    ]);
+
+    let results = TypedFetch::new("key").base_url(server.url()).threads(1).fetch(&[code("AAPL")]);
+    assert!(matches!(results[0].1, Err(Error::CsvParsing { row: Some(1), .. })));
+}
+
+#[test]
+fn data_rows() {
+    let row: DataRow = serde_json::from_str("[\"2016-02-10\", 94.27, null]").unwrap();
+
+    assert_eq!(row, DataRow {
+        date: "2016-02-10".to_string(),
+        values: vec![Some(94.27), None],
+    });
+
+    assert_eq!(serde_json::to_string(&row).unwrap(), "[\"2016-02-10\",94.27,null]");
+
+    let query = DataQuery::new("WIKI", "AAPL");
+    let rows: Vec<DataRow> = query.decode(b"2016-02-10,94.27,\n2016-02-09,95.29,95.94\n").unwrap();
+
+    assert_eq!(rows[0].values, [Some(94.27), None]);
+    assert_eq!(rows[1].values, [Some(95.29), Some(95.94)]);
+
+    assert!(serde_json::from_str::<DataRow>("[]").is_err());
+    assert!(query.decode::<DataRow>(b"2016-02-10,high\n").is_err());
+}
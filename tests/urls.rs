@@ -0,0 +1,378 @@
+extern crate quandl_v3;
+
+use quandl_v3::Error;
+use quandl_v3::prelude::*;
+
+/// `(url, parsed_url)` of a query, whatever its type.
+///
+macro_rules! urls {
+    (typed $t:ty, $query:expr) => {
+        (ApiCall::<$t>::url(&$query), ApiCall::<$t>::parsed_url(&$query).unwrap().to_string())
+    };
+
+    ($query:expr) => {
+        ($query.url(), $query.parsed_url().unwrap().to_string())
+    };
+}
+
+type Rows = Vec<(String, f64)>;
+
+fn snapshots() -> Vec<((String, String), &'static str)> {
+    vec![
+        (urls!(DatabaseMetadataQuery::new("WIKI")),
+         "https://www.quandl.com/api/v3/databases/WIKI.json"),
+
+        (urls!(DatabaseMetadataQuery::new("WIKI").api_key("KEY")),
+         "https://www.quandl.com/api/v3/databases/WIKI.json?api_key=KEY"),
+
+        (urls!(DatasetMetadataQuery::new("WIKI", "AAPL").api_key("KEY")),
+         "https://www.quandl.com/api/v3/datasets/WIKI/AAPL/metadata.json?api_key=KEY"),
+
+        (urls!(DatabaseSearch::new()),
+         "https://www.quandl.com/api/v3/databases.json"),
+
+        (urls!(DatabaseSearch::new().query(["stock", "price"]).per_page(5).page(2)),
+         "https://www.quandl.com/api/v3/databases.json?query=stock+price&per_page=5&page=2"),
+
+        (urls!(DatabaseSearch::new().api_key("KEY").page(1)),
+         "https://www.quandl.com/api/v3/databases.json?api_key=KEY&page=1"),
+
+        (urls!(DatasetSearch::new("WIKI")),
+         "https://www.quandl.com/api/v3/datasets.json?database_code=WIKI"),
+
+        (urls!(DatasetSearch::new("WIKI").query(["apple"]).api_key("KEY")),
+         "https://www.quandl.com/api/v3/datasets.json?api_key=KEY&query=apple&database_code=WIKI"),
+
+        (urls!(CodeListQuery::new("WIKI").api_key("KEY")),
+         "https://www.quandl.com/api/v3/databases/WIKI/codes?api_key=KEY"),
+
+        (urls!(DatabaseDownloadQuery::new("WIKI").partial().api_key("KEY")),
+         "https://www.quandl.com/api/v3/databases/WIKI/data?download_type=partial&api_key=KEY"),
+
+        (urls!(typed Rows, DataQuery::new("WIKI", "AAPL")),
+         "https://www.quandl.com/api/v3/datasets/WIKI/AAPL/data.csv?exclude_column_names=true"),
+
+        (urls!(typed Rows, DataQuery::new("WIKI", "AAPL")
+                   .api_key("KEY")
+                   .rows(10)
+                   .limit(5)
+                   .order(Order::asc)
+                   .collapse(Frequency::monthly)
+                   .transform(Transform::rdiff)
+                   .end_date(2016, 2, 1)
+                   .start_date(2015, 12, 31)
+                   .column_index(4)),
+         "https://www.quandl.com/api/v3/datasets/WIKI/AAPL/data.csv?exclude_column_names=true&\
+          api_key=KEY&rows=10&limit=5&order=asc&collapse=monthly&transform=rdiff&\
+          end_date=2016-02-01&start_date=2015-12-31&column_index=4"),
+
+        (urls!(typed Dataset<(String, f64)>,
+               DataAndMetadataQuery::new("WIKI", "AAPL").start_date(1999, 1, 2)),
+         "https://www.quandl.com/api/v3/datasets/WIKI/AAPL.json?start_date=1999-01-02"),
+
+        (urls!(typed Rows,
+               DataQuery::new("WIKI", "AAPL").base_url("http://localhost:8080/api/v3/")),
+         "http://localhost:8080/api/v3/datasets/WIKI/AAPL/data.csv?exclude_column_names=true"),
+
+        (urls!(DatasetMetadataQuery::by_id(9775409).api_key("KEY")),
+         "https://www.quandl.com/api/v3/datasets/9775409/metadata.json?api_key=KEY"),
+
+        (urls!(typed Rows, DataQuery::by_id(9775409).rows(5)),
+         "https://www.quandl.com/api/v3/datasets/9775409/data.csv?exclude_column_names=true&\
+          rows=5"),
+
+        (urls!(typed Dataset<(String, f64)>, DataAndMetadataQuery::by_id(9775409)),
+         "https://www.quandl.com/api/v3/datasets/9775409.json"),
+    ]
+}
+
+#[test]
+fn url_snapshots() {
+    for ((url, parsed_url), expected) in snapshots() {
+        assert_eq!(url, expected);
+        assert_eq!(parsed_url, expected);
+    }
+}
+
+#[test]
+fn arguments_are_encoded() {
+    let mut search = DatasetSearch::new("WIKI");
+    search.query(["S&P", "500%"]).api_key("a=b&c");
+
+    assert_eq!(search.url(), "https://www.quandl.com/api/v3/datasets.json?api_key=a%3Db%26c&\
+                              query=S%26P+500%25&database_code=WIKI");
+
+    let pairs: Vec<(String, String)> = {
+        search.parsed_url().unwrap().query_pairs().into_owned().collect()
+    };
+
+    assert_eq!(pairs, [
+        ("api_key".to_string(), "a=b&c".to_string()),
+        ("query".to_string(), "S&P 500%".to_string()),
+        ("database_code".to_string(), "WIKI".to_string()),
+    ]);
+}
+
+#[test]
+fn parsed_url_encodes_the_path() {
+    let url = DatasetMetadataQuery::new("WIKI", "A B?").parsed_url().unwrap();
+    assert_eq!(url.path(), "/api/v3/datasets/WIKI/A%20B%3F/metadata.json");
+    assert_eq!(url.query(), None);
+}
+
+#[test]
+fn parsed_url_keeps_the_base_url_query() {
+    let mut query = DatabaseMetadataQuery::new("WIKI");
+    query.base_url("https://proxy.example.com/quandl/api/v3?token=xyz").api_key("KEY");
+
+    assert_eq!(query.parsed_url().unwrap().as_str(),
+               "https://proxy.example.com/quandl/api/v3/databases/WIKI.json?\
+                token=xyz&api_key=KEY");
+
+    // The URL can then be adjusted as needed.
+    let mut url = query.parsed_url().unwrap();
+    url.set_host(Some("mirror.example.com")).unwrap();
+    url.query_pairs_mut().append_pair("source", "tests");
+
+    assert_eq!(url.as_str(), "https://mirror.example.com/quandl/api/v3/databases/WIKI.json?\
+                              token=xyz&api_key=KEY&source=tests");
+}
+
+#[test]
+fn invalid_base_url() {
+    let mut query = DatabaseMetadataQuery::new("WIKI");
+    query.base_url("quandl.com/api/v3");
+
+    match query.parsed_url() {
+        Err(Error::ParsingFailed(message)) => {
+            assert_eq!(message, "invalid base URL 'quandl.com/api/v3': relative URL without a \
+                                 base.");
+        },
+
+        other => panic!("expected a parsing error, got {:?}", other),
+    }
+
+    query.base_url("localhost:8080/api/v3");
+
+    match query.parsed_url() {
+        Err(Error::ParsingFailed(message)) => {
+            assert_eq!(message, "invalid base URL 'localhost:8080/api/v3': not a hierarchical \
+                                 URL.");
+        },
+
+        other => panic!("expected a parsing error, got {:?}", other),
+    }
+}
+
+#[test]
+fn phrases_and_exclusions() {
+    let mut search = DatabaseSearch::new();
+
+    search.query(["oil", "\"crude oil\" -gasoline"]);
+    assert_eq!(search.url(), "https://www.quandl.com/api/v3/databases.json?\
+                              query=oil+%22crude%20oil%22+-gasoline");
+
+    search.phrase("  natural   gas ").exclude("jet fuel").exclude("-diesel").exclude("-");
+    search.per_page(10);
+
+    assert_eq!(search.url(), "https://www.quandl.com/api/v3/databases.json?\
+                              query=oil+%22crude%20oil%22+-gasoline+%22natural%20gas%22+\
+                              -%22jet%20fuel%22+-diesel&per_page=10");
+
+    // Quotes cannot be escaped, so they are dropped from phrases and exclusions.
+    let mut search = DatasetSearch::new("WIKI");
+    search.phrase("say \"cheese\"").exclude("\"x\"").phrase("\"\"").exclude(" ");
+
+    assert_eq!(search.url(), "https://www.quandl.com/api/v3/datasets.json?\
+                              query=%22say%20cheese%22+-x&database_code=WIKI");
+
+    // `query` replaces everything, and keeps an unbalanced quote to the end of the keywords.
+    search.query(["\"S&P 500", "index\""]);
+    assert_eq!(search.url(), "https://www.quandl.com/api/v3/datasets.json?\
+                              query=%22S%26P%20500+index%22&database_code=WIKI");
+}
+
+#[test]
+fn date_strings() {
+    let mut tuples = DataQuery::new("WIKI", "AAPL");
+    tuples.start_date(2015, 12, 31).end_date(2016, 2, 1);
+
+    let mut strings = DataQuery::new("WIKI", "AAPL");
+    strings.start_date_str("2015-12-31").unwrap().end_date_str(" 2016-02-01\n").unwrap();
+
+    assert_eq!(strings, tuples);
+    assert_eq!(DataParameters::fmt(&strings), DataParameters::fmt(&tuples));
+
+    let mut query = DataAndMetadataQuery::new("WIKI", "AAPL");
+    query.start_date_str("0999-01-02").unwrap().order(Order::asc);
+
+    assert_eq!(DataParameters::fmt(&query),
+               Some(String::from("order=asc&start_date=0999-01-02")));
+}
+
+#[test]
+fn fiscal_periods() {
+    use quandl_v3::calendar::PeriodSpec;
+
+    let june = |quarter| PeriodSpec::fiscal_quarter(2016, quarter).year_end_month(6);
+
+    let mut periods = DataQuery::new("WIKI", "AAPL");
+    periods.start_period(june(2)).end_period(june(3));
+
+    let mut dates = DataQuery::new("WIKI", "AAPL");
+    dates.start_date(2015, 10, 1).end_date(2016, 3, 31);
+
+    assert_eq!(urls!(typed Rows, periods), urls!(typed Rows, dates));
+    assert_eq!(DataParameters::fmt(&periods),
+               Some(String::from("end_date=2016-03-31&start_date=2015-10-01")));
+
+    // Whichever of the date and the period is set last is sent.
+    periods.end_date(2016, 1, 15);
+    assert_eq!(DataParameters::fmt(&periods),
+               Some(String::from("end_date=2016-01-15&start_date=2015-10-01")));
+
+    periods.start_date(2015, 1, 2).start_period(PeriodSpec::fiscal_year(2016));
+    assert_eq!(DataParameters::fmt(&periods),
+               Some(String::from("end_date=2016-01-15&start_date=2016-01-01")));
+
+    // Periods are checked against each other as dates are.
+    let mut inverted = DataQuery::new("WIKI", "AAPL");
+    inverted.start_period(june(3)).end_period(june(2));
+
+    let fields: Vec<_> = inverted.validate().unwrap_err().iter().map(|x| x.field).collect();
+    assert_eq!(fields, vec!["start_date"]);
+}
+
+#[test]
+fn invalid_date_strings() {
+    let cases = [
+        "2016-2-1", "16-02-01", "2016-02-1", "2016-02-30", "2016-13-01", "2016/02/01",
+        "20160201", "2016-02-01T00:00:00", "yesterday", "", "2016-0a-01", "+2016-02-01",
+    ];
+
+    let mut query = DataQuery::new("WIKI", "AAPL");
+    query.start_date(2015, 1, 2);
+
+    for &case in &cases {
+        match query.start_date_str(case) {
+            Err(Error::ParsingFailed(message)) => {
+                assert_eq!(message, format!("invalid start_date '{}', expected an existing date \
+                                             formatted as YYYY-MM-DD.",
+                                            case));
+            },
+
+            other => panic!("expected {:?} to be rejected, got {:?}", case, other),
+        }
+    }
+
+    assert!(matches!(query.end_date_str("2016-02-30"), Err(Error::ParsingFailed(ref message))
+                     if message.starts_with("invalid end_date '2016-02-30'")));
+
+    // Rejected dates leave the query unchanged.
+    assert_eq!(DataParameters::fmt(&query), Some(String::from("start_date=2015-01-02")));
+}
+
+#[test]
+fn api_versions() {
+    let beta = "https://www.quandl.com/api/beta";
+
+    let cases = vec![
+        (urls!(DatabaseMetadataQuery::new("WIKI").api_version("beta")),
+         format!("{}/databases/WIKI.json", beta)),
+
+        (urls!(DatasetSearch::new("WIKI").api_version("beta").per_page(5)),
+         format!("{}/datasets.json?per_page=5&database_code=WIKI", beta)),
+
+        (urls!(DatasetListingQuery::new("WIKI").api_version("beta")),
+         format!("{}/datasets.csv?database_code=WIKI", beta)),
+
+        (urls!(CodeListQuery::new("WIKI").api_version("beta")),
+         format!("{}/databases/WIKI/codes", beta)),
+
+        (urls!(DatabaseDownloadQuery::new("WIKI").api_version("beta")),
+         format!("{}/databases/WIKI/data", beta)),
+
+        (urls!(typed Rows, DataQuery::new("WIKI", "AAPL").api_version("beta").rows(1)),
+         format!("{}/datasets/WIKI/AAPL/data.csv?exclude_column_names=true&rows=1", beta)),
+
+        // The default version, explicitly.
+        (urls!(typed Dataset<(String, f64)>,
+               DataAndMetadataQuery::new("WIKI", "AAPL").api_version(QUANDL_API_VERSION)),
+         format!("{}/datasets/WIKI/AAPL.json", QUANDL_API_URL)),
+
+        // The last segment of the base URL is the one replaced.
+        (urls!(DatabaseMetadataQuery::new("WIKI")
+                   .base_url("http://localhost:8080/api/v3/")
+                   .api_version("v4")),
+         "http://localhost:8080/api/v4/databases/WIKI.json".to_string()),
+
+        (urls!(DatabaseMetadataQuery::new("WIKI")
+                   .base_url("http://localhost:8080")
+                   .api_version("v4")),
+         "http://localhost:8080/v4/databases/WIKI.json".to_string()),
+    ];
+
+    for ((url, parsed_url), expected) in cases {
+        assert_eq!(url, expected);
+        assert_eq!(parsed_url, expected);
+    }
+
+    // Only `parsed_url` keeps the query string of the base URL after the path.
+    let mut query = DatabaseMetadataQuery::new("WIKI");
+    query.api_version("v4").base_url("http://localhost:8080/proxy/api/v3?token=secret");
+
+    assert_eq!(query.parsed_url().unwrap().as_str(),
+               "http://localhost:8080/proxy/api/v4/databases/WIKI.json?token=secret");
+}
+
+/// Every variant of the three enums, through a `match` which stops compiling if one is added.
+///
+fn every_token() -> Vec<(&'static str, &'static str)> {
+    let frequency = |x: Frequency| match x {
+        Frequency::none | Frequency::daily | Frequency::weekly | Frequency::monthly
+            | Frequency::quarterly | Frequency::annual => x.as_api_token(),
+    };
+
+    let order = |x: Order| match x {
+        Order::asc | Order::desc => x.as_api_token(),
+    };
+
+    let transform = |x: Transform| match x {
+        Transform::none | Transform::diff | Transform::rdiff | Transform::rdiff_from
+            | Transform::cumul | Transform::normalize => x.as_api_token(),
+    };
+
+    vec![
+        (frequency(Frequency::none), "none"),
+        (frequency(Frequency::daily), "daily"),
+        (frequency(Frequency::weekly), "weekly"),
+        (frequency(Frequency::monthly), "monthly"),
+        (frequency(Frequency::quarterly), "quarterly"),
+        (frequency(Frequency::annual), "annual"),
+        (order(Order::asc), "asc"),
+        (order(Order::desc), "desc"),
+        (transform(Transform::none), "none"),
+        (transform(Transform::diff), "diff"),
+        (transform(Transform::rdiff), "rdiff"),
+        (transform(Transform::rdiff_from), "rdiff_from"),
+        (transform(Transform::cumul), "cumul"),
+        (transform(Transform::normalize), "normalize"),
+    ]
+}
+
+#[test]
+fn api_tokens() {
+    for (token, documented) in every_token() {
+        assert_eq!(token, documented);
+    }
+}
+
+#[test]
+fn api_tokens_in_urls() {
+    let mut query = DataQuery::new("WIKI", "AAPL");
+    query.order(Order::asc).collapse(Frequency::quarterly).transform(Transform::rdiff_from);
+
+    assert_eq!(ApiCall::<Rows>::url(&query),
+               "https://www.quandl.com/api/v3/datasets/WIKI/AAPL/data.csv?\
+                exclude_column_names=true&order=asc&collapse=quarterly&transform=rdiff_from");
+}
@@ -0,0 +1,451 @@
+extern crate quandl_v3;
+
+mod common;
+
+use quandl_v3::{Error, ValidationError};
+use quandl_v3::config::Config;
+use quandl_v3::prelude::*;
+
+use common::{MockServer, Response};
+
+fn server() -> MockServer {
+    MockServer::start(|_| Response::not_found())
+}
+
+fn fields(result: Result<(), Vec<ValidationError>>) -> Vec<&'static str> {
+    result.unwrap_err().iter().map(|e| e.field).collect()
+}
+
+/// `send` refuses to dispatch the query when strict, and lets Quandl deal with it otherwise.
+///
+fn check_strictness<Q, F>(mut query: Q, server: &MockServer, send: F)
+    where Q: ApiParameters,
+          F: Fn(&Q) -> Error,
+{
+    let hits = server.hits();
+
+    query.base_url(server.url()).strict(true);
+
+    match send(&query) {
+        Error::ValidationFailed(ref errors) => assert!(!errors.is_empty()),
+        other => panic!("expected a validation failure, got {:?}", other),
+    }
+
+    assert_eq!(server.hits(), hits);
+
+    query.strict(false);
+    assert!(matches!(send(&query), Error::NotFound { .. }));
+    assert_eq!(server.hits(), hits + 1);
+}
+
+#[test]
+fn valid_queries() {
+    let mut data = DataQuery::new("WIKI", "AAPL");
+    data.api_key("KEY").rows(5).start_date(2016, 2, 29).end_date(2016, 3, 1);
+    assert_eq!(data.validate(), Ok(()));
+
+    let mut search = DatasetSearch::new("WIKI");
+    search.per_page(100).page(1);
+    assert_eq!(search.validate(), Ok(()));
+
+    assert_eq!(DatabaseMetadataQuery::new("WIKI").validate(), Ok(()));
+    assert_eq!(DatasetMetadataQuery::new("WIKI", "AAPL").validate(), Ok(()));
+    assert_eq!(DatabaseSearch::new().validate(), Ok(()));
+    assert_eq!(CodeListQuery::new("WIKI").validate(), Ok(()));
+    assert_eq!(DatabaseDownloadQuery::new("WIKI").validate(), Ok(()));
+    assert_eq!(DataAndMetadataQuery::new("WIKI", "AAPL").validate(), Ok(()));
+}
+
+#[test]
+fn dataset_ids() {
+    assert_eq!(DataQuery::by_id(9775409).validate(), Ok(()));
+    assert_eq!(DatasetMetadataQuery::by_id(9775409).validate(), Ok(()));
+    assert_eq!(DataAndMetadataQuery::by_id(9775409).validate(), Ok(()));
+
+    // Either the id or the codes address the dataset, not both.
+    let mut data = DataQuery::by_id(9775409);
+    data.set_dataset_code("AAPL");
+    assert_eq!(fields(data.validate()), vec!["id"]);
+
+    let mut metadata = DatasetMetadataQuery::new("WIKI", "AAPL");
+    metadata.id = Some(9775409);
+    assert_eq!(fields(metadata.validate()), vec!["id"]);
+
+    let mut dataset = DataAndMetadataQuery::by_id(9775409);
+    dataset.set_database_code("WIKI");
+
+    let server = server();
+    dataset.base_url(server.url()).strict(true);
+
+    let sent: quandl_v3::Result<Dataset<(String, f64)>> = dataset.send();
+    assert!(matches!(sent, Err(Error::ValidationFailed(_))), "{:?}", sent);
+    assert_eq!(server.hits(), 0);
+}
+
+#[test]
+fn data_query_reports_every_problem() {
+    let mut query = DataQuery::new("", "WIKI/AAPL");
+    query.api_key("").rows(5).limit(5).start_date(2016, 2, 30).end_date(2015, 13, 1);
+
+    assert_eq!(query.validate(), Err(vec![
+        ValidationError::new("api_key", "it is empty."),
+        ValidationError::new("database_code", "must not be empty."),
+        ValidationError::new("dataset_code", "must not contain '/', got 'WIKI/AAPL' (the database \
+                                              and dataset codes are given separately)."),
+        ValidationError::new("limit", "is the same parameter as rows, which is also set."),
+        ValidationError::new("start_date", "2016-02-30 is not a valid date."),
+        ValidationError::new("end_date", "2015-13-01 is not a valid date."),
+    ]));
+
+    let mut query = DataQuery::new("WIKI", "AAPL");
+    query.start_date(2016, 3, 1).end_date(2016, 2, 1);
+
+    assert_eq!(query.validate(), Err(vec![
+        ValidationError::new("start_date", "2016-03-01 is after the end date 2016-02-01."),
+    ]));
+}
+
+#[test]
+fn data_query_strictness() {
+    let mut query = DataQuery::new("WIKI", "AAPL");
+    query.start_date(2016, 3, 1).end_date(2016, 2, 1);
+
+    check_strictness(query, &server(), |query| {
+        ApiCall::<Vec<(String, f64)>>::send(query).unwrap_err()
+    });
+}
+
+#[test]
+fn data_and_metadata_query() {
+    let mut query = DataAndMetadataQuery::new("WIKI AAPL", "");
+    query.rows(1).limit(1);
+
+    assert_eq!(fields(query.validate()), vec!["database_code", "dataset_code", "limit"]);
+
+    check_strictness(query, &server(), |query| {
+        ApiCall::<Dataset<(String, f64)>>::send(query).unwrap_err()
+    });
+}
+
+#[test]
+fn metadata_queries() {
+    let server = server();
+
+    let query = DatabaseMetadataQuery::new("WIKI/");
+    assert_eq!(fields(query.validate()), vec!["database_code"]);
+    check_strictness(query, &server, |query| query.send().unwrap_err());
+
+    let query = DatasetMetadataQuery::new("", "");
+    assert_eq!(fields(query.validate()), vec!["database_code", "dataset_code"]);
+    check_strictness(query, &server, |query| query.send().unwrap_err());
+}
+
+#[test]
+fn search_queries() {
+    let server = server();
+
+    let mut query = DatabaseSearch::new();
+    query.per_page(0).page(0);
+
+    assert_eq!(query.validate(), Err(vec![
+        ValidationError::new("per_page", "must be at least 1."),
+        ValidationError::new("page", "must be at least 1, pages start at 1."),
+    ]));
+
+    check_strictness(query, &server, |query| query.send().unwrap_err());
+
+    let mut query = DatasetSearch::new("WIKI/AAPL");
+    query.api_key("A&B").per_page(101);
+
+    assert_eq!(query.validate(), Err(vec![
+        ValidationError::new("api_key", "it contains '&'."),
+        ValidationError::new("database_code", "must not contain '/', got 'WIKI/AAPL' (the \
+                                               database and dataset codes are given separately)."),
+        ValidationError::new("per_page", "must be at most 100, got 101."),
+    ]));
+
+    // Malformed keys are always rejected, so this one is checked with a valid key.
+    query.api_key("KEY");
+    check_strictness(query, &server, |query| query.send().unwrap_err());
+}
+
+#[cfg(feature = "zip")]
+#[test]
+fn code_list_query() {
+    let query = CodeListQuery::new("WI KI");
+
+    assert_eq!(query.validate(), Err(vec![
+        ValidationError::new("database_code", "must not contain whitespace, got \"WI KI\"."),
+    ]));
+
+    check_strictness(query, &server(), |query| query.send().unwrap_err());
+}
+
+#[test]
+fn database_download_query() {
+    let server = server();
+    let path = std::env::temp_dir().join(format!("quandl-v3-strict-{}.zip", std::process::id()));
+
+    let mut query = DatabaseDownloadQuery::new("");
+    query.base_url(server.url()).strict(true);
+
+    assert_eq!(fields(query.validate()), vec!["database_code"]);
+    assert!(matches!(query.download_to_file_resumable(&path), Err(Error::ValidationFailed(_))));
+    assert_eq!(server.hits(), 0);
+
+    query.strict(false);
+    assert!(query.download_to_file_resumable(&path).is_err());
+    assert!(server.hits() > 0);
+}
+
+#[cfg(feature = "batch")]
+#[test]
+fn strict_batch_queries_are_not_sent() {
+    let server = server();
+
+    let results: Vec<_> = {
+        let mut batch_query = BatchQuery::new();
+
+        for code in &["WIKI/AAPL", "WIKI"] {
+            let mut query = DatabaseMetadataQuery::new(code);
+            query.base_url(server.url()).api_key("KEY").strict(true);
+            batch_query.query(query);
+        }
+
+        batch_query.run().collect()
+    };
+
+    assert!(matches!(results[0], Err(Error::ValidationFailed(_))));
+    assert!(matches!(results[1], Err(Error::NotFound { .. })));
+    assert_eq!(server.hits(), 1);
+}
+
+#[test]
+fn api_versions() {
+    let mut query = DatabaseMetadataQuery::new("WIKI");
+
+    for version in &["v3", "beta", "v4-preview"] {
+        query.api_version(version);
+        assert_eq!(query.validate(), Ok(()));
+    }
+
+    let cases = [
+        ("", "must not be empty."),
+        ("api/v3", "must not contain '/', got 'api/v3'."),
+        ("v3/", "must not contain '/', got 'v3/'."),
+        ("v 3", "must not contain whitespace, got \"v 3\"."),
+        ("v3\n", "must not contain whitespace, got \"v3\\n\"."),
+    ];
+
+    for &(version, message) in &cases {
+        query.api_version(version);
+        assert_eq!(query.validate(), Err(vec![ValidationError::new("api_version", message)]));
+    }
+
+    let mut query = DataQuery::new("WIKI", "AAPL");
+    query.api_version("v 3");
+    assert_eq!(fields(query.validate()), vec!["api_version"]);
+
+    check_strictness(query, &server(), |query| {
+        ApiCall::<Vec<(String, f64)>>::send(query).unwrap_err()
+    });
+}
+
+#[test]
+fn display() {
+    let error = Error::ValidationFailed(vec![
+        ValidationError::new("database_code", "must not be empty."),
+        ValidationError::new("page", "must be at least 1, pages start at 1."),
+    ]);
+
+    assert_eq!(error.to_string(), "query failed validation: database_code: must not be empty. \
+                                   page: must be at least 1, pages start at 1.");
+}
+
+#[test]
+fn database_codes() {
+    let valid = ["WIKI", "FRED", "CHRIS", "ZACKS_ES", "BCHARTS2", "A", "X1_2"];
+
+    for code in &valid {
+        assert_eq!(DatabaseMetadataQuery::new(code).validate(), Ok(()), "{}", code);
+    }
+
+    let long = "W".repeat(33);
+
+    let invalid = [
+        ("", "must not be empty.".to_string()),
+        ("WIKI/AAPL", "must not contain '/', got 'WIKI/AAPL' (the database and dataset codes are \
+                       given separately).".to_string()),
+        (" WIKI", "must not contain whitespace, got \" WIKI\".".to_string()),
+        (&long, "must be at most 32 characters long, got 33.".to_string()),
+        ("wiki", "must be uppercase, got 'wiki' (see ApiParameters::auto_uppercase).".to_string()),
+        ("Wiki", "must be uppercase, got 'Wiki' (see ApiParameters::auto_uppercase).".to_string()),
+        ("WI-KI", "must only contain uppercase letters, digits, '_', got '-' in 'WI-KI'."
+                  .to_string()),
+        ("WIKI.", "must only contain uppercase letters, digits, '_', got '.' in 'WIKI.'."
+                  .to_string()),
+        ("WÏKI", "must only contain uppercase letters, digits, '_', got 'Ï' in 'WÏKI'."
+                 .to_string()),
+    ];
+
+    for (code, message) in &invalid {
+        let expected = Err(vec![ValidationError::new("database_code", message)]);
+
+        assert_eq!(DatabaseMetadataQuery::new(code).validate(), expected, "{}", code);
+        assert_eq!(DatasetSearch::new(code).validate(), expected, "{}", code);
+        assert_eq!(DatasetListingQuery::new(code).validate(), expected, "{}", code);
+        assert_eq!(CodeListQuery::new(code).validate(), expected, "{}", code);
+        assert_eq!(DatabaseDownloadQuery::new(code).validate(), expected, "{}", code);
+        assert_eq!(DataQuery::new(code, "AAPL").validate(), expected, "{}", code);
+    }
+}
+
+#[test]
+fn dataset_codes() {
+    let valid = ["AAPL", "BRK_A", "CME_CL1", "BRK.B", "EUR-USD", "10Y", &"A".repeat(128)];
+
+    for code in &valid {
+        assert_eq!(DataQuery::new("WIKI", code).validate(), Ok(()), "{}", code);
+    }
+
+    let long = "A".repeat(129);
+
+    let invalid = [
+        ("", "must not be empty.".to_string()),
+        ("AAPL/", "must not contain '/', got 'AAPL/' (the database and dataset codes are given \
+                   separately).".to_string()),
+        ("AA PL", "must not contain whitespace, got \"AA PL\".".to_string()),
+        (&long, "must be at most 128 characters long, got 129.".to_string()),
+        ("aapl", "must be uppercase, got 'aapl' (see ApiParameters::auto_uppercase).".to_string()),
+        ("BRK.b", "must be uppercase, got 'BRK.b' (see ApiParameters::auto_uppercase)."
+                  .to_string()),
+        ("AAPL!", "must only contain uppercase letters, digits, '_', '.', '-', got '!' in \
+                   'AAPL!'.".to_string()),
+        ("A+B", "must only contain uppercase letters, digits, '_', '.', '-', got '+' in 'A+B'."
+                .to_string()),
+    ];
+
+    for (code, message) in &invalid {
+        let expected = Err(vec![ValidationError::new("dataset_code", message)]);
+
+        assert_eq!(DataQuery::new("WIKI", code).validate(), expected, "{}", code);
+        assert_eq!(DatasetMetadataQuery::new("WIKI", code).validate(), expected, "{}", code);
+        assert_eq!(DataAndMetadataQuery::new("WIKI", code).validate(), expected, "{}", code);
+    }
+
+    // Both codes are reported.
+    assert_eq!(fields(DataQuery::new("wiki/aapl", "").validate()),
+               vec!["database_code", "dataset_code"]);
+}
+
+#[test]
+fn from_code() {
+    let query = DataQuery::from_code("WIKI/BRK.B").unwrap();
+    assert_eq!((query.database_code(), query.dataset_code()), ("WIKI", "BRK.B"));
+    assert_eq!(query, DataQuery::new("WIKI", "BRK.B"));
+
+    assert_eq!(DatasetMetadataQuery::from_code("WIKI/AAPL").unwrap(),
+               DatasetMetadataQuery::new("WIKI", "AAPL"));
+    assert_eq!(DataAndMetadataQuery::from_code("WIKI/AAPL").unwrap(),
+               DataAndMetadataQuery::new("WIKI", "AAPL"));
+
+    let database = DatabaseMetadataQuery::from_code("WIKI").unwrap();
+    assert_eq!(database, DatabaseMetadataQuery::new("WIKI"));
+
+    assert_eq!(DatasetSearch::from_code("WIKI").unwrap(), DatasetSearch::new("WIKI"));
+    assert_eq!(DatasetListingQuery::from_code("WIKI").unwrap(), DatasetListingQuery::new("WIKI"));
+    assert_eq!(CodeListQuery::from_code("WIKI").unwrap(), CodeListQuery::new("WIKI"));
+
+    let download = DatabaseDownloadQuery::from_code("WIKI").unwrap();
+    assert_eq!(download, DatabaseDownloadQuery::new("WIKI"));
+
+    let cases = [
+        ("wiki/aapl", vec!["database_code", "dataset_code"]),
+        ("WIKI/AAPL/X", vec!["dataset_code"]),
+        ("/AAPL", vec!["database_code"]),
+        ("WIKI/", vec!["dataset_code"]),
+        ("WIKI", vec!["dataset_code"]),
+        ("", vec!["dataset_code"]),
+    ];
+
+    for (code, expected) in &cases {
+        match DataQuery::from_code(code) {
+            Err(Error::ValidationFailed(errors)) => {
+                let fields: Vec<_> = errors.iter().map(|e| e.field).collect();
+                assert_eq!(&fields, expected, "{}", code);
+            },
+
+            other => panic!("{}: expected a validation failure, got {:?}", code, other),
+        }
+    }
+
+    match DataQuery::from_code("WIKI") {
+        Err(Error::ValidationFailed(errors)) => assert_eq!(errors, vec![
+            ValidationError::new("dataset_code", "must be given along with the database code, as \
+                                                  in 'WIKI/AAPL', got 'WIKI'."),
+        ]),
+
+        other => panic!("{:?}", other),
+    }
+
+    assert!(matches!(DatabaseMetadataQuery::from_code("wiki"), Err(Error::ValidationFailed(_))));
+    assert!(matches!(CodeListQuery::from_code("WIKI/AAPL"), Err(Error::ValidationFailed(_))));
+
+    // Only the codes are checked eagerly.
+    let _guard = Config::override_scope(Config {
+        api_key: Some(ApiKey::new("A&B")),
+        .. Config::default()
+    });
+
+    assert!(DataQuery::from_code("WIKI/AAPL").is_ok());
+}
+
+#[test]
+fn auto_uppercase() {
+    let server = MockServer::routes(vec![
+        ("/api/v3/datasets/WIKI/BRK.B/data.csv", Response::csv("2016-02-10,94.27\n")),
+        ("/api/v3/datasets/WIKI/BRK.B/metadata.json", Response::not_found()),
+    ]);
+
+    let mut query = DataQuery::new("wiki", "brk.b");
+    query.base_url(server.url()).strict(true);
+
+    assert_eq!(fields(query.validate()), vec!["database_code", "dataset_code"]);
+
+    query.auto_uppercase(true);
+    assert_eq!(query.validate(), Ok(()));
+
+    // The fields are left as given, only what is sent is uppercased.
+    assert_eq!(query.database_code(), "wiki");
+
+    let rows: Vec<(String, f64)> = query.send().unwrap();
+    assert_eq!(rows, vec![("2016-02-10".to_string(), 94.27)]);
+    assert_eq!(server.requests()[0].path, "/api/v3/datasets/WIKI/BRK.B/data.csv");
+
+    let mut search = DatasetSearch::new("Wiki");
+    search.auto_uppercase(true);
+    assert!(search.url().ends_with("database_code=WIKI"), "{}", search.url());
+
+    let mut metadata = DatabaseMetadataQuery::new("wiki");
+    metadata.auto_uppercase(true);
+    assert!(metadata.url().contains("/databases/WIKI.json"), "{}", metadata.url());
+
+    // Only letters are uppercased, other problems are still reported.
+    let mut query = DataQuery::new("wiki", "aa pl");
+    query.auto_uppercase(true);
+
+    assert_eq!(query.validate(), Err(vec![
+        ValidationError::new("dataset_code", "must not contain whitespace, got \"AA PL\"."),
+    ]));
+
+    // Config sets the default, which from_code takes into account.
+    let _guard = Config::override_scope(Config {
+        auto_uppercase: true,
+        .. Config::default()
+    });
+
+    let query = DataQuery::from_code("wiki/aapl").unwrap();
+    assert_eq!(query.database_code(), "wiki");
+    let url = ApiCall::<Vec<(String, f64)>>::url(&query);
+    assert!(url.contains("/datasets/WIKI/AAPL/data.csv"), "{}", url);
+}
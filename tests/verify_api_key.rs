@@ -0,0 +1,114 @@
+extern crate quandl_v3;
+
+mod common;
+
+use std::net::TcpListener;
+
+use quandl_v3::{verify_api_key, Error, KeyInfo};
+use quandl_v3::config::Config;
+
+use common::{MockServer, Response};
+
+static DATABASE_SEARCH: &str = include_str!("fixtures/database_search.json");
+
+fn quandl_error(status: u16, code: &str) -> Response {
+    Response::new(status)
+        .header("Content-Type", "application/json")
+        .body(format!(r#"{{"quandl_error": {{"code": "{}", "message": "Nope."}}}}"#, code))
+}
+
+/// Server telling keys apart by their name.
+///
+fn server() -> MockServer {
+    MockServer::start(|request| {
+        if request.path != "/api/v3/databases.json" {
+            return Response::not_found();
+        }
+
+        let key = {
+            request.query.split('&')
+                .find(|x| x.starts_with("api_key="))
+                .map(|x| x["api_key=".len()..].to_string())
+                .unwrap_or_default()
+        };
+
+        match &key[..] {
+            "GOOD" => {
+                Response::json(DATABASE_SEARCH)
+                    .header("X-RateLimit-Limit", "50000")
+                    .header("X-RateLimit-Remaining", "49999")
+            },
+
+            "UNMETERED" => Response::json(DATABASE_SEARCH),
+            "REVOKED" => quandl_error(401, "QEAx03"),
+            "FORBIDDEN" => quandl_error(403, "QEPx04"),
+            "UNKNOWN" => quandl_error(400, "QEAx01"),
+            _ => quandl_error(500, "QEMx01"),
+        }
+    })
+}
+
+fn verify(base_url: &str, key: &str) -> Result<KeyInfo, Error> {
+    let _guard = Config::override_scope(Config {
+        base_url: Some(base_url.to_string()),
+        .. Config::default()
+    });
+
+    verify_api_key(key)
+}
+
+#[test]
+fn valid_keys() {
+    let server = server();
+
+    assert_eq!(verify(&server.url(), " GOOD ").unwrap(), KeyInfo::Valid {
+        limit: Some(50_000),
+        remaining: Some(49_999),
+    });
+
+    assert_eq!(verify(&server.url(), "UNMETERED").unwrap(), KeyInfo::Valid {
+        limit: None,
+        remaining: None,
+    });
+
+    // A single, minimal call.
+    assert_eq!(server.requests()[0].query, "api_key=GOOD&per_page=1");
+}
+
+#[test]
+fn invalid_keys() {
+    let server = server();
+
+    for &key in &["REVOKED", "FORBIDDEN", "UNKNOWN"] {
+        assert_eq!(verify(&server.url(), key).unwrap(), KeyInfo::Invalid, "{}", key);
+    }
+
+    assert_eq!(server.hits(), 3);
+
+    // Malformed keys are not sent.
+    for &key in &["", "   ", "A&B", "A=B", "A B"] {
+        assert_eq!(verify(&server.url(), key).unwrap(), KeyInfo::Invalid, "{:?}", key);
+    }
+
+    assert_eq!(server.hits(), 3);
+}
+
+#[test]
+fn other_failures() {
+    let server = server();
+
+    match verify(&server.url(), "BROKEN") {
+        Err(Error::ApiCallFailed(e)) => assert_eq!(e.quandl_error.code, "QEMx01"),
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn network_failures_do_not_quote_the_key() {
+    let address = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+    let error = verify(&format!("http://{}/api/v3", address), "SECRET123").unwrap_err();
+
+    assert!(matches!(error, Error::DownloadFailed(_)), "{:?}", error);
+    assert!(!format!("{:?}", error).contains("SECRET123"), "{:?}", error);
+    assert!(!error.to_string().contains("SECRET123"), "{}", error);
+}
@@ -0,0 +1,134 @@
+extern crate quandl_v3;
+
+mod common;
+
+use quandl_v3::calendar::{DateRange, NaiveDate};
+use quandl_v3::prelude::*;
+use quandl_v3::{Error, Warning, WithWarnings};
+
+use common::{MockServer, Response};
+
+static THREE_COLUMNS: &[u8] = include_bytes!("fixtures/data_3_columns.csv");
+static DRIFTED: &[u8] = include_bytes!("fixtures/data_drifted.csv");
+
+fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(year, month, day).unwrap()
+}
+
+#[test]
+fn clean_decode_has_no_warnings() {
+    let query = DataQuery::new("WIKI", "AAPL");
+
+    let strict: Vec<(String, f64, f64)> = query.decode(THREE_COLUMNS).unwrap();
+    let lossy: WithWarnings<Vec<(String, f64, f64)>> = query.decode_lossy(THREE_COLUMNS).unwrap();
+
+    assert!(lossy.is_clean());
+    assert_eq!(lossy.into_value(), strict);
+}
+
+#[test]
+fn lossy_decode_rejects_strict_width() {
+    let mut query = DataQuery::new("WIKI", "AAPL");
+
+    let data: WithWarnings<Vec<(String, f64, f64)>> = query.decode_lossy(DRIFTED).unwrap();
+    assert!(data.is_clean());
+    assert_eq!(data.len(), 4);
+
+    query.strict_width(3);
+
+    match query.decode_lossy::<(String, f64, f64)>(DRIFTED) {
+        Err(Error::ValidationFailed(errors)) => assert_eq!(errors[0].field, "strict_width"),
+        result => panic!("unexpected result: {:?}", result),
+    }
+}
+
+#[test]
+fn lossy_decode_reports_undecodable_rows() {
+    let query = DataQuery::new("WIKI", "AAPL");
+    let payload = b"2016-02-10,94.27\n2016-02-09,n/a\n2016-02-08,\n2016-02-05,96.52\n";
+
+    let data: WithWarnings<Vec<(String, f64)>> = query.decode_lossy(&payload[..]).unwrap();
+
+    assert_eq!(data.warnings, vec![Warning::RowsSkipped(2)]);
+    assert_eq!(data.value, vec![("2016-02-10".to_string(), 94.27),
+                                ("2016-02-05".to_string(), 96.52)]);
+
+    assert!(query.decode::<(String, f64)>(&payload[..]).is_err());
+}
+
+#[test]
+fn send_lossy_attaches_warnings() {
+    let server = MockServer::routes(vec![
+        ("/api/v3/datasets/WIKI/AAPL/data.csv",
+         Response::csv("2016-02-10,94.27\n2016-02-09,n/a\n2016-02-05,96.52\n")),
+    ]);
+
+    let mut query = DataQuery::new("WIKI", "AAPL");
+    query.base_url(server.url());
+
+    let data: WithWarnings<Vec<(String, f64)>> = query.send_lossy().unwrap();
+    assert_eq!(data.warnings, vec![Warning::RowsSkipped(1)]);
+
+    // Strict widths are rejected before anything is sent.
+    query.strict_width(2);
+
+    match query.send_lossy::<(String, f64)>() {
+        Err(Error::ValidationFailed(errors)) => assert_eq!(errors[0].field, "strict_width"),
+        result => panic!("unexpected result: {:?}", result),
+    }
+
+    assert_eq!(server.hits(), 1);
+
+    // Download failures are still errors.
+    let mut query = DataQuery::new("WIKI", "MISSING");
+    query.base_url(server.url());
+
+    match query.send_lossy::<(String, f64, f64)>() {
+        Err(Error::NotFound { api_error: Some(e), .. }) => {
+            assert_eq!(e.quandl_error.code, "QECx02")
+        },
+
+        result => panic!("unexpected result: {:?}", result),
+    }
+}
+
+#[test]
+fn with_warnings_conversions() {
+    let mut result = WithWarnings::new(vec![1, 2, 3]);
+    assert!(result.is_clean());
+
+    result.push(4);
+    result.warnings.push(Warning::RowsSkipped(2));
+
+    let result = result.map(|xs| xs.len());
+    assert_eq!(*result, 4);
+
+    let (value, warnings) = result.into_parts();
+    assert_eq!(value, 4);
+    assert_eq!(warnings, vec![Warning::RowsSkipped(2)]);
+}
+
+#[test]
+fn warning_messages() {
+    let january = DateRange::new(date(2016, 1, 1), date(2016, 1, 31));
+    let half = DateRange::new(date(2016, 1, 15), date(2016, 1, 31));
+
+    let messages: Vec<String> = {
+        [Warning::RowsSkipped(3),
+         Warning::DateGap(january),
+         Warning::MetadataUnavailable(Error::DownloadFailed("timeout".into())),
+         Warning::CoverageShortfall { requested: january, actual: Some(half) },
+         Warning::CoverageShortfall { requested: january, actual: None }]
+            .iter()
+            .map(|warning| warning.to_string())
+            .collect()
+    };
+
+    assert_eq!(messages, vec![
+        "3 rows could not be decoded and were skipped.",
+        "no data from 2016-01-01 to 2016-01-31.",
+        "metadata unavailable: download failed with error 'timeout'.",
+        "requested data from 2016-01-01 to 2016-01-31 but got data from 2016-01-15 to 2016-01-31.",
+        "requested data from 2016-01-01 to 2016-01-31 but got none.",
+    ]);
+}